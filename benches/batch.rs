@@ -0,0 +1,119 @@
+// Compares the throughput of 1000 Compare operations awaited one after another
+// with the same operations performed as a batch. The server is an in-process
+// responder answering each request as soon as it's received, so the difference
+// comes from not waiting for the round trip of each operation.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use ldap3::asn1::{
+    parse_tag, parse_uint, write, ASNTag, Enumerated, Integer, OctetString, Sequence, Tag, TagClass,
+};
+use ldap3::{Ldap, LdapConnAsync};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::runtime::Runtime;
+
+const OPS: usize = 1000;
+
+fn compare_true(msgid: i64) -> Vec<u8> {
+    let msg = Tag::Sequence(Sequence {
+        inner: vec![
+            Tag::Integer(Integer {
+                inner: msgid,
+                ..Default::default()
+            }),
+            Tag::Sequence(Sequence {
+                id: 15,
+                class: TagClass::Application,
+                inner: vec![
+                    Tag::Enumerated(Enumerated {
+                        inner: 6,
+                        ..Default::default()
+                    }),
+                    Tag::OctetString(OctetString::default()),
+                    Tag::OctetString(OctetString::default()),
+                ],
+            }),
+        ],
+        ..Default::default()
+    });
+    let mut buf = bytes::BytesMut::new();
+    write::encode_into(&mut buf, msg.into_structure()).expect("encoded");
+    buf.to_vec()
+}
+
+async fn server() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("listener");
+    let port = listener.local_addr().expect("local addr").port();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.expect("accept");
+        let mut buf = vec![];
+        let mut chunk = [0u8; 16384];
+        loop {
+            let mut replies = vec![];
+            while let Ok((rest, tag)) = parse_tag(&buf) {
+                let len = buf.len() - rest.len();
+                let msgid = tag
+                    .expect_constructed()
+                    .and_then(|mut parts| parts.drain(..).next())
+                    .and_then(|msgid| msgid.expect_primitive())
+                    .expect("msgid");
+                replies.extend(compare_true(parse_uint(&msgid).expect("msgid").1 as i64));
+                buf.drain(..len);
+            }
+            if !replies.is_empty() && stream.write_all(&replies).await.is_err() {
+                return;
+            }
+            match stream.read(&mut chunk).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => buf.extend_from_slice(&chunk[..n]),
+            }
+        }
+    });
+    port
+}
+
+async fn sequential(ldap: &mut Ldap) {
+    for _ in 0..OPS {
+        let res = ldap
+            .compare("uid=test,dc=example,dc=org", "cn", "test")
+            .await
+            .expect("compare");
+        assert!(res.equal().expect("equal"));
+    }
+}
+
+async fn batched(ldap: &mut Ldap, concurrency: usize) {
+    let mut batch = ldap.batch();
+    for _ in 0..OPS {
+        batch.compare("uid=test,dc=example,dc=org", "cn", "test");
+    }
+    for res in batch.execute(concurrency).await {
+        assert!(res.expect("result").result().rc == 6);
+    }
+}
+
+fn compares(c: &mut Criterion) {
+    let rt = Runtime::new().expect("runtime");
+    let mut ldap = rt.block_on(async {
+        let port = server().await;
+        let (conn, ldap) = LdapConnAsync::new(&format!("ldap://127.0.0.1:{}", port))
+            .await
+            .expect("connection");
+        ldap3::drive!(conn);
+        ldap
+    });
+    let mut group = c.benchmark_group("1000 compares");
+    group.sample_size(20);
+    group.bench_function("sequential", |b| {
+        b.iter(|| rt.block_on(sequential(&mut ldap)))
+    });
+    for concurrency in [10, 100] {
+        group.bench_function(format!("batch, concurrency {}", concurrency), |b| {
+            b.iter(|| rt.block_on(batched(&mut ldap, concurrency)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, compares);
+criterion_main!(benches);
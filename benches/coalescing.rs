@@ -0,0 +1,55 @@
+// Compares the time taken by 10000 pipelined Compare operations against the
+// scripted server, with each request flushed as soon as it's written, and with
+// the requests waiting in the queue written together. All operations are started
+// at once, so that the connection always has a backlog of requests to coalesce.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use ldap3::testkit::{done, MockHandle, MockServer};
+use ldap3::{Ldap, LdapConnAsync, LdapConnSettings};
+use tokio::runtime::Runtime;
+
+const OPS: usize = 10_000;
+
+async fn connect(server: &MockHandle, coalescing: Option<usize>) -> Ldap {
+    let settings = LdapConnSettings::new().set_write_coalescing(coalescing);
+    let (conn, ldap) = LdapConnAsync::with_settings(settings, &server.url())
+        .await
+        .expect("connection");
+    ldap3::drive!(conn);
+    ldap
+}
+
+async fn pipelined(ldap: &mut Ldap) {
+    let mut batch = ldap.batch();
+    for _ in 0..OPS {
+        batch.compare("uid=test,dc=example,dc=org", "cn", "test");
+    }
+    for res in batch.execute(OPS).await {
+        assert!(res.expect("result").result().rc == 6);
+    }
+}
+
+fn compares(c: &mut Criterion) {
+    let rt = Runtime::new().expect("runtime");
+    let server = rt.block_on(async {
+        MockServer::new()
+            .on_compare(|_| vec![done(6)])
+            .start()
+            .await
+            .expect("server")
+    });
+    let mut group = c.benchmark_group("10000 pipelined compares");
+    group.sample_size(10);
+    for coalescing in [None, Some(64)] {
+        let mut ldap = rt.block_on(connect(&server, coalescing));
+        let name = match coalescing {
+            None => String::from("flush per request"),
+            Some(max) => format!("coalescing up to {}", max),
+        };
+        group.bench_function(name, |b| b.iter(|| rt.block_on(pipelined(&mut ldap))));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, compares);
+criterion_main!(benches);
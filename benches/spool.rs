@@ -0,0 +1,146 @@
+// Compares spooling 10000 search entries to memory and reading them back with
+// SpoolWriter/SpoolReader against the same round trip through LDIF. The LDIF
+// side is a minimal writer and parser: values which aren't safe strings are
+// base64-encoded, and lines aren't folded.
+
+use std::collections::HashMap;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use ldap3::{ResultEntry, SearchEntry, SpoolReader, SpoolWriter};
+
+const ENTRIES: usize = 10000;
+
+fn sample(n: usize) -> SearchEntry {
+    let attrs = HashMap::from([
+        (String::from("cn"), vec![format!("User {}", n)]),
+        (String::from("sn"), vec![String::from("User")]),
+        (String::from("mail"), vec![format!("user{}@example.org", n)]),
+        (
+            String::from("objectClass"),
+            vec![
+                String::from("top"),
+                String::from("person"),
+                String::from("inetOrgPerson"),
+            ],
+        ),
+    ]);
+    let photo: Vec<u8> = (0..2048).map(|i| (i * 7 + n) as u8).collect();
+    SearchEntry {
+        dn: format!("uid=user{},ou=People,dc=example,dc=org", n),
+        attrs,
+        bin_attrs: HashMap::from([(String::from("jpegPhoto"), vec![photo])]),
+        truncated: vec![],
+    }
+}
+
+fn spool_round_trip(entries: &[ResultEntry]) -> usize {
+    let mut writer = SpoolWriter::new(Vec::new()).expect("writer");
+    for re in entries {
+        writer.write(re).expect("write");
+    }
+    let spool = writer.into_inner().expect("spool");
+    let mut count = 0;
+    for re in SpoolReader::new(&spool[..]).expect("reader") {
+        let entry = SearchEntry::construct(re.expect("entry"));
+        assert!(!entry.dn.is_empty());
+        count += 1;
+    }
+    count
+}
+
+fn safe_string(val: &[u8]) -> bool {
+    !matches!(val.first(), Some(b' ' | b':' | b'<'))
+        && val.iter().all(|&c| (0x20..0x7f).contains(&c))
+}
+
+fn push_value(ldif: &mut String, name: &str, val: &[u8]) {
+    ldif.push_str(name);
+    if safe_string(val) {
+        ldif.push_str(": ");
+        ldif.push_str(std::str::from_utf8(val).expect("ascii"));
+    } else {
+        ldif.push_str(":: ");
+        ldif.push_str(&STANDARD.encode(val));
+    }
+    ldif.push('\n');
+}
+
+fn to_ldif(entries: &[SearchEntry]) -> String {
+    let mut ldif = String::new();
+    for entry in entries {
+        push_value(&mut ldif, "dn", entry.dn.as_bytes());
+        for (name, vals) in &entry.attrs {
+            for val in vals {
+                push_value(&mut ldif, name, val.as_bytes());
+            }
+        }
+        for (name, vals) in &entry.bin_attrs {
+            for val in vals {
+                push_value(&mut ldif, name, val);
+            }
+        }
+        ldif.push('\n');
+    }
+    ldif
+}
+
+fn from_ldif(ldif: &str) -> Vec<SearchEntry> {
+    let mut entries = vec![];
+    for record in ldif.split("\n\n").filter(|r| !r.is_empty()) {
+        let mut entry = SearchEntry {
+            dn: String::new(),
+            attrs: HashMap::new(),
+            bin_attrs: HashMap::new(),
+            truncated: vec![],
+        };
+        for line in record.lines() {
+            let (name, rest) = line.split_once(':').expect("attribute line");
+            let val = match rest.strip_prefix(": ") {
+                Some(b64) => STANDARD.decode(b64).expect("base64"),
+                None => rest.trim_start().as_bytes().to_vec(),
+            };
+            if name == "dn" {
+                entry.dn = String::from_utf8(val).expect("dn");
+                continue;
+            }
+            match String::from_utf8(val) {
+                Ok(val) => entry.attrs.entry(name.to_owned()).or_default().push(val),
+                Err(e) => entry
+                    .bin_attrs
+                    .entry(name.to_owned())
+                    .or_default()
+                    .push(e.into_bytes()),
+            }
+        }
+        entries.push(entry);
+    }
+    entries
+}
+
+fn ldif_round_trip(entries: &[SearchEntry]) -> usize {
+    from_ldif(&to_ldif(entries)).len()
+}
+
+fn round_trips(c: &mut Criterion) {
+    let entries: Vec<SearchEntry> = (0..ENTRIES).map(sample).collect();
+    let result_entries: Vec<ResultEntry> = entries
+        .iter()
+        .cloned()
+        .map(SearchEntry::into_result_entry)
+        .collect();
+    assert_eq!(spool_round_trip(&result_entries), ENTRIES);
+    assert_eq!(ldif_round_trip(&entries), ENTRIES);
+    let mut group = c.benchmark_group("10000 entries");
+    group.sample_size(20);
+    group.throughput(Throughput::Elements(ENTRIES as u64));
+    group.bench_function("spool round trip", |b| {
+        b.iter(|| spool_round_trip(&result_entries))
+    });
+    group.bench_function("LDIF round trip", |b| b.iter(|| ldif_round_trip(&entries)));
+    group.finish();
+}
+
+criterion_group!(benches, round_trips);
+criterion_main!(benches);
@@ -0,0 +1,380 @@
+// A small command-line LDAP client, built exclusively on the public API
+// of the crate. Besides being a usage example for the most common operations,
+// it can be used to reproduce problems against a particular server.
+//
+// Usage:
+//
+//   ldapclient [-H url] [-D bind_dn -w password] command [args...]
+//
+// Commands:
+//
+//   search [-s base|one|sub] [-z page_size] base [filter [attr...]]
+//       Paged search, printing the entries in LDIF.
+//   add file
+//       Add the entries from an LDIF file.
+//   modify file
+//       Apply the "changetype: modify" records from an LDIF file.
+//   passwd dn [new_password]
+//       Change the password, letting the server generate it if not given.
+//   whoami
+//       Print the authorization identity of the connection.
+//   move dn new_rdn [new_superior]
+//       Rename and/or move an entry.
+//   health
+//       Print the naming contexts and protocol versions from the root DSE.
+//
+// The LDIF support covers what's needed for the commands above: attribute
+// values can be plain or base64-encoded, and lines can be folded.
+//
+// The integration test for this program, tests/ldapclient.rs, includes the file as
+// a module and runs the commands against a scripted server.
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::io::Write;
+
+use ldap3::adapters::{Adapter, EntriesOnly, PagedResults};
+use ldap3::exop::{PasswordModify, PasswordModifyResp, WhoAmI, WhoAmIResp};
+use ldap3::{BinaryPolicy, ConstructOptions, LdapConnAsync, Mod, Scope, SearchEntry};
+
+type CmdResult<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+#[tokio::main]
+async fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let stdout = std::io::stdout();
+    if let Err(e) = run(&args, &mut stdout.lock()).await {
+        eprintln!("ldapclient: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Parse the command line and execute the command, writing the output to `out`.
+pub async fn run(args: &[String], out: &mut dyn Write) -> CmdResult<()> {
+    let mut url = String::from("ldap://localhost:389");
+    let mut bind = None;
+    let mut bind_dn = None;
+    let mut args = args.iter().map(String::as_str);
+    let cmd = loop {
+        match args.next() {
+            Some("-H") => url = String::from(args.next().ok_or("-H needs an argument")?),
+            Some("-D") => bind_dn = Some(args.next().ok_or("-D needs an argument")?),
+            Some("-w") => {
+                let pw = args.next().ok_or("-w needs an argument")?;
+                bind = Some((bind_dn.take().ok_or("-w needs a preceding -D")?, pw));
+            }
+            Some(cmd) => break cmd,
+            None => return Err("no command given".into()),
+        }
+    };
+    let args = args.collect::<Vec<_>>();
+    let (conn, mut ldap) = LdapConnAsync::new(&url).await?;
+    ldap3::drive!(conn);
+    if let Some((dn, pw)) = bind {
+        ldap.simple_bind(dn, pw).await?.success()?;
+    }
+    match cmd {
+        "search" => search(&mut ldap, &args, out).await?,
+        "add" => {
+            let [file] = args[..] else {
+                return Err("usage: add file".into());
+            };
+            for rec in parse_ldif(&std::fs::read_to_string(file)?)? {
+                if rec.changetype.as_deref().unwrap_or("add") != "add" {
+                    return Err(format!("{}: not an add record", rec.dn).into());
+                }
+                let mut attrs: Vec<(Vec<u8>, HashSet<Vec<u8>>)> = vec![];
+                for (name, val) in rec.lines {
+                    match attrs.iter_mut().find(|(n, _)| *n == name.as_bytes()) {
+                        Some((_, vals)) => {
+                            vals.insert(val);
+                        }
+                        None => attrs.push((name.into_bytes(), HashSet::from([val]))),
+                    }
+                }
+                ldap.add(&rec.dn, attrs).await?.success()?;
+                writeln!(out, "added: {}", rec.dn)?;
+            }
+        }
+        "modify" => {
+            let [file] = args[..] else {
+                return Err("usage: modify file".into());
+            };
+            for rec in parse_ldif(&std::fs::read_to_string(file)?)? {
+                if rec.changetype.as_deref() != Some("modify") {
+                    return Err(format!("{}: not a modify record", rec.dn).into());
+                }
+                ldap.modify(&rec.dn, modifications(&rec)?)
+                    .await?
+                    .success()?;
+                writeln!(out, "modified: {}", rec.dn)?;
+            }
+        }
+        "passwd" => {
+            let (dn, new_pass) = match args[..] {
+                [dn] => (dn, None),
+                [dn, pass] => (dn, Some(pass)),
+                _ => return Err("usage: passwd dn [new_password]".into()),
+            };
+            let (exop, _res) = ldap
+                .extended(PasswordModify {
+                    user_id: Some(dn),
+                    old_pass: None,
+                    new_pass,
+                })
+                .await?
+                .success()?;
+            if exop.val.is_some() {
+                let resp: PasswordModifyResp = exop.parse();
+                writeln!(out, "new password: {}", resp.gen_pass)?;
+            } else {
+                writeln!(out, "password changed: {}", dn)?;
+            }
+        }
+        "whoami" => {
+            let (exop, _res) = ldap.extended(WhoAmI).await?.success()?;
+            let whoami: WhoAmIResp = exop.parse();
+            writeln!(out, "{}", whoami.authzid)?;
+        }
+        "move" => {
+            let (dn, rdn, new_sup) = match args[..] {
+                [dn, rdn] => (dn, rdn, None),
+                [dn, rdn, sup] => (dn, rdn, Some(sup)),
+                _ => return Err("usage: move dn new_rdn [new_superior]".into()),
+            };
+            ldap.modifydn(dn, rdn, true, new_sup).await?.success()?;
+            writeln!(out, "moved: {}", dn)?;
+        }
+        "health" => {
+            let dse = ldap.root_dse().await?;
+            writeln!(
+                out,
+                "LDAP versions: {}",
+                dse.supported_ldap_version.join(", ")
+            )?;
+            for nc in &dse.naming_contexts {
+                writeln!(out, "naming context: {}", nc)?;
+            }
+        }
+        _ => return Err(format!("unknown command: {}", cmd).into()),
+    }
+    ldap.unbind().await?;
+    Ok(())
+}
+
+async fn search(ldap: &mut ldap3::Ldap, args: &[&str], out: &mut dyn Write) -> CmdResult<()> {
+    let mut scope = Scope::Subtree;
+    let mut page_size = 500;
+    let mut args = args.iter().copied();
+    let base = loop {
+        match args.next() {
+            Some("-s") => {
+                scope = match args.next() {
+                    Some("base") => Scope::Base,
+                    Some("one") => Scope::OneLevel,
+                    Some("sub") => Scope::Subtree,
+                    _ => return Err("-s needs one of: base, one, sub".into()),
+                }
+            }
+            Some("-z") => page_size = args.next().ok_or("-z needs an argument")?.parse()?,
+            Some(base) => break base,
+            None => {
+                return Err(
+                    "usage: search [-s scope] [-z page_size] base [filter [attr...]]".into(),
+                )
+            }
+        }
+    };
+    let filter = args.next().unwrap_or("(objectClass=*)");
+    let attrs = args.collect::<Vec<_>>();
+    let adapters: Vec<Box<dyn Adapter<_, _>>> = vec![
+        Box::new(EntriesOnly::new()),
+        Box::new(PagedResults::new(page_size)),
+    ];
+    let mut stream = ldap
+        .streaming_search_with(adapters, base, scope, filter, attrs)
+        .await?;
+    let opts = ConstructOptions::new().binary_policy(BinaryPolicy::default());
+    let mut first = true;
+    while let Some(entry) = stream.next().await? {
+        if !first {
+            writeln!(out)?;
+        }
+        first = false;
+        write_entry(out, &SearchEntry::try_construct_with(entry, &opts)?)?;
+    }
+    stream.finish().await.success()?;
+    Ok(())
+}
+
+struct LdifRecord {
+    dn: String,
+    changetype: Option<String>,
+    lines: Vec<(String, Vec<u8>)>,
+}
+
+fn parse_ldif(text: &str) -> CmdResult<Vec<LdifRecord>> {
+    let mut records = vec![];
+    let mut lines: Vec<String> = vec![];
+    for line in text.lines().chain(Some("")) {
+        if let Some(cont) = line.strip_prefix(' ') {
+            lines
+                .last_mut()
+                .ok_or("continuation line at the start of a record")?
+                .push_str(cont);
+            continue;
+        }
+        if !line.is_empty() {
+            lines.push(String::from(line));
+            continue;
+        }
+        let mut record: Option<LdifRecord> = None;
+        for line in lines.drain(..).filter(|l| !l.starts_with('#')) {
+            let (name, val) = parse_line(&line)?;
+            match record {
+                None if name.eq_ignore_ascii_case("version") => (),
+                None if name.eq_ignore_ascii_case("dn") => {
+                    record = Some(LdifRecord {
+                        dn: String::from_utf8(val)?,
+                        changetype: None,
+                        lines: vec![],
+                    })
+                }
+                None => return Err(format!("expected dn, found: {}", line).into()),
+                Some(ref mut rec)
+                    if rec.changetype.is_none()
+                        && rec.lines.is_empty()
+                        && name.eq_ignore_ascii_case("changetype") =>
+                {
+                    rec.changetype = Some(String::from_utf8(val)?.to_ascii_lowercase());
+                }
+                Some(ref mut rec) => rec.lines.push((name, val)),
+            }
+        }
+        records.extend(record);
+    }
+    Ok(records)
+}
+
+fn parse_line(line: &str) -> CmdResult<(String, Vec<u8>)> {
+    if line == "-" {
+        return Ok((String::from("-"), vec![]));
+    }
+    let (name, rest) = line
+        .split_once(':')
+        .ok_or_else(|| format!("malformed line: {}", line))?;
+    let val = match rest.strip_prefix(':') {
+        Some(b64) => base64_decode(b64.trim())?,
+        None => rest.trim_start().as_bytes().to_vec(),
+    };
+    Ok((String::from(name), val))
+}
+
+fn modifications(rec: &LdifRecord) -> CmdResult<Vec<Mod<Vec<u8>>>> {
+    let mut mods = vec![];
+    let mut lines = rec.lines.iter();
+    while let Some((op, attr)) = lines.next() {
+        let mut vals = HashSet::new();
+        for (name, val) in lines.by_ref() {
+            if name == "-" {
+                break;
+            }
+            if !name.as_bytes().eq_ignore_ascii_case(attr) {
+                return Err(
+                    format!("{}: attribute {} in a change of another", rec.dn, name).into(),
+                );
+            }
+            vals.insert(val.clone());
+        }
+        let attr = attr.clone();
+        mods.push(match op.to_ascii_lowercase().as_str() {
+            "add" => Mod::Add(attr, vals),
+            "delete" => Mod::Delete(attr, vals),
+            "replace" => Mod::Replace(attr, vals),
+            _ => return Err(format!("{}: unknown modification: {}", rec.dn, op).into()),
+        });
+    }
+    Ok(mods)
+}
+
+// Values of binary attributes are always base64-encoded, even if they happen to be
+// printable.
+fn write_entry(out: &mut dyn Write, entry: &SearchEntry) -> CmdResult<()> {
+    write_value(out, "dn", entry.dn.as_bytes(), false)?;
+    let mut attrs: HashMap<&str, (Vec<&[u8]>, bool)> = HashMap::new();
+    for (name, vals) in &entry.attrs {
+        attrs.insert(name, (vals.iter().map(|v| v.as_bytes()).collect(), false));
+    }
+    for (name, vals) in &entry.bin_attrs {
+        attrs.insert(name, (vals.iter().map(|v| v.as_slice()).collect(), true));
+    }
+    let mut names = attrs.keys().copied().collect::<Vec<_>>();
+    names.sort_unstable_by_key(|name| name.to_ascii_lowercase());
+    for name in names {
+        let (ref vals, binary) = attrs[name];
+        for val in vals {
+            write_value(out, name, val, binary)?;
+        }
+    }
+    Ok(())
+}
+
+// RFC 2849: a value must be base64-encoded if it isn't a "safe string".
+fn write_value(out: &mut dyn Write, name: &str, val: &[u8], binary: bool) -> CmdResult<()> {
+    let safe = !binary
+        && val
+            .iter()
+            .all(|&c| c > 0 && c < 0x80 && c != b'\n' && c != b'\r')
+        && !matches!(val.first(), Some(b' ' | b':' | b'<'))
+        && val.last() != Some(&b' ');
+    if safe {
+        writeln!(out, "{}: {}", name, std::str::from_utf8(val)?)?;
+    } else {
+        writeln!(out, "{}:: {}", name, base64_encode(val))?;
+    }
+    Ok(())
+}
+
+const BASE64_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut s = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, &b)| acc | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                s.push(BASE64_CHARS[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                s.push('=');
+            }
+        }
+    }
+    s
+}
+
+fn base64_decode(s: &str) -> CmdResult<Vec<u8>> {
+    let s = s.trim_end_matches('=');
+    let mut data = Vec::with_capacity(s.len() * 3 / 4);
+    let mut acc = 0u32;
+    for (i, c) in s.bytes().enumerate() {
+        let v = BASE64_CHARS
+            .iter()
+            .position(|&b| b == c)
+            .ok_or("invalid base64 value")?;
+        acc = acc << 6 | v as u32;
+        if i % 4 == 3 {
+            data.extend(&acc.to_be_bytes()[1..]);
+            acc = 0;
+        }
+    }
+    match s.len() % 4 {
+        2 => data.push((acc >> 4) as u8),
+        3 => data.extend(&((acc >> 2) as u16).to_be_bytes()),
+        1 => return Err("invalid base64 value".into()),
+        _ => (),
+    }
+    Ok(data)
+}
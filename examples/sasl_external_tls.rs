@@ -0,0 +1,44 @@
+// Demonstrates:
+//
+// 1. TLS connection with a client certificate;
+// 2. SASL EXTERNAL bind using the TLS client identity;
+// 3. "Who Am I?" Extended operation.
+//
+// Uses the async client. The certificate and the PKCS#8 private key,
+// both in PEM format, are read from the files named on the command line.
+//
+// Notice: needs the tls-native feature. The server must be configured to
+// request client certificates and map them to authorization identities.
+
+use std::env;
+use std::fs;
+
+use ldap3::exop::{WhoAmI, WhoAmIResp};
+use ldap3::result::Result;
+use ldap3::{LdapConnAsync, LdapConnSettings};
+use native_tls::Identity;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        eprintln!("usage: {} <cert.pem> <key.pem>", args[0]);
+        std::process::exit(1);
+    }
+    let cert = fs::read(&args[1])?;
+    let key = fs::read(&args[2])?;
+    let identity = Identity::from_pkcs8(&cert, &key).expect("identity");
+    let (conn, mut ldap) = LdapConnAsync::with_settings(
+        LdapConnSettings::new()
+            .set_client_identity(identity)
+            .set_no_tls_verify(true),
+        "ldaps://localhost:2636",
+    )
+    .await?;
+    ldap3::drive!(conn);
+    let _res = ldap.sasl_external_bind().await?.success()?;
+    let (exop, _res) = ldap.extended(WhoAmI).await?.success()?;
+    let whoami: WhoAmIResp = exop.parse();
+    println!("{}", whoami.authzid);
+    ldap.unbind().await
+}
@@ -1,11 +1,8 @@
 // Demonstrates:
 //
 // 1. Using a synchronous streaming Search.
-// 2. A commented-out invalid use of LdapConn while
-//    a derived EntryStream is active.
-// 3. The technique for abandoning the search, with
-//    directions for the ordering of steps to avoid
-//    double-borrowing.
+// 2. Abandoning the search through the LdapConn
+//    while the derived EntryStream is still active.
 
 use ldap3::result::Result;
 use ldap3::{LdapConn, Scope, SearchEntry};
@@ -18,20 +15,15 @@ fn main() -> Result<()> {
         "(&(l=ma*)(objectClass=locality))",
         vec!["l"],
     )?;
-    while let Some(entry) = search.next()? {
+    if let Some(entry) = search.next()? {
         let entry = SearchEntry::construct(entry);
         println!("{:?}", entry);
+        // The rest of the entries are not needed. A stream
+        // terminated by Abandon has no final result, so it's
+        // not checked for success.
+        let msgid = search.last_id();
+        ldap.abandon(msgid)?;
     }
-    // The following two statements show how one would
-    // Abandon a Search. The statements are commented out
-    // because the ldap handle shouldn't be used before result()
-    // is called on the streaming hanlde. To work, a) abandon()
-    // should follow result(), b) there should be no error
-    // handling of result(), because a prematurely finished
-    // stream will always return an error.
-    //
-    //let msgid = search.last_id();
-    //ldap.abandon(msgid)?;
-    let _res = search.result().success()?;
-    Ok(ldap.unbind()?)
+    let _res = search.result();
+    ldap.unbind()
 }
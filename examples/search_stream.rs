@@ -0,0 +1,31 @@
+// Demonstrates using a streaming Search with the combinators
+// from the futures crate: the first ten entries are retrieved,
+// and the overall result is obtained afterwards.
+
+use futures::{StreamExt, TryStreamExt};
+use ldap3::result::Result;
+use ldap3::{LdapConnAsync, Scope, SearchEntry};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let (conn, mut ldap) = LdapConnAsync::new("ldap://localhost:2389").await?;
+    ldap3::drive!(conn);
+    let mut stream = ldap
+        .streaming_search(
+            "ou=Places,dc=example,dc=org",
+            Scope::Subtree,
+            "(objectClass=locality)",
+            vec!["l"],
+        )
+        .await?
+        .into_stream();
+    let entries: Vec<_> = stream.by_ref().take(10).try_collect().await?;
+    for entry in entries {
+        println!("{:?}", SearchEntry::construct(entry));
+    }
+    // If there were more than ten entries, the result will
+    // be synthetic, since the stream wasn't read to the end.
+    let res = stream.finish().await;
+    println!("{:?}", res);
+    ldap.unbind().await
+}
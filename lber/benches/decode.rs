@@ -0,0 +1,161 @@
+//! Decoding throughput and allocation count for a stream of search result entries,
+//! comparing payloads copied out of the input with payloads sharing the input buffer.
+//!
+//! Run with `cargo bench -p lber`.
+
+extern crate bytes;
+#[macro_use]
+extern crate criterion;
+extern crate lber;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use bytes::{Bytes, BytesMut};
+use criterion::{black_box, BatchSize, Criterion, Throughput};
+
+use lber::common::TagClass;
+use lber::parse::{parse_tag, parse_tag_bytes, parse_tag_len};
+use lber::structures::{ASNTag, Integer, OctetString, Sequence, Set, Tag};
+use lber::write;
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const ENTRIES: usize = 100_000;
+
+fn octet_string(s: &[u8]) -> Tag {
+    Tag::OctetString(OctetString {
+        inner: s.to_vec(),
+        ..Default::default()
+    })
+}
+
+fn attribute(name: &str, values: &[&[u8]]) -> Tag {
+    Tag::Sequence(Sequence {
+        inner: vec![
+            octet_string(name.as_bytes()),
+            Tag::Set(Set {
+                inner: values.iter().map(|v| octet_string(v)).collect(),
+                ..Default::default()
+            }),
+        ],
+        ..Default::default()
+    })
+}
+
+// LDAPMessage with a SearchResultEntry, shaped like a typical directory user entry.
+fn entry(n: usize) -> Tag {
+    let uid = format!("user{}", n);
+    let cn = format!("Test User {}", n);
+    let mail = format!("user{}@example.org", n);
+    let photo = vec![(n % 251) as u8; 512];
+    Tag::Sequence(Sequence {
+        inner: vec![
+            Tag::Integer(Integer {
+                inner: 2,
+                ..Default::default()
+            }),
+            Tag::Sequence(Sequence {
+                id: 4,
+                class: TagClass::Application,
+                inner: vec![
+                    octet_string(format!("uid={},ou=people,dc=example,dc=org", uid).as_bytes()),
+                    Tag::Sequence(Sequence {
+                        inner: vec![
+                            attribute("objectClass", &[b"top", b"person", b"inetOrgPerson"]),
+                            attribute("uid", &[uid.as_bytes()]),
+                            attribute("cn", &[cn.as_bytes()]),
+                            attribute("sn", &[b"User"]),
+                            attribute("mail", &[mail.as_bytes()]),
+                            attribute("jpegPhoto", &[&photo]),
+                        ],
+                        ..Default::default()
+                    }),
+                ],
+            }),
+        ],
+        ..Default::default()
+    })
+}
+
+fn stream() -> BytesMut {
+    let mut buf = BytesMut::new();
+    for n in 0..ENTRIES {
+        write::encode_into(&mut buf, entry(n).into_structure()).expect("encoded");
+    }
+    buf
+}
+
+// The decoding strategy used before payloads became shareable: parse from
+// the buffer, copying each payload, then clone the parsed tree.
+fn decode_copying(mut buf: BytesMut) -> usize {
+    let mut count = 0;
+    while !buf.is_empty() {
+        let (rest, tag) = parse_tag(&buf).expect("tag");
+        let consumed = buf.len() - rest.len();
+        black_box(tag.clone());
+        let _ = buf.split_to(consumed);
+        count += 1;
+    }
+    count
+}
+
+// Split each complete message off the buffer and let payloads reference it.
+fn decode_shared(mut buf: BytesMut) -> usize {
+    let mut count = 0;
+    while !buf.is_empty() {
+        let (_, len) = parse_tag_len(&buf).expect("length");
+        let frame: Bytes = buf.split_to(len).freeze();
+        let (_, tag) = parse_tag_bytes(&frame).expect("tag");
+        black_box(tag);
+        count += 1;
+    }
+    count
+}
+
+fn allocations(decode: fn(BytesMut) -> usize, buf: &BytesMut) -> usize {
+    let buf = buf.clone();
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    assert_eq!(decode(buf), ENTRIES);
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let buf = stream();
+    println!(
+        "allocations for {} entries: copying {}, shared {}",
+        ENTRIES,
+        allocations(decode_copying, &buf),
+        allocations(decode_shared, &buf)
+    );
+
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Bytes(buf.len() as u64));
+    group.sample_size(10);
+    group.bench_function("copying", |b| {
+        b.iter_batched(|| buf.clone(), decode_copying, BatchSize::LargeInput)
+    });
+    group.bench_function("shared", |b| {
+        b.iter_batched(|| buf.clone(), decode_shared, BatchSize::LargeInput)
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_decode);
+criterion_main!(benches);
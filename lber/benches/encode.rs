@@ -0,0 +1,186 @@
+//! Encoding throughput and allocated memory for Add requests, comparing an encoder which
+//! builds each constructed value in a temporary buffer with the single-pass encoder of
+//! `write::encode_into()`. One case is a batch of typical entries, the other a single
+//! entry with a very large value.
+//!
+//! Run with `cargo bench -p lber --bench encode`.
+
+extern crate bytes;
+#[macro_use]
+extern crate criterion;
+extern crate lber;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use bytes::{Bytes, BytesMut};
+use criterion::{black_box, BatchSize, Criterion, Throughput};
+
+use lber::common::TagClass;
+use lber::structure::{StructureTag, PL};
+use lber::structures::{ASNTag, Integer, OctetString, Sequence, Set, Tag};
+use lber::write;
+
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const ENTRIES: usize = 10_000;
+const LARGE_VALUE: usize = 50 * 1024 * 1024;
+
+fn attribute(name: &str, values: Vec<Tag>) -> Tag {
+    Tag::Sequence(Sequence {
+        inner: vec![
+            OctetString::from_bytes(Bytes::copy_from_slice(name.as_bytes())),
+            Tag::Set(Set {
+                inner: values,
+                ..Default::default()
+            }),
+        ],
+        ..Default::default()
+    })
+}
+
+fn text(s: String) -> Tag {
+    OctetString::from_bytes(s)
+}
+
+// LDAPMessage with an AddRequest.
+fn add_request(n: usize, photo: Bytes) -> StructureTag {
+    Tag::Sequence(Sequence {
+        inner: vec![
+            Tag::Integer(Integer {
+                inner: n as i64 + 1,
+                ..Default::default()
+            }),
+            Tag::Sequence(Sequence {
+                id: 8,
+                class: TagClass::Application,
+                inner: vec![
+                    text(format!("uid=user{},ou=people,dc=example,dc=org", n)),
+                    Tag::Sequence(Sequence {
+                        inner: vec![
+                            attribute(
+                                "objectClass",
+                                vec![text("top".into()), text("inetOrgPerson".into())],
+                            ),
+                            attribute("uid", vec![text(format!("user{}", n))]),
+                            attribute("cn", vec![text(format!("Test User {}", n))]),
+                            attribute("sn", vec![text("User".into())]),
+                            attribute("jpegPhoto", vec![OctetString::from_bytes(photo)]),
+                        ],
+                        ..Default::default()
+                    }),
+                ],
+            }),
+        ],
+        ..Default::default()
+    })
+    .into_structure()
+}
+
+fn header(buf: &mut Vec<u8>, class: TagClass, id: u64, constructed: bool, len: usize) {
+    buf.push((class as u8) << 6 | (constructed as u8) << 5 | id as u8);
+    if len < 128 {
+        buf.push(len as u8);
+    } else {
+        let repr = len.to_be_bytes();
+        let skip = repr.iter().take_while(|&&b| b == 0).count();
+        buf.push((repr.len() - skip) as u8 | 0x80);
+        buf.extend_from_slice(&repr[skip..]);
+    }
+}
+
+// The encoding strategy used before lengths were computed up front: encode the
+// elements of each constructed value into a temporary buffer to learn its length.
+fn encode_nested(buf: &mut Vec<u8>, tag: StructureTag) {
+    match tag.payload {
+        PL::P(v) => {
+            header(buf, tag.class, tag.id, false, v.len());
+            buf.extend(v);
+        }
+        PL::C(tags) => {
+            let mut tmp = Vec::new();
+            for tag in tags {
+                encode_nested(&mut tmp, tag);
+            }
+            header(buf, tag.class, tag.id, true, tmp.len());
+            buf.extend(tmp);
+        }
+    }
+}
+
+fn encode_copying(msgs: Vec<StructureTag>) -> BytesMut {
+    let mut buf = BytesMut::new();
+    for msg in msgs {
+        let mut tag_vec = Vec::new();
+        encode_nested(&mut tag_vec, msg);
+        buf.extend(tag_vec);
+    }
+    buf
+}
+
+fn encode_single_pass(msgs: Vec<StructureTag>) -> BytesMut {
+    let mut buf = BytesMut::new();
+    for msg in msgs {
+        write::encode_into(&mut buf, msg).expect("encoded");
+    }
+    buf
+}
+
+fn allocated(encode: fn(Vec<StructureTag>) -> BytesMut, msgs: &[StructureTag]) -> usize {
+    let msgs = msgs.to_vec();
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    black_box(encode(msgs));
+    ALLOCATED.load(Ordering::Relaxed) - before
+}
+
+fn bench_case(c: &mut Criterion, name: &str, msgs: Vec<StructureTag>) {
+    let encoded = encode_single_pass(msgs.clone());
+    assert_eq!(encoded, encode_copying(msgs.clone()));
+    println!(
+        "{}: {} bytes encoded, allocated: copying {}, single pass {}",
+        name,
+        encoded.len(),
+        allocated(encode_copying, &msgs),
+        allocated(encode_single_pass, &msgs)
+    );
+
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes(encoded.len() as u64));
+    group.sample_size(10);
+    group.bench_function("copying", |b| {
+        b.iter_batched(|| msgs.clone(), encode_copying, BatchSize::LargeInput)
+    });
+    group.bench_function("single pass", |b| {
+        b.iter_batched(|| msgs.clone(), encode_single_pass, BatchSize::LargeInput)
+    });
+    group.finish();
+}
+
+fn bench_encode(c: &mut Criterion) {
+    let photo = Bytes::from(vec![0x5a; 512]);
+    let batch = (0..ENTRIES)
+        .map(|n| add_request(n, photo.clone()))
+        .collect();
+    bench_case(c, "add batch", batch);
+    let large = vec![add_request(0, Bytes::from(vec![0xa5; LARGE_VALUE]))];
+    bench_case(c, "large value", large);
+}
+
+criterion_group!(benches, bench_encode);
+criterion_main!(benches);
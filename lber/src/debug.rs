@@ -0,0 +1,288 @@
+//! Human-readable rendering of BER structures, for debugging.
+//!
+//! Each tag is shown on its own line, indented by its nesting depth, with its class,
+//! number, and content length. Primitive values are followed by a preview of their
+//! first bytes in hex and ASCII.
+use std::fmt;
+
+use common::TagClass;
+use parse::parse_tag;
+use structure::{StructureTag, PL};
+
+/// Number of payload bytes shown for a primitive value.
+const PREVIEW_LEN: usize = 16;
+
+/// Write an indented tree representation of the tag.
+pub fn dump(tag: &StructureTag, out: &mut impl fmt::Write) -> fmt::Result {
+    dump_inner(tag, &mut vec![], &|_| false, out)
+}
+
+/// Write the tree representation of the tag like [`dump()`](fn.dump.html), but
+/// without showing the contents of the tags for which `hide` returns `true`.
+/// Such a tag is rendered with its class and number, followed by
+/// `<redacted, N bytes>`, where `N` is the content length.
+///
+/// The argument of `hide` is the path of the tag from the top, as the sequence
+/// of indices of the containing tags in their parents. The top tag has an
+/// empty path, its second element the path `[1]`, and so on.
+pub fn dump_redacted(
+    tag: &StructureTag,
+    hide: &dyn Fn(&[usize]) -> bool,
+    out: &mut impl fmt::Write,
+) -> fmt::Result {
+    dump_inner(tag, &mut vec![], hide, out)
+}
+
+/// Parse the raw BER data and render all tags in it, in the manner of
+/// [`dump()`](fn.dump.html). If some data can't be parsed, it's appended
+/// as a hex dump.
+pub fn dump_bytes(raw: &[u8]) -> String {
+    let mut out = String::new();
+    let mut i = raw;
+    while !i.is_empty() {
+        match parse_tag(i) {
+            Ok((rest, tag)) => {
+                let _ = dump(&tag, &mut out);
+                i = rest;
+            }
+            Err(_) => {
+                let _ = hex_dump(raw, raw.len() - i.len(), &mut out);
+                break;
+            }
+        }
+    }
+    out
+}
+
+fn dump_inner(
+    tag: &StructureTag,
+    path: &mut Vec<usize>,
+    hide: &dyn Fn(&[usize]) -> bool,
+    out: &mut impl fmt::Write,
+) -> fmt::Result {
+    let class = match tag.class {
+        TagClass::Universal => "UNIVERSAL",
+        TagClass::Application => "APPLICATION",
+        TagClass::Context => "CONTEXT",
+        TagClass::Private => "PRIVATE",
+    };
+    write!(
+        out,
+        "{:indent$}[{} {}]",
+        "",
+        class,
+        tag.id,
+        indent = path.len() * 2
+    )?;
+    if tag.class == TagClass::Universal {
+        if let Some(name) = universal_name(tag.id) {
+            write!(out, " {}", name)?;
+        }
+    }
+    let len = content_len(tag);
+    let plural = if len == 1 { "" } else { "s" };
+    if hide(path) {
+        return writeln!(out, ", <redacted, {} byte{}>", len, plural);
+    }
+    write!(out, ", {} byte{}", len, plural)?;
+    match tag.payload {
+        PL::P(ref p) => {
+            if !p.is_empty() {
+                write!(out, ": ")?;
+                preview(p, out)?;
+            }
+            writeln!(out)
+        }
+        PL::C(ref tags) => {
+            writeln!(out)?;
+            for (n, tag) in tags.iter().enumerate() {
+                path.push(n);
+                dump_inner(tag, path, hide, out)?;
+                path.pop();
+            }
+            Ok(())
+        }
+    }
+}
+
+fn universal_name(id: u64) -> Option<&'static str> {
+    Some(match id {
+        1 => "BOOLEAN",
+        2 => "INTEGER",
+        3 => "BIT STRING",
+        4 => "OCTET STRING",
+        5 => "NULL",
+        6 => "OBJECT IDENTIFIER",
+        10 => "ENUMERATED",
+        12 => "UTF8String",
+        16 => "SEQUENCE",
+        17 => "SET",
+        19 => "PrintableString",
+        22 => "IA5String",
+        23 => "UTCTime",
+        24 => "GeneralizedTime",
+        _ => return None,
+    })
+}
+
+fn preview(p: &[u8], out: &mut impl fmt::Write) -> fmt::Result {
+    let shown = &p[..p.len().min(PREVIEW_LEN)];
+    for (n, b) in shown.iter().enumerate() {
+        if n > 0 {
+            write!(out, " ")?;
+        }
+        write!(out, "{:02x}", b)?;
+    }
+    if p.len() > shown.len() {
+        write!(out, " ..")?;
+    }
+    write!(out, "  |")?;
+    ascii(shown, out)?;
+    write!(out, "|")
+}
+
+fn ascii(bytes: &[u8], out: &mut impl fmt::Write) -> fmt::Result {
+    for &b in bytes {
+        let c = if (0x20..0x7f).contains(&b) {
+            b as char
+        } else {
+            '.'
+        };
+        write!(out, "{}", c)?;
+    }
+    Ok(())
+}
+
+// Offsets are shown relative to the start of `raw`.
+fn hex_dump(raw: &[u8], start: usize, out: &mut impl fmt::Write) -> fmt::Result {
+    let tail = &raw[start..];
+    writeln!(out, "unparseable data, {} bytes:", tail.len())?;
+    for (n, line) in tail.chunks(16).enumerate() {
+        write!(out, "  {:04x}:", start + n * 16)?;
+        for b in line {
+            write!(out, " {:02x}", b)?;
+        }
+        write!(out, "{:1$}  |", "", (16 - line.len()) * 3)?;
+        ascii(line, out)?;
+        writeln!(out, "|")?;
+    }
+    Ok(())
+}
+
+fn content_len(tag: &StructureTag) -> usize {
+    match tag.payload {
+        PL::P(ref p) => p.len(),
+        PL::C(ref tags) => tags.iter().map(encoded_len).sum(),
+    }
+}
+
+// Length of the complete encoding, as produced by the writer.
+fn encoded_len(tag: &StructureTag) -> usize {
+    let len = content_len(tag);
+    let mut id_len = 1;
+    if tag.id > 30 {
+        let mut id = tag.id;
+        while id > 0 {
+            id_len += 1;
+            id >>= 7;
+        }
+    }
+    let mut len_len = 1;
+    if len >= 128 {
+        let mut l = len;
+        while l > 0 {
+            len_len += 1;
+            l >>= 8;
+        }
+    }
+    id_len + len_len + len
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bytes::BytesMut;
+    use structures::{ASNTag, Integer, OctetString, Sequence, Tag};
+    use write;
+
+    fn bind_request() -> Vec<u8> {
+        let msg = Tag::Sequence(Sequence {
+            inner: vec![
+                Tag::Integer(Integer {
+                    inner: 1,
+                    ..Default::default()
+                }),
+                Tag::Sequence(Sequence {
+                    id: 0,
+                    class: TagClass::Application,
+                    inner: vec![
+                        Tag::Integer(Integer {
+                            inner: 3,
+                            ..Default::default()
+                        }),
+                        Tag::OctetString(OctetString {
+                            inner: b"cn=admin,dc=example,dc=org".to_vec(),
+                            ..Default::default()
+                        }),
+                        Tag::OctetString(OctetString {
+                            id: 0,
+                            class: TagClass::Context,
+                            inner: b"secret".to_vec(),
+                        }),
+                    ],
+                }),
+            ],
+            ..Default::default()
+        });
+        let mut buf = BytesMut::new();
+        write::encode_into(&mut buf, msg.into_structure()).expect("encoded");
+        buf.to_vec()
+    }
+
+    #[test]
+    fn simple_bind() {
+        let expected = "\
+[UNIVERSAL 16] SEQUENCE, 44 bytes
+  [UNIVERSAL 2] INTEGER, 1 byte: 01  |.|
+  [APPLICATION 0], 39 bytes
+    [UNIVERSAL 2] INTEGER, 1 byte: 03  |.|
+    [UNIVERSAL 4] OCTET STRING, 26 bytes: 63 6e 3d 61 64 6d 69 6e 2c 64 63 3d 65 78 61 6d ..  |cn=admin,dc=exam|
+    [CONTEXT 0], 6 bytes: 73 65 63 72 65 74  |secret|
+";
+        let raw = bind_request();
+        assert_eq!(raw.len(), 2 + 44);
+        let (_, tag) = parse_tag(&raw).expect("parsed");
+        let mut out = String::new();
+        dump(&tag, &mut out).expect("dumped");
+        assert_eq!(out, expected);
+        assert_eq!(dump_bytes(&raw), expected);
+    }
+
+    #[test]
+    fn redacted_password() {
+        let expected = "\
+[UNIVERSAL 16] SEQUENCE, 44 bytes
+  [UNIVERSAL 2] INTEGER, 1 byte: 01  |.|
+  [APPLICATION 0], 39 bytes
+    [UNIVERSAL 2] INTEGER, 1 byte: 03  |.|
+    [UNIVERSAL 4] OCTET STRING, 26 bytes: 63 6e 3d 61 64 6d 69 6e 2c 64 63 3d 65 78 61 6d ..  |cn=admin,dc=exam|
+    [CONTEXT 0], <redacted, 6 bytes>
+";
+        let (_, tag) = parse_tag(&bind_request()).expect("parsed");
+        let mut out = String::new();
+        dump_redacted(&tag, &|path| path == [1, 2], &mut out).expect("dumped");
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn unparseable_tail() {
+        let mut raw = vec![0x05, 0x00];
+        raw.extend_from_slice(&[0x30, 0x84, 0x7f, 0xff, 0xff, 0xff, b'a', b'b']);
+        let expected = "\
+[UNIVERSAL 5] NULL, 0 bytes
+unparseable data, 8 bytes:
+  0002: 30 84 7f ff ff ff 61 62                          |0.....ab|
+";
+        assert_eq!(dump_bytes(&raw), expected);
+    }
+}
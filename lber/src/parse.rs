@@ -1,5 +1,6 @@
 use std::convert::TryFrom;
 
+use bytes::Bytes;
 use common::TagClass;
 use common::TagStructure;
 use structure::{StructureTag, PL};
@@ -26,40 +27,127 @@ fn tagnr_bits(i: (&[u8], usize)) -> nom::IResult<(&[u8], usize), u64> {
 }
 
 fn parse_type_header(i: &[u8]) -> nom::IResult<&[u8], (TagClass, TagStructure, u64)> {
-    nom::bits(tuple((class_bits, pc_bit, tagnr_bits)))(i)
+    let (mut i, (class, structure, id)) = nom::bits(tuple((class_bits, pc_bit, tagnr_bits)))(i)?;
+    if id != 0x1f {
+        return Ok((i, (class, structure, id)));
+    }
+    // High tag number form: the number follows in base 128, with the high bit set
+    // on all but the last octet. The first octet can't be zero (X.690, 8.1.2.4.2).
+    let start = i;
+    let failure = |kind| nom::Err::Failure(Error::from_error_kind(start, kind));
+    let mut id: u64 = 0;
+    loop {
+        let (j, byte) = number::be_u8(i)?;
+        i = j;
+        if id == 0 && byte == 0x80 {
+            return Err(failure(ErrorKind::Verify));
+        }
+        if id > u64::MAX >> 7 {
+            return Err(failure(ErrorKind::TooLarge));
+        }
+        id = (id << 7) | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            return Ok((i, (class, structure, id)));
+        }
+    }
 }
 
 fn parse_length(i: &[u8]) -> nom::IResult<&[u8], usize> {
-    let (i, len) = number::be_u8(i)?;
-    if len < 128 {
-        Ok((i, len as usize))
-    } else {
-        let len = len - 128;
-        let (i, b) = take(len)(i)?;
-        let (_, len) = parse_uint(b)?;
-        Ok((
-            i,
-            usize::try_from(len)
-                .map_err(|_| nom::Err::Failure(Error::from_error_kind(i, ErrorKind::TooLarge)))?,
-        ))
+    let failure = |kind| nom::Err::Failure(Error::from_error_kind(i, kind));
+    let (j, len) = number::be_u8(i)?;
+    match len {
+        0..=127 => Ok((j, len as usize)),
+        // Indefinite form, which LDAP doesn't allow (RFC 4511, Section 5.1).
+        0x80 => Err(failure(ErrorKind::LengthValue)),
+        // Reserved for future extensions (X.690, 8.1.3.5).
+        0xff => Err(failure(ErrorKind::Verify)),
+        _ => {
+            let len = len & 0x7f;
+            if len > 8 {
+                return Err(failure(ErrorKind::TooLarge));
+            }
+            let (j, b) = take(len)(j)?;
+            let (_, len) = parse_uint(b)?;
+            Ok((
+                j,
+                usize::try_from(len).map_err(|_| failure(ErrorKind::TooLarge))?,
+            ))
+        }
     }
 }
 
 /// Extract an unsigned integer value from BER data.
+///
+/// Values longer than eight bytes don't fit into the result, and produce an error.
 pub fn parse_uint(i: &[u8]) -> nom::IResult<&[u8], u64> {
+    if i.len() > 8 {
+        return Err(nom::Err::Error(Error::from_error_kind(
+            i,
+            ErrorKind::TooLarge,
+        )));
+    }
     Ok((i, i.iter().fold(0, |res, &byte| (res << 8) | byte as u64)))
 }
 
-/// Parse raw BER data into a serializable structure.
-pub fn parse_tag(i: &[u8]) -> nom::IResult<&[u8], StructureTag> {
-    let (mut i, ((class, structure, id), len)) = tuple((parse_type_header, parse_length))(i)?;
+/// Extract a signed integer value from BER data.
+///
+/// The value is interpreted as a two's complement number, with the sign taken from the
+/// most significant bit of the first byte. Redundant leading bytes, which don't change
+/// the value, are accepted, but the significant part must fit into eight bytes.
+pub fn parse_int(i: &[u8]) -> nom::IResult<&[u8], i64> {
+    if i.is_empty() {
+        return Err(nom::Err::Error(Error::from_error_kind(i, ErrorKind::Eof)));
+    }
+    let mut sig = i;
+    while sig.len() > 1
+        && ((sig[0] == 0x00 && sig[1] & 0x80 == 0) || (sig[0] == 0xff && sig[1] & 0x80 != 0))
+    {
+        sig = &sig[1..];
+    }
+    if sig.len() > 8 {
+        return Err(nom::Err::Error(Error::from_error_kind(
+            i,
+            ErrorKind::TooLarge,
+        )));
+    }
+    let init = if sig[0] & 0x80 != 0 { -1 } else { 0 };
+    Ok((
+        &i[i.len()..],
+        sig.iter().fold(init, |res, &byte| (res << 8) | byte as i64),
+    ))
+}
+
+type Header = ((TagClass, TagStructure, u64), usize);
+
+fn parse_header(i: &[u8]) -> nom::IResult<&[u8], Header> {
+    tuple((parse_type_header, parse_length))(i)
+}
+
+/// Determine the length of the complete BER encoding of the first tag in the input
+/// by parsing only the tag header. The returned input is positioned after the header.
+///
+/// This can be used to find out whether the whole tag is present in a buffer before
+/// attempting to parse it.
+pub fn parse_tag_len(i: &[u8]) -> nom::IResult<&[u8], usize> {
+    let (rest, (_, len)) = parse_header(i)?;
+    let total = (i.len() - rest.len())
+        .checked_add(len)
+        .ok_or_else(|| nom::Err::Failure(Error::from_error_kind(i, ErrorKind::TooLarge)))?;
+    Ok((rest, total))
+}
+
+fn parse_tag_with<'a, F>(i: &'a [u8], payload: &F) -> nom::IResult<&'a [u8], StructureTag>
+where
+    F: Fn(&'a [u8]) -> Bytes,
+{
+    let (mut i, ((class, structure, id), len)) = parse_header(i)?;
 
     let pl: PL = match structure {
         TagStructure::Primitive => {
             let (j, content) = take(len)(i)?;
             i = j;
 
-            PL::P(content.to_vec())
+            PL::P(payload(content))
         }
         TagStructure::Constructed => {
             let (j, mut content) = take(len)(i)?;
@@ -67,7 +155,7 @@ pub fn parse_tag(i: &[u8]) -> nom::IResult<&[u8], StructureTag> {
 
             let mut tv: Vec<StructureTag> = Vec::new();
             while content.input_len() > 0 {
-                let (j, sub) = parse_tag(content)?;
+                let (j, sub) = parse_tag_with(content, payload)?;
                 content = j;
                 tv.push(sub);
             }
@@ -86,6 +174,20 @@ pub fn parse_tag(i: &[u8]) -> nom::IResult<&[u8], StructureTag> {
     ))
 }
 
+/// Parse raw BER data into a serializable structure.
+///
+/// Primitive payloads are copied from the input.
+pub fn parse_tag(i: &[u8]) -> nom::IResult<&[u8], StructureTag> {
+    parse_tag_with(i, &Bytes::copy_from_slice)
+}
+
+/// Parse raw BER data into a serializable structure, without copying primitive
+/// payloads. They will instead reference the input buffer, which will be kept
+/// alive as long as any part of the structure is.
+pub fn parse_tag_bytes(input: &Bytes) -> nom::IResult<&[u8], StructureTag> {
+    parse_tag_with(input, &|content| input.slice_ref(content))
+}
+
 pub struct Parser;
 
 impl Parser {
@@ -122,7 +224,7 @@ mod test {
         let result_tag = StructureTag {
             class: TagClass::Universal,
             id: 2u64,
-            payload: PL::P(vec![255, 127]),
+            payload: PL::P(Bytes::from_static(&[255, 127])),
         };
         let rest_tag: Vec<u8> = vec![];
 
@@ -142,7 +244,7 @@ mod test {
             payload: PL::C(vec![StructureTag {
                 class: TagClass::Universal,
                 id: 12u64,
-                payload: PL::P(vec![72, 101, 108, 108, 111, 32, 87, 111, 114, 108, 100, 33]),
+                payload: PL::P(Bytes::from_static(b"Hello World!")),
             }]),
         };
         let rest_tag: Vec<u8> = vec![];
@@ -183,12 +285,12 @@ mod test {
                 StructureTag {
                     class: TagClass::Context,
                     id: 0,
-                    payload: PL::P(vec![74, 117, 115, 116, 65, 76, 111, 110, 103, 84, 97, 103]),
+                    payload: PL::P(Bytes::from_static(b"JustALongTag")),
                 },
                 StructureTag {
                     class: TagClass::Context,
                     id: 1,
-                    payload: PL::P(vec![
+                    payload: PL::P(Bytes::from(vec![
                         74, 117, 115, 116, 65, 76, 111, 110, 103, 84, 97, 103, 74, 117, 115, 116,
                         65, 76, 111, 110, 103, 84, 97, 103, 74, 117, 115, 116, 65, 76, 111, 110,
                         103, 84, 97, 103, 74, 117, 115, 116, 65, 76, 111, 110, 103, 84, 97, 103,
@@ -204,7 +306,7 @@ mod test {
                         74, 117, 115, 116, 65, 76, 111, 110, 103, 84, 97, 103, 74, 117, 115, 116,
                         65, 76, 111, 110, 103, 84, 97, 103, 74, 117, 115, 116, 65, 76, 111, 110,
                         103, 84, 97, 103, 74, 117, 115, 116, 65, 76, 111, 110, 103, 84, 97, 103,
-                    ]),
+                    ])),
                 },
             ]),
         };
@@ -214,4 +316,172 @@ mod test {
         let tag = parse_tag(&bytes[..]);
         assert_eq!(tag, Ok((&rest_tag[..], result_tag)));
     }
+
+    #[test]
+    fn test_tag_len() {
+        let bytes: Vec<u8> = vec![48, 129, 4, 2, 2, 255, 127, 0];
+        assert_eq!(
+            parse_tag_len(&bytes[..2]).map_err(|e| e.is_incomplete()),
+            Err(true)
+        );
+        assert_eq!(parse_tag_len(&bytes[..]), Ok((&bytes[3..], 7)));
+    }
+
+    #[test]
+    fn test_shared_payload() {
+        let bytes = Bytes::from_static(&[48, 6, 4, 1, 97, 4, 1, 98, 0]);
+        let (rest, tag) = parse_tag_bytes(&bytes).expect("tag");
+        assert_eq!(rest, &[0]);
+        assert_eq!(Ok((rest, tag.clone())), parse_tag(&bytes[..]));
+        let inner = tag.expect_constructed().expect("sequence");
+        let b = inner[1]
+            .clone()
+            .expect_primitive_bytes()
+            .expect("octet string");
+        assert_eq!(b, &b"b"[..]);
+        assert_eq!(b.as_ptr(), bytes[7..].as_ptr());
+    }
+
+    #[test]
+    fn test_high_tag_numbers() {
+        let cases: &[(u64, &[u8])] = &[
+            (30, &[0x9e]),
+            (31, &[0x9f, 0x1f]),
+            (127, &[0x9f, 0x7f]),
+            (128, &[0x9f, 0x81, 0x00]),
+            (16383, &[0x9f, 0xff, 0x7f]),
+        ];
+        for &(id, header) in cases {
+            let mut bytes = header.to_vec();
+            bytes.extend_from_slice(&[1, 97]);
+            let result_tag = StructureTag {
+                class: TagClass::Context,
+                id,
+                payload: PL::P(Bytes::from_static(b"a")),
+            };
+            assert_eq!(parse_tag(&bytes[..]), Ok((&[][..], result_tag)));
+            assert_eq!(
+                parse_tag_len(&bytes[..header.len()]).map_err(|e| e.is_incomplete()),
+                Err(true)
+            );
+        }
+        // Application class, constructed.
+        let bytes: Vec<u8> = vec![0x7f, 0x81, 0x00, 3, 4, 1, 97];
+        let (_, tag) = parse_tag(&bytes[..]).expect("tag");
+        assert_eq!((tag.class, tag.id), (TagClass::Application, 128));
+        assert_eq!(tag.expect_constructed().map(|v| v.len()), Some(1));
+    }
+
+    #[test]
+    fn test_invalid_high_tag_numbers() {
+        let bytes: Vec<u8> = vec![0x9f, 0x80, 0x01, 1, 97];
+        assert_eq!(
+            parse_tag(&bytes[..]),
+            Err(nom::Err::Failure(Error::new(
+                &bytes[1..],
+                ErrorKind::Verify
+            )))
+        );
+        let mut bytes: Vec<u8> = vec![0x9f];
+        bytes.extend_from_slice(&[0xff; 10]);
+        bytes.extend_from_slice(&[0x7f, 1, 97]);
+        assert_eq!(
+            parse_tag(&bytes[..]),
+            Err(nom::Err::Failure(Error::new(
+                &bytes[1..],
+                ErrorKind::TooLarge
+            )))
+        );
+    }
+
+    #[test]
+    fn test_indefinite_length() {
+        let bytes: Vec<u8> = vec![48, 128, 2, 1, 1, 0, 0];
+        assert_eq!(
+            parse_tag(&bytes[..]),
+            Err(nom::Err::Failure(Error::new(
+                &bytes[1..],
+                ErrorKind::LengthValue
+            )))
+        );
+    }
+
+    #[test]
+    fn test_reserved_length() {
+        let bytes: Vec<u8> = vec![4, 255, 0, 0];
+        assert_eq!(
+            parse_tag(&bytes[..]),
+            Err(nom::Err::Failure(Error::new(
+                &bytes[1..],
+                ErrorKind::Verify
+            )))
+        );
+    }
+
+    #[test]
+    fn test_too_many_length_octets() {
+        let bytes: Vec<u8> = vec![4, 137, 0, 0, 0, 0, 0, 0, 0, 0, 1, 97];
+        assert_eq!(
+            parse_tag(&bytes[..]),
+            Err(nom::Err::Failure(Error::new(
+                &bytes[1..],
+                ErrorKind::TooLarge
+            )))
+        );
+        // Eight octets are fine, as long as the value fits.
+        let bytes: Vec<u8> = vec![4, 136, 0, 0, 0, 0, 0, 0, 0, 1, 97];
+        assert_eq!(parse_tag_len(&bytes[..]), Ok((&bytes[10..], 11)));
+    }
+
+    #[test]
+    fn test_uint_too_long() {
+        assert_eq!(
+            parse_uint(&[1, 0, 0, 0, 0, 0, 0, 0, 0]),
+            Err(nom::Err::Error(Error::new(
+                &[1, 0, 0, 0, 0, 0, 0, 0, 0][..],
+                ErrorKind::TooLarge
+            )))
+        );
+        assert_eq!(parse_uint(&[255; 8]), Ok((&[255; 8][..], u64::MAX)));
+    }
+
+    #[test]
+    fn test_int() {
+        let empty: &[u8] = &[];
+        assert_eq!(parse_int(&[0x00, 0x80]), Ok((empty, 128)));
+        assert_eq!(parse_int(&[0x00, 0x00, 0x01]), Ok((empty, 1)));
+        assert_eq!(parse_int(&[0x7f]), Ok((empty, 127)));
+        assert_eq!(parse_int(&[0x80]), Ok((empty, -128)));
+        assert_eq!(parse_int(&[0xff]), Ok((empty, -1)));
+        assert_eq!(parse_int(&[0xff, 0x7f]), Ok((empty, -129)));
+        assert_eq!(parse_int(&[0xff, 0xff, 0x80]), Ok((empty, -128)));
+        assert_eq!(
+            parse_int(&[0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]),
+            Ok((empty, i64::MAX))
+        );
+        assert_eq!(
+            parse_int(&[0x80, 0, 0, 0, 0, 0, 0, 0]),
+            Ok((empty, i64::MIN))
+        );
+        assert_eq!(
+            parse_int(&[0x00, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]),
+            Ok((empty, i64::MAX))
+        );
+    }
+
+    #[test]
+    fn test_int_invalid() {
+        let too_large = [0x00, 0x80, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(
+            parse_int(&too_large),
+            Err(nom::Err::Error(Error::new(
+                &too_large[..],
+                ErrorKind::TooLarge
+            )))
+        );
+        assert_eq!(
+            parse_int(&[]),
+            Err(nom::Err::Error(Error::new(&[][..], ErrorKind::Eof)))
+        );
+    }
 }
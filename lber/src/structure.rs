@@ -1,4 +1,6 @@
+use bytes::Bytes;
 use common::TagClass;
+use parse::parse_int;
 
 /// ASN.1 structure prepared for serialization.
 #[derive(Clone, PartialEq, Debug, Eq)]
@@ -11,8 +13,9 @@ pub struct StructureTag {
 /// Tagged value payload.
 #[derive(Clone, PartialEq, Debug, Eq)]
 pub enum PL {
-    /// Primitive value.
-    P(Vec<u8>),
+    /// Primitive value. When parsed with [`parse_tag_bytes()`](../parse/fn.parse_tag_bytes.html),
+    /// the payload shares the buffer with the original input.
+    P(Bytes),
     /// Constructed value.
     C(Vec<StructureTag>),
 }
@@ -42,11 +45,33 @@ impl StructureTag {
     }
 
     pub fn expect_primitive(self) -> Option<Vec<u8>> {
+        self.expect_primitive_bytes().map(Vec::from)
+    }
+
+    /// Return the primitive payload without copying it.
+    pub fn expect_primitive_bytes(self) -> Option<Bytes> {
         match self.payload {
             PL::P(i) => Some(i),
             PL::C(_) => None,
         }
     }
+
+    /// Interpret the primitive payload as a boolean. The payload must be exactly
+    /// one byte long; any non-zero value is true.
+    pub fn expect_bool(self) -> Option<bool> {
+        match self.payload {
+            PL::P(ref i) if i.len() == 1 => Some(i[0] != 0),
+            _ => None,
+        }
+    }
+
+    /// Interpret the primitive payload as a signed integer.
+    pub fn expect_i64(self) -> Option<i64> {
+        match self.payload {
+            PL::P(ref i) => parse_int(i).ok().map(|(_, i)| i),
+            PL::C(_) => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -62,7 +87,7 @@ mod tests {
             payload: PL::C(vec![StructureTag {
                 class: TagClass::Universal,
                 id: 2u64,
-                payload: PL::P(vec![0x16, 0x16]),
+                payload: PL::P(Bytes::from_static(&[0x16, 0x16])),
             }]),
         };
 
@@ -83,12 +108,12 @@ mod tests {
                 StructureTag {
                     class: TagClass::Universal,
                     id: 2u64,
-                    payload: PL::P(vec![0x16, 0x16]),
+                    payload: PL::P(Bytes::from_static(&[0x16, 0x16])),
                 },
                 StructureTag {
                     class: TagClass::Application,
                     id: 3u64,
-                    payload: PL::P(vec![0x3, 0x3]),
+                    payload: PL::P(Bytes::from_static(&[0x3, 0x3])),
                 },
             ]),
         };
@@ -109,4 +134,27 @@ mod tests {
         assert!(a.is_some());
         assert!(b.is_some());
     }
+
+    #[test]
+    fn typed_payloads() {
+        let prim = |payload: &'static [u8]| StructureTag {
+            class: TagClass::Universal,
+            id: 1,
+            payload: PL::P(Bytes::from_static(payload)),
+        };
+        assert_eq!(prim(&[0xff]).expect_bool(), Some(true));
+        assert_eq!(prim(&[0x01]).expect_bool(), Some(true));
+        assert_eq!(prim(&[0x00]).expect_bool(), Some(false));
+        assert_eq!(prim(&[]).expect_bool(), None);
+        assert_eq!(prim(&[0x00, 0x00]).expect_bool(), None);
+        assert_eq!(prim(&[0xfe]).expect_i64(), Some(-2));
+        assert_eq!(prim(&[]).expect_i64(), None);
+        let cons = StructureTag {
+            class: TagClass::Universal,
+            id: 2,
+            payload: PL::C(vec![]),
+        };
+        assert_eq!(cons.clone().expect_bool(), None);
+        assert_eq!(cons.expect_i64(), None);
+    }
 }
@@ -1,3 +1,4 @@
+use bytes::Bytes;
 use std::default;
 use structure;
 use universal;
@@ -18,7 +19,11 @@ impl ASNTag for Boolean {
         structure::StructureTag {
             id: self.id,
             class: self.class,
-            payload: structure::PL::P(if self.inner { vec![0xFF] } else { vec![0x00] }),
+            payload: structure::PL::P(Bytes::from_static(if self.inner {
+                &[0xFF]
+            } else {
+                &[0x00]
+            })),
         }
     }
 }
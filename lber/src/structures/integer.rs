@@ -1,5 +1,7 @@
 use super::ASNTag;
+use bytes::Bytes;
 use common::TagClass;
+use parse::parse_int;
 use structure;
 use universal;
 
@@ -21,35 +23,36 @@ pub struct Enumerated {
     pub inner: i64,
 }
 
-fn i_e_into_structure(id: u64, class: TagClass, inner: i64) -> structure::StructureTag {
-    let mut count = 0u8;
-    let mut rem: i64 = if inner >= 0 { inner } else { -inner };
-    while {
-        count += 1;
-        rem >>= 8;
-        rem > 0
-    } {}
-
-    // Ensure that the most significant bit is always 0, because BER uses signed numbers.
-    // We shift away all but the most significant bit and check that.
-    // See #21
-    if inner > 0 && inner >> ((8 * count) - 1) == 1 {
-        count += 1;
+impl Integer {
+    /// Extract the value from a parsed integer, irrespective of its tag. Returns `None`
+    /// if the tag is constructed, or if its value doesn't fit into an `i64`.
+    pub fn from_structure(tag: &structure::StructureTag) -> Option<i64> {
+        match tag.payload {
+            structure::PL::P(ref v) => parse_int(v).ok().map(|(_, i)| i),
+            structure::PL::C(_) => None,
+        }
     }
+}
 
-    let mut count = count as usize;
-    let mut out: Vec<u8> = Vec::with_capacity(count);
+fn i_e_into_structure(id: u64, class: TagClass, inner: i64) -> structure::StructureTag {
+    // Use the shortest two's complement representation. A leading byte can be dropped
+    // if it only repeats the sign bit of the following one. This also ensures that the
+    // most significant bit of a positive number is always 0, because BER uses signed
+    // numbers. See #21
     let repr = inner.to_be_bytes();
-    if count > repr.len() {
-        out.push(0);
-        count -= 1;
+    let mut skip = 0;
+    while skip < repr.len() - 1
+        && ((repr[skip] == 0x00 && repr[skip + 1] & 0x80 == 0)
+            || (repr[skip] == 0xff && repr[skip + 1] & 0x80 != 0))
+    {
+        skip += 1;
     }
-    out.extend_from_slice(&repr[repr.len() - count..]);
+    let out = repr[skip..].to_vec();
 
     structure::StructureTag {
         id,
         class,
-        payload: structure::PL::P(out),
+        payload: structure::PL::P(Bytes::from(out)),
     }
 }
 
@@ -87,7 +90,9 @@ impl default::Default for Enumerated {
 
 #[cfg(test)]
 mod test {
-    use super::i_e_into_structure;
+    use super::{i_e_into_structure, Integer};
+
+    use bytes::Bytes;
 
     use common::TagClass;
     use structure;
@@ -96,7 +101,7 @@ mod test {
     fn test_not_unnecessary_octets() {
         // 127 can be encoded into 8 bits
         let result = i_e_into_structure(2, TagClass::Universal, 127);
-        let correct = structure::PL::P(vec![127]);
+        let correct = structure::PL::P(Bytes::from_static(&[127]));
         assert_eq![result.payload, correct];
     }
 
@@ -105,7 +110,31 @@ mod test {
         // 128 can be encoded cannot be encoded into a 8 bit signed number
         // See #21
         let result = i_e_into_structure(2, TagClass::Universal, 128);
-        let correct = structure::PL::P(vec![0, 128]);
+        let correct = structure::PL::P(Bytes::from_static(&[0, 128]));
         assert_eq![result.payload, correct];
     }
+
+    #[test]
+    fn test_negative() {
+        let result = i_e_into_structure(2, TagClass::Universal, -129);
+        let correct = structure::PL::P(Bytes::from_static(&[0xff, 0x7f]));
+        assert_eq![result.payload, correct];
+        let result = i_e_into_structure(2, TagClass::Universal, -128);
+        let correct = structure::PL::P(Bytes::from_static(&[0x80]));
+        assert_eq![result.payload, correct];
+    }
+
+    #[test]
+    fn test_from_structure() {
+        for &i in &[0, 1, 127, 128, 256, -1, -128, -129, i64::MAX, i64::MIN] {
+            let tag = i_e_into_structure(2, TagClass::Universal, i);
+            assert_eq!(Integer::from_structure(&tag), Some(i));
+        }
+        let tag = structure::StructureTag {
+            id: 2,
+            class: TagClass::Universal,
+            payload: structure::PL::C(vec![]),
+        };
+        assert_eq!(Integer::from_structure(&tag), None);
+    }
 }
@@ -1,10 +1,17 @@
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+use common::TagClass;
 use structure;
+use universal::Types;
 
 pub mod boolean;
 pub mod explicit;
 pub mod integer;
 pub mod null;
 pub mod octetstring;
+pub mod oid;
 pub mod sequence;
 
 // Reexport everything
@@ -13,6 +20,7 @@ pub use self::explicit::ExplicitTag;
 pub use self::integer::{Enumerated, Integer};
 pub use self::null::Null;
 pub use self::octetstring::OctetString;
+pub use self::oid::{oid_from_structure, ObjectIdentifier, ParseOidError};
 pub use self::sequence::{Sequence, SequenceOf, Set, SetOf};
 
 /// Conversion of a tag into a serializable form.
@@ -43,6 +51,8 @@ pub enum Tag {
     Boolean(boolean::Boolean),
     /// Null value.
     Null(null::Null),
+    /// Object identifier.
+    ObjectIdentifier(oid::ObjectIdentifier),
     /// Explicitly tagged value. LDAP uses implicit tagging, but external structures might not.
     ExplicitTag(explicit::ExplicitTag),
     /// Serializable value.
@@ -59,8 +69,234 @@ impl ASNTag for Tag {
             Tag::OctetString(i) => i.into_structure(),
             Tag::Boolean(i) => i.into_structure(),
             Tag::Null(i) => i.into_structure(),
+            Tag::ObjectIdentifier(i) => i.into_structure(),
             Tag::ExplicitTag(i) => i.into_structure(),
             Tag::StructureTag(s) => s,
         }
     }
 }
+
+/// Error converting a parsed structure into a [`Tag`](enum.Tag.html): the payload of
+/// a universal tag doesn't conform to its type. Contains the offending tag.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidTag(pub structure::StructureTag);
+
+impl fmt::Display for InvalidTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid payload for universal tag {}", self.0.id)
+    }
+}
+
+impl Error for InvalidTag {}
+
+impl TryFrom<structure::StructureTag> for Tag {
+    type Error = InvalidTag;
+
+    /// Map universal tags of the basic types to their typed counterparts, recursively
+    /// for sequences and sets. Tags of other classes and types are returned as
+    /// `Tag::StructureTag`, since their meaning depends on the context.
+    fn try_from(tag: structure::StructureTag) -> Result<Tag, InvalidTag> {
+        use parse::parse_int;
+        use structure::{StructureTag, PL};
+
+        const BOOLEAN: u64 = Types::Boolean as u64;
+        const INTEGER: u64 = Types::Integer as u64;
+        const OCTET_STRING: u64 = Types::OctetString as u64;
+        const NULL: u64 = Types::Null as u64;
+        const OBJECT_IDENTIFIER: u64 = Types::ObjectIdentifier as u64;
+        const ENUMERATED: u64 = Types::Enumerated as u64;
+        const SEQUENCE: u64 = Types::Sequence as u64;
+        const SET: u64 = Types::Set as u64;
+
+        if tag.class != TagClass::Universal {
+            return Ok(Tag::StructureTag(tag));
+        }
+        let StructureTag { id, class, payload } = tag;
+        let typed = match (id, &payload) {
+            (INTEGER, PL::P(p)) => parse_int(p)
+                .ok()
+                .map(|(_, inner)| Tag::Integer(Integer { id, class, inner })),
+            (ENUMERATED, PL::P(p)) => parse_int(p)
+                .ok()
+                .map(|(_, inner)| Tag::Enumerated(Enumerated { id, class, inner })),
+            (BOOLEAN, PL::P(p)) if p.len() == 1 => Some(Tag::Boolean(Boolean {
+                id,
+                class,
+                inner: p[0] != 0,
+            })),
+            (NULL, PL::P(p)) if p.is_empty() => Some(Tag::Null(Null {
+                id,
+                class,
+                inner: (),
+            })),
+            (OCTET_STRING, PL::P(p)) => Some(Tag::OctetString(OctetString {
+                id,
+                class,
+                inner: p.to_vec(),
+            })),
+            (OBJECT_IDENTIFIER, PL::P(_)) => oid_from_structure(&StructureTag {
+                id,
+                class,
+                payload: payload.clone(),
+            })
+            .map(|inner| Tag::ObjectIdentifier(ObjectIdentifier { id, class, inner })),
+            (SEQUENCE, PL::C(_)) | (SET, PL::C(_)) => {
+                let inner = match payload {
+                    PL::C(inner) => inner
+                        .into_iter()
+                        .map(Tag::try_from)
+                        .collect::<Result<_, _>>()?,
+                    PL::P(_) => unreachable!(),
+                };
+                return Ok(if id == SEQUENCE {
+                    Tag::Sequence(Sequence { id, class, inner })
+                } else {
+                    Tag::Set(Set { id, class, inner })
+                });
+            }
+            (BOOLEAN, _)
+            | (INTEGER, _)
+            | (OCTET_STRING, _)
+            | (NULL, _)
+            | (OBJECT_IDENTIFIER, _)
+            | (ENUMERATED, _)
+            | (SEQUENCE, _)
+            | (SET, _) => None,
+            _ => return Ok(Tag::StructureTag(StructureTag { id, class, payload })),
+        };
+        typed.ok_or(InvalidTag(StructureTag { id, class, payload }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bytes::{Bytes, BytesMut};
+    use parse::parse_tag;
+    use structure::{StructureTag, PL};
+    use write;
+
+    // Small deterministic generator, so that failures are reproducible.
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: u64) -> u64 {
+            self.next() % n
+        }
+    }
+
+    fn random_int(rng: &mut XorShift) -> i64 {
+        // Favor small magnitudes and the boundaries between encoded lengths.
+        match rng.below(4) {
+            0 => rng.next() as i64,
+            1 => rng.next() as i64 >> (rng.below(64)),
+            2 => (1i64 << rng.below(63)) - (rng.below(3) as i64),
+            _ => -(1i64 << rng.below(63)) + (rng.below(3) as i64) - 1,
+        }
+    }
+
+    fn random_tag(rng: &mut XorShift, depth: u32) -> Tag {
+        let kinds = if depth == 0 { 6 } else { 8 };
+        match rng.below(kinds) {
+            0 => Tag::Integer(Integer {
+                inner: random_int(rng),
+                ..Default::default()
+            }),
+            1 => Tag::Enumerated(Enumerated {
+                inner: random_int(rng),
+                ..Default::default()
+            }),
+            2 => Tag::Boolean(Boolean {
+                inner: rng.below(2) == 1,
+                ..Default::default()
+            }),
+            3 => Tag::Null(Null::default()),
+            4 => Tag::OctetString(OctetString {
+                inner: (0..rng.below(300)).map(|_| rng.next() as u8).collect(),
+                ..Default::default()
+            }),
+            5 => {
+                let first = rng.below(3);
+                let second = if first < 2 { rng.below(40) } else { rng.next() };
+                let mut inner = vec![first, second];
+                inner.extend((0..rng.below(6)).map(|_| rng.next() >> rng.below(64)));
+                Tag::ObjectIdentifier(ObjectIdentifier {
+                    inner,
+                    ..Default::default()
+                })
+            }
+            6 => Tag::Sequence(Sequence {
+                inner: (0..rng.below(5))
+                    .map(|_| random_tag(rng, depth - 1))
+                    .collect(),
+                ..Default::default()
+            }),
+            _ => Tag::Set(Set {
+                inner: (0..rng.below(5))
+                    .map(|_| random_tag(rng, depth - 1))
+                    .collect(),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        for _ in 0..2000 {
+            let tag = random_tag(&mut rng, 3);
+            let mut buf = BytesMut::new();
+            write::encode_into(&mut buf, tag.clone().into_structure()).expect("encoded");
+            let (rest, parsed) = parse_tag(&buf).expect("parsed");
+            assert!(rest.is_empty());
+            assert_eq!(Tag::try_from(parsed), Ok(tag));
+        }
+    }
+
+    #[test]
+    fn invalid_payloads() {
+        let prim = |id: Types, payload: &'static [u8]| StructureTag {
+            class: TagClass::Universal,
+            id: id as u64,
+            payload: PL::P(Bytes::from_static(payload)),
+        };
+        for tag in [
+            prim(Types::Boolean, &[]),
+            prim(Types::Boolean, &[0, 0]),
+            prim(Types::Null, &[0]),
+            prim(Types::Integer, &[]),
+            prim(Types::ObjectIdentifier, &[0x2b, 0x86]),
+            prim(Types::Sequence, &[]),
+            StructureTag {
+                class: TagClass::Universal,
+                id: Types::OctetString as u64,
+                payload: PL::C(vec![]),
+            },
+        ] {
+            assert_eq!(Tag::try_from(tag.clone()), Err(InvalidTag(tag)));
+        }
+    }
+
+    #[test]
+    fn untyped_tags() {
+        let ctx = StructureTag {
+            class: TagClass::Context,
+            id: 1,
+            payload: PL::P(Bytes::from_static(&[0xff])),
+        };
+        assert_eq!(Tag::try_from(ctx.clone()), Ok(Tag::StructureTag(ctx)));
+        let utf8 = StructureTag {
+            class: TagClass::Universal,
+            id: Types::Utf8String as u64,
+            payload: PL::P(Bytes::from_static(b"abc")),
+        };
+        assert_eq!(Tag::try_from(utf8.clone()), Ok(Tag::StructureTag(utf8)));
+    }
+}
@@ -1,3 +1,4 @@
+use bytes::Bytes;
 use std::default;
 use structure;
 use universal;
@@ -18,7 +19,7 @@ impl ASNTag for Null {
         structure::StructureTag {
             id: self.id,
             class: self.class,
-            payload: structure::PL::P(Vec::new()),
+            payload: structure::PL::P(Bytes::new()),
         }
     }
 }
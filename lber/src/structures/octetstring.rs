@@ -1,8 +1,9 @@
+use bytes::Bytes;
 use std::default;
 use structure;
 use universal;
 
-use super::ASNTag;
+use super::{ASNTag, Tag};
 use common::TagClass;
 
 /// String of bytes.
@@ -13,12 +14,34 @@ pub struct OctetString {
     pub inner: Vec<u8>,
 }
 
+impl OctetString {
+    /// Create a universal octet string tag with an already allocated payload, which is
+    /// used for encoding without being copied into a `Vec<u8>` first.
+    pub fn from_bytes<B: Into<Bytes>>(inner: B) -> Tag {
+        OctetString::from_tagged_bytes(
+            TagClass::Universal,
+            universal::Types::OctetString as u64,
+            inner,
+        )
+    }
+
+    /// Create an implicitly tagged octet string tag with an already allocated payload,
+    /// like [`from_bytes()`](#method.from_bytes).
+    pub fn from_tagged_bytes<B: Into<Bytes>>(class: TagClass, id: u64, inner: B) -> Tag {
+        Tag::StructureTag(structure::StructureTag {
+            id,
+            class,
+            payload: structure::PL::P(inner.into()),
+        })
+    }
+}
+
 impl ASNTag for OctetString {
     fn into_structure(self) -> structure::StructureTag {
         structure::StructureTag {
             id: self.id,
             class: self.class,
-            payload: structure::PL::P(self.inner),
+            payload: structure::PL::P(Bytes::from(self.inner)),
         }
     }
 }
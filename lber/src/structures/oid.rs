@@ -0,0 +1,252 @@
+use super::ASNTag;
+use bytes::Bytes;
+use common::TagClass;
+use structure;
+use universal;
+
+use std::convert::TryFrom;
+use std::default;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Object identifier, as a sequence of arcs.
+///
+/// An identifier must have at least two arcs. The first arc is 0, 1, or 2, and if
+/// it's 0 or 1, the second one must be less than 40, since BER packs the first two
+/// arcs into a single subidentifier. Parsing the dotted notation with
+/// [`from_str()`](#method.from_str) enforces these rules.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectIdentifier {
+    pub id: u64,
+    pub class: TagClass,
+    pub inner: Vec<u64>,
+}
+
+/// Error parsing the dotted notation of an [`ObjectIdentifier`](struct.ObjectIdentifier.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseOidError;
+
+impl fmt::Display for ParseOidError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid object identifier")
+    }
+}
+
+impl Error for ParseOidError {}
+
+// Check the constraints on the first two arcs.
+fn valid_arcs(arcs: &[u64]) -> bool {
+    match arcs {
+        [0..=1, second, ..] => *second < 40,
+        [2, _, ..] => true,
+        _ => false,
+    }
+}
+
+// Append the base-128 encoding of a subidentifier, most significant group first,
+// with the high bit set on every byte but the last.
+fn push_subid(out: &mut Vec<u8>, subid: u128) {
+    let groups = (128 - subid.leading_zeros()).div_ceil(7).max(1);
+    for n in (0..groups).rev() {
+        let byte = (subid >> (n * 7)) as u8 & 0x7f;
+        out.push(if n > 0 { byte | 0x80 } else { byte });
+    }
+}
+
+impl ASNTag for ObjectIdentifier {
+    /// Encode the identifier.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the identifier has fewer than two arcs, or if the first two arcs
+    /// are out of range.
+    fn into_structure(self) -> structure::StructureTag {
+        assert!(valid_arcs(&self.inner), "invalid object identifier arcs");
+        let mut out = Vec::with_capacity(self.inner.len() * 2);
+        push_subid(&mut out, self.inner[0] as u128 * 40 + self.inner[1] as u128);
+        for &arc in &self.inner[2..] {
+            push_subid(&mut out, arc as u128);
+        }
+        structure::StructureTag {
+            id: self.id,
+            class: self.class,
+            payload: structure::PL::P(Bytes::from(out)),
+        }
+    }
+}
+
+/// Extract the arcs from a parsed object identifier, irrespective of its tag. Returns
+/// `None` if the tag is constructed, if the encoding is invalid or not minimal, or if
+/// an arc doesn't fit into a `u64`.
+pub fn oid_from_structure(tag: &structure::StructureTag) -> Option<Vec<u64>> {
+    let bytes = match tag.payload {
+        structure::PL::P(ref bytes) => bytes,
+        structure::PL::C(_) => return None,
+    };
+    let mut subids = vec![];
+    let mut subid: u128 = 0;
+    let mut start = true;
+    for &byte in bytes.iter() {
+        // A leading 0x80 byte would be a redundant zero group.
+        if start && byte == 0x80 {
+            return None;
+        }
+        if subid > u128::MAX >> 7 {
+            return None;
+        }
+        subid = (subid << 7) | (byte & 0x7f) as u128;
+        start = byte & 0x80 == 0;
+        if start {
+            subids.push(subid);
+            subid = 0;
+        }
+    }
+    if !start || subids.is_empty() {
+        return None;
+    }
+    let mut arcs = Vec::with_capacity(subids.len() + 1);
+    let (first, second) = match subids[0] {
+        n if n < 40 => (0, n),
+        n if n < 80 => (1, n - 40),
+        n => (2, n - 80),
+    };
+    arcs.push(first);
+    for subid in std::iter::once(second).chain(subids[1..].iter().copied()) {
+        arcs.push(u64::try_from(subid).ok()?);
+    }
+    Some(arcs)
+}
+
+impl FromStr for ObjectIdentifier {
+    type Err = ParseOidError;
+
+    /// Parse the dotted notation. Arcs are decimal numbers without leading zeros.
+    fn from_str(s: &str) -> Result<ObjectIdentifier, ParseOidError> {
+        let inner = s
+            .split('.')
+            .map(|arc| {
+                let digits = arc.bytes().all(|b| b.is_ascii_digit());
+                if !digits || arc.is_empty() || (arc.len() > 1 && arc.starts_with('0')) {
+                    return Err(ParseOidError);
+                }
+                arc.parse().map_err(|_| ParseOidError)
+            })
+            .collect::<Result<Vec<u64>, _>>()?;
+        if !valid_arcs(&inner) {
+            return Err(ParseOidError);
+        }
+        Ok(ObjectIdentifier {
+            inner,
+            ..Default::default()
+        })
+    }
+}
+
+impl fmt::Display for ObjectIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (n, arc) in self.inner.iter().enumerate() {
+            if n > 0 {
+                write!(f, ".")?;
+            }
+            write!(f, "{}", arc)?;
+        }
+        Ok(())
+    }
+}
+
+impl default::Default for ObjectIdentifier {
+    fn default() -> Self {
+        ObjectIdentifier {
+            id: universal::Types::ObjectIdentifier as u64,
+            class: TagClass::Universal,
+            inner: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn encoded(oid: &str) -> Vec<u8> {
+        let oid: ObjectIdentifier = oid.parse().expect("oid");
+        match oid.into_structure().payload {
+            structure::PL::P(bytes) => bytes.to_vec(),
+            structure::PL::C(_) => unreachable!(),
+        }
+    }
+
+    fn decoded(bytes: &'static [u8]) -> Option<Vec<u64>> {
+        oid_from_structure(&structure::StructureTag {
+            id: universal::Types::ObjectIdentifier as u64,
+            class: TagClass::Universal,
+            payload: structure::PL::P(Bytes::from_static(bytes)),
+        })
+    }
+
+    #[test]
+    fn oid_vectors() {
+        let vectors: &[(&str, &[u8])] = &[
+            ("2.999", &[0x88, 0x37]),
+            (
+                "1.3.6.1.4.1.311.21.20",
+                &[0x2b, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x15, 0x14],
+            ),
+            ("0.39", &[0x27]),
+            ("1.2.268435456", &[0x2a, 0x81, 0x80, 0x80, 0x80, 0x00]),
+            ("1.2.4294967295", &[0x2a, 0x8f, 0xff, 0xff, 0xff, 0x7f]),
+            (
+                "1.2.18446744073709551615",
+                &[
+                    0x2a, 0x81, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f,
+                ],
+            ),
+            (
+                "2.18446744073709551615",
+                &[0x82, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x4f],
+            ),
+        ];
+        for &(oid, bytes) in vectors {
+            assert_eq!(encoded(oid), bytes, "encoding {}", oid);
+            let arcs = decoded(bytes).expect("decoded");
+            let parsed = ObjectIdentifier {
+                inner: arcs,
+                ..Default::default()
+            };
+            assert_eq!(parsed.to_string(), oid);
+        }
+    }
+
+    #[test]
+    fn invalid_oids() {
+        for oid in [
+            "",
+            "1",
+            "3.1",
+            "0.40",
+            "1.2.",
+            ".1.2",
+            "1..2",
+            "1.02",
+            "1.+2",
+            "1.2.-3",
+            "1.2.18446744073709551616",
+        ] {
+            assert_eq!(
+                oid.parse::<ObjectIdentifier>(),
+                Err(ParseOidError),
+                "{}",
+                oid
+            );
+        }
+        for bytes in [
+            &b""[..],
+            b"\x2a\x86",
+            b"\x2a\x80\x01",
+            b"\x2a\x82\x80\x80\x80\x80\x80\x80\x80\x80\x00",
+        ] {
+            assert_eq!(decoded(bytes), None);
+        }
+    }
+}
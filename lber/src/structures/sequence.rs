@@ -1,6 +1,10 @@
+use std::cmp::Ordering;
 use std::default;
 use structure;
 use universal;
+use write;
+
+use bytes::BytesMut;
 
 use super::{ASNTag, Tag};
 use common::TagClass;
@@ -51,6 +55,22 @@ impl ASNTag for Set {
     }
 }
 
+impl Set {
+    /// Convert the set into a tag structure whose elements are in the canonical order
+    /// of X.690, section 11.6, instead of the order of `inner`.
+    ///
+    /// See [`der_order()`](fn.der_order.html) for the ordering.
+    pub fn into_der_structure(self) -> structure::StructureTag {
+        structure::StructureTag {
+            id: self.id,
+            class: self.class,
+            payload: structure::PL::C(der_order(
+                self.inner.into_iter().map(|x| x.into_structure()).collect(),
+            )),
+        }
+    }
+}
+
 impl default::Default for Set {
     fn default() -> Self {
         Set {
@@ -105,6 +125,22 @@ impl<T: ASNTag + Sized> ASNTag for SetOf<T> {
     }
 }
 
+impl<T: ASNTag + Sized> SetOf<T> {
+    /// Convert the set into a tag structure whose elements are in the canonical order
+    /// of X.690, section 11.6, instead of the order of `inner`.
+    ///
+    /// See [`der_order()`](fn.der_order.html) for the ordering.
+    pub fn into_der_structure(self) -> structure::StructureTag {
+        structure::StructureTag {
+            id: self.id,
+            class: self.class,
+            payload: structure::PL::C(der_order(
+                self.inner.into_iter().map(|x| x.into_structure()).collect(),
+            )),
+        }
+    }
+}
+
 impl<T: ASNTag + Sized> default::Default for SetOf<T> {
     fn default() -> Self {
         SetOf {
@@ -114,3 +150,126 @@ impl<T: ASNTag + Sized> default::Default for SetOf<T> {
         }
     }
 }
+
+/// Sort the elements of a SET OF by their encodings, as required by DER.
+///
+/// The encodings are compared as octet strings, with the shorter one padded with
+/// trailing zero octets (X.690, section 11.6). For elements of the same type, such
+/// as the values of an attribute, this puts shorter encodings first, and orders those
+/// of equal length lexicographically. Elements whose padded encodings are equal keep
+/// their relative order.
+pub fn der_order(tags: Vec<structure::StructureTag>) -> Vec<structure::StructureTag> {
+    let mut encoded: Vec<_> = tags
+        .into_iter()
+        .map(|tag| {
+            let mut buf = BytesMut::new();
+            write::encode_tag(&mut buf, &tag);
+            (buf, tag)
+        })
+        .collect();
+    encoded.sort_by(|(a, _), (b, _)| padded_cmp(a, b));
+    encoded.into_iter().map(|(_, tag)| tag).collect()
+}
+
+fn padded_cmp(a: &[u8], b: &[u8]) -> Ordering {
+    let common = a.len().min(b.len());
+    match a[..common].cmp(&b[..common]) {
+        Ordering::Equal => (),
+        ord => return ord,
+    }
+    if a[common..].iter().any(|&o| o != 0) {
+        Ordering::Greater
+    } else if b[common..].iter().any(|&o| o != 0) {
+        Ordering::Less
+    } else {
+        Ordering::Equal
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use parse::parse_tag;
+    use structure::{StructureTag, PL};
+    use structures::OctetString;
+
+    fn values(vals: &[&[u8]]) -> Vec<Tag> {
+        vals.iter()
+            .map(|v| {
+                Tag::OctetString(OctetString {
+                    inner: v.to_vec(),
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+
+    fn payloads(tag: &StructureTag) -> Vec<Vec<u8>> {
+        match tag.payload {
+            PL::C(ref tags) => tags
+                .iter()
+                .map(|t| match t.payload {
+                    PL::P(ref v) => v.to_vec(),
+                    PL::C(_) => panic!("constructed element"),
+                })
+                .collect(),
+            PL::P(_) => panic!("primitive set"),
+        }
+    }
+
+    #[test]
+    fn der_set_ordering() {
+        let unordered: &[&[u8]] = &[b"zz", b"abc", b"b", b"", b"ab", b"a", &[0xff; 200]];
+        let set = Set {
+            inner: values(unordered),
+            ..Default::default()
+        };
+        let plain = set.clone().into_structure();
+        assert_eq!(payloads(&plain), unordered.to_vec());
+        let der = set.into_der_structure();
+        let ordered: &[&[u8]] = &[b"", b"a", b"b", b"ab", b"zz", b"abc", &[0xff; 200]];
+        assert_eq!(payloads(&der), ordered.to_vec());
+
+        let set_of = SetOf {
+            inner: unordered
+                .iter()
+                .map(|v| OctetString {
+                    inner: v.to_vec(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        assert_eq!(set_of.into_der_structure(), der);
+    }
+
+    #[test]
+    fn der_set_decoding() {
+        let set = Set {
+            inner: values(&[b"second", b"1st", b"third!", b""]),
+            ..Default::default()
+        };
+        let der = set.into_der_structure();
+        let mut buf = BytesMut::new();
+        write::encode_into(&mut buf, der.clone()).expect("encoded");
+        let (rest, parsed) = parse_tag(&buf).expect("parsed");
+        assert!(rest.is_empty());
+        assert_eq!(parsed, der);
+        assert_eq!(
+            payloads(&parsed),
+            vec![
+                b"".to_vec(),
+                b"1st".to_vec(),
+                b"second".to_vec(),
+                b"third!".to_vec()
+            ]
+        );
+    }
+
+    #[test]
+    fn padded_comparison() {
+        assert_eq!(padded_cmp(b"\x04\x01a", b"\x04\x01a\x00"), Ordering::Equal);
+        assert_eq!(padded_cmp(b"\x04\x01a", b"\x04\x01a\x01"), Ordering::Less);
+        assert_eq!(padded_cmp(b"\x30\x00", b"\x04\x05hello"), Ordering::Greater);
+    }
+}
@@ -1,117 +1,131 @@
 //! BER encoding support.
-use bytes::BytesMut;
+use bytes::{BufMut, BytesMut};
 use common::{TagClass, TagStructure};
 use structure::{StructureTag, PL};
 
-use std::io::{self, Write};
+use std::io;
 
 /// BER-encode a tag structure into the provided buffer.
+///
+/// The buffer is grown to fit the whole encoding before writing, and each primitive
+/// payload is copied into it exactly once.
 pub fn encode_into(buf: &mut BytesMut, tag: StructureTag) -> io::Result<()> {
-    let mut tag_vec = Vec::new();
-    encode_inner(&mut tag_vec, tag)?;
-    buf.extend(tag_vec);
+    encode_tag(buf, &tag);
     Ok(())
 }
 
-fn encode_inner(buf: &mut Vec<u8>, tag: StructureTag) -> io::Result<()> {
+/// Return the length of the BER encoding of a tag structure, including the type
+/// and length octets.
+pub fn encoded_len(tag: &StructureTag) -> usize {
+    let len = content_len(tag);
+    type_len(tag.id) + length_len(len) + len
+}
+
+fn content_len(tag: &StructureTag) -> usize {
+    match tag.payload {
+        PL::P(ref v) => v.len(),
+        PL::C(ref tags) => tags.iter().map(encoded_len).sum(),
+    }
+}
+
+// Encode a borrowed tag structure. The content lengths of all tags are computed in
+// a single pass before writing, since each of them is needed for a length octet.
+pub(crate) fn encode_tag(buf: &mut BytesMut, tag: &StructureTag) {
+    let mut lens = vec![];
+    let len = content_lens(tag, &mut lens);
+    buf.reserve(type_len(tag.id) + length_len(len) + len);
+    encode_inner(buf, tag, &mut lens.into_iter());
+}
+
+// Append the content lengths of the tag and all tags nested in it to `lens`, in the
+// order in which the tags are encoded, and return the content length of the tag.
+fn content_lens(tag: &StructureTag, lens: &mut Vec<usize>) -> usize {
+    match tag.payload {
+        PL::P(ref v) => {
+            lens.push(v.len());
+            v.len()
+        }
+        PL::C(ref tags) => {
+            let pos = lens.len();
+            lens.push(0);
+            let len = tags
+                .iter()
+                .map(|tag| {
+                    let len = content_lens(tag, lens);
+                    type_len(tag.id) + length_len(len) + len
+                })
+                .sum();
+            lens[pos] = len;
+            len
+        }
+    }
+}
+
+fn encode_inner(buf: &mut BytesMut, tag: &StructureTag, lens: &mut impl Iterator<Item = usize>) {
     let structure = match tag.payload {
         PL::P(_) => TagStructure::Primitive,
         PL::C(_) => TagStructure::Constructed,
     };
 
     write_type(buf, tag.class, structure, tag.id);
+    write_length(buf, lens.next().expect("content length"));
     match tag.payload {
-        PL::P(v) => {
-            write_length(buf, v.len());
-            buf.extend(v);
-        }
-        PL::C(tags) => {
-            let mut tmp = Vec::new();
+        PL::P(ref v) => buf.extend_from_slice(v),
+        PL::C(ref tags) => {
             for tag in tags {
-                encode_inner(&mut tmp, tag)?;
+                encode_inner(buf, tag, lens);
             }
-            write_length(buf, tmp.len());
-            buf.extend(tmp);
         }
-    };
-
-    Ok(())
+    }
 }
 
-fn write_type(w: &mut dyn Write, class: TagClass, structure: TagStructure, id: u64) {
-    let extended_tag: Option<Vec<u8>>;
+fn type_len(id: u64) -> usize {
+    if id > 30 {
+        1 + (64 - id.leading_zeros() as usize).div_ceil(7)
+    } else {
+        1
+    }
+}
 
-    let type_byte = {
-        // First two bits: Class
-        (class as u8) << 6 |
-        // Bit 6: Primitive/Constructed
-        (structure as u8) << 5 |
+fn write_type(buf: &mut BytesMut, class: TagClass, structure: TagStructure, id: u64) {
+    // First two bits: Class
+    // Bit 6: Primitive/Constructed
+    let class_bits = (class as u8) << 6 | (structure as u8) << 5;
+    if id <= 30 {
         // Bit 5-1: Tag Number
-        if id > 30
-        {
-            let mut tagbytes: Vec<u8> = Vec::new();
-
-            let mut tag = id;
-            while tag > 0
-            {
-                // Only take the 7 lower bits.
-                let byte = (tag & 0x7F) as u8;
-
-                tag >>= 7;
-
-                tagbytes.push(byte);
-            }
-
-            extended_tag = Some(tagbytes);
-
-            // This means we need to set the 5 tag bits to 11111, so 31 or 0x1F
-            0x1F
-        }
-        else
-        {
-            extended_tag = None;
-            id as u8
-        }
-    }; // let type_byte
-
-    let _ = w.write(&[type_byte]);
-
-    if let Some(mut ext_bytes) = extended_tag {
-        for _ in 0..ext_bytes.len() - 1 {
-            let mut byte = ext_bytes.pop().unwrap();
-
-            // Set the first bit
-            byte |= 0x80;
-
-            let _ = w.write(&[byte]);
-        }
+        buf.put_u8(class_bits | id as u8);
+        return;
+    }
+    // This means we need to set the 5 tag bits to 11111, so 31 or 0x1F, and
+    // follow with the tag number in base 128, high bit set on all but the last byte.
+    buf.put_u8(class_bits | 0x1F);
+    let groups = type_len(id) - 1;
+    for n in (0..groups).rev() {
+        let byte = ((id >> (7 * n)) & 0x7F) as u8;
+        buf.put_u8(if n > 0 { byte | 0x80 } else { byte });
+    }
+}
 
-        let byte = ext_bytes.pop().unwrap();
-        let _ = w.write(&[byte]);
+fn length_len(length: usize) -> usize {
+    if length < 128 {
+        1
+    } else {
+        1 + (usize::BITS - length.leading_zeros()).div_ceil(8) as usize
     }
 }
 
 // Yes I know you could overflow the length in theory. But, do you have 2^64 bytes of memory?
-fn write_length(w: &mut dyn Write, length: usize) {
+fn write_length(buf: &mut BytesMut, length: usize) {
     // Short form
     if length < 128 {
-        let _ = w.write(&[length as u8]);
+        buf.put_u8(length as u8);
     }
     // Long form
     else {
-        let mut count = 0u8;
-        let mut len = length;
-        while {
-            count += 1;
-            len >>= 8;
-            len > 0
-        } {}
-
-        let _ = w.write(&[count | 0x80]);
+        let count = length_len(length) - 1;
+        buf.put_u8(count as u8 | 0x80);
         let repr = &length.to_be_bytes();
-        let len = repr.len();
-        let bytes = &repr[(len - (count as usize))..];
-        let _ = w.write_all(bytes);
+        buf.extend_from_slice(&repr[(repr.len() - count)..]);
     }
 }
 
@@ -198,4 +212,71 @@ mod tests {
 
         assert_eq!(buf, expected);
     }
+
+    #[test]
+    fn encoded_len_matches() {
+        let tag = Tag::Sequence(Sequence {
+            id: 1000,
+            class: Context,
+            inner: vec![
+                Tag::OctetString(OctetString {
+                    inner: vec![0x55; 300],
+                    ..Default::default()
+                }),
+                Tag::OctetString(OctetString {
+                    id: 31,
+                    class: Context,
+                    inner: vec![0xaa; 70000],
+                }),
+            ],
+        })
+        .into_structure();
+
+        let len = super::encoded_len(&tag);
+        let mut buf = BytesMut::new();
+        super::encode_into(&mut buf, tag).unwrap();
+
+        assert_eq!(len, buf.len());
+        assert_eq!(&buf[..7], &[0xbf, 0x87, 0x68, 0x83, 0x01, 0x12, 0xa6]);
+        assert_eq!(&buf[7..12], &[0x04, 0x82, 0x01, 0x2c, 0x55]);
+        assert_eq!(&buf[311..318], &[0x9f, 0x1f, 0x83, 0x01, 0x11, 0x70, 0xaa]);
+    }
+
+    #[test]
+    fn high_tag_numbers() {
+        use parse::parse_tag;
+
+        let cases: &[(u64, &[u8])] = &[
+            (30, &[0x9e]),
+            (31, &[0x9f, 0x1f]),
+            (127, &[0x9f, 0x7f]),
+            (128, &[0x9f, 0x81, 0x00]),
+            (16383, &[0x9f, 0xff, 0x7f]),
+        ];
+        for &(id, header) in cases {
+            let tag = Tag::OctetString(OctetString {
+                id,
+                class: Context,
+                inner: b"a".to_vec(),
+            })
+            .into_structure();
+            let mut buf = BytesMut::new();
+            super::encode_into(&mut buf, tag.clone()).unwrap();
+            assert_eq!(&buf[..header.len()], header);
+            assert_eq!(&buf[header.len()..], &[1, 97]);
+            assert_eq!(super::encoded_len(&tag), buf.len());
+            assert_eq!(parse_tag(&buf), Ok((&[][..], tag)));
+
+            let tag = Tag::Sequence(Sequence {
+                id,
+                class: Application,
+                inner: vec![Tag::Null(Null::default())],
+            })
+            .into_structure();
+            let mut buf = BytesMut::new();
+            super::encode_into(&mut buf, tag.clone()).unwrap();
+            assert_eq!(buf[0], header[0] & 0x1f | 0x60);
+            assert_eq!(parse_tag(&buf), Ok((&[][..], tag)));
+        }
+    }
 }
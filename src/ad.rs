@@ -0,0 +1,316 @@
+//! Active Directory value helpers.
+//!
+//! AD stores several commonly used attributes in binary or otherwise opaque formats:
+//! `objectGUID` is a 16-byte GUID in the mixed-endian Windows layout, `objectSid`
+//! and `tokenGroups` are binary security identifiers, and time stamps like
+//! `pwdLastSet` or `lastLogonTimestamp` are FILETIME values, counting 100-nanosecond
+//! intervals since January 1, 1601 (UTC). The functions in this module convert those
+//! values to and from their customary string representations.
+//!
+//! Binary values are found in the `bin_attrs` map of a
+//! [`SearchEntry`](../struct.SearchEntry.html); constructing the entry with the default
+//! [`BinaryPolicy`](../struct.BinaryPolicy.html) ensures that `objectGUID` and `objectSid`
+//! are always placed there. To search by GUID or SID, the binary value must be escaped
+//! in the filter, which is what [`guid_filter_bytes()`](fn.guid_filter_bytes.html) and
+//! [`sid_filter_bytes()`](fn.sid_filter_bytes.html) do.
+
+use std::fmt::Write;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use thiserror::Error;
+
+/// Errors in converting AD values.
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum AdError {
+    /// Binary GUID doesn't have exactly 16 bytes.
+    #[error("invalid binary GUID length: {0}")]
+    GuidLength(usize),
+    /// GUID string is not in the `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx` format.
+    #[error("malformed GUID string: {0}")]
+    MalformedGuid(String),
+    /// Binary SID is truncated or its length doesn't match the sub-authority count.
+    #[error("invalid binary SID length: {0}")]
+    SidLength(usize),
+    /// SID revision is not 1.
+    #[error("unsupported SID revision: {0}")]
+    SidRevision(u8),
+    /// SID string is not in the `S-1-...` format, or its components are out of range.
+    #[error("malformed SID string: {0}")]
+    MalformedSid(String),
+}
+
+// Seconds between 1601-01-01 and 1970-01-01.
+const FILETIME_EPOCH_OFFSET: u64 = 11_644_473_600;
+const FILETIME_NEVER: i64 = 0x7FFF_FFFF_FFFF_FFFF;
+const MAX_SUB_AUTHORITIES: usize = 15;
+
+/// Convert a binary GUID into the canonical string form, e.g.
+/// `6b29fc40-ca47-1067-b31d-00dd010662da`.
+///
+/// The first three groups are stored in little-endian order, the last two as is.
+pub fn parse_guid(bytes: &[u8]) -> Result<String, AdError> {
+    if bytes.len() != 16 {
+        return Err(AdError::GuidLength(bytes.len()));
+    }
+    Ok(format!(
+        "{:08x}-{:04x}-{:04x}-{}-{}",
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        u16::from_le_bytes([bytes[4], bytes[5]]),
+        u16::from_le_bytes([bytes[6], bytes[7]]),
+        hex(&bytes[8..10]),
+        hex(&bytes[10..16])
+    ))
+}
+
+/// Convert a GUID string into its binary form. Surrounding braces are allowed, and
+/// hex digits can be in either case.
+pub fn guid_to_bytes(guid: &str) -> Result<Vec<u8>, AdError> {
+    let malformed = || AdError::MalformedGuid(guid.to_owned());
+    let s = guid
+        .strip_prefix('{')
+        .and_then(|s| s.strip_suffix('}'))
+        .unwrap_or(guid);
+    let groups = s.split('-').collect::<Vec<_>>();
+    if groups.len() != 5
+        || groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .any(|(g, len)| g.len() != len || !g.bytes().all(|c| c.is_ascii_hexdigit()))
+    {
+        return Err(malformed());
+    }
+    let mut bytes = Vec::with_capacity(16);
+    for (i, group) in groups.iter().enumerate() {
+        let mut group_bytes = unhex(group).ok_or_else(malformed)?;
+        if i < 3 {
+            group_bytes.reverse();
+        }
+        bytes.extend(group_bytes);
+    }
+    Ok(bytes)
+}
+
+/// Convert a GUID string into an escaped filter literal, suitable for
+/// `(objectGUID=...)`.
+pub fn guid_filter_bytes(guid: &str) -> Result<String, AdError> {
+    guid_to_bytes(guid).map(|bytes| escape_bytes(&bytes))
+}
+
+/// Convert a binary SID into the string form, e.g. `S-1-5-32-544`.
+///
+/// The identifier authority is printed in decimal if it fits into 32 bits, and in
+/// hexadecimal otherwise, following the Windows convention.
+pub fn parse_sid(bytes: &[u8]) -> Result<String, AdError> {
+    if bytes.len() < 8 {
+        return Err(AdError::SidLength(bytes.len()));
+    }
+    if bytes[0] != 1 {
+        return Err(AdError::SidRevision(bytes[0]));
+    }
+    let count = bytes[1] as usize;
+    if count > MAX_SUB_AUTHORITIES || bytes.len() != 8 + 4 * count {
+        return Err(AdError::SidLength(bytes.len()));
+    }
+    let authority = bytes[2..8]
+        .iter()
+        .fold(0u64, |acc, &b| (acc << 8) | b as u64);
+    let mut sid = if authority >> 32 == 0 {
+        format!("S-1-{}", authority)
+    } else {
+        format!("S-1-0x{:012X}", authority)
+    };
+    for sub in bytes[8..].chunks(4) {
+        write!(
+            sid,
+            "-{}",
+            u32::from_le_bytes([sub[0], sub[1], sub[2], sub[3]])
+        )
+        .expect("formatted");
+    }
+    Ok(sid)
+}
+
+/// Convert a SID string into its binary form. The leading `S` can be in either case.
+pub fn sid_to_bytes(sid: &str) -> Result<Vec<u8>, AdError> {
+    let malformed = || AdError::MalformedSid(sid.to_owned());
+    let mut parts = sid.split('-');
+    if !parts.next().is_some_and(|s| s.eq_ignore_ascii_case("S")) {
+        return Err(malformed());
+    }
+    if parts.next() != Some("1") {
+        return Err(malformed());
+    }
+    let authority = match parts.next() {
+        Some(a) if a.starts_with("0x") || a.starts_with("0X") => u64::from_str_radix(&a[2..], 16),
+        Some(a) => a.parse::<u64>(),
+        None => return Err(malformed()),
+    }
+    .map_err(|_| malformed())?;
+    if authority >> 48 != 0 {
+        return Err(malformed());
+    }
+    let subs = parts
+        .map(|s| s.parse::<u32>().map_err(|_| malformed()))
+        .collect::<Result<Vec<_>, _>>()?;
+    if subs.len() > MAX_SUB_AUTHORITIES {
+        return Err(malformed());
+    }
+    let mut bytes = Vec::with_capacity(8 + 4 * subs.len());
+    bytes.push(1);
+    bytes.push(subs.len() as u8);
+    bytes.extend(&authority.to_be_bytes()[2..]);
+    for sub in subs {
+        bytes.extend(&sub.to_le_bytes());
+    }
+    Ok(bytes)
+}
+
+/// Convert a SID string into an escaped filter literal, suitable for
+/// `(objectSid=...)`.
+pub fn sid_filter_bytes(sid: &str) -> Result<String, AdError> {
+    sid_to_bytes(sid).map(|bytes| escape_bytes(&bytes))
+}
+
+/// Encode a password as a `unicodePwd` value.
+///
+/// AD expects the new password to be enclosed in double quotes and encoded in UTF-16LE.
+/// The value can only be written over an encrypted connection.
+pub fn unicode_pwd(password: &str) -> Vec<u8> {
+    format!("\"{}\"", password)
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect()
+}
+
+/// Convert a FILETIME value into `SystemTime`.
+///
+/// Returns `None` for the values which AD uses to mean "never" or "not set", 0 and
+/// 0x7FFFFFFFFFFFFFFF, and for negative values.
+pub fn filetime_to_unix(filetime: i64) -> Option<SystemTime> {
+    if filetime <= 0 || filetime == FILETIME_NEVER {
+        return None;
+    }
+    let filetime = filetime as u64;
+    let since_1601 = Duration::new(filetime / 10_000_000, (filetime % 10_000_000) as u32 * 100);
+    let offset = Duration::from_secs(FILETIME_EPOCH_OFFSET);
+    if since_1601 >= offset {
+        UNIX_EPOCH.checked_add(since_1601 - offset)
+    } else {
+        UNIX_EPOCH.checked_sub(offset - since_1601)
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut s, b| {
+        write!(s, "{:02x}", b).expect("formatted");
+        s
+    })
+}
+
+fn unhex(s: &str) -> Option<Vec<u8>> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn escape_bytes(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut s, b| {
+        write!(s, "\\{:02x}", b).expect("formatted");
+        s
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Example GUID from [MS-DTYP] and its packet representation.
+    const GUID: &str = "6b29fc40-ca47-1067-b31d-00dd010662da";
+    const GUID_BYTES: &[u8] = b"\x40\xfc\x29\x6b\x47\xca\x67\x10\xb3\x1d\x00\xdd\x01\x06\x62\xda";
+
+    #[test]
+    fn guid_round_trip() {
+        assert_eq!(parse_guid(GUID_BYTES).unwrap(), GUID);
+        assert_eq!(guid_to_bytes(GUID).unwrap(), GUID_BYTES);
+        assert_eq!(
+            guid_to_bytes("{6B29FC40-CA47-1067-B31D-00DD010662DA}").unwrap(),
+            GUID_BYTES
+        );
+        assert_eq!(
+            guid_filter_bytes(GUID).unwrap(),
+            "\\40\\fc\\29\\6b\\47\\ca\\67\\10\\b3\\1d\\00\\dd\\01\\06\\62\\da"
+        );
+    }
+
+    #[test]
+    fn guid_errors() {
+        assert_eq!(parse_guid(&GUID_BYTES[1..]), Err(AdError::GuidLength(15)));
+        assert!(matches!(
+            guid_to_bytes("6b29fc40-ca47-1067-b31d00dd010662da"),
+            Err(AdError::MalformedGuid(_))
+        ));
+        assert!(matches!(
+            guid_to_bytes("6b29fc40-ca47-1067-b31d-00dd010662dg"),
+            Err(AdError::MalformedGuid(_))
+        ));
+    }
+
+    #[test]
+    fn sid_round_trip() {
+        // Well-known SID of BUILTIN\Administrators.
+        let admins = b"\x01\x02\x00\x00\x00\x00\x00\x05\x20\x00\x00\x00\x20\x02\x00\x00";
+        assert_eq!(parse_sid(admins).unwrap(), "S-1-5-32-544");
+        assert_eq!(sid_to_bytes("S-1-5-32-544").unwrap(), admins);
+        // Domain Admins SID from the Microsoft security identifier documentation.
+        let domain = "S-1-5-21-1004336348-1177238915-682003330-512";
+        let bytes = sid_to_bytes(domain).unwrap();
+        assert_eq!(
+            hex(&bytes),
+            "010500000000000515000000dcf4dc3b833d2b46828ba62800020000"
+        );
+        assert_eq!(parse_sid(&bytes).unwrap(), domain);
+        // Everyone, with no sub-authority beyond the well-known one.
+        assert_eq!(
+            sid_filter_bytes("S-1-1-0").unwrap(),
+            "\\01\\01\\00\\00\\00\\00\\00\\01\\00\\00\\00\\00"
+        );
+        let large = "S-1-0x123456789ABC-1";
+        assert_eq!(parse_sid(&sid_to_bytes(large).unwrap()).unwrap(), large);
+    }
+
+    #[test]
+    fn sid_errors() {
+        assert_eq!(parse_sid(b"\x01\x02\x00"), Err(AdError::SidLength(3)));
+        assert_eq!(
+            parse_sid(b"\x02\x00\x00\x00\x00\x00\x00\x05"),
+            Err(AdError::SidRevision(2))
+        );
+        assert_eq!(
+            parse_sid(b"\x01\x02\x00\x00\x00\x00\x00\x05\x20\x00\x00\x00"),
+            Err(AdError::SidLength(12))
+        );
+        for bad in ["S-2-5-32", "S-1-5-x", "X-1-5", "S-1-281474976710656-1"] {
+            assert!(matches!(sid_to_bytes(bad), Err(AdError::MalformedSid(_))));
+        }
+    }
+
+    #[test]
+    fn password() {
+        assert_eq!(unicode_pwd("ab"), b"\"\0a\0b\0\"\0");
+        assert_eq!(unicode_pwd("é€"), b"\"\0\xe9\0\xac\x20\"\0");
+        assert_eq!(unicode_pwd("𝄞").len(), 8);
+    }
+
+    #[test]
+    fn filetime() {
+        assert_eq!(filetime_to_unix(0), None);
+        assert_eq!(filetime_to_unix(0x7FFF_FFFF_FFFF_FFFF), None);
+        assert_eq!(filetime_to_unix(-1), None);
+        assert_eq!(filetime_to_unix(116_444_736_000_000_000), Some(UNIX_EPOCH));
+        assert_eq!(
+            filetime_to_unix(132_000_000_000_000_001),
+            Some(UNIX_EPOCH + Duration::new(1_555_526_400, 100))
+        );
+    }
+}
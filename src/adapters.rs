@@ -18,14 +18,19 @@
 //! Adapters must be written with async calls, but work equally well for both async and sync versions of the API
 //! because the sync API is just a blocking façade for the async one.
 
-use std::fmt::Debug;
+use std::collections::{HashSet, VecDeque};
+use std::fmt::{self, Debug};
 use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use crate::controls::{self, Control, ControlType};
+use crate::attr_desc;
+use crate::controls::{self, Control, ControlType, MakeCritical};
+use crate::controls_impl::DIRSYNC_OID;
 use crate::ldap::Ldap;
 use crate::result::{LdapError, LdapResult, Result};
 use crate::search::parse_refs;
-use crate::search::{ResultEntry, Scope, SearchStream};
+use crate::search::{ResultEntry, Scope, SearchEntry, SearchStream};
 
 use async_trait::async_trait;
 
@@ -44,6 +49,10 @@ use async_trait::async_trait;
 /// To use a bare instance of a struct implementing this trait in the call to `streaming_search_with()`, the struct
 /// must also implement [`SoloMarker`](trait.SoloMarker.html).
 ///
+/// Since adapters are only required to live as long as `'a`, they can hold references to data owned by
+/// the caller, such as a counter or a cancellation flag, without wrapping it in an `Arc`, as long as
+/// the data outlives the stream.
+///
 /// There are three points where an adapter can hook into a Search:
 ///
 /// 1. Initialization, which can be used to capture the parameters of the operation
@@ -61,6 +70,14 @@ use async_trait::async_trait;
 /// `async_trait` proc macro from the `async-trait` crate. To make chaining work, all trait methods must call
 /// the corresponding method on the passed stream handle.
 ///
+/// An adapter isn't limited to the entries it receives from the rest of the chain. It can keep returning
+/// entries of its own after the upcall has returned `Ok(None)`, as [`Concat`](struct.Concat.html) does; if
+/// it can hold such entries while an adapter before it in the chain returns `Ok(None)`, it should report
+/// them through [`has_pending()`](#method.has_pending), so that the stream doesn't end prematurely. An adapter
+/// can also decide that the Search has returned enough, and stop the operation on the server by calling
+/// [`abandon_inner()`](../struct.SearchStream.html#method.abandon_inner) on the stream handle, as
+/// [`Limit`](struct.Limit.html) does.
+///
 /// Additional details of the calling structure are provided in the documentation of the
 /// [`StreamState`](../enum.StreamState.html) enum.
 ///
@@ -159,6 +176,14 @@ pub trait Adapter<'a, S, A>: AdapterClone<'a, S, A> + Debug + Send + Sync + 'a {
 
     /// Return the result from the stream.
     async fn finish(&mut self, stream: &mut SearchStream<'a, S, A>) -> LdapResult;
+
+    /// Check whether the adapter has entries to return which don't depend on the rest of
+    /// the chain. If any adapter in the chain returns `true`, the stream isn't marked as
+    /// `Done` when the outermost `next()` returns `Ok(None)`. The default implementation
+    /// returns `false`.
+    fn has_pending(&self) -> bool {
+        false
+    }
 }
 
 /// Helper trait to enforce `Clone` on `Adapter` implementors.
@@ -285,6 +310,34 @@ where
     }
 }
 
+/// Information about a page of a Search with the [`PagedResults`](struct.PagedResults.html) adapter.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct PageInfo {
+    /// Zero-based index of the page.
+    pub index: usize,
+    /// Number of directory entries in the page.
+    pub entries: usize,
+    /// Server's estimate of the result set size from the response control of the page,
+    /// if it was sent and non-zero.
+    pub size_estimate: Option<i32>,
+    /// Time from sending the request for the page to receiving its result.
+    pub elapsed: Duration,
+}
+
+/// Decision about continuing a Search with the [`PagedResults`](struct.PagedResults.html) adapter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PageDecision {
+    /// Fetch the next page.
+    Continue,
+    /// Fetch the next page, changing the page size for it and all subsequent pages.
+    ContinueWith { page_size: i32 },
+    /// Stop the Search after the current page.
+    Stop,
+}
+
+type PageCallback = dyn Fn(PageInfo) -> PageDecision + Send + Sync;
+
 /// Adapter which fetches Search results with a Paged Results control.
 ///
 /// The adapter adds a Paged Results control with the user-supplied page size to
@@ -292,17 +345,77 @@ where
 /// control; if it does, an error is reported. If the complete result set is not
 /// retrieved in the first protocol operation, the adapter will automatically issue
 /// further Searches until the whole search is done.
-#[derive(Clone, Debug)]
+///
+/// The number of pages can be limited with [`max_pages()`](#method.max_pages), and
+/// a closure set with [`on_page()`](#method.on_page) can examine each completed page and
+/// decide how to proceed. When the Search is stopped before the last page, the adapter
+/// asks the server to release the result set by sending the cookie with a page size
+/// of zero, and the overall result is that of the last retrieved page. The server's
+/// estimate of the result set size can be obtained after the Search with
+/// [`size_estimate()`](#method.size_estimate) on any clone of the adapter instance.
+///
+/// ```rust,no_run
+/// # use ldap3::adapters::{Adapter, EntriesOnly, PageDecision, PagedResults};
+/// # use ldap3::{LdapConn, Scope};
+/// # use std::time::Duration;
+/// # let mut ldap = LdapConn::new("ldapi://ldapi").unwrap();
+/// let paged = PagedResults::new(500).max_pages(20).on_page(|info| {
+///     if info.elapsed > Duration::from_secs(5) {
+///         PageDecision::ContinueWith { page_size: 100 }
+///     } else {
+///         PageDecision::Continue
+///     }
+/// });
+/// let adapters: Vec<Box<dyn Adapter<_, _>>> =
+///     vec![Box::new(EntriesOnly::new()), Box::new(paged.clone())];
+/// let mut stream = ldap.streaming_search_with(
+///     adapters,
+///     "dc=example,dc=org",
+///     Scope::Subtree,
+///     "(objectClass=person)",
+///     vec!["cn"],
+/// ).unwrap();
+/// while let Some(_entry) = stream.next().unwrap() {}
+/// stream.finish().success().unwrap();
+/// let estimate = paged.size_estimate();
+/// # let _ = estimate;
+/// ```
+#[derive(Clone)]
 pub struct PagedResults<S: AsRef<str>, A> {
     page_size: i32,
+    max_pages: Option<usize>,
+    on_page: Option<Arc<PageCallback>>,
+    estimate: Arc<Mutex<Option<i32>>>,
+    size: i32,
+    page: usize,
+    page_entries: usize,
+    page_start: Instant,
     ldap: Option<Ldap>,
     base: String,
     scope: Scope,
     filter: String,
     attrs: Option<A>,
+    keep_response: bool,
     _s: PhantomData<S>,
 }
 
+impl<S: AsRef<str>, A: Debug> Debug for PagedResults<S, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PagedResults")
+            .field("page_size", &self.page_size)
+            .field("max_pages", &self.max_pages)
+            .field("size", &self.size)
+            .field("page", &self.page)
+            .field("ldap", &self.ldap)
+            .field("base", &self.base)
+            .field("scope", &self.scope)
+            .field("filter", &self.filter)
+            .field("attrs", &self.attrs)
+            .field("keep_response", &self.keep_response)
+            .finish_non_exhaustive()
+    }
+}
+
 impl<S, A> SoloMarker for PagedResults<S, A>
 where
     S: AsRef<str> + Send + Sync,
@@ -319,14 +432,94 @@ where
     pub fn new(page_size: i32) -> Self {
         Self {
             page_size,
+            max_pages: None,
+            on_page: None,
+            estimate: Arc::new(Mutex::new(None)),
+            size: page_size,
+            page: 0,
+            page_entries: 0,
+            page_start: Instant::now(),
             ldap: None,
             base: String::from(""),
             scope: Scope::Base,
             filter: String::from(""),
             attrs: None,
+            keep_response: false,
             _s: PhantomData,
         }
     }
+
+    /// Stop the Search after retrieving at most `max_pages` pages.
+    pub fn max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = Some(max_pages);
+        self
+    }
+
+    /// Call the closure after each retrieved page, including the last one, with the
+    /// information about the page. The returned value decides whether the Search
+    /// continues, and with which page size; it's ignored after the last page.
+    /// The page limit set with [`max_pages()`](#method.max_pages) takes precedence
+    /// over the decision.
+    pub fn on_page<F>(mut self, f: F) -> Self
+    where
+        F: Fn(PageInfo) -> PageDecision + Send + Sync + 'static,
+    {
+        self.on_page = Some(Arc::new(f));
+        self
+    }
+
+    /// Return the server's estimate of the result set size, which is the last non-zero
+    /// size received in a Paged Results response control of the Search.
+    pub fn size_estimate(&self) -> Option<i32> {
+        *self.estimate.lock().expect("paged estimate")
+    }
+
+    /// Leave the Paged Results response control of the last page in the
+    /// overall result of the Search, instead of removing it.
+    pub(crate) fn keep_response(mut self) -> Self {
+        self.keep_response = true;
+        self
+    }
+
+    fn page_ldap(&self, size: i32, cookie: Vec<u8>) -> Ldap {
+        let ldap_ref = self.ldap.as_ref().expect("ldap_ref");
+        let mut ldap = ldap_ref.clone();
+        ldap.timeout = ldap_ref.timeout;
+        ldap.deadline = ldap_ref.deadline;
+        ldap.cancel = ldap_ref.cancel.clone();
+        ldap.search_opts = ldap_ref.search_opts.clone();
+        let mut controls = ldap_ref.controls.clone().expect("saved ctrls");
+        controls.push(controls::PagedResults { size, cookie }.into());
+        ldap.controls = Some(controls);
+        ldap
+    }
+
+    // Decide whether to fetch the page after the one just completed.
+    fn decide(&mut self, size_estimate: Option<i32>) -> bool {
+        let info = PageInfo {
+            index: self.page,
+            entries: self.page_entries,
+            size_estimate,
+            elapsed: self.page_start.elapsed(),
+        };
+        self.page += 1;
+        self.page_entries = 0;
+        let decision = match self.on_page {
+            Some(ref on_page) => on_page(info),
+            None => PageDecision::Continue,
+        };
+        if self.max_pages.is_some_and(|max| self.page >= max) {
+            return false;
+        }
+        match decision {
+            PageDecision::Continue => true,
+            PageDecision::ContinueWith { page_size } => {
+                self.size = page_size;
+                true
+            }
+            PageDecision::Stop => false,
+        }
+    }
 }
 
 #[async_trait]
@@ -346,6 +539,8 @@ where
         let stream_ldap = stream.ldap_handle();
         let mut ldap = stream_ldap.clone();
         ldap.timeout = stream_ldap.timeout;
+        ldap.deadline = stream_ldap.deadline;
+        ldap.cancel = stream_ldap.cancel.clone();
         ldap.search_opts = stream_ldap.search_opts.clone();
         let empty_ctrls = vec![];
         let mut found_pr = false;
@@ -384,62 +579,264 @@ where
         self.scope = scope;
         self.filter = String::from(filter);
         self.attrs = Some(attrs.clone());
+        self.size = self.page_size;
+        self.page = 0;
+        self.page_entries = 0;
+        self.page_start = Instant::now();
+        *self.estimate.lock().expect("paged estimate") = None;
+        stream.start(base, scope, filter, attrs).await
+    }
+
+    async fn next(&mut self, stream: &mut SearchStream<'a, S, A>) -> Result<Option<ResultEntry>> {
+        loop {
+            let res = match stream.next().await? {
+                Some(re) => {
+                    if !re.is_ref() && !re.is_intermediate() {
+                        self.page_entries += 1;
+                    }
+                    return Ok(Some(re));
+                }
+                None => match stream.res.as_mut() {
+                    Some(res) => res,
+                    None => return Ok(None),
+                },
+            };
+            let pr_index = res
+                .ctrls
+                .iter()
+                .position(|c| matches!(c, Control(Some(ControlType::PagedResults), _)));
+            let Some(pr_index) = pr_index else {
+                return Ok(None);
+            };
+            let pr: controls::PagedResults = res.ctrls[pr_index].1.try_parse()?;
+            let size_estimate = Some(pr.size).filter(|&size| size != 0);
+            if size_estimate.is_some() {
+                *self.estimate.lock().expect("paged estimate") = size_estimate;
+            }
+            let more = self.decide(size_estimate);
+            if pr.cookie.is_empty() || !more {
+                if !self.keep_response {
+                    res.ctrls.remove(pr_index);
+                }
+                if !pr.cookie.is_empty() {
+                    // Let the server release the rest of the result set.
+                    let mut ldap = self.page_ldap(0, pr.cookie);
+                    let released = async {
+                        let mut release = ldap
+                            .streaming_search(
+                                &self.base,
+                                self.scope,
+                                &self.filter,
+                                self.attrs.as_ref().unwrap(),
+                            )
+                            .await?;
+                        while release.next().await?.is_some() {}
+                        release.finish().await.success().map(|_| ())
+                    };
+                    if let Err(e) = released.await {
+                        warn!("error releasing the paged result set: {}", e);
+                    }
+                }
+                return Ok(None);
+            }
+            let mut ldap = self.page_ldap(self.size, pr.cookie);
+            self.page_start = Instant::now();
+            let new_stream = ldap
+                .streaming_search(
+                    &self.base,
+                    self.scope,
+                    &self.filter,
+                    self.attrs.as_ref().unwrap(),
+                )
+                .await?;
+            // Again, we're replacing the innards of the original stream with
+            // the contents of the new one.
+            stream.ldap = new_stream.ldap;
+            stream.rx = new_stream.rx;
+        }
+    }
+
+    async fn finish(&mut self, stream: &mut SearchStream<'a, S, A>) -> LdapResult {
+        stream.finish().await
+    }
+}
+
+/// Adapter which synchronizes with Active Directory using the DirSync control.
+///
+/// The adapter adds a critical [`DirSync`](../controls/struct.DirSync.html) control with the
+/// given flags and cookie to a Search operation, which must not already contain one. As long
+/// as the server indicates that there is more data, the adapter issues further Searches
+/// with the returned cookie. The response control of the last Search is left in the
+/// overall result.
+///
+/// The cookie of the last response can be obtained with [`cookie()`](#method.cookie) on
+/// any clone of the adapter instance, including one made before the instance was passed
+/// to the Search. Storing it and passing it to the constructor the next time retrieves
+/// just the changes made in the meantime.
+///
+/// ```rust,no_run
+/// # use ldap3::adapters::{Adapter, DirSync, EntriesOnly};
+/// # use ldap3::controls::LDAP_DIRSYNC_OBJECT_SECURITY;
+/// # use ldap3::{LdapConn, Scope};
+/// # let mut ldap = LdapConn::new("ldap://dc.example.org").unwrap();
+/// # let saved_cookie = vec![];
+/// let dirsync = DirSync::new(LDAP_DIRSYNC_OBJECT_SECURITY, saved_cookie);
+/// let adapters: Vec<Box<dyn Adapter<_, _>>> =
+///     vec![Box::new(EntriesOnly::new()), Box::new(dirsync.clone())];
+/// let mut stream = ldap.streaming_search_with(
+///     adapters,
+///     "dc=example,dc=org",
+///     Scope::Subtree,
+///     "(objectClass=user)",
+///     vec!["cn", "memberOf"],
+/// ).unwrap();
+/// while let Some(_entry) = stream.next().unwrap() {}
+/// stream.finish().success().unwrap();
+/// let saved_cookie = dirsync.cookie();
+/// # let _ = saved_cookie;
+/// ```
+#[derive(Clone, Debug)]
+pub struct DirSync<S: AsRef<str>, A> {
+    flags: u32,
+    max_bytes: i32,
+    cookie: Arc<Mutex<Vec<u8>>>,
+    ldap: Option<Ldap>,
+    base: String,
+    scope: Scope,
+    filter: String,
+    attrs: Option<A>,
+    _s: PhantomData<S>,
+}
+
+impl<S, A> SoloMarker for DirSync<S, A>
+where
+    S: AsRef<str> + Send + Sync,
+    A: AsRef<[S]> + Send + Sync,
+{
+}
+
+impl<S, A> DirSync<S, A>
+where
+    S: AsRef<str> + Send + Sync,
+    A: AsRef<[S]> + Send + Sync,
+{
+    /// Construct a new adapter instance with the given flags and the cookie from the
+    /// previous synchronization, which should be empty the first time.
+    pub fn new(flags: u32, cookie: Vec<u8>) -> Self {
+        Self {
+            flags,
+            max_bytes: 0,
+            cookie: Arc::new(Mutex::new(cookie)),
+            ldap: None,
+            base: String::from(""),
+            scope: Scope::Base,
+            filter: String::from(""),
+            attrs: None,
+            _s: PhantomData,
+        }
+    }
+
+    /// Set the maximum size of the data returned by each Search. The default of zero
+    /// leaves the limit to the server.
+    pub fn max_bytes(mut self, max_bytes: i32) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Return the current cookie, which is the one from the last response once the
+    /// Search is done.
+    pub fn cookie(&self) -> Vec<u8> {
+        self.cookie.lock().expect("dirsync cookie").clone()
+    }
+
+    fn control(&self) -> controls::RawControl {
+        controls::DirSync {
+            flags: self.flags,
+            max_bytes: self.max_bytes,
+            cookie: self.cookie(),
+        }
+        .critical()
+        .into()
+    }
+}
+
+#[async_trait]
+impl<'a, S, A> Adapter<'a, S, A> for DirSync<S, A>
+where
+    S: AsRef<str> + Clone + Debug + Send + Sync + 'a,
+    A: AsRef<[S]> + Clone + Debug + Send + Sync + 'a,
+{
+    async fn start(
+        &mut self,
+        stream: &mut SearchStream<'a, S, A>,
+        base: &str,
+        scope: Scope,
+        filter: &str,
+        attrs: A,
+    ) -> Result<()> {
+        let stream_ldap = stream.ldap_handle();
+        let mut ldap = stream_ldap.clone();
+        ldap.timeout = stream_ldap.timeout;
+        ldap.deadline = stream_ldap.deadline;
+        ldap.cancel = stream_ldap.cancel.clone();
+        ldap.search_opts = stream_ldap.search_opts.clone();
+        let mut controls = stream_ldap.controls.clone().unwrap_or_default();
+        if controls.iter().any(|c| c.ctype == DIRSYNC_OID) {
+            return Err(LdapError::AdapterInit(String::from(
+                "found DirSync control in op set",
+            )));
+        }
+        ldap.controls = Some(controls.clone());
+        controls.push(self.control());
+        stream.ldap.controls = Some(controls);
+        self.ldap = Some(ldap);
+        self.base = String::from(base);
+        self.scope = scope;
+        self.filter = String::from(filter);
+        self.attrs = Some(attrs.clone());
         stream.start(base, scope, filter, attrs).await
     }
 
     async fn next(&mut self, stream: &mut SearchStream<'a, S, A>) -> Result<Option<ResultEntry>> {
-        'ent: loop {
+        loop {
             match stream.next().await {
                 Ok(None) => {
-                    let mut pr_index = None;
-                    let ctrls = if let Some(res_ref) = stream.res.as_mut() {
-                        &mut res_ref.ctrls
-                    } else {
-                        return Ok(None);
-                    };
-                    for (cno, ctrl) in ctrls.iter().enumerate() {
-                        if let Control(Some(ControlType::PagedResults), ref raw) = *ctrl {
-                            pr_index = Some(cno);
-                            let pr: controls::PagedResults = raw.parse();
-                            if pr.cookie.is_empty() {
-                                break;
+                    let resp = stream.res.as_ref().and_then(|res| {
+                        res.ctrls.iter().find_map(|ctrl| match *ctrl {
+                            Control(Some(ControlType::DirSync), ref raw) => {
+                                Some(raw.try_parse::<controls::DirSyncResp>())
                             }
-                            let ldap_ref = self.ldap.as_ref().expect("ldap_ref");
-                            let mut ldap = ldap_ref.clone();
-                            ldap.timeout = ldap_ref.timeout;
-                            ldap.search_opts = ldap_ref.search_opts.clone();
-                            let mut controls = ldap_ref.controls.clone().expect("saved ctrls");
-                            controls.push(
-                                controls::PagedResults {
-                                    size: self.page_size,
-                                    cookie: pr.cookie.clone(),
-                                }
-                                .into(),
-                            );
-                            ldap.controls = Some(controls);
-                            let new_stream = match ldap
-                                .streaming_search(
-                                    &self.base,
-                                    self.scope,
-                                    &self.filter,
-                                    self.attrs.as_ref().unwrap(),
-                                )
-                                .await
-                            {
-                                Ok(strm) => strm,
-                                Err(e) => return Err(e),
-                            };
-                            // Again, we're replacing the innards of the original stream with
-                            // the contents of the new one.
-                            stream.ldap = new_stream.ldap;
-                            stream.rx = new_stream.rx;
-                            continue 'ent;
-                        }
-                    }
-                    if let Some(pr_index) = pr_index {
-                        ctrls.remove(pr_index);
+                            _ => None,
+                        })
+                    });
+                    let resp = match resp {
+                        Some(resp) => resp?,
+                        None => return Ok(None),
+                    };
+                    *self.cookie.lock().expect("dirsync cookie") = resp.cookie;
+                    if !resp.more_data || stream.res.as_ref().is_some_and(|res| res.rc != 0) {
+                        return Ok(None);
                     }
-                    return Ok(None);
+                    let ldap_ref = self.ldap.as_ref().expect("ldap_ref");
+                    let mut ldap = ldap_ref.clone();
+                    ldap.timeout = ldap_ref.timeout;
+                    ldap.deadline = ldap_ref.deadline;
+                    ldap.cancel = ldap_ref.cancel.clone();
+                    ldap.search_opts = ldap_ref.search_opts.clone();
+                    let mut controls = ldap_ref.controls.clone().expect("saved ctrls");
+                    controls.push(self.control());
+                    ldap.controls = Some(controls);
+                    let new_stream = ldap
+                        .streaming_search(
+                            &self.base,
+                            self.scope,
+                            &self.filter,
+                            self.attrs.as_ref().unwrap(),
+                        )
+                        .await?;
+                    // Replace the innards of the original stream with those of the new one.
+                    stream.ldap = new_stream.ldap;
+                    stream.rx = new_stream.rx;
                 }
                 any => return any,
             }
@@ -450,3 +847,340 @@ where
         stream.finish().await
     }
 }
+
+type EntryMapper<'f> = dyn Fn(ResultEntry) -> Option<ResultEntry> + Send + Sync + 'f;
+
+/// Adapter which transforms or discards the items of a Search with a user-supplied closure.
+///
+/// The closure is called with each item returned from the rest of the adapter chain,
+/// including referrals and intermediate messages, unless an adapter earlier in the chain
+/// has removed them. If the closure returns `None`, the item is dropped, and the adapter
+/// proceeds with the next one.
+///
+/// ```rust,no_run
+/// # use ldap3::adapters::{Adapter, EntriesOnly, MapEntries};
+/// # use ldap3::{LdapConn, Scope, SearchEntry};
+/// # let mut ldap = LdapConn::new("ldapi://ldapi").unwrap();
+/// let adapters: Vec<Box<dyn Adapter<_, _>>> = vec![
+///     Box::new(EntriesOnly::new()),
+///     Box::new(MapEntries::new(|re| {
+///         let entry = SearchEntry::construct(re.clone());
+///         if entry.attrs.contains_key("mail") {
+///             Some(re)
+///         } else {
+///             None
+///         }
+///     })),
+/// ];
+/// let mut stream = ldap.streaming_search_with(
+///     adapters,
+///     "dc=example,dc=org",
+///     Scope::Subtree,
+///     "(objectClass=person)",
+///     vec!["mail"]
+/// );
+/// # let _ = stream;
+/// ```
+#[derive(Clone)]
+pub struct MapEntries<'f> {
+    f: Arc<EntryMapper<'f>>,
+}
+
+impl<'f> MapEntries<'f> {
+    /// Construct a new adapter instance with the given closure. The closure may borrow
+    /// from its environment, as long as the borrow outlives the Search.
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(ResultEntry) -> Option<ResultEntry> + Send + Sync + 'f,
+    {
+        Self { f: Arc::new(f) }
+    }
+}
+
+impl Debug for MapEntries<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MapEntries").finish_non_exhaustive()
+    }
+}
+
+impl SoloMarker for MapEntries<'_> {}
+
+#[async_trait]
+impl<'a, 'f: 'a, S, A> Adapter<'a, S, A> for MapEntries<'f>
+where
+    S: AsRef<str> + Send + Sync + 'a,
+    A: AsRef<[S]> + Send + Sync + 'a,
+{
+    async fn start(
+        &mut self,
+        stream: &mut SearchStream<'a, S, A>,
+        base: &str,
+        scope: Scope,
+        filter: &str,
+        attrs: A,
+    ) -> Result<()> {
+        stream.start(base, scope, filter, attrs).await
+    }
+
+    async fn next(&mut self, stream: &mut SearchStream<'a, S, A>) -> Result<Option<ResultEntry>> {
+        while let Some(re) = stream.next().await? {
+            if let Some(re) = (self.f)(re) {
+                return Ok(Some(re));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn finish(&mut self, stream: &mut SearchStream<'a, S, A>) -> LdapResult {
+        stream.finish().await
+    }
+}
+
+/// Adapter which removes the attributes not on a list from Search entries.
+///
+/// Each directory entry is parsed, stripped of the attributes whose names, without
+/// options, don't match any name on the list, and re-encoded, so that the consumers
+/// of the stream, including [`SearchEntry::construct()`](../struct.SearchEntry.html#method.construct),
+/// see only the projection. Names are compared case-insensitively. Referrals and
+/// intermediate messages are passed through unchanged.
+///
+/// This is useful when the attribute list of a Search can't be controlled by the caller.
+/// When it can, requesting just the needed attributes from the server is preferable.
+#[derive(Clone, Debug)]
+pub struct ProjectAttrs {
+    inner: MapEntries<'static>,
+}
+
+impl ProjectAttrs {
+    /// Construct a new adapter instance which keeps the listed attributes.
+    pub fn new(keep: Vec<String>) -> Self {
+        let keep: HashSet<String> = keep.into_iter().map(|a| a.to_ascii_lowercase()).collect();
+        let inner = MapEntries::new(move |re| {
+            if re.is_ref() || re.is_intermediate() {
+                return Some(re);
+            }
+            let ctrls = re.1.clone();
+            let mut entry = SearchEntry::construct(re);
+            let kept =
+                |name: &String| keep.contains(&attr_desc::base_name(name).to_ascii_lowercase());
+            entry.attrs.retain(|name, _| kept(name));
+            entry.bin_attrs.retain(|name, _| kept(name));
+            Some(ResultEntry(entry.to_structure(), ctrls, None))
+        });
+        Self { inner }
+    }
+}
+
+impl SoloMarker for ProjectAttrs {}
+
+#[async_trait]
+impl<'a, S, A> Adapter<'a, S, A> for ProjectAttrs
+where
+    S: AsRef<str> + Send + Sync + 'a,
+    A: AsRef<[S]> + Send + Sync + 'a,
+{
+    async fn start(
+        &mut self,
+        stream: &mut SearchStream<'a, S, A>,
+        base: &str,
+        scope: Scope,
+        filter: &str,
+        attrs: A,
+    ) -> Result<()> {
+        Adapter::<S, A>::start(&mut self.inner, stream, base, scope, filter, attrs).await
+    }
+
+    async fn next(&mut self, stream: &mut SearchStream<'a, S, A>) -> Result<Option<ResultEntry>> {
+        Adapter::<S, A>::next(&mut self.inner, stream).await
+    }
+
+    async fn finish(&mut self, stream: &mut SearchStream<'a, S, A>) -> LdapResult {
+        Adapter::<S, A>::finish(&mut self.inner, stream).await
+    }
+}
+
+/// Adapter which stops a Search after a number of directory entries.
+///
+/// Referrals and intermediate messages are passed through, and don't count toward the
+/// limit. When the limit is reached, the operation is abandoned on the server with
+/// [`abandon_inner()`](../struct.SearchStream.html#method.abandon_inner), and the result of
+/// the Search will have the result code 0 (success), unless the server completes the
+/// operation before that. Unlike the size limit of the Search, the limit is enforced
+/// on the client, and applies across all operations of adapters later in the chain,
+/// such as [`PagedResults`](struct.PagedResults.html).
+///
+/// ```rust,no_run
+/// # use ldap3::adapters::{Adapter, EntriesOnly, Limit};
+/// # use ldap3::{LdapConn, Scope};
+/// # let mut ldap = LdapConn::new("ldapi://ldapi").unwrap();
+/// let adapters: Vec<Box<dyn Adapter<_, _>>> = vec![
+///     Box::new(Limit::new(10)),
+///     Box::new(EntriesOnly::new()),
+/// ];
+/// let mut stream = ldap.streaming_search_with(
+///     adapters,
+///     "dc=example,dc=org",
+///     Scope::Subtree,
+///     "(objectClass=person)",
+///     vec!["cn"]
+/// );
+/// # let _ = stream;
+/// ```
+#[derive(Clone, Debug)]
+pub struct Limit {
+    limit: usize,
+    count: usize,
+}
+
+impl Limit {
+    /// Construct a new adapter instance which returns at most `limit` entries.
+    pub fn new(limit: usize) -> Self {
+        Self { limit, count: 0 }
+    }
+}
+
+impl SoloMarker for Limit {}
+
+#[async_trait]
+impl<'a, S, A> Adapter<'a, S, A> for Limit
+where
+    S: AsRef<str> + Send + Sync + 'a,
+    A: AsRef<[S]> + Send + Sync + 'a,
+{
+    async fn start(
+        &mut self,
+        stream: &mut SearchStream<'a, S, A>,
+        base: &str,
+        scope: Scope,
+        filter: &str,
+        attrs: A,
+    ) -> Result<()> {
+        self.count = 0;
+        stream.start(base, scope, filter, attrs).await
+    }
+
+    async fn next(&mut self, stream: &mut SearchStream<'a, S, A>) -> Result<Option<ResultEntry>> {
+        if self.count >= self.limit {
+            stream.abandon_inner().await?;
+            return Ok(None);
+        }
+        let re = stream.next().await?;
+        if let Some(ref re) = re {
+            if !re.is_ref() && !re.is_intermediate() {
+                self.count += 1;
+                if self.count >= self.limit {
+                    stream.abandon_inner().await?;
+                }
+            }
+        }
+        Ok(re)
+    }
+
+    async fn finish(&mut self, stream: &mut SearchStream<'a, S, A>) -> LdapResult {
+        stream.finish().await
+    }
+}
+
+type EntrySource<'f> = dyn Fn() -> Vec<ResultEntry> + Send + Sync + 'f;
+
+/// Adapter which appends the entries produced by a user-supplied closure to a Search.
+///
+/// The closure is called once, when the rest of the adapter chain has returned all of
+/// its items, and the entries it produces are returned in order before the stream ends.
+/// It's not called if the Search fails. The entries can be constructed from
+/// [`SearchEntry`](../struct.SearchEntry.html) values with
+/// [`into_result_entry()`](../struct.SearchEntry.html#method.into_result_entry).
+///
+/// ```rust,no_run
+/// # use std::collections::HashMap;
+/// # use ldap3::adapters::{Adapter, Concat, EntriesOnly};
+/// # use ldap3::{LdapConn, Scope, SearchEntry};
+/// # let mut ldap = LdapConn::new("ldapi://ldapi").unwrap();
+/// let adapters: Vec<Box<dyn Adapter<_, _>>> = vec![
+///     Box::new(EntriesOnly::new()),
+///     Box::new(Concat::new(|| {
+///         let entry = SearchEntry {
+///             dn: "cn=summary".to_string(),
+///             attrs: HashMap::new(),
+///             bin_attrs: HashMap::new(),
+///             truncated: vec![],
+///         };
+///         vec![entry.into_result_entry()]
+///     })),
+/// ];
+/// let mut stream = ldap.streaming_search_with(
+///     adapters,
+///     "dc=example,dc=org",
+///     Scope::Subtree,
+///     "(objectClass=person)",
+///     vec!["cn"]
+/// );
+/// # let _ = stream;
+/// ```
+#[derive(Clone)]
+pub struct Concat<'f> {
+    f: Arc<EntrySource<'f>>,
+    appended: Option<VecDeque<ResultEntry>>,
+}
+
+impl<'f> Concat<'f> {
+    /// Construct a new adapter instance with the given closure. The closure may borrow
+    /// from its environment, as long as the borrow outlives the Search.
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn() -> Vec<ResultEntry> + Send + Sync + 'f,
+    {
+        Self {
+            f: Arc::new(f),
+            appended: None,
+        }
+    }
+}
+
+impl Debug for Concat<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Concat")
+            .field("appended", &self.appended.as_ref().map(VecDeque::len))
+            .finish_non_exhaustive()
+    }
+}
+
+impl SoloMarker for Concat<'_> {}
+
+#[async_trait]
+impl<'a, 'f: 'a, S, A> Adapter<'a, S, A> for Concat<'f>
+where
+    S: AsRef<str> + Send + Sync + 'a,
+    A: AsRef<[S]> + Send + Sync + 'a,
+{
+    async fn start(
+        &mut self,
+        stream: &mut SearchStream<'a, S, A>,
+        base: &str,
+        scope: Scope,
+        filter: &str,
+        attrs: A,
+    ) -> Result<()> {
+        self.appended = None;
+        stream.start(base, scope, filter, attrs).await
+    }
+
+    async fn next(&mut self, stream: &mut SearchStream<'a, S, A>) -> Result<Option<ResultEntry>> {
+        if self.appended.is_none() {
+            match stream.next().await? {
+                Some(re) => return Ok(Some(re)),
+                None => self.appended = Some((self.f)().into()),
+            }
+        }
+        Ok(self.appended.as_mut().and_then(VecDeque::pop_front))
+    }
+
+    async fn finish(&mut self, stream: &mut SearchStream<'a, S, A>) -> LdapResult {
+        self.appended = None;
+        stream.finish().await
+    }
+
+    fn has_pending(&self) -> bool {
+        self.appended.as_ref().is_some_and(|q| !q.is_empty())
+    }
+}
@@ -0,0 +1,84 @@
+//! Attribute descriptions.
+//!
+//! An attribute description, as used in search requests and returned in entries, consists
+//! of the attribute type name or OID, optionally followed by options separated by
+//! semicolons, e.g., `userCertificate;binary` or `cn;lang-en`. Both the name and the options
+//! are case-insensitive. The functions in this module split descriptions into their parts
+//! and compare them accordingly.
+
+/// Split an attribute description into the base name and the list of options.
+///
+/// ```rust
+/// use ldap3::attr_desc;
+///
+/// assert_eq!(attr_desc::parse("userCertificate;binary"), ("userCertificate", vec!["binary"]));
+/// assert_eq!(attr_desc::parse("cn"), ("cn", vec![]));
+/// ```
+pub fn parse(desc: &str) -> (&str, Vec<&str>) {
+    let mut parts = desc.split(';');
+    let name = parts.next().unwrap_or(desc);
+    (name, parts.collect())
+}
+
+/// Return the base name of an attribute description, without the options.
+pub fn base_name(desc: &str) -> &str {
+    desc.split(';').next().unwrap_or(desc)
+}
+
+/// Check whether an attribute description has the given option. The comparison is
+/// case-insensitive.
+pub fn has_option(desc: &str, option: &str) -> bool {
+    desc.split(';')
+        .skip(1)
+        .any(|opt| opt.eq_ignore_ascii_case(option))
+}
+
+/// Check whether an attribute description has the base name `name`, ignoring the options
+/// and the case of the name.
+pub fn matches_name(desc: &str, name: &str) -> bool {
+    base_name(desc).eq_ignore_ascii_case(base_name(name))
+}
+
+/// Append the `binary` option to each attribute name which doesn't already have it,
+/// producing a list of attributes for a Search request.
+///
+/// ```rust
+/// use ldap3::attr_desc::attrs_with_binary;
+///
+/// assert_eq!(
+///     attrs_with_binary(&["userCertificate", "cACertificate;binary"]),
+///     vec!["userCertificate;binary", "cACertificate;binary"]
+/// );
+/// ```
+pub fn attrs_with_binary<S: AsRef<str>>(names: &[S]) -> Vec<String> {
+    names
+        .iter()
+        .map(|name| {
+            let name = name.as_ref();
+            if has_option(name, "binary") {
+                String::from(name)
+            } else {
+                format!("{};binary", name)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{base_name, has_option, matches_name, parse};
+
+    #[test]
+    fn descriptions() {
+        assert_eq!(parse("cn;lang-en;x-foo"), ("cn", vec!["lang-en", "x-foo"]));
+        assert_eq!(parse("2.5.4.36;Binary"), ("2.5.4.36", vec!["Binary"]));
+        assert_eq!(base_name("cn;lang-en;x-foo"), "cn");
+        assert!(has_option("cn;lang-en;x-foo", "X-FOO"));
+        assert!(has_option("userCertificate;Binary", "binary"));
+        assert!(!has_option("binary", "binary"));
+        assert!(matches_name("CN;lang-en;x-foo", "cn"));
+        assert!(matches_name("cn", "Cn;lang-de"));
+        assert!(!matches_name("cn;lang-en", "sn"));
+        assert!(!matches_name("cnx", "cn"));
+    }
+}
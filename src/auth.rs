@@ -0,0 +1,207 @@
+use crate::conn::{LdapConnAsync, LdapConnSettings};
+use crate::ldap::Ldap;
+use crate::result::{LdapError, Result};
+use crate::search::{Scope, SearchEntry};
+use crate::util::ldap_escape;
+
+const INVALID_CREDENTIALS: u32 = 49;
+
+/// Outcome of verifying a user's credentials with an [`Authenticator`](struct.Authenticator.html).
+#[derive(Debug)]
+pub enum AuthOutcome {
+    /// The user was found, and the password is correct.
+    Success {
+        /// DN of the user entry.
+        dn: String,
+    },
+    /// No entry matches the user filter.
+    NoSuchUser,
+    /// More than one entry matches the user filter; the value is the number of matches.
+    /// The credentials are not checked, since the user is ambiguous.
+    MultipleMatches(u32),
+    /// The user was found, but the bind with the password failed with invalidCredentials (49).
+    BadCredentials,
+}
+
+impl AuthOutcome {
+    /// Return `true` if the credentials were verified.
+    pub fn is_success(&self) -> bool {
+        matches!(self, AuthOutcome::Success { .. })
+    }
+}
+
+/// Verifier of user names and passwords by binding to the directory.
+///
+/// Verification looks up the user's entry with a Search, using a filter instantiated
+/// from a template, then tries a simple Bind as that entry with the supplied password.
+/// The Search is done on a separate connection, bound with the service account if one
+/// is configured, and anonymously otherwise; the Bind uses another connection. Both
+/// connections are closed before returning the outcome.
+///
+/// ```rust,no_run
+/// # use ldap3::{Authenticator, AuthOutcome};
+/// # async fn f() -> ldap3::result::Result<()> {
+/// let auth = Authenticator::new("ldap://localhost:2389", "ou=People,dc=example,dc=org", "(uid={})")
+///     .with_service_bind("cn=reader,dc=example,dc=org", "secret");
+/// match auth.authenticate("jdoe", "password").await? {
+///     AuthOutcome::Success { dn } => println!("authenticated as {}", dn),
+///     outcome => println!("not authenticated: {:?}", outcome),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Authenticator {
+    url: String,
+    settings: LdapConnSettings,
+    service_bind: Option<(String, String)>,
+    search_base: String,
+    filter_template: String,
+}
+
+impl Authenticator {
+    /// Create an authenticator for the server at `url`, which searches for users under
+    /// `search_base` with a filter made by replacing every `{}` in `filter_template` with
+    /// the escaped user name, e.g., `(uid={})`. The search is anonymous by default.
+    pub fn new(url: &str, search_base: &str, filter_template: &str) -> Self {
+        Authenticator {
+            url: String::from(url),
+            settings: LdapConnSettings::new(),
+            service_bind: None,
+            search_base: String::from(search_base),
+            filter_template: String::from(filter_template),
+        }
+    }
+
+    /// Use `settings` for both connections.
+    pub fn with_settings(mut self, settings: LdapConnSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Bind the search connection with the given DN and password.
+    pub fn with_service_bind(mut self, bind_dn: &str, bind_pw: &str) -> Self {
+        self.service_bind = Some((String::from(bind_dn), String::from(bind_pw)));
+        self
+    }
+
+    /// Verify the user name and password.
+    ///
+    /// An empty password is rejected with `LdapError::InvalidRequest` without contacting
+    /// the server: a simple Bind with a DN and an empty password is an unauthenticated
+    /// Bind ([RFC 4513, section 5.1.2](https://tools.ietf.org/html/rfc4513#section-5.1.2)),
+    /// which many servers accept, and would be mistaken for a successful verification.
+    /// A filter template without `{}` is rejected in the same way. If connecting, binding
+    /// with the service account, searching, or binding as the user fails for a reason
+    /// other than invalid credentials, the error is returned, since the credentials
+    /// couldn't be verified.
+    pub async fn authenticate(&self, username: &str, password: &str) -> Result<AuthOutcome> {
+        if password.is_empty() {
+            return Err(LdapError::InvalidRequest {
+                field: "password",
+                reason: String::from("empty password would make an unauthenticated bind"),
+            });
+        }
+        let filter = user_filter(&self.filter_template, username)?;
+        let dn = match self.find_user(&filter).await? {
+            Ok(dn) => dn,
+            Err(outcome) => return Ok(outcome),
+        };
+        if self.bind_user(&dn, password).await? {
+            Ok(AuthOutcome::Success { dn })
+        } else {
+            Ok(AuthOutcome::BadCredentials)
+        }
+    }
+
+    #[cfg_attr(docsrs, doc(cfg(feature = "sync")))]
+    #[cfg(feature = "sync")]
+    /// Synchronous version of [`authenticate()`](#method.authenticate), which runs the
+    /// exchange on its own single-threaded runtime.
+    pub fn authenticate_sync(&self, username: &str, password: &str) -> Result<AuthOutcome> {
+        let rt = crate::rt::Runtime::new()?;
+        crate::sync::block_on(&rt, self.authenticate(username, password))
+    }
+
+    async fn connect(&self) -> Result<Ldap> {
+        let (conn, ldap) = LdapConnAsync::with_settings(self.settings.clone(), &self.url).await?;
+        crate::drive!(conn);
+        Ok(ldap)
+    }
+
+    // Find the DN of the single entry matching the filter. If there isn't exactly one,
+    // the inner error is the outcome of the verification.
+    async fn find_user(&self, filter: &str) -> Result<std::result::Result<String, AuthOutcome>> {
+        let mut ldap = self.connect().await?;
+        if let Some((ref bind_dn, ref bind_pw)) = self.service_bind {
+            ldap.simple_bind(bind_dn, bind_pw).await?.success()?;
+        }
+        let res = ldap
+            .search(&self.search_base, Scope::Subtree, filter, vec!["1.1"])
+            .await;
+        let _ = ldap.unbind().await;
+        let (mut entries, _res) = res?.success()?;
+        Ok(match entries.len() {
+            0 => Err(AuthOutcome::NoSuchUser),
+            1 => Ok(SearchEntry::construct(entries.remove(0)).dn),
+            n => Err(AuthOutcome::MultipleMatches(n as u32)),
+        })
+    }
+
+    // Bind as the user. Invalid credentials are not an error, but a false result.
+    async fn bind_user(&self, dn: &str, password: &str) -> Result<bool> {
+        let mut ldap = self.connect().await?;
+        let res = ldap.simple_bind(dn, password).await;
+        let _ = ldap.unbind().await;
+        let res = res?;
+        match res.rc {
+            0 => Ok(true),
+            INVALID_CREDENTIALS => Ok(false),
+            _ => Err(LdapError::from(res)),
+        }
+    }
+}
+
+fn user_filter(template: &str, username: &str) -> Result<String> {
+    if !template.contains("{}") {
+        return Err(LdapError::InvalidRequest {
+            field: "filter template",
+            reason: String::from("no {} placeholder for the user name"),
+        });
+    }
+    Ok(template.replace("{}", &ldap_escape(username)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn filter_escaping() {
+        assert_eq!(
+            user_filter("(&(objectClass=person)(uid={}))", "j*doe)(uid=*").unwrap(),
+            "(&(objectClass=person)(uid=j\\2adoe\\29\\28uid=\\2a))"
+        );
+        assert_eq!(
+            user_filter("(|(uid={})(mail={}))", "jdoe").unwrap(),
+            "(|(uid=jdoe)(mail=jdoe))"
+        );
+        assert!(matches!(
+            user_filter("(uid=jdoe)", "jdoe"),
+            Err(LdapError::InvalidRequest { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn empty_password_rejected() {
+        // Nothing listens on the URL; the request must be rejected before connecting.
+        let auth = Authenticator::new("ldap://127.0.0.1:1", "dc=example,dc=org", "(uid={})");
+        assert!(matches!(
+            auth.authenticate("jdoe", "").await,
+            Err(LdapError::InvalidRequest {
+                field: "password",
+                ..
+            })
+        ));
+    }
+}
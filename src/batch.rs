@@ -0,0 +1,418 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::time::Duration;
+
+use crate::controls_impl::IntoRawControlVec;
+use crate::exop::Exop;
+use crate::ldap::{Ldap, Mod, ValueBytes};
+use crate::protocol::MaybeControls;
+use crate::result::{CompareResult, ExopResult, LdapResult, Result, SearchResult};
+use crate::search::Scope;
+
+use bytes::Bytes;
+use futures_util::stream::{self, StreamExt};
+
+#[derive(Clone, Debug)]
+enum BatchOp {
+    Search {
+        base: String,
+        scope: Scope,
+        filter: String,
+        attrs: Vec<String>,
+    },
+    Compare {
+        dn: String,
+        attr: String,
+        val: Bytes,
+    },
+    Add {
+        dn: String,
+        attrs: Vec<(Bytes, HashSet<Bytes>)>,
+    },
+    Modify {
+        dn: String,
+        mods: Vec<Mod<Bytes>>,
+    },
+    Delete {
+        dn: String,
+    },
+    ModifyDN {
+        dn: String,
+        rdn: String,
+        delete_old: bool,
+        new_sup: Option<String>,
+    },
+    Extended(Exop),
+}
+
+/// Result of a single operation in a [`Batch`](struct.Batch.html).
+///
+/// The variant corresponds to the queued operation, and contains the value which
+/// the equivalent method of [`Ldap`](struct.Ldap.html) would have returned.
+#[derive(Clone, Debug)]
+pub enum BatchItemResult {
+    Search(SearchResult),
+    Compare(CompareResult),
+    Add(LdapResult),
+    Modify(LdapResult),
+    Delete(LdapResult),
+    ModifyDN(LdapResult),
+    Extended(ExopResult),
+}
+
+impl BatchItemResult {
+    /// Return the result of the operation.
+    pub fn result(&self) -> &LdapResult {
+        match self {
+            BatchItemResult::Search(SearchResult(_, res))
+            | BatchItemResult::Compare(CompareResult(res))
+            | BatchItemResult::Add(res)
+            | BatchItemResult::Modify(res)
+            | BatchItemResult::Delete(res)
+            | BatchItemResult::ModifyDN(res)
+            | BatchItemResult::Extended(ExopResult(_, res)) => res,
+        }
+    }
+}
+
+/// Operation queued in a [`Batch`](struct.Batch.html).
+///
+/// An item starts with the controls and the timeout which were set on the handle when
+/// the batch was created. They can be replaced for the item by calling the methods of
+/// this struct, which work like their namesakes on [`Ldap`](struct.Ldap.html).
+#[derive(Clone, Debug)]
+pub struct BatchItem {
+    op: BatchOp,
+    controls: MaybeControls,
+    timeout: Option<Duration>,
+}
+
+impl BatchItem {
+    /// Pass the provided request control(s) to this operation.
+    pub fn with_controls<V: IntoRawControlVec>(&mut self, ctrls: V) -> &mut Self {
+        self.controls = Some(ctrls.into());
+        self
+    }
+
+    /// Perform this operation with the timeout specified in `duration`.
+    pub fn with_timeout(&mut self, duration: Duration) -> &mut Self {
+        self.timeout = Some(duration);
+        self
+    }
+
+    async fn run(self, mut ldap: Ldap) -> Result<BatchItemResult> {
+        ldap.controls = self.controls;
+        ldap.timeout = self.timeout;
+        Ok(match self.op {
+            BatchOp::Search {
+                base,
+                scope,
+                filter,
+                attrs,
+            } => BatchItemResult::Search(ldap.search(&base, scope, &filter, attrs).await?),
+            BatchOp::Compare { dn, attr, val } => {
+                BatchItemResult::Compare(ldap.compare(&dn, &attr, val).await?)
+            }
+            BatchOp::Add { dn, attrs } => BatchItemResult::Add(ldap.add(&dn, attrs).await?),
+            BatchOp::Modify { dn, mods } => BatchItemResult::Modify(ldap.modify(&dn, mods).await?),
+            BatchOp::Delete { dn } => BatchItemResult::Delete(ldap.delete(&dn).await?),
+            BatchOp::ModifyDN {
+                dn,
+                rdn,
+                delete_old,
+                new_sup,
+            } => BatchItemResult::ModifyDN(
+                ldap.modifydn(&dn, &rdn, delete_old, new_sup.as_deref())
+                    .await?,
+            ),
+            BatchOp::Extended(exop) => BatchItemResult::Extended(ldap.extended(exop).await?),
+        })
+    }
+}
+
+fn to_bytes<S: ValueBytes>(val: S) -> Bytes {
+    val.into_value_bytes()
+}
+
+fn set_to_bytes<S: ValueBytes + Eq + Hash>(set: HashSet<S>) -> HashSet<Bytes> {
+    set.into_iter().map(to_bytes).collect()
+}
+
+/// Independent operations to be performed concurrently.
+///
+/// A batch is created by [`Ldap::batch()`](struct.Ldap.html#method.batch). Operations are
+/// queued by calling the methods named after the corresponding methods of `Ldap`, which
+/// take the same arguments, except that the attribute list of a Search must be a vector.
+/// Calling [`execute()`](#method.execute) performs all operations, each on
+/// its own clone of the handle, running at most the given number of them at the same time.
+///
+/// Since the operations are multiplexed over a single connection, there is no guarantee
+/// about the order in which the server will process them. Operations which depend on each
+/// other shouldn't be placed in the same batch.
+///
+/// ```rust,no_run
+/// # use ldap3::{LdapConnAsync, BatchItemResult};
+/// # use ldap3::result::Result;
+/// # use std::time::Duration;
+/// # #[tokio::main]
+/// # async fn main() -> Result<()> {
+/// # let (conn, mut ldap) = LdapConnAsync::new("ldap://localhost:2389").await?;
+/// # ldap3::drive!(conn);
+/// let mut batch = ldap.batch();
+/// for uid in ["alice", "bob", "carol"] {
+///     let dn = format!("uid={},ou=People,dc=example,dc=org", uid);
+///     batch
+///         .compare(&dn, "employeeType", "staff")
+///         .with_timeout(Duration::from_secs(5));
+/// }
+/// for res in batch.execute(10).await {
+///     if let BatchItemResult::Compare(cmp) = res? {
+///         println!("{}", cmp.equal()?);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Batch {
+    ldap: Ldap,
+    controls: MaybeControls,
+    timeout: Option<Duration>,
+    items: Vec<BatchItem>,
+}
+
+impl Batch {
+    pub(crate) fn new(ldap: Ldap, controls: MaybeControls, timeout: Option<Duration>) -> Self {
+        Batch {
+            ldap,
+            controls,
+            timeout,
+            items: vec![],
+        }
+    }
+
+    fn push(&mut self, op: BatchOp) -> &mut BatchItem {
+        self.items.push(BatchItem {
+            op,
+            controls: self.controls.clone(),
+            timeout: self.timeout,
+        });
+        self.items.last_mut().expect("item")
+    }
+
+    /// Number of queued operations.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Check whether no operations have been queued.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Queue a Search. See [`Ldap::search()`](struct.Ldap.html#method.search).
+    pub fn search<S: AsRef<str>>(
+        &mut self,
+        base: &str,
+        scope: Scope,
+        filter: &str,
+        attrs: Vec<S>,
+    ) -> &mut BatchItem {
+        self.push(BatchOp::Search {
+            base: base.to_owned(),
+            scope,
+            filter: filter.to_owned(),
+            attrs: attrs.iter().map(|attr| attr.as_ref().to_owned()).collect(),
+        })
+    }
+
+    /// Queue a Compare. See [`Ldap::compare()`](struct.Ldap.html#method.compare).
+    pub fn compare<B: ValueBytes>(&mut self, dn: &str, attr: &str, val: B) -> &mut BatchItem {
+        self.push(BatchOp::Compare {
+            dn: dn.to_owned(),
+            attr: attr.to_owned(),
+            val: to_bytes(val),
+        })
+    }
+
+    /// Queue an Add. See [`Ldap::add()`](struct.Ldap.html#method.add).
+    pub fn add<S: ValueBytes + Eq + Hash>(
+        &mut self,
+        dn: &str,
+        attrs: Vec<(S, HashSet<S>)>,
+    ) -> &mut BatchItem {
+        self.push(BatchOp::Add {
+            dn: dn.to_owned(),
+            attrs: attrs
+                .into_iter()
+                .map(|(attr, vals)| (to_bytes(attr), set_to_bytes(vals)))
+                .collect(),
+        })
+    }
+
+    /// Queue a Modify. See [`Ldap::modify()`](struct.Ldap.html#method.modify).
+    pub fn modify<S: ValueBytes + Eq + Hash>(
+        &mut self,
+        dn: &str,
+        mods: Vec<Mod<S>>,
+    ) -> &mut BatchItem {
+        self.push(BatchOp::Modify {
+            dn: dn.to_owned(),
+            mods: mods
+                .into_iter()
+                .map(|m| match m {
+                    Mod::Add(attr, set) => Mod::Add(to_bytes(attr), set_to_bytes(set)),
+                    Mod::Delete(attr, set) => Mod::Delete(to_bytes(attr), set_to_bytes(set)),
+                    Mod::Replace(attr, set) => Mod::Replace(to_bytes(attr), set_to_bytes(set)),
+                    Mod::Increment(attr, val) => Mod::Increment(to_bytes(attr), to_bytes(val)),
+                })
+                .collect(),
+        })
+    }
+
+    /// Queue a Delete. See [`Ldap::delete()`](struct.Ldap.html#method.delete).
+    pub fn delete(&mut self, dn: &str) -> &mut BatchItem {
+        self.push(BatchOp::Delete { dn: dn.to_owned() })
+    }
+
+    /// Queue a ModifyDN. See [`Ldap::modifydn()`](struct.Ldap.html#method.modifydn).
+    pub fn modifydn(
+        &mut self,
+        dn: &str,
+        rdn: &str,
+        delete_old: bool,
+        new_sup: Option<&str>,
+    ) -> &mut BatchItem {
+        self.push(BatchOp::ModifyDN {
+            dn: dn.to_owned(),
+            rdn: rdn.to_owned(),
+            delete_old,
+            new_sup: new_sup.map(str::to_owned),
+        })
+    }
+
+    /// Queue an Extended operation. See [`Ldap::extended()`](struct.Ldap.html#method.extended).
+    pub fn extended<E: Into<Exop>>(&mut self, exop: E) -> &mut BatchItem {
+        self.push(BatchOp::Extended(exop.into()))
+    }
+
+    /// Perform the queued operations, running at most `concurrency` of them at the same
+    /// time. A `concurrency` of zero is treated as one.
+    ///
+    /// The results are returned in the order in which the operations were queued. The
+    /// failure of one operation doesn't affect the others.
+    pub async fn execute(self, concurrency: usize) -> Vec<Result<BatchItemResult>> {
+        let ldap = self.ldap;
+        let mut results: Vec<_> = stream::iter(self.items.into_iter().enumerate())
+            .map(|(index, item)| {
+                let ldap = ldap.clone();
+                async move { (index, item.run(ldap).await) }
+            })
+            .buffer_unordered(concurrency.max(1))
+            .collect()
+            .await;
+        results.sort_unstable_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, res)| res).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conn::LdapConnAsync;
+    use crate::controls::{ManageDsaIt, RawControl};
+    use crate::fixtures::{self, message, result, Requests};
+    use crate::result::LdapError;
+
+    use tokio::io::AsyncWriteExt;
+    use tokio::sync::mpsc;
+    use tokio::time;
+
+    // Scripted server answering Compare requests for "cn=<n>" after (10 - n) milliseconds,
+    // so that later requests are answered first. The comparison is true for even n. An entry
+    // named "cn=silent" gets no answer. The DN and the number of controls of each request
+    // are reported through the channel.
+    async fn server() -> (String, mpsc::UnboundedReceiver<(String, usize)>) {
+        let (listener, url) = fixtures::listener().await;
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.expect("accept");
+            let (rd, mut wr) = stream.into_split();
+            let (reply_tx, mut reply_rx) = mpsc::unbounded_channel::<Vec<u8>>();
+            tokio::spawn(async move {
+                while let Some(reply) = reply_rx.recv().await {
+                    if wr.write_all(&reply).await.is_err() {
+                        return;
+                    }
+                }
+            });
+            let mut requests = Requests::new(rd);
+            while let Some(req) = requests.next().await {
+                if req.op.id != 14 {
+                    continue;
+                }
+                let dn = req.dn();
+                let _ = tx.send((dn.clone(), req.controls.len()));
+                let n: u64 = match dn.strip_prefix("cn=").and_then(|n| n.parse().ok()) {
+                    Some(n) => n,
+                    None => continue,
+                };
+                let reply_tx = reply_tx.clone();
+                tokio::spawn(async move {
+                    time::sleep(Duration::from_millis(10u64.saturating_sub(n))).await;
+                    let rc = if n.is_multiple_of(2) { 6 } else { 5 };
+                    let _ = reply_tx.send(message(req.msgid, result(15, rc)));
+                });
+            }
+        });
+        (url, rx)
+    }
+
+    #[tokio::test]
+    async fn ordering_and_isolation() {
+        let (url, mut received) = server().await;
+        let (conn, mut ldap) = LdapConnAsync::new(&url).await.expect("connection");
+        crate::drive!(conn);
+        let mut batch = ldap.with_controls(ManageDsaIt).batch();
+        for n in 0..10 {
+            if n == 3 {
+                batch
+                    .compare("cn=silent", "cn", "x")
+                    .with_timeout(Duration::from_millis(50));
+            } else {
+                let item = batch.compare(&format!("cn={}", n), "cn", "x");
+                if n == 5 {
+                    item.with_controls(vec![
+                        RawControl::from(ManageDsaIt),
+                        RawControl::from(ManageDsaIt),
+                    ]);
+                }
+            }
+        }
+        assert_eq!(batch.len(), 10);
+        let results = batch.execute(4).await;
+        assert_eq!(results.len(), 10);
+        for (n, res) in results.into_iter().enumerate() {
+            if n == 3 {
+                assert!(matches!(res, Err(LdapError::Timeout { .. })));
+                continue;
+            }
+            match res.expect("result") {
+                BatchItemResult::Compare(cmp) => {
+                    assert_eq!(cmp.equal().expect("compare"), n.is_multiple_of(2))
+                }
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+        drop(ldap);
+        let mut ctrls = vec![];
+        while let Some((dn, count)) = received.recv().await {
+            ctrls.push((dn, count));
+        }
+        ctrls.sort();
+        assert_eq!(ctrls.len(), 10);
+        for (dn, count) in ctrls {
+            assert_eq!(count, if dn == "cn=5" { 2 } else { 1 }, "{}", dn);
+        }
+    }
+}
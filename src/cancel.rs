@@ -0,0 +1,310 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::Notify;
+
+/// The reason an operation was cancelled.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CancelCause {
+    /// The token was triggered by calling [`cancel()`](struct.OpCancellation.html#method.cancel).
+    User,
+    /// The timeout of the operation expired before the token was triggered.
+    Timeout,
+}
+
+impl fmt::Display for CancelCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CancelCause::User => write!(f, "user request"),
+            CancelCause::Timeout => write!(f, "timeout"),
+        }
+    }
+}
+
+/// Cancellation token for LDAP operations.
+///
+/// A token is attached to the next operation with
+/// [`Ldap::with_cancellation()`](struct.Ldap.html#method.with_cancellation), and
+/// triggered by calling [`cancel()`](#method.cancel) on any of its clones, possibly
+/// from another task or thread. Cloning is cheap, and all clones share the same state.
+///
+/// When the token is triggered, the operation stops waiting for the response, sends
+/// an Abandon request for its message id if the operation can be abandoned (Bind and
+/// StartTLS can't), discards any response which may yet arrive, and returns
+/// [`LdapError::Cancelled`](result/enum.LdapError.html#variant.Cancelled). For a streaming
+/// Search, the cancellation is observed by [`next()`](struct.SearchStream.html#method.next),
+/// and the error carries the number of entries returned by the stream before cancellation.
+///
+/// A token can be triggered only once; the first trigger wins, and its cause is recorded.
+/// If the operation also has a timeout, the expiry of the timeout triggers the token with
+/// [`CancelCause::Timeout`](enum.CancelCause.html#variant.Timeout), and the operation is
+/// handled as cancelled. Triggering the token after the operation has completed has no
+/// effect on it, while an operation started with an already triggered token fails
+/// immediately, without sending the request to the server.
+#[derive(Clone, Debug, Default)]
+pub struct OpCancellation {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    cause: Mutex<Option<CancelCause>>,
+    notify: Notify,
+}
+
+impl OpCancellation {
+    /// Create a new, untriggered token.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trigger the token. Returns `true` if this call triggered it, and `false`
+    /// if it had already been triggered.
+    pub fn cancel(&self) -> bool {
+        self.trigger(CancelCause::User)
+    }
+
+    /// Check whether the token has been triggered.
+    pub fn is_cancelled(&self) -> bool {
+        self.cause().is_some()
+    }
+
+    /// Return the cause of cancellation, if the token has been triggered.
+    pub fn cause(&self) -> Option<CancelCause> {
+        *self.inner.cause.lock().expect("cancel cause mutex")
+    }
+
+    pub(crate) fn trigger(&self, cause: CancelCause) -> bool {
+        let mut current = self.inner.cause.lock().expect("cancel cause mutex");
+        if current.is_some() {
+            return false;
+        }
+        *current = Some(cause);
+        drop(current);
+        self.inner.notify.notify_waiters();
+        true
+    }
+
+    /// Wait until the token is triggered, returning the recorded cause.
+    pub async fn cancelled(&self) -> CancelCause {
+        loop {
+            // The future must be created before checking the state, so that
+            // a concurrent notify_waiters() isn't missed.
+            let notified = self.inner.notify.notified();
+            if let Some(cause) = self.cause() {
+                return cause;
+            }
+            notified.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+    use std::time::Duration;
+
+    use super::{CancelCause, OpCancellation};
+    use crate::conn::LdapConnAsync;
+    use crate::fixtures::{self, entry, message, result};
+    use crate::ldap::Ldap;
+    use crate::result::LdapError;
+    use crate::search::Scope;
+
+    use tokio::sync::mpsc;
+    use tokio::time;
+
+    // Scripted server: Delete and Search requests naming "cn=hang" are never completed,
+    // the others are answered immediately. A Search always returns two entries first.
+    // The ids of abandoned operations are sent to the returned channel.
+    async fn server() -> (String, mpsc::UnboundedReceiver<u64>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let url = fixtures::serve(move |req| match req.op.id {
+            3 => {
+                let mut reply = message(req.msgid, entry("cn=a", &[]));
+                reply.extend(message(req.msgid, entry("cn=b", &[])));
+                if req.dn() != "cn=hang" {
+                    reply.extend(message(req.msgid, result(5, 0)));
+                }
+                reply
+            }
+            10 if req.dn() == "cn=hang" => vec![],
+            10 => message(req.msgid, result(11, 0)),
+            16 => {
+                let _ = tx.send(req.abandoned());
+                vec![]
+            }
+            _ => vec![],
+        })
+        .await;
+        (url, rx)
+    }
+
+    async fn connect(url: &str) -> Ldap {
+        let (conn, ldap) = LdapConnAsync::new(url).await.expect("connection");
+        crate::drive!(conn);
+        ldap
+    }
+
+    // A completed round trip guarantees that all previously sent Abandons were seen.
+    async fn abandoned(ldap: &mut Ldap, rx: &mut mpsc::UnboundedReceiver<u64>) -> Vec<u64> {
+        ldap.delete("cn=sync").await.expect("sync");
+        let mut ids = vec![];
+        while let Ok(id) = rx.try_recv() {
+            ids.push(id);
+        }
+        ids
+    }
+
+    #[test]
+    fn first_trigger_wins() {
+        let cancel = OpCancellation::new();
+        let clone = cancel.clone();
+        assert!(!cancel.is_cancelled());
+        assert!(cancel.trigger(CancelCause::Timeout));
+        assert!(!clone.cancel());
+        assert_eq!(clone.cause(), Some(CancelCause::Timeout));
+    }
+
+    #[tokio::test]
+    async fn cancel_single_op() {
+        let (url, mut rx) = server().await;
+        let mut ldap = connect(&url).await;
+        let cancel = OpCancellation::new();
+        let trigger = cancel.clone();
+        tokio::spawn(async move {
+            time::sleep(Duration::from_millis(50)).await;
+            trigger.cancel();
+        });
+        let res = ldap.with_cancellation(&cancel).delete("cn=hang").await;
+        assert!(matches!(
+            res,
+            Err(LdapError::Cancelled {
+                cause: CancelCause::User,
+                entries: 0
+            })
+        ));
+        let id = ldap.last_id() as u64;
+        assert_eq!(abandoned(&mut ldap, &mut rx).await, vec![id]);
+    }
+
+    #[tokio::test]
+    async fn cancel_search_mid_stream() {
+        let (url, mut rx) = server().await;
+        let mut ldap = connect(&url).await;
+        let cancel = OpCancellation::new();
+        let mut stream = ldap
+            .with_cancellation(&cancel)
+            .streaming_search("cn=hang", Scope::Base, "(objectClass=*)", vec!["*"])
+            .await
+            .expect("stream");
+        assert!(stream.next().await.expect("entry").is_some());
+        assert!(stream.next().await.expect("entry").is_some());
+        let trigger = cancel.clone();
+        tokio::spawn(async move {
+            time::sleep(Duration::from_millis(50)).await;
+            trigger.cancel();
+        });
+        assert!(matches!(
+            stream.next().await,
+            Err(LdapError::Cancelled {
+                cause: CancelCause::User,
+                entries: 2
+            })
+        ));
+        assert_eq!(stream.finish().await.rc, 88);
+        let id = stream.ldap_handle().last_id() as u64;
+        assert_eq!(abandoned(&mut ldap, &mut rx).await, vec![id]);
+    }
+
+    #[tokio::test]
+    async fn timeout_triggers_token() {
+        let (url, mut rx) = server().await;
+        let mut ldap = connect(&url).await;
+        let cancel = OpCancellation::new();
+        let res = ldap
+            .with_timeout(Duration::from_millis(50))
+            .with_cancellation(&cancel)
+            .delete("cn=hang")
+            .await;
+        assert!(matches!(
+            res,
+            Err(LdapError::Cancelled {
+                cause: CancelCause::Timeout,
+                ..
+            })
+        ));
+        assert_eq!(cancel.cause(), Some(CancelCause::Timeout));
+        assert!(!cancel.cancel());
+        assert_eq!(abandoned(&mut ldap, &mut rx).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn triggered_token_before_and_after_op() {
+        let (url, mut rx) = server().await;
+        let mut ldap = connect(&url).await;
+        let cancel = OpCancellation::new();
+        ldap.with_cancellation(&cancel)
+            .delete("cn=x")
+            .await
+            .expect("delete")
+            .success()
+            .expect("success");
+        assert!(cancel.cancel());
+        let res = ldap.with_cancellation(&cancel).delete("cn=x").await;
+        assert!(matches!(res, Err(LdapError::Cancelled { entries: 0, .. })));
+        assert!(abandoned(&mut ldap, &mut rx).await.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn cancel_racing_completion() {
+        let (url, mut rx) = server().await;
+        let mut ldap = connect(&url).await;
+        let mut cancelled = HashSet::new();
+        for i in 0..100 {
+            let cancel = OpCancellation::new();
+            let trigger = cancel.clone();
+            let canceller = tokio::spawn(async move {
+                trigger.cancel();
+            });
+            let (res, id) = time::timeout(Duration::from_secs(5), async {
+                if i % 2 == 0 {
+                    let res = ldap.with_cancellation(&cancel).delete("cn=x").await;
+                    (res.map(|_| ()), ldap.last_id())
+                } else {
+                    let mut stream = match ldap
+                        .with_cancellation(&cancel)
+                        .streaming_search("cn=x", Scope::Base, "(objectClass=*)", vec!["*"])
+                        .await
+                    {
+                        Ok(stream) => stream,
+                        Err(e) => return (Err(e), 0),
+                    };
+                    let res = loop {
+                        match stream.next().await {
+                            Ok(Some(_)) => continue,
+                            Ok(None) => break Ok(()),
+                            Err(e) => break Err(e),
+                        }
+                    };
+                    stream.finish().await;
+                    (res, stream.ldap_handle().last_id())
+                }
+            })
+            .await
+            .expect("operation hung");
+            canceller.await.expect("canceller");
+            match res {
+                Ok(()) => (),
+                Err(LdapError::Cancelled { .. }) => {
+                    cancelled.insert(id as u64);
+                }
+                Err(e) => panic!("unexpected error: {}", e),
+            }
+        }
+        let ids = abandoned(&mut ldap, &mut rx).await;
+        let unique: HashSet<_> = ids.iter().cloned().collect();
+        assert_eq!(ids.len(), unique.len(), "operation abandoned twice");
+        assert!(unique.is_subset(&cancelled));
+    }
+}
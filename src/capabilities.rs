@@ -0,0 +1,200 @@
+use crate::mapping::lookup;
+use crate::password::AD_CAPABILITY_OID;
+use crate::schema::RootDse;
+
+// LDAP_CAP_ACTIVE_DIRECTORY_ADAM_OID, advertised by AD LDS.
+const ADAM_CAPABILITY_OID: &str = "1.2.840.113556.1.4.1851";
+
+/// Kind of directory server, inferred from its root DSE.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ServerFlavor {
+    /// OpenLDAP.
+    OpenLdap,
+    /// Active Directory, including AD LDS.
+    ActiveDirectory,
+    /// 389 Directory Server, and the servers derived from it, such as Red Hat Directory Server.
+    Ds389,
+    /// None of the above, or a server which doesn't identify itself.
+    Unknown,
+}
+
+impl ServerFlavor {
+    // Active Directory is recognized by its capabilities or by its forest root naming
+    // context, 389 DS by its vendor name or version, and OpenLDAP by its vendor name,
+    // or by the object class of its root DSE, since it doesn't publish vendorName by
+    // default.
+    fn infer(root_dse: &RootDse) -> ServerFlavor {
+        let attrs = &root_dse.attrs;
+        let any = |attr: &str, pred: &dyn Fn(&str) -> bool| {
+            lookup(attrs, attr).is_some_and(|vals| vals.iter().any(|val| pred(val)))
+        };
+        if any("supportedCapabilities", &|oid| {
+            oid == AD_CAPABILITY_OID || oid == ADAM_CAPABILITY_OID
+        }) || lookup(attrs, "rootDomainNamingContext").is_some()
+        {
+            ServerFlavor::ActiveDirectory
+        } else if any("vendorName", &|name| {
+            name.contains("389 Project") || name.contains("Fedora Project")
+        }) || any("vendorVersion", &|version| {
+            version.starts_with("389-Directory")
+        }) {
+            ServerFlavor::Ds389
+        } else if any("vendorName", &|name| name.contains("OpenLDAP"))
+            || any("objectClass", &|oc| {
+                oc.eq_ignore_ascii_case("OpenLDAProotDSE")
+            })
+        {
+            ServerFlavor::OpenLdap
+        } else {
+            ServerFlavor::Unknown
+        }
+    }
+}
+
+/// Capabilities of a server, as advertised in its root DSE.
+///
+/// An instance is obtained with [`Ldap::capabilities()`](struct.Ldap.html#method.capabilities),
+/// which caches it for all handles of the connection, or constructed from an already
+/// retrieved root DSE with [`from_root_dse()`](#method.from_root_dse).
+#[derive(Clone, Debug)]
+pub struct ServerCapabilities {
+    root_dse: RootDse,
+    vendor: ServerFlavor,
+}
+
+impl ServerCapabilities {
+    /// Determine the capabilities from the root DSE.
+    pub fn from_root_dse(root_dse: RootDse) -> Self {
+        let vendor = ServerFlavor::infer(&root_dse);
+        ServerCapabilities { root_dse, vendor }
+    }
+
+    /// Check whether the control with the given OID is listed in `supportedControl`.
+    pub fn supports_control(&self, oid: &str) -> bool {
+        self.root_dse.supported_control.iter().any(|c| c == oid)
+    }
+
+    /// Check whether the extended operation with the given OID is listed in `supportedExtension`.
+    pub fn supports_exop(&self, oid: &str) -> bool {
+        self.root_dse.supported_extension.iter().any(|e| e == oid)
+    }
+
+    /// Check whether the SASL mechanism is listed in `supportedSASLMechanisms`. Mechanism
+    /// names are compared case-insensitively.
+    pub fn supports_sasl_mech(&self, mech: &str) -> bool {
+        self.root_dse
+            .supported_sasl_mechanisms
+            .iter()
+            .any(|m| m.eq_ignore_ascii_case(mech))
+    }
+
+    /// Return the values of `namingContexts`.
+    pub fn naming_contexts(&self) -> &[String] {
+        &self.root_dse.naming_contexts
+    }
+
+    /// Return the kind of the server.
+    pub fn vendor(&self) -> ServerFlavor {
+        self.vendor
+    }
+
+    /// Return the root DSE from which the capabilities were determined.
+    pub fn root_dse(&self) -> &RootDse {
+        &self.root_dse
+    }
+
+    // Check whether the server lists its supported controls, but not the given one.
+    // A server may hide the list from unauthenticated clients, so an empty list doesn't
+    // rule out any control.
+    pub(crate) fn lacks_control(&self, oid: &str) -> bool {
+        !self.root_dse.supported_control.is_empty() && !self.supports_control(oid)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::search::SearchEntry;
+
+    type Attrs<'a> = &'a [(&'a str, &'a [&'a str])];
+
+    fn from_attrs(attrs: Attrs) -> ServerCapabilities {
+        let entry = SearchEntry {
+            dn: String::new(),
+            attrs: attrs
+                .iter()
+                .map(|(name, vals)| {
+                    (
+                        name.to_string(),
+                        vals.iter().map(|v| v.to_string()).collect(),
+                    )
+                })
+                .collect(),
+            bin_attrs: HashMap::new(),
+            truncated: vec![],
+        };
+        ServerCapabilities::from_root_dse(RootDse::from_entry(entry))
+    }
+
+    #[test]
+    fn flavor_inference() {
+        let cases: &[(Attrs, ServerFlavor)] = &[
+            (
+                &[("supportedCapabilities", &["1.2.840.113556.1.4.800"])],
+                ServerFlavor::ActiveDirectory,
+            ),
+            (
+                &[("supportedcapabilities", &["1.2.840.113556.1.4.1851"])],
+                ServerFlavor::ActiveDirectory,
+            ),
+            (
+                &[("rootDomainNamingContext", &["DC=example,DC=org"])],
+                ServerFlavor::ActiveDirectory,
+            ),
+            (&[("vendorName", &["389 Project"])], ServerFlavor::Ds389),
+            (
+                &[("vendorVersion", &["389-Directory/2.4.5 B2024.011.0000"])],
+                ServerFlavor::Ds389,
+            ),
+            (
+                &[("objectClass", &["top", "OpenLDAProotDSE"])],
+                ServerFlavor::OpenLdap,
+            ),
+            (
+                &[("vendorName", &["OpenLDAP Foundation"])],
+                ServerFlavor::OpenLdap,
+            ),
+            (
+                &[("objectClass", &["top", "extensibleObject"])],
+                ServerFlavor::Unknown,
+            ),
+            (&[], ServerFlavor::Unknown),
+        ];
+        for (attrs, flavor) in cases {
+            assert_eq!(from_attrs(attrs).vendor(), *flavor, "{:?}", attrs);
+        }
+    }
+
+    #[test]
+    fn supported_features() {
+        let caps = from_attrs(&[
+            ("namingContexts", &["dc=example,dc=org"]),
+            ("supportedControl", &["1.2.840.113556.1.4.319"]),
+            ("supportedExtension", &["1.3.6.1.4.1.4203.1.11.1"]),
+            ("supportedSASLMechanisms", &["EXTERNAL", "SCRAM-SHA-256"]),
+        ]);
+        assert!(caps.supports_control("1.2.840.113556.1.4.319"));
+        assert!(!caps.supports_control("1.2.840.113556.1.4.805"));
+        assert!(caps.lacks_control("1.2.840.113556.1.4.805"));
+        assert!(caps.supports_exop("1.3.6.1.4.1.4203.1.11.1"));
+        assert!(!caps.supports_exop("1.3.6.1.4.1.1466.20037"));
+        assert!(caps.supports_sasl_mech("scram-sha-256"));
+        assert!(!caps.supports_sasl_mech("GSSAPI"));
+        assert_eq!(caps.naming_contexts(), ["dc=example,dc=org"]);
+        let hidden = from_attrs(&[]);
+        assert!(!hidden.lacks_control("1.2.840.113556.1.4.805"));
+    }
+}
@@ -0,0 +1,216 @@
+use lber::common::TagClass;
+use lber::parse::parse_tag;
+use lber::structure::StructureTag;
+use lber::universal::Types;
+
+use sha2::{Digest, Sha224, Sha256, Sha384, Sha512};
+
+// Hash functions which can be used for tls-server-end-point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EndpointHash {
+    Sha224,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl EndpointHash {
+    fn digest(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            EndpointHash::Sha224 => Sha224::digest(data).to_vec(),
+            EndpointHash::Sha256 => Sha256::digest(data).to_vec(),
+            EndpointHash::Sha384 => Sha384::digest(data).to_vec(),
+            EndpointHash::Sha512 => Sha512::digest(data).to_vec(),
+        }
+    }
+}
+
+const RSASSA_PSS_OID: &str = "1.2.840.113549.1.1.10";
+
+// Hash of the signature algorithm, or of the digest algorithm from the RSASSA-PSS
+// parameters. MD5 and SHA-1 are replaced by SHA-256, as required by RFC 5929.
+fn endpoint_hash(oid: &str) -> Option<EndpointHash> {
+    match oid {
+        // md5WithRSAEncryption, sha1WithRSAEncryption, ecdsa-with-SHA1, dsa-with-sha1,
+        // and the SHA-1 and MD5 digest algorithms.
+        "1.2.840.113549.1.1.4"
+        | "1.2.840.113549.1.1.5"
+        | "1.2.840.10045.4.1"
+        | "1.2.840.10040.4.3"
+        | "1.3.14.3.2.26"
+        | "1.2.840.113549.2.5" => Some(EndpointHash::Sha256),
+        "1.2.840.113549.1.1.14" | "1.2.840.10045.4.3.1" | "2.16.840.1.101.3.4.3.1" => {
+            Some(EndpointHash::Sha224)
+        }
+        "1.2.840.113549.1.1.11" | "1.2.840.10045.4.3.2" | "2.16.840.1.101.3.4.3.2" => {
+            Some(EndpointHash::Sha256)
+        }
+        "1.2.840.113549.1.1.12" | "1.2.840.10045.4.3.3" => Some(EndpointHash::Sha384),
+        "1.2.840.113549.1.1.13" | "1.2.840.10045.4.3.4" => Some(EndpointHash::Sha512),
+        "2.16.840.1.101.3.4.2.4" => Some(EndpointHash::Sha224),
+        "2.16.840.1.101.3.4.2.1" => Some(EndpointHash::Sha256),
+        "2.16.840.1.101.3.4.2.2" => Some(EndpointHash::Sha384),
+        "2.16.840.1.101.3.4.2.3" => Some(EndpointHash::Sha512),
+        _ => None,
+    }
+}
+
+fn oid_string(bytes: &[u8]) -> Option<String> {
+    let mut arcs = vec![];
+    let mut arc = 0u64;
+    for (i, b) in bytes.iter().enumerate() {
+        if arc == 0 && *b == 0x80 {
+            return None;
+        }
+        arc = arc.checked_mul(128)? | (b & 0x7f) as u64;
+        if b & 0x80 == 0 {
+            arcs.push(arc);
+            arc = 0;
+        } else if i == bytes.len() - 1 {
+            return None;
+        }
+    }
+    let first = *arcs.first()?;
+    let (top, second) = match first {
+        0..=39 => (0, first),
+        40..=79 => (1, first - 40),
+        _ => (2, first - 80),
+    };
+    let mut oid = format!("{}.{}", top, second);
+    for arc in &arcs[1..] {
+        oid.push_str(&format!(".{}", arc));
+    }
+    Some(oid)
+}
+
+fn expect_oid(tag: StructureTag) -> Option<String> {
+    let bytes = tag
+        .match_class(TagClass::Universal)
+        .and_then(|t| t.match_id(Types::ObjectIdentifier as u64))
+        .and_then(|t| t.expect_primitive())?;
+    oid_string(&bytes)
+}
+
+// The digest algorithm of RSASSA-PSS is the first, optional, explicitly tagged
+// element of its parameters. If absent, it's SHA-1.
+fn pss_hash(params: Option<StructureTag>) -> Option<EndpointHash> {
+    let hash_alg = params
+        .and_then(|p| p.expect_constructed())
+        .and_then(|comps| {
+            comps
+                .into_iter()
+                .find(|c| c.class == TagClass::Context && c.id == 0)
+        });
+    let oid = match hash_alg {
+        Some(tag) => tag
+            .expect_constructed()?
+            .into_iter()
+            .next()?
+            .expect_constructed()?
+            .into_iter()
+            .next()
+            .and_then(expect_oid)?,
+        None => return Some(EndpointHash::Sha256),
+    };
+    endpoint_hash(&oid)
+}
+
+/// Compute the __tls-server-end-point__ channel binding data
+/// ([RFC 5929](https://tools.ietf.org/html/rfc5929#section-4)) from the DER-encoded
+/// server certificate. The certificate is hashed with the hash function of its signature
+/// algorithm, using SHA-256 instead of MD5 or SHA-1. If the signature algorithm doesn't
+/// have a single hash function, as is the case with EdDSA, the channel binding is
+/// undefined, and the return value is `None`.
+pub(crate) fn tls_server_end_point(cert_der: &[u8]) -> Option<Vec<u8>> {
+    let cert = match parse_tag(cert_der) {
+        Ok((_, cert)) => cert,
+        Err(_) => return None,
+    };
+    let mut comps = cert.expect_constructed()?.into_iter();
+    let mut sig_alg = comps.nth(1)?.expect_constructed()?.into_iter();
+    let oid = expect_oid(sig_alg.next()?)?;
+    let hash = if oid == RSASSA_PSS_OID {
+        pss_hash(sig_alg.next())
+    } else {
+        endpoint_hash(&oid)
+    };
+    match hash {
+        Some(hash) => Some(hash.digest(cert_der)),
+        None => {
+            warn!(
+                "no channel binding hash for signature algorithm, oid={}",
+                oid
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{oid_string, tls_server_end_point};
+
+    use sha2::{Digest, Sha384, Sha512};
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn oids() {
+        assert_eq!(
+            oid_string(&[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b]).as_deref(),
+            Some("1.2.840.113549.1.1.11")
+        );
+        assert_eq!(
+            oid_string(&[0x2b, 0x65, 0x70]).as_deref(),
+            Some("1.3.101.112")
+        );
+        assert_eq!(
+            oid_string(&[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01]).as_deref(),
+            Some("2.16.840.1.101.3.4.2.1")
+        );
+        assert_eq!(oid_string(&[0x2a, 0x86]), None);
+        assert_eq!(oid_string(&[]), None);
+    }
+
+    #[test]
+    fn end_point_hashes() {
+        // MD5 and SHA-1 are upgraded to SHA-256.
+        let md5 = include_bytes!("../data/tls/cb/rsa-md5.der");
+        assert_eq!(
+            hex(&tls_server_end_point(md5).expect("md5")),
+            "f9c571ea0980f32b78ca49c8f16bd9b9a80a7e2c72169f4a9d83f2de7a7169b0"
+        );
+        let sha1 = include_bytes!("../data/tls/cb/rsa-sha1.der");
+        assert_eq!(
+            hex(&tls_server_end_point(sha1).expect("sha1")),
+            "e6a9c95cd68e4fed258700f25d0cf0d26f1a5b72f4d2ceca215b6e4c8637e0c5"
+        );
+        let sha256 = include_bytes!("../data/tls/cert.der");
+        assert_eq!(
+            hex(&tls_server_end_point(sha256).expect("sha256")),
+            "7f5352b4df4a0a621f1f43d8a18e701d933c3415f926b6543299c1e71c63ee74"
+        );
+        let ecdsa = include_bytes!("../data/tls/cb/ecdsa-sha384.der");
+        assert_eq!(
+            tls_server_end_point(ecdsa).expect("ecdsa"),
+            Sha384::digest(ecdsa).to_vec()
+        );
+        let pss = include_bytes!("../data/tls/cb/rsa-pss-sha384.der");
+        assert_eq!(
+            tls_server_end_point(pss).expect("pss"),
+            Sha384::digest(pss).to_vec()
+        );
+        let sha512 = include_bytes!("../data/tls/cb/rsa-sha512.der");
+        assert_eq!(
+            tls_server_end_point(sha512).expect("sha512"),
+            Sha512::digest(sha512).to_vec()
+        );
+        assert_eq!(
+            tls_server_end_point(include_bytes!("../data/tls/cb/ed25519.der")),
+            None
+        );
+        assert_eq!(tls_server_end_point(b"not a certificate"), None);
+    }
+}
@@ -1,7 +1,15 @@
-use std::collections::{HashMap, HashSet};
-#[cfg(feature = "tls-rustls")]
-use std::net::IpAddr;
+use std::collections::{HashMap, HashSet, VecDeque};
+#[cfg(unix)]
+use std::ffi::OsString;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+#[cfg(unix)]
+use std::os::unix::ffi::OsStringExt;
+use std::path::Path;
+#[cfg(unix)]
+use std::path::PathBuf;
 use std::pin::Pin;
+use std::result::Result as StdResult;
 #[cfg(feature = "tls-rustls")]
 use std::str::FromStr;
 #[cfg(feature = "gssapi")]
@@ -10,15 +18,29 @@ use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
 use std::time::Duration;
 
+use crate::controls::Control;
 #[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
 use crate::exop_impl::StartTLS;
-use crate::ldap::Ldap;
-use crate::protocol::{ItemSender, LdapCodec, LdapOp, MaybeControls, MiscSender, ResultSender};
-use crate::result::{LdapError, Result};
+use crate::ldap::{alloc_msgid, Ldap};
+use crate::ldaprc::{DefaultsInfo, LdapRc};
+use crate::observer::{LdapObserver, Observer};
+use crate::protocol::{
+    BoxedReader, ItemSender, LdapCodec, LdapOp, MaybeControls, MiscSender, ResultSender, Segment,
+    Segments,
+};
+use crate::ratelimit::TokenBucket;
+use crate::result::{LdapError, LdapResultExt, Result};
+use crate::retry::RetryPolicy;
+#[cfg(unix)]
+use crate::rt::UnixStream;
+use crate::rt::{self, TcpStream};
 use crate::search::SearchItem;
+use crate::stats::ConnStats;
+use crate::unsolicited::{Notifier, UnsolicitedNotification};
 use crate::RequestId;
 
-use lber::structures::{Null, Tag};
+use lber::common::TagClass;
+use lber::structures::{ASNTag, Enumerated, Null, OctetString, Sequence, Tag};
 
 #[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
 use futures_util::future::TryFutureExt;
@@ -26,21 +48,19 @@ use futures_util::sink::SinkExt;
 #[cfg(feature = "tls-rustls")]
 use lazy_static::lazy_static;
 #[cfg(feature = "tls-native")]
-use native_tls::TlsConnector;
+use native_tls::{Certificate, Identity, TlsConnector};
 #[cfg(unix)]
 use percent_encoding::percent_decode;
-#[cfg(all(feature = "gssapi", feature = "tls-rustls"))]
-use ring::digest::{self, digest, Algorithm};
 #[cfg(feature = "tls-rustls")]
-use rustls::{pki_types::CertificateDer, pki_types::ServerName, ClientConfig, RootCertStore};
-use tokio::io::{self, AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
-use tokio::net::TcpStream;
-#[cfg(unix)]
-use tokio::net::UnixStream;
+use rustls::{
+    pki_types::CertificateDer, pki_types::PrivateKeyDer, pki_types::ServerName, ClientConfig,
+    RootCertStore,
+};
+use socket2::TcpKeepalive;
+use tokio::io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
 use tokio::sync::mpsc;
-#[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
+use tokio::sync::mpsc::error::TrySendError;
 use tokio::sync::oneshot;
-use tokio::time;
 #[cfg(all(feature = "tls-native", not(feature = "tls-rustls")))]
 use tokio_native_tls::{TlsConnector as TokioTlsConnector, TlsStream};
 #[cfg(all(feature = "tls-rustls", not(feature = "tls-native")))]
@@ -58,6 +78,19 @@ enum ConnType {
     Tls(TlsStream<TcpStream>),
     #[cfg(unix)]
     Unix(UnixStream),
+    Boxed(Pin<Box<dyn Transport>>),
+}
+
+// Transport supplied by the caller. The I/O traits can't be combined in a trait object
+// directly, hence the helper trait.
+trait Transport: AsyncRead + AsyncWrite + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Send> Transport for T {}
+
+impl std::fmt::Debug for dyn Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Transport")
+    }
 }
 
 #[cfg(feature = "tls-rustls")]
@@ -123,22 +156,6 @@ lazy_static! {
     };
 }
 
-#[cfg(all(feature = "gssapi", feature = "tls-rustls"))]
-lazy_static! {
-    static ref ENDPOINT_ALG: HashMap<&'static str, &'static Algorithm> = {
-        HashMap::from([
-            ("1.2.840.113549.1.1.4", &digest::SHA256),
-            ("1.2.840.113549.1.1.5", &digest::SHA256),
-            ("1.2.840.113549.1.1.11", &digest::SHA256),
-            ("1.2.840.113549.1.1.12", &digest::SHA384),
-            ("1.2.840.113549.1.1.13", &digest::SHA512),
-            ("1.2.840.10045.4.3.2", &digest::SHA256),
-            ("1.2.840.10045.4.3.3", &digest::SHA384),
-            ("1.2.840.10045.4.3.4", &digest::SHA512),
-        ])
-    };
-}
-
 impl AsyncRead for ConnType {
     fn poll_read(
         self: Pin<&mut Self>,
@@ -151,6 +168,7 @@ impl AsyncRead for ConnType {
             ConnType::Tls(tls) => Pin::new(tls).poll_read(cx, buf),
             #[cfg(unix)]
             ConnType::Unix(us) => Pin::new(us).poll_read(cx, buf),
+            ConnType::Boxed(t) => t.as_mut().poll_read(cx, buf),
         }
     }
 }
@@ -163,6 +181,7 @@ impl AsyncWrite for ConnType {
             ConnType::Tls(tls) => Pin::new(tls).poll_write(cx, buf),
             #[cfg(unix)]
             ConnType::Unix(us) => Pin::new(us).poll_write(cx, buf),
+            ConnType::Boxed(t) => t.as_mut().poll_write(cx, buf),
         }
     }
 
@@ -173,6 +192,7 @@ impl AsyncWrite for ConnType {
             ConnType::Tls(tls) => Pin::new(tls).poll_flush(cx),
             #[cfg(unix)]
             ConnType::Unix(us) => Pin::new(us).poll_flush(cx),
+            ConnType::Boxed(t) => t.as_mut().poll_flush(cx),
         }
     }
 
@@ -183,10 +203,48 @@ impl AsyncWrite for ConnType {
             ConnType::Tls(tls) => Pin::new(tls).poll_shutdown(cx),
             #[cfg(unix)]
             ConnType::Unix(us) => Pin::new(us).poll_shutdown(cx),
+            ConnType::Boxed(t) => t.as_mut().poll_shutdown(cx),
         }
     }
 }
 
+/// TLS parameters of an established connection.
+///
+/// An instance is captured right after the TLS handshake, for both `ldaps` and StartTLS
+/// connections, and can be retrieved with [`Ldap::tls_info()`](struct.Ldap.html#method.tls_info).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TlsInfo {
+    /// Server certificate in DER format. Empty if the server didn't present a certificate.
+    pub peer_der: Vec<u8>,
+    /// Negotiated protocol version, if the TLS backend reports it.
+    pub protocol: Option<String>,
+    /// Negotiated cipher suite, if the TLS backend reports it.
+    pub cipher: Option<String>,
+}
+
+/// Handling of the responses whose message ID doesn't belong to an outstanding operation.
+///
+/// Such responses can only come from a broken peer, e.g., a load balancer which reuses
+/// a server connection previously given to another client, or a server which answers
+/// an operation twice. The exception are responses which arrive for an operation after
+/// the client abandoned it, timed it out, cancelled it, or dropped its handle: those are
+/// a normal occurrence, and aren't subject to the policy. The client remembers the IDs
+/// of the last 1024 operations it gave up on in that way; a late response for an older
+/// one is unmatched. Every unmatched response is counted in
+/// [`ConnStats::unmatched_responses()`](struct.ConnStats.html#method.unmatched_responses).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unmatched {
+    /// Discard the response silently.
+    Ignore,
+    /// Discard the response, logging a warning. At most `per_minute` warnings are logged
+    /// in any minute; the number of responses discarded without a warning is included
+    /// in the next logged warning.
+    WarnSampled { per_minute: u32 },
+    /// Treat the server as broken: fail all outstanding operations, and terminate the
+    /// connection with [`LdapError::ProtocolViolation`](result/enum.LdapError.html#variant.ProtocolViolation).
+    Disconnect,
+}
+
 /// Additional settings for an LDAP connection.
 ///
 /// The structure is opaque for better extensibility. An instance with
@@ -198,12 +256,37 @@ pub struct LdapConnSettings {
     conn_timeout: Option<Duration>,
     #[cfg(feature = "tls-native")]
     connector: Option<TlsConnector>,
+    #[cfg(feature = "tls-native")]
+    identity: Option<Identity>,
     #[cfg(feature = "tls-rustls")]
     config: Option<Arc<ClientConfig>>,
+    #[cfg(feature = "tls-rustls")]
+    client_cert: Option<(Vec<CertificateDer<'static>>, Arc<PrivateKeyDer<'static>>)>,
     #[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
     starttls: bool,
     #[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
     no_tls_verify: bool,
+    #[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
+    pub(crate) root_certs_pem: Vec<Vec<u8>>,
+    search_queue_size: Option<usize>,
+    max_ber_size: Option<usize>,
+    wire_trace: Option<bool>,
+    keep_raw_frames: Option<bool>,
+    write_coalescing: Option<usize>,
+    abandon_on_timeout: Option<bool>,
+    unmatched_response_policy: Option<Unmatched>,
+    strict_validation: Option<bool>,
+    canonical_sets: Option<bool>,
+    ldapi_external_bind: Option<bool>,
+    happy_eyeballs: Option<bool>,
+    happy_eyeballs_delay: Option<Duration>,
+    local_bind_addr: Option<IpAddr>,
+    tcp_keepalive: Option<Duration>,
+    tcp_nodelay: Option<bool>,
+    #[cfg(target_os = "linux")]
+    tcp_user_timeout: Option<Duration>,
+    retry_policy: Option<RetryPolicy>,
+    observer: Option<Observer>,
 }
 
 impl LdapConnSettings {
@@ -218,6 +301,13 @@ impl LdapConnSettings {
     /// be established before the timeout expires, an error will be
     /// returned to the user. Defaults to `None`, meaning an infinite
     /// timeout.
+    ///
+    /// The timeout covers name resolution, connection attempts and the TLS
+    /// handshake. When the server has several addresses which are tried one by
+    /// one, each attempt gets an equal share of the time left, so an address
+    /// which doesn't answer can't hold up the rest. If no attempt succeeds in
+    /// time, the error is `LdapError::ConnectFailed`, listing the addresses
+    /// which were tried.
     pub fn set_conn_timeout(mut self, timeout: Duration) -> Self {
         self.conn_timeout = Some(timeout);
         self
@@ -232,6 +322,22 @@ impl LdapConnSettings {
         self
     }
 
+    #[cfg(feature = "tls-native")]
+    /// Set the client identity, consisting of the certificate chain and the private key,
+    /// which will be presented to the server during the TLS handshake. This makes it
+    /// possible to use [`sasl_external_bind()`](struct.Ldap.html#method.sasl_external_bind)
+    /// over a TLS connection.
+    ///
+    /// The identity is attached to the connector constructed by the library, and can be
+    /// combined with [`set_no_tls_verify()`](#method.set_no_tls_verify). It can't be combined
+    /// with a custom connector given to [`set_connector()`](#method.set_connector), since
+    /// there is no way to modify an already built connector; if both are set, establishing
+    /// a TLS connection will fail with [`LdapError::IdentityWithCustomTls`](result/enum.LdapError.html#variant.IdentityWithCustomTls).
+    pub fn set_client_identity(mut self, identity: Identity) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+
     #[cfg(feature = "tls-rustls")]
     /// Set a custom TLS configuration, which enables setting various options
     /// when establishing a secure connection. The default of `None` will
@@ -244,6 +350,26 @@ impl LdapConnSettings {
         self
     }
 
+    #[cfg(feature = "tls-rustls")]
+    /// Set the client certificate chain and private key, which will be presented to the
+    /// server during the TLS handshake. This makes it possible to use
+    /// [`sasl_external_bind()`](struct.Ldap.html#method.sasl_external_bind) over a TLS
+    /// connection.
+    ///
+    /// The certificate is attached to the configuration constructed by the library, and can
+    /// be combined with [`set_no_tls_verify()`](#method.set_no_tls_verify). It can't be
+    /// combined with a custom configuration given to [`set_config()`](#method.set_config);
+    /// if both are set, establishing a TLS connection will fail with
+    /// [`LdapError::IdentityWithCustomTls`](result/enum.LdapError.html#variant.IdentityWithCustomTls).
+    pub fn set_client_cert(
+        mut self,
+        cert_chain: Vec<CertificateDer<'static>>,
+        key: PrivateKeyDer<'static>,
+    ) -> Self {
+        self.client_cert = Some((cert_chain, Arc::new(key)));
+        self
+    }
+
     #[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
     /// If `true`, use the StartTLS extended operation to establish a
     /// secure connection. Defaults to `false`.
@@ -272,10 +398,374 @@ impl LdapConnSettings {
         self.no_tls_verify = no_tls_verify;
         self
     }
+
+    #[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
+    /// Add the certificates from a PEM bundle to the trusted roots for verifying
+    /// the server certificate. The method can be called more than once. The bundle
+    /// is parsed when establishing a TLS connection, which fails if it's invalid.
+    ///
+    /// Like the client identity, the certificates are added to the connector or
+    /// configuration constructed by the library, and can't be combined with a custom
+    /// one; if both are set, establishing a TLS connection will fail with
+    /// [`LdapError::IdentityWithCustomTls`](result/enum.LdapError.html#variant.IdentityWithCustomTls).
+    pub fn add_root_certificates_pem(mut self, pem: &[u8]) -> Self {
+        self.root_certs_pem.push(pem.to_vec());
+        self
+    }
+
+    /// Create the settings from the client configuration in the style of OpenLDAP's
+    /// `ldap.conf(5)`. The configuration is read from the same files and environment
+    /// variables as OpenLDAP's, in the same order: the system-wide `ldap.conf`,
+    /// `ldaprc` and `.ldaprc` in the home directory, `ldaprc` in the current directory,
+    /// the file named by `LDAPCONF`, the files named by `LDAPRC`, and finally the
+    /// `LDAP<KEYWORD>` variables, such as `LDAPURI` or `LDAPTLS_REQCERT`. Later values
+    /// override the earlier ones. If `LDAPNOINIT` is set, nothing is read.
+    ///
+    /// The recognized keywords are __URI__, __BASE__, __NETWORK_TIMEOUT__, __TIMEOUT__,
+    /// __TLS_CACERT__, __TLS_CACERTDIR__, __TLS_REQCERT__, and __SASL_MECH__. Other
+    /// keywords are ignored with a warning. __NETWORK_TIMEOUT__ sets the connection
+    /// timeout, and the values of __TLS_REQCERT__ which don't abort the connection with
+    /// a bad certificate, "never" and "allow", turn off certificate verification. The
+    /// values which aren't connection settings are returned in [`DefaultsInfo`].
+    pub fn from_env() -> Result<(LdapConnSettings, DefaultsInfo)> {
+        LdapRc::from_env()?.into_settings()
+    }
+
+    /// Create the settings from a single configuration file in the style of OpenLDAP's
+    /// `ldap.conf(5)`. See [`from_env()`](#method.from_env) for the recognized keywords.
+    pub fn from_ldaprc(path: &Path) -> Result<(LdapConnSettings, DefaultsInfo)> {
+        LdapRc::from_file(path)?.into_settings()
+    }
+
+    /// Set the number of search result items which can be queued for a streaming
+    /// Search before the connection stops reading from the socket. Defaults to 1024.
+    /// A value of zero is treated as one.
+    ///
+    /// When the queue of a Search is full, the connection won't read anything else
+    /// until the consumer retrieves some items, which lets TCP flow control slow down
+    /// the server. Responses to other operations on the same connection are then also
+    /// held up, so an application which waits for another operation to complete
+    /// before reading the pending entries of a Search will deadlock. Finishing or
+    /// dropping the stream releases the connection.
+    pub fn set_search_queue_size(mut self, size: usize) -> Self {
+        self.search_queue_size = Some(size.max(1));
+        self
+    }
+
+    fn search_queue_size(&self) -> usize {
+        self.search_queue_size.unwrap_or(DEFAULT_SEARCH_QUEUE_SIZE)
+    }
+
+    /// Set the maximum size in bytes of a single message received from the server.
+    /// Defaults to 8 MiB.
+    ///
+    /// The size is checked as soon as the length header of a message arrives, and a
+    /// larger message terminates the connection with
+    /// [`LdapError::DecodingFrameTooLarge`](result/enum.LdapError.html#variant.DecodingFrameTooLarge)
+    /// without buffering its contents. Servers returning entries with large values,
+    /// such as photos or certificate revocation lists, may need a higher limit.
+    pub fn set_max_ber_size(mut self, size: usize) -> Self {
+        self.max_ber_size = Some(size);
+        self
+    }
+
+    fn max_ber_size(&self) -> usize {
+        self.max_ber_size.unwrap_or(DEFAULT_MAX_BER_SIZE)
+    }
+
+    /// If `true`, every message sent or received on the connection will be logged with
+    /// the target `ldap3::wire` at the DEBUG level, rendered as a tree of BER elements.
+    /// Defaults to `false`, in which case messages are logged at the TRACE level, if that
+    /// level is enabled for the target.
+    ///
+    /// The contents of the credentials in Bind requests and of the passwords in Password
+    /// Modify requests are never shown; only their length is logged.
+    pub fn set_wire_trace(mut self, wire_trace: bool) -> Self {
+        self.wire_trace = Some(wire_trace);
+        self
+    }
+
+    fn wire_trace(&self) -> bool {
+        self.wire_trace.unwrap_or(false)
+    }
+
+    /// If `true`, the encoding of every entry and reference received in a Search is
+    /// kept as it arrived from the server, and made available through
+    /// [`ResultEntry::raw()`](struct.ResultEntry.html#method.raw). Defaults to `false`.
+    ///
+    /// The kept bytes share the buffer of the received message, so no copies are made,
+    /// but the whole message stays in memory for as long as the entry is alive.
+    pub fn set_keep_raw_frames(mut self, keep_raw_frames: bool) -> Self {
+        self.keep_raw_frames = Some(keep_raw_frames);
+        self
+    }
+
+    fn keep_raw_frames(&self) -> bool {
+        self.keep_raw_frames.unwrap_or(false)
+    }
+
+    /// Set the maximum number of queued requests which are written to the socket
+    /// together, with a single flush. Defaults to `None`, meaning that every request
+    /// is flushed as soon as it's written. A value of zero is treated as one.
+    ///
+    /// With coalescing, the connection keeps writing the requests which are already
+    /// waiting to be sent, and flushes the socket when there are no more, or when the
+    /// maximum is reached. The write buffer is also flushed whenever it grows past
+    /// 8 KiB. When many small operations are started concurrently, e.g., through a
+    /// [`Batch`](struct.Batch.html), this saves a system call per operation, while
+    /// a lone request is still sent immediately.
+    pub fn set_write_coalescing(mut self, max_requests: Option<usize>) -> Self {
+        self.write_coalescing = max_requests.map(|max| max.max(1));
+        self
+    }
+
+    /// If `true`, an operation which times out, as set by
+    /// [`Ldap::with_timeout()`](struct.Ldap.html#method.with_timeout), will also be
+    /// abandoned on the server. Defaults to `true`.
+    ///
+    /// Bind, StartTLS, and the operations which don't have a response can't be
+    /// abandoned, and are only forgotten locally.
+    pub fn set_abandon_on_timeout(mut self, abandon_on_timeout: bool) -> Self {
+        self.abandon_on_timeout = Some(abandon_on_timeout);
+        self
+    }
+
+    fn abandon_on_timeout(&self) -> bool {
+        self.abandon_on_timeout.unwrap_or(true)
+    }
+
+    /// Set the handling of the responses whose message ID doesn't belong to an outstanding
+    /// operation; see [`Unmatched`](enum.Unmatched.html). Defaults to
+    /// `Unmatched::WarnSampled { per_minute: 60 }`.
+    pub fn set_unmatched_response_policy(mut self, policy: Unmatched) -> Self {
+        self.unmatched_response_policy = Some(policy);
+        self
+    }
+
+    fn unmatched_response_policy(&self) -> Unmatched {
+        self.unmatched_response_policy
+            .unwrap_or(Unmatched::WarnSampled { per_minute: 60 })
+    }
+
+    /// If `true`, the DNs, RDNs, filters and Increment values of requests will be checked
+    /// before the requests are sent, and an operation with an invalid request will fail
+    /// with [`LdapError::InvalidRequest`](result/enum.LdapError.html#variant.InvalidRequest)
+    /// without contacting the server. Likewise, a known control attached to an operation
+    /// with which it can't be used will fail the operation with
+    /// [`LdapError::ControlNotAllowed`](result/enum.LdapError.html#variant.ControlNotAllowed).
+    /// Defaults to `true`.
+    ///
+    /// Turning the checks off makes it possible to send malformed requests on purpose, e.g.
+    /// for testing servers.
+    pub fn set_strict_validation(mut self, strict_validation: bool) -> Self {
+        self.strict_validation = Some(strict_validation);
+        self
+    }
+
+    fn strict_validation(&self) -> bool {
+        self.strict_validation.unwrap_or(true)
+    }
+
+    /// If `true`, the values of each attribute in Add and Modify requests will be sent
+    /// in the canonical order of DER (X.690, section 11.6): shorter values first, and
+    /// values of equal length in lexicographic order. Defaults to `false`, which sends
+    /// the values in the iteration order of their `HashSet`, which varies between runs.
+    ///
+    /// The order of values has no meaning in LDAP, but a fixed order makes the encoded
+    /// requests reproducible, e.g., for comparing them in tests or signing them.
+    pub fn set_canonical_sets(mut self, canonical_sets: bool) -> Self {
+        self.canonical_sets = Some(canonical_sets);
+        self
+    }
+
+    fn canonical_sets(&self) -> bool {
+        self.canonical_sets.unwrap_or(false)
+    }
+
+    /// If `true`, a connection made with an `ldapi://` URL will perform a SASL EXTERNAL
+    /// Bind as soon as it's established, so that the server can authenticate the client
+    /// by the credentials of the peer process. Defaults to `false`. Settings with this
+    /// flag have no effect on other URL schemes.
+    ///
+    /// If the Bind fails, the error is returned from the function which opens the
+    /// connection.
+    pub fn set_ldapi_external_bind(mut self, ldapi_external_bind: bool) -> Self {
+        self.ldapi_external_bind = Some(ldapi_external_bind);
+        self
+    }
+
+    #[cfg(unix)]
+    fn ldapi_external_bind(&self) -> bool {
+        self.ldapi_external_bind.unwrap_or(false)
+    }
+
+    /// If `true`, connection attempts to the addresses of a server with both IPv6 and IPv4
+    /// addresses will alternate between the two families, starting with IPv6, and a new
+    /// attempt will be started if the previous one doesn't complete in the delay set by
+    /// [`set_happy_eyeballs_delay()`](#method.set_happy_eyeballs_delay), without waiting
+    /// for it to fail ("Happy Eyeballs", RFC 8305). The first established connection is used.
+    /// Defaults to `false`, meaning that the addresses are tried one by one, in the order
+    /// returned by the resolver.
+    pub fn set_happy_eyeballs(mut self, happy_eyeballs: bool) -> Self {
+        self.happy_eyeballs = Some(happy_eyeballs);
+        self
+    }
+
+    /// Set the delay between the starts of successive connection attempts when Happy
+    /// Eyeballs is turned on. Defaults to 250 milliseconds.
+    pub fn set_happy_eyeballs_delay(mut self, delay: Duration) -> Self {
+        self.happy_eyeballs_delay = Some(delay);
+        self
+    }
+
+    fn attempt_delay(&self) -> Option<Duration> {
+        if self.happy_eyeballs.unwrap_or(false) {
+            Some(
+                self.happy_eyeballs_delay
+                    .unwrap_or(DEFAULT_HAPPY_EYEBALLS_DELAY),
+            )
+        } else {
+            None
+        }
+    }
+
+    /// Set the local address of TCP connections, for clients which must use a particular
+    /// interface on a multi-homed host. The local port is chosen by the system. Defaults
+    /// to `None`, leaving the choice of the address to the system as well. A connection
+    /// to an address of a family different from the local address will fail.
+    pub fn set_local_bind_addr(mut self, addr: IpAddr) -> Self {
+        self.local_bind_addr = Some(addr);
+        self
+    }
+
+    /// Turn on TCP keepalive probes for the connection, with the given idle time before
+    /// the first probe, which is also used as the interval between the probes where the
+    /// platform allows setting it. Defaults to `None`, meaning no keepalive probes, which
+    /// can let a firewall silently drop an idle long-lived connection.
+    ///
+    /// The socket options set through `LdapConnSettings` apply to all TCP connections,
+    /// including the ones upgraded to TLS.
+    pub fn set_tcp_keepalive(mut self, keepalive: Option<Duration>) -> Self {
+        self.tcp_keepalive = keepalive;
+        self
+    }
+
+    /// If `true`, the `TCP_NODELAY` option will be set on the connection, disabling the
+    /// Nagle algorithm, which delays sending small requests. Defaults to `true`.
+    pub fn set_tcp_nodelay(mut self, nodelay: bool) -> Self {
+        self.tcp_nodelay = Some(nodelay);
+        self
+    }
+
+    fn tcp_nodelay(&self) -> bool {
+        self.tcp_nodelay.unwrap_or(true)
+    }
+
+    #[cfg(target_os = "linux")]
+    /// Set the `TCP_USER_TIMEOUT` option of the connection, which limits the time that
+    /// the sent data may remain unacknowledged before the system closes the connection.
+    /// Defaults to the system setting. Only available on Linux.
+    pub fn set_tcp_user_timeout(mut self, timeout: Duration) -> Self {
+        self.tcp_user_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the policy for retrying operations which fail with a transient error, such as
+    /// a busy server. Defaults to `None`, meaning that no operation is retried. See
+    /// [`RetryPolicy`](struct.RetryPolicy.html) for the operations which the policy
+    /// applies to.
+    pub fn set_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Set the observer which will be notified of every operation request and response
+    /// on the connection. See [`LdapObserver`](trait.LdapObserver.html) for details.
+    pub fn set_observer(mut self, observer: Arc<dyn LdapObserver>) -> Self {
+        self.observer = Some(Observer(observer));
+        self
+    }
+}
+
+const DEFAULT_SEARCH_QUEUE_SIZE: usize = 1024;
+pub(crate) const DEFAULT_MAX_BER_SIZE: usize = 8 * 1024 * 1024;
+const DEFAULT_HAPPY_EYEBALLS_DELAY: Duration = Duration::from_millis(250);
+const RETIRED_IDS: usize = 1024;
+
+// Search item which couldn't be queued because the queue was full.
+struct PendingItem {
+    id: RequestId,
+    tx: ItemSender,
+    item: (SearchItem, Vec<Control>),
+    remove: bool,
+}
+
+// Detector of the responses for message IDs which don't belong to any operation, with
+// the state of the policy for handling them. The IDs of the operations given up by the
+// client are kept in the order of retirement, and the oldest are forgotten.
+struct UnmatchedIds {
+    policy: Unmatched,
+    bucket: Option<TokenBucket>,
+    suppressed: u64,
+    retired: HashSet<RequestId>,
+    retired_order: VecDeque<RequestId>,
+}
+
+impl UnmatchedIds {
+    fn new(policy: Unmatched) -> Self {
+        let bucket = match policy {
+            Unmatched::WarnSampled { per_minute } => {
+                Some(TokenBucket::new(per_minute, Duration::from_secs(60)))
+            }
+            _ => None,
+        };
+        UnmatchedIds {
+            policy,
+            bucket,
+            suppressed: 0,
+            retired: HashSet::new(),
+            retired_order: VecDeque::new(),
+        }
+    }
+
+    // Note the ID of an operation abandoned, timed out or cancelled by the client, for
+    // which a late response isn't a protocol violation.
+    fn retire(&mut self, id: RequestId) {
+        if !self.retired.insert(id) {
+            return;
+        }
+        self.retired_order.push_back(id);
+        if self.retired_order.len() > RETIRED_IDS {
+            if let Some(oldest) = self.retired_order.pop_front() {
+                self.retired.remove(&oldest);
+            }
+        }
+    }
+
+    fn unmatched(&self, id: RequestId) -> bool {
+        !self.retired.contains(&id)
+    }
+
+    fn warn(&mut self, id: RequestId) {
+        let Some(ref mut bucket) = self.bucket else {
+            return;
+        };
+        if !bucket.take() {
+            self.suppressed += 1;
+            return;
+        }
+        match std::mem::take(&mut self.suppressed) {
+            0 => warn!("unmatched id: {}", id),
+            n => warn!("unmatched id: {} ({} more since the last warning)", id, n),
+        }
+    }
 }
 
 enum LoopMode {
-    #[allow(dead_code)]
+    #[cfg_attr(
+        not(any(unix, feature = "tls-native", feature = "tls-rustls")),
+        allow(dead_code)
+    )]
     SingleOp,
     Continuous,
 }
@@ -286,7 +776,7 @@ enum LoopMode {
 /// In this version of the interface, opening a connection with [`new()`](#method.new)
 /// will return a tuple consisting of the connection itself and an [`Ldap`](struct.Ldap.html)
 /// handle for performing the LDAP operations. The connection must be spawned on the active
-/// Tokio executor before using the handle. A convenience macro, [`drive!`](macro.drive.html), is
+/// executor before using the handle. A convenience macro, [`drive!`](macro.drive.html), is
 /// provided by the library. For the connection `conn`, it does the equivalent of:
 ///
 /// ```rust,no_run
@@ -308,7 +798,7 @@ enum LoopMode {
 ///
 /// The `Ldap` handle can be freely cloned, with each clone capable of launching a separate
 /// LDAP operation multiplexed on the original connection. Dropping the last handle will automatically
-/// close the connection.
+/// close the connection, after sending an Unbind if the application hasn't done so.
 ///
 /// Some connections need additional parameters, but providing many separate functions to initialize
 /// them, singly or in combination, would result in a cumbersome interface. Instead, connection
@@ -319,12 +809,21 @@ pub struct LdapConnAsync {
     msgmap: Arc<Mutex<(i32, HashSet<i32>)>>,
     resultmap: HashMap<i32, ResultSender>,
     searchmap: HashMap<i32, ItemSender>,
-    rx: mpsc::UnboundedReceiver<(RequestId, LdapOp, Tag, MaybeControls, ResultSender)>,
+    rx: mpsc::UnboundedReceiver<OpTuple>,
     id_scrub_rx: mpsc::UnboundedReceiver<RequestId>,
     misc_rx: mpsc::UnboundedReceiver<MiscSender>,
+    pending: Option<PendingItem>,
+    notifier: Notifier,
+    term_tx: Option<oneshot::Sender<StdResult<(), String>>>,
+    unbound: bool,
+    write_coalescing: Option<usize>,
+    unmatched: UnmatchedIds,
+    stats: ConnStats,
     stream: Framed<ConnType, LdapCodec>,
 }
 
+type OpTuple = (RequestId, LdapOp, Tag, MaybeControls, ResultSender);
+
 /// Drive the connection until its completion. __*__
 ///
 /// See the introduction of [LdapConnAsync](struct.LdapConnAsync.html) for the exact code produced by
@@ -332,7 +831,7 @@ pub struct LdapConnAsync {
 #[macro_export]
 macro_rules! drive {
     ($conn:expr) => {
-        $crate::tokio::spawn(async move {
+        $crate::rt::spawn(async move {
             if let Err(e) = $conn.drive().await {
                 $crate::log::warn!("LDAP connection error: {}", e);
             }
@@ -340,6 +839,186 @@ macro_rules! drive {
     };
 }
 
+// Address of a Unix domain socket from an `ldapi://` URL. The host part of the URL is
+// the percent-encoded socket path. A name starting with a NUL or an `@` (encoded as
+// `%00` or `%40`) denotes a socket in the Linux abstract namespace.
+#[cfg(unix)]
+#[derive(Debug, PartialEq)]
+enum UnixAddr {
+    Path(PathBuf),
+    Abstract(Vec<u8>),
+}
+
+#[cfg(unix)]
+impl UnixAddr {
+    fn from_url(url: &Url) -> Result<UnixAddr> {
+        if url.port().is_some() {
+            return Err(LdapError::PortInUnixPath);
+        }
+        if url.query().is_some() {
+            return Err(LdapError::QueryInUnixPath);
+        }
+        let path = url.host_str().unwrap_or("");
+        if path.is_empty() {
+            return Err(LdapError::EmptyUnixPath);
+        }
+        if path.contains(':') {
+            return Err(LdapError::PortInUnixPath);
+        }
+        let dec_path: Vec<u8> = percent_decode(path.as_bytes()).collect();
+        Ok(match dec_path.first() {
+            Some(0) | Some(b'@') => UnixAddr::Abstract(dec_path[1..].to_vec()),
+            _ => UnixAddr::Path(PathBuf::from(OsString::from_vec(dec_path))),
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn connect_abstract(name: &[u8]) -> Result<UnixStream> {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::{SocketAddr, UnixStream as StdUnixStream};
+
+    let connect = || {
+        let addr = SocketAddr::from_abstract_name(name)?;
+        let stream = StdUnixStream::connect_addr(&addr)?;
+        rt::unix_from_std(stream)
+    };
+    connect().map_err(|source| LdapError::ConnectFailed {
+        host: format!("@{}", String::from_utf8_lossy(name)),
+        addrs: vec![],
+        source,
+    })
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn connect_abstract(_name: &[u8]) -> Result<UnixStream> {
+    Err(LdapError::PlatformUnsupported {
+        what: String::from("abstract Unix domain socket"),
+    })
+}
+
+// Order the resolved addresses of a server for connection attempts. With Happy Eyeballs,
+// IPv6 and IPv4 addresses alternate, starting with IPv6, and the relative order within
+// each family is kept; otherwise, the resolver order is used unchanged.
+fn attempt_order(addrs: Vec<SocketAddr>, happy_eyeballs: bool) -> Vec<SocketAddr> {
+    if !happy_eyeballs {
+        return addrs;
+    }
+    let (v6, v4): (Vec<_>, Vec<_>) = addrs.into_iter().partition(SocketAddr::is_ipv6);
+    let (mut v6, mut v4) = (v6.into_iter(), v4.into_iter());
+    let mut ordered = Vec::with_capacity(v6.len() + v4.len());
+    loop {
+        match (v6.next(), v4.next()) {
+            (None, None) => break,
+            (a6, a4) => ordered.extend(a6.into_iter().chain(a4)),
+        }
+    }
+    ordered
+}
+
+fn set_socket_options(stream: &TcpStream, settings: &LdapConnSettings) -> io::Result<()> {
+    let socket = rt::sock_ref(stream);
+    socket.set_tcp_nodelay(settings.tcp_nodelay())?;
+    if let Some(time) = settings.tcp_keepalive {
+        let keepalive = TcpKeepalive::new().with_time(time);
+        #[cfg(any(target_os = "linux", target_os = "macos", windows))]
+        let keepalive = keepalive.with_interval(time);
+        socket.set_tcp_keepalive(&keepalive)?;
+    }
+    #[cfg(target_os = "linux")]
+    if let Some(timeout) = settings.tcp_user_timeout {
+        socket.set_tcp_user_timeout(Some(timeout))?;
+    }
+    Ok(())
+}
+
+// Connect to the first reachable address from the list. Without a delay, the addresses
+// are tried one after another. With a delay, a new attempt is started when the delay
+// expires or the latest attempt fails, whichever comes first, while earlier attempts
+// are still allowed to complete.
+//
+// With a deadline, each sequential attempt gets an equal share of the time remaining
+// for the addresses which haven't been tried yet, so that an address which never answers
+// doesn't use up the time meant for the others. Staggered attempts are all abandoned at
+// the deadline. Either way, running out of time fails with ConnectFailed.
+async fn connect_any(
+    host: &str,
+    addrs: Vec<SocketAddr>,
+    local: Option<IpAddr>,
+    delay: Option<Duration>,
+    deadline: Option<rt::Instant>,
+) -> Result<TcpStream> {
+    use futures_util::stream::FuturesUnordered;
+
+    let mut last_err = None;
+    let mut tried = Vec::with_capacity(addrs.len());
+    match delay {
+        None => {
+            for (n, addr) in addrs.iter().enumerate() {
+                tried.push(*addr);
+                let attempt = rt::connect_tcp(*addr, local);
+                let res = match deadline {
+                    Some(deadline) => {
+                        let remaining = deadline.saturating_duration_since(rt::Instant::now());
+                        rt::timeout(remaining / (addrs.len() - n) as u32, attempt)
+                            .await
+                            .unwrap_or_else(|_| Err(attempt_timed_out()))
+                    }
+                    None => attempt.await,
+                };
+                match res {
+                    Ok(stream) => return Ok(stream),
+                    Err(e) => last_err = Some(e),
+                }
+            }
+        }
+        Some(delay) => {
+            let staggered = async {
+                let mut pending = addrs.iter();
+                let mut attempts = FuturesUnordered::new();
+                loop {
+                    if let Some(addr) = pending.next() {
+                        tried.push(*addr);
+                        attempts.push(rt::connect_tcp(*addr, local));
+                    } else if attempts.is_empty() {
+                        return None;
+                    }
+                    tokio::select! {
+                        Some(res) = attempts.next() => match res {
+                            Ok(stream) => return Some(stream),
+                            Err(e) => last_err = Some(e),
+                        },
+                        _ = rt::sleep(delay), if pending.len() > 0 => (),
+                    }
+                }
+            };
+            match within(deadline, staggered).await {
+                Ok(Some(stream)) => return Ok(stream),
+                Ok(None) => (),
+                Err(_) => last_err = Some(attempt_timed_out()),
+            }
+        }
+    }
+    Err(LdapError::ConnectFailed {
+        host: String::from(host),
+        source: last_err
+            .unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no addresses for host")),
+        addrs: tried,
+    })
+}
+
+// Run the future to completion, or until the deadline, if there is one.
+async fn within<F: Future>(deadline: Option<rt::Instant>, future: F) -> Result<F::Output> {
+    Ok(match deadline {
+        Some(deadline) => rt::timeout_at(deadline, future).await?,
+        None => future.await,
+    })
+}
+
+fn attempt_timed_out() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "connection attempt timed out")
+}
+
 impl LdapConnAsync {
     /// Open a connection to an LDAP server specified by `url`, using
     /// `settings` to specify additional parameters.
@@ -354,11 +1033,15 @@ impl LdapConnAsync {
     /// library will recognize one or more URL schemes.
     ///
     /// The __ldap__ scheme, which uses a plain TCP connection, is always available. Unix-like
-    /// platforms also support __ldapi__, using Unix domain sockets. With the __tls__ or
+    /// platforms also support __ldapi__, using Unix domain sockets. The socket path is the
+    /// percent-encoded host part of the URL, e.g., `ldapi://%2fvar%2frun%2fldapi`. On Linux,
+    /// a socket in the abstract namespace is named with a leading NUL or `@`, which must
+    /// also be percent-encoded: `ldapi://%00name` or `ldapi://%40name`. A port or a query
+    /// string in an __ldapi__ URL is an error. With the __tls__ or
     /// __tls-rustls__ feature, the __ldaps__ scheme and StartTLS over __ldap__ are additionally
     /// supported.
     ///
-    /// The connection element in the returned tuple must be spawned on the current Tokio
+    /// The connection element in the returned tuple must be spawned on the current
     /// executor before using the `Ldap` element. See the introduction to this struct's
     /// documentation.
     pub async fn new(url: &str) -> Result<(Self, Ldap)> {
@@ -382,12 +1065,7 @@ impl LdapConnAsync {
             // But no issue in the Rust repo.
             let mut settings = settings;
             let timeout = settings.conn_timeout.take();
-            let conn_future = LdapConnAsync::new_tcp(url, settings);
-            Ok(if let Some(timeout) = timeout {
-                time::timeout(timeout, conn_future).await?
-            } else {
-                conn_future.await
-            }?)
+            LdapConnAsync::new_tcp(url, settings, timeout).await
         }
     }
 
@@ -396,27 +1074,117 @@ impl LdapConnAsync {
         Self::from_url_with_settings(LdapConnSettings::new(), url).await
     }
 
-    #[cfg(unix)]
-    async fn new_unix(url: &Url, _settings: LdapConnSettings) -> Result<(Self, Ldap)> {
-        let path = url.host_str().unwrap_or("");
-        if path.is_empty() {
-            return Err(LdapError::EmptyUnixPath);
+    /// Open a connection using the client configuration, which is read as described for
+    /// [`LdapConnSettings::from_env()`](struct.LdapConnSettings.html#method.from_env).
+    /// The configured URIs are tried in order until a connection succeeds; if none are
+    /// configured, `ldap://localhost` is used. The configuration defaults are returned
+    /// as the third element of the tuple.
+    pub async fn from_env() -> Result<(Self, Ldap, DefaultsInfo)> {
+        let (settings, defaults) = LdapConnSettings::from_env()?;
+        let mut uris = defaults.uris();
+        if uris.is_empty() {
+            uris.push("ldap://localhost");
         }
-        if path.contains(':') {
-            return Err(LdapError::PortInUnixPath);
+        let mut last_err = None;
+        for uri in uris {
+            match Self::with_settings(settings.clone(), uri).await {
+                Ok((conn, ldap)) => return Ok((conn, ldap, defaults)),
+                Err(e) => {
+                    debug!("connection to {} failed: {}", uri, e);
+                    last_err = Some(e);
+                }
+            }
+        }
+        Err(last_err.expect("at least one uri"))
+    }
+
+    /// Create a connection over a transport supplied by the caller, such as an SSH
+    /// tunnel or a WebSocket bridge, instead of opening one from an URL. The transport
+    /// must already be connected; it's used as it is, without any handshake. For TLS,
+    /// perform the handshake first and pass the resulting TLS stream. Under the
+    /// __runtime-async-std__ feature, the transport must also implement the Tokio I/O
+    /// traits, which can be achieved with the `compat` wrappers from `tokio-util`.
+    ///
+    /// The connection must be driven as with [`new()`](#method.new).
+    pub fn from_transport<T>(transport: T) -> (Self, Ldap)
+    where
+        T: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        Self::conn_pair(
+            ConnType::Boxed(Box::pin(transport)),
+            &LdapConnSettings::new(),
+        )
+    }
+
+    /// Create a connection over a transport supplied by the caller, using `settings` to
+    /// specify additional parameters. See [`from_transport()`](#method.from_transport).
+    ///
+    /// Settings which concern establishing a network connection, such as the TCP socket
+    /// options, Happy Eyeballs, the local bind address, and the TLS configuration, have
+    /// no effect. The connection timeout and StartTLS would suggest a guarantee which
+    /// can't be given, so setting them returns
+    /// [`LdapError::TransportSetting`](result/enum.LdapError.html#variant.TransportSetting).
+    pub fn from_transport_with_settings<T>(
+        settings: LdapConnSettings,
+        transport: T,
+    ) -> Result<(Self, Ldap)>
+    where
+        T: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        if settings.conn_timeout.is_some() {
+            return Err(LdapError::TransportSetting {
+                setting: "connection timeout",
+            });
+        }
+        if settings.starttls() {
+            return Err(LdapError::TransportSetting {
+                setting: "StartTLS",
+            });
+        }
+        Ok(Self::conn_pair(
+            ConnType::Boxed(Box::pin(transport)),
+            &settings,
+        ))
+    }
+
+    #[cfg(unix)]
+    async fn new_unix(url: &Url, settings: LdapConnSettings) -> Result<(Self, Ldap)> {
+        let stream = match UnixAddr::from_url(url)? {
+            UnixAddr::Path(path) => {
+                rt::connect_unix(&path)
+                    .await
+                    .map_err(|source| LdapError::ConnectFailed {
+                        host: path.to_string_lossy().into_owned(),
+                        addrs: vec![],
+                        source,
+                    })?
+            }
+            UnixAddr::Abstract(name) => connect_abstract(&name)?,
+        };
+        let (mut conn, mut ldap) = Self::conn_pair(ConnType::Unix(stream), &settings);
+        if settings.ldapi_external_bind() {
+            let (conn_res, res) =
+                tokio::try_join!(conn.turn(LoopMode::SingleOp), ldap.sasl_external_bind())?;
+            conn = conn_res;
+            res.success()?;
         }
-        let dec_path = percent_decode(path.as_bytes()).decode_utf8_lossy();
-        let stream = UnixStream::connect(dec_path.as_ref()).await?;
-        Ok(Self::conn_pair(ConnType::Unix(stream)))
+        Ok((conn, ldap))
     }
 
     #[cfg(not(unix))]
     async fn new_unix(_url: &Url, _settings: LdapConnSettings) -> Result<(Self, Ldap)> {
-        unimplemented!("no Unix domain sockets on non-Unix platforms");
+        Err(LdapError::PlatformUnsupported {
+            what: String::from("ldapi scheme"),
+        })
     }
 
     #[allow(unused_mut)]
-    async fn new_tcp(url: &Url, mut settings: LdapConnSettings) -> Result<(Self, Ldap)> {
+    async fn new_tcp(
+        url: &Url,
+        mut settings: LdapConnSettings,
+        timeout: Option<Duration>,
+    ) -> Result<(Self, Ldap)> {
+        let deadline = timeout.map(|timeout| rt::Instant::now() + timeout);
         let mut port = 389;
         let scheme = match url.scheme() {
             s @ "ldap" => {
@@ -432,54 +1200,84 @@ impl LdapConnAsync {
                 port = 636;
                 s
             }
+            #[cfg(not(any(feature = "tls-native", feature = "tls-rustls")))]
+            "ldaps" => {
+                return Err(LdapError::FeatureNotEnabled {
+                    what: String::from("ldaps scheme"),
+                    feature: "tls",
+                })
+            }
             s => return Err(LdapError::UnknownScheme(String::from(s))),
         };
         if let Some(url_port) = url.port() {
             port = url_port;
         }
-        let (_hostname, host_port) = match url.host_str() {
+        let (hostname, host_port) = match url.host_str() {
             Some(h) if !h.is_empty() => (h, format!("{}:{}", h, port)),
             Some(h) if !h.is_empty() => ("localhost", format!("localhost:{}", port)),
             _ => panic!("unexpected None from url.host_str()"),
         };
-        let stream = TcpStream::connect(host_port.as_str()).await?;
-        let (mut conn, mut ldap) = Self::conn_pair(ConnType::Tcp(stream));
-        match scheme {
-            "ldap" => (),
-            #[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
-            s @ "ldaps" | s @ "starttls" => {
-                if s == "starttls" {
-                    let (tx, rx) = oneshot::channel();
-                    tokio::spawn(async move {
-                        conn.single_op(tx).await;
-                    });
-                    let res =
-                        tokio::try_join!(rx.map_err(LdapError::from), ldap.extended(StartTLS));
-                    match res {
-                        Ok((conn_res, res)) => {
-                            conn = conn_res?;
-                            res.success()?;
-                        }
-                        Err(e) => return Err(e),
+        let addrs = match within(deadline, rt::lookup_host(host_port.as_str())).await? {
+            Ok(addrs) => attempt_order(addrs, settings.attempt_delay().is_some()),
+            Err(source) => {
+                return Err(LdapError::ConnectFailed {
+                    host: host_port.clone(),
+                    addrs: vec![],
+                    source,
+                })
+            }
+        };
+        let stream = connect_any(
+            &host_port,
+            addrs,
+            settings.local_bind_addr,
+            settings.attempt_delay(),
+            deadline,
+        )
+        .await?;
+        set_socket_options(&stream, &settings)?;
+        let (mut conn, mut ldap) = Self::conn_pair(ConnType::Tcp(stream), &settings);
+        ldap.host_port = Arc::new(Some((hostname.to_ascii_lowercase(), port)));
+        within(deadline, async move {
+            match scheme {
+                "ldap" => (),
+                #[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
+                s @ "ldaps" | s @ "starttls" => {
+                    if s == "starttls" {
+                        let (tx, rx) = oneshot::channel();
+                        rt::spawn(async move {
+                            conn.single_op(tx).await;
+                        });
+                        let res =
+                            tokio::try_join!(rx.map_err(LdapError::from), ldap.extended(StartTLS));
+                        conn = match res {
+                            Ok((Ok(conn), res)) => match res.success() {
+                                Ok(_) => conn,
+                                Err(e) => return Err(conn.poison(e)),
+                            },
+                            Ok((Err(e), _)) | Err(e) => return Err(ldap.poison(e)),
+                        };
                     }
+                    let parts = conn.stream.into_parts();
+                    let tls_stream = if let ConnType::Tcp(stream) = parts.io {
+                        // The server may have switched to TLS, so a plaintext connection
+                        // can't be resumed after a failed upgrade.
+                        match LdapConnAsync::create_tls_stream(settings, hostname, stream).await {
+                            Ok(tls_stream) => tls_stream,
+                            Err(e) => return Err(ldap.poison(e)),
+                        }
+                    } else {
+                        panic!("underlying stream not TCP");
+                    };
+                    ldap.tls_info = Arc::new(Some(LdapConnAsync::get_tls_info(&tls_stream)));
+                    conn.stream = parts.codec.framed(ConnType::Tls(tls_stream));
+                    ldap.has_tls = true;
                 }
-                let parts = conn.stream.into_parts();
-                let tls_stream = if let ConnType::Tcp(stream) = parts.io {
-                    LdapConnAsync::create_tls_stream(settings, _hostname, stream).await?
-                } else {
-                    panic!("underlying stream not TCP");
-                };
-                #[cfg(any(feature = "gssapi", feature = "ntlm"))]
-                {
-                    ldap.tls_endpoint_token =
-                        Arc::new(LdapConnAsync::get_tls_endpoint_token(&tls_stream));
-                }
-                conn.stream = parts.codec.framed(ConnType::Tls(tls_stream));
-                ldap.has_tls = true;
+                _ => unreachable!(),
             }
-            _ => unimplemented!(),
-        }
-        Ok((conn, ldap))
+            Ok((conn, ldap))
+        })
+        .await?
     }
 
     #[cfg(feature = "tls-native")]
@@ -488,14 +1286,11 @@ impl LdapConnAsync {
         hostname: &str,
         stream: TcpStream,
     ) -> Result<TlsStream<TcpStream>> {
-        let connector = match settings.connector {
-            Some(connector) => connector,
-            None => LdapConnAsync::create_connector(&settings),
-        };
+        let connector = LdapConnAsync::tls_connector(settings)?;
         TokioTlsConnector::from(connector)
             .connect(hostname, stream)
             .await
-            .map_err(LdapError::from)
+            .map_err(handshake_failed)
     }
 
     #[cfg(feature = "tls-rustls")]
@@ -505,10 +1300,7 @@ impl LdapConnAsync {
         stream: TcpStream,
     ) -> Result<TlsStream<TcpStream>> {
         let no_tls_verify = settings.no_tls_verify;
-        let config = match settings.config {
-            Some(config) => config,
-            None => LdapConnAsync::create_config(&settings),
-        };
+        let config = LdapConnAsync::tls_config(settings)?;
         TokioTlsConnector::from(config)
             .connect(
                 ServerName::try_from(hostname)
@@ -527,78 +1319,129 @@ impl LdapConnAsync {
                 stream,
             )
             .await
-            .map_err(LdapError::from)
+            .map_err(handshake_failed)
     }
 
     #[cfg(feature = "tls-rustls")]
-    fn create_config(settings: &LdapConnSettings) -> Arc<ClientConfig> {
-        let mut config = ClientConfig::builder()
-            .with_root_certificates(CACERTS.clone())
-            .with_no_client_auth();
+    fn tls_config(settings: LdapConnSettings) -> Result<Arc<ClientConfig>> {
+        match settings.config {
+            Some(_) if settings.client_cert.is_some() || !settings.root_certs_pem.is_empty() => {
+                Err(LdapError::IdentityWithCustomTls)
+            }
+            Some(config) => Ok(config),
+            None => LdapConnAsync::create_config(&settings),
+        }
+    }
+
+    #[cfg(feature = "tls-rustls")]
+    fn create_config(settings: &LdapConnSettings) -> Result<Arc<ClientConfig>> {
+        let mut roots = CACERTS.clone();
+        for pem in &settings.root_certs_pem {
+            for cert in rustls_pemfile::certs(&mut &pem[..]) {
+                roots.add(cert?)?;
+            }
+        }
+        let builder = ClientConfig::builder().with_root_certificates(roots);
+        let mut config = match settings.client_cert {
+            Some((ref cert_chain, ref key)) => {
+                builder.with_client_auth_cert(cert_chain.clone(), key.clone_key())?
+            }
+            None => builder.with_no_client_auth(),
+        };
         if settings.no_tls_verify {
             let no_cert_verifier = NoCertVerification;
             config
                 .dangerous()
                 .set_certificate_verifier(Arc::new(no_cert_verifier));
         }
-        Arc::new(config)
+        Ok(Arc::new(config))
+    }
+
+    #[cfg(feature = "tls-native")]
+    fn tls_connector(settings: LdapConnSettings) -> Result<TlsConnector> {
+        match settings.connector {
+            Some(_) if settings.identity.is_some() || !settings.root_certs_pem.is_empty() => {
+                Err(LdapError::IdentityWithCustomTls)
+            }
+            Some(connector) => Ok(connector),
+            None => LdapConnAsync::create_connector(&settings),
+        }
     }
 
     #[cfg(feature = "tls-native")]
-    fn create_connector(settings: &LdapConnSettings) -> TlsConnector {
+    fn create_connector(settings: &LdapConnSettings) -> Result<TlsConnector> {
         let mut builder = TlsConnector::builder();
         if settings.no_tls_verify {
             builder.danger_accept_invalid_certs(true);
         }
-        builder.build().expect("connector")
+        if let Some(ref identity) = settings.identity {
+            builder.identity(identity.clone());
+        }
+        for pem in &settings.root_certs_pem {
+            for cert in Certificate::stack_from_pem(pem)? {
+                builder.add_root_certificate(cert);
+            }
+        }
+        Ok(builder.build()?)
     }
 
-    #[cfg(all(any(feature = "gssapi", feature = "ntlm"), feature = "tls-native"))]
-    fn get_tls_endpoint_token(s: &TlsStream<TcpStream>) -> Option<Vec<u8>> {
-        match s.get_ref().tls_server_end_point() {
-            Ok(ep) => {
-                if ep.is_none() {
-                    warn!("no endpoint token returned");
-                }
-                ep
-            }
+    #[cfg(feature = "tls-native")]
+    fn get_tls_info(s: &TlsStream<TcpStream>) -> TlsInfo {
+        let peer_der = match s.get_ref().peer_certificate() {
+            Ok(Some(cert)) => cert.to_der().unwrap_or_else(|e| {
+                warn!("error encoding peer certificate: {}", e);
+                vec![]
+            }),
+            Ok(None) => vec![],
             Err(e) => {
-                warn!("error calculating endpoint token: {}", e);
-                None
+                warn!("error retrieving peer certificate: {}", e);
+                vec![]
             }
+        };
+        // native-tls doesn't expose the negotiated parameters
+        TlsInfo {
+            peer_der,
+            protocol: None,
+            cipher: None,
         }
     }
 
-    #[cfg(all(any(feature = "gssapi", feature = "ntlm"), feature = "tls-rustls"))]
-    fn get_tls_endpoint_token(s: &TlsStream<TcpStream>) -> Option<Vec<u8>> {
-        use x509_parser::prelude::*;
-
-        if let Some(certs) = s.get_ref().1.peer_certificates() {
-            let peer_cert = &certs[0];
-            let leaf = match X509Certificate::from_der(peer_cert) {
-                Ok(leaf) => leaf,
-                Err(e) => {
-                    warn!("error parsing peer certificate: {}", e);
-                    return None;
-                }
-            };
-            let sigalg = leaf.1.signature_algorithm.algorithm.to_id_string();
-            if let Some(alg) = ENDPOINT_ALG.get(&*sigalg) {
-                Some(Vec::from(digest(alg, peer_cert).as_ref()))
-            } else {
-                warn!("unknown signature algorithm, oid={}", sigalg);
-                None
-            }
-        } else {
-            warn!("no peer certificates found");
-            None
+    #[cfg(feature = "tls-rustls")]
+    fn get_tls_info(s: &TlsStream<TcpStream>) -> TlsInfo {
+        let conn = s.get_ref().1;
+        let peer_der = conn
+            .peer_certificates()
+            .and_then(|certs| certs.first())
+            .map(|cert| cert.to_vec())
+            .unwrap_or_default();
+        let protocol = conn.protocol_version().map(|v| {
+            v.as_str()
+                .map(String::from)
+                .unwrap_or_else(|| format!("{:?}", v))
+        });
+        let cipher = conn.negotiated_cipher_suite().map(|cs| {
+            let suite = cs.suite();
+            suite
+                .as_str()
+                .map(String::from)
+                .unwrap_or_else(|| format!("{:?}", suite))
+        });
+        TlsInfo {
+            peer_der,
+            protocol,
+            cipher,
         }
     }
 
-    fn conn_pair(ctype: ConnType) -> (Self, Ldap) {
+    fn conn_pair(ctype: ConnType, settings: &LdapConnSettings) -> (Self, Ldap) {
         #[cfg(feature = "gssapi")]
         let client_ctx = Arc::new(Mutex::new(None));
+        let stats = ConnStats::new();
         let codec = LdapCodec {
+            max_ber_size: settings.max_ber_size(),
+            wire_trace: settings.wire_trace(),
+            keep_raw: settings.keep_raw_frames(),
+            stats: stats.clone(),
             #[cfg(feature = "gssapi")]
             has_decoded_data: false,
             #[cfg(feature = "gssapi")]
@@ -618,6 +1461,13 @@ impl LdapConnAsync {
             rx,
             id_scrub_rx,
             misc_rx,
+            pending: None,
+            notifier: Notifier::new(),
+            term_tx: None,
+            unbound: false,
+            write_coalescing: settings.write_coalescing,
+            unmatched: UnmatchedIds::new(settings.unmatched_response_policy()),
+            stats: stats.clone(),
             stream: codec.framed(ctype),
         };
         let ldap = Ldap {
@@ -629,11 +1479,25 @@ impl LdapConnAsync {
             sasl_param,
             #[cfg(feature = "gssapi")]
             client_ctx,
-            #[cfg(any(feature = "gssapi", feature = "ntlm"))]
-            tls_endpoint_token: Arc::new(None),
+            tls_info: Arc::new(None),
+            host_port: Arc::new(None),
             has_tls: false,
+            search_queue_size: settings.search_queue_size(),
+            abandon_on_timeout: settings.abandon_on_timeout(),
+            strict_validation: settings.strict_validation(),
+            canonical_sets: settings.canonical_sets(),
+            notifier: conn.notifier.handle(),
+            stats,
+            observer: settings.observer.clone(),
+            cancel: None,
+            retry_policy: settings.retry_policy.clone().map(Arc::new),
+            retries: false,
+            capabilities: Arc::new(Mutex::new(None)),
+            no_probe: false,
             last_id: 0,
+            last_started: None,
             timeout: None,
+            deadline: None,
             controls: None,
             search_opts: None,
         };
@@ -677,8 +1541,46 @@ impl LdapConnAsync {
     }
 
     /// Repeatedly poll the connection until it exits.
-    pub async fn drive(self) -> Result<()> {
-        self.turn(LoopMode::Continuous).await.map(|_| ())
+    ///
+    /// If the connection is closed by the server or fails, any operations still in
+    /// progress are completed with the result code 52 (unavailable) and a diagnostic
+    /// message describing the cause.
+    pub async fn drive(mut self) -> Result<()> {
+        let term_tx = self.term_tx.take();
+        let closer = self.notifier.take_closer();
+        let res = self.turn(LoopMode::Continuous).await.map(|_| ());
+        let outcome = res.as_ref().map(|_| ()).map_err(|e| e.to_string());
+        if let Some(term_tx) = term_tx {
+            let _ = term_tx.send(outcome.clone());
+        }
+        if let Some(closer) = closer {
+            let _ = closer.send(Some(outcome));
+        }
+        res
+    }
+
+    /// Return a receiver for the outcome of [`drive()`](#method.drive).
+    ///
+    /// When the connection terminates, the receiver gets `Ok(())` if it was closed in an
+    /// orderly fashion, or the description of the error returned by `drive()`. This
+    /// makes the cause of termination observable even when the connection is spawned by
+    /// [`drive!`](macro.drive.html). Only the most recently obtained receiver is notified.
+    pub fn termination_handle(&mut self) -> oneshot::Receiver<StdResult<(), String>> {
+        let (tx, rx) = oneshot::channel();
+        self.term_tx = Some(tx);
+        rx
+    }
+
+    // Tear down the connection after a failed TLS upgrade. The failure is recorded for
+    // all handles, and the outstanding operations are dropped along with the channels,
+    // so that they fail with the same error instead of waiting for responses which
+    // can't arrive.
+    fn poison(mut self, e: LdapError) -> LdapError {
+        let e = self.notifier.handle().poison(e);
+        self.pending = None;
+        self.resultmap.clear();
+        self.searchmap.clear();
+        e
     }
 
     #[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
@@ -688,53 +1590,306 @@ impl LdapConnAsync {
         }
     }
 
+    // Write a streamed request directly to the socket, bypassing the codec. Buffered
+    // frames are flushed first, so that the order of requests is preserved.
+    async fn write_streamed(&mut self, msg: Segments) -> Result<()> {
+        self.stream.flush().await?;
+        let mut buf = vec![0u8; STREAM_CHUNK_SIZE];
+        let io = self.stream.get_mut();
+        for part in msg.parts {
+            match part {
+                Segment::Bytes(bytes) => {
+                    io.write_all(&bytes).await?;
+                    self.stats.written(bytes.len());
+                }
+                Segment::Reader(len, reader) => {
+                    copy_value(io, reader, len, &mut buf).await?;
+                    self.stats.written(len as usize);
+                }
+            }
+        }
+        io.flush().await?;
+        Ok(())
+    }
+
+    // Write a request to the socket. If `buffered` is true, the request may be left in
+    // the write buffer, and the caller must flush it. Return `true` if the request is
+    // waiting for its response through the result map.
+    async fn dispatch(&mut self, op_tuple: OpTuple, buffered: bool) -> Result<bool> {
+        let (id, op, tag, controls, tx) = op_tuple;
+        let op = match op {
+            LdapOp::Streamed(req) => {
+                let (msg, status) = req.into_message(id, controls);
+                match self.write_streamed(msg).await {
+                    Ok(()) => {
+                        self.stats.op_sent();
+                        let _ = status.send(Ok(()));
+                        self.resultmap.insert(id, tx);
+                        return Ok(true);
+                    }
+                    Err(e) => {
+                        warn!("streamed request error, op={}: {}", id, e);
+                        let _ = status.send(Err(e));
+                        self.abort_pending("streamed request aborted");
+                        return Err(LdapError::from(io::Error::other(
+                            "streamed request aborted",
+                        )));
+                    }
+                }
+            }
+            op => op,
+        };
+        if let LdapOp::Unbind = op {
+            if self.unbound {
+                let _ = tx.send((
+                    Tag::Null(Null {
+                        ..Default::default()
+                    }),
+                    vec![],
+                ));
+                return Ok(false);
+            }
+        }
+        if let LdapOp::Search(ref search_tx) = op {
+            self.searchmap.insert(id, search_tx.clone());
+        }
+        // Unbind is followed by closing the socket, so it's always flushed.
+        let res = match op {
+            LdapOp::Unbind => self.stream.send((id, tag, controls)).await,
+            _ if buffered => self.stream.feed((id, tag, controls)).await,
+            _ => self.stream.send((id, tag, controls)).await,
+        };
+        if let Err(e) = res {
+            warn!("socket send error: {}", e);
+            let cause = format!("socket send error: {}", e);
+            if let Err(e) = tx.send((unavailable(&cause), vec![])) {
+                warn!("ldap result send error: {:?}", e);
+            }
+            self.abort_pending(&cause);
+            return Err(LdapError::from(e));
+        }
+        self.stats.op_sent();
+        match op {
+            LdapOp::Single => {
+                self.resultmap.insert(id, tx);
+                return Ok(true);
+            }
+            LdapOp::Search(_) => (),
+            LdapOp::Abandon(msgid) => {
+                self.drop_pending(msgid);
+                self.resultmap.remove(&msgid);
+                self.searchmap.remove(&msgid);
+                self.unmatched.retire(msgid);
+                let mut msgmap = self.msgmap.lock().expect("msgmap mutex (abandon)");
+                msgmap.1.remove(&id);
+            }
+            LdapOp::Streamed(_) => unreachable!(),
+            LdapOp::Unbind => {
+                self.unbound = true;
+                if let Err(e) = self.stream.get_mut().shutdown().await {
+                    warn!("socket shutdown error: {}", e);
+                }
+                if let Err(e) = self.stream.close().await {
+                    warn!("socket close error: {}", e);
+                }
+            }
+        }
+        if let Err(e) = tx.send((
+            Tag::Null(Null {
+                ..Default::default()
+            }),
+            vec![],
+        )) {
+            warn!("ldap null result send error: {:?}", e);
+        }
+        Ok(false)
+    }
+
+    // Unbind and close the socket after the last handle is gone, unless the Unbind was
+    // already sent through a handle. Errors are only logged, since nobody is left to
+    // receive them.
+    async fn close_gracefully(&mut self) {
+        if self.unbound {
+            return;
+        }
+        self.unbound = true;
+        let id = {
+            let mut msgmap = self.msgmap.lock().expect("msgmap mutex (close)");
+            match alloc_msgid(&mut msgmap, RequestId::MAX) {
+                Ok(id) => id,
+                Err(e) => {
+                    warn!("unbind not sent: {}", e);
+                    return;
+                }
+            }
+        };
+        let req = Tag::Null(Null {
+            id: 2,
+            class: TagClass::Application,
+            inner: (),
+        });
+        if let Err(e) = self.stream.send((id, req, None)).await {
+            warn!("unbind send error: {}", e);
+            return;
+        }
+        self.stats.op_sent();
+        if let Err(e) = self.stream.get_mut().shutdown().await {
+            warn!("socket shutdown error: {}", e);
+        }
+    }
+
+    // Forget a Search which has completed or whose stream is gone, releasing its ID.
+    fn end_search(&mut self, id: RequestId) {
+        self.searchmap.remove(&id);
+        let mut msgmap = self.msgmap.lock().expect("msgmap mutex (end search)");
+        msgmap.1.remove(&id);
+    }
+
+    // Forget the operations whose handles are gone without waiting for the response,
+    // such as those whose futures were dropped, releasing their IDs. A handle asks for
+    // this by scrubbing the ID zero, which is never allocated.
+    fn reconcile(&mut self) {
+        let mut gone: Vec<_> = self
+            .resultmap
+            .iter()
+            .filter(|(_, tx)| tx.is_closed())
+            .map(|(id, _)| *id)
+            .collect();
+        gone.extend(
+            self.searchmap
+                .iter()
+                .filter(|(_, tx)| tx.is_closed())
+                .map(|(id, _)| *id),
+        );
+        if gone.is_empty() {
+            return;
+        }
+        debug!("releasing {} abandoned message IDs", gone.len());
+        let mut msgmap = self.msgmap.lock().expect("msgmap mutex (reconcile)");
+        for id in gone {
+            if self
+                .pending
+                .as_ref()
+                .is_some_and(|pending| pending.id == id)
+            {
+                self.pending = None;
+            }
+            self.resultmap.remove(&id);
+            self.searchmap.remove(&id);
+            self.unmatched.retire(id);
+            msgmap.1.remove(&id);
+        }
+    }
+
+    // Discard the pending search item of the operation with the given id, if any.
+    fn drop_pending(&mut self, id: RequestId) {
+        if self
+            .pending
+            .as_ref()
+            .is_some_and(|pending| pending.id == id)
+        {
+            self.pending = None;
+        }
+    }
+
+    // Complete all outstanding operations with an "unavailable" result, since no
+    // further responses can arrive.
+    fn abort_pending(&mut self, cause: &str) {
+        for (id, tx) in self.resultmap.drain() {
+            if tx.send((unavailable(cause), vec![])).is_err() {
+                debug!("op {} gone before connection teardown", id);
+            }
+        }
+        let mut pending = self.pending.take();
+        for (id, tx) in self.searchmap.drain() {
+            // An undelivered item must precede the final result, unless it's the
+            // real one.
+            let mut items = vec![];
+            let mut done = false;
+            if pending.as_ref().is_some_and(|pending| pending.id == id) {
+                let pending = pending.take().expect("pending item");
+                items.push(pending.item);
+                done = pending.remove;
+            }
+            if !done {
+                items.push((SearchItem::Done(unavailable(cause).into()), vec![]));
+            }
+            let mut items = items.into_iter();
+            while let Some(item) = items.next() {
+                match tx.try_send(item) {
+                    Ok(()) => (),
+                    Err(TrySendError::Closed(_)) => break,
+                    Err(TrySendError::Full(item)) => {
+                        // The stream is behind; let it drain at its own pace.
+                        rt::spawn(async move {
+                            for item in std::iter::once(item).chain(items) {
+                                if tx.send(item).await.is_err() {
+                                    break;
+                                }
+                            }
+                        });
+                        break;
+                    }
+                }
+            }
+        }
+        self.msgmap
+            .lock()
+            .expect("msgmap mutex (teardown)")
+            .1
+            .clear();
+    }
+
     async fn turn(mut self, mode: LoopMode) -> Result<Self> {
         loop {
+            if let Some(e) = self.notifier.poisoned() {
+                return Err(self.poison(e));
+            }
+            self.stats
+                .sample(self.resultmap.len(), self.searchmap.len(), self.rx.len());
+            let pending_tx = self.pending.as_ref().map(|pending| pending.tx.clone());
             tokio::select! {
                 req_id = self.id_scrub_rx.recv() => {
-                    if let Some(req_id) = req_id {
+                    if req_id == Some(0) {
+                        self.reconcile();
+                    } else if let Some(req_id) = req_id {
+                        self.drop_pending(req_id);
                         self.resultmap.remove(&req_id);
                         self.searchmap.remove(&req_id);
+                        self.unmatched.retire(req_id);
                         let mut msgmap = self.msgmap.lock().expect("msgmap mutex (id_scrub)");
                         msgmap.1.remove(&req_id);
                     }
                 },
                 op_tuple = self.rx.recv() => {
-                    if let Some((id, op, tag, controls, tx)) = op_tuple {
-                        if let LdapOp::Search(ref search_tx) = op {
-                            self.searchmap.insert(id, search_tx.clone());
+                    let Some(op_tuple) = op_tuple else {
+                        self.close_gracefully().await;
+                        break;
+                    };
+                    // In the single-op mode, requests queued after the current one must
+                    // wait for the connection to be upgraded.
+                    let coalesce = match mode {
+                        LoopMode::SingleOp => None,
+                        LoopMode::Continuous => self.write_coalescing,
+                    };
+                    let mut awaiting = self.dispatch(op_tuple, coalesce.is_some()).await?;
+                    if let Some(max) = coalesce {
+                        let mut queued = 1;
+                        while queued < max && !self.unbound {
+                            match self.rx.try_recv() {
+                                Ok(op_tuple) => awaiting = self.dispatch(op_tuple, true).await?,
+                                Err(_) => break,
+                            }
+                            queued += 1;
                         }
-                        if let Err(e) = self.stream.send((id, tag, controls)).await {
-                            warn!("socket send error: {}", e);
+                        if let Err(e) = self.stream.flush().await {
+                            warn!("socket flush error: {}", e);
+                            self.abort_pending(&format!("socket send error: {}", e));
                             return Err(LdapError::from(e));
-                        } else {
-                            match op {
-                                LdapOp::Single => {
-                                    self.resultmap.insert(id, tx);
-                                    continue;
-                                },
-                                LdapOp::Search(_) => (),
-                                LdapOp::Abandon(msgid) => {
-                                    self.resultmap.remove(&msgid);
-                                    self.searchmap.remove(&msgid);
-                                    let mut msgmap = self.msgmap.lock().expect("msgmap mutex (abandon)");
-                                    msgmap.1.remove(&id);
-                                },
-                                LdapOp::Unbind => {
-                                    if let Err(e) = self.stream.get_mut().shutdown().await {
-                                        warn!("socket shutdown error: {}", e);
-                                    }
-                                    if let Err(e) = self.stream.close().await {
-                                        warn!("socket close error: {}", e);
-                                    }
-                                },
-                            }
-                            if let Err(e) = tx.send((Tag::Null(Null { ..Default::default() }), vec![])) {
-                                warn!("ldap null result send error: {:?}", e);
-                            }
                         }
-                    } else {
-                        break;
+                    }
+                    if awaiting {
+                        continue;
                     }
                 },
                 misc = self.misc_rx.recv() => {
@@ -753,45 +1908,116 @@ impl LdapConnAsync {
                             },
                         }
                     } else {
+                        self.close_gracefully().await;
                         break;
                     }
                 },
-                resp = self.stream.next() => {
-                    let (id, (tag, controls)) = match resp {
-                        None => break,
+                permit = async { pending_tx.expect("pending tx").reserve_owned().await }, if pending_tx.is_some() => {
+                    let pending = self.pending.take().expect("pending item");
+                    match permit {
+                        Ok(permit) => {
+                            permit.send(pending.item);
+                            if pending.remove {
+                                self.end_search(pending.id);
+                            }
+                        }
+                        Err(e) => {
+                            warn!("ldap search item send error, op={}: {:?}", pending.id, e);
+                            self.end_search(pending.id);
+                        }
+                    }
+                },
+                resp = self.stream.next(), if self.pending.is_none() => {
+                    let (id, (tag, controls), raw) = match resp {
+                        None => {
+                            self.abort_pending("connection closed by server");
+                            break;
+                        }
                         Some(Err(e)) => {
                             warn!("socket receive error: {}", e);
+                            self.abort_pending(&format!("socket receive error: {}", e));
+                            if e.get_ref().is_some_and(|inner| inner.is::<LdapError>()) {
+                                let inner = e.into_inner().expect("inner error");
+                                return Err(*inner.downcast::<LdapError>().expect("ldap error"));
+                            }
                             return Err(LdapError::from(e));
                         },
                         Some(Ok(resp)) => resp,
                     };
                     if let Some(tx) = self.searchmap.get(&id) {
-                        let protoop = if let Tag::StructureTag(protoop) = tag {
-                            protoop
-                        } else {
-                            panic!("unmatched tag structure: {:?}", tag);
-                        };
-                        let (item, mut remove) = match protoop.id {
-                            4 | 25 => (SearchItem::Entry(protoop), false),
-                            5 => (SearchItem::Done(Tag::StructureTag(protoop).into()), true),
-                            19 => (SearchItem::Referral(protoop), false),
-                            _ => panic!("unrecognized op id: {}", protoop.id),
+                        // An op which doesn't belong to a Search fails the operation, but
+                        // leaves the connection usable for the others.
+                        let (item, mut remove) = match tag {
+                            Tag::StructureTag(protoop) => match protoop.id {
+                                4 | 25 => (SearchItem::Entry(protoop, raw), false),
+                                5 => {
+                                    self.stats.result_received();
+                                    (SearchItem::Done(Tag::StructureTag(protoop).into()), true)
+                                }
+                                19 => (SearchItem::Referral(protoop, raw), false),
+                                tag_id => {
+                                    warn!("unexpected protocol op in search, op={}: tag {}", id, tag_id);
+                                    let err = LdapError::UnexpectedPdu { msgid: id, tag_id };
+                                    (SearchItem::Failed(err), true)
+                                }
+                            },
+                            tag => {
+                                warn!("unmatched tag structure in search, op={}: {:?}", id, tag);
+                                let err = LdapError::UnexpectedPdu { msgid: id, tag_id: 0 };
+                                (SearchItem::Failed(err), true)
+                            }
                         };
-                        if let Err(e) = tx.send((item, controls)) {
-                            warn!("ldap search item send error, op={}: {:?}", id, e);
-                            remove = true;
+                        if let SearchItem::Failed(_) = item {
+                            let mut msgmap = self.msgmap.lock().expect("msgmap mutex (stream rx)");
+                            msgmap.1.remove(&id);
+                        }
+                        match tx.try_send((item, controls)) {
+                            Ok(()) => (),
+                            Err(TrySendError::Full(item)) => {
+                                self.pending = Some(PendingItem {
+                                    id,
+                                    tx: tx.clone(),
+                                    item,
+                                    remove,
+                                });
+                                remove = false;
+                            }
+                            Err(e) => {
+                                warn!("ldap search item send error, op={}: {:?}", id, e);
+                                self.unmatched.retire(id);
+                                remove = true;
+                            }
                         }
                         if remove {
-                            self.searchmap.remove(&id);
+                            self.end_search(id);
                         }
                     } else if let Some(tx) = self.resultmap.remove(&id) {
+                        self.stats.result_received();
                         if let Err(e) = tx.send((tag, controls)) {
                             warn!("ldap result send error: {:?}", e);
                         }
                         let mut msgmap = self.msgmap.lock().expect("msgmap mutex (stream rx)");
                         msgmap.1.remove(&id);
+                    } else if id == 0 && matches!(tag, Tag::StructureTag(ref t) if t.id == 24) {
+                        let LdapResultExt(mut result, exop, _) = LdapResultExt::from(tag);
+                        result.ctrls = controls;
+                        self.notifier.notify(UnsolicitedNotification { result, exop });
+                        if self.notifier.disconnected() {
+                            break;
+                        }
+                    } else if self.unmatched.unmatched(id) {
+                        self.stats.unmatched_received();
+                        match self.unmatched.policy {
+                            Unmatched::Ignore => (),
+                            Unmatched::WarnSampled { .. } => self.unmatched.warn(id),
+                            Unmatched::Disconnect => {
+                                warn!("unmatched id: {}, closing the connection", id);
+                                self.abort_pending(&format!("response for unknown message ID {}", id));
+                                return Err(LdapError::ProtocolViolation { msgid: id });
+                            }
+                        }
                     } else {
-                        warn!("unmatched id: {}", id);
+                        debug!("response for a retired operation, id: {}", id);
                     }
                 },
             };
@@ -802,3 +2028,1042 @@ impl LdapConnAsync {
         Ok(self)
     }
 }
+
+#[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
+fn handshake_failed<E: Into<LdapError>>(e: E) -> LdapError {
+    LdapError::TlsHandshakeFailed {
+        source: Arc::new(e.into()),
+    }
+}
+
+// Result with the code 52 (unavailable), used for the operations which can't
+// complete because the connection is gone.
+fn unavailable(cause: &str) -> Tag {
+    Tag::StructureTag(
+        Tag::Sequence(Sequence {
+            id: 1,
+            class: TagClass::Application,
+            inner: vec![
+                Tag::Enumerated(Enumerated {
+                    inner: 52,
+                    ..Default::default()
+                }),
+                Tag::OctetString(OctetString::default()),
+                Tag::OctetString(OctetString {
+                    inner: Vec::from(cause),
+                    ..Default::default()
+                }),
+            ],
+        })
+        .into_structure(),
+    )
+}
+
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+// Copy exactly `len` bytes from the reader of a streamed value. Before writing the
+// last chunk, the reader is checked for excess data, so that a request with a
+// mismatched value length is never completed on the wire.
+async fn copy_value(
+    io: &mut ConnType,
+    mut reader: BoxedReader,
+    len: u64,
+    buf: &mut [u8],
+) -> Result<()> {
+    let mut remaining = len;
+    while remaining > 0 {
+        let want = remaining.min(buf.len() as u64) as usize;
+        let n = reader.read(&mut buf[..want]).await?;
+        if n == 0 {
+            return Err(LdapError::StreamedValueLength {
+                declared: len,
+                received: len - remaining,
+            });
+        }
+        remaining -= n as u64;
+        if remaining == 0 {
+            check_excess(&mut reader, len).await?;
+        }
+        io.write_all(&buf[..n]).await?;
+    }
+    if len == 0 {
+        check_excess(&mut reader, len).await?;
+    }
+    Ok(())
+}
+
+async fn check_excess(reader: &mut BoxedReader, len: u64) -> Result<()> {
+    let mut extra = [0u8; 1];
+    if reader.read(&mut extra).await? > 0 {
+        return Err(LdapError::StreamedValueLength {
+            declared: len,
+            received: len + 1,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LdapConnAsync, LdapConnSettings};
+    use crate::fixtures::{self, entry, message, octet_string, result, Requests};
+    use crate::ldap::Ldap;
+    use crate::result::LdapError;
+    use crate::search::Scope;
+    use crate::unsolicited::NOTICE_OF_DISCONNECTION_OID;
+
+    use lber::common::TagClass;
+    use lber::structures::{Enumerated, Integer, OctetString, Sequence, Tag};
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn no_tls_info_on_plain_connection() {
+        let (_listener, url) = fixtures::listener().await;
+        let (_conn, ldap) = LdapConnAsync::new(&url).await.expect("connection");
+        assert!(ldap.tls_info().is_none());
+    }
+
+    #[tokio::test]
+    async fn unknown_scheme() {
+        let res = LdapConnAsync::new("ldapx://localhost").await;
+        assert!(matches!(res, Err(LdapError::UnknownScheme(s)) if s == "ldapx"));
+    }
+
+    #[cfg(not(any(feature = "tls-native", feature = "tls-rustls")))]
+    #[tokio::test]
+    async fn ldaps_without_tls_feature() {
+        let res = LdapConnAsync::new("ldaps://localhost").await;
+        assert!(matches!(
+            res,
+            Err(LdapError::FeatureNotEnabled { feature: "tls", .. })
+        ));
+    }
+
+    #[cfg(not(unix))]
+    #[tokio::test]
+    async fn ldapi_on_non_unix() {
+        let res = LdapConnAsync::new("ldapi://%2fvar%2frun%2fldapi").await;
+        assert!(matches!(res, Err(LdapError::PlatformUnsupported { .. })));
+    }
+
+    #[test]
+    fn address_order() {
+        use std::net::SocketAddr;
+
+        let addrs: Vec<SocketAddr> = [
+            "[::1]:389",
+            "[::2]:389",
+            "[::3]:389",
+            "10.0.0.1:389",
+            "10.0.0.2:389",
+        ]
+        .iter()
+        .map(|a| a.parse().expect("address"))
+        .collect();
+        let reordered: Vec<SocketAddr> = ["10.0.0.1:389", "[::1]:389", "10.0.0.2:389"]
+            .iter()
+            .map(|a| a.parse().expect("address"))
+            .collect();
+        assert_eq!(super::attempt_order(reordered.clone(), false), reordered);
+        let order: Vec<_> = super::attempt_order(reordered, true)
+            .iter()
+            .map(SocketAddr::to_string)
+            .collect();
+        assert_eq!(order, ["[::1]:389", "10.0.0.1:389", "10.0.0.2:389"]);
+        let order: Vec<_> = super::attempt_order(addrs, true)
+            .iter()
+            .map(SocketAddr::to_string)
+            .collect();
+        assert_eq!(
+            order,
+            [
+                "[::1]:389",
+                "10.0.0.1:389",
+                "[::2]:389",
+                "10.0.0.2:389",
+                "[::3]:389"
+            ]
+        );
+    }
+
+    // Address of a local TCP port with nothing listening on it.
+    async fn closed_port() -> std::net::SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("listener");
+        listener.local_addr().expect("address")
+    }
+
+    // Address of a listener whose accept queue is full, so that connection attempts to
+    // it get no answer. The returned sockets must be kept open.
+    #[cfg(target_os = "linux")]
+    fn stalled_port() -> (std::net::SocketAddr, Vec<socket2::Socket>) {
+        use socket2::{Domain, Socket, Type};
+        use std::time::Duration;
+
+        let listener = Socket::new(Domain::IPV4, Type::STREAM, None).expect("socket");
+        let any: std::net::SocketAddr = "127.0.0.1:0".parse().expect("address");
+        listener.bind(&any.into()).expect("bind");
+        listener.listen(0).expect("listen");
+        let addr = listener
+            .local_addr()
+            .expect("address")
+            .as_socket()
+            .expect("inet address");
+        let mut sockets = vec![listener];
+        loop {
+            let client = Socket::new(Domain::IPV4, Type::STREAM, None).expect("socket");
+            match client.connect_timeout(&addr.into(), Duration::from_millis(200)) {
+                Ok(()) => sockets.push(client),
+                Err(_) => return (addr, sockets),
+            }
+        }
+    }
+
+    fn peer_addr(stream: &crate::rt::TcpStream) -> std::net::SocketAddr {
+        let addr = crate::rt::sock_ref(stream).peer_addr().expect("peer");
+        addr.as_socket().expect("inet address")
+    }
+
+    #[tokio::test]
+    async fn connect_fallback() {
+        use std::time::{Duration, Instant};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("listener");
+        let open = listener.local_addr().expect("address");
+        let closed = closed_port().await;
+        for delay in [None, Some(Duration::from_millis(50))] {
+            let stream = super::connect_any(
+                "test.example.org:389",
+                vec![closed, open],
+                None,
+                delay,
+                None,
+            )
+            .await
+            .expect("connection");
+            assert_eq!(peer_addr(&stream), open);
+        }
+
+        // An address in the IPv6 discard prefix either never answers or is reported
+        // as unreachable at once. Either way, the next address must be tried early.
+        let blackhole = "[100::1]:389".parse().expect("address");
+        let start = Instant::now();
+        let stream = tokio::time::timeout(
+            Duration::from_secs(5),
+            super::connect_any(
+                "test.example.org:389",
+                vec![blackhole, open],
+                None,
+                Some(Duration::from_millis(100)),
+                None,
+            ),
+        )
+        .await
+        .expect("no timeout")
+        .expect("connection");
+        assert_eq!(peer_addr(&stream), open);
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        let (closed1, closed2) = (closed_port().await, closed_port().await);
+        let err = super::connect_any(
+            "test.example.org:389",
+            vec![closed1, closed2],
+            None,
+            None,
+            None,
+        )
+        .await
+        .expect_err("refused");
+        assert!(
+            matches!(err, LdapError::ConnectFailed { ref addrs, .. } if addrs == &[closed1, closed2])
+        );
+        assert!(err.to_string().starts_with(&format!(
+            "unable to connect to test.example.org:389 ({}, {}): ",
+            closed1, closed2
+        )));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn connect_attempt_timeout() {
+        use std::time::Duration;
+
+        let (stalled, _sockets) = stalled_port();
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("listener");
+        let open = listener.local_addr().expect("address");
+        let deadline = crate::rt::Instant::now() + Duration::from_secs(1);
+        let stream = super::connect_any(
+            "test.example.org:389",
+            vec![stalled, open],
+            None,
+            None,
+            Some(deadline),
+        )
+        .await
+        .expect("connection");
+        assert_eq!(peer_addr(&stream), open);
+
+        let closed = closed_port().await;
+        for delay in [None, Some(Duration::from_millis(100))] {
+            let deadline = crate::rt::Instant::now() + Duration::from_millis(300);
+            let err = super::connect_any(
+                "test.example.org:389",
+                vec![closed, stalled],
+                None,
+                delay,
+                Some(deadline),
+            )
+            .await
+            .expect_err("timeout");
+            match err {
+                LdapError::ConnectFailed { addrs, source, .. } => {
+                    assert_eq!(addrs, [closed, stalled]);
+                    assert_eq!(source.kind(), std::io::ErrorKind::TimedOut);
+                }
+                err => panic!("{:?}", err),
+            }
+        }
+
+        let url = format!("ldap://{}", stalled);
+        let settings = LdapConnSettings::new().set_conn_timeout(Duration::from_millis(200));
+        let err = match LdapConnAsync::with_settings(settings, &url).await {
+            Ok(_) => panic!("stalled connection"),
+            Err(e) => e,
+        };
+        assert!(
+            matches!(err, LdapError::ConnectFailed { ref addrs, .. } if addrs == &[stalled]),
+            "{:?}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn operation_without_connection() {
+        let (_listener, url) = fixtures::listener().await;
+        let (conn, mut ldap) = LdapConnAsync::new(&url).await.expect("connection");
+        drop(conn);
+        let err = ldap
+            .compare("uid=test,dc=example,dc=org", "cn", "test")
+            .await
+            .expect_err("no connection");
+        assert!(
+            matches!(err, LdapError::Operation { op: "compare", id: 1, ref source } if matches!(**source, LdapError::OpSend { .. })),
+            "{:?}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn local_bind_addr() {
+        let (listener, url) = fixtures::listener().await;
+        let local = "127.0.0.1".parse().expect("address");
+        let settings = LdapConnSettings::new()
+            .set_local_bind_addr(local)
+            .set_happy_eyeballs(true);
+        let (conn, (_, peer)) = tokio::join!(LdapConnAsync::with_settings(settings, &url), async {
+            listener.accept().await.expect("accept")
+        });
+        conn.expect("connection");
+        assert_eq!(peer.ip(), local);
+
+        let settings = LdapConnSettings::new().set_local_bind_addr("::1".parse().expect("address"));
+        let err = match LdapConnAsync::with_settings(settings, &url).await {
+            Ok(_) => panic!("family mismatch"),
+            Err(e) => e,
+        };
+        assert!(matches!(err, LdapError::ConnectFailed { .. }), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn tcp_socket_options() {
+        use super::ConnType;
+        use crate::rt::sock_ref;
+        use std::time::Duration;
+
+        let (_listener, url) = fixtures::listener().await;
+        let (conn, _) = LdapConnAsync::new(&url).await.expect("connection");
+        let ConnType::Tcp(ref stream) = conn.stream.get_ref() else {
+            panic!("not a TCP connection");
+        };
+        let socket = sock_ref(stream);
+        assert!(socket.tcp_nodelay().expect("nodelay"));
+        assert!(!socket.keepalive().expect("keepalive"));
+
+        let settings = LdapConnSettings::new()
+            .set_tcp_nodelay(false)
+            .set_tcp_keepalive(Some(Duration::from_secs(30)));
+        #[cfg(target_os = "linux")]
+        let settings = settings.set_tcp_user_timeout(Duration::from_secs(5));
+        let (conn, _) = LdapConnAsync::with_settings(settings, &url)
+            .await
+            .expect("connection");
+        let ConnType::Tcp(ref stream) = conn.stream.get_ref() else {
+            panic!("not a TCP connection");
+        };
+        let socket = sock_ref(stream);
+        assert!(!socket.tcp_nodelay().expect("nodelay"));
+        assert!(socket.keepalive().expect("keepalive"));
+        assert_eq!(
+            socket.tcp_keepalive_time().expect("keepalive time"),
+            Duration::from_secs(30)
+        );
+        #[cfg(target_os = "linux")]
+        {
+            assert_eq!(
+                socket.tcp_keepalive_interval().expect("keepalive interval"),
+                Duration::from_secs(30)
+            );
+            assert_eq!(
+                socket.tcp_user_timeout().expect("user timeout"),
+                Some(Duration::from_secs(5))
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn ldapi_addresses() {
+        use super::UnixAddr;
+        use std::path::PathBuf;
+        use url::Url;
+
+        let addr = |url: &str| UnixAddr::from_url(&Url::parse(url).expect("url"));
+        assert_eq!(
+            addr("ldapi://%2fvar%2frun%2fslapd%2Fldapi").expect("path"),
+            UnixAddr::Path(PathBuf::from("/var/run/slapd/ldapi"))
+        );
+        assert_eq!(
+            addr("ldapi://%2ftmp%2fa%20b%2fsock%c3%a9").expect("path"),
+            UnixAddr::Path(PathBuf::from("/tmp/a b/sock\u{e9}"))
+        );
+        assert_eq!(
+            addr("ldapi://%00slapd%2f1").expect("abstract"),
+            UnixAddr::Abstract(b"slapd/1".to_vec())
+        );
+        assert_eq!(
+            addr("ldapi://%40slapd").expect("abstract"),
+            UnixAddr::Abstract(b"slapd".to_vec())
+        );
+        assert!(matches!(addr("ldapi://"), Err(LdapError::EmptyUnixPath)));
+        assert!(matches!(
+            addr("ldapi://%2fsock:389"),
+            Err(LdapError::PortInUnixPath)
+        ));
+        assert!(matches!(
+            addr("ldapi://%2fsock?cn"),
+            Err(LdapError::QueryInUnixPath)
+        ));
+    }
+
+    // Answer a single Bind on the Unix socket with the given result code, and report
+    // the SASL mechanism of the Bind.
+    #[cfg(unix)]
+    fn external_bind_server(
+        listener: tokio::net::UnixListener,
+        rc: i64,
+    ) -> tokio::sync::oneshot::Receiver<String> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.expect("accept");
+            let (rd, mut wr) = stream.into_split();
+            let mut requests = Requests::new(rd);
+            let req = requests.next().await.expect("bind request");
+            let sasl = req
+                .op
+                .expect_constructed()
+                .and_then(|mut op| op.pop())
+                .and_then(|sasl| sasl.expect_constructed())
+                .expect("sasl credentials");
+            let mech = sasl[0].clone().expect_primitive().expect("mechanism");
+            tx.send(String::from_utf8(mech).expect("mechanism"))
+                .expect("sent");
+            wr.write_all(&message(req.msgid, result(1, rc)))
+                .await
+                .expect("write");
+            let _ = requests.next().await;
+        });
+        rx
+    }
+
+    #[cfg(unix)]
+    fn socket_path(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("ldap3-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn ldapi_external_bind() {
+        let settings = || LdapConnSettings::new().set_ldapi_external_bind(true);
+        let path = socket_path("bind-ok");
+        let listener = tokio::net::UnixListener::bind(&path).expect("listener");
+        let mech = external_bind_server(listener, 0);
+        let url = format!(
+            "ldapi://{}",
+            percent_encoding::utf8_percent_encode(
+                path.to_str().expect("path"),
+                percent_encoding::NON_ALPHANUMERIC
+            )
+        );
+        let (conn, mut ldap) = LdapConnAsync::with_settings(settings(), &url)
+            .await
+            .expect("connection");
+        assert_eq!(mech.await.expect("mechanism"), "EXTERNAL");
+        crate::drive!(conn);
+        ldap.unbind().await.expect("unbind");
+        let _ = std::fs::remove_file(&path);
+
+        let path = socket_path("bind-fail");
+        let listener = tokio::net::UnixListener::bind(&path).expect("listener");
+        let _mech = external_bind_server(listener, 48);
+        let url = format!(
+            "ldapi://{}",
+            path.to_str().expect("path").replace('/', "%2f")
+        );
+        let res = LdapConnAsync::with_settings(settings(), &url).await;
+        assert!(matches!(res, Err(LdapError::LdapResult { ref result }) if result.rc == 48));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn ldapi_abstract_socket() {
+        use std::os::linux::net::SocketAddrExt;
+        use std::os::unix::net::{SocketAddr, UnixListener};
+
+        let name = format!("ldap3-test-{}", std::process::id());
+        let addr = SocketAddr::from_abstract_name(name.as_bytes()).expect("address");
+        let listener = UnixListener::bind_addr(&addr).expect("listener");
+        listener.set_nonblocking(true).expect("nonblocking");
+        let listener = tokio::net::UnixListener::from_std(listener).expect("listener");
+        let mech = external_bind_server(listener, 0);
+        let settings = LdapConnSettings::new().set_ldapi_external_bind(true);
+        let (_conn, _ldap) =
+            LdapConnAsync::with_settings(settings, &format!("ldapi://%40{}", name))
+                .await
+                .expect("connection");
+        assert_eq!(mech.await.expect("mechanism"), "EXTERNAL");
+    }
+
+    // Connect to a server which sends the given bytes, then keeps the socket open
+    // until the returned sender is used or dropped.
+    async fn raw_server(
+        settings: LdapConnSettings,
+        msg: Vec<u8>,
+    ) -> (LdapConnAsync, Ldap, tokio::sync::oneshot::Sender<()>) {
+        let (listener, url) = fixtures::listener().await;
+        let (done_tx, done_rx) = tokio::sync::oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.expect("accept");
+            stream.write_all(&msg).await.expect("write");
+            let _ = done_rx.await;
+        });
+        let (conn, ldap) = LdapConnAsync::with_settings(settings, &url)
+            .await
+            .expect("connection");
+        (conn, ldap, done_tx)
+    }
+
+    async fn drive_result(conn: LdapConnAsync) -> Result<(), LdapError> {
+        tokio::time::timeout(std::time::Duration::from_secs(5), conn.drive())
+            .await
+            .expect("immediate failure")
+    }
+
+    #[tokio::test]
+    async fn oversized_message() {
+        // Claims a 2 GiB message, but only sends a few bytes of it.
+        let mut msg = vec![0x30, 0x84, 0x7f, 0xff, 0xff, 0xff];
+        msg.extend_from_slice(&[0x55; 64]);
+        let settings = LdapConnSettings::new().set_max_ber_size(1024 * 1024);
+        let (mut conn, _ldap, _done) = raw_server(settings, msg).await;
+        let termination = conn.termination_handle();
+        assert!(matches!(
+            drive_result(conn).await,
+            Err(LdapError::DecodingFrameTooLarge {
+                size: 0x8000_0005,
+                limit: 0x10_0000
+            })
+        ));
+        assert_eq!(
+            termination.await.expect("termination"),
+            Err(String::from(
+                "received message of 2147483653 bytes exceeds the limit of 1048576 bytes"
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn indefinite_length_message() {
+        // SearchResultDone in a message of indefinite length.
+        let msg = vec![
+            0x30, 0x80, 0x02, 0x01, 0x02, 0x65, 0x07, 0x0a, 0x01, 0x00, 0x04, 0x00, 0x04, 0x00,
+            0x00, 0x00,
+        ];
+        let (conn, _ldap, _done) = raw_server(LdapConnSettings::new(), msg).await;
+        match drive_result(conn).await {
+            Err(LdapError::Io { source }) => {
+                assert_eq!(source.to_string(), "decoding error")
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    // Notice of Disconnection with result code 52 (unavailable). Active Directory
+    // places the OID outside the ExtendedResponse sequence.
+    fn notice_of_disconnection(ad_style: bool) -> Vec<u8> {
+        let name = Tag::OctetString(OctetString {
+            id: 10,
+            class: TagClass::Context,
+            inner: Vec::from(NOTICE_OF_DISCONNECTION_OID),
+        });
+        let mut resp = vec![
+            Tag::Enumerated(Enumerated {
+                inner: 52,
+                ..Default::default()
+            }),
+            octet_string(b""),
+            octet_string(b"shutting down"),
+        ];
+        let mut msg = vec![Tag::Integer(Integer {
+            inner: 0,
+            ..Default::default()
+        })];
+        if !ad_style {
+            resp.push(name.clone());
+        }
+        msg.push(Tag::Sequence(Sequence {
+            id: 24,
+            class: TagClass::Application,
+            inner: resp,
+        }));
+        if ad_style {
+            msg.push(name);
+        }
+        fixtures::encode(Tag::Sequence(Sequence {
+            inner: msg,
+            ..Default::default()
+        }))
+    }
+
+    #[tokio::test]
+    async fn disconnect_during_search() {
+        let (listener, url) = fixtures::listener().await;
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.expect("accept");
+            let (rd, mut wr) = stream.into_split();
+            // Wait for the search request before announcing the disconnection.
+            let _ = Requests::new(rd).next().await.expect("search request");
+            wr.write_all(&notice_of_disconnection(false))
+                .await
+                .expect("write");
+        });
+        let (conn, mut ldap) = LdapConnAsync::new(&url).await.expect("connection");
+        let mut notifications = ldap.unsolicited_notifications();
+        let driver = tokio::spawn(conn.drive());
+        let mut stream = ldap
+            .streaming_search(
+                "dc=example,dc=org",
+                Scope::Subtree,
+                "(objectClass=*)",
+                vec!["*"],
+            )
+            .await
+            .expect("search");
+        let res = tokio::time::timeout(std::time::Duration::from_secs(5), stream.next()).await;
+        assert!(matches!(
+            res.expect("search result"),
+            Err(LdapError::ServerDisconnect { rc: 52, ref text }) if text == "shutting down"
+        ));
+        let notification = notifications.recv().await.expect("notification");
+        assert!(notification.is_disconnect());
+        assert_eq!(notification.result.rc, 52);
+        assert!(driver.await.expect("driver").is_ok());
+        assert!(notifications.recv().await.is_err());
+        assert!(matches!(
+            ldap.simple_bind("", "").await,
+            Err(LdapError::ServerDisconnect { rc: 52, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn active_directory_notice() {
+        let (conn, ldap, _done) =
+            raw_server(LdapConnSettings::new(), notice_of_disconnection(true)).await;
+        let mut notifications = ldap.unsolicited_notifications();
+        assert!(drive_result(conn).await.is_ok());
+        let notification = notifications.recv().await.expect("notification");
+        assert_eq!(
+            notification.exop.name.as_deref(),
+            Some(NOTICE_OF_DISCONNECTION_OID)
+        );
+        assert_eq!(notification.result.text, "shutting down");
+    }
+
+    #[tokio::test]
+    async fn peer_closes_during_search() {
+        let (listener, url) = fixtures::listener().await;
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.expect("accept");
+            let (rd, mut wr) = stream.into_split();
+            let req = Requests::new(rd).next().await.expect("search request");
+            let entry = message(req.msgid, entry("dc=example,dc=org", &[]));
+            wr.write_all(&entry).await.expect("write");
+        });
+        let (mut conn, mut ldap) = LdapConnAsync::new(&url).await.expect("connection");
+        let termination = conn.termination_handle();
+        crate::drive!(conn);
+        let res = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            ldap.search(
+                "dc=example,dc=org",
+                Scope::Base,
+                "(objectClass=*)",
+                vec!["1.1"],
+            ),
+        )
+        .await
+        .expect("search result")
+        .expect("search");
+        assert_eq!(res.0.len(), 1);
+        match res.success() {
+            Err(LdapError::LdapResult { result }) => {
+                assert_eq!(result.rc, 52);
+                assert_eq!(result.text, "connection closed by server");
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+        assert_eq!(termination.await.expect("termination"), Ok(()));
+        assert_eq!(ldap.msgmap.lock().expect("msgmap").1.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn unexpected_op_in_search() {
+        let (listener, url) = fixtures::listener().await;
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.expect("accept");
+            let (rd, mut wr) = stream.into_split();
+            let mut requests = Requests::new(rd);
+            let search = requests.next().await.expect("search request");
+            tx.send(()).expect("search read");
+            let bind = requests.next().await.expect("bind request");
+            // An ExtendedResponse for the Search, then the BindResponse.
+            wr.write_all(&message(search.msgid, result(24, 0)))
+                .await
+                .expect("write");
+            wr.write_all(&message(bind.msgid, result(1, 0)))
+                .await
+                .expect("write");
+            let _ = requests.next().await;
+        });
+        let (conn, mut ldap) = LdapConnAsync::new(&url).await.expect("connection");
+        let driver = tokio::spawn(conn.drive());
+        let mut stream = ldap
+            .streaming_search(
+                "dc=example,dc=org",
+                Scope::Subtree,
+                "(objectClass=*)",
+                vec!["*"],
+            )
+            .await
+            .expect("search");
+        rx.await.expect("search request");
+        let mut bind_ldap = ldap.clone();
+        let bind = tokio::spawn(async move { bind_ldap.simple_bind("", "").await });
+        let res = tokio::time::timeout(std::time::Duration::from_secs(5), stream.next()).await;
+        assert!(matches!(
+            res.expect("search result"),
+            Err(LdapError::UnexpectedPdu {
+                msgid: 1,
+                tag_id: 24
+            })
+        ));
+        let res = tokio::time::timeout(std::time::Duration::from_secs(5), bind)
+            .await
+            .expect("bind result")
+            .expect("bind task")
+            .expect("bind");
+        assert_eq!(res.rc, 0);
+        assert!(!driver.is_finished());
+        assert_eq!(ldap.msgmap.lock().expect("msgmap").1.len(), 0);
+    }
+
+    #[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
+    #[tokio::test]
+    async fn poisoned_connection() {
+        let (listener, url) = fixtures::listener().await;
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.expect("accept");
+            let mut requests = Requests::new(stream);
+            let _ = requests.next().await.expect("compare request");
+            tx.send(()).expect("compare read");
+            let _ = requests.next().await;
+        });
+        let (conn, mut ldap) = LdapConnAsync::new(&url).await.expect("connection");
+        let driver = tokio::spawn(conn.drive());
+        let mut compare_ldap = ldap.clone();
+        let compare = tokio::spawn(async move {
+            compare_ldap
+                .compare("cn=test,dc=example,dc=org", "cn", "test")
+                .await
+        });
+        rx.await.expect("compare request");
+        let e = ldap.poison(LdapError::from(std::io::Error::other("bad record")));
+        assert!(matches!(e, LdapError::TlsHandshakeFailed { .. }));
+        let timeout = std::time::Duration::from_secs(5);
+        let res = tokio::time::timeout(timeout, compare)
+            .await
+            .expect("compare result")
+            .expect("compare task");
+        assert!(matches!(res, Err(LdapError::TlsHandshakeFailed { .. })));
+        let res = tokio::time::timeout(timeout, driver)
+            .await
+            .expect("driver result")
+            .expect("driver task");
+        assert!(matches!(res, Err(LdapError::TlsHandshakeFailed { .. })));
+        match ldap.simple_bind("", "").await {
+            Err(LdapError::TlsHandshakeFailed { source }) => {
+                assert!(matches!(*source, LdapError::Io { .. }))
+            }
+            res => panic!("unexpected bind result: {:?}", res),
+        }
+    }
+
+    #[tokio::test]
+    async fn unbind_on_drop() {
+        let timeout = std::time::Duration::from_secs(5);
+        let (url, server) = fixtures::read_until_close().await;
+        let (conn, ldap) = LdapConnAsync::new(&url).await.expect("connection");
+        let driver = tokio::spawn(conn.drive());
+        let other = ldap.clone();
+        drop(ldap);
+        drop(other);
+        tokio::time::timeout(timeout, driver)
+            .await
+            .expect("driver result")
+            .expect("driver task")
+            .expect("driver");
+        let received = tokio::time::timeout(timeout, server)
+            .await
+            .expect("server result")
+            .expect("server task");
+        // UnbindRequest with the message ID 1, followed by the end of the stream.
+        assert_eq!(received, [0x30, 0x05, 0x02, 0x01, 0x01, 0x42, 0x00]);
+    }
+
+    #[tokio::test]
+    async fn close_unbinds_once() {
+        let timeout = std::time::Duration::from_secs(5);
+        let (url, server) = fixtures::read_until_close().await;
+        let (conn, mut ldap) = LdapConnAsync::new(&url).await.expect("connection");
+        let driver = tokio::spawn(conn.drive());
+        ldap.unbind().await.expect("unbind");
+        tokio::time::timeout(timeout, ldap.close())
+            .await
+            .expect("close result")
+            .expect("close");
+        tokio::time::timeout(timeout, driver)
+            .await
+            .expect("driver result")
+            .expect("driver task")
+            .expect("driver");
+        let received = tokio::time::timeout(timeout, server)
+            .await
+            .expect("server result")
+            .expect("server task");
+        assert_eq!(received, [0x30, 0x05, 0x02, 0x01, 0x01, 0x42, 0x00]);
+    }
+
+    #[cfg(not(all(feature = "gssapi", feature = "ntlm", feature = "scram")))]
+    #[tokio::test]
+    async fn sasl_bind_without_feature() {
+        let (_listener, url) = fixtures::listener().await;
+        let (_conn, mut ldap) = LdapConnAsync::new(&url).await.expect("connection");
+        #[cfg(not(feature = "gssapi"))]
+        assert!(matches!(
+            ldap.sasl_gssapi_bind("localhost").await,
+            Err(LdapError::FeatureNotEnabled {
+                feature: "gssapi",
+                ..
+            })
+        ));
+        #[cfg(not(feature = "ntlm"))]
+        assert!(matches!(
+            ldap.sasl_ntlm_bind("user", "pass").await,
+            Err(LdapError::FeatureNotEnabled {
+                feature: "ntlm",
+                ..
+            })
+        ));
+        #[cfg(not(feature = "scram"))]
+        assert!(matches!(
+            ldap.sasl_scram_bind(crate::ScramMech::Sha256, "user", "pass")
+                .await,
+            Err(LdapError::FeatureNotEnabled {
+                feature: "scram",
+                ..
+            })
+        ));
+    }
+
+    #[cfg(feature = "tls-native")]
+    #[tokio::test]
+    async fn tls_info_on_ldaps_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("listener");
+        let port = listener.local_addr().expect("local addr").port();
+        let acceptor = tokio_native_tls::TlsAcceptor::from(
+            native_tls::TlsAcceptor::new(identity()).expect("acceptor"),
+        );
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.expect("accept");
+            acceptor.accept(stream).await.expect("handshake")
+        });
+        let (_conn, ldap) = LdapConnAsync::with_settings(
+            LdapConnSettings::new().set_no_tls_verify(true),
+            &format!("ldaps://127.0.0.1:{}", port),
+        )
+        .await
+        .expect("connection");
+        let _stream = server.await.expect("server");
+        let info = ldap.tls_info().expect("TLS info");
+        assert_eq!(info.peer_der, include_bytes!("../data/tls/cert.der"));
+        assert_eq!(
+            ldap.channel_binding_token().expect("channel binding"),
+            crate::channel_binding::tls_server_end_point(&info.peer_der).expect("hash")
+        );
+    }
+
+    #[cfg(feature = "tls-native")]
+    fn identity() -> native_tls::Identity {
+        native_tls::Identity::from_pkcs8(
+            include_bytes!("../data/tls/cert.pem"),
+            include_bytes!("../data/tls/key.pem"),
+        )
+        .expect("identity")
+    }
+
+    #[cfg(feature = "tls-native")]
+    #[test]
+    fn connector_with_identity() {
+        let settings = LdapConnSettings::new()
+            .set_no_tls_verify(true)
+            .set_client_identity(identity());
+        assert!(LdapConnAsync::tls_connector(settings).is_ok());
+    }
+
+    #[cfg(feature = "tls-native")]
+    #[test]
+    fn custom_connector_with_identity() {
+        let connector = native_tls::TlsConnector::new().expect("connector");
+        let settings = LdapConnSettings::new()
+            .set_connector(connector)
+            .set_client_identity(identity());
+        assert!(matches!(
+            LdapConnAsync::tls_connector(settings),
+            Err(LdapError::IdentityWithCustomTls)
+        ));
+    }
+
+    #[cfg(feature = "tls-native")]
+    #[test]
+    fn connector_with_root_certs() {
+        let pem = include_bytes!("../data/tls/cert.pem");
+        let settings = LdapConnSettings::new().add_root_certificates_pem(pem);
+        assert!(LdapConnAsync::tls_connector(settings).is_ok());
+        let connector = native_tls::TlsConnector::new().expect("connector");
+        let settings = LdapConnSettings::new()
+            .set_connector(connector)
+            .add_root_certificates_pem(pem);
+        assert!(matches!(
+            LdapConnAsync::tls_connector(settings),
+            Err(LdapError::IdentityWithCustomTls)
+        ));
+    }
+
+    #[cfg(feature = "tls-rustls")]
+    fn client_cert() -> (
+        Vec<rustls::pki_types::CertificateDer<'static>>,
+        rustls::pki_types::PrivateKeyDer<'static>,
+    ) {
+        use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+
+        (
+            vec![CertificateDer::from(
+                &include_bytes!("../data/tls/cert.der")[..],
+            )],
+            PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(
+                &include_bytes!("../data/tls/key.der")[..],
+            )),
+        )
+    }
+
+    #[cfg(feature = "tls-rustls")]
+    #[test]
+    fn config_with_client_cert() {
+        let (cert_chain, key) = client_cert();
+        let settings = LdapConnSettings::new()
+            .set_no_tls_verify(true)
+            .set_client_cert(cert_chain, key);
+        let config = LdapConnAsync::tls_config(settings).expect("config");
+        assert!(config.client_auth_cert_resolver.has_certs());
+    }
+
+    #[cfg(feature = "tls-rustls")]
+    #[tokio::test]
+    async fn tls_info_on_ldaps_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("listener");
+        let port = listener.local_addr().expect("local addr").port();
+        let (cert_chain, key) = client_cert();
+        let config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(cert_chain, key)
+            .expect("server config");
+        let acceptor = tokio_rustls::TlsAcceptor::from(std::sync::Arc::new(config));
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.expect("accept");
+            acceptor.accept(stream).await.expect("handshake")
+        });
+        let (_conn, ldap) = LdapConnAsync::with_settings(
+            LdapConnSettings::new().set_no_tls_verify(true),
+            &format!("ldaps://127.0.0.1:{}", port),
+        )
+        .await
+        .expect("connection");
+        let _stream = server.await.expect("server");
+        let info = ldap.tls_info().expect("TLS info");
+        assert_eq!(info.peer_der, include_bytes!("../data/tls/cert.der"));
+        assert!(info.protocol.is_some());
+        assert!(info.cipher.is_some());
+    }
+
+    #[cfg(feature = "tls-rustls")]
+    #[test]
+    fn custom_config_with_client_cert() {
+        let (cert_chain, key) = client_cert();
+        let config = rustls::ClientConfig::builder()
+            .with_root_certificates(rustls::RootCertStore::empty())
+            .with_no_client_auth();
+        let settings = LdapConnSettings::new()
+            .set_config(std::sync::Arc::new(config))
+            .set_client_cert(cert_chain, key);
+        assert!(matches!(
+            LdapConnAsync::tls_config(settings),
+            Err(LdapError::IdentityWithCustomTls)
+        ));
+    }
+
+    #[cfg(feature = "tls-rustls")]
+    #[test]
+    fn config_with_root_certs() {
+        let settings = LdapConnSettings::new()
+            .add_root_certificates_pem(include_bytes!("../data/tls/cert.pem"));
+        assert!(LdapConnAsync::tls_config(settings).is_ok());
+        let settings = LdapConnSettings::new().add_root_certificates_pem(
+            b"-----BEGIN CERTIFICATE-----\nAAAA\n-----END CERTIFICATE-----\n",
+        );
+        assert!(LdapConnAsync::tls_config(settings).is_err());
+    }
+}
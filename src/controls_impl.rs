@@ -1,9 +1,11 @@
 use std::collections::HashMap;
 
-use lber::structure::{StructureTag, PL};
+use lber::structure::StructureTag;
 use lber::structures::{ASNTag, Boolean, OctetString, Sequence, Tag};
 use lber::universal::Types;
 
+use crate::result::{self, LdapError};
+
 use lazy_static::lazy_static;
 
 /// Recognized control types.
@@ -21,6 +23,12 @@ pub enum ControlType {
     SyncState,
     ManageDsaIt,
     MatchedValues,
+    EntryChange,
+    DirSync,
+    ExtendedDn,
+    ShowDeleted,
+    PasswordExpired,
+    PasswordExpiring,
 }
 
 mod assertion;
@@ -32,6 +40,14 @@ pub use self::content_sync::{EntryState, RefreshMode, SyncDone, SyncInfo, SyncRe
 
 mod paged_results;
 pub use self::paged_results::PagedResults;
+pub(crate) use self::paged_results::PAGED_RESULTS_OID;
+
+mod password_expiry;
+pub(crate) use self::password_expiry::password_expiry;
+pub use self::password_expiry::{PasswordExpired, PasswordExpiring, PasswordExpiry};
+
+mod persistent_search;
+pub use self::persistent_search::{ChangeType, EntryChange, PersistentSearch};
 
 mod proxy_auth;
 pub use self::proxy_auth::ProxyAuth;
@@ -42,6 +58,26 @@ pub use self::read_entry::{PostRead, PostReadResp, PreRead, PreReadResp, ReadEnt
 mod relax_rules;
 pub use self::relax_rules::RelaxRules;
 
+mod show_deleted;
+pub use self::show_deleted::ShowDeleted;
+
+mod tree_delete;
+pub use self::tree_delete::TreeDelete;
+pub(crate) use self::tree_delete::TREE_DELETE_OID;
+
+mod txn_spec;
+pub use self::txn_spec::TxnSpec;
+
+mod dirsync;
+pub(crate) use self::dirsync::DIRSYNC_OID;
+pub use self::dirsync::{
+    DirSync, DirSyncResp, LDAP_DIRSYNC_ANCESTORS_FIRST_ORDER, LDAP_DIRSYNC_INCREMENTAL_VALUES,
+    LDAP_DIRSYNC_OBJECT_SECURITY, LDAP_DIRSYNC_PUBLIC_DATA_ONLY,
+};
+
+mod extended_dn;
+pub use self::extended_dn::{parse_extended_dn, ExtendedDn, ExtendedDnFormat, ExtendedDnRequest};
+
 mod manage_dsa_it;
 pub use self::manage_dsa_it::ManageDsaIt;
 
@@ -59,10 +95,54 @@ lazy_static! {
         map.insert(self::content_sync::SYNC_STATE_OID, ControlType::SyncState);
         map.insert(self::manage_dsa_it::MANAGE_DSA_IT_OID, ControlType::ManageDsaIt);
         map.insert(self::matched_values::MATCHED_VALUES_OID, ControlType::MatchedValues);
+        map.insert(self::persistent_search::ENTRY_CHANGE_OID, ControlType::EntryChange);
+        map.insert(self::dirsync::DIRSYNC_OID, ControlType::DirSync);
+        map.insert(self::extended_dn::EXTENDED_DN_OID, ControlType::ExtendedDn);
+        map.insert(self::show_deleted::SHOW_DELETED_OID, ControlType::ShowDeleted);
+        map.insert(self::password_expiry::PASSWORD_EXPIRED_OID, ControlType::PasswordExpired);
+        map.insert(self::password_expiry::PASSWORD_EXPIRING_OID, ControlType::PasswordExpiring);
         map
     };
 }
 
+// Operations with which the request controls implemented by this library can be used,
+// named as in observer::op_name(). Controls which can be used with any operation, and
+// those this library doesn't know, aren't listed.
+#[rustfmt::skip]
+lazy_static! {
+    static ref CONTROL_OPS: HashMap<&'static str, &'static [&'static str]> = {
+        let mut map: HashMap<_, &'static [&'static str]> = HashMap::new();
+        map.insert(self::paged_results::PAGED_RESULTS_OID, &["search"]);
+        map.insert(self::content_sync::SYNC_REQUEST_OID, &["search"]);
+        map.insert(self::persistent_search::PERSISTENT_SEARCH_OID, &["search"]);
+        map.insert(self::dirsync::DIRSYNC_OID, &["search"]);
+        map.insert(self::matched_values::MATCHED_VALUES_OID, &["search"]);
+        map.insert(self::extended_dn::EXTENDED_DN_OID, &["search"]);
+        map.insert(self::assertion::ASSERTION_OID, &["add", "compare", "delete", "modify", "modifydn", "search"]);
+        map.insert(self::read_entry::PRE_READ_OID, &["delete", "modify", "modifydn"]);
+        map.insert(self::read_entry::POST_READ_OID, &["add", "modify", "modifydn"]);
+        map.insert(self::tree_delete::TREE_DELETE_OID, &["delete"]);
+        map.insert(self::relax_rules::RELAX_RULES_OID, &["add", "delete", "modify", "modifydn"]);
+        map.insert(self::proxy_auth::PROXY_AUTH_OID, &["add", "compare", "delete", "extended", "modify", "modifydn", "search"]);
+        map
+    };
+}
+
+// Check that the known controls in `ctrls` can be used with the operation `op`.
+pub(crate) fn check_control_ops(ctrls: &[RawControl], op: &'static str) -> result::Result<()> {
+    for ctrl in ctrls {
+        if let Some(ops) = CONTROL_OPS.get(ctrl.ctype.as_str()) {
+            if !ops.contains(&op) {
+                return Err(LdapError::ControlNotAllowed {
+                    oid: ctrl.ctype.clone(),
+                    op,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Conversion trait for single control instances.
 ///
 /// The [`Ldap::with_controls()`](crate::Ldap::with_controls) method and its sync counterpart
@@ -129,9 +209,20 @@ where
 }
 
 /// Conversion trait for response controls.
-pub trait ControlParser {
+///
+/// An implementation provides [`try_parse()`](#tymethod.try_parse), which must not panic
+/// on malformed input; [`parse()`](#method.parse) is derived from it.
+pub trait ControlParser: Sized {
+    /// Convert the raw BER value into a control-specific struct, or return an error
+    /// if the value is malformed.
+    fn try_parse(val: &[u8]) -> result::Result<Self>;
+
     /// Convert the raw BER value into a control-specific struct.
-    fn parse(val: &[u8]) -> Self;
+    ///
+    /// The conversion panics if the value is malformed.
+    fn parse(val: &[u8]) -> Self {
+        Self::try_parse(val).expect("control value")
+    }
 }
 
 /// Response control.
@@ -142,6 +233,22 @@ pub trait ControlParser {
 #[derive(Clone, Debug)]
 pub struct Control(pub Option<ControlType>, pub RawControl);
 
+// A control is serialized as its raw part; the type is recovered from the OID.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Control {
+    fn serialize<S: serde::Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        self.1.serialize(ser)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Control {
+    fn deserialize<D: serde::Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        let raw = RawControl::deserialize(de)?;
+        Ok(Control(CONTROLS.get(&*raw.ctype).copied(), raw))
+    }
+}
+
 /// Generic control.
 ///
 /// This struct can be used both for request and response controls. For requests, an
@@ -151,37 +258,45 @@ pub struct Control(pub Option<ControlType>, pub RawControl);
 /// `into()` on the instance.
 ///
 /// For responses, an instance is packed into a [`Control`](struct.Control.html) and
-/// can be parsed by calling type-qualified [`parse()`](#method.parse) on that instance,
-/// if a [`ControlParser`](trait.ControlParser.html) implementation exists for the
+/// can be parsed by calling type-qualified [`parse()`](#method.parse) or
+/// [`try_parse()`](#method.try_parse) on that instance, if a
+/// [`ControlParser`](trait.ControlParser.html) implementation exists for the
 /// specified type.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RawControl {
     /// OID of the control.
     pub ctype: String,
     /// Criticality, has no meaning on response.
     pub crit: bool,
     /// Raw value of the control, if any.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::opt_bytes"))]
     pub val: Option<Vec<u8>>,
 }
 
 impl RawControl {
     /// Parse the generic control into a control-specific struct.
     ///
-    /// The parser will panic if the control value is `None`.
-    /// __Note__: no control known to the author signals the lack of return value by
-    /// omitting the control value, so this shouldn't be a problem in practice.
-    /// Nevertheless, it should be possible to report this along with other parsing errors,
-    /// if it proves necessary.
+    /// The parser will panic if the control value is `None` or malformed. No control
+    /// known to the author signals the lack of return value by omitting the control value,
+    /// but a misbehaving server can send anything; use [`try_parse()`](#method.try_parse)
+    /// to handle both cases as errors.
     pub fn parse<T: ControlParser>(&self) -> T {
         T::parse(self.val.as_ref().expect("value"))
     }
+
+    /// Parse the generic control into a control-specific struct, returning an error
+    /// instead of panicking if the control value is absent or malformed.
+    pub fn try_parse<T: ControlParser>(&self) -> result::Result<T> {
+        match self.val {
+            Some(ref val) => T::try_parse(val),
+            None => Err(LdapError::MalformedControl("missing value")),
+        }
+    }
 }
 
 pub fn build_tag(rc: RawControl) -> StructureTag {
-    let mut seq = vec![Tag::OctetString(OctetString {
-        inner: Vec::from(rc.ctype.as_bytes()),
-        ..Default::default()
-    })];
+    let mut seq = vec![OctetString::from_bytes(rc.ctype)];
     if rc.crit {
         seq.push(Tag::Boolean(Boolean {
             inner: true,
@@ -189,10 +304,7 @@ pub fn build_tag(rc: RawControl) -> StructureTag {
         }));
     }
     if let Some(val) = rc.val {
-        seq.push(Tag::OctetString(OctetString {
-            inner: val,
-            ..Default::default()
-        }));
+        seq.push(OctetString::from_bytes(val));
     }
     Tag::Sequence(Sequence {
         inner: seq,
@@ -217,18 +329,11 @@ pub fn parse_controls(t: StructureTag) -> Vec<Control> {
         let next = components.next();
         let (crit, maybe_val) = match next {
             None => (false, None),
-            Some(c) => match c {
-                StructureTag {
-                    id, ref payload, ..
-                } if id == Types::Boolean as u64 => match *payload {
-                    PL::P(ref v) => (v[0] != 0, components.next()),
-                    PL::C(_) => panic!("decoding error"),
-                },
-                StructureTag { id, .. } if id == Types::OctetString as u64 => {
-                    (false, Some(c.clone()))
-                }
-                _ => panic!("decoding error"),
-            },
+            Some(c) if c.id == Types::Boolean as u64 => {
+                (c.expect_bool().expect("criticality"), components.next())
+            }
+            Some(c) if c.id == Types::OctetString as u64 => (false, Some(c)),
+            Some(_) => panic!("decoding error"),
         };
         let val = maybe_val.map(|v| v.expect_primitive().expect("octet string"));
         let known_type = CONTROLS.get(&*ctype).copied();
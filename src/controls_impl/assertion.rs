@@ -2,6 +2,7 @@ use bytes::BytesMut;
 
 use super::{MakeCritical, RawControl};
 use crate::filter::parse;
+use crate::result::{LdapError, Result};
 use lber::structures::ASNTag;
 use lber::write;
 
@@ -16,24 +17,70 @@ pub struct Assertion<S> {
 
 impl<S: AsRef<str>> Assertion<S> {
     /// Create a new control instance with the specified filter.
+    ///
+    /// The filter is parsed when the control is encoded, which will panic if the
+    /// filter is invalid. Use [`try_new()`](#method.try_new) for filters which
+    /// aren't known to be valid.
     #[allow(clippy::new_ret_no_self)]
     pub fn new(filter: S) -> RawControl {
         Assertion { filter }.into()
     }
+
+    /// Create a new control instance with the specified filter, returning
+    /// `LdapError::FilterParsing` if the filter is invalid. The control isn't
+    /// critical; to make it so, set its `crit` field to `true`.
+    pub fn try_new(filter: S) -> Result<RawControl> {
+        Ok(RawControl {
+            ctype: ASSERTION_OID.to_owned(),
+            crit: false,
+            val: Some(encode_filter(filter.as_ref())?),
+        })
+    }
+}
+
+fn encode_filter(filter: &str) -> Result<Vec<u8>> {
+    let tag = parse(filter).map_err(|_| LdapError::FilterParsing)?;
+    let mut buf = BytesMut::with_capacity(filter.len()); // ballpark
+    write::encode_into(&mut buf, tag.into_structure())?;
+    Ok(Vec::from(&buf[..]))
 }
 
 impl<S> MakeCritical for Assertion<S> {}
 
 impl<S: AsRef<str>> From<Assertion<S>> for RawControl {
     fn from(assn: Assertion<S>) -> RawControl {
-        let filter_ref = assn.filter.as_ref();
-        let filter = parse(filter_ref).expect("filter").into_structure();
-        let mut buf = BytesMut::with_capacity(filter_ref.len()); // ballpark
-        write::encode_into(&mut buf, filter).expect("encoded");
         RawControl {
             ctype: ASSERTION_OID.to_owned(),
             crit: false,
-            val: Some(Vec::from(&buf[..])),
+            val: Some(encode_filter(assn.filter.as_ref()).expect("filter")),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Assertion, ASSERTION_OID};
+    use crate::filter::parse;
+    use crate::result::LdapError;
+
+    use bytes::BytesMut;
+    use lber::structures::ASNTag;
+    use lber::write;
+
+    #[test]
+    fn assertion_value() {
+        let filter = "(&(objectClass=person)(version=5))";
+        let ctrl = Assertion::try_new(filter).expect("control");
+        let mut buf = BytesMut::new();
+        write::encode_into(&mut buf, parse(filter).expect("filter").into_structure())
+            .expect("encoded");
+        assert_eq!(ctrl.ctype, ASSERTION_OID);
+        assert!(!ctrl.crit);
+        assert_eq!(ctrl.val.as_deref(), Some(&buf[..]));
+        assert_eq!(Assertion::new(filter).val, ctrl.val);
+        assert!(matches!(
+            Assertion::try_new("(version=5"),
+            Err(LdapError::FilterParsing)
+        ));
+    }
+}
@@ -1,12 +1,14 @@
 use std::collections::HashSet;
 
 use crate::controls::{ControlParser, MakeCritical, RawControl};
+use crate::intermediate::{IntermediateParser, IntermediateResponse};
+use crate::result::{LdapError, Result};
 use crate::ResultEntry;
 
 use bytes::BytesMut;
 
 use lber::common::TagClass;
-use lber::parse::{parse_tag, parse_uint};
+use lber::parse::parse_tag;
 use lber::structure::{StructureTag, PL};
 use lber::structures::{ASNTag, Boolean, Enumerated, OctetString, Sequence, Tag};
 use lber::universal::Types;
@@ -100,45 +102,41 @@ pub enum EntryState {
 }
 
 impl ControlParser for SyncState {
-    fn parse(val: &[u8]) -> Self {
+    fn try_parse(val: &[u8]) -> Result<Self> {
         let mut tags = match parse_tag(val) {
-            IResult::Ok((_, tag)) => tag,
-            _ => panic!("syncstate: failed to parse tag"),
+            IResult::Ok((_, tag)) => tag.expect_constructed(),
+            _ => None,
         }
-        .expect_constructed()
-        .expect("syncstate: elements")
+        .ok_or(LdapError::MalformedControl("syncstate: not a sequence"))?
         .into_iter();
-        let state = match match parse_uint(
-            tags.next()
-                .expect("syncstate: element 1")
-                .match_class(TagClass::Universal)
-                .and_then(|t| t.match_id(Types::Enumerated as u64))
-                .and_then(|t| t.expect_primitive())
-                .expect("syncstate: state")
-                .as_slice(),
-        ) {
-            Ok((_, state)) => state,
-            _ => panic!("syncstate: failed to parse state"),
-        } {
-            0 => EntryState::Present,
-            1 => EntryState::Add,
-            2 => EntryState::Modify,
-            3 => EntryState::Delete,
-            _ => panic!("syncstate: unknown state"),
+        let state = match tags
+            .next()
+            .and_then(|t| t.match_class(TagClass::Universal))
+            .and_then(|t| t.match_id(Types::Enumerated as u64))
+            .and_then(|t| t.expect_i64())
+        {
+            Some(0) => EntryState::Present,
+            Some(1) => EntryState::Add,
+            Some(2) => EntryState::Modify,
+            Some(3) => EntryState::Delete,
+            _ => return Err(LdapError::MalformedControl("syncstate: invalid state")),
         };
         let entry_uuid = tags
             .next()
-            .expect("syncstate: element 2")
-            .expect_primitive()
-            .expect("syncstate: entryUUID");
-        let cookie = tags
-            .next()
-            .map(|tag| tag.expect_primitive().expect("syncstate: synCookie"));
-        SyncState {
+            .and_then(|t| t.expect_primitive())
+            .ok_or(LdapError::MalformedControl("syncstate: invalid entryUUID"))?;
+        let cookie = match tags.next() {
+            Some(tag) => Some(
+                tag.expect_primitive()
+                    .ok_or(LdapError::MalformedControl("syncstate: invalid syncCookie"))?,
+            ),
+            None => None,
+        };
+        Ok(SyncState {
             state,
             entry_uuid,
             cookie,
-        }
+        })
     }
 }
 
@@ -150,37 +148,38 @@ pub struct SyncDone {
 }
 
 impl ControlParser for SyncDone {
-    fn parse(val: &[u8]) -> Self {
+    fn try_parse(val: &[u8]) -> Result<Self> {
         let tags = match parse_tag(val) {
-            Ok((_, tag)) => tag,
-            _ => panic!("syncdone: failed to parse tag"),
+            Ok((_, tag)) => tag.expect_constructed(),
+            _ => None,
         }
-        .expect_constructed()
-        .expect("syncdone: elements")
-        .into_iter();
+        .ok_or(LdapError::MalformedControl("syncdone: not a sequence"))?;
         let mut cookie = None;
         let mut refresh_deletes = false;
         for tag in tags {
             match tag {
-                StructureTag { id, payload, .. } if id == Types::OctetString as u64 => {
-                    cookie = Some(match payload {
-                        PL::P(ostr) => ostr,
-                        PL::C(_) => panic!("syncdone: constructed octet string?"),
-                    });
+                StructureTag { id, .. } if id == Types::OctetString as u64 => {
+                    cookie = Some(
+                        tag.expect_primitive()
+                            .ok_or(LdapError::MalformedControl("syncdone: invalid syncCookie"))?,
+                    );
+                }
+                StructureTag { id, .. } if id == Types::Boolean as u64 => {
+                    refresh_deletes = tag
+                        .expect_bool()
+                        .ok_or(LdapError::MalformedControl("syncdone: invalid boolean"))?;
                 }
-                StructureTag { id, payload, .. } if id == Types::Boolean as u64 => {
-                    refresh_deletes = match payload {
-                        PL::P(ostr) => ostr[0] != 0,
-                        PL::C(_) => panic!("syncdone: constructed boolean?"),
-                    };
+                _ => {
+                    return Err(LdapError::MalformedControl(
+                        "syncdone: unrecognized component",
+                    ))
                 }
-                _ => panic!("syncdone: unrecognized component"),
             }
         }
-        SyncDone {
+        Ok(SyncDone {
             cookie,
             refresh_deletes,
-        }
+        })
     }
 }
 
@@ -204,113 +203,183 @@ pub enum SyncInfo {
 }
 
 /// Parse the Sync Info value from the Search result entry.
-pub fn parse_syncinfo(entry: ResultEntry) -> SyncInfo {
-    let mut tags = entry
-        .0
-        .match_id(25)
-        .and_then(|t| t.expect_constructed())
-        .expect("intermediate seq")
-        .into_iter();
-    loop {
-        match tags.next() {
-            None => panic!("syncinfo: out of tags"),
-            Some(tag) if tag.id == 0 => {
-                let oid = String::from_utf8(tag.expect_primitive().expect("octet string"))
-                    .expect("intermediate oid");
-                if oid != SYNC_INFO_OID {
-                    panic!("syncinfo: oid mismatch");
+///
+/// The entry must be an Intermediate Response. The value is decoded through the
+/// [`IntermediateParser`](../trait.IntermediateParser.html) implementation of `SyncInfo`.
+/// A malformed response, or one with a different OID, is reported as
+/// `LdapError::MalformedIntermediate`.
+pub fn parse_syncinfo(entry: ResultEntry) -> Result<SyncInfo> {
+    let ir = IntermediateResponse::parse(&entry)?;
+    if let Some(ref name) = ir.name {
+        if name != SYNC_INFO_OID {
+            return Err(LdapError::MalformedIntermediate("syncinfo: oid mismatch"));
+        }
+    }
+    ir.try_parse_val()
+}
+
+impl IntermediateParser for SyncInfo {
+    fn try_parse(val: &[u8]) -> Result<SyncInfo> {
+        let (choice, payload) = match parse_tag(val) {
+            Ok((_, StructureTag { id, class, payload }))
+                if class == TagClass::Context && id < 4 =>
+            {
+                (id, payload)
+            }
+            Ok(_) => return Err(LdapError::MalformedIntermediate("syncinfo: unknown choice")),
+            Err(_) => return Err(LdapError::MalformedIntermediate("syncinfo: not BER")),
+        };
+        let components = match payload {
+            PL::P(cookie) if choice == 0 => return Ok(SyncInfo::NewCookie(cookie.to_vec())),
+            PL::C(components) if choice != 0 => components,
+            _ => {
+                return Err(LdapError::MalformedIntermediate(
+                    "syncinfo: invalid encoding",
+                ))
+            }
+        };
+        let mut sync_cookie = None;
+        let mut flag = choice != 3;
+        let mut uuids = HashSet::new();
+        // Components must appear in order: cookie, flag, and, for syncIdSet, the UUIDs.
+        let mut next = 1;
+        for comp in components {
+            match comp {
+                StructureTag { id, class, .. }
+                    if class == TagClass::Universal
+                        && id == Types::OctetString as u64
+                        && next <= 1 =>
+                {
+                    sync_cookie = Some(comp.expect_primitive().ok_or(
+                        LdapError::MalformedIntermediate("syncinfo: invalid syncCookie"),
+                    )?);
+                    next = 2;
+                }
+                StructureTag { id, class, .. }
+                    if class == TagClass::Universal && id == Types::Boolean as u64 && next <= 2 =>
+                {
+                    flag = comp.expect_bool().ok_or(LdapError::MalformedIntermediate(
+                        "syncinfo: invalid boolean",
+                    ))?;
+                    next = 3;
+                }
+                StructureTag { id, class, .. }
+                    if class == TagClass::Universal
+                        && id == Types::Set as u64
+                        && choice == 3
+                        && next <= 3 =>
+                {
+                    uuids = comp
+                        .expect_constructed()
+                        .ok_or(LdapError::MalformedIntermediate(
+                            "syncinfo: invalid uuid set",
+                        ))?
+                        .into_iter()
+                        .map(|u| {
+                            u.expect_primitive()
+                                .ok_or(LdapError::MalformedIntermediate("syncinfo: invalid uuid"))
+                        })
+                        .collect::<Result<_>>()?;
+                    next = 4;
+                }
+                _ => {
+                    return Err(LdapError::MalformedIntermediate(
+                        "syncinfo: unrecognized component",
+                    ))
                 }
             }
-            Some(tag) if tag.id == 1 => {
-                let syncinfo_val =
-                    match parse_tag(tag.expect_primitive().expect("octet string").as_ref()) {
-                        Ok((_, tag)) => tag,
-                        _ => panic!("syncinfo: error parsing value"),
-                    };
-                return match syncinfo_val {
-                    StructureTag { id, class, payload } if class == TagClass::Context && id < 4 => {
-                        match id {
-                            0 => {
-                                let cookie = match payload {
-                                    PL::P(payload) => payload,
-                                    PL::C(_) => panic!("syncinfo: [0] not primitive"),
-                                };
-                                SyncInfo::NewCookie(cookie)
-                            }
-                            1..=3 => {
-                                let mut syncinfo_val = match payload {
-                                    PL::C(payload) => payload,
-                                    PL::P(_) => panic!("syncinfo: [1,2,3] not a sequence"),
-                                }
-                                .into_iter();
-                                let mut sync_cookie = None;
-                                let mut flag = id != 3;
-                                let mut uuids = HashSet::new();
-                                let mut pass = 1;
-                                'it: loop {
-                                    match syncinfo_val.next() {
-                                        None => break 'it,
-                                        Some(comp) => match comp {
-                                            StructureTag { id, class, .. }
-                                                if class == TagClass::Universal
-                                                    && id == Types::OctetString as u64
-                                                    && pass <= 1 =>
-                                            {
-                                                sync_cookie = comp.expect_primitive();
-                                            }
-                                            StructureTag { id, class, .. }
-                                                if class == TagClass::Universal
-                                                    && id == Types::Boolean as u64
-                                                    && pass <= 2 =>
-                                            {
-                                                flag = comp
-                                                    .expect_primitive()
-                                                    .expect("octet string")[0]
-                                                    != 0;
-                                            }
-                                            StructureTag { id, class, .. }
-                                                if class == TagClass::Universal
-                                                    && id == Types::Set as u64
-                                                    && pass <= 3 =>
-                                            {
-                                                uuids = comp
-                                                    .expect_constructed()
-                                                    .expect("uuid set")
-                                                    .into_iter()
-                                                    .map(|u| {
-                                                        u.expect_primitive().expect("octet string")
-                                                    })
-                                                    .collect();
-                                            }
-                                            _ => panic!(),
-                                        },
-                                    }
-                                    pass += 1;
-                                }
-                                match id {
-                                    1 => SyncInfo::RefreshDelete {
-                                        cookie: sync_cookie,
-                                        refresh_done: flag,
-                                    },
-                                    2 => SyncInfo::RefreshPresent {
-                                        cookie: sync_cookie,
-                                        refresh_done: flag,
-                                    },
-                                    3 => SyncInfo::SyncIdSet {
-                                        cookie: sync_cookie,
-                                        refresh_deletes: flag,
-                                        sync_uuids: uuids,
-                                    },
-                                    _ => panic!("syncinfo: got id > 3"),
-                                }
-                            }
-                            _ => panic!("syncinfo: got id > 3"),
-                        }
-                    }
-                    _ => panic!("syncinfo: got id > 3"),
-                };
+        }
+        Ok(match choice {
+            1 => SyncInfo::RefreshDelete {
+                cookie: sync_cookie,
+                refresh_done: flag,
+            },
+            2 => SyncInfo::RefreshPresent {
+                cookie: sync_cookie,
+                refresh_done: flag,
+            },
+            _ => SyncInfo::SyncIdSet {
+                cookie: sync_cookie,
+                refresh_deletes: flag,
+                sync_uuids: uuids,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn malformed_sync_state() {
+        let ss = SyncState::try_parse(b"\x30\x07\x0a\x01\x01\x04\x02id").expect("parsed");
+        assert!(matches!(ss.state, EntryState::Add));
+        assert_eq!(ss.entry_uuid, b"id");
+        assert!(ss.cookie.is_none());
+        for bad in [
+            &b"\x04\x00"[..],
+            b"\x30\x03\x0a\x01\x01",
+            b"\x30\x05\x0a\x01\x07\x04\x00",
+            b"\x30\x05\x02\x01\x01\x04\x00",
+            b"\x30\x07\x0a\x01\x01\x04\x00\x30\x00",
+        ] {
+            assert!(matches!(
+                SyncState::try_parse(bad),
+                Err(LdapError::MalformedControl(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn malformed_sync_info() {
+        let si =
+            SyncInfo::try_parse(b"\xa3\x0d\x04\x02ck\x31\x07\x04\x02u1\x04\x01u").expect("parsed");
+        assert!(matches!(
+            si,
+            SyncInfo::SyncIdSet { cookie: Some(ref c), refresh_deletes: false, ref sync_uuids }
+                if c == b"ck" && sync_uuids.len() == 2
+        ));
+        let si = SyncInfo::try_parse(b"\xa1\x03\x01\x01\x00").expect("parsed");
+        assert!(matches!(
+            si,
+            SyncInfo::RefreshDelete {
+                cookie: None,
+                refresh_done: false
             }
-            _ => panic!("syncinfo: unrecognized tag"),
+        ));
+        for bad in [
+            &b"\x04\x00"[..],
+            b"\xa4\x00",
+            b"\xa0\x00",
+            b"\x81\x00",
+            b"\xa2\x06\x01\x01\xff\x04\x01c",
+            b"\xa2\x02\x31\x00",
+            b"\xa3\x04\x31\x02\x30\x00",
+            b"\xa3",
+        ] {
+            assert!(matches!(
+                SyncInfo::try_parse(bad),
+                Err(LdapError::MalformedIntermediate(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn malformed_sync_done() {
+        let sd = SyncDone::try_parse(b"\x30\x07\x04\x02ck\x01\x01\xff").expect("parsed");
+        assert_eq!(sd.cookie.as_deref(), Some(&b"ck"[..]));
+        assert!(sd.refresh_deletes);
+        for bad in [
+            &b"\x01\x01\xff"[..],
+            b"\x30\x03\x02\x01\x01",
+            b"\x30\x04\x01\x02\xff\xff",
+            b"\x30\x02\x24\x00",
+        ] {
+            assert!(matches!(
+                SyncDone::try_parse(bad),
+                Err(LdapError::MalformedControl(_))
+            ));
         }
     }
 }
@@ -0,0 +1,157 @@
+use super::{ControlParser, MakeCritical, RawControl};
+use crate::result::{LdapError, Result};
+
+use bytes::BytesMut;
+
+use lber::common::TagClass;
+use lber::parse::parse_tag;
+use lber::structures::{ASNTag, Integer, OctetString, Sequence, Tag};
+use lber::universal::Types;
+use lber::write;
+
+pub const DIRSYNC_OID: &str = "1.2.840.113556.1.4.841";
+
+/// Return the entries and attributes for which the user has read access, instead of
+/// requiring the Replicating Directory Changes right.
+pub const LDAP_DIRSYNC_OBJECT_SECURITY: u32 = 0x0000_0001;
+/// Return parents before their children.
+pub const LDAP_DIRSYNC_ANCESTORS_FIRST_ORDER: u32 = 0x0000_0800;
+/// Don't return private data, such as passwords, even if the user has access to it.
+pub const LDAP_DIRSYNC_PUBLIC_DATA_ONLY: u32 = 0x0000_2000;
+/// Return only the changed values of multi-valued attributes.
+pub const LDAP_DIRSYNC_INCREMENTAL_VALUES: u32 = 0x8000_0000;
+
+/// Active Directory DirSync request control (MS-ADTS, section 3.1.1.3.4.1.3).
+///
+/// A Search with this control returns the entries changed since the state identified
+/// by the cookie, which is empty for the first synchronization. The response is
+/// [`DirSyncResp`](struct.DirSyncResp.html). The [`DirSync`](../adapters/struct.DirSync.html)
+/// adapter handles the repeated Searches needed to retrieve all changes.
+///
+/// Active Directory requires the control to be marked as critical.
+#[derive(Clone, Debug, Default)]
+pub struct DirSync {
+    /// Combination of the `LDAP_DIRSYNC_*` flags.
+    pub flags: u32,
+    /// Maximum size of the returned data in bytes. If zero, the server's limit is used.
+    pub max_bytes: i32,
+    /// Cookie from the previous synchronization.
+    pub cookie: Vec<u8>,
+}
+
+impl MakeCritical for DirSync {}
+
+impl From<DirSync> for RawControl {
+    fn from(ds: DirSync) -> RawControl {
+        let cookie_len = ds.cookie.len();
+        let cval = Tag::Sequence(Sequence {
+            inner: vec![
+                // The server expects the flags as a four-byte value, so the ones
+                // with the high bit set must be sent as negative numbers.
+                Tag::Integer(Integer {
+                    inner: ds.flags as i32 as i64,
+                    ..Default::default()
+                }),
+                Tag::Integer(Integer {
+                    inner: ds.max_bytes as i64,
+                    ..Default::default()
+                }),
+                Tag::OctetString(OctetString {
+                    inner: ds.cookie,
+                    ..Default::default()
+                }),
+            ],
+            ..Default::default()
+        })
+        .into_structure();
+        let mut buf = BytesMut::with_capacity(cookie_len + 24);
+        write::encode_into(&mut buf, cval).expect("encoded");
+        RawControl {
+            ctype: DIRSYNC_OID.to_owned(),
+            crit: false,
+            val: Some(Vec::from(&buf[..])),
+        }
+    }
+}
+
+/// Active Directory DirSync response control.
+#[derive(Clone, Debug)]
+pub struct DirSyncResp {
+    /// If `true`, there are more changes to retrieve with the returned cookie.
+    pub more_data: bool,
+    /// Cookie identifying the synchronization state.
+    pub cookie: Vec<u8>,
+}
+
+impl ControlParser for DirSyncResp {
+    fn try_parse(val: &[u8]) -> Result<DirSyncResp> {
+        let mut comps = match parse_tag(val) {
+            Ok((_, tag)) => tag.expect_constructed(),
+            _ => None,
+        }
+        .ok_or(LdapError::MalformedControl("dirsync: not a sequence"))?
+        .into_iter();
+        let more_data = comps
+            .next()
+            .and_then(|t| t.match_class(TagClass::Universal))
+            .and_then(|t| t.match_id(Types::Integer as u64))
+            .and_then(|t| t.expect_i64())
+            .ok_or(LdapError::MalformedControl(
+                "dirsync: invalid more results flag",
+            ))?
+            != 0;
+        let _unused = comps
+            .next()
+            .ok_or(LdapError::MalformedControl("dirsync: missing element"))?;
+        let cookie = comps
+            .next()
+            .and_then(|t| t.expect_primitive())
+            .ok_or(LdapError::MalformedControl("dirsync: invalid cookie"))?;
+        Ok(DirSyncResp { more_data, cookie })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        DirSync, DirSyncResp, LDAP_DIRSYNC_INCREMENTAL_VALUES, LDAP_DIRSYNC_OBJECT_SECURITY,
+    };
+    use crate::controls::{ControlParser, RawControl};
+    use crate::result::LdapError;
+
+    #[test]
+    fn dirsync_value() {
+        let ds = DirSync {
+            flags: LDAP_DIRSYNC_OBJECT_SECURITY | LDAP_DIRSYNC_INCREMENTAL_VALUES,
+            max_bytes: 0,
+            cookie: b"ck".to_vec(),
+        };
+        let rc = RawControl::from(ds);
+        assert_eq!(rc.ctype, "1.2.840.113556.1.4.841");
+        assert_eq!(
+            rc.val.expect("value"),
+            [
+                0x30, 0x0d, 0x02, 0x04, 0x80, 0x00, 0x00, 0x01, 0x02, 0x01, 0x00, 0x04, 0x02, b'c',
+                b'k'
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_dirsync_resp() {
+        let resp =
+            DirSyncResp::try_parse(b"\x30\x08\x02\x01\x01\x02\x01\x00\x04\x00").expect("parsed");
+        assert!(resp.more_data);
+        for bad in [
+            &b"\x02\x01\x01"[..],
+            b"\x30\x03\x04\x01\x01",
+            b"\x30\x06\x02\x01\x01\x02\x01\x00",
+            b"\x30\x0a\x02\x01\x01\x02\x01\x00\x30\x02\x04\x00",
+        ] {
+            assert!(matches!(
+                DirSyncResp::try_parse(bad),
+                Err(LdapError::MalformedControl(_))
+            ));
+        }
+    }
+}
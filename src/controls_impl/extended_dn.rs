@@ -0,0 +1,149 @@
+use super::{MakeCritical, RawControl};
+
+use bytes::BytesMut;
+
+use lber::structures::{ASNTag, Integer, Sequence, Tag};
+use lber::write;
+
+pub const EXTENDED_DN_OID: &str = "1.2.840.113556.1.4.529";
+
+/// Format of the GUID and SID in the DNs returned with the Extended DN control.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExtendedDnFormat {
+    /// Hexadecimal encoding of the binary values.
+    #[default]
+    Hex = 0,
+    /// Standard string forms, e.g., `S-1-5-21-...` for a SID.
+    String = 1,
+}
+
+/// Active Directory Extended DN control.
+///
+/// With this control, the DNs of the entries returned by a Search are prefixed with
+/// the object's GUID and, if it has one, SID, e.g., `<GUID=...>;<SID=...>;cn=...`.
+/// Such a DN can be split into its parts with [`parse_extended_dn()`](fn.parse_extended_dn.html).
+/// This control can only be used for requests; there is no corresponding response control.
+#[derive(Clone, Debug, Default)]
+pub struct ExtendedDnRequest {
+    /// Format of the GUID and SID.
+    pub format: ExtendedDnFormat,
+}
+
+impl MakeCritical for ExtendedDnRequest {}
+
+impl From<ExtendedDnRequest> for RawControl {
+    fn from(ed: ExtendedDnRequest) -> RawControl {
+        let cval = Tag::Sequence(Sequence {
+            inner: vec![Tag::Integer(Integer {
+                inner: ed.format as i64,
+                ..Default::default()
+            })],
+            ..Default::default()
+        })
+        .into_structure();
+        let mut buf = BytesMut::with_capacity(8);
+        write::encode_into(&mut buf, cval).expect("encoded");
+        RawControl {
+            ctype: EXTENDED_DN_OID.to_owned(),
+            crit: false,
+            val: Some(Vec::from(&buf[..])),
+        }
+    }
+}
+
+/// Parts of a DN returned with the Extended DN control.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtendedDn {
+    /// Object GUID.
+    pub guid: Option<String>,
+    /// Object SID, absent for objects which don't have one.
+    pub sid: Option<String>,
+    /// Plain DN of the object.
+    pub dn: String,
+}
+
+/// Split a DN returned with the Extended DN control into the GUID, SID, and the plain DN.
+///
+/// Components other than the GUID and the SID are skipped. A DN without the prefix
+/// components is returned unchanged, with both the GUID and the SID absent.
+///
+/// ```rust
+/// use ldap3::controls::parse_extended_dn;
+///
+/// let ed = parse_extended_dn("<GUID=b3d2e4c1>;<SID=0105>;cn=Test,dc=example,dc=org");
+/// assert_eq!(ed.guid.as_deref(), Some("b3d2e4c1"));
+/// assert_eq!(ed.sid.as_deref(), Some("0105"));
+/// assert_eq!(ed.dn, "cn=Test,dc=example,dc=org");
+/// ```
+pub fn parse_extended_dn(dn: &str) -> ExtendedDn {
+    let mut guid = None;
+    let mut sid = None;
+    let mut rest = dn;
+    while let Some(comp) = rest.strip_prefix('<') {
+        let Some((comp, after)) = comp.split_once('>') else {
+            break;
+        };
+        if let Some((name, value)) = comp.split_once('=') {
+            if name.eq_ignore_ascii_case("GUID") {
+                guid = Some(String::from(value));
+            } else if name.eq_ignore_ascii_case("SID") {
+                sid = Some(String::from(value));
+            }
+        }
+        match after.strip_prefix(';') {
+            Some(after) => rest = after,
+            None => {
+                rest = after;
+                break;
+            }
+        }
+    }
+    ExtendedDn {
+        guid,
+        sid,
+        dn: String::from(rest),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_extended_dn, ExtendedDnFormat, ExtendedDnRequest};
+    use crate::controls::RawControl;
+
+    #[test]
+    fn extended_dn_value() {
+        let rc = RawControl::from(ExtendedDnRequest::default());
+        assert_eq!(rc.ctype, "1.2.840.113556.1.4.529");
+        assert_eq!(rc.val.expect("value"), [0x30, 0x03, 0x02, 0x01, 0x00]);
+        let rc = RawControl::from(ExtendedDnRequest {
+            format: ExtendedDnFormat::String,
+        });
+        assert_eq!(rc.val.expect("value"), [0x30, 0x03, 0x02, 0x01, 0x01]);
+    }
+
+    #[test]
+    fn extended_dns() {
+        let ed = parse_extended_dn(
+            "<GUID={2c4a9e0e-3b33-4f4e-9b5c-0c1e8d3f2a11}>;<SID=S-1-5-21-1-2-3-1104>;CN=User,DC=ad,DC=example",
+        );
+        assert_eq!(
+            ed.guid.as_deref(),
+            Some("{2c4a9e0e-3b33-4f4e-9b5c-0c1e8d3f2a11}")
+        );
+        assert_eq!(ed.sid.as_deref(), Some("S-1-5-21-1-2-3-1104"));
+        assert_eq!(ed.dn, "CN=User,DC=ad,DC=example");
+
+        let ed = parse_extended_dn("<GUID=0e9e4a2c333b4e4f>;OU=Sales,DC=ad,DC=example");
+        assert_eq!(ed.guid.as_deref(), Some("0e9e4a2c333b4e4f"));
+        assert_eq!(ed.sid, None);
+        assert_eq!(ed.dn, "OU=Sales,DC=ad,DC=example");
+
+        let ed = parse_extended_dn("cn=plain,dc=example,dc=org");
+        assert_eq!((ed.guid, ed.sid), (None, None));
+        assert_eq!(ed.dn, "cn=plain,dc=example,dc=org");
+
+        let ed = parse_extended_dn("<GUID=ab");
+        assert_eq!(ed.guid, None);
+        assert_eq!(ed.dn, "<GUID=ab");
+    }
+}
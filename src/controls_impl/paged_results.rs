@@ -1,9 +1,10 @@
 use super::{ControlParser, MakeCritical, RawControl};
+use crate::result::{LdapError, Result};
 
 use bytes::BytesMut;
 
 use lber::common::TagClass;
-use lber::parse::{parse_tag, parse_uint};
+use lber::parse::parse_tag;
 use lber::structures::{ASNTag, Integer, OctetString, Sequence, Tag};
 use lber::universal::Types;
 use lber::write;
@@ -53,32 +54,59 @@ impl From<PagedResults> for RawControl {
 }
 
 impl ControlParser for PagedResults {
-    fn parse(val: &[u8]) -> PagedResults {
+    fn try_parse(val: &[u8]) -> Result<PagedResults> {
         let mut pr_comps = match parse_tag(val) {
-            Ok((_, tag)) => tag,
-            _ => panic!("failed to parse paged results value components"),
+            Ok((_, tag)) => tag.expect_constructed(),
+            _ => None,
         }
-        .expect_constructed()
-        .expect("paged results components")
+        .ok_or(LdapError::MalformedControl("paged results: not a sequence"))?
         .into_iter();
-        let size = match parse_uint(
-            pr_comps
-                .next()
-                .expect("element")
-                .match_class(TagClass::Universal)
-                .and_then(|t| t.match_id(Types::Integer as u64))
-                .and_then(|t| t.expect_primitive())
-                .expect("paged results size")
-                .as_slice(),
-        ) {
-            Ok((_, size)) => size as i32,
-            _ => panic!("failed to parse size"),
-        };
+        let size = pr_comps
+            .next()
+            .and_then(|t| t.match_class(TagClass::Universal))
+            .and_then(|t| t.match_id(Types::Integer as u64))
+            .and_then(|t| t.expect_i64())
+            .ok_or(LdapError::MalformedControl("paged results: invalid size"))?;
+        // The size is only an estimate, clamp it to the range of the field.
+        let size = size.clamp(0, i32::MAX as i64) as i32;
         let cookie = pr_comps
             .next()
-            .expect("element")
-            .expect_primitive()
-            .expect("octet string");
-        PagedResults { size, cookie }
+            .and_then(|t| t.expect_primitive())
+            .ok_or(LdapError::MalformedControl("paged results: invalid cookie"))?;
+        Ok(PagedResults { size, cookie })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn malformed_paged_results() {
+        let pr = PagedResults::try_parse(b"\x30\x05\x02\x01\x05\x04\x00").expect("parsed");
+        assert_eq!(pr.size, 5);
+        assert!(pr.cookie.is_empty());
+        for bad in [
+            &b""[..],
+            b"\x04\x00",
+            b"\x30\x00",
+            b"\x30\x03\x02\x01\x05",
+            b"\x30\x05\x04\x01\x05\x04\x00",
+            b"\x30\x07\x02\x01\x05\x30\x02\x04\x00",
+        ] {
+            assert!(matches!(
+                PagedResults::try_parse(bad),
+                Err(LdapError::MalformedControl(_))
+            ));
+        }
+        let raw = RawControl {
+            ctype: PAGED_RESULTS_OID.to_owned(),
+            crit: false,
+            val: None,
+        };
+        assert!(matches!(
+            raw.try_parse::<PagedResults>(),
+            Err(LdapError::MalformedControl("missing value"))
+        ));
     }
 }
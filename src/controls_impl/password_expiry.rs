@@ -0,0 +1,107 @@
+use super::{Control, ControlParser};
+use crate::result::Result;
+
+pub const PASSWORD_EXPIRED_OID: &str = "2.16.840.1.113730.3.4.4";
+pub const PASSWORD_EXPIRING_OID: &str = "2.16.840.1.113730.3.4.5";
+
+/// Netscape Password Expired response control.
+///
+/// Returned by servers derived from the Netscape Directory Server, such as 389 DS and
+/// the Sun/Oracle servers, with a Bind whose password has expired and must be changed.
+/// The Bind may still succeed, but the only operation the server will allow is changing
+/// the password. The value of the control, nominally `"0"`, carries no information,
+/// and is ignored.
+///
+/// This control can only be used for responses; see also
+/// [`LdapResult::password_expiry()`](../result/struct.LdapResult.html#method.password_expiry).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PasswordExpired;
+
+impl ControlParser for PasswordExpired {
+    fn try_parse(_val: &[u8]) -> Result<PasswordExpired> {
+        Ok(PasswordExpired)
+    }
+}
+
+/// Netscape Password Expiring response control.
+///
+/// Returned with a successful Bind whose password will expire soon. Unlike most
+/// controls, the value isn't BER-encoded: it's the number of seconds until expiry as
+/// a decimal string. A value which isn't a number is kept in its raw form, so that
+/// parsing never fails.
+///
+/// This control can only be used for responses; see also
+/// [`LdapResult::password_expiry()`](../result/struct.LdapResult.html#method.password_expiry).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PasswordExpiring {
+    /// Seconds until the password expires.
+    Seconds(u64),
+    /// Raw value which couldn't be parsed as a number of seconds.
+    Unparsed(Vec<u8>),
+}
+
+impl ControlParser for PasswordExpiring {
+    fn try_parse(val: &[u8]) -> Result<PasswordExpiring> {
+        let secs = std::str::from_utf8(val).ok().and_then(|s| {
+            s.trim_matches(|c: char| c.is_ascii_whitespace() || c == '\0')
+                .parse()
+                .ok()
+        });
+        Ok(match secs {
+            Some(secs) => PasswordExpiring::Seconds(secs),
+            None => PasswordExpiring::Unparsed(val.to_vec()),
+        })
+    }
+}
+
+/// Password expiry notice from the response controls of an operation, as returned by
+/// [`LdapResult::password_expiry()`](../result/struct.LdapResult.html#method.password_expiry).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PasswordExpiry {
+    /// The password has expired.
+    Expired,
+    /// The password will expire in the given number of seconds.
+    Expiring(u64),
+    /// The password will expire, but the value of the control, given here, couldn't
+    /// be parsed as the time until expiry.
+    Unparsed(Vec<u8>),
+}
+
+// Find the password expiry controls in `ctrls`. Expiry takes precedence if both are
+// present.
+pub(crate) fn password_expiry(ctrls: &[Control]) -> Option<PasswordExpiry> {
+    let find = |oid| ctrls.iter().map(|c| &c.1).find(|raw| raw.ctype == oid);
+    if find(PASSWORD_EXPIRED_OID).is_some() {
+        return Some(PasswordExpiry::Expired);
+    }
+    let raw = find(PASSWORD_EXPIRING_OID)?;
+    let val = raw.val.as_deref().unwrap_or_default();
+    Some(match PasswordExpiring::parse(val) {
+        PasswordExpiring::Seconds(secs) => PasswordExpiry::Expiring(secs),
+        PasswordExpiring::Unparsed(val) => PasswordExpiry::Unparsed(val),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn expiring_values() {
+        assert_eq!(
+            PasswordExpiring::parse(b"86400"),
+            PasswordExpiring::Seconds(86400)
+        );
+        assert_eq!(
+            PasswordExpiring::parse(b" 0\0"),
+            PasswordExpiring::Seconds(0)
+        );
+        for val in [&b"-5"[..], b"12 days", b"", b"\xff1", b"\x04\x0286"] {
+            assert_eq!(
+                PasswordExpiring::parse(val),
+                PasswordExpiring::Unparsed(val.to_vec())
+            );
+        }
+        assert_eq!(PasswordExpired::parse(b"garbage"), PasswordExpired);
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::HashSet;
+
+use super::{ControlParser, MakeCritical, RawControl};
+use crate::result::{LdapError, Result};
+
+use bytes::BytesMut;
+
+use lber::common::TagClass;
+use lber::parse::parse_tag;
+use lber::structures::{ASNTag, Boolean, Integer, Sequence, Tag};
+use lber::universal::Types;
+use lber::write;
+
+pub const PERSISTENT_SEARCH_OID: &str = "2.16.840.1.113730.3.4.3";
+pub const ENTRY_CHANGE_OID: &str = "2.16.840.1.113730.3.4.7";
+
+/// Type of change reported by a Persistent Search.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChangeType {
+    Add = 1,
+    Delete = 2,
+    Modify = 4,
+    ModDn = 8,
+}
+
+impl ChangeType {
+    fn from_i64(val: i64) -> Option<ChangeType> {
+        match val {
+            1 => Some(ChangeType::Add),
+            2 => Some(ChangeType::Delete),
+            4 => Some(ChangeType::Modify),
+            8 => Some(ChangeType::ModDn),
+            _ => None,
+        }
+    }
+}
+
+/// Persistent Search control
+/// ([draft-ietf-ldapext-psearch](https://tools.ietf.org/html/draft-ietf-ldapext-psearch-03)).
+///
+/// A Search with this control doesn't complete after returning the matching entries,
+/// but keeps returning the entries which are changed afterwards. The
+/// [`Ldap::watch()`](../struct.Ldap.html#method.watch) method sets up such a Search.
+#[derive(Clone, Debug)]
+pub struct PersistentSearch {
+    /// Types of changes which should be returned.
+    pub change_types: HashSet<ChangeType>,
+    /// If `true`, the entries matching the Search at the time of the request are not
+    /// returned, only the subsequently changed ones.
+    pub changes_only: bool,
+    /// If `true`, each changed entry is accompanied by an [`EntryChange`](struct.EntryChange.html)
+    /// control.
+    pub return_ecs: bool,
+}
+
+impl Default for PersistentSearch {
+    /// Return all types of changes, without the initial entries, with Entry Change
+    /// Notification controls.
+    fn default() -> Self {
+        PersistentSearch {
+            change_types: HashSet::from([
+                ChangeType::Add,
+                ChangeType::Delete,
+                ChangeType::Modify,
+                ChangeType::ModDn,
+            ]),
+            changes_only: true,
+            return_ecs: true,
+        }
+    }
+}
+
+impl MakeCritical for PersistentSearch {}
+
+impl From<PersistentSearch> for RawControl {
+    fn from(ps: PersistentSearch) -> RawControl {
+        let change_types = ps.change_types.iter().fold(0, |acc, ct| acc | *ct as i64);
+        let cval = Tag::Sequence(Sequence {
+            inner: vec![
+                Tag::Integer(Integer {
+                    inner: change_types,
+                    ..Default::default()
+                }),
+                Tag::Boolean(Boolean {
+                    inner: ps.changes_only,
+                    ..Default::default()
+                }),
+                Tag::Boolean(Boolean {
+                    inner: ps.return_ecs,
+                    ..Default::default()
+                }),
+            ],
+            ..Default::default()
+        })
+        .into_structure();
+        let mut buf = BytesMut::with_capacity(16);
+        write::encode_into(&mut buf, cval).expect("encoded");
+        RawControl {
+            ctype: PERSISTENT_SEARCH_OID.to_owned(),
+            crit: false,
+            val: Some(Vec::from(&buf[..])),
+        }
+    }
+}
+
+/// Entry Change Notification response control
+/// ([draft-ietf-ldapext-psearch](https://tools.ietf.org/html/draft-ietf-ldapext-psearch-03)).
+///
+/// The control accompanies an entry returned by a Persistent Search because of a change.
+#[derive(Clone, Debug)]
+pub struct EntryChange {
+    /// Type of the change.
+    pub change_type: ChangeType,
+    /// DN of the entry before a ModifyDN operation.
+    pub previous_dn: Option<String>,
+    /// Change number of the operation, if the server supports a change log.
+    pub change_number: Option<i64>,
+}
+
+impl ControlParser for EntryChange {
+    fn try_parse(val: &[u8]) -> Result<EntryChange> {
+        let mut comps = match parse_tag(val) {
+            Ok((_, tag)) => tag.expect_constructed(),
+            _ => None,
+        }
+        .ok_or(LdapError::MalformedControl("entry change: not a sequence"))?
+        .into_iter();
+        let change_type = comps
+            .next()
+            .and_then(|t| t.match_class(TagClass::Universal))
+            .and_then(|t| t.match_id(Types::Enumerated as u64))
+            .and_then(|t| t.expect_i64())
+            .and_then(ChangeType::from_i64)
+            .ok_or(LdapError::MalformedControl(
+                "entry change: invalid change type",
+            ))?;
+        let mut previous_dn = None;
+        let mut change_number = None;
+        for comp in comps {
+            match comp.id {
+                id if id == Types::OctetString as u64 => {
+                    let dn = comp
+                        .expect_primitive()
+                        .and_then(|dn| String::from_utf8(dn).ok())
+                        .ok_or(LdapError::MalformedControl(
+                            "entry change: invalid previous DN",
+                        ))?;
+                    previous_dn = Some(dn);
+                }
+                id if id == Types::Integer as u64 => {
+                    let number = comp.expect_i64().ok_or(LdapError::MalformedControl(
+                        "entry change: invalid change number",
+                    ))?;
+                    change_number = Some(number);
+                }
+                _ => {
+                    return Err(LdapError::MalformedControl(
+                        "entry change: unexpected element",
+                    ))
+                }
+            }
+        }
+        Ok(EntryChange {
+            change_type,
+            previous_dn,
+            change_number,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::{ChangeType, EntryChange, PersistentSearch};
+    use crate::controls::{ControlParser, RawControl};
+    use crate::result::LdapError;
+
+    #[test]
+    fn persistent_search_value() {
+        let ps = PersistentSearch {
+            change_types: HashSet::from([ChangeType::Add, ChangeType::ModDn]),
+            changes_only: false,
+            return_ecs: true,
+        };
+        let rc = RawControl::from(ps);
+        assert_eq!(rc.ctype, "2.16.840.1.113730.3.4.3");
+        assert_eq!(
+            rc.val.expect("value"),
+            [0x30, 0x09, 0x02, 0x01, 0x09, 0x01, 0x01, 0x00, 0x01, 0x01, 0xff]
+        );
+    }
+
+    #[test]
+    fn malformed_entry_change() {
+        let ec =
+            EntryChange::try_parse(b"\x30\x0a\x0a\x01\x08\x04\x02ab\x02\x01\x07").expect("parsed");
+        assert_eq!(ec.change_type, ChangeType::ModDn);
+        assert_eq!(ec.previous_dn.as_deref(), Some("ab"));
+        assert_eq!(ec.change_number, Some(7));
+        for bad in [
+            &b"\x0a\x01\x01"[..],
+            b"\x30\x00",
+            b"\x30\x03\x0a\x01\x03",
+            b"\x30\x07\x0a\x01\x01\x04\x02\xff\xfe",
+            b"\x30\x06\x0a\x01\x01\x01\x01\xff",
+        ] {
+            assert!(matches!(
+                EntryChange::try_parse(bad),
+                Err(LdapError::MalformedControl(_))
+            ));
+        }
+    }
+}
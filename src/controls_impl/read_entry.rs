@@ -3,8 +3,8 @@ use std::collections::HashMap;
 use bytes::BytesMut;
 
 use super::{ControlParser, MakeCritical, RawControl};
+use crate::result::{LdapError, Result};
 use crate::search::{ResultEntry, SearchEntry};
-use lber::parse::parse_tag;
 use lber::structures::{ASNTag, OctetString, Sequence, Tag};
 use lber::write;
 
@@ -107,15 +107,37 @@ fn from_read_entry<S: AsRef<str>>(re: ReadEntry<S>) -> RawControl {
 }
 
 impl ControlParser for ReadEntryResp {
-    fn parse(val: &[u8]) -> ReadEntryResp {
-        let tag = match parse_tag(val) {
-            Ok((_, tag)) => tag,
-            _ => panic!("failed to parse pre-read attribute values"),
-        };
-        let se = SearchEntry::construct(ResultEntry::new(tag));
-        ReadEntryResp {
+    fn try_parse(val: &[u8]) -> Result<ReadEntryResp> {
+        let entry = ResultEntry::from_bytes(val)?;
+        if entry.is_ref() {
+            return Err(LdapError::MalformedControl("read entry: not an entry"));
+        }
+        let se = SearchEntry::construct(entry);
+        Ok(ReadEntryResp {
             attrs: se.attrs,
             bin_attrs: se.bin_attrs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn malformed_read_entry_resp() {
+        let resp = ReadEntryResp::try_parse(
+            b"\x64\x10\x04\x03o=x\x30\x09\x30\x07\x04\x01a\x31\x02\x04\x00",
+        )
+        .expect("parsed");
+        assert_eq!(resp.attrs["a"], vec![String::new()]);
+        for bad in [
+            &b"\x30\x00"[..],
+            b"\x64\x05\x04\x03o=x",
+            b"\x64\x07\x04\x03o=x\x04\x00",
+            b"\x73\x03\x04\x01x",
+        ] {
+            assert!(ReadEntryResp::try_parse(bad).is_err());
         }
     }
 }
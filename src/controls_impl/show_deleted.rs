@@ -0,0 +1,22 @@
+use super::{MakeCritical, RawControl};
+
+/// Active Directory Show Deleted control.
+///
+/// A Search with this control also returns the deleted objects (tombstones).
+/// This control can only be used for requests; there is no corresponding
+/// response control.
+pub struct ShowDeleted;
+
+pub const SHOW_DELETED_OID: &str = "1.2.840.113556.1.4.417";
+
+impl MakeCritical for ShowDeleted {}
+
+impl From<ShowDeleted> for RawControl {
+    fn from(_sd: ShowDeleted) -> RawControl {
+        RawControl {
+            ctype: SHOW_DELETED_OID.to_owned(),
+            crit: false,
+            val: None,
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use super::{MakeCritical, RawControl};
+
+/// Tree Delete control (MS-ADTS, section 3.1.1.3.4.1.15).
+///
+/// Attached to a Delete operation, this control makes the server delete the whole subtree
+/// rooted at the named entry. It's supported by Active Directory and some other servers, and
+/// should be marked critical, so that a server which doesn't support it won't attempt
+/// a regular delete. There is no corresponding result control.
+pub struct TreeDelete;
+
+pub const TREE_DELETE_OID: &str = "1.2.840.113556.1.4.805";
+
+impl MakeCritical for TreeDelete {}
+
+impl From<TreeDelete> for RawControl {
+    fn from(_td: TreeDelete) -> RawControl {
+        RawControl {
+            ctype: TREE_DELETE_OID.to_owned(),
+            crit: false,
+            val: None,
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use super::RawControl;
+
+/// Transaction Specification control ([RFC 5805](https://tools.ietf.org/html/rfc5805)).
+///
+/// The control attaches an update operation to the transaction identified by `txn_id`,
+/// as returned by the Start Transaction extended operation. It's always critical. There
+/// is no corresponding result control. The [`Transaction`](../struct.Transaction.html)
+/// API attaches the control automatically.
+#[derive(Clone, Debug)]
+pub struct TxnSpec {
+    /// Transaction identifier.
+    pub txn_id: Vec<u8>,
+}
+
+pub const TXN_SPEC_OID: &str = "1.3.6.1.1.21.2";
+
+impl From<TxnSpec> for RawControl {
+    fn from(ts: TxnSpec) -> RawControl {
+        RawControl {
+            ctype: TXN_SPEC_OID.to_owned(),
+            crit: true,
+            val: Some(ts.txn_id),
+        }
+    }
+}
@@ -0,0 +1,697 @@
+//! Computing the modifications which turn one entry into another.
+//!
+//! [`diff_entries()`](fn.diff_entries.html) compares the textual attributes of two
+//! [`SearchEntry`](../struct.SearchEntry.html) instances, typically the current state of an
+//! entry read from the directory and the desired state, and produces a list of
+//! [`Mod`](../enum.Mod.html) elements for a Modify operation which would make the former
+//! equal to the latter. [`diff_entries_raw()`](fn.diff_entries_raw.html) does the same for
+//! all attributes, including binary ones, producing byte-valued modifications.
+//!
+//! Attribute names are matched case-insensitively, and values are compared exactly, as
+//! sets: neither their order nor duplicates matter. An attribute without values is treated
+//! as absent. The modifications are ordered by the lowercased attribute name, so the output
+//! for the same pair of entries is always the same.
+//!
+//! ```rust,no_run
+//! # use ldap3::{LdapConn, Scope, SearchEntry};
+//! # use ldap3::diff::{diff_entries, DiffOptions};
+//! # fn main() -> ldap3::result::Result<()> {
+//! # let mut ldap = LdapConn::new("ldap://localhost:2389")?;
+//! # let desired: SearchEntry = unimplemented!();
+//! let (mut entries, _) = ldap
+//!     .search(&desired.dn, Scope::Base, "(objectClass=*)", vec!["*"])?
+//!     .success()?;
+//! let current = SearchEntry::construct(entries.remove(0));
+//! let opts = DiffOptions::new().ignore("userPassword");
+//! let mods = diff_entries(&current, &desired, &opts);
+//! if !mods.is_empty() {
+//!     ldap.modify(&desired.dn, mods)?.success()?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use crate::ldap::Mod;
+use crate::matching::caseignore_cmp;
+use crate::search::SearchEntry;
+
+/// Treatment of the `objectClass` attribute when computing the differences.
+///
+/// Changing the object classes of an entry is restricted by the servers: the structural
+/// object class can't be removed at all, and removing an auxiliary one may require removing
+/// its attributes in the same operation. For that reason, by default the differences in
+/// object classes are only used to add the missing ones.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ObjectClassPolicy {
+    /// Don't generate any modifications of `objectClass`.
+    Ignore,
+    /// Add the object classes missing from the current entry, but don't remove any.
+    #[default]
+    AddOnly,
+    /// Treat `objectClass` like any other attribute.
+    Full,
+}
+
+/// Options for computing the differences between entries.
+#[derive(Clone, Debug, Default)]
+pub struct DiffOptions {
+    minimal_changes: bool,
+    ignore_attrs: Vec<String>,
+    object_class: ObjectClassPolicy,
+}
+
+impl DiffOptions {
+    /// Create an instance with default values: no minimal changes, no ignored attributes,
+    /// and the `AddOnly` object class policy.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// If `true`, an attribute present in both entries with different values is changed by
+    /// deleting the extra values and adding the missing ones. Otherwise, which is the default,
+    /// the values of the attribute are replaced with the desired ones.
+    ///
+    /// Minimal changes are preferable for large multi-valued attributes like group membership,
+    /// since they don't rewrite the whole attribute, and concurrent changes of unrelated values
+    /// are preserved. For single-valued attributes, the result is the same either way.
+    pub fn minimal_changes(mut self, minimal_changes: bool) -> Self {
+        self.minimal_changes = minimal_changes;
+        self
+    }
+
+    /// Exclude an attribute from the comparison. Can be called repeatedly.
+    pub fn ignore(mut self, attr: &str) -> Self {
+        self.ignore_attrs.push(attr.to_ascii_lowercase());
+        self
+    }
+
+    /// Set the treatment of the `objectClass` attribute.
+    pub fn object_class(mut self, policy: ObjectClassPolicy) -> Self {
+        self.object_class = policy;
+        self
+    }
+}
+
+/// Options for [`Ldap::ensure_entry()`](../struct.Ldap.html#method.ensure_entry).
+#[derive(Clone, Debug, Default)]
+pub struct EnsureOptions {
+    pub(crate) delete_extra: bool,
+    object_class: ObjectClassPolicy,
+    pub(crate) relax_rules: bool,
+}
+
+impl EnsureOptions {
+    /// Create an instance with default values: extra attributes are left alone, object
+    /// classes are only added, and no Relax Rules control is sent.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// If `true`, user attributes of the existing entry which aren't among the desired ones
+    /// are deleted. Otherwise, which is the default, only the listed attributes are examined.
+    pub fn delete_extra(mut self, delete_extra: bool) -> Self {
+        self.delete_extra = delete_extra;
+        self
+    }
+
+    /// Set the treatment of the `objectClass` attribute of the existing entry.
+    pub fn object_class(mut self, policy: ObjectClassPolicy) -> Self {
+        self.object_class = policy;
+        self
+    }
+
+    /// If `true`, attach the critical [`RelaxRules`](../controls/struct.RelaxRules.html)
+    /// control to the Modify operation. Some servers require it for changing the structural
+    /// object class of an entry, or modifying otherwise read-only attributes.
+    pub fn relax_rules(mut self, relax_rules: bool) -> Self {
+        self.relax_rules = relax_rules;
+        self
+    }
+}
+
+/// Outcome of [`Ldap::ensure_entry()`](../struct.Ldap.html#method.ensure_entry).
+#[derive(Clone, Debug, PartialEq)]
+pub enum EnsureOutcome {
+    /// The entry didn't exist, and was added.
+    Created,
+    /// The entry existed, and was modified with the contained list of changes.
+    Modified(Vec<Mod<String>>),
+    /// The entry already had the desired attribute values.
+    Unchanged,
+}
+
+// Attribute values keyed by the lowercased name, with the original name.
+type AttrMap<V> = HashMap<String, (String, HashSet<V>)>;
+
+fn collect<'e, V, I>(attrs: I, opts: &DiffOptions) -> AttrMap<V>
+where
+    V: Eq + Hash,
+    I: Iterator<Item = (&'e String, Vec<V>)>,
+{
+    let mut map: AttrMap<V> = HashMap::new();
+    for (attr, vals) in attrs {
+        let key = attr.to_ascii_lowercase();
+        if vals.is_empty() || opts.ignore_attrs.contains(&key) {
+            continue;
+        }
+        map.entry(key)
+            .or_insert_with(|| (attr.clone(), HashSet::new()))
+            .1
+            .extend(vals);
+    }
+    map
+}
+
+fn diff<V>(
+    mut current: AttrMap<V>,
+    mut desired: AttrMap<V>,
+    opts: &DiffOptions,
+    name: impl Fn(&str) -> V,
+) -> Vec<Mod<V>>
+where
+    V: AsRef<[u8]> + Eq + Hash + Clone,
+{
+    let mut keys: Vec<_> = current.keys().chain(desired.keys()).cloned().collect();
+    keys.sort_unstable();
+    keys.dedup();
+    let mut mods = vec![];
+    for key in keys {
+        let full = key != "objectclass" || opts.object_class == ObjectClassPolicy::Full;
+        if key == "objectclass" && opts.object_class == ObjectClassPolicy::Ignore {
+            continue;
+        }
+        match (current.remove(&key), desired.remove(&key)) {
+            (None, Some((attr, vals))) => mods.push(Mod::Add(name(&attr), vals)),
+            (Some((attr, _)), None) => {
+                if full {
+                    mods.push(Mod::Delete(name(&attr), HashSet::new()));
+                }
+            }
+            (Some((_, cur_vals)), Some((attr, vals))) => {
+                if cur_vals == vals {
+                    continue;
+                }
+                let added: HashSet<_> = vals.difference(&cur_vals).cloned().collect();
+                if !full {
+                    if !added.is_empty() {
+                        mods.push(Mod::Add(name(&attr), added));
+                    }
+                } else if opts.minimal_changes {
+                    let deleted: HashSet<_> = cur_vals.difference(&vals).cloned().collect();
+                    if !deleted.is_empty() {
+                        mods.push(Mod::Delete(name(&attr), deleted));
+                    }
+                    if !added.is_empty() {
+                        mods.push(Mod::Add(name(&attr), added));
+                    }
+                } else {
+                    mods.push(Mod::Replace(name(&attr), vals));
+                }
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+    mods
+}
+
+/// Compute the modifications of the textual attributes which turn `current` into `desired`.
+///
+/// An attribute present only in `desired` is added, one present only in `current` is
+/// deleted in its entirety, and one present in both with different values is either
+/// replaced or changed value by value, depending on
+/// [`DiffOptions::minimal_changes()`](struct.DiffOptions.html#method.minimal_changes).
+/// If values are deleted and added in the same attribute, the deletion comes first.
+/// The attribute names in the modifications are spelled as in `desired`, if present there.
+///
+/// Binary attributes are not examined; use [`diff_entries_raw()`](fn.diff_entries_raw.html)
+/// if the entries have them. The DNs of the entries are not compared.
+pub fn diff_entries(
+    current: &SearchEntry,
+    desired: &SearchEntry,
+    opts: &DiffOptions,
+) -> Vec<Mod<String>> {
+    let text = |entry: &SearchEntry| {
+        collect(
+            entry.attrs.iter().map(|(attr, vals)| (attr, vals.clone())),
+            opts,
+        )
+    };
+    diff(text(current), text(desired), opts, str::to_owned)
+}
+
+/// Compute the modifications of all attributes which turn `current` into `desired`.
+///
+/// This function works like [`diff_entries()`](fn.diff_entries.html), but it examines both
+/// textual and binary attributes, and produces byte-valued modifications, with textual
+/// values converted to their UTF-8 representation. A value is the same whether it's found
+/// among the textual or the binary attributes.
+pub fn diff_entries_raw(
+    current: &SearchEntry,
+    desired: &SearchEntry,
+    opts: &DiffOptions,
+) -> Vec<Mod<Vec<u8>>> {
+    let raw = |entry: &SearchEntry| {
+        let text = entry.attrs.iter().map(|(attr, vals)| {
+            let vals: Vec<_> = vals.iter().map(|val| val.as_bytes().to_vec()).collect();
+            (attr, vals)
+        });
+        let binary = entry
+            .bin_attrs
+            .iter()
+            .map(|(attr, vals)| (attr, vals.clone()));
+        collect(text.chain(binary), opts)
+    };
+    diff(raw(current), raw(desired), opts, |attr| {
+        attr.as_bytes().to_vec()
+    })
+}
+
+// Unescaped attribute/value pairs of the leftmost RDN of the DN. Only the escapes
+// of single characters are recognized, which is enough for comparing values.
+fn rdn_values(dn: &str) -> Vec<(String, String)> {
+    let mut pairs = vec![];
+    let mut component = String::new();
+    let mut chars = dn.chars();
+    loop {
+        match chars.next() {
+            Some('\\') => component.extend(chars.next()),
+            Some(c) if c != ',' && c != '+' => component.push(c),
+            end => {
+                if let Some((attr, val)) = component.split_once('=') {
+                    pairs.push((attr.trim().to_owned(), val.trim().to_owned()));
+                }
+                component.clear();
+                if end != Some('+') {
+                    break;
+                }
+            }
+        }
+    }
+    pairs
+}
+
+// Compute the Modify list for Ldap::ensure_entry(), given the existing entry.
+pub(crate) fn ensure_mods(
+    current: &SearchEntry,
+    mut desired: SearchEntry,
+    opts: &EnsureOptions,
+) -> Vec<Mod<String>> {
+    // An entry found under a DN which differs only in case may have RDN values which
+    // also differ in case; keep the server's spelling, since changing the value of a
+    // naming attribute would amount to a rename.
+    for (attr, rdn_val) in rdn_values(&current.dn) {
+        let vals = desired
+            .attrs
+            .iter_mut()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&attr))
+            .map(|(_, vals)| vals);
+        for val in vals.into_iter().flatten() {
+            if caseignore_cmp(val, &rdn_val) == Ordering::Equal {
+                val.clone_from(&rdn_val);
+            }
+        }
+    }
+    let mut diff_opts = DiffOptions::new()
+        .minimal_changes(true)
+        .object_class(opts.object_class);
+    if !opts.delete_extra {
+        for attr in current.attrs.keys() {
+            if !desired
+                .attrs
+                .keys()
+                .any(|name| name.eq_ignore_ascii_case(attr))
+            {
+                diff_opts = diff_opts.ignore(attr);
+            }
+        }
+    }
+    diff_entries(current, &desired, &diff_opts)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::conn::LdapConnAsync;
+    use crate::fixtures::{self, message, result};
+    use crate::result::LdapError;
+
+    use lber::structures::Tag;
+    use tokio::sync::mpsc;
+
+    fn entry(attrs: &[(&str, &[&str])]) -> SearchEntry {
+        SearchEntry {
+            dn: String::from("cn=test,dc=example,dc=org"),
+            attrs: attrs
+                .iter()
+                .map(|(attr, vals)| {
+                    (
+                        attr.to_string(),
+                        vals.iter().map(|val| val.to_string()).collect(),
+                    )
+                })
+                .collect(),
+            bin_attrs: HashMap::new(),
+            truncated: vec![],
+        }
+    }
+
+    fn set(vals: &[&str]) -> HashSet<String> {
+        vals.iter().map(|val| val.to_string()).collect()
+    }
+
+    // Apply the modifications to the attributes keyed by lowercased names, as a server would.
+    fn apply_mods(
+        attrs: &mut BTreeMap<String, HashSet<String>>,
+        mods: Vec<Mod<String>>,
+    ) -> Result<(), String> {
+        for m in mods {
+            match m {
+                Mod::Add(attr, vals) => {
+                    let cur = attrs.entry(attr.to_ascii_lowercase()).or_default();
+                    if vals.is_empty() || vals.iter().any(|val| cur.contains(val)) {
+                        return Err(format!("add to {}: empty or existing value", attr));
+                    }
+                    cur.extend(vals);
+                }
+                Mod::Delete(attr, vals) => {
+                    let key = attr.to_ascii_lowercase();
+                    let cur = attrs
+                        .get_mut(&key)
+                        .ok_or_else(|| format!("delete from {}: no such attribute", attr))?;
+                    if vals.is_empty() {
+                        cur.clear();
+                    } else {
+                        for val in vals {
+                            if !cur.remove(&val) {
+                                return Err(format!("delete from {}: no such value", attr));
+                            }
+                        }
+                    }
+                    if cur.is_empty() {
+                        attrs.remove(&key);
+                    }
+                }
+                Mod::Replace(attr, vals) => {
+                    let key = attr.to_ascii_lowercase();
+                    if vals.is_empty() {
+                        attrs.remove(&key);
+                    } else {
+                        attrs.insert(key, vals);
+                    }
+                }
+                Mod::Increment(attr, _) => return Err(format!("increment of {}", attr)),
+            }
+        }
+        Ok(())
+    }
+
+    fn normalized(entry: &SearchEntry) -> BTreeMap<String, HashSet<String>> {
+        let mut attrs: BTreeMap<String, HashSet<String>> = BTreeMap::new();
+        for (attr, vals) in entry.attrs.iter().filter(|(_, vals)| !vals.is_empty()) {
+            attrs
+                .entry(attr.to_ascii_lowercase())
+                .or_default()
+                .extend(vals.iter().cloned());
+        }
+        attrs
+    }
+
+    #[test]
+    fn basic_mods() {
+        let current = entry(&[
+            ("objectClass", &["top", "person"]),
+            ("cn", &["test"]),
+            ("sn", &["Old"]),
+            ("description", &["gone"]),
+            ("member", &["a", "b", "c"]),
+            ("modifyTimestamp", &["1"]),
+        ]);
+        let desired = entry(&[
+            ("objectclass", &["top", "inetOrgPerson"]),
+            ("CN", &["test"]),
+            ("sn", &["New"]),
+            ("mail", &["test@example.org"]),
+            ("member", &["b", "c", "d"]),
+        ]);
+        let opts = DiffOptions::new().ignore("modifyTimestamp");
+        assert_eq!(
+            diff_entries(&current, &desired, &opts),
+            vec![
+                Mod::Delete(String::from("description"), HashSet::new()),
+                Mod::Add(String::from("mail"), set(&["test@example.org"])),
+                Mod::Replace(String::from("member"), set(&["b", "c", "d"])),
+                Mod::Add(String::from("objectclass"), set(&["inetOrgPerson"])),
+                Mod::Replace(String::from("sn"), set(&["New"])),
+            ]
+        );
+        let opts = opts
+            .minimal_changes(true)
+            .object_class(ObjectClassPolicy::Full);
+        let mods = diff_entries(&current, &desired, &opts);
+        assert_eq!(
+            &mods[2..4],
+            [
+                Mod::Delete(String::from("member"), set(&["a"])),
+                Mod::Add(String::from("member"), set(&["d"])),
+            ]
+        );
+        assert!(mods.contains(&Mod::Delete(String::from("objectclass"), set(&["person"]))));
+        let opts = DiffOptions::new().object_class(ObjectClassPolicy::Ignore);
+        assert!(diff_entries(&entry(&[]), &entry(&[("objectClass", &["x"])]), &opts).is_empty());
+    }
+
+    #[test]
+    fn raw_mods() {
+        let mut current = entry(&[("cn", &["test"])]);
+        current
+            .bin_attrs
+            .insert(String::from("jpegPhoto"), vec![vec![0xff, 0xd8]]);
+        let mut desired = entry(&[]);
+        desired.bin_attrs.insert(
+            String::from("cn"),
+            vec![b"test".to_vec(), b"\xfftest".to_vec()],
+        );
+        assert_eq!(
+            diff_entries_raw(
+                &current,
+                &desired,
+                &DiffOptions::new().minimal_changes(true)
+            ),
+            vec![
+                Mod::Add(b"cn".to_vec(), HashSet::from([b"\xfftest".to_vec()])),
+                Mod::Delete(b"jpegPhoto".to_vec(), HashSet::new()),
+            ]
+        );
+    }
+
+    // Small xorshift generator, so that the test is reproducible.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % n
+        }
+
+        fn entry(&mut self) -> SearchEntry {
+            let mut entry = entry(&[]);
+            for attr in ["cn", "CN", "sn", "mail", "member", "description"] {
+                if self.below(3) == 0 {
+                    continue;
+                }
+                let vals = (0..self.below(4))
+                    .map(|_| format!("v{}", self.below(5)))
+                    .collect();
+                entry.attrs.insert(attr.to_owned(), vals);
+            }
+            entry
+        }
+    }
+
+    #[test]
+    fn applied_mods_yield_desired() {
+        let mut rng = Rng(0x2545f4914f6cdd1d);
+        for _ in 0..500 {
+            let (current, desired) = (rng.entry(), rng.entry());
+            for minimal in [false, true] {
+                let opts = DiffOptions::new().minimal_changes(minimal);
+                let mut attrs = normalized(&current);
+                apply_mods(&mut attrs, diff_entries(&current, &desired, &opts)).expect("applied");
+                assert_eq!(
+                    attrs,
+                    normalized(&desired),
+                    "{:?} -> {:?}",
+                    current,
+                    desired
+                );
+                let mut attrs = normalized(&desired);
+                assert_eq!(
+                    apply_mods(&mut attrs, diff_entries(&desired, &desired, &opts)),
+                    Ok(())
+                );
+                assert_eq!(attrs, normalized(&desired));
+            }
+        }
+    }
+
+    // Operation received by the scripted server: protocol op tag, DN, and control OIDs.
+    type Received = (u64, String, Vec<String>);
+
+    // Scripted server holding at most one entry. A Search returns the entry, or finishes
+    // with `search_rc` if there's none; Add and Modify always succeed.
+    async fn server(
+        existing: Option<SearchEntry>,
+        search_rc: i64,
+    ) -> (String, mpsc::UnboundedReceiver<Received>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let url = fixtures::serve(move |req| {
+            let ctrls = req.controls.iter().map(|ctrl| ctrl.ctype.clone()).collect();
+            tx.send((req.op.id, req.dn(), ctrls)).expect("sent");
+            match req.op.id {
+                3 => match existing {
+                    Some(ref entry) => {
+                        let mut reply = message(req.msgid, Tag::StructureTag(entry.to_structure()));
+                        reply.extend(message(req.msgid, result(5, 0)));
+                        reply
+                    }
+                    None => message(req.msgid, result(5, search_rc)),
+                },
+                6 => message(req.msgid, result(7, 0)),
+                8 => message(req.msgid, result(9, 0)),
+                _ => vec![],
+            }
+        })
+        .await;
+        (url, rx)
+    }
+
+    async fn ensure(
+        existing: Option<SearchEntry>,
+        search_rc: i64,
+        attrs: &[(&str, &[&str])],
+        opts: EnsureOptions,
+    ) -> (crate::result::Result<EnsureOutcome>, Vec<Received>) {
+        let (url, mut received) = server(existing, search_rc).await;
+        let (conn, mut ldap) = LdapConnAsync::new(&url).await.expect("connection");
+        crate::drive!(conn);
+        let attrs = attrs
+            .iter()
+            .map(|(attr, vals)| (attr.to_string(), set(vals)))
+            .collect();
+        let res = ldap
+            .ensure_entry("cn=test,dc=example,dc=org", attrs, &opts)
+            .await;
+        drop(ldap);
+        let mut ops = vec![];
+        while let Some(op) = received.recv().await {
+            ops.push(op);
+        }
+        // Dropping the handle unbinds.
+        assert_eq!(ops.pop().map(|op| op.0), Some(2));
+        (res, ops)
+    }
+
+    const DN: &str = "cn=test,dc=example,dc=org";
+
+    #[tokio::test]
+    async fn ensure_created() {
+        let (res, ops) = ensure(
+            None,
+            32,
+            &[("objectClass", &["person"]), ("cn", &["test"])],
+            EnsureOptions::new(),
+        )
+        .await;
+        assert_eq!(res.expect("ensured"), EnsureOutcome::Created);
+        let ops: Vec<_> = ops.iter().map(|(id, dn, _)| (*id, dn.as_str())).collect();
+        assert_eq!(ops, [(3, DN), (8, DN)]);
+    }
+
+    #[tokio::test]
+    async fn ensure_search_failure() {
+        let (res, ops) = ensure(None, 50, &[("cn", &["test"])], EnsureOptions::new()).await;
+        assert!(matches!(
+            res,
+            Err(LdapError::LdapResult { result }) if result.rc == 50
+        ));
+        assert_eq!(ops.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn ensure_modified() {
+        let existing = entry(&[("cn", &["test"]), ("sn", &["Old"]), ("description", &["x"])]);
+        let (res, ops) = ensure(
+            Some(existing.clone()),
+            0,
+            &[("cn", &["test"]), ("sn", &["New"])],
+            EnsureOptions::new().relax_rules(true),
+        )
+        .await;
+        assert_eq!(
+            res.expect("ensured"),
+            EnsureOutcome::Modified(vec![
+                Mod::Delete(String::from("sn"), set(&["Old"])),
+                Mod::Add(String::from("sn"), set(&["New"])),
+            ])
+        );
+        assert_eq!(ops[1].0, 6);
+        assert_eq!(ops[1].2, ["1.3.6.1.4.1.4203.666.5.12"]);
+        let (res, _) = ensure(
+            Some(existing),
+            0,
+            &[("cn", &["test"])],
+            EnsureOptions::new().delete_extra(true),
+        )
+        .await;
+        let mods = match res.expect("ensured") {
+            EnsureOutcome::Modified(mods) => mods,
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        };
+        assert_eq!(mods.len(), 2);
+        assert!(mods.contains(&Mod::Delete(String::from("description"), HashSet::new())));
+    }
+
+    #[tokio::test]
+    async fn ensure_unchanged() {
+        let existing = entry(&[("cn", &["test"]), ("description", &["x"])]);
+        let (res, ops) = ensure(
+            Some(existing),
+            0,
+            &[("CN", &["test"])],
+            EnsureOptions::new(),
+        )
+        .await;
+        assert_eq!(res.expect("ensured"), EnsureOutcome::Unchanged);
+        assert_eq!(ops.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn ensure_differently_cased_dn() {
+        let mut existing = entry(&[("cn", &["Test"]), ("sn", &["Old"])]);
+        existing.dn = String::from("CN=Test,DC=Example,DC=Org");
+        let (res, ops) = ensure(
+            Some(existing),
+            0,
+            &[("cn", &["test"]), ("sn", &["New"])],
+            EnsureOptions::new(),
+        )
+        .await;
+        assert_eq!(
+            res.expect("ensured"),
+            EnsureOutcome::Modified(vec![
+                Mod::Delete(String::from("sn"), set(&["Old"])),
+                Mod::Add(String::from("sn"), set(&["New"])),
+            ])
+        );
+        let ops: Vec<_> = ops.iter().map(|(id, dn, _)| (*id, dn.as_str())).collect();
+        assert_eq!(ops, [(3, DN), (6, "CN=Test,DC=Example,DC=Org")]);
+    }
+}
@@ -1,15 +1,26 @@
 use lber::common::TagClass;
 use lber::structures::{OctetString, Tag};
 
+use crate::result::{LdapError, Result};
+
 mod whoami;
-pub use self::whoami::{WhoAmI, WhoAmIResp};
+pub use self::whoami::{WhoAmI, WhoAmIResp, WHOAMI_OID};
 
 mod starttls;
+#[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
 pub use self::starttls::StartTLS;
+pub use self::starttls::STARTTLS_OID;
+
+mod cancel;
+pub use self::cancel::Cancel;
 
 mod passmod;
+pub(crate) use self::passmod::PASSMOD_OID;
 pub use self::passmod::{PasswordModify, PasswordModifyResp};
 
+mod txn;
+pub use self::txn::{EndTxn, EndTxnResp, StartTxn, StartTxnResp};
+
 /// Generic extended operation.
 ///
 /// Since the same struct can be used both for requests and responses,
@@ -32,12 +43,32 @@ impl Exop {
     pub fn parse<T: ExopParser>(&self) -> T {
         T::parse(self.val.as_ref().expect("value"))
     }
+
+    /// Parse the generic exop into a exop-specific struct, returning an error
+    /// instead of panicking if the value is absent or malformed.
+    pub fn try_parse<T: ExopParser>(&self) -> Result<T> {
+        match self.val {
+            Some(ref val) => T::try_parse(val),
+            None => Err(LdapError::MalformedExop("missing value")),
+        }
+    }
 }
 
 /// Conversion trait for Extended response values.
-pub trait ExopParser {
+///
+/// An implementation provides [`try_parse()`](#tymethod.try_parse), which must not panic
+/// on malformed input; [`parse()`](#method.parse) is derived from it.
+pub trait ExopParser: Sized {
+    /// Convert the raw BER value into an exop-specific struct, or return an error
+    /// if the value is malformed.
+    fn try_parse(val: &[u8]) -> Result<Self>;
+
     /// Convert the raw BER value into an exop-specific struct.
-    fn parse(val: &[u8]) -> Self;
+    ///
+    /// The conversion panics if the value is malformed.
+    fn parse(val: &[u8]) -> Self {
+        Self::try_parse(val).expect("exop value")
+    }
 }
 
 pub fn construct_exop(exop: Exop) -> Vec<Tag> {
@@ -48,11 +79,7 @@ pub fn construct_exop(exop: Exop) -> Vec<Tag> {
         inner: exop.name.unwrap().into_bytes(),
     })];
     if let Some(val) = exop.val {
-        seq.push(Tag::OctetString(OctetString {
-            id: 1,
-            class: TagClass::Context,
-            inner: val,
-        }));
+        seq.push(OctetString::from_tagged_bytes(TagClass::Context, 1, val));
     }
     seq
 }
@@ -0,0 +1,39 @@
+use super::Exop;
+use crate::RequestId;
+
+use bytes::BytesMut;
+
+use lber::structures::{ASNTag, Integer, Sequence, Tag};
+use lber::write;
+
+pub const CANCEL_OID: &str = "1.3.6.1.1.8";
+
+/// Cancel extended operation ([RFC 3909](https://tools.ietf.org/html/rfc3909)).
+///
+/// Unlike Abandon, Cancel has a response, and the cancelled operation is also
+/// answered with the result code 118 (canceled). The response to a successful
+/// Cancel doesn't carry a value, so there is no response struct.
+#[derive(Clone, Debug)]
+pub struct Cancel {
+    /// Message ID of the operation to cancel.
+    pub msgid: RequestId,
+}
+
+impl From<Cancel> for Exop {
+    fn from(c: Cancel) -> Exop {
+        let cancel_val = Tag::Sequence(Sequence {
+            inner: vec![Tag::Integer(Integer {
+                inner: c.msgid as i64,
+                ..Default::default()
+            })],
+            ..Default::default()
+        })
+        .into_structure();
+        let mut buf = BytesMut::new();
+        write::encode_into(&mut buf, cancel_val).expect("encoded");
+        Exop {
+            name: Some(CANCEL_OID.to_owned()),
+            val: Some(Vec::from(&buf[..])),
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use super::{Exop, ExopParser};
+use crate::result::{LdapError, Result};
 
 use bytes::BytesMut;
 
@@ -85,25 +86,45 @@ impl<'a> From<PasswordModify<'a>> for Exop {
 }
 
 impl ExopParser for PasswordModifyResp {
-    fn parse(val: &[u8]) -> PasswordModifyResp {
-        let tags = match parse_tag(val) {
-            Ok((_, tag)) => tag,
-            _ => panic!("failed to parse password modify return value"),
-        };
-        let mut tags = tags
-            .expect_constructed()
-            .expect("password modify sequence")
-            .into_iter();
+    fn try_parse(val: &[u8]) -> Result<PasswordModifyResp> {
+        let mut tags = match parse_tag(val) {
+            Ok((_, tag)) => tag.expect_constructed(),
+            _ => None,
+        }
+        .ok_or(LdapError::MalformedExop("password modify: not a sequence"))?
+        .into_iter();
         let gen_pass = tags
             .next()
-            .expect("element")
-            .match_class(TagClass::Context)
+            .and_then(|t| t.match_class(TagClass::Context))
             .and_then(|t| t.match_id(0))
             .and_then(|t| t.expect_primitive())
-            .expect("generated password")
-            .as_slice()
-            .to_owned();
-        let gen_pass = String::from_utf8(gen_pass).expect("generated password not UTF-8");
-        PasswordModifyResp { gen_pass }
+            .and_then(|pass| String::from_utf8(pass).ok())
+            .ok_or(LdapError::MalformedExop(
+                "password modify: invalid generated password",
+            ))?;
+        Ok(PasswordModifyResp { gen_pass })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn malformed_passmod_resp() {
+        let resp = PasswordModifyResp::try_parse(b"\x30\x04\x80\x02pw").expect("parsed");
+        assert_eq!(resp.gen_pass, "pw");
+        for bad in [
+            &b"\x80\x02pw"[..],
+            b"\x30\x00",
+            b"\x30\x04\x81\x02pw",
+            b"\x30\x04\x04\x02pw",
+            b"\x30\x04\x80\x02\xff\xfe",
+        ] {
+            assert!(matches!(
+                PasswordModifyResp::try_parse(bad),
+                Err(LdapError::MalformedExop(_))
+            ));
+        }
     }
 }
@@ -0,0 +1,249 @@
+use super::{Exop, ExopParser};
+use crate::controls_impl::{parse_controls, Control};
+use crate::result::{LdapError, Result};
+use crate::RequestId;
+
+use bytes::BytesMut;
+
+use lber::parse::{parse_int, parse_tag};
+use lber::structure::{StructureTag, PL};
+use lber::structures::{ASNTag, Boolean, OctetString, Sequence, Tag};
+use lber::universal::Types;
+use lber::write;
+
+pub const START_TXN_OID: &str = "1.3.6.1.1.21.1";
+pub const END_TXN_OID: &str = "1.3.6.1.1.21.3";
+
+/// Start Transaction extended operation ([RFC 5805](https://tools.ietf.org/html/rfc5805)).
+///
+/// The request has no value. The response value is the identifier of the new transaction,
+/// which must be sent with each update in the transaction in a
+/// [`TxnSpec`](../controls/struct.TxnSpec.html) control. See
+/// [`Ldap::begin_transaction()`](../struct.Ldap.html#method.begin_transaction) for a
+/// higher-level interface.
+#[derive(Clone, Debug)]
+pub struct StartTxn;
+
+/// Start Transaction response.
+#[derive(Clone, Debug)]
+pub struct StartTxnResp {
+    /// Transaction identifier.
+    pub txn_id: Vec<u8>,
+}
+
+impl From<StartTxn> for Exop {
+    fn from(_st: StartTxn) -> Exop {
+        Exop {
+            name: Some(START_TXN_OID.to_owned()),
+            val: None,
+        }
+    }
+}
+
+impl ExopParser for StartTxnResp {
+    fn try_parse(val: &[u8]) -> Result<StartTxnResp> {
+        if val.is_empty() {
+            return Err(LdapError::MalformedTxnResponse("no transaction identifier"));
+        }
+        Ok(StartTxnResp {
+            txn_id: val.to_vec(),
+        })
+    }
+}
+
+/// End Transaction extended operation ([RFC 5805](https://tools.ietf.org/html/rfc5805)).
+///
+/// Commit or abort the transaction identified by `txn_id`.
+#[derive(Clone, Debug)]
+pub struct EndTxn {
+    /// If `true`, commit the transaction, otherwise abort it.
+    pub commit: bool,
+    /// Transaction identifier.
+    pub txn_id: Vec<u8>,
+}
+
+/// End Transaction response.
+///
+/// The response value is optional; if the server doesn't send it, both fields are empty.
+#[derive(Clone, Debug, Default)]
+pub struct EndTxnResp {
+    /// Message ID of the update which caused the transaction to fail, if the server
+    /// identified it.
+    pub failed_msgid: Option<RequestId>,
+    /// Response controls of the updates in a committed transaction, by the message ID
+    /// of the update, for the updates which have them.
+    pub update_controls: Vec<(RequestId, Vec<Control>)>,
+}
+
+impl From<EndTxn> for Exop {
+    fn from(et: EndTxn) -> Exop {
+        let mut inner = vec![];
+        // Commit is the DEFAULT, which DER omits.
+        if !et.commit {
+            inner.push(Tag::Boolean(Boolean {
+                inner: false,
+                ..Default::default()
+            }));
+        }
+        inner.push(Tag::OctetString(OctetString {
+            inner: et.txn_id,
+            ..Default::default()
+        }));
+        let end_val = Tag::Sequence(Sequence {
+            inner,
+            ..Default::default()
+        })
+        .into_structure();
+        let mut buf = BytesMut::new();
+        write::encode_into(&mut buf, end_val).expect("encoded");
+        Exop {
+            name: Some(END_TXN_OID.to_owned()),
+            val: Some(Vec::from(&buf[..])),
+        }
+    }
+}
+
+fn parse_msgid(val: &[u8]) -> Result<RequestId> {
+    match parse_int(val) {
+        Ok((_, id)) if (0..=RequestId::MAX as i64).contains(&id) => Ok(id as RequestId),
+        _ => Err(LdapError::MalformedTxnResponse("invalid message ID")),
+    }
+}
+
+// Check the shape of a Controls sequence, since parse_controls() panics on bad input.
+fn valid_controls(ctrls: &StructureTag) -> bool {
+    const BOOLEAN: u64 = Types::Boolean as u64;
+    const OCTET_STRING: u64 = Types::OctetString as u64;
+    let ctrls = match ctrls.payload {
+        PL::C(ref ctrls) => ctrls,
+        PL::P(_) => return false,
+    };
+    ctrls.iter().all(|ctrl| {
+        let comps = match ctrl.payload {
+            PL::C(ref comps) if !comps.is_empty() && comps.len() <= 3 => comps,
+            _ => return false,
+        };
+        let ids: Option<Vec<_>> = comps
+            .iter()
+            .map(|comp| match comp.payload {
+                PL::P(ref v) if comp.id == BOOLEAN && v.len() != 1 => None,
+                PL::P(_) => Some(comp.id),
+                PL::C(_) => None,
+            })
+            .collect();
+        let ctype_utf8 = match comps[0].payload {
+            PL::P(ref v) => std::str::from_utf8(v).is_ok(),
+            PL::C(_) => false,
+        };
+        ctype_utf8
+            && matches!(
+                ids.as_deref(),
+                Some([OCTET_STRING])
+                    | Some([OCTET_STRING, BOOLEAN | OCTET_STRING])
+                    | Some([OCTET_STRING, BOOLEAN, OCTET_STRING])
+            )
+    })
+}
+
+impl ExopParser for EndTxnResp {
+    fn try_parse(val: &[u8]) -> Result<EndTxnResp> {
+        let elems = match parse_tag(val) {
+            Ok((&[], tag)) => match tag.payload {
+                PL::C(elems) => elems,
+                PL::P(_) => return Err(LdapError::MalformedTxnResponse("not a sequence")),
+            },
+            _ => return Err(LdapError::MalformedTxnResponse("BER decoding error")),
+        };
+        let mut resp = EndTxnResp::default();
+        for elem in elems {
+            match elem.payload {
+                PL::P(ref id) if elem.id == Types::Integer as u64 => {
+                    resp.failed_msgid = Some(parse_msgid(id)?);
+                }
+                PL::C(updates) if elem.id == Types::Sequence as u64 => {
+                    for update in updates {
+                        let mut parts = match update.payload {
+                            PL::C(parts) if parts.len() == 2 => parts.into_iter(),
+                            _ => return Err(LdapError::MalformedTxnResponse("invalid update")),
+                        };
+                        let msgid = match parts.next().expect("element").payload {
+                            PL::P(ref id) => parse_msgid(id)?,
+                            PL::C(_) => {
+                                return Err(LdapError::MalformedTxnResponse("invalid message ID"))
+                            }
+                        };
+                        let ctrls = parts.next().expect("element");
+                        if !valid_controls(&ctrls) {
+                            return Err(LdapError::MalformedTxnResponse("invalid controls"));
+                        }
+                        resp.update_controls.push((msgid, parse_controls(ctrls)));
+                    }
+                }
+                _ => return Err(LdapError::MalformedTxnResponse("unexpected element")),
+            }
+        }
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn end_txn_encoding() {
+        let commit = Exop::from(EndTxn {
+            commit: true,
+            txn_id: b"t1".to_vec(),
+        });
+        assert_eq!(commit.val.unwrap(), b"\x30\x04\x04\x02t1");
+        let abort = Exop::from(EndTxn {
+            commit: false,
+            txn_id: b"t1".to_vec(),
+        });
+        assert_eq!(abort.val.unwrap(), b"\x30\x07\x01\x01\x00\x04\x02t1");
+    }
+
+    #[test]
+    fn malformed_start_txn_resp() {
+        assert_eq!(
+            StartTxnResp::try_parse(b"t1").expect("parsed").txn_id,
+            b"t1"
+        );
+        assert!(matches!(
+            StartTxnResp::try_parse(b""),
+            Err(LdapError::MalformedTxnResponse(_))
+        ));
+    }
+
+    #[test]
+    fn end_txn_resp_decoding() {
+        // Failed message ID 5, and controls of message 6, one with criticality.
+        let val = b"\x30\x1a\x02\x01\x05\x30\x15\x30\x13\x02\x01\x06\x30\x0e\x30\x0c\
+                    \x04\x03\x31\x2e\x32\x01\x01\xff\x04\x02ok";
+        let resp = EndTxnResp::try_parse(val).expect("decoded");
+        assert_eq!(resp.failed_msgid, Some(5));
+        assert_eq!(resp.update_controls.len(), 1);
+        let (id, ref ctrls) = resp.update_controls[0];
+        assert_eq!(id, 6);
+        assert_eq!(ctrls[0].1.ctype, "1.2");
+        assert!(ctrls[0].1.crit);
+        assert_eq!(ctrls[0].1.val.as_deref(), Some(&b"ok"[..]));
+
+        let empty = EndTxnResp::try_parse(b"\x30\x00").expect("decoded");
+        assert!(empty.failed_msgid.is_none() && empty.update_controls.is_empty());
+
+        for bad in [
+            &b"\x04\x00"[..],
+            b"\x30\x03\x02\x01\xff",
+            b"\x30\x03\x04\x01\x05",
+            b"\x30\x08\x30\x06\x30\x04\x02\x01\x06\x04\x00",
+            b"\x30\x0c\x30\x0a\x30\x08\x02\x01\x06\x30\x03\x30\x01\x05",
+        ] {
+            assert!(matches!(
+                EndTxnResp::try_parse(bad),
+                Err(LdapError::MalformedTxnResponse(_))
+            ));
+        }
+    }
+}
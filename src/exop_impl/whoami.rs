@@ -1,6 +1,7 @@
 use std::str;
 
 use super::{Exop, ExopParser};
+use crate::result::{LdapError, Result};
 
 pub const WHOAMI_OID: &str = "1.3.6.1.4.1.4203.1.11.3";
 
@@ -30,9 +31,33 @@ impl From<WhoAmI> for Exop {
 }
 
 impl ExopParser for WhoAmIResp {
-    fn parse(val: &[u8]) -> WhoAmIResp {
-        WhoAmIResp {
-            authzid: str::from_utf8(val).expect("authzid").to_owned(),
-        }
+    fn try_parse(val: &[u8]) -> Result<WhoAmIResp> {
+        let authzid =
+            str::from_utf8(val).map_err(|_| LdapError::MalformedExop("whoami: invalid authzid"))?;
+        Ok(WhoAmIResp {
+            authzid: authzid.to_owned(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn malformed_whoami_resp() {
+        assert_eq!(
+            WhoAmIResp::try_parse(b"dn:o=x").expect("parsed").authzid,
+            "dn:o=x"
+        );
+        assert!(matches!(
+            WhoAmIResp::try_parse(b"u:\xff"),
+            Err(LdapError::MalformedExop(_))
+        ));
+        let exop = Exop::from(WhoAmI);
+        assert!(matches!(
+            exop.try_parse::<WhoAmIResp>(),
+            Err(LdapError::MalformedExop("missing value"))
+        ));
     }
 }
@@ -0,0 +1,321 @@
+//! Export of search results to NDJSON and CSV.
+//!
+//! The functions in this module drive a [`SearchStream`](../struct.SearchStream.html)
+//! to completion, writing each entry to the output as it arrives, so that the size of
+//! the result set isn't limited by the available memory. Search result references and
+//! intermediate messages are counted, but not written. If the stream returns an error,
+//! the output is flushed and the error is returned; the entries written so far remain
+//! in the output. If writing to the output fails, the Search is abandoned, and the write
+//! error is returned.
+//!
+//! ```rust,no_run
+//! # use ldap3::export::{self, ExportOptions};
+//! # use ldap3::{LdapConnAsync, Scope};
+//! # async fn f() -> ldap3::result::Result<()> {
+//! # let (conn, mut ldap) = LdapConnAsync::new("ldap://localhost:2389").await?;
+//! # ldap3::drive!(conn);
+//! let mut stream = ldap
+//!     .streaming_search("dc=example,dc=org", Scope::Subtree, "(objectClass=*)", vec!["*"])
+//!     .await?;
+//! let opts = ExportOptions::new().flatten(true);
+//! let stats = export::ndjson(&mut stream, std::io::stdout().lock(), &opts).await?;
+//! eprintln!("{} entries", stats.entries);
+//! stats.result.success()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! This module is available with the __serde__ feature.
+
+use std::io::{self, Write};
+
+use crate::result::{LdapResult, Result};
+use crate::search::{SearchEntry, SearchStream};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::Value;
+
+/// Options for exporting search results.
+#[derive(Clone, Debug)]
+pub struct ExportOptions {
+    flatten: bool,
+    separator: String,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            flatten: false,
+            separator: String::from(";"),
+        }
+    }
+}
+
+impl ExportOptions {
+    /// Create an instance with the default values: attributes aren't flattened, and
+    /// multiple values in a CSV field are separated by `;`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// In NDJSON output, represent an attribute with a single value as a string instead
+    /// of an array with one element. Applies to both textual and binary attributes.
+    pub fn flatten(mut self, flatten: bool) -> Self {
+        self.flatten = flatten;
+        self
+    }
+
+    /// Set the separator between the values of a multi-valued attribute in a CSV field.
+    pub fn separator(mut self, separator: &str) -> Self {
+        self.separator = String::from(separator);
+        self
+    }
+}
+
+/// Statistics of a completed export.
+#[derive(Clone, Debug)]
+pub struct ExportStats {
+    /// Number of entries written.
+    pub entries: usize,
+    /// Number of search result references, which are not written.
+    pub referrals: usize,
+    /// Result of the Search.
+    pub result: LdapResult,
+}
+
+/// Write the entries returned by a Search as newline-delimited JSON.
+///
+/// Each entry is written on its own line, in the format produced by the serde support
+/// of [`SearchEntry`](../struct.SearchEntry.html): an object with the members `dn`,
+/// `attrs`, and `bin_attrs`, where binary values are Base64-encoded. With
+/// [`flatten()`](struct.ExportOptions.html#method.flatten), single values aren't
+/// wrapped in arrays.
+pub async fn ndjson<'a, S, A, W>(
+    stream: &mut SearchStream<'a, S, A>,
+    out: W,
+    opts: &ExportOptions,
+) -> Result<ExportStats>
+where
+    S: AsRef<str> + Send + Sync + 'a,
+    A: AsRef<[S]> + Send + Sync + 'a,
+    W: Write,
+{
+    drive(stream, out, |out, entry| {
+        serde_json::to_writer(&mut *out, &json_entry(entry, opts)).map_err(io::Error::from)?;
+        out.write_all(b"\n")
+    })
+    .await
+}
+
+/// Write the entries returned by a Search as CSV, with the given columns.
+///
+/// The first record is the header, containing the column names. Every column is the
+/// name of an attribute, matched case-insensitively, except for `dn`, which is the entry
+/// DN. Multiple values are joined with the [`separator`](struct.ExportOptions.html#method.separator),
+/// and binary values are Base64-encoded; a missing attribute is an empty field. Fields
+/// are quoted as needed, and records are terminated by CRLF, as described in
+/// [RFC 4180](https://tools.ietf.org/html/rfc4180).
+pub async fn csv<'a, S, A, W>(
+    stream: &mut SearchStream<'a, S, A>,
+    mut out: W,
+    columns: &[&str],
+    opts: &ExportOptions,
+) -> Result<ExportStats>
+where
+    S: AsRef<str> + Send + Sync + 'a,
+    A: AsRef<[S]> + Send + Sync + 'a,
+    W: Write,
+{
+    let header: Vec<_> = columns.iter().map(|col| String::from(*col)).collect();
+    if let Err(e) = write_record(&mut out, &header) {
+        return write_failed(stream, e).await;
+    }
+    drive(stream, out, |out, entry| {
+        write_record(out, &csv_record(entry, columns, opts))
+    })
+    .await
+}
+
+async fn drive<'a, S, A, W, F>(
+    stream: &mut SearchStream<'a, S, A>,
+    mut out: W,
+    mut write: F,
+) -> Result<ExportStats>
+where
+    S: AsRef<str> + Send + Sync + 'a,
+    A: AsRef<[S]> + Send + Sync + 'a,
+    W: Write,
+    F: FnMut(&mut W, &SearchEntry) -> io::Result<()>,
+{
+    let mut entries = 0;
+    let mut referrals = 0;
+    loop {
+        let entry = match stream.next().await {
+            Ok(Some(entry)) => entry,
+            Ok(None) => break,
+            Err(e) => {
+                let _ = out.flush();
+                return Err(e);
+            }
+        };
+        if entry.is_ref() {
+            referrals += 1;
+            continue;
+        }
+        if entry.is_intermediate() {
+            continue;
+        }
+        if let Err(e) = write(&mut out, &SearchEntry::construct(entry)) {
+            return write_failed(stream, e).await;
+        }
+        entries += 1;
+    }
+    if let Err(e) = out.flush() {
+        return write_failed(stream, e).await;
+    }
+    Ok(ExportStats {
+        entries,
+        referrals,
+        result: stream.finish().await,
+    })
+}
+
+// Abandon the Search after a failed write, so that the connection doesn't keep
+// receiving its entries, and return the write error.
+async fn write_failed<'a, S, A, T>(stream: &mut SearchStream<'a, S, A>, e: io::Error) -> Result<T>
+where
+    S: AsRef<str> + Send + Sync + 'a,
+    A: AsRef<[S]> + Send + Sync + 'a,
+{
+    let _ = stream.abandon().await;
+    Err(e.into())
+}
+
+fn json_entry(entry: &SearchEntry, opts: &ExportOptions) -> Value {
+    let mut value = serde_json::to_value(entry).expect("entry value");
+    if opts.flatten {
+        for map in ["attrs", "bin_attrs"] {
+            if let Some(Value::Object(attrs)) = value.get_mut(map) {
+                for vals in attrs.values_mut() {
+                    if let Value::Array(arr) = vals {
+                        if arr.len() == 1 {
+                            *vals = arr.remove(0);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    value
+}
+
+fn csv_record(entry: &SearchEntry, columns: &[&str], opts: &ExportOptions) -> Vec<String> {
+    columns
+        .iter()
+        .map(|col| {
+            if col.eq_ignore_ascii_case("dn") {
+                return entry.dn.clone();
+            }
+            let text = entry
+                .attrs
+                .iter()
+                .filter(|(name, _)| name.eq_ignore_ascii_case(col))
+                .flat_map(|(_, vals)| vals.iter().cloned());
+            let bin = entry
+                .bin_attrs
+                .iter()
+                .filter(|(name, _)| name.eq_ignore_ascii_case(col))
+                .flat_map(|(_, vals)| vals.iter().map(|val| STANDARD.encode(val)));
+            text.chain(bin).collect::<Vec<_>>().join(&opts.separator)
+        })
+        .collect()
+}
+
+fn write_record<W: Write>(out: &mut W, fields: &[String]) -> io::Result<()> {
+    for (n, field) in fields.iter().enumerate() {
+        if n > 0 {
+            out.write_all(b",")?;
+        }
+        if field.contains([',', '"', '\r', '\n']) {
+            write!(out, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            out.write_all(field.as_bytes())?;
+        }
+    }
+    out.write_all(b"\r\n")
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    use serde_json::json;
+
+    fn entry() -> SearchEntry {
+        SearchEntry {
+            dn: String::from("cn=Doe\\, John,dc=example,dc=org"),
+            attrs: HashMap::from([
+                (String::from("cn"), vec![String::from("Doe, John")]),
+                (
+                    String::from("mail"),
+                    vec![
+                        String::from("jdoe@example.org"),
+                        String::from("john@example.org"),
+                    ],
+                ),
+                (
+                    String::from("description"),
+                    vec![String::from("say \"hi\"")],
+                ),
+            ]),
+            bin_attrs: HashMap::from([(String::from("jpegPhoto"), vec![b"\xff\xd8".to_vec()])]),
+            truncated: vec![],
+        }
+    }
+
+    #[test]
+    fn json_flattening() {
+        let opts = ExportOptions::new();
+        assert_eq!(
+            json_entry(&entry(), &opts),
+            json!({
+                "dn": "cn=Doe\\, John,dc=example,dc=org",
+                "attrs": {
+                    "cn": ["Doe, John"],
+                    "mail": ["jdoe@example.org", "john@example.org"],
+                    "description": ["say \"hi\""],
+                },
+                "bin_attrs": { "jpegPhoto": ["/9g="] },
+            })
+        );
+        let opts = ExportOptions::new().flatten(true);
+        assert_eq!(
+            json_entry(&entry(), &opts),
+            json!({
+                "dn": "cn=Doe\\, John,dc=example,dc=org",
+                "attrs": {
+                    "cn": "Doe, John",
+                    "mail": ["jdoe@example.org", "john@example.org"],
+                    "description": "say \"hi\"",
+                },
+                "bin_attrs": { "jpegPhoto": "/9g=" },
+            })
+        );
+    }
+
+    #[test]
+    fn csv_quoting() {
+        let opts = ExportOptions::new().separator(" | ");
+        let columns = ["DN", "mail", "jpegphoto", "description", "sn"];
+        let mut out = vec![];
+        write_record(&mut out, &csv_record(&entry(), &columns, &opts)).expect("record");
+        assert_eq!(
+            String::from_utf8(out).expect("utf-8"),
+            "\"cn=Doe\\, John,dc=example,dc=org\",jdoe@example.org | john@example.org,/9g=,\
+             \"say \"\"hi\"\"\",\r\n"
+        );
+    }
+}
@@ -41,21 +41,21 @@ pub fn parse_matched_values(input: impl AsRef<[u8]>) -> Result<Tag, ()> {
     }
 }
 
-const AND_FILT: u64 = 0;
-const OR_FILT: u64 = 1;
-const NOT_FILT: u64 = 2;
-
-const EQ_MATCH: u64 = 3;
-const SUBSTR_MATCH: u64 = 4;
-const GTE_MATCH: u64 = 5;
-const LTE_MATCH: u64 = 6;
-const PRES_MATCH: u64 = 7;
-const APPROX_MATCH: u64 = 8;
-const EXT_MATCH: u64 = 9;
-
-const SUB_INITIAL: u64 = 0;
-const SUB_ANY: u64 = 1;
-const SUB_FINAL: u64 = 2;
+pub(crate) const AND_FILT: u64 = 0;
+pub(crate) const OR_FILT: u64 = 1;
+pub(crate) const NOT_FILT: u64 = 2;
+
+pub(crate) const EQ_MATCH: u64 = 3;
+pub(crate) const SUBSTR_MATCH: u64 = 4;
+pub(crate) const GTE_MATCH: u64 = 5;
+pub(crate) const LTE_MATCH: u64 = 6;
+pub(crate) const PRES_MATCH: u64 = 7;
+pub(crate) const APPROX_MATCH: u64 = 8;
+pub(crate) const EXT_MATCH: u64 = 9;
+
+pub(crate) const SUB_INITIAL: u64 = 0;
+pub(crate) const SUB_ANY: u64 = 1;
+pub(crate) const SUB_FINAL: u64 = 2;
 
 fn filtexpr(i: &[u8]) -> IResult<&[u8], Tag> {
     alt((filter, item))(i)
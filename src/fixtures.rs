@@ -0,0 +1,253 @@
+// Fixtures for the unit tests which talk to a scripted server: builders for the
+// messages the server sends, and a reader decoding the requests it receives.
+
+#[cfg(feature = "sync")]
+use std::io::Read;
+
+use crate::controls::RawControl;
+use crate::controls_impl::{build_tag, parse_controls};
+
+use bytes::BytesMut;
+use lber::common::TagClass;
+use lber::parse::{parse_tag, parse_uint};
+use lber::structure::{StructureTag, PL};
+use lber::structures::{ASNTag, Enumerated, Integer, OctetString, Sequence, Set, Tag};
+use lber::write;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+pub(crate) fn octet_string(val: &[u8]) -> Tag {
+    Tag::OctetString(OctetString {
+        inner: val.to_vec(),
+        ..Default::default()
+    })
+}
+
+// SearchResultEntry with the given DN and attributes.
+pub(crate) fn entry(dn: &str, attrs: &[(&str, &[&[u8]])]) -> Tag {
+    let attrs = attrs
+        .iter()
+        .map(|(name, vals)| {
+            Tag::Sequence(Sequence {
+                inner: vec![
+                    octet_string(name.as_bytes()),
+                    Tag::Set(Set {
+                        inner: vals.iter().map(|val| octet_string(val)).collect(),
+                        ..Default::default()
+                    }),
+                ],
+                ..Default::default()
+            })
+        })
+        .collect();
+    Tag::Sequence(Sequence {
+        id: 4,
+        class: TagClass::Application,
+        inner: vec![
+            octet_string(dn.as_bytes()),
+            Tag::Sequence(Sequence {
+                inner: attrs,
+                ..Default::default()
+            }),
+        ],
+    })
+}
+
+// LDAPResult with an empty matched DN and diagnostic message, as the protocol op
+// with the given tag.
+pub(crate) fn result(op: u64, rc: i64) -> Tag {
+    Tag::Sequence(Sequence {
+        id: op,
+        class: TagClass::Application,
+        inner: vec![
+            Tag::Enumerated(Enumerated {
+                inner: rc,
+                ..Default::default()
+            }),
+            octet_string(b""),
+            octet_string(b""),
+        ],
+    })
+}
+
+pub(crate) fn encode(tag: Tag) -> Vec<u8> {
+    let mut buf = BytesMut::new();
+    write::encode_into(&mut buf, tag.into_structure()).expect("encoded");
+    buf.to_vec()
+}
+
+pub(crate) fn message(msgid: i64, op: Tag) -> Vec<u8> {
+    message_with_controls(msgid, op, vec![])
+}
+
+pub(crate) fn message_with_controls(msgid: i64, op: Tag, ctrls: Vec<RawControl>) -> Vec<u8> {
+    let mut inner = vec![
+        Tag::Integer(Integer {
+            inner: msgid,
+            ..Default::default()
+        }),
+        op,
+    ];
+    if !ctrls.is_empty() {
+        inner.push(Tag::Sequence(Sequence {
+            id: 0,
+            class: TagClass::Context,
+            inner: ctrls
+                .into_iter()
+                .map(|ctrl| Tag::StructureTag(build_tag(ctrl)))
+                .collect(),
+        }));
+    }
+    encode(Tag::Sequence(Sequence {
+        inner,
+        ..Default::default()
+    }))
+}
+
+// Request received by a scripted server.
+pub(crate) struct Request {
+    pub(crate) msgid: i64,
+    pub(crate) op: StructureTag,
+    pub(crate) controls: Vec<RawControl>,
+    // Encoded message.
+    pub(crate) raw: Vec<u8>,
+}
+
+impl Request {
+    // DN of the request, which is the payload of a Delete, and the first element
+    // of the other operations which have one.
+    pub(crate) fn dn(&self) -> String {
+        let dn = match self.op.payload {
+            PL::P(ref dn) => dn,
+            PL::C(ref elems) => match elems.first().map(|elem| &elem.payload) {
+                Some(PL::P(dn)) => dn,
+                _ => return String::new(),
+            },
+        };
+        String::from_utf8(dn.to_vec()).expect("dn")
+    }
+
+    // Message ID in the payload of an Abandon.
+    pub(crate) fn abandoned(&self) -> u64 {
+        match self.op.payload {
+            PL::P(ref id) => parse_uint(id).expect("id").1,
+            PL::C(_) => panic!("not an abandon request"),
+        }
+    }
+
+    pub(crate) fn has_control(&self, oid: &str) -> bool {
+        self.controls.iter().any(|ctrl| ctrl.ctype == oid)
+    }
+}
+
+// Take the first complete message from the buffer.
+fn decode(buf: &mut Vec<u8>) -> Option<Request> {
+    let (len, tag) = match parse_tag(buf) {
+        Ok((rest, tag)) => (buf.len() - rest.len(), tag),
+        Err(_) => return None,
+    };
+    let raw = buf.drain(..len).collect();
+    let mut parts = tag.expect_constructed().expect("message").into_iter();
+    let msgid = parts
+        .next()
+        .expect("msgid")
+        .expect_primitive()
+        .expect("int");
+    let msgid = parse_uint(&msgid).expect("msgid").1 as i64;
+    let op = parts.next().expect("op");
+    let controls = parts
+        .next()
+        .map(|ctrls| parse_controls(ctrls).into_iter().map(|c| c.1).collect())
+        .unwrap_or_default();
+    Some(Request {
+        msgid,
+        op,
+        controls,
+        raw,
+    })
+}
+
+// Reader of the requests arriving on a stream. The end of the stream and read
+// errors both end the sequence of requests.
+pub(crate) struct Requests<R> {
+    rd: R,
+    buf: Vec<u8>,
+}
+
+impl<R> Requests<R> {
+    pub(crate) fn new(rd: R) -> Self {
+        Requests { rd, buf: vec![] }
+    }
+}
+
+impl<R: AsyncRead + Unpin> Requests<R> {
+    pub(crate) async fn next(&mut self) -> Option<Request> {
+        loop {
+            if let Some(req) = decode(&mut self.buf) {
+                return Some(req);
+            }
+            let mut chunk = [0u8; 4096];
+            match self.rd.read(&mut chunk).await {
+                Ok(0) | Err(_) => return None,
+                Ok(n) => self.buf.extend_from_slice(&chunk[..n]),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "sync")]
+impl<R: Read> Requests<R> {
+    pub(crate) fn next_blocking(&mut self) -> Option<Request> {
+        loop {
+            if let Some(req) = decode(&mut self.buf) {
+                return Some(req);
+            }
+            let mut chunk = [0u8; 4096];
+            match self.rd.read(&mut chunk) {
+                Ok(0) | Err(_) => return None,
+                Ok(n) => self.buf.extend_from_slice(&chunk[..n]),
+            }
+        }
+    }
+}
+
+// Listener on a random port of the loopback interface, with its URL.
+pub(crate) async fn listener() -> (TcpListener, String) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("listener");
+    let port = listener.local_addr().expect("local addr").port();
+    (listener, format!("ldap://127.0.0.1:{}", port))
+}
+
+// Accept one connection, and answer each request with the bytes returned by `reply`,
+// which may be empty. Returns the URL of the server.
+pub(crate) async fn serve<F>(mut reply: F) -> String
+where
+    F: FnMut(Request) -> Vec<u8> + Send + 'static,
+{
+    let (listener, url) = listener().await;
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.expect("accept");
+        let (rd, mut wr) = stream.into_split();
+        let mut requests = Requests::new(rd);
+        while let Some(req) = requests.next().await {
+            let msg = reply(req);
+            if !msg.is_empty() && wr.write_all(&msg).await.is_err() {
+                return;
+            }
+        }
+    });
+    url
+}
+
+// Accept one connection, and return everything the client sent before closing it.
+pub(crate) async fn read_until_close() -> (String, JoinHandle<Vec<u8>>) {
+    let (listener, url) = listener().await;
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.expect("accept");
+        let mut buf = vec![];
+        stream.read_to_end(&mut buf).await.expect("read");
+        buf
+    });
+    (url, server)
+}
@@ -0,0 +1,386 @@
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+
+use crate::ldap::Ldap;
+use crate::protocol::MaybeControls;
+use crate::result::{LdapError, Result, SearchResult};
+use crate::search::{Scope, SearchEntry};
+use crate::subtree::rearm;
+use crate::util::ldap_escape;
+
+/// OID of the Active Directory LDAP_MATCHING_RULE_IN_CHAIN matching rule.
+pub const MATCHING_RULE_IN_CHAIN_OID: &str = "1.2.840.113556.1.4.1941";
+
+/// Resolution of nested group membership, for [`Ldap::is_member()`](struct.Ldap.html#method.is_member)
+/// and [`Ldap::member_of()`](struct.Ldap.html#method.member_of).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NestedMode {
+    /// Only direct membership is considered.
+    Direct,
+    /// Nesting is resolved by the server, using the Active Directory
+    /// LDAP_MATCHING_RULE_IN_CHAIN extensible match. A single Search is made.
+    MatchingRuleInChain,
+    /// Nesting is resolved by the client, expanding groups breadth-first, with one
+    /// Search per group. Groups which have already been visited are skipped, so that
+    /// cyclic membership doesn't prevent termination. At most `max_depth` levels of
+    /// nesting below the direct memberships are expanded; zero is equivalent to `Direct`.
+    Recursive { max_depth: usize },
+}
+
+/// Schema of group membership.
+///
+/// The default describes groups which list their members by DN in the `member` or
+/// `uniqueMember` attribute, and users which list their groups in `memberOf`, as
+/// maintained by Active Directory or the OpenLDAP __memberof__ overlay. For groups
+/// which list their members by user name, such as `posixGroup` with `memberUid`, use
+/// [`posix()`](#method.posix). Nested groups are always members by DN, so they are
+/// only found if the member attributes hold DNs.
+#[derive(Clone, Debug)]
+pub struct GroupSchema {
+    pub(crate) member_attrs: Vec<String>,
+    pub(crate) member_of_attr: String,
+    pub(crate) uid_attr: Option<String>,
+    pub(crate) group_base: Option<String>,
+}
+
+impl Default for GroupSchema {
+    fn default() -> Self {
+        GroupSchema {
+            member_attrs: vec![String::from("member"), String::from("uniqueMember")],
+            member_of_attr: String::from("memberOf"),
+            uid_attr: None,
+            group_base: None,
+        }
+    }
+}
+
+impl GroupSchema {
+    /// Create an instance with the default values, as described for the struct.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an instance for `posixGroup` entries, which list their members by the
+    /// value of the `uid` attribute of the user entry in `memberUid`. Since users don't
+    /// list their groups, finding the groups of a user needs a
+    /// [`group_base()`](#method.group_base).
+    pub fn posix() -> Self {
+        GroupSchema {
+            member_attrs: vec![String::from("memberUid")],
+            uid_attr: Some(String::from("uid")),
+            ..Default::default()
+        }
+    }
+
+    /// Set the attributes of a group entry which list its members.
+    pub fn member_attrs(mut self, attrs: &[&str]) -> Self {
+        self.member_attrs = attrs.iter().map(|a| String::from(*a)).collect();
+        self
+    }
+
+    /// Set the attribute of a user entry which lists its groups.
+    pub fn member_of_attr(mut self, attr: &str) -> Self {
+        self.member_of_attr = String::from(attr);
+        self
+    }
+
+    /// Identify users in the member attributes by the value of `attr` in the user entry,
+    /// instead of by DN. With `None`, revert to DNs.
+    pub fn uid_attr(mut self, attr: Option<&str>) -> Self {
+        self.uid_attr = attr.map(String::from);
+        self
+    }
+
+    /// Find the groups of a user by searching the subtree under `base` for the entries
+    /// which list the user as a member, instead of reading the user's `memberOf`.
+    pub fn group_base(mut self, base: &str) -> Self {
+        self.group_base = Some(String::from(base));
+        self
+    }
+
+    // Filter matching the groups which list `value` as a member.
+    fn member_filter(&self, value: &str) -> String {
+        let value = ldap_escape(value);
+        let terms: Vec<_> = self
+            .member_attrs
+            .iter()
+            .map(|attr| format!("({}={})", attr, value))
+            .collect();
+        match terms.len() {
+            1 => terms.into_iter().next().unwrap(),
+            _ => format!("(|{})", terms.concat()),
+        }
+    }
+}
+
+// Membership values are compared as DNs, which are case-insensitive in practice,
+// so both sides are normalized the same way.
+fn normalize(value: &str) -> String {
+    value.trim().to_lowercase()
+}
+
+// Values of the attributes named in `attrs`, matched case-insensitively, since
+// the server may return a name in a different case.
+fn values(entry: &SearchEntry, attrs: &[String]) -> Vec<String> {
+    entry
+        .attrs
+        .iter()
+        .filter(|(name, _)| attrs.iter().any(|a| a.eq_ignore_ascii_case(name)))
+        .flat_map(|(_, vals)| vals.iter().cloned())
+        .collect()
+}
+
+// Entries returned by a Search. A missing base is not an error, since membership
+// values may name entries which don't exist.
+async fn search(
+    ldap: &mut Ldap,
+    base: &str,
+    scope: Scope,
+    filter: &str,
+    attrs: Vec<&str>,
+    controls: &MaybeControls,
+    timeout: Option<Duration>,
+) -> Result<Vec<SearchEntry>> {
+    rearm(ldap, controls, timeout);
+    let SearchResult(entries, res) = ldap.search(base, scope, filter, attrs).await?;
+    match res.rc {
+        0 => Ok(entries.into_iter().map(SearchEntry::construct).collect()),
+        32 => Ok(vec![]),
+        _ => Err(LdapError::from(res)),
+    }
+}
+
+// Value identifying the user in the member attributes, if the user has one.
+async fn member_value(
+    ldap: &mut Ldap,
+    user_dn: &str,
+    schema: &GroupSchema,
+    controls: &MaybeControls,
+    timeout: Option<Duration>,
+) -> Result<Option<String>> {
+    let uid_attr = match schema.uid_attr {
+        Some(ref attr) => attr,
+        None => return Ok(Some(String::from(user_dn))),
+    };
+    let entries = search(
+        ldap,
+        user_dn,
+        Scope::Base,
+        "(objectClass=*)",
+        vec![uid_attr.as_str()],
+        controls,
+        timeout,
+    )
+    .await?;
+    Ok(entries
+        .first()
+        .and_then(|e| values(e, std::slice::from_ref(uid_attr)).into_iter().next()))
+}
+
+pub(crate) async fn is_member(
+    ldap: &mut Ldap,
+    group_dn: &str,
+    user_dn: &str,
+    nested: NestedMode,
+    schema: &GroupSchema,
+    controls: &MaybeControls,
+    timeout: Option<Duration>,
+) -> Result<bool> {
+    if nested == NestedMode::MatchingRuleInChain {
+        let filter = format!(
+            "({}:{}:={})",
+            schema.member_of_attr,
+            MATCHING_RULE_IN_CHAIN_OID,
+            ldap_escape(group_dn)
+        );
+        let entries = search(
+            ldap,
+            user_dn,
+            Scope::Base,
+            &filter,
+            vec!["1.1"],
+            controls,
+            timeout,
+        )
+        .await?;
+        return Ok(!entries.is_empty());
+    }
+    let max_depth = match nested {
+        NestedMode::Recursive { max_depth } => max_depth,
+        _ => 0,
+    };
+    let value = match member_value(ldap, user_dn, schema, controls, timeout).await? {
+        Some(value) => normalize(&value),
+        None => return Ok(false),
+    };
+    let attrs: Vec<&str> = schema.member_attrs.iter().map(String::as_str).collect();
+    let mut visited = HashSet::from([normalize(group_dn)]);
+    let mut pending = VecDeque::from([(String::from(group_dn), 0)]);
+    while let Some((dn, depth)) = pending.pop_front() {
+        let entries = search(
+            ldap,
+            &dn,
+            Scope::Base,
+            "(objectClass=*)",
+            attrs.clone(),
+            controls,
+            timeout,
+        )
+        .await?;
+        let members = entries
+            .first()
+            .map(|e| values(e, &schema.member_attrs))
+            .unwrap_or_default();
+        if members.iter().any(|m| normalize(m) == value) {
+            return Ok(true);
+        }
+        if depth < max_depth && schema.uid_attr.is_none() {
+            for member in members {
+                if visited.insert(normalize(&member)) {
+                    pending.push_back((member, depth + 1));
+                }
+            }
+        }
+    }
+    Ok(false)
+}
+
+// Groups which directly list `value` as a member. If `entry_dn` is given, it's the
+// entry whose own list of groups can be read instead of searching.
+async fn direct_groups(
+    ldap: &mut Ldap,
+    entry_dn: Option<&str>,
+    value: &str,
+    schema: &GroupSchema,
+    controls: &MaybeControls,
+    timeout: Option<Duration>,
+) -> Result<Vec<String>> {
+    match (&schema.group_base, entry_dn) {
+        (Some(base), _) => {
+            let entries = search(
+                ldap,
+                base,
+                Scope::Subtree,
+                &schema.member_filter(value),
+                vec!["1.1"],
+                controls,
+                timeout,
+            )
+            .await?;
+            Ok(entries.into_iter().map(|e| e.dn).collect())
+        }
+        (None, Some(dn)) => {
+            let attrs = std::slice::from_ref(&schema.member_of_attr);
+            let entries = search(
+                ldap,
+                dn,
+                Scope::Base,
+                "(objectClass=*)",
+                vec![schema.member_of_attr.as_str()],
+                controls,
+                timeout,
+            )
+            .await?;
+            Ok(entries
+                .first()
+                .map(|e| values(e, attrs))
+                .unwrap_or_default())
+        }
+        (None, None) => Err(LdapError::InvalidRequest {
+            field: "group schema",
+            reason: String::from("group base is needed to find groups by user name"),
+        }),
+    }
+}
+
+pub(crate) async fn member_of(
+    ldap: &mut Ldap,
+    user_dn: &str,
+    nested: NestedMode,
+    schema: &GroupSchema,
+    controls: &MaybeControls,
+    timeout: Option<Duration>,
+) -> Result<Vec<String>> {
+    if nested == NestedMode::MatchingRuleInChain {
+        let base = match schema.group_base {
+            Some(ref base) => base.clone(),
+            None => {
+                rearm(ldap, controls, timeout);
+                let root_dse = ldap.root_dse().await?;
+                match root_dse.attrs.get("defaultNamingContext") {
+                    Some(ctx) if !ctx.is_empty() => ctx[0].clone(),
+                    _ => root_dse
+                        .naming_contexts
+                        .into_iter()
+                        .next()
+                        .unwrap_or_default(),
+                }
+            }
+        };
+        let member_attr = schema.member_attrs.first().map_or("member", String::as_str);
+        let filter = format!(
+            "({}:{}:={})",
+            member_attr,
+            MATCHING_RULE_IN_CHAIN_OID,
+            ldap_escape(user_dn)
+        );
+        let entries = search(
+            ldap,
+            &base,
+            Scope::Subtree,
+            &filter,
+            vec!["1.1"],
+            controls,
+            timeout,
+        )
+        .await?;
+        return Ok(entries.into_iter().map(|e| e.dn).collect());
+    }
+    let max_depth = match nested {
+        NestedMode::Recursive { max_depth } => max_depth,
+        _ => 0,
+    };
+    let value = match member_value(ldap, user_dn, schema, controls, timeout).await? {
+        Some(value) => value,
+        None => return Ok(vec![]),
+    };
+    let user_entry = schema.uid_attr.is_none().then_some(user_dn);
+    let mut groups = vec![];
+    let mut visited = HashSet::from([normalize(user_dn)]);
+    let mut pending = VecDeque::new();
+    for group in direct_groups(ldap, user_entry, &value, schema, controls, timeout).await? {
+        if visited.insert(normalize(&group)) {
+            pending.push_back((group, 0));
+        }
+    }
+    while let Some((group, depth)) = pending.pop_front() {
+        if depth < max_depth {
+            let parents = direct_groups(ldap, Some(&group), &group, schema, controls, timeout);
+            for parent in parents.await? {
+                if visited.insert(normalize(&parent)) {
+                    pending.push_back((parent, depth + 1));
+                }
+            }
+        }
+        groups.push(group);
+    }
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn member_filters() {
+        let schema = GroupSchema::new();
+        assert_eq!(
+            schema.member_filter("cn=a(b),dc=example,dc=org"),
+            "(|(member=cn=a\\28b\\29,dc=example,dc=org)(uniqueMember=cn=a\\28b\\29,dc=example,dc=org))"
+        );
+        assert_eq!(
+            GroupSchema::posix().member_filter("jdoe"),
+            "(memberUid=jdoe)"
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use crate::result::{LdapError, LdapResult, Result};
+
+/// Outcome of [`Ldap::check_health()`](struct.Ldap.html#method.check_health).
+#[derive(Debug)]
+pub enum HealthStatus {
+    /// The server answered the probe successfully, with the given round-trip time.
+    Healthy { rtt: Duration },
+    /// The server answered, but with an error result, e.g., unwillingToPerform (53) from
+    /// a server in maintenance, or busy (51). The connection works, but the server may not
+    /// be able to serve requests.
+    Degraded { reason: LdapResult },
+    /// The probe failed without an answer from the server: it timed out, or the connection
+    /// is broken. The result code unavailable (52), which is also reported for an operation
+    /// interrupted by the loss of the connection, is classified here.
+    Unusable { error: LdapError },
+}
+
+impl HealthStatus {
+    pub(crate) fn classify(res: Result<LdapResult>, rtt: Duration) -> Self {
+        match res {
+            Ok(res) if res.rc == 0 => HealthStatus::Healthy { rtt },
+            Ok(res) if res.rc == 52 => HealthStatus::Unusable {
+                error: LdapError::from(res),
+            },
+            Ok(res) => HealthStatus::Degraded { reason: res },
+            Err(error) => HealthStatus::Unusable { error },
+        }
+    }
+
+    /// Check whether the status is `Healthy`.
+    pub fn is_healthy(&self) -> bool {
+        matches!(self, HealthStatus::Healthy { .. })
+    }
+
+    /// Check whether the status is `Unusable`, in which case the connection should be
+    /// replaced.
+    pub fn is_unusable(&self) -> bool {
+        matches!(self, HealthStatus::Unusable { .. })
+    }
+}
@@ -0,0 +1,222 @@
+use crate::result::{LdapError, Result};
+use crate::search::ResultEntry;
+
+use lber::common::TagClass;
+use lber::structure::PL;
+
+/// Intermediate Response message.
+///
+/// An operation may return any number of Intermediate Responses before its final
+/// result. For Search, they arrive through the stream of result entries; see
+/// [`SearchStream`](struct.SearchStream.html) for details. The generic form
+/// of the response is obtained from such an entry with [`parse()`](#method.parse).
+///
+/// Like an [extended operation](exop/struct.Exop.html), the response has an
+/// optional OID and an optional value, whose structure depends on the OID. A specific
+/// response implemented by the library, or by third-party code, can decode the value
+/// by implementing the [`IntermediateParser`](trait.IntermediateParser.html) trait.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IntermediateResponse {
+    /// OID of the response.
+    pub name: Option<String>,
+    /// Response value.
+    pub val: Option<Vec<u8>>,
+}
+
+impl IntermediateResponse {
+    /// Extract the Intermediate Response from a result entry. The entry must be
+    /// an intermediate message, as indicated by
+    /// [`ResultEntry::is_intermediate()`](struct.ResultEntry.html#method.is_intermediate).
+    /// Response controls are not part of the returned value, but remain accessible
+    /// in the entry.
+    pub fn parse(re: &ResultEntry) -> Result<IntermediateResponse> {
+        if !re.is_intermediate() {
+            return Err(LdapError::MalformedIntermediate(
+                "not an intermediate message",
+            ));
+        }
+        let tags = match re.0.payload {
+            PL::C(ref tags) => tags,
+            PL::P(_) => return Err(LdapError::MalformedIntermediate("not a sequence")),
+        };
+        let mut ir = IntermediateResponse {
+            name: None,
+            val: None,
+        };
+        for tag in tags {
+            match (tag.class, tag.id, &tag.payload) {
+                (TagClass::Context, 0, PL::P(name)) if ir.name.is_none() && ir.val.is_none() => {
+                    let name = String::from_utf8(name.to_vec())
+                        .map_err(|_| LdapError::MalformedIntermediate("invalid name"))?;
+                    ir.name = Some(name);
+                }
+                (TagClass::Context, 1, PL::P(val)) if ir.val.is_none() => {
+                    ir.val = Some(val.to_vec());
+                }
+                _ => return Err(LdapError::MalformedIntermediate("unexpected element")),
+            }
+        }
+        Ok(ir)
+    }
+
+    /// Parse the value of the response into a response-specific struct.
+    ///
+    /// The parser will panic if the value is `None` or malformed. See
+    /// [control parsing](controls/struct.RawControl.html#method.parse),
+    /// which behaves analogously, for discussion and rationale. Use
+    /// [`try_parse_val()`](#method.try_parse_val) to handle both cases as errors.
+    pub fn parse_val<T: IntermediateParser>(&self) -> T {
+        T::parse(self.val.as_ref().expect("value"))
+    }
+
+    /// Parse the value of the response into a response-specific struct, returning an
+    /// error instead of panicking if the value is absent or malformed.
+    pub fn try_parse_val<T: IntermediateParser>(&self) -> Result<T> {
+        match self.val {
+            Some(ref val) => T::try_parse(val),
+            None => Err(LdapError::MalformedIntermediate("missing value")),
+        }
+    }
+}
+
+/// Conversion trait for Intermediate Response values.
+///
+/// An implementation provides [`try_parse()`](#tymethod.try_parse), which must not panic
+/// on malformed input; [`parse()`](#method.parse) is derived from it.
+pub trait IntermediateParser: Sized {
+    /// Convert the raw BER value into a response-specific struct, or return an error
+    /// if the value is malformed.
+    fn try_parse(val: &[u8]) -> Result<Self>;
+
+    /// Convert the raw BER value into a response-specific struct.
+    ///
+    /// The conversion panics if the value is malformed.
+    fn parse(val: &[u8]) -> Self {
+        Self::try_parse(val).expect("intermediate value")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::controls::{Control, RawControl, SyncInfo};
+
+    use lber::structure::StructureTag;
+    use lber::structures::{ASNTag, OctetString, Sequence, Tag};
+
+    fn context_string(id: u64, inner: &[u8]) -> Tag {
+        Tag::OctetString(OctetString {
+            id,
+            class: TagClass::Context,
+            inner: inner.to_vec(),
+        })
+    }
+
+    fn intermediate(inner: Vec<Tag>) -> StructureTag {
+        Tag::Sequence(Sequence {
+            id: 25,
+            class: TagClass::Application,
+            inner,
+        })
+        .into_structure()
+    }
+
+    // syncInfo value: newcookie [0] "rid=001,csn=1"
+    const SYNC_INFO_VAL: &[u8] = b"\x80\x0drid=001,csn=1";
+
+    #[test]
+    fn sync_info() {
+        let re = ResultEntry(
+            intermediate(vec![
+                context_string(0, b"1.3.6.1.4.1.4203.1.9.1.4"),
+                context_string(1, SYNC_INFO_VAL),
+            ]),
+            vec![Control(
+                None,
+                RawControl {
+                    ctype: String::from("1.2.3.4"),
+                    crit: false,
+                    val: None,
+                },
+            )],
+            None,
+        );
+        let ir = IntermediateResponse::parse(&re).expect("intermediate");
+        assert_eq!(ir.name.as_deref(), Some("1.3.6.1.4.1.4203.1.9.1.4"));
+        assert_eq!(ir.val.as_deref(), Some(SYNC_INFO_VAL));
+        assert!(matches!(
+            ir.parse_val::<SyncInfo>(),
+            SyncInfo::NewCookie(cookie) if cookie == b"rid=001,csn=1"
+        ));
+        assert_eq!(re.1.len(), 1);
+        assert!(matches!(
+            crate::controls::parse_syncinfo(re),
+            Ok(SyncInfo::NewCookie(_))
+        ));
+    }
+
+    #[test]
+    fn optional_components() {
+        let ir = IntermediateResponse::parse(&ResultEntry::new(intermediate(vec![])))
+            .expect("intermediate");
+        assert_eq!(
+            ir,
+            IntermediateResponse {
+                name: None,
+                val: None
+            }
+        );
+        let ir =
+            IntermediateResponse::parse(&ResultEntry::new(intermediate(vec![context_string(
+                1, b"value",
+            )])))
+            .expect("intermediate");
+        assert_eq!(ir.name, None);
+        assert_eq!(ir.val.as_deref(), Some(&b"value"[..]));
+    }
+
+    #[test]
+    fn malformed() {
+        let entry = Tag::Sequence(Sequence {
+            id: 4,
+            class: TagClass::Application,
+            inner: vec![],
+        })
+        .into_structure();
+        assert!(matches!(
+            IntermediateResponse::parse(&ResultEntry::new(entry)),
+            Err(LdapError::MalformedIntermediate(_))
+        ));
+        let reversed = intermediate(vec![context_string(1, b"value"), context_string(0, b"1.2")]);
+        assert!(matches!(
+            IntermediateResponse::parse(&ResultEntry::new(reversed)),
+            Err(LdapError::MalformedIntermediate(_))
+        ));
+        let no_value = ResultEntry::new(intermediate(vec![context_string(
+            0,
+            b"1.3.6.1.4.1.4203.1.9.1.4",
+        )]));
+        let ir = IntermediateResponse::parse(&no_value).expect("intermediate");
+        assert!(matches!(
+            ir.try_parse_val::<SyncInfo>(),
+            Err(LdapError::MalformedIntermediate(_))
+        ));
+        assert!(matches!(
+            crate::controls::parse_syncinfo(no_value),
+            Err(LdapError::MalformedIntermediate(_))
+        ));
+        let bad_value = ResultEntry::new(intermediate(vec![context_string(1, b"\x04\x00")]));
+        assert!(matches!(
+            crate::controls::parse_syncinfo(bad_value),
+            Err(LdapError::MalformedIntermediate(_))
+        ));
+        let other_oid = ResultEntry::new(intermediate(vec![
+            context_string(0, b"1.2.3.4"),
+            context_string(1, SYNC_INFO_VAL),
+        ]));
+        assert!(matches!(
+            crate::controls::parse_syncinfo(other_oid),
+            Err(LdapError::MalformedIntermediate(_))
+        ));
+    }
+}
@@ -1,28 +1,64 @@
-use std::collections::HashSet;
-use std::hash::Hash;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::{Hash, Hasher};
 #[cfg(feature = "gssapi")]
 use std::sync::RwLock;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use crate::adapters::{EntriesOnly, IntoAdapterVec};
-use crate::controls_impl::IntoRawControlVec;
+use crate::ad::unicode_pwd;
+use crate::adapters::{Adapter, EntriesOnly, IntoAdapterVec, PagedResults};
+use crate::batch::Batch;
+use crate::cancel::{CancelCause, OpCancellation};
+use crate::capabilities::ServerCapabilities;
+#[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
+use crate::channel_binding::tls_server_end_point;
+use crate::conn::TlsInfo;
+use crate::controls::{
+    Assertion, Control, MakeCritical, PersistentSearch, RawControl, RelaxRules, TreeDelete,
+};
+use crate::controls_impl::{
+    check_control_ops, IntoRawControlVec, PAGED_RESULTS_OID, TREE_DELETE_OID,
+};
+use crate::diff::{ensure_mods, EnsureOptions, EnsureOutcome};
 use crate::exop::Exop;
-use crate::exop_impl::construct_exop;
-use crate::protocol::{LdapOp, MaybeControls, MiscSender, ResultSender};
+use crate::exop_impl::{construct_exop, PasswordModify, PASSMOD_OID, STARTTLS_OID, WHOAMI_OID};
+use crate::groups::{self, GroupSchema, NestedMode};
+use crate::health::HealthStatus;
+use crate::mapping::lookup;
+use crate::observer::{op_name, Observer};
+use crate::password::{ad_password_mods, PasswordFlavor, PasswordStatus, STATUS_ATTRS};
+use crate::protocol::{LdapOp, MaybeControls, MiscSender, ResultSender, Segments, StreamedRequest};
 use crate::result::{
     CompareResult, ExopResult, LdapError, LdapResult, LdapResultExt, Result, SearchResult,
 };
-use crate::search::{Scope, SearchOptions, SearchStream};
+use crate::retry::RetryPolicy;
+use crate::rt;
+use crate::schema::{RootDse, Schema};
+use crate::scram::ScramMech;
+#[cfg(feature = "scram")]
+use crate::scram::{client_nonce, ScramClient};
+use crate::search::{Scope, SearchEntry, SearchOptions, SearchStream};
+use crate::stats::ConnStats;
+use crate::subtree::{enumerate, rearm, SubtreeDeleteOptions, SubtreeDeleteStats};
+use crate::txn::Transaction;
+use crate::unsolicited::{NotifierHandle, UnsolicitedNotification};
+use crate::util::LdapUrl;
 use crate::RequestId;
 
+use bytes::Bytes;
 use lber::common::TagClass;
-use lber::structures::{Boolean, Enumerated, Integer, Null, OctetString, Sequence, Set, Tag};
+use lber::structure::StructureTag;
+use lber::structures::{
+    Boolean, Enumerated, ExplicitTag, Integer, Null, ObjectIdentifier, OctetString, Sequence, Set,
+    Tag,
+};
 
 #[cfg(feature = "gssapi")]
 use cross_krb5::{ClientCtx, InitiateFlags, K5Ctx, Step};
-use tokio::sync::{mpsc, oneshot};
-use tokio::time;
+use tokio::io::AsyncRead;
+use tokio::sync::{broadcast, mpsc, oneshot};
 
 /// SASL bind exchange wrapper.
 #[allow(dead_code)]
@@ -30,7 +66,19 @@ use tokio::time;
 pub(crate) struct SaslCreds(pub Option<Vec<u8>>);
 
 /// Possible sub-operations for the Modify operation.
+///
+/// The attribute names and values can be of any type which can be viewed as a byte slice.
+/// To mix textual and binary values in the same operation, use
+/// [`ModOwned`](type.ModOwned.html), whose values are [`AttrValue`](enum.AttrValue.html)s.
+///
+/// With the __serde__ feature, an instance is represented as a single-entry map whose key
+/// is the lowercase name of the variant, such as `{"add": ["cn", ["value"]]}`.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum Mod<S: AsRef<[u8]> + Eq + Hash> {
     /// Add an attribute, with at least one value.
     Add(S, HashSet<S>),
@@ -42,6 +90,219 @@ pub enum Mod<S: AsRef<[u8]> + Eq + Hash> {
     Increment(S, S),
 }
 
+/// Modify sub-operation with owned values, which may be textual or binary.
+pub type ModOwned = Mod<AttrValue>;
+
+/// Outcome of a conditional update with [`modify_if()`](struct.Ldap.html#method.modify_if)
+/// or [`delete_if()`](struct.Ldap.html#method.delete_if).
+#[derive(Clone, Debug)]
+pub enum ModifyIfOutcome {
+    /// The assertion held, and the operation succeeded.
+    Applied(LdapResult),
+    /// The assertion didn't hold, and the entry was left unchanged. The result has
+    /// the code 122 (assertionFailed).
+    PreconditionFailed(LdapResult),
+}
+
+/// Owned attribute value, textual or binary.
+///
+/// Values of both kinds can be mixed in the same [`Mod`](enum.Mod.html) or attribute list of
+/// [`Ldap::add()`](struct.Ldap.html#method.add), which is convenient when an operation writes
+/// both ordinary and binary attributes, like `userCertificate`. Attribute names are also
+/// `AttrValue`s in that case, and are most easily given by calling `into()` on a string.
+/// Values are compared and hashed by their bytes, so that a text and a binary value with
+/// the same contents are equal.
+///
+/// With the __serde__ feature, a textual value is represented as a string, and a binary
+/// value as a map with a single `bin` key, whose value is the Base64-encoded byte string.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(
+        from = "crate::serde_impl::AttrValueRepr",
+        into = "crate::serde_impl::AttrValueRepr"
+    )
+)]
+pub enum AttrValue {
+    Text(String),
+    Bin(Vec<u8>),
+}
+
+impl AttrValue {
+    /// Create a value of the AD `unicodePwd` attribute from a password. See
+    /// [`ad::unicode_pwd()`](ad/fn.unicode_pwd.html).
+    pub fn ad_password(password: &str) -> Self {
+        AttrValue::Bin(unicode_pwd(password))
+    }
+}
+
+impl AsRef<[u8]> for AttrValue {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            AttrValue::Text(s) => s.as_bytes(),
+            AttrValue::Bin(b) => b,
+        }
+    }
+}
+
+impl PartialEq for AttrValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_ref() == other.as_ref()
+    }
+}
+
+impl Eq for AttrValue {}
+
+impl Hash for AttrValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_ref().hash(state)
+    }
+}
+
+impl From<&str> for AttrValue {
+    fn from(s: &str) -> Self {
+        AttrValue::Text(s.to_owned())
+    }
+}
+
+impl From<String> for AttrValue {
+    fn from(s: String) -> Self {
+        AttrValue::Text(s)
+    }
+}
+
+impl From<&[u8]> for AttrValue {
+    fn from(b: &[u8]) -> Self {
+        AttrValue::Bin(b.to_vec())
+    }
+}
+
+impl From<Vec<u8>> for AttrValue {
+    fn from(b: Vec<u8>) -> Self {
+        AttrValue::Bin(b)
+    }
+}
+
+/// Attribute name or value which can be put into a request.
+///
+/// The operations which write an entry, such as [`Ldap::add()`](struct.Ldap.html#method.add)
+/// and [`Ldap::modify()`](struct.Ldap.html#method.modify), accept any type implementing this
+/// trait. Owned buffers (`String`, `Vec<u8>`, `Bytes`, an owned `Cow<[u8]>` and
+/// [`AttrValue`](enum.AttrValue.html)) are moved into the request without copying their
+/// contents, which matters for large binary values; borrowed ones are copied.
+pub trait ValueBytes: AsRef<[u8]> {
+    /// Convert the value into a buffer which can be shared with the encoder.
+    fn into_value_bytes(self) -> Bytes;
+}
+
+impl ValueBytes for &str {
+    fn into_value_bytes(self) -> Bytes {
+        Bytes::copy_from_slice(self.as_bytes())
+    }
+}
+
+impl ValueBytes for &String {
+    fn into_value_bytes(self) -> Bytes {
+        Bytes::copy_from_slice(self.as_bytes())
+    }
+}
+
+impl ValueBytes for String {
+    fn into_value_bytes(self) -> Bytes {
+        Bytes::from(self)
+    }
+}
+
+impl ValueBytes for &[u8] {
+    fn into_value_bytes(self) -> Bytes {
+        Bytes::copy_from_slice(self)
+    }
+}
+
+impl<const N: usize> ValueBytes for &[u8; N] {
+    fn into_value_bytes(self) -> Bytes {
+        Bytes::copy_from_slice(self)
+    }
+}
+
+impl ValueBytes for &Vec<u8> {
+    fn into_value_bytes(self) -> Bytes {
+        Bytes::copy_from_slice(self)
+    }
+}
+
+impl ValueBytes for Vec<u8> {
+    fn into_value_bytes(self) -> Bytes {
+        Bytes::from(self)
+    }
+}
+
+impl ValueBytes for Bytes {
+    fn into_value_bytes(self) -> Bytes {
+        self
+    }
+}
+
+impl ValueBytes for Cow<'_, [u8]> {
+    fn into_value_bytes(self) -> Bytes {
+        match self {
+            Cow::Borrowed(b) => b.into_value_bytes(),
+            Cow::Owned(b) => b.into_value_bytes(),
+        }
+    }
+}
+
+impl ValueBytes for AttrValue {
+    fn into_value_bytes(self) -> Bytes {
+        match self {
+            AttrValue::Text(s) => s.into_value_bytes(),
+            AttrValue::Bin(b) => b.into_value_bytes(),
+        }
+    }
+}
+
+impl ValueBytes for &AttrValue {
+    fn into_value_bytes(self) -> Bytes {
+        self.as_ref().into_value_bytes()
+    }
+}
+
+// Universal octet string with a copy of the string, for DNs and attribute descriptions.
+fn octet_str(s: &str) -> Tag {
+    OctetString::from_bytes(Bytes::copy_from_slice(s.as_bytes()))
+}
+
+/// Source of an attribute value for [`Ldap::add_streaming()`](struct.Ldap.html#method.add_streaming).
+pub enum ValueSource {
+    /// Value held in memory.
+    InMemory(Vec<u8>),
+    /// Value of exactly `len` bytes, read from `reader` while the request is being written.
+    Reader {
+        len: u64,
+        reader: Box<dyn AsyncRead + Send + Unpin>,
+    },
+}
+
+impl ValueSource {
+    /// Create a value which will be read from `reader`.
+    pub fn reader<R: AsyncRead + Send + Unpin + 'static>(len: u64, reader: R) -> Self {
+        ValueSource::Reader {
+            len,
+            reader: Box::new(reader),
+        }
+    }
+}
+
+impl fmt::Debug for ValueSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueSource::InMemory(v) => f.debug_tuple("InMemory").field(v).finish(),
+            ValueSource::Reader { len, .. } => f.debug_struct("Reader").field("len", len).finish(),
+        }
+    }
+}
+
 /// Asynchronous handle for LDAP operations. __*__
 ///
 /// All LDAP operations allow attaching a series of request controls, which augment or modify
@@ -71,7 +332,8 @@ pub enum Mod<S: AsRef<[u8]> + Eq + Hash> {
 ///
 /// The handle can be freely cloned. Each clone will multiplex the invoked LDAP operations on
 /// the same underlying connection. Dropping the last handle will automatically close the
-/// connection.
+/// connection, sending an Unbind first if none was sent before. To close the connection
+/// and learn how it ended, use [`close()`](#method.close).
 #[derive(Debug)]
 pub struct Ldap {
     pub(crate) msgmap: Arc<Mutex<(RequestId, HashSet<RequestId>)>>,
@@ -79,14 +341,28 @@ pub struct Ldap {
     pub(crate) id_scrub_tx: mpsc::UnboundedSender<RequestId>,
     pub(crate) misc_tx: mpsc::UnboundedSender<MiscSender>,
     pub(crate) last_id: RequestId,
+    pub(crate) last_started: Option<Instant>,
     #[cfg(feature = "gssapi")]
     pub(crate) sasl_param: Arc<RwLock<(bool, u32)>>, // sasl_wrap, sasl_max_send
     #[cfg(feature = "gssapi")]
     pub(crate) client_ctx: Arc<Mutex<Option<ClientCtx>>>,
-    #[cfg(any(feature = "gssapi", feature = "ntlm"))]
-    pub(crate) tls_endpoint_token: Arc<Option<Vec<u8>>>,
+    pub(crate) tls_info: Arc<Option<TlsInfo>>,
+    pub(crate) host_port: Arc<Option<(String, u16)>>,
     pub(crate) has_tls: bool,
+    pub(crate) search_queue_size: usize,
+    pub(crate) abandon_on_timeout: bool,
+    pub(crate) strict_validation: bool,
+    pub(crate) canonical_sets: bool,
+    pub(crate) notifier: NotifierHandle,
+    pub(crate) stats: ConnStats,
+    pub(crate) observer: Option<Observer>,
+    pub(crate) cancel: Option<OpCancellation>,
+    pub(crate) retry_policy: Option<Arc<RetryPolicy>>,
+    pub(crate) retries: bool,
+    pub(crate) capabilities: Arc<Mutex<Option<ServerCapabilities>>>,
+    pub(crate) no_probe: bool,
     pub timeout: Option<Duration>,
+    pub deadline: Option<Instant>,
     pub controls: MaybeControls,
     pub search_opts: Option<SearchOptions>,
 }
@@ -102,27 +378,96 @@ impl Clone for Ldap {
             sasl_param: self.sasl_param.clone(),
             #[cfg(feature = "gssapi")]
             client_ctx: self.client_ctx.clone(),
-            #[cfg(any(feature = "gssapi", feature = "ntlm"))]
-            tls_endpoint_token: self.tls_endpoint_token.clone(),
+            tls_info: self.tls_info.clone(),
+            host_port: self.host_port.clone(),
             has_tls: self.has_tls,
+            search_queue_size: self.search_queue_size,
+            abandon_on_timeout: self.abandon_on_timeout,
+            strict_validation: self.strict_validation,
+            canonical_sets: self.canonical_sets,
+            notifier: self.notifier.clone(),
+            stats: self.stats.clone(),
+            observer: self.observer.clone(),
+            cancel: None,
+            retry_policy: self.retry_policy.clone(),
+            retries: false,
+            capabilities: self.capabilities.clone(),
+            no_probe: false,
             last_id: 0,
+            last_started: None,
             timeout: None,
+            deadline: None,
             controls: None,
             search_opts: None,
         }
     }
 }
 
+// Wrap the channel binding application data into the SEC_CHANNEL_BINDINGS structure
+// expected by SSPI: eight little-endian 32-bit fields, of which only the length and
+// the offset of the application data are set, followed by the data.
+#[cfg(any(feature = "ntlm", test))]
+fn sec_channel_bindings(app_data: &[u8]) -> Vec<u8> {
+    const HEADER_LEN: u32 = 32;
+
+    let mut buf = vec![0; HEADER_LEN as usize - 8];
+    buf.extend((app_data.len() as u32).to_le_bytes());
+    buf.extend(HEADER_LEN.to_le_bytes());
+    buf.extend(app_data);
+    buf
+}
+
+// Security layers of the SASL GSSAPI mechanism (RFC 4752, section 3.3).
+#[cfg(any(feature = "gssapi", test))]
+const GSSAUTH_P_NONE: u8 = 1;
+#[cfg(any(feature = "gssapi", test))]
+const GSSAUTH_P_PRIVACY: u8 = 4;
+
+// Process the unwrapped last challenge of a GSSAPI bind, which holds the mask of the
+// security layers offered by the server and the maximum size of a message it will
+// receive. The confidentiality layer is required in the clear, and no layer over TLS.
+// Return the chosen layer, the maximum size of a message to send, and the reply, which
+// must be wrapped before sending.
+#[cfg(any(feature = "gssapi", test))]
+fn gssapi_security_layer(
+    challenge: &[u8],
+    has_tls: bool,
+) -> std::result::Result<(u8, u32, [u8; 4]), String> {
+    let challenge: [u8; 4] = challenge.try_into().map_err(|_| {
+        format!(
+            "invalid security layer challenge length: {}",
+            challenge.len()
+        )
+    })?;
+    let needed_layer = if has_tls {
+        GSSAUTH_P_NONE
+    } else {
+        GSSAUTH_P_PRIVACY
+    };
+    if challenge[0] & needed_layer == 0 {
+        return Err(format!(
+            "no appropriate security layer offered: needed {}, mask {}",
+            needed_layer, challenge[0]
+        ));
+    }
+    let send_max_size = u32::from_be_bytes([0, challenge[1], challenge[2], challenge[3]]);
+    // Without a security layer, the maximum size must be zero. Otherwise, the constant
+    // is taken from OpenLDAP GSSAPI code as a fallback value for broken GSSAPI libraries.
+    // It's meant to serve as a safe value until gss_wrap_size_limit() equivalent is
+    // available in cross-krb5.
+    let recv_max_size = if needed_layer == GSSAUTH_P_NONE {
+        0
+    } else {
+        0x9FFFB8
+    };
+    let reply = (recv_max_size | (needed_layer as u32) << 24).to_be_bytes();
+    Ok((needed_layer, send_max_size, reply))
+}
+
 fn sasl_bind_req(mech: &str, creds: Option<&[u8]>) -> Tag {
-    let mut inner_vec = vec![Tag::OctetString(OctetString {
-        inner: Vec::from(mech),
-        ..Default::default()
-    })];
+    let mut inner_vec = vec![octet_str(mech)];
     if let Some(creds) = creds {
-        inner_vec.push(Tag::OctetString(OctetString {
-            inner: creds.to_vec(),
-            ..Default::default()
-        }));
+        inner_vec.push(OctetString::from_bytes(Bytes::copy_from_slice(creds)));
     }
     Tag::Sequence(Sequence {
         id: 0,
@@ -145,28 +490,255 @@ fn sasl_bind_req(mech: &str, creds: Option<&[u8]>) -> Tag {
     })
 }
 
+// Number of message IDs in use at which a handle asks the connection to release the
+// IDs of operations whose handles are gone, and at every multiple of which it asks again.
+const RECONCILE_INTERVAL: usize = 1024;
+
+// Allocate the next free message ID between 1 and `max_id`, marking it as in use. The
+// search for a free ID starts after the last allocated one, so that it's short unless
+// almost all IDs are in use.
+pub(crate) fn alloc_msgid(
+    msgmap: &mut (RequestId, HashSet<RequestId>),
+    max_id: RequestId,
+) -> Result<RequestId> {
+    if msgmap.1.len() >= max_id as usize {
+        return Err(LdapError::MsgIdExhausted);
+    }
+    let mut next_ldap_id = msgmap.0;
+    loop {
+        if next_ldap_id >= max_id {
+            next_ldap_id = 1;
+        } else {
+            next_ldap_id += 1;
+        }
+        if !msgmap.1.contains(&next_ldap_id) {
+            break;
+        }
+    }
+    msgmap.0 = next_ldap_id;
+    msgmap.1.insert(next_ldap_id);
+    Ok(next_ldap_id)
+}
+
+// Abandon can't be applied to Bind or StartTLS. (Nor to Unbind and Abandon, but they
+// don't have a response to wait for.)
+fn is_abandonable(req: &Tag) -> bool {
+    match req {
+        Tag::Sequence(Sequence {
+            id: 0,
+            class: TagClass::Application,
+            ..
+        }) => false,
+        Tag::Sequence(Sequence {
+            id: 23,
+            class: TagClass::Application,
+            inner,
+        }) => {
+            !matches!(inner.first(), Some(Tag::OctetString(OctetString { inner: oid, .. })) if oid == STARTTLS_OID.as_bytes())
+        }
+        _ => true,
+    }
+}
+
+// Number of the tag, if it's in the Application class.
+fn application_id(req: &Tag) -> Option<u64> {
+    let (class, id) = match *req {
+        Tag::Integer(Integer { class, id, .. })
+        | Tag::Enumerated(Enumerated { class, id, .. })
+        | Tag::Sequence(Sequence { class, id, .. })
+        | Tag::Set(Set { class, id, .. })
+        | Tag::OctetString(OctetString { class, id, .. })
+        | Tag::Boolean(Boolean { class, id, .. })
+        | Tag::Null(Null { class, id, .. })
+        | Tag::ObjectIdentifier(ObjectIdentifier { class, id, .. })
+        | Tag::ExplicitTag(ExplicitTag { class, id, .. })
+        | Tag::StructureTag(StructureTag { class, id, .. }) => (class, id),
+    };
+    (class == TagClass::Application).then_some(id)
+}
+
+// Set of attribute values, in the canonical DER order if requested.
+fn value_set(inner: Vec<Tag>, canonical: bool) -> Tag {
+    let set = Set {
+        inner,
+        ..Default::default()
+    };
+    if canonical {
+        Tag::StructureTag(set.into_der_structure())
+    } else {
+        Tag::Set(set)
+    }
+}
+
+fn raw_op_error(reason: &str) -> LdapError {
+    LdapError::InvalidRequest {
+        field: "protocol op",
+        reason: String::from(reason),
+    }
+}
+
+// Compare and Who Am I can be repeated without changing their outcome. (So can Search,
+// but it's retried as a whole, since its result arrives through the stream.)
+fn is_idempotent(req: &Tag) -> bool {
+    match req {
+        Tag::Sequence(Sequence {
+            id: 14,
+            class: TagClass::Application,
+            ..
+        }) => true,
+        Tag::Sequence(Sequence {
+            id: 23,
+            class: TagClass::Application,
+            inner,
+        }) => {
+            matches!(inner.first(), Some(Tag::OctetString(OctetString { inner: oid, .. })) if oid == WHOAMI_OID.as_bytes())
+        }
+        _ => false,
+    }
+}
+
+// Operation modifiers set on the handle, which are taken by the next operation. A retried
+// operation restores them before each attempt.
+struct Pending {
+    controls: MaybeControls,
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+    cancel: Option<OpCancellation>,
+    search_opts: Option<SearchOptions>,
+}
+
+impl Pending {
+    fn save(ldap: &Ldap) -> Self {
+        Pending {
+            controls: ldap.controls.clone(),
+            timeout: ldap.timeout,
+            deadline: ldap.deadline,
+            cancel: ldap.cancel.clone(),
+            search_opts: ldap.search_opts.clone(),
+        }
+    }
+
+    fn restore(&self, ldap: &mut Ldap) {
+        rearm(ldap, &self.controls, self.timeout);
+        ldap.deadline = self.deadline;
+        ldap.cancel = self.cancel.clone();
+        ldap.search_opts = self.search_opts.clone();
+    }
+}
+
+fn conditional_outcome(res: LdapResult) -> Result<ModifyIfOutcome> {
+    match res.rc {
+        0 => Ok(ModifyIfOutcome::Applied(res)),
+        122 => Ok(ModifyIfOutcome::PreconditionFailed(res)),
+        _ => Err(LdapError::from(res)),
+    }
+}
+
+fn invalid(field: &'static str, reason: &str) -> LdapError {
+    LdapError::InvalidRequest {
+        field,
+        reason: reason.to_owned(),
+    }
+}
+
+// Split `s` on the unescaped occurrences of `sep`, checking that every escape is either
+// a special character or a pair of hex digits, as required by RFC 4514.
+pub(crate) fn split_unescaped(s: &str, sep: u8) -> std::result::Result<Vec<&str>, &'static str> {
+    let bytes = s.as_bytes();
+    let mut parts = vec![];
+    let (mut start, mut i) = (0, 0);
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => match (bytes.get(i + 1), bytes.get(i + 2)) {
+                (Some(h), Some(l)) if h.is_ascii_hexdigit() && l.is_ascii_hexdigit() => i += 3,
+                (Some(c), _) if b" \"#+,;<=>\\".contains(c) => i += 2,
+                _ => return Err("invalid escape sequence"),
+            },
+            c if c == sep => {
+                parts.push(&s[start..i]);
+                i += 1;
+                start = i;
+            }
+            _ => i += 1,
+        }
+    }
+    parts.push(&s[start..]);
+    Ok(parts)
+}
+
+fn check_rdn(rdn: &str) -> std::result::Result<(), &'static str> {
+    for ava in split_unescaped(rdn, b'+')? {
+        let attr = match ava.split_once('=') {
+            Some((attr, _)) => attr.trim_matches(' '),
+            None => return Err("RDN without a value"),
+        };
+        let descr = attr.starts_with(|c: char| c.is_ascii_alphabetic())
+            && attr.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+        let numericoid = !attr.is_empty()
+            && attr
+                .split('.')
+                .all(|arc| !arc.is_empty() && arc.chars().all(|c| c.is_ascii_digit()));
+        if !descr && !numericoid {
+            return Err("invalid attribute type in RDN");
+        }
+    }
+    Ok(())
+}
+
+// Check that `dn` is a syntactically valid DN. The check is lenient about spaces around
+// the separators, and accepts Active Directory's extended forms like `<GUID=...>` unchecked.
+// An empty DN is accepted unless `required` is true.
+pub(crate) fn validate_dn(field: &'static str, dn: &str, required: bool) -> Result<()> {
+    if dn.contains('\0') {
+        return Err(invalid(field, "NUL character"));
+    }
+    if dn.is_empty() {
+        return match required {
+            true => Err(invalid(field, "empty DN")),
+            false => Ok(()),
+        };
+    }
+    if dn.starts_with('<') {
+        return Ok(());
+    }
+    split_unescaped(dn, b',')
+        .and_then(|rdns| rdns.into_iter().try_for_each(check_rdn))
+        .map_err(|reason| invalid(field, reason))
+}
+
+// Check that `rdn` is a single, syntactically valid RDN.
+pub(crate) fn validate_rdn(rdn: &str) -> Result<()> {
+    if rdn.contains('\0') {
+        return Err(invalid("rdn", "NUL character"));
+    }
+    if rdn.is_empty() {
+        return Err(invalid("rdn", "empty RDN"));
+    }
+    match split_unescaped(rdn, b',') {
+        Ok(rdns) if rdns.len() > 1 => Err(invalid("rdn", "more than one RDN")),
+        res => res
+            .and_then(|_| check_rdn(rdn))
+            .map_err(|reason| invalid("rdn", reason)),
+    }
+}
+
+// Check that `val` is a decimal integer, as required for the value of an Increment.
+fn validate_increment(val: &[u8]) -> Result<()> {
+    let digits = val.strip_prefix(b"-").unwrap_or(val);
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+        return Err(invalid("mods", "Increment value is not an integer"));
+    }
+    Ok(())
+}
+
 impl Ldap {
-    fn next_msgid(&mut self) -> i32 {
+    fn next_msgid(&mut self) -> Result<RequestId> {
         let mut msgmap = self.msgmap.lock().expect("msgmap mutex (inc id)");
-        let last_ldap_id = msgmap.0;
-        let mut next_ldap_id = last_ldap_id;
-        loop {
-            if next_ldap_id == std::i32::MAX {
-                next_ldap_id = 1;
-            } else {
-                next_ldap_id += 1;
-            }
-            if !msgmap.1.contains(&next_ldap_id) {
-                break;
-            }
-            assert_ne!(
-                next_ldap_id, last_ldap_id,
-                "LDAP message id wraparound with no free slots"
-            );
+        let id = alloc_msgid(&mut msgmap, RequestId::MAX)?;
+        if msgmap.1.len().is_multiple_of(RECONCILE_INTERVAL) {
+            let _ = self.id_scrub_tx.send(0);
         }
-        msgmap.0 = next_ldap_id;
-        msgmap.1.insert(next_ldap_id);
-        next_ldap_id
+        Ok(id)
     }
 
     pub(crate) async fn op_call(
@@ -174,25 +746,198 @@ impl Ldap {
         op: LdapOp,
         req: Tag,
     ) -> Result<(LdapResult, Exop, SaslCreds)> {
-        let id = self.next_msgid();
+        let retries = std::mem::take(&mut self.retries);
+        let policy = match self.retry_policy.clone() {
+            Some(policy) if matches!(op, LdapOp::Single) && (retries || is_idempotent(&req)) => {
+                policy
+            }
+            _ => return self.op_attempt(op, req).await,
+        };
+        let pending = Pending::save(self);
+        let mut attempt = 1;
+        loop {
+            let res = self.op_attempt(LdapOp::Single, req.clone()).await;
+            let retry = match res {
+                Ok((ref result, _, _)) => policy.retry_rc(result.rc),
+                Err(ref e) => policy.retry_err(e),
+            };
+            if !retry || !policy.pause(attempt, pending.deadline).await {
+                return res;
+            }
+            pending.restore(self);
+            attempt += 1;
+        }
+    }
+
+    async fn op_attempt(&mut self, op: LdapOp, req: Tag) -> Result<(LdapResult, Exop, SaslCreds)> {
+        let cancel = self.cancel.take();
+        if let Some(cause) = cancel.as_ref().and_then(OpCancellation::cause) {
+            self.timeout = None;
+            self.deadline = None;
+            self.controls = None;
+            return Err(LdapError::Cancelled { cause, entries: 0 });
+        }
+        if let Some(e) = self.notifier.poisoned() {
+            self.timeout = None;
+            self.deadline = None;
+            self.controls = None;
+            return Err(e);
+        }
+        let deadline = self.deadline.take();
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            self.timeout = None;
+            self.controls = None;
+            return Err(LdapError::DeadlineExceeded);
+        }
+        let name = match op {
+            LdapOp::Streamed(_) => "add",
+            _ => op_name(&req),
+        };
+        if self.strict_validation {
+            if let Some(Err(e)) = self.controls.as_deref().map(|c| check_control_ops(c, name)) {
+                self.timeout = None;
+                self.controls = None;
+                return Err(e);
+            }
+        }
+        let id = match self.next_msgid() {
+            Ok(id) => id,
+            Err(e) => {
+                self.timeout = None;
+                self.controls = None;
+                return Err(e);
+            }
+        };
         self.last_id = id;
+        let abandonable = match op {
+            LdapOp::Single | LdapOp::Streamed(_) => is_abandonable(&req),
+            _ => false,
+        };
+        let is_search = matches!(op, LdapOp::Search(_));
+        self.last_started = self.observer.as_ref().map(|observer| {
+            observer.0.on_request(id, name);
+            Instant::now()
+        });
         let (tx, rx) = oneshot::channel();
-        self.tx.send((id, op, req, self.controls.take(), tx))?;
-        let response = if let Some(timeout) = self.timeout.take() {
-            let res = time::timeout(timeout, rx).await;
-            if res.is_err() {
-                self.id_scrub_tx.send(self.last_id)?;
+        let timeout = self.timeout.take();
+        self.tx
+            .send((id, op, req, self.controls.take(), tx))
+            .map_err(|e| self.notifier.map_err(e.into()).in_operation(name, id))?;
+        let wait = async {
+            match (cancel, timeout) {
+                (Some(cancel), timeout) => {
+                    let sleep = async {
+                        match timeout {
+                            Some(timeout) => rt::sleep(timeout).await,
+                            None => futures_util::future::pending().await,
+                        }
+                    };
+                    let cause = tokio::select! {
+                        biased;
+                        res = rx => Ok(res),
+                        cause = cancel.cancelled() => Err(cause),
+                        _ = sleep => {
+                            cancel.trigger(CancelCause::Timeout);
+                            Err(cancel.cause().unwrap_or(CancelCause::Timeout))
+                        },
+                    };
+                    match cause {
+                        Ok(res) => res,
+                        Err(cause) => {
+                            self.cancel_msgid(id, abandonable).await?;
+                            return Err(LdapError::Cancelled { cause, entries: 0 });
+                        }
+                    }
+                }
+                (None, Some(timeout)) => {
+                    let res = rt::timeout(timeout, rx).await;
+                    if res.is_err() {
+                        self.expire_msgid(id, abandonable)?;
+                    }
+                    res?
+                }
+                (None, None) => rx.await,
             }
-            res?
-        } else {
-            rx.await
-        }?;
+            .map_err(|e| self.notifier.map_err(e.into()).in_operation(name, id))
+        };
+        let response = match deadline {
+            Some(deadline) => {
+                let res = rt::timeout_at(rt::instant_from_std(deadline), wait).await;
+                match res {
+                    Ok(res) => res?,
+                    Err(_) => {
+                        self.expire_msgid(id, abandonable)?;
+                        return Err(LdapError::DeadlineExceeded);
+                    }
+                }
+            }
+            None => wait.await?,
+        };
         let (ldap_ext, controls) = (LdapResultExt::from(response.0), response.1);
         let (mut result, exop, sasl_creds) = (ldap_ext.0, ldap_ext.1, ldap_ext.2);
         result.ctrls = controls;
+        // The result of a Search arrives through its stream.
+        if !is_search {
+            self.observe_response(result.rc);
+        }
         Ok((result, exop, sasl_creds))
     }
 
+    // Mark the connection as unusable after a failed TLS upgrade. The connection loop
+    // is woken up by a no-op scrubbing request, and tears the connection down.
+    #[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
+    pub(crate) fn poison(&self, e: LdapError) -> LdapError {
+        let e = self.notifier.poison(e);
+        let _ = self.id_scrub_tx.send(0);
+        e
+    }
+
+    pub(crate) fn observe_response(&self, rc: u32) {
+        if let (Some(observer), Some(started)) = (self.observer.as_ref(), self.last_started) {
+            observer.0.on_response(self.last_id, rc, started.elapsed());
+        }
+    }
+
+    // Discard the local state of a cancelled operation, and ask the server to
+    // abandon it if the operation permits.
+    pub(crate) async fn cancel_msgid(&mut self, msgid: RequestId, abandon: bool) -> Result<()> {
+        self.id_scrub_tx.send(msgid)?;
+        if !abandon {
+            return Ok(());
+        }
+        // The connection replies as soon as the request is written; an error
+        // means that the connection is gone, and there's nothing to abandon.
+        let _ = self.send_abandon(msgid)?.await;
+        Ok(())
+    }
+
+    // Discard the local state of a timed-out operation, and abandon it if the operation
+    // permits and the connection is so configured. Unlike cancellation, this doesn't wait
+    // for the Abandon request to be written, since the timeout may have been caused by
+    // the connection being unable to make progress.
+    pub(crate) fn expire_msgid(&mut self, msgid: RequestId, abandonable: bool) -> Result<()> {
+        self.id_scrub_tx.send(msgid)?;
+        if abandonable && self.abandon_on_timeout {
+            let rx = self.send_abandon(msgid)?;
+            rt::spawn(async move {
+                let _ = rx.await;
+            });
+        }
+        Ok(())
+    }
+
+    fn send_abandon(&mut self, msgid: RequestId) -> Result<oneshot::Receiver<(Tag, Vec<Control>)>> {
+        let id = self.next_msgid()?;
+        let req = Tag::Integer(Integer {
+            id: 16,
+            class: TagClass::Application,
+            inner: msgid as i64,
+        });
+        let (tx, rx) = oneshot::channel();
+        self.tx.send((id, LdapOp::Abandon(msgid), req, None, tx))?;
+        Ok(rx)
+    }
+
     /// Use the provided `SearchOptions` with the next Search operation, which can
     /// be invoked directly on the result of this method. If this method is used in
     /// combination with a non-Search operation, the provided options will be silently
@@ -216,6 +961,13 @@ impl Ldap {
     /// latter is intended to make the call site less noisy, since it's expected
     /// that passing a single control will comprise the majority of uses.
     ///
+    /// A control implemented by this library which can't be used with the operation,
+    /// such as Paged Results with anything but a Search, makes the operation fail with
+    /// [`LdapError::ControlNotAllowed`](result/enum.LdapError.html#variant.ControlNotAllowed)
+    /// without contacting the server, unless the connection was made with
+    /// [`set_strict_validation(false)`](struct.LdapConnSettings.html#method.set_strict_validation).
+    /// Other controls are sent as given.
+    ///
     /// The desired operation can be invoked on the result of this method.
     pub fn with_controls<V: IntoRawControlVec>(&mut self, ctrls: V) -> &mut Self {
         self.controls = Some(ctrls.into());
@@ -235,6 +987,58 @@ impl Ldap {
         self
     }
 
+    /// Retry the next operation according to the retry policy of the connection, set with
+    /// [`LdapConnSettings::set_retry_policy()`](struct.LdapConnSettings.html#method.set_retry_policy),
+    /// even if the operation isn't one which is retried automatically. It's up to the caller
+    /// to ensure that repeating the operation is safe, e.g., that a Modify which adds a value
+    /// won't fail when repeated after its first attempt succeeded but the response was lost.
+    /// Without a retry policy, this method has no effect.
+    ///
+    /// The desired operation can be invoked on the result of this method.
+    pub fn with_retries(&mut self) -> &mut Self {
+        self.retries = true;
+        self
+    }
+
+    /// Make the next helper which consults the server capabilities choose its default
+    /// strategy without them, so that neither the cached capabilities nor the root DSE
+    /// are examined. The helpers which honor this flag are [`search_paged()`](#method.search_paged)
+    /// and [`delete_subtree_with()`](#method.delete_subtree_with); for
+    /// [`change_password()`](#method.change_password), an explicit flavor has the same effect.
+    ///
+    /// The desired operation can be invoked on the result of this method.
+    pub fn without_probe(&mut self) -> &mut Self {
+        self.no_probe = true;
+        self
+    }
+
+    /// Perform the next operation with a deadline. If the operation hasn't completed
+    /// by the given instant, it will return
+    /// [`LdapError::DeadlineExceeded`](result/enum.LdapError.html#variant.DeadlineExceeded),
+    /// and be abandoned on the server like an operation which has timed out.
+    ///
+    /// Unlike the timeout, the deadline isn't reset for each Search reply, and it also
+    /// applies to all operations which an adapter performs on behalf of a Search, such
+    /// as the requests for subsequent pages by
+    /// [`PagedResults`](adapters/struct.PagedResults.html). Both can be set for the same
+    /// operation; whichever expires first ends it.
+    ///
+    /// The desired operation can be invoked on the result of this method.
+    pub fn with_deadline(&mut self, deadline: Instant) -> &mut Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Attach a cancellation token to the next operation. See
+    /// [`OpCancellation`](struct.OpCancellation.html) for the description of how the
+    /// operation is cancelled, and how cancellation interacts with the timeout.
+    ///
+    /// The desired operation can be invoked on the result of this method.
+    pub fn with_cancellation(&mut self, cancel: &OpCancellation) -> &mut Self {
+        self.cancel = Some(cancel.clone());
+        self
+    }
+
     /// Do a simple Bind with the provided DN (`bind_dn`) and password (`bind_pw`).
     pub async fn simple_bind(&mut self, bind_dn: &str, bind_pw: &str) -> Result<LdapResult> {
         let req = Tag::Sequence(Sequence {
@@ -245,15 +1049,12 @@ impl Ldap {
                     inner: 3,
                     ..Default::default()
                 }),
-                Tag::OctetString(OctetString {
-                    inner: Vec::from(bind_dn),
-                    ..Default::default()
-                }),
-                Tag::OctetString(OctetString {
-                    id: 0,
-                    class: TagClass::Context,
-                    inner: Vec::from(bind_pw),
-                }),
+                octet_str(bind_dn),
+                OctetString::from_tagged_bytes(
+                    TagClass::Context,
+                    0,
+                    Bytes::copy_from_slice(bind_pw.as_bytes()),
+                ),
             ],
         });
         Ok(self.op_call(LdapOp::Single, req).await?.0)
@@ -268,8 +1069,6 @@ impl Ldap {
         Ok(self.op_call(LdapOp::Single, req).await?.0)
     }
 
-    #[cfg_attr(docsrs, doc(cfg(feature = "gssapi")))]
-    #[cfg(feature = "gssapi")]
     /// Do an SASL GSSAPI bind on the connection, using the default Kerberos credentials
     /// for the current user and `server_fqdn` for the LDAP server SPN. If the connection
     /// is in the clear, request and install the Kerberos confidentiality protection
@@ -278,55 +1077,66 @@ impl Ldap {
     ///
     /// On TLS connections, the __tls-server-end-point__ channel binding token will be
     /// supplied to the server if possible. This enables binding to Active Directory servers
-    /// with the strictest LDAP channel binding enforcement policy.
+    /// with the strictest LDAP channel binding enforcement policy. To supply different
+    /// channel binding data, or none, use
+    /// [`sasl_gssapi_bind_with_cb()`](#method.sasl_gssapi_bind_with_cb).
     ///
     /// The underlying GSSAPI libraries issue blocking filesystem and network calls when
     /// querying the ticket cache or the Kerberos servers. Therefore, the method should not
     /// be used in heavily concurrent contexts with frequent Bind operations.
+    ///
+    /// Without the __gssapi__ feature, the method returns `LdapError::FeatureNotEnabled`.
     pub async fn sasl_gssapi_bind(&mut self, server_fqdn: &str) -> Result<LdapResult> {
+        let cb_data = self.channel_binding_token();
+        self.sasl_gssapi_bind_with_cb(server_fqdn, cb_data.as_deref())
+            .await
+    }
+
+    #[cfg_attr(docsrs, doc(cfg(feature = "gssapi")))]
+    #[cfg(feature = "gssapi")]
+    /// Do an SASL GSSAPI bind like [`sasl_gssapi_bind()`](#method.sasl_gssapi_bind),
+    /// supplying `cb_data` as the __tls-server-end-point__ channel binding data. If
+    /// `cb_data` is `None`, no channel binding is used.
+    pub async fn sasl_gssapi_bind_with_cb(
+        &mut self,
+        server_fqdn: &str,
+        cb_data: Option<&[u8]>,
+    ) -> Result<LdapResult> {
         const LDAP_RESULT_SASL_BIND_IN_PROGRESS: u32 = 14;
-        const GSSAUTH_P_NONE: u8 = 1;
-        const GSSAUTH_P_PRIVACY: u8 = 4;
+
+        fn gss_err(e: impl fmt::Display) -> LdapError {
+            LdapError::GssapiOperationError(format!("{:#}", e))
+        }
 
         let mut spn = String::from("ldap/");
         spn.push_str(server_fqdn);
-        let cti = if self.has_tls {
-            let cbt = {
-                let mut cbt = Vec::from(&b"tls-server-end-point:"[..]);
-                if let Some(ref token) = self.tls_endpoint_token.as_ref() {
-                    cbt.extend(token);
-                    Some(cbt)
-                } else {
-                    None
-                }
+        let cbt = cb_data.map(|data| [&b"tls-server-end-point:"[..], data].concat());
+        let cti = ClientCtx::new(InitiateFlags::empty(), None, &spn, cbt.as_deref());
+        let (mut pending_ctx, token) = cti.map_err(gss_err)?;
+        let mut token = token.to_vec();
+        // Context establishment may take several rounds, each feeding the server's
+        // token to the context until it's complete.
+        let (mut client_ctx, final_token) = loop {
+            let req = sasl_bind_req("GSSAPI", Some(&token));
+            let ans = self.op_call(LdapOp::Single, req).await?;
+            if (ans.0).rc != LDAP_RESULT_SASL_BIND_IN_PROGRESS {
+                return Ok(ans.0);
+            }
+            let server_token = match (ans.2).0 {
+                Some(token) => token,
+                _ => return Err(LdapError::NoGssapiToken),
             };
-            ClientCtx::new(InitiateFlags::empty(), None, &spn, cbt.as_deref())
-        } else {
-            ClientCtx::new(InitiateFlags::empty(), None, &spn, None)
-        };
-        let (client_ctx, token) =
-            cti.map_err(|e| LdapError::GssapiOperationError(format!("{:#}", e)))?;
-        let req = sasl_bind_req("GSSAPI", Some(&token));
-        let ans = self.op_call(LdapOp::Single, req).await?;
-        if (ans.0).rc != LDAP_RESULT_SASL_BIND_IN_PROGRESS {
-            return Ok(ans.0);
-        }
-        let token = match (ans.2).0 {
-            Some(token) => token,
-            _ => return Err(LdapError::NoGssapiToken),
-        };
-        let step = client_ctx
-            .step(&token)
-            .map_err(|e| LdapError::GssapiOperationError(format!("{:#}", e)))?;
-        let mut client_ctx = match step {
-            Step::Finished((ctx, None)) => ctx,
-            _ => {
-                return Err(LdapError::GssapiOperationError(String::from(
-                    "GSSAPI exchange not finished or has an additional token",
-                )))
+            match pending_ctx.step(&server_token).map_err(gss_err)? {
+                Step::Finished((ctx, token)) => break (ctx, token.map(|t| t.to_vec())),
+                Step::Continue((ctx, next_token)) => {
+                    pending_ctx = ctx;
+                    token = next_token.to_vec();
+                }
             }
         };
-        let req = sasl_bind_req("GSSAPI", None);
+        // The established context may produce a last token for the server; if not,
+        // the response is empty. The server then offers the security layers.
+        let req = sasl_bind_req("GSSAPI", final_token.as_deref());
         let ans = self.op_call(LdapOp::Single, req).await?;
         if (ans.0).rc != LDAP_RESULT_SASL_BIND_IN_PROGRESS {
             return Ok(ans.0);
@@ -335,34 +1145,14 @@ impl Ldap {
             Some(token) => token,
             _ => return Err(LdapError::NoGssapiToken),
         };
-        let mut buf = client_ctx
-            .unwrap(&token)
-            .map_err(|e| LdapError::GssapiOperationError(format!("{:#}", e)))?;
-        let needed_layer = if self.has_tls {
-            GSSAUTH_P_NONE
-        } else {
-            GSSAUTH_P_PRIVACY
-        };
-        if buf[0] | needed_layer == 0 {
-            return Err(LdapError::GssapiOperationError(format!(
-                "no appropriate security layer offered: needed {}, mask {}",
-                needed_layer, buf[0]
-            )));
-        }
-        // FIXME: the max_size constant is taken from OpenLDAP GSSAPI code as a fallback
-        // value for broken GSSAPI libraries. It's meant to serve as a safe value until
-        // gss_wrap_size_limit() equivalent is available in cross-krb5.
-        let recv_max_size = (0x9FFFB8u32 | (needed_layer as u32) << 24).to_be_bytes();
-        let size_msg = client_ctx
-            .wrap(true, &recv_max_size)
-            .map_err(|e| LdapError::GssapiOperationError(format!("{:#}", e)))?;
-        let req = sasl_bind_req("GSSAPI", Some(&size_msg));
+        let challenge = client_ctx.unwrap(&token).map_err(gss_err)?;
+        let (layer, send_max_size, reply) = gssapi_security_layer(&challenge, self.has_tls)
+            .map_err(LdapError::GssapiOperationError)?;
+        let reply = client_ctx.wrap(true, &reply).map_err(gss_err)?;
+        let req = sasl_bind_req("GSSAPI", Some(&reply));
         let res = self.op_call(LdapOp::Single, req).await?.0;
         if res.rc == 0 {
-            if needed_layer == GSSAUTH_P_PRIVACY {
-                buf[0] = 0;
-                let send_max_size =
-                    u32::from_be_bytes((&buf[..]).try_into().expect("send max size"));
+            if layer == GSSAUTH_P_PRIVACY {
                 if send_max_size == 0 {
                     warn!("got zero send_max_size, will be treated as unlimited");
                 }
@@ -376,18 +1166,51 @@ impl Ldap {
         Ok(res)
     }
 
-    #[cfg_attr(docsrs, doc(cfg(feature = "ntlm")))]
-    #[cfg(feature = "ntlm")]
+    #[cfg(not(feature = "gssapi"))]
+    /// Stub for the SASL GSSAPI bind, used when the __gssapi__ feature is not enabled.
+    /// Always returns `LdapError::FeatureNotEnabled`.
+    pub async fn sasl_gssapi_bind_with_cb(
+        &mut self,
+        _server_fqdn: &str,
+        _cb_data: Option<&[u8]>,
+    ) -> Result<LdapResult> {
+        Err(LdapError::FeatureNotEnabled {
+            what: String::from("SASL GSSAPI mechanism"),
+            feature: "gssapi",
+        })
+    }
+
     /// Do an SASL GSS-SPNEGO bind with an NTLMSSP exchange on the connection. Username
     /// and password must be provided, since the method is incapable of retrieving the
     /// credentials associated with the login session (which would only work on Windows
     /// anyway.) To specify the domain, incorporate it into the username, using the
     /// `DOMAIN\user` or `user@DOMAIN` format.
     ///
+    /// On TLS connections, the __tls-server-end-point__ channel binding token will be
+    /// supplied to the server if possible. To supply different channel binding data, or
+    /// none, use [`sasl_ntlm_bind_with_cb()`](#method.sasl_ntlm_bind_with_cb).
+    ///
     /// __Caveat:__ the connection is not encrypted by NTLM "sealing". For encryption, use
-    /// TLS. Additionally, no channel binding token is sent on a TLS connection, so some
-    /// strictly configured servers may refuse to work. If possible, use Kerberos/GSSAPI.
+    /// TLS. If possible, use Kerberos/GSSAPI.
+    ///
+    /// Without the __ntlm__ feature, the method returns `LdapError::FeatureNotEnabled`.
     pub async fn sasl_ntlm_bind(&mut self, username: &str, password: &str) -> Result<LdapResult> {
+        let cb_data = self.channel_binding_token();
+        self.sasl_ntlm_bind_with_cb(username, password, cb_data.as_deref())
+            .await
+    }
+
+    #[cfg_attr(docsrs, doc(cfg(feature = "ntlm")))]
+    #[cfg(feature = "ntlm")]
+    /// Do an SASL GSS-SPNEGO/NTLM bind like [`sasl_ntlm_bind()`](#method.sasl_ntlm_bind),
+    /// supplying `cb_data` as the __tls-server-end-point__ channel binding data. If
+    /// `cb_data` is `None`, no channel binding is used.
+    pub async fn sasl_ntlm_bind_with_cb(
+        &mut self,
+        username: &str,
+        password: &str,
+        cb_data: Option<&[u8]>,
+    ) -> Result<LdapResult> {
         const LDAP_RESULT_SASL_BIND_IN_PROGRESS: u32 = 14;
 
         use sspi::{
@@ -400,11 +1223,18 @@ impl Ldap {
             ntlm: &mut Ntlm,
             acq_creds: &mut AcquireCredentialsHandleResult<Option<AuthIdentityBuffers>>,
             input: &[u8],
+            bindings: Option<&[u8]>,
         ) -> Result<Vec<u8>> {
             let mut input = vec![SecurityBuffer::new(
                 input.to_vec(),
                 SecurityBufferType::Token,
             )];
+            if let Some(bindings) = bindings {
+                input.push(SecurityBuffer::new(
+                    bindings.to_vec(),
+                    SecurityBufferType::ChannelBindings,
+                ));
+            }
             let mut output = vec![SecurityBuffer::new(Vec::new(), SecurityBufferType::Token)];
             let mut builder = ntlm
                 .initialize_security_context()
@@ -435,7 +1265,10 @@ impl Ldap {
             .with_credential_use(CredentialUse::Outbound)
             .with_auth_data(&identity)
             .execute(&mut ntlm)?;
-        let req = sasl_bind_req("GSS-SPNEGO", Some(&step(&mut ntlm, &mut acq_creds, &[])?));
+        let req = sasl_bind_req(
+            "GSS-SPNEGO",
+            Some(&step(&mut ntlm, &mut acq_creds, &[], None)?),
+        );
         let (res, _, token) = self.op_call(LdapOp::Single, req).await?;
         if res.rc != LDAP_RESULT_SASL_BIND_IN_PROGRESS {
             return Ok(res);
@@ -444,21 +1277,122 @@ impl Ldap {
             Some(token) => token,
             _ => return Err(LdapError::NoNtlmChallengeToken),
         };
-        if self.has_tls {
-            let mut cbt = Vec::from(&b"tls-server-end-point:"[..]);
-            if let Some(ref token) = self.tls_endpoint_token.as_ref() {
-                cbt.extend(token);
-                // temporary private extension, will see how best to incorporate into sspi-rs
-                // ntlm.set_channel_bindings(&cbt);
-            }
-        }
+        let bindings = cb_data
+            .map(|data| sec_channel_bindings(&[&b"tls-server-end-point:"[..], data].concat()));
         let req = sasl_bind_req(
             "GSS-SPNEGO",
-            Some(&step(&mut ntlm, &mut acq_creds, &token)?),
+            Some(&step(
+                &mut ntlm,
+                &mut acq_creds,
+                &token,
+                bindings.as_deref(),
+            )?),
         );
         Ok(self.op_call(LdapOp::Single, req).await?.0)
     }
 
+    #[cfg(not(feature = "ntlm"))]
+    /// Stub for the SASL GSS-SPNEGO/NTLM bind, used when the __ntlm__ feature is not enabled.
+    /// Always returns `LdapError::FeatureNotEnabled`.
+    pub async fn sasl_ntlm_bind_with_cb(
+        &mut self,
+        _username: &str,
+        _password: &str,
+        _cb_data: Option<&[u8]>,
+    ) -> Result<LdapResult> {
+        Err(LdapError::FeatureNotEnabled {
+            what: String::from("SASL GSS-SPNEGO/NTLM mechanism"),
+            feature: "ntlm",
+        })
+    }
+
+    #[cfg_attr(docsrs, doc(cfg(feature = "scram")))]
+    #[cfg(feature = "scram")]
+    /// Do an SASL SCRAM bind ([RFC 5802](https://tools.ietf.org/html/rfc5802)) on the
+    /// connection, with the hash function determined by `mech`. The password is never sent
+    /// to the server. After the exchange, the server's signature is verified, and if it
+    /// doesn't match, the method returns `LdapError::ScramServerSignature` even if the
+    /// server reported success; the connection should then be regarded as compromised.
+    ///
+    /// Channel binding is not supported. The password is used as given, without SASLprep
+    /// normalization, which makes no difference for ASCII passwords. An iteration count
+    /// above 1,000,000 in the server's challenge is rejected with `LdapError::ScramProtocol`.
+    ///
+    /// Without the __scram__ feature, the method returns `LdapError::FeatureNotEnabled`.
+    pub async fn sasl_scram_bind(
+        &mut self,
+        mech: ScramMech,
+        user: &str,
+        password: &str,
+    ) -> Result<LdapResult> {
+        let client = ScramClient::new(mech, user, password, client_nonce()?);
+        self.sasl_scram_exchange(mech, client).await
+    }
+
+    #[cfg(feature = "scram")]
+    pub(crate) async fn sasl_scram_exchange(
+        &mut self,
+        mech: ScramMech,
+        mut client: ScramClient,
+    ) -> Result<LdapResult> {
+        const LDAP_RESULT_SASL_BIND_IN_PROGRESS: u32 = 14;
+
+        let req = sasl_bind_req(mech.name(), Some(client.client_first().as_bytes()));
+        let (res, _, creds) = self.op_call(LdapOp::Single, req).await?;
+        if res.rc != LDAP_RESULT_SASL_BIND_IN_PROGRESS {
+            if res.rc == 0 {
+                return Err(LdapError::ScramProtocol(String::from(
+                    "bind completed without server-first message",
+                )));
+            }
+            return Ok(res);
+        }
+        let server_first = match creds.0 {
+            Some(creds) => creds,
+            _ => {
+                return Err(LdapError::ScramProtocol(String::from(
+                    "no server-first message",
+                )))
+            }
+        };
+        let client_final = client.client_final(&server_first)?;
+        let req = sasl_bind_req(mech.name(), Some(client_final.as_bytes()));
+        let (res, _, creds) = self.op_call(LdapOp::Single, req).await?;
+        if res.rc != 0 && res.rc != LDAP_RESULT_SASL_BIND_IN_PROGRESS {
+            return Ok(res);
+        }
+        // The server-final message usually accompanies the successful result, but it
+        // may also be sent as another challenge, requiring an empty response.
+        match creds.0 {
+            Some(server_final) => client.verify(&server_final)?,
+            None => {
+                return Err(LdapError::ScramProtocol(String::from(
+                    "no server-final message",
+                )))
+            }
+        }
+        if res.rc == 0 {
+            return Ok(res);
+        }
+        let req = sasl_bind_req(mech.name(), Some(b""));
+        Ok(self.op_call(LdapOp::Single, req).await?.0)
+    }
+
+    #[cfg(not(feature = "scram"))]
+    /// Stub for the SASL SCRAM bind, used when the __scram__ feature is not enabled.
+    /// Always returns `LdapError::FeatureNotEnabled`.
+    pub async fn sasl_scram_bind(
+        &mut self,
+        mech: ScramMech,
+        _user: &str,
+        _password: &str,
+    ) -> Result<LdapResult> {
+        Err(LdapError::FeatureNotEnabled {
+            what: format!("SASL {} mechanism", mech.name()),
+            feature: "scram",
+        })
+    }
+
     /// Perform a Search with the given base DN (`base`), scope, filter, and
     /// the list of attributes to be returned (`attrs`). If `attrs` is empty,
     /// or if it contains a special name `*` (asterisk), return all (user) attributes.
@@ -472,24 +1406,339 @@ impl Ldap {
     /// referrals in the result stream will be collected in the `refs` vector of the
     /// operation result. Any intermediate messages will be discarded.
     ///
-    /// This method should be used if it's known that the result set won't be
-    /// large. For other situations, one can use [`streaming_search()`](#method.streaming_search).
-    pub async fn search<'a, S: AsRef<str> + Send + Sync + 'a, A: AsRef<[S]> + Send + Sync + 'a>(
-        &mut self,
-        base: &str,
-        scope: Scope,
-        filter: &str,
-        attrs: A,
-    ) -> Result<SearchResult> {
-        let mut stream = self
-            .streaming_search_with(EntriesOnly::new(), base, scope, filter, attrs)
-            .await?;
-        let mut re_vec = vec![];
-        while let Some(entry) = stream.next().await? {
-            re_vec.push(entry);
+    /// This method should be used if it's known that the result set won't be
+    /// large. For other situations, one can use [`streaming_search()`](#method.streaming_search).
+    ///
+    /// The attribute list can be anything which can be viewed as a slice of strings,
+    /// owned or borrowed, here and in the streaming variants, with or without adapters:
+    ///
+    /// ```rust,no_run
+    /// # use ldap3::adapters::PagedResults;
+    /// # use ldap3::{Ldap, Scope, result::Result};
+    /// struct Config {
+    ///     attrs: Vec<String>,
+    /// }
+    ///
+    /// # async fn searches(ldap: &mut Ldap) -> Result<()> {
+    /// let config = Config { attrs: vec![String::from("cn")] };
+    /// let base = "dc=example,dc=org";
+    /// ldap.search(base, Scope::Subtree, "(uid=*)", vec!["cn", "mail"]).await?;
+    /// ldap.search(base, Scope::Subtree, "(uid=*)", &["cn", "mail"]).await?;
+    /// ldap.search(base, Scope::Subtree, "(uid=*)", vec![String::from("cn")]).await?;
+    /// ldap.search(base, Scope::Subtree, "(uid=*)", &config.attrs).await?;
+    /// let mut stream = ldap
+    ///     .streaming_search(base, Scope::Subtree, "(uid=*)", &config.attrs[..])
+    ///     .await?;
+    /// while let Some(_entry) = stream.next().await? {}
+    /// let mut stream = ldap
+    ///     .streaming_search_with(PagedResults::new(100), base, Scope::Subtree, "(uid=*)", &["cn"])
+    ///     .await?;
+    /// while let Some(_entry) = stream.next().await? {}
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Aliases are not dereferenced by default. To have the server follow them, set
+    /// the [`DerefAliases`](enum.DerefAliases.html) mode in the options passed to
+    /// [`with_search_options()`](#method.with_search_options).
+    pub async fn search<'a, S: AsRef<str> + Send + Sync + 'a, A: AsRef<[S]> + Send + Sync + 'a>(
+        &mut self,
+        base: &str,
+        scope: Scope,
+        filter: &str,
+        attrs: A,
+    ) -> Result<SearchResult> {
+        let policy = match self.retry_policy.clone() {
+            Some(policy) => policy,
+            None => return self.search_attempt(base, scope, filter, attrs).await,
+        };
+        let attrs: Vec<&str> = attrs.as_ref().iter().map(AsRef::as_ref).collect();
+        let pending = Pending::save(self);
+        let mut attempt = 1;
+        loop {
+            let res = self.search_attempt(base, scope, filter, &attrs).await;
+            let retry = match res {
+                Ok(ref res) => policy.retry_rc(res.1.rc),
+                Err(ref e) => policy.retry_err(e),
+            };
+            if !retry || !policy.pause(attempt, pending.deadline).await {
+                return res;
+            }
+            pending.restore(self);
+            attempt += 1;
+        }
+    }
+
+    async fn search_attempt<
+        'a,
+        S: AsRef<str> + Send + Sync + 'a,
+        A: AsRef<[S]> + Send + Sync + 'a,
+    >(
+        &mut self,
+        base: &str,
+        scope: Scope,
+        filter: &str,
+        attrs: A,
+    ) -> Result<SearchResult> {
+        let mut stream = self
+            .streaming_search_with(EntriesOnly::new(), base, scope, filter, attrs)
+            .await?;
+        let mut re_vec = vec![];
+        while let Some(entry) = stream.next().await? {
+            re_vec.push(entry);
+        }
+        let res = stream.finish().await;
+        Ok(SearchResult(re_vec, res))
+    }
+
+    /// Perform a Search like [`search()`](#method.search) (q.v., also for the
+    /// parameters), but retrieve the results in pages of `page_size` entries with
+    /// the Paged Results control, so that a server-imposed limit on the size of
+    /// a single result set doesn't truncate the search. All pages are collected
+    /// into the returned structure.
+    ///
+    /// Controls set with [`with_controls()`](#method.with_controls) are sent with
+    /// the request for each page; they must not include a Paged Results control.
+    /// The Paged Results response control of the last page is left in the `ctrls`
+    /// vector of the operation result, where it can be examined for the server's
+    /// estimate of the result set size.
+    ///
+    /// The [server capabilities](#method.capabilities) are consulted first, and if the
+    /// server lists its supported controls without Paged Results, a plain Search is
+    /// performed instead. A failure to read the capabilities is ignored. Calling
+    /// [`without_probe()`](#method.without_probe) beforehand skips the check.
+    pub async fn search_paged<
+        'a,
+        S: AsRef<str> + Clone + fmt::Debug + Send + Sync + 'a,
+        A: AsRef<[S]> + Clone + fmt::Debug + Send + Sync + 'a,
+    >(
+        &mut self,
+        base: &str,
+        scope: Scope,
+        filter: &str,
+        attrs: A,
+        page_size: i32,
+    ) -> Result<SearchResult> {
+        if !std::mem::take(&mut self.no_probe) {
+            if let Ok(caps) = self.capabilities().await {
+                if caps.lacks_control(PAGED_RESULTS_OID) {
+                    return self.search(base, scope, filter, attrs).await;
+                }
+            }
+        }
+        let adapters: Vec<Box<dyn Adapter<S, A>>> = vec![
+            Box::new(EntriesOnly::new()),
+            Box::new(PagedResults::new(page_size).keep_response()),
+        ];
+        let mut stream = self
+            .streaming_search_with(adapters, base, scope, filter, attrs)
+            .await?;
+        let mut re_vec = vec![];
+        while let Some(entry) = stream.next().await? {
+            re_vec.push(entry);
+        }
+        let res = stream.finish().await;
+        Ok(SearchResult(re_vec, res))
+    }
+
+    /// Perform a Search described by an LDAP URL. The search base, scope, filter and
+    /// attribute list are taken from the URL, with missing components filled in by
+    /// [`LdapUrl::parse()`](struct.LdapUrl.html#method.parse). URL extensions are ignored.
+    ///
+    /// If the URL names a host, it must match the host and port of this connection, or
+    /// the method will return an error; setting the `allow_foreign_host` flag of the URL
+    /// disables this check. A URL with an empty host, such as `ldap:///dc=example,dc=org`,
+    /// can be used with any connection.
+    pub async fn search_url(&mut self, url: &LdapUrl) -> Result<SearchResult> {
+        if !url.host.is_empty() && !url.allow_foreign_host {
+            let matches = match self.host_port.as_ref() {
+                Some((host, port)) => {
+                    url.host.eq_ignore_ascii_case(host) && url.port_or_default() == *port
+                }
+                None => false,
+            };
+            if !matches {
+                return Err(LdapError::ForeignHost(format!(
+                    "{}:{}",
+                    url.host,
+                    url.port_or_default()
+                )));
+            }
+        }
+        self.search(&url.base, url.scope, &url.filter, &url.attrs)
+            .await
+    }
+
+    /// Perform a Search with the given base DN, scope and filter, and return the DNs
+    /// of the found entries. No attributes are requested, and referrals in the result
+    /// stream are skipped. An unsuccessful result code is returned as an error.
+    pub async fn search_dns(
+        &mut self,
+        base: &str,
+        scope: Scope,
+        filter: &str,
+    ) -> Result<Vec<String>> {
+        let mut stream = self
+            .streaming_search_with(EntriesOnly::new(), base, scope, filter, vec!["1.1"])
+            .await?;
+        let mut dns = vec![];
+        while let Some(entry) = stream.next().await? {
+            dns.push(SearchEntry::construct(entry).dn);
+        }
+        stream.finish().await.success()?;
+        Ok(dns)
+    }
+
+    /// Check whether the entry named by `dn` exists, with a base-scoped Search for
+    /// `(objectClass=*)`. The result code noSuchObject (32) produces `Ok(false)`,
+    /// while other unsuccessful result codes are returned as errors.
+    pub async fn exists(&mut self, dn: &str) -> Result<bool> {
+        let mut stream = self
+            .streaming_search_with(
+                EntriesOnly::new(),
+                dn,
+                Scope::Base,
+                "(objectClass=*)",
+                vec!["1.1"],
+            )
+            .await?;
+        let mut found = false;
+        while stream.next().await?.is_some() {
+            found = true;
+        }
+        let res = stream.finish().await;
+        match res.rc {
+            0 => Ok(found),
+            32 => Ok(false),
+            _ => Err(LdapError::from(res)),
+        }
+    }
+
+    /// Count the entries matching a Search with the given base DN, scope and filter.
+    /// Entries are counted as they arrive, without requesting any attributes, and
+    /// referrals are skipped.
+    ///
+    /// If the server ends the Search with sizeLimitExceeded (4), and advertises support
+    /// for the Paged Results control in its [capabilities](#method.capabilities), the Search
+    /// is repeated with paging,
+    /// using the number of entries received before the limit was hit as the page size.
+    /// Controls, the timeout and Search options set on the handle apply to both Searches.
+    pub async fn count(&mut self, base: &str, scope: Scope, filter: &str) -> Result<u64> {
+        let controls = self.controls.clone();
+        let timeout = self.timeout;
+        let deadline = self.deadline;
+        let cancel = self.cancel.clone();
+        let search_opts = self.search_opts.clone();
+        let mut stream = self
+            .streaming_search_with(EntriesOnly::new(), base, scope, filter, vec!["1.1"])
+            .await?;
+        let mut count = 0;
+        while stream.next().await?.is_some() {
+            count += 1;
+        }
+        let res = stream.finish().await;
+        if res.rc != 4 {
+            res.success()?;
+            return Ok(count);
+        }
+        if !self
+            .capabilities()
+            .await?
+            .supports_control(PAGED_RESULTS_OID)
+        {
+            return Err(LdapError::from(res));
+        }
+        let page_size = count.max(1) as i32;
+        rearm(self, &controls, timeout);
+        self.deadline = deadline;
+        self.cancel = cancel;
+        self.search_opts = search_opts;
+        let adapters: Vec<Box<dyn Adapter<&str, Vec<&str>>>> = vec![
+            Box::new(EntriesOnly::new()),
+            Box::new(PagedResults::new(page_size)),
+        ];
+        let mut stream = self
+            .streaming_search_with(adapters, base, scope, filter, vec!["1.1"])
+            .await?;
+        let mut count = 0;
+        while stream.next().await?.is_some() {
+            count += 1;
+        }
+        stream.finish().await.success()?;
+        Ok(count)
+    }
+
+    /// Retrieve the root DSE of the server, and parse its well-known attributes.
+    ///
+    /// All user and operational attributes of the root DSE are requested.
+    pub async fn root_dse(&mut self) -> Result<RootDse> {
+        let (entries, _res) = self
+            .search("", Scope::Base, "(objectClass=*)", vec!["*", "+"])
+            .await?
+            .success()?;
+        match entries.into_iter().next() {
+            Some(entry) => Ok(RootDse::from_entry(SearchEntry::construct(entry))),
+            None => Err(LdapError::EntryNotFound(String::from(""))),
+        }
+    }
+
+    /// Return the capabilities of the server, as advertised in its root DSE.
+    ///
+    /// The root DSE is read on the first call, and the capabilities are cached for the
+    /// connection, so that all clones of the handle share them. A failure to read the
+    /// root DSE is returned as an error, and isn't cached. The Search ignores the
+    /// modifiers set on the handle, which are left for the next operation.
+    ///
+    /// The capabilities a server advertises may depend on the authentication state, and
+    /// can change after a Bind; [`invalidate_capabilities()`](#method.invalidate_capabilities)
+    /// discards the cached ones.
+    pub async fn capabilities(&mut self) -> Result<ServerCapabilities> {
+        if let Some(caps) = self.capabilities.lock().expect("capabilities").as_ref() {
+            return Ok(caps.clone());
+        }
+        let pending = Pending::save(self);
+        let retries = std::mem::take(&mut self.retries);
+        rearm(self, &None, None);
+        self.deadline = None;
+        self.cancel = None;
+        self.search_opts = None;
+        let res = self.root_dse().await;
+        pending.restore(self);
+        self.retries = retries;
+        let caps = ServerCapabilities::from_root_dse(res?);
+        *self.capabilities.lock().expect("capabilities") = Some(caps.clone());
+        Ok(caps)
+    }
+
+    /// Discard the cached server capabilities, for this handle and all its clones. The
+    /// root DSE will be read again on the next call to [`capabilities()`](#method.capabilities).
+    pub fn invalidate_capabilities(&mut self) {
+        self.capabilities.lock().expect("capabilities").take();
+    }
+
+    /// Retrieve the schema of the server.
+    ///
+    /// The DN of the subschema entry is taken from the root DSE, falling back to
+    /// `cn=Subschema` if the root DSE doesn't advertise it. Attribute type and object
+    /// class definitions which can't be parsed are skipped.
+    pub async fn schema(&mut self) -> Result<Schema> {
+        let root_dse = self.root_dse().await?;
+        let dn = root_dse
+            .subschema_subentry
+            .unwrap_or_else(|| String::from("cn=Subschema"));
+        let (entries, _res) = self
+            .search(
+                &dn,
+                Scope::Base,
+                "(objectClass=subschema)",
+                vec!["attributeTypes", "objectClasses"],
+            )
+            .await?
+            .success()?;
+        match entries.into_iter().next() {
+            Some(entry) => Ok(Schema::from_entry(SearchEntry::construct(entry))),
+            None => Err(LdapError::EntryNotFound(dn)),
         }
-        let res = stream.finish().await;
-        Ok(SearchResult(re_vec, res))
     }
 
     /// Perform a Search, but unlike [`search()`](#method.search) (q.v., also for
@@ -530,29 +1779,58 @@ impl Ldap {
         let mut ldap = self.clone();
         ldap.controls = self.controls.take();
         ldap.timeout = self.timeout.take();
+        ldap.deadline = self.deadline.take();
+        ldap.cancel = self.cancel.take();
         ldap.search_opts = self.search_opts.take();
         let mut stream = SearchStream::new(ldap, adapters.into());
         stream.start(base, scope, filter, attrs).await?;
         Ok(stream)
     }
 
+    /// Watch the entries in the scope of a Search for changes, using a streaming Search
+    /// with a critical [`PersistentSearch`](controls/struct.PersistentSearch.html) control
+    /// built from `psearch`. The other parameters are the same as for
+    /// [`streaming_search()`](#method.streaming_search).
+    ///
+    /// The Search doesn't end by itself, and [`next()`](struct.SearchStream.html#method.next)
+    /// keeps returning the changed entries as the server sends them. If the control requests
+    /// them, each entry carries an [`EntryChange`](controls/struct.EntryChange.html) control
+    /// among its [`controls()`](struct.ResultEntry.html#method.controls). A timeout set on the
+    /// handle applies to the wait for each entry separately, while a deadline would end
+    /// the watch. The Search is stopped with
+    /// [`abandon()`](struct.SearchStream.html#method.abandon).
+    pub async fn watch<'a, S: AsRef<str> + Send + Sync + 'a, A: AsRef<[S]> + Send + Sync + 'a>(
+        &mut self,
+        base: &str,
+        scope: Scope,
+        filter: &str,
+        attrs: A,
+        psearch: PersistentSearch,
+    ) -> Result<SearchStream<'a, S, A>> {
+        let mut ctrl = RawControl::from(psearch);
+        ctrl.crit = true;
+        self.controls.get_or_insert_with(Vec::new).push(ctrl);
+        self.streaming_search(base, scope, filter, attrs).await
+    }
+
     /// Add an entry named by `dn`, with the list of attributes and their values
     /// given in `attrs`. None of the `HashSet`s of values for an attribute may
     /// be empty.
-    pub async fn add<S: AsRef<[u8]> + Eq + Hash>(
+    pub async fn add<S: ValueBytes + Eq + Hash>(
         &mut self,
         dn: &str,
         attrs: Vec<(S, HashSet<S>)>,
     ) -> Result<LdapResult> {
+        if self.strict_validation {
+            validate_dn("dn", dn, true)?;
+        }
+        let canonical = self.canonical_sets;
         let mut any_empty = false;
         let req = Tag::Sequence(Sequence {
             id: 8,
             class: TagClass::Application,
             inner: vec![
-                Tag::OctetString(OctetString {
-                    inner: Vec::from(dn.as_bytes()),
-                    ..Default::default()
-                }),
+                octet_str(dn),
                 Tag::Sequence(Sequence {
                     inner: attrs
                         .into_iter()
@@ -562,22 +1840,13 @@ impl Ldap {
                             }
                             Tag::Sequence(Sequence {
                                 inner: vec![
-                                    Tag::OctetString(OctetString {
-                                        inner: Vec::from(name.as_ref()),
-                                        ..Default::default()
-                                    }),
-                                    Tag::Set(Set {
-                                        inner: vals
-                                            .into_iter()
-                                            .map(|v| {
-                                                Tag::OctetString(OctetString {
-                                                    inner: Vec::from(v.as_ref()),
-                                                    ..Default::default()
-                                                })
-                                            })
+                                    OctetString::from_bytes(name.into_value_bytes()),
+                                    value_set(
+                                        vals.into_iter()
+                                            .map(|v| OctetString::from_bytes(v.into_value_bytes()))
                                             .collect(),
-                                        ..Default::default()
-                                    }),
+                                        canonical,
+                                    ),
                                 ],
                                 ..Default::default()
                             })
@@ -593,35 +1862,96 @@ impl Ldap {
         Ok(self.op_call(LdapOp::Single, req).await?.0)
     }
 
+    /// Add an entry named by `dn`, streaming the values given by readers directly to the
+    /// connection, without holding them in memory.
+    ///
+    /// Each element of `attrs` is a single attribute value; multiple values of an attribute
+    /// are given by repeating its name. Values from [`ValueSource::Reader`](enum.ValueSource.html)
+    /// must have their length declared in advance, since it's a part of the BER encoding of
+    /// the request. While the request is written, the connection doesn't process other
+    /// requests or responses.
+    ///
+    /// If a reader returns fewer or more bytes than declared, the request can't be correctly
+    /// completed. In that case, it's left unfinished on the wire and the connection is closed,
+    /// failing all outstanding operations. This operation returns
+    /// [`LdapError::StreamedValueLength`](result/enum.LdapError.html#variant.StreamedValueLength).
+    /// Streaming is not available on connections with SASL security layers.
+    pub async fn add_streaming<S: ValueBytes>(
+        &mut self,
+        dn: &str,
+        attrs: Vec<(S, ValueSource)>,
+    ) -> Result<LdapResult> {
+        #[cfg(feature = "gssapi")]
+        if self.sasl_param.read().expect("sasl param").0 {
+            return Err(LdapError::StreamingWithSaslWrap);
+        }
+        if self.strict_validation {
+            validate_dn("dn", dn, true)?;
+        }
+        let mut grouped: Vec<(S, Vec<ValueSource>)> = vec![];
+        for (name, val) in attrs {
+            match grouped
+                .iter_mut()
+                .find(|(n, _)| n.as_ref() == name.as_ref())
+            {
+                Some((_, vals)) => vals.push(val),
+                None => grouped.push((name, vec![val])),
+            }
+        }
+        let mut attr_list = Segments::default();
+        for (name, vals) in grouped {
+            let mut val_set = Segments::default();
+            for val in vals {
+                match val {
+                    ValueSource::InMemory(v) => val_set.push_tag(OctetString::from_bytes(v)),
+                    ValueSource::Reader { len, reader } => val_set.push_reader(len, reader),
+                }
+            }
+            let mut attr = Segments::default();
+            attr.push_tag(OctetString::from_bytes(name.into_value_bytes()));
+            attr.append(val_set.wrap(0x31));
+            attr_list.append(attr.wrap(0x30));
+        }
+        let mut op = Segments::default();
+        op.push_tag(octet_str(dn));
+        op.append(attr_list.wrap(0x30));
+        let (status, mut status_rx) = oneshot::channel();
+        let req = StreamedRequest {
+            op: op.wrap(0x68),
+            status,
+        };
+        let res = self
+            .op_call(LdapOp::Streamed(Box::new(req)), Tag::Null(Null::default()))
+            .await;
+        if let Ok(Err(e)) = status_rx.try_recv() {
+            return Err(e);
+        }
+        Ok(res?.0)
+    }
+
     /// Compare the value(s) of the attribute `attr` within an entry named by `dn` with the
     /// value `val`. If any of the values is identical to the provided one, return result code 5
     /// (`compareTrue`), otherwise return result code 6 (`compareFalse`). If access control
     /// rules on the server disallow comparison, another result code will be used to indicate
     /// an error.
-    pub async fn compare<B: AsRef<[u8]>>(
+    pub async fn compare<B: ValueBytes>(
         &mut self,
         dn: &str,
         attr: &str,
         val: B,
     ) -> Result<CompareResult> {
+        if self.strict_validation {
+            validate_dn("dn", dn, false)?;
+        }
         let req = Tag::Sequence(Sequence {
             id: 14,
             class: TagClass::Application,
             inner: vec![
-                Tag::OctetString(OctetString {
-                    inner: Vec::from(dn.as_bytes()),
-                    ..Default::default()
-                }),
+                octet_str(dn),
                 Tag::Sequence(Sequence {
                     inner: vec![
-                        Tag::OctetString(OctetString {
-                            inner: Vec::from(attr.as_bytes()),
-                            ..Default::default()
-                        }),
-                        Tag::OctetString(OctetString {
-                            inner: Vec::from(val.as_ref()),
-                            ..Default::default()
-                        }),
+                        octet_str(attr),
+                        OctetString::from_bytes(val.into_value_bytes()),
                     ],
                     ..Default::default()
                 }),
@@ -632,6 +1962,9 @@ impl Ldap {
 
     /// Delete an entry named by `dn`.
     pub async fn delete(&mut self, dn: &str) -> Result<LdapResult> {
+        if self.strict_validation {
+            validate_dn("dn", dn, true)?;
+        }
         let req = Tag::OctetString(OctetString {
             id: 10,
             class: TagClass::Application,
@@ -640,22 +1973,175 @@ impl Ldap {
         Ok(self.op_call(LdapOp::Single, req).await?.0)
     }
 
+    /// Delete the entry named by `dn` together with all its subordinates, using the
+    /// default [`SubtreeDeleteOptions`](struct.SubtreeDeleteOptions.html).
+    ///
+    /// See [`delete_subtree_with()`](#method.delete_subtree_with).
+    pub async fn delete_subtree(&mut self, dn: &str) -> Result<SubtreeDeleteStats> {
+        self.delete_subtree_with(dn, &SubtreeDeleteOptions::new())
+            .await
+    }
+
+    /// Delete the entry named by `dn` together with all its subordinates.
+    ///
+    /// Unless disabled in `opts`, the subtree is first deleted with a single Delete operation
+    /// carrying the critical [`TreeDelete`](controls/struct.TreeDelete.html) control. If the
+    /// server rejects the control with unavailableCriticalExtension (12) or unwillingToPerform
+    /// (53), the subtree is enumerated with OneLevel Searches, ignoring referrals, and its
+    /// entries are deleted one by one, subordinates first. An entry which can't be deleted
+    /// is recorded in the returned [`SubtreeDeleteStats`](struct.SubtreeDeleteStats.html),
+    /// and its superiors are skipped, but the deletion of the other entries proceeds.
+    ///
+    /// The whole subtree is enumerated before any entry is deleted. If it exceeds the depth
+    /// or entry limits set in `opts`, the method returns `LdapError::SubtreeLimit` without
+    /// deleting anything. Any other failure of the initial Delete or of a Search is returned
+    /// as an error. Controls and the timeout set on the handle apply to each operation.
+    ///
+    /// The TreeDelete attempt is skipped if the [server capabilities](#method.capabilities)
+    /// list the supported controls without TreeDelete. A failure to read the capabilities
+    /// is ignored. Calling [`without_probe()`](#method.without_probe) beforehand skips
+    /// the check.
+    pub async fn delete_subtree_with(
+        &mut self,
+        dn: &str,
+        opts: &SubtreeDeleteOptions,
+    ) -> Result<SubtreeDeleteStats> {
+        let mut tree_delete = opts.tree_delete;
+        if tree_delete && !std::mem::take(&mut self.no_probe) {
+            if let Ok(caps) = self.capabilities().await {
+                tree_delete = !caps.lacks_control(TREE_DELETE_OID);
+            }
+        }
+        let controls = self.controls.take();
+        let timeout = self.timeout.take();
+        let mut stats = SubtreeDeleteStats::default();
+        if tree_delete {
+            let mut td_controls = controls.clone().unwrap_or_default();
+            td_controls.push(RawControl::from(TreeDelete.critical()));
+            rearm(self, &Some(td_controls), timeout);
+            let res = self.delete(dn).await?;
+            match res.rc {
+                0 => {
+                    stats.tree_delete = true;
+                    stats.deleted = 1;
+                    return Ok(stats);
+                }
+                12 | 53 => (),
+                _ => return Err(LdapError::from(res)),
+            }
+        }
+        let entries = enumerate(self, dn, opts, &controls, timeout).await?;
+        let mut blocked = vec![false; entries.len()];
+        for (index, (dn, sup)) in entries.iter().enumerate().rev() {
+            if blocked[index] {
+                stats.skipped += 1;
+            } else {
+                rearm(self, &controls, timeout);
+                let res = self.delete(dn).await?;
+                if res.rc == 0 {
+                    stats.deleted += 1;
+                    continue;
+                }
+                stats.failed.push((dn.clone(), res));
+            }
+            if let Some(sup) = sup {
+                blocked[*sup] = true;
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Check whether the entry named by `user_dn` is a member of the group named by
+    /// `group_dn`, with nesting resolved according to `nested`, using the default
+    /// [`GroupSchema`](struct.GroupSchema.html).
+    ///
+    /// See [`is_member_with()`](#method.is_member_with).
+    pub async fn is_member(
+        &mut self,
+        group_dn: &str,
+        user_dn: &str,
+        nested: NestedMode,
+    ) -> Result<bool> {
+        self.is_member_with(group_dn, user_dn, nested, &GroupSchema::default())
+            .await
+    }
+
+    /// Check whether the entry named by `user_dn` is a member of the group named by
+    /// `group_dn`, with group membership described by `schema`.
+    ///
+    /// With `NestedMode::MatchingRuleInChain`, the user entry is searched for with the
+    /// extensible match of the group DN on the attribute which lists the user's groups,
+    /// `memberOf` by default. Otherwise, the member attributes of the group are read,
+    /// and for `NestedMode::Recursive`, so are those of its member entries, level by
+    /// level, until the user is found. Membership values are compared case-insensitively.
+    /// A group or a member entry which doesn't exist is treated as having no members.
+    /// Controls and the timeout set on the handle apply to each operation.
+    pub async fn is_member_with(
+        &mut self,
+        group_dn: &str,
+        user_dn: &str,
+        nested: NestedMode,
+        schema: &GroupSchema,
+    ) -> Result<bool> {
+        let controls = self.controls.take();
+        let timeout = self.timeout.take();
+        groups::is_member(self, group_dn, user_dn, nested, schema, &controls, timeout).await
+    }
+
+    /// Return the DNs of the groups which the entry named by `user_dn` is a member of,
+    /// with nesting resolved according to `nested`, using the default
+    /// [`GroupSchema`](struct.GroupSchema.html).
+    ///
+    /// See [`member_of_with()`](#method.member_of_with).
+    pub async fn member_of(&mut self, user_dn: &str, nested: NestedMode) -> Result<Vec<String>> {
+        self.member_of_with(user_dn, nested, &GroupSchema::default())
+            .await
+    }
+
+    /// Return the DNs of the groups which the entry named by `user_dn` is a member of,
+    /// with group membership described by `schema`.
+    ///
+    /// The direct groups are read from the user entry's `memberOf`, unless the schema
+    /// has a group base, in which case they are searched for under it. With
+    /// `NestedMode::Recursive`, the groups of each group are found in the same way,
+    /// breadth-first, and the returned list is in that order, without duplicates. With
+    /// `NestedMode::MatchingRuleInChain`, a single Search is made with the extensible
+    /// match of the user DN on the first member attribute, under the group base or,
+    /// without one, the `defaultNamingContext` or the first naming context of the server.
+    /// Controls and the timeout set on the handle apply to each operation.
+    pub async fn member_of_with(
+        &mut self,
+        user_dn: &str,
+        nested: NestedMode,
+        schema: &GroupSchema,
+    ) -> Result<Vec<String>> {
+        let controls = self.controls.take();
+        let timeout = self.timeout.take();
+        groups::member_of(self, user_dn, nested, schema, &controls, timeout).await
+    }
+
     /// Modify an entry named by `dn` by sequentially applying the modifications given by `mods`.
     /// See the [`Mod`](enum.Mod.html) documentation for the description of possible values.
-    pub async fn modify<S: AsRef<[u8]> + Eq + Hash>(
+    pub async fn modify<S: ValueBytes + Eq + Hash>(
         &mut self,
         dn: &str,
         mods: Vec<Mod<S>>,
     ) -> Result<LdapResult> {
+        if self.strict_validation {
+            validate_dn("dn", dn, false)?;
+            for m in &mods {
+                if let Mod::Increment(_, val) = m {
+                    validate_increment(val.as_ref())?;
+                }
+            }
+        }
+        let canonical = self.canonical_sets;
         let mut any_add_empty = false;
         let req = Tag::Sequence(Sequence {
             id: 6,
             class: TagClass::Application,
             inner: vec![
-                Tag::OctetString(OctetString {
-                    inner: Vec::from(dn.as_bytes()),
-                    ..Default::default()
-                }),
+                octet_str(dn),
                 Tag::Sequence(Sequence {
                     inner: mods
                         .into_iter()
@@ -679,22 +2165,15 @@ impl Ldap {
                             });
                             let part_attr = Tag::Sequence(Sequence {
                                 inner: vec![
-                                    Tag::OctetString(OctetString {
-                                        inner: Vec::from(attr.as_ref()),
-                                        ..Default::default()
-                                    }),
-                                    Tag::Set(Set {
-                                        inner: set
-                                            .into_iter()
+                                    OctetString::from_bytes(attr.into_value_bytes()),
+                                    value_set(
+                                        set.into_iter()
                                             .map(|val| {
-                                                Tag::OctetString(OctetString {
-                                                    inner: Vec::from(val.as_ref()),
-                                                    ..Default::default()
-                                                })
+                                                OctetString::from_bytes(val.into_value_bytes())
                                             })
                                             .collect(),
-                                        ..Default::default()
-                                    }),
+                                        canonical,
+                                    ),
                                 ],
                                 ..Default::default()
                             });
@@ -714,6 +2193,110 @@ impl Ldap {
         Ok(self.op_call(LdapOp::Single, req).await?.0)
     }
 
+    /// Modify the entry named by `dn` only if it matches `assertion_filter`, e.g.
+    /// `(version=5)`, which makes it possible to implement optimistic concurrency.
+    ///
+    /// The filter is sent in a critical [`Assertion`](controls/struct.Assertion.html)
+    /// control, added to any controls set on the handle; the server evaluates it against
+    /// the entry and performs the Modify only if it's true. An assertion which doesn't
+    /// hold is reported as [`ModifyIfOutcome::PreconditionFailed`](enum.ModifyIfOutcome.html),
+    /// while any other unsuccessful result is returned as an error. An invalid filter
+    /// fails with `LdapError::FilterParsing` before anything is sent.
+    pub async fn modify_if<S: ValueBytes + Eq + Hash>(
+        &mut self,
+        dn: &str,
+        assertion_filter: &str,
+        mods: Vec<Mod<S>>,
+    ) -> Result<ModifyIfOutcome> {
+        self.push_assertion(assertion_filter)?;
+        conditional_outcome(self.modify(dn, mods).await?)
+    }
+
+    /// Delete the entry named by `dn` only if it matches `assertion_filter`. See
+    /// [`modify_if()`](#method.modify_if) for the details.
+    pub async fn delete_if(&mut self, dn: &str, assertion_filter: &str) -> Result<ModifyIfOutcome> {
+        self.push_assertion(assertion_filter)?;
+        conditional_outcome(self.delete(dn).await?)
+    }
+
+    fn push_assertion(&mut self, filter: &str) -> Result<()> {
+        let mut assertion = Assertion::try_new(filter)?;
+        assertion.crit = true;
+        self.controls.get_or_insert_with(Vec::new).push(assertion);
+        Ok(())
+    }
+
+    /// Make sure that the entry named by `dn` exists and has the attribute values given
+    /// in `attrs`.
+    ///
+    /// The entry is first read with a base-scoped Search. If the Search fails with
+    /// noSuchObject (32), the entry is added. Otherwise, the existing entry is compared with
+    /// the desired attributes using [`diff_entries()`](diff/fn.diff_entries.html) with minimal
+    /// changes, and the differences, if any, are applied with a Modify operation. Any other
+    /// failure of an operation is returned as an error. The entry must be within the reach of
+    /// a Search, so that it doesn't appear to be missing because of access control.
+    ///
+    /// An existing entry whose DN differs from `dn` only in case is modified, not renamed,
+    /// and the values of its naming attributes are compared case-insensitively with the
+    /// desired ones. Controls and the timeout set on the handle apply to the final Add or
+    /// Modify operation.
+    ///
+    /// See [`EnsureOptions`](diff/struct.EnsureOptions.html) for the treatment of the
+    /// existing attributes which aren't listed, and of object classes.
+    pub async fn ensure_entry(
+        &mut self,
+        dn: &str,
+        attrs: Vec<(String, HashSet<String>)>,
+        opts: &EnsureOptions,
+    ) -> Result<EnsureOutcome> {
+        let mut controls = self.controls.take();
+        let timeout = self.timeout.take();
+        let mut wanted: Vec<&str> = attrs.iter().map(|(attr, _)| attr.as_str()).collect();
+        if opts.delete_extra || wanted.is_empty() {
+            wanted = vec!["*"];
+        }
+        let res = self
+            .search(dn, Scope::Base, "(objectClass=*)", wanted)
+            .await?;
+        let current = match res.1.rc {
+            0 => res.0.into_iter().next().map(SearchEntry::construct),
+            32 => None,
+            _ => return Err(LdapError::from(res.1)),
+        };
+        if let Some(timeout) = timeout {
+            self.with_timeout(timeout);
+        }
+        let current = match current {
+            Some(current) => current,
+            None => {
+                self.controls = controls;
+                self.add(dn, attrs).await?.success()?;
+                return Ok(EnsureOutcome::Created);
+            }
+        };
+        let desired = SearchEntry {
+            dn: dn.to_owned(),
+            attrs: attrs
+                .into_iter()
+                .map(|(attr, vals)| (attr, vals.into_iter().collect()))
+                .collect(),
+            bin_attrs: HashMap::new(),
+            truncated: vec![],
+        };
+        let mods = ensure_mods(&current, desired, opts);
+        if mods.is_empty() {
+            return Ok(EnsureOutcome::Unchanged);
+        }
+        if opts.relax_rules {
+            controls
+                .get_or_insert_with(Vec::new)
+                .push(RawControl::from(RelaxRules.critical()));
+        }
+        self.controls = controls;
+        self.modify(&current.dn, mods.clone()).await?.success()?;
+        Ok(EnsureOutcome::Modified(mods))
+    }
+
     /// Rename and/or move an entry named by `dn`. The new name is given by `rdn`. If
     /// `delete_old` is `true`, delete the previous value of the naming attribute from
     /// the entry. If the entry is to be moved elsewhere in the DIT, `new_sup` gives
@@ -725,26 +2308,27 @@ impl Ldap {
         delete_old: bool,
         new_sup: Option<&str>,
     ) -> Result<LdapResult> {
+        if self.strict_validation {
+            validate_dn("dn", dn, true)?;
+            validate_rdn(rdn)?;
+            if let Some(new_sup) = new_sup {
+                validate_dn("new_sup", new_sup, false)?;
+            }
+        }
         let mut params = vec![
-            Tag::OctetString(OctetString {
-                inner: Vec::from(dn.as_bytes()),
-                ..Default::default()
-            }),
-            Tag::OctetString(OctetString {
-                inner: Vec::from(rdn.as_bytes()),
-                ..Default::default()
-            }),
+            octet_str(dn),
+            octet_str(rdn),
             Tag::Boolean(Boolean {
                 inner: delete_old,
                 ..Default::default()
             }),
         ];
         if let Some(new_sup) = new_sup {
-            params.push(Tag::OctetString(OctetString {
-                id: 0,
-                class: TagClass::Context,
-                inner: Vec::from(new_sup.as_bytes()),
-            }));
+            params.push(OctetString::from_tagged_bytes(
+                TagClass::Context,
+                0,
+                Bytes::copy_from_slice(new_sup.as_bytes()),
+            ));
         }
         let req = Tag::Sequence(Sequence {
             id: 12,
@@ -754,6 +2338,109 @@ impl Ldap {
         Ok(self.op_call(LdapOp::Single, req).await?.0)
     }
 
+    /// Change the password of the user named by `user_dn` to `new`, using the method
+    /// given by `flavor`.
+    ///
+    /// If `old` is `Some`, it must be the current password, and the change is performed
+    /// as the user changing their own password. Otherwise, the password is reset, which
+    /// normally requires administrative privileges.
+    ///
+    /// * With [`PasswordFlavor::Exop`](enum.PasswordFlavor.html#variant.Exop), the
+    ///   [`PasswordModify`](exop/struct.PasswordModify.html) extended operation is used.
+    ///
+    /// * With [`PasswordFlavor::ActiveDirectory`](enum.PasswordFlavor.html#variant.ActiveDirectory),
+    ///   `unicodePwd` is modified: the old value is deleted and the new one added for a change,
+    ///   or the value is replaced for a reset. The values are encoded with
+    ///   [`ad::unicode_pwd()`](ad/fn.unicode_pwd.html). Since AD only accepts the modification
+    ///   over an encrypted connection, the method returns `LdapError::TlsRequired` if the
+    ///   connection doesn't use TLS or a SASL security layer.
+    ///
+    /// * With [`PasswordFlavor::Auto`](enum.PasswordFlavor.html#variant.Auto), the method is
+    ///   chosen by the [server capabilities](#method.capabilities), which are read first if
+    ///   they aren't cached: the extended operation if it's supported, otherwise the AD method
+    ///   if the server is Active Directory. If neither applies, the method returns
+    ///   `LdapError::PasswordChangeUnsupported`. An explicit flavor skips the capability check.
+    ///
+    /// Controls and the timeout set on the handle apply to the operation which changes
+    /// the password. The result of that operation is returned.
+    pub async fn change_password(
+        &mut self,
+        user_dn: &str,
+        old: Option<&str>,
+        new: &str,
+        flavor: PasswordFlavor,
+    ) -> Result<LdapResult> {
+        let flavor = match flavor {
+            PasswordFlavor::Auto => {
+                let caps = self.capabilities().await?;
+                PasswordFlavor::detect(&caps, PASSMOD_OID)
+                    .ok_or(LdapError::PasswordChangeUnsupported)?
+            }
+            flavor => flavor,
+        };
+        if flavor == PasswordFlavor::Exop {
+            let pm = PasswordModify {
+                user_id: Some(user_dn),
+                old_pass: old,
+                new_pass: Some(new),
+            };
+            return Ok(self.extended(pm).await?.1);
+        }
+        #[cfg(feature = "gssapi")]
+        let sasl_wrap = self.sasl_param.read().expect("sasl param").0;
+        #[cfg(not(feature = "gssapi"))]
+        let sasl_wrap = false;
+        if !self.has_tls && !sasl_wrap {
+            return Err(LdapError::TlsRequired);
+        }
+        self.modify(user_dn, ad_password_mods(old, new)).await
+    }
+
+    /// Read the password and lockout state of the account named by `user_dn`.
+    ///
+    /// The entry is read with a base-scoped Search for all operational attributes and the
+    /// AD account attributes, and its attributes are interpreted by
+    /// [`PasswordStatus::from_entry()`](struct.PasswordStatus.html#method.from_entry). If the
+    /// entry names an OpenLDAP password policy with `pwdPolicySubentry`, and the policy entry
+    /// can be read, the expiration time is computed from its `pwdMaxAge`. Controls and the
+    /// timeout set on the handle apply to the Search for the user entry.
+    pub async fn password_status(&mut self, user_dn: &str) -> Result<PasswordStatus> {
+        let (entries, _res) = self
+            .search(user_dn, Scope::Base, "(objectClass=*)", STATUS_ATTRS)
+            .await?
+            .success()?;
+        let entry = match entries.into_iter().next() {
+            Some(entry) => SearchEntry::construct(entry),
+            None => return Err(LdapError::EntryNotFound(user_dn.to_owned())),
+        };
+        let policy = lookup(&entry.attrs, "pwdPolicySubentry")
+            .and_then(|vals| vals.first())
+            .cloned();
+        let mut status = PasswordStatus::from_entry(entry);
+        if let Some(policy) = policy {
+            let res = self
+                .search(&policy, Scope::Base, "(objectClass=*)", vec!["pwdMaxAge"])
+                .await?;
+            let max_age = match res.1.rc {
+                0 => res
+                    .0
+                    .into_iter()
+                    .next()
+                    .map(SearchEntry::construct)
+                    .and_then(|entry| {
+                        lookup(&entry.attrs, "pwdMaxAge")
+                            .and_then(|vals| vals.first())
+                            .and_then(|val| val.parse().ok())
+                    }),
+                _ => None,
+            };
+            if let Some(max_age) = max_age {
+                status.apply_max_age(max_age);
+            }
+        }
+        Ok(status)
+    }
+
     /// Perform an Extended operation given by `exop`. Extended operations are defined in the
     /// [`exop`](exop/index.html) module. See the module-level documentation for the list of extended
     /// operations supported by this library and procedures for defining custom exops.
@@ -771,7 +2458,96 @@ impl Ldap {
             .map(|et| ExopResult(et.1, et.0))
     }
 
+    /// Send a caller-constructed protocol operation, and return the components of its
+    /// result: the LDAPResult, the Extended operation name and value, if the response
+    /// carries them, and the response controls. The controls are moved out of the
+    /// `LdapResult`, whose `ctrls` will be empty.
+    ///
+    /// This is an escape hatch for operations which the library doesn't implement, such as
+    /// vendor-specific extended operations with a nonstandard envelope. The `req` is the
+    /// protocolOp element of the LDAPMessage, and must be in the Application class; tag
+    /// types are available in the [`asn1`](asn1/index.html) module. The message ID is
+    /// assigned by the connection and must not be part of `req`, and the response is
+    /// matched to the request by that ID. Controls, the timeout, and the other modifiers
+    /// set on the handle apply as with any other operation.
+    ///
+    /// The operation must elicit a single response message from the server. Abandon and
+    /// Unbind, which have no response, and Search, which has several, are rejected with
+    /// `LdapError::InvalidRequest`, as is a `req` outside the Application class; for
+    /// Search-like operations, use [`raw_search_op()`](#method.raw_search_op).
+    pub async fn raw_op(&mut self, req: Tag) -> Result<(LdapResult, Exop, Vec<Control>)> {
+        match application_id(&req) {
+            Some(2) | Some(16) => return Err(raw_op_error("operation without a response")),
+            Some(3) => return Err(raw_op_error("Search must use raw_search_op()")),
+            Some(_) => (),
+            None => return Err(raw_op_error("not in the Application class")),
+        }
+        let (mut result, exop, _) = self.op_call(LdapOp::Single, req).await?;
+        let ctrls = std::mem::take(&mut result.ctrls);
+        Ok((result, exop, ctrls))
+    }
+
+    /// Send a caller-constructed protocol operation whose responses have the form of
+    /// Search responses, and return the stream of its results. See
+    /// [`raw_op()`](#method.raw_op) for the handling of `req`.
+    ///
+    /// The server must answer the operation with any number of SearchResultEntry,
+    /// SearchResultReference, or IntermediateResponse messages, followed by a
+    /// SearchResultDone. Any other response fails the stream with
+    /// `LdapError::UnexpectedPdu`. Of the [search options](struct.SearchOptions.html) set
+    /// on the handle, only the client-side limits apply, and adapters can't be used.
+    pub async fn raw_search_op(
+        &mut self,
+        req: Tag,
+    ) -> Result<SearchStream<'static, String, Vec<String>>> {
+        match application_id(&req) {
+            Some(2) | Some(16) => return Err(raw_op_error("operation without a response")),
+            Some(_) => (),
+            None => return Err(raw_op_error("not in the Application class")),
+        }
+        let mut ldap = self.clone();
+        ldap.controls = self.controls.take();
+        ldap.timeout = self.timeout.take();
+        ldap.deadline = self.deadline.take();
+        ldap.cancel = self.cancel.take();
+        ldap.search_opts = self.search_opts.take();
+        let mut stream = SearchStream::new(ldap, vec![]);
+        stream.start_raw(req).await?;
+        Ok(stream)
+    }
+
+    /// Create a [`Batch`](struct.Batch.html) of independent operations, which will be performed
+    /// concurrently over the connection of this handle.
+    ///
+    /// Controls and the timeout set on the handle are taken over by the batch, and become the
+    /// defaults for all operations queued in it. Each operation can override them.
+    pub fn batch(&mut self) -> Batch {
+        let controls = self.controls.take();
+        let timeout = self.timeout.take();
+        Batch::new(self.clone(), controls, timeout)
+    }
+
+    /// Start a transaction ([RFC 5805](https://tools.ietf.org/html/rfc5805)), with the
+    /// Start Transaction extended operation.
+    ///
+    /// The returned [`Transaction`](struct.Transaction.html) has its own clone of this
+    /// handle, and is used to queue updates and end the transaction. Controls and the
+    /// timeout set on this handle apply to the Start Transaction operation. A non-success
+    /// result is returned as an error, as is a response without a transaction identifier,
+    /// with `LdapError::MalformedTxnResponse`.
+    pub async fn begin_transaction(&mut self) -> Result<Transaction> {
+        let mut ldap = self.clone();
+        ldap.controls = self.controls.take();
+        ldap.timeout = self.timeout.take();
+        ldap.deadline = self.deadline.take();
+        ldap.cancel = self.cancel.take();
+        Transaction::begin(ldap).await
+    }
+
     /// Terminate the connection to the server.
+    ///
+    /// Controls set with [`with_controls()`](#method.with_controls) are sent with the
+    /// Unbind request.
     pub async fn unbind(&mut self) -> Result<()> {
         let req = Tag::Null(Null {
             id: 2,
@@ -781,6 +2557,23 @@ impl Ldap {
         self.op_call(LdapOp::Unbind, req).await.map(|_| ())
     }
 
+    /// Unbind, and wait for the connection to terminate.
+    ///
+    /// Unlike [`unbind()`](#method.unbind), this method consumes the handle and returns
+    /// only when the connection driver has finished, with
+    /// `LdapError::ConnectionTerminated` if it stopped because of an error. The driver
+    /// keeps running while other handles exist, until the server closes the connection
+    /// after the Unbind.
+    pub async fn close(mut self) -> Result<()> {
+        self.unbind().await?;
+        let notifier = self.notifier.clone();
+        drop(self);
+        notifier
+            .closed()
+            .await
+            .map_err(LdapError::ConnectionTerminated)
+    }
+
     /// Return the message ID of the last active operation. When the handle is initialized, this
     /// value is set to zero. The intended use is to obtain the ID of a timed out operation for
     /// passing it to an Abandon or Cancel operation.
@@ -792,6 +2585,9 @@ impl Ldap {
     }
 
     /// Ask the server to abandon an operation identified by `msgid`.
+    ///
+    /// Controls set with [`with_controls()`](#method.with_controls) are sent with the
+    /// Abandon request.
     pub async fn abandon(&mut self, msgid: RequestId) -> Result<()> {
         let req = Tag::Integer(Integer {
             id: 16,
@@ -811,6 +2607,31 @@ impl Ldap {
         self.tx.is_closed()
     }
 
+    /// Check whether the connection is usable, by reading the root DSE without any
+    /// attributes within the given time. This is suitable as a readiness probe.
+    ///
+    /// The probe is a single Base Search of the root DSE, requesting the attribute `1.1`;
+    /// it's not retried, even if the connection has a retry policy. Its result is classified
+    /// as described for [`HealthStatus`](enum.HealthStatus.html). Controls and other
+    /// modifiers set on the handle for the next operation aren't used by the probe, and
+    /// remain set after it.
+    pub async fn check_health(&mut self, timeout: Duration) -> HealthStatus {
+        let pending = Pending::save(self);
+        let retries = std::mem::take(&mut self.retries);
+        rearm(self, &None, Some(timeout));
+        self.deadline = None;
+        self.cancel = None;
+        self.search_opts = None;
+        let start = Instant::now();
+        let res = self
+            .search_attempt("", Scope::Base, "(objectClass=*)", vec!["1.1"])
+            .await;
+        let rtt = start.elapsed();
+        pending.restore(self);
+        self.retries = retries;
+        HealthStatus::classify(res.map(|res| res.1), rtt)
+    }
+
     /// Return the TLS peer certificate in DER format.
     ///
     /// The method returns Ok(None) if no certificate was found or
@@ -827,4 +2648,316 @@ impl Ldap {
             Ok(None)
         }
     }
+
+    /// Return a handle to the statistics of the connection, which are shared by all
+    /// clones of this handle. See [`ConnStats`](struct.ConnStats.html).
+    pub fn conn_stats(&self) -> ConnStats {
+        self.stats.clone()
+    }
+
+    /// Return the TLS parameters of the connection.
+    ///
+    /// The parameters, which include the peer certificate in DER format, are captured
+    /// immediately after the TLS handshake. The method returns `None` if the connection
+    /// doesn't use TLS.
+    pub fn tls_info(&self) -> Option<TlsInfo> {
+        self.tls_info.as_ref().clone()
+    }
+
+    /// Return the __tls-server-end-point__ channel binding data
+    /// ([RFC 5929](https://tools.ietf.org/html/rfc5929#section-4)) of the connection,
+    /// which is the hash of the server certificate. The hash function is the one used
+    /// in the signature of the certificate, except that MD5 and SHA-1 are replaced by
+    /// SHA-256.
+    ///
+    /// The method returns `None` if the connection doesn't use TLS, or if the channel
+    /// binding is undefined for the certificate, since its signature algorithm doesn't
+    /// use a single hash function.
+    pub fn channel_binding_token(&self) -> Option<Vec<u8>> {
+        #[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
+        {
+            self.tls_info
+                .as_ref()
+                .as_ref()
+                .and_then(|info| tls_server_end_point(&info.peer_der))
+        }
+        #[cfg(not(any(feature = "tls-native", feature = "tls-rustls")))]
+        {
+            None
+        }
+    }
+
+    /// Subscribe to the Unsolicited Notifications sent by the server.
+    ///
+    /// The receiver gets every notification which arrives after the call. When the
+    /// connection terminates, the channel is closed. A Notice of Disconnection is
+    /// always the last notification, since the connection is closed after receiving it.
+    pub fn unsolicited_notifications(&self) -> broadcast::Receiver<UnsolicitedNotification> {
+        self.notifier.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conn::{LdapConnAsync, LdapConnSettings};
+    use crate::fixtures::{self, message, result};
+
+    #[test]
+    fn channel_bindings_structure() {
+        let buf = sec_channel_bindings(b"tls-server-end-point:\x01\x02");
+        assert_eq!(buf.len(), 32 + 23);
+        assert!(buf[..24].iter().all(|b| *b == 0));
+        assert_eq!(buf[24..32], [23, 0, 0, 0, 32, 0, 0, 0]);
+        assert_eq!(&buf[32..], b"tls-server-end-point:\x01\x02");
+    }
+
+    #[test]
+    fn msgid_stress() {
+        // Start close enough to the end of the range to wrap around.
+        let start = RequestId::MAX - 5_000_000;
+        let mut msgmap = (start, HashSet::new());
+        let mut in_flight = std::collections::VecDeque::new();
+        let mut lost = vec![];
+        for n in 0..10_000_000 {
+            let id = alloc_msgid(&mut msgmap, RequestId::MAX).expect("id");
+            assert!(id > 0);
+            // Every thousandth response never arrives, and the ID is only released
+            // by reconciliation.
+            if n % 1000 == 0 {
+                lost.push(id);
+            } else {
+                in_flight.push_back(id);
+            }
+            if in_flight.len() > 100 {
+                let done = in_flight.pop_front().expect("in flight");
+                assert!(msgmap.1.remove(&done));
+            }
+            if msgmap.1.len().is_multiple_of(RECONCILE_INTERVAL) {
+                for id in lost.drain(..) {
+                    assert!(msgmap.1.remove(&id));
+                }
+            }
+        }
+        assert!(msgmap.0 < start);
+        assert!(msgmap.1.len() <= 100 + RECONCILE_INTERVAL);
+    }
+
+    #[test]
+    fn msgid_exhaustion() {
+        let max_id = 1000;
+        let mut msgmap = (500, (1..=max_id).collect::<HashSet<_>>());
+        assert!(matches!(
+            alloc_msgid(&mut msgmap, max_id),
+            Err(LdapError::MsgIdExhausted)
+        ));
+        msgmap.1.remove(&17);
+        assert_eq!(alloc_msgid(&mut msgmap, max_id).expect("id"), 17);
+        msgmap.1.remove(&max_id);
+        assert_eq!(alloc_msgid(&mut msgmap, max_id).expect("id"), max_id);
+        assert!(matches!(
+            alloc_msgid(&mut msgmap, max_id),
+            Err(LdapError::MsgIdExhausted)
+        ));
+    }
+
+    #[test]
+    fn gssapi_security_layers() {
+        // Over TLS, no layer is used, and the maximum size in the reply is zero.
+        assert_eq!(
+            gssapi_security_layer(&[0x07, 0x00, 0xa0, 0x00], true),
+            Ok((GSSAUTH_P_NONE, 0xa000, [0x01, 0x00, 0x00, 0x00]))
+        );
+        assert_eq!(
+            gssapi_security_layer(&[0x06, 0x10, 0x00, 0x00], false),
+            Ok((GSSAUTH_P_PRIVACY, 0x100000, [0x04, 0x9f, 0xff, 0xb8]))
+        );
+        assert!(gssapi_security_layer(&[0x01, 0x00, 0x00, 0x00], false).is_err());
+        assert!(gssapi_security_layer(&[0x06, 0x00, 0x00, 0x00], true).is_err());
+        assert!(gssapi_security_layer(&[0x07, 0x00], true).is_err());
+    }
+
+    #[test]
+    fn dns() {
+        for dn in [
+            "cn=test,dc=example,dc=org",
+            "cn=a+sn=b, ou=x",
+            "cn=a\\,b,dc=org",
+            "cn=\\23\\c3\\a9",
+            "2.5.4.3=x",
+            "cn=",
+            "<GUID=5e0f2b3c>",
+        ] {
+            assert!(validate_dn("dn", dn, true).is_ok(), "{}", dn);
+        }
+        for dn in [
+            "",
+            "cn=a\0",
+            "cn",
+            "cn=a,,dc=org",
+            "c n=a",
+            "cn=a\\",
+            "cn=\\x",
+        ] {
+            assert!(
+                matches!(
+                    validate_dn("dn", dn, true),
+                    Err(LdapError::InvalidRequest { field: "dn", .. })
+                ),
+                "{:?}",
+                dn
+            );
+        }
+        assert!(validate_dn("base", "", false).is_ok());
+    }
+
+    #[test]
+    fn rdns() {
+        assert!(validate_rdn("cn=a\\,b").is_ok());
+        assert!(validate_rdn("cn=a+uid=b").is_ok());
+        assert!(validate_rdn("").is_err());
+        assert!(validate_rdn("cn=a,dc=org").is_err());
+        assert!(validate_rdn("=a").is_err());
+    }
+
+    #[test]
+    fn increments() {
+        assert!(validate_increment(b"42").is_ok());
+        assert!(validate_increment(b"-1").is_ok());
+        assert!(validate_increment(b"").is_err());
+        assert!(validate_increment(b"-").is_err());
+        assert!(validate_increment(b"1.5").is_err());
+    }
+
+    #[test]
+    fn attr_values() {
+        let text = AttrValue::from("abc");
+        let bin = AttrValue::from(b"abc".to_vec());
+        assert_eq!(text, bin);
+        assert_eq!(HashSet::from([text, bin]).len(), 1);
+        assert_eq!(AttrValue::ad_password("pw").as_ref(), b"\"\0p\0w\0\"\0");
+    }
+
+    #[test]
+    fn owned_values_not_copied() {
+        let photo = vec![0xffu8; 4096];
+        let ptr = photo.as_ptr();
+        assert_eq!(photo.into_value_bytes().as_ptr(), ptr);
+        let value = AttrValue::from(String::from("abc"));
+        let ptr = value.as_ref().as_ptr();
+        assert_eq!(value.into_value_bytes().as_ptr(), ptr);
+        let borrowed = "abc";
+        assert_ne!(borrowed.into_value_bytes().as_ptr(), borrowed.as_ptr());
+    }
+
+    // Scripted server answering every request which has a response with success.
+    async fn server() -> String {
+        fixtures::serve(|req| match req.op.id {
+            3 => message(req.msgid, result(5, 0)),
+            id @ (6 | 8 | 10 | 12) => message(req.msgid, result(id + 1, 0)),
+            _ => vec![],
+        })
+        .await
+    }
+
+    async fn connect(settings: LdapConnSettings) -> Ldap {
+        let url = server().await;
+        let (conn, ldap) = LdapConnAsync::with_settings(settings, &url)
+            .await
+            .expect("connection");
+        crate::drive!(conn);
+        ldap
+    }
+
+    fn rejected<T: fmt::Debug>(res: Result<T>, field: &str) {
+        match res {
+            Err(LdapError::InvalidRequest { field: f, .. }) if f == field => (),
+            res => panic!("expected invalid {}, got {:?}", field, res),
+        }
+    }
+
+    #[tokio::test]
+    async fn rejected_locally() {
+        let mut ldap = connect(LdapConnSettings::new()).await;
+        rejected(ldap.add("", vec![("cn", HashSet::from(["x"]))]).await, "dn");
+        rejected(
+            ldap.add_streaming("cn=a\0", vec![("cn", ValueSource::InMemory(b"x".to_vec()))])
+                .await,
+            "dn",
+        );
+        rejected(ldap.delete("").await, "dn");
+        rejected(ldap.compare("cn", "cn", "x").await, "dn");
+        rejected(
+            ldap.modify("cn=a,dc=org", vec![Mod::Increment("uidNumber", "one")])
+                .await,
+            "mods",
+        );
+        rejected(ldap.modifydn("cn=a,dc=org", "", true, None).await, "rdn");
+        rejected(
+            ldap.modifydn("cn=a,dc=org", "cn=b", true, Some("dc=org,"))
+                .await,
+            "new_sup",
+        );
+        rejected(
+            ldap.search("cn=a\\", Scope::Base, "(objectClass=*)", vec!["1.1"])
+                .await,
+            "base",
+        );
+        rejected(
+            ldap.search("", Scope::Base, "(cn=a\0)", vec!["1.1"]).await,
+            "filter",
+        );
+        assert_eq!(ldap.last_id(), 0);
+        let res = ldap.delete("cn=a,dc=org").await.expect("delete");
+        assert_eq!(res.rc, 0);
+        assert_ne!(ldap.last_id(), 0);
+    }
+
+    #[tokio::test]
+    async fn mixed_values() {
+        let mut ldap = connect(LdapConnSettings::new()).await;
+        let cert: &[u8] = b"\x30\x82\x01\x0a";
+        let attrs: Vec<(AttrValue, HashSet<AttrValue>)> = vec![
+            ("cn".into(), HashSet::from(["test".into()])),
+            (
+                "userCertificate;binary".into(),
+                HashSet::from([cert.into()]),
+            ),
+        ];
+        let res = ldap.add("cn=test,dc=org", attrs).await.expect("add");
+        assert_eq!(res.rc, 0);
+        let mods: Vec<ModOwned> = vec![
+            Mod::Replace(
+                "unicodePwd".into(),
+                HashSet::from([AttrValue::ad_password("secret")]),
+            ),
+            Mod::Add("description".into(), HashSet::from(["text".into()])),
+            Mod::Increment("uidNumber".into(), "1".into()),
+        ];
+        let res = ldap.modify("cn=test,dc=org", mods).await.expect("modify");
+        assert_eq!(res.rc, 0);
+    }
+
+    #[tokio::test]
+    async fn validation_disabled() {
+        let mut ldap = connect(LdapConnSettings::new().set_strict_validation(false)).await;
+        let res = ldap.delete("").await.expect("delete");
+        assert_eq!(res.rc, 0);
+        let res = ldap
+            .modify("cn=a,dc=org", vec![Mod::Increment("uidNumber", "one")])
+            .await
+            .expect("modify");
+        assert_eq!(res.rc, 0);
+        assert_ne!(ldap.last_id(), 0);
+    }
+
+    #[tokio::test]
+    async fn timeout_cleared_on_send_error() {
+        let url = server().await;
+        let (conn, mut ldap) = LdapConnAsync::new(&url).await.expect("connection");
+        drop(conn);
+        ldap.with_timeout(Duration::from_secs(1));
+        assert!(ldap.delete("cn=a,dc=org").await.is_err());
+        assert!(ldap.timeout.is_none());
+    }
 }
@@ -0,0 +1,418 @@
+// Client configuration in the style of OpenLDAP's ldap.conf(5), from files and
+// LDAP<KEYWORD> environment variables. Only the keywords which map to connection
+// settings or to the defaults in DefaultsInfo are recognized.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::conn::LdapConnSettings;
+use crate::result::{LdapError, Result};
+
+/// Defaults from the client configuration which aren't connection settings.
+///
+/// Returned together with the settings by
+/// [`LdapConnSettings::from_env()`](struct.LdapConnSettings.html#method.from_env) and
+/// [`LdapConnSettings::from_ldaprc()`](struct.LdapConnSettings.html#method.from_ldaprc).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DefaultsInfo {
+    /// Server URIs (__URI__), separated by whitespace.
+    pub uri: Option<String>,
+    /// Default search base (__BASE__).
+    pub base: Option<String>,
+    /// Default operation timeout (__TIMEOUT__), meant for
+    /// [`Ldap::with_timeout()`](struct.Ldap.html#method.with_timeout).
+    pub timeout: Option<Duration>,
+    /// Default SASL mechanism (__SASL_MECH__).
+    pub sasl_mech: Option<String>,
+}
+
+impl DefaultsInfo {
+    /// Return the individual server URIs, in the order of preference.
+    pub fn uris(&self) -> Vec<&str> {
+        self.uri
+            .as_deref()
+            .map(|uri| uri.split_whitespace().collect())
+            .unwrap_or_default()
+    }
+}
+
+// Server certificate checking level (TLS_REQCERT). OpenLDAP's "allow" proceeds with
+// a bad certificate, so it's equivalent to "never" for the purpose of verification.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ReqCert {
+    Never,
+    Allow,
+    Try,
+    Demand,
+}
+
+// System-wide configuration files, of which the first one found is read.
+const SYSTEM_CONF: &[&str] = &["/etc/openldap/ldap.conf", "/etc/ldap/ldap.conf"];
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct LdapRc {
+    uri: Option<String>,
+    base: Option<String>,
+    network_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    tls_cacert: Option<PathBuf>,
+    tls_cacertdir: Option<PathBuf>,
+    tls_reqcert: Option<ReqCert>,
+    sasl_mech: Option<String>,
+}
+
+impl LdapRc {
+    // Read the configuration from the files and the environment, in the order used by
+    // OpenLDAP, with later sources overriding the earlier ones: the system file, ldaprc
+    // and .ldaprc in the home directory, ldaprc in the current directory, the file
+    // named by LDAPCONF, the files named by LDAPRC, and finally LDAP<KEYWORD> variables.
+    // If LDAPNOINIT is set, no configuration is read.
+    pub(crate) fn from_env() -> Result<Self> {
+        Self::from_sources(|name| env::var(name).ok(), SYSTEM_CONF)
+    }
+
+    fn from_sources<F: Fn(&str) -> Option<String>>(var: F, system_conf: &[&str]) -> Result<Self> {
+        let mut rc = LdapRc::default();
+        if var("LDAPNOINIT").is_some() {
+            return Ok(rc);
+        }
+        if let Some(path) = system_conf.iter().map(Path::new).find(|p| p.is_file()) {
+            rc.read_file(path)?;
+        }
+        let home = var("HOME").map(PathBuf::from);
+        let rc_files = |name: &str| {
+            let mut files = vec![];
+            if let Some(ref home) = home {
+                files.push(home.join(name));
+                files.push(home.join(format!(".{}", name)));
+            }
+            files.push(PathBuf::from(name));
+            files
+        };
+        for path in rc_files("ldaprc") {
+            rc.read_optional(&path)?;
+        }
+        if let Some(conf) = var("LDAPCONF") {
+            rc.read_optional(Path::new(&conf))?;
+        }
+        if let Some(name) = var("LDAPRC") {
+            for path in rc_files(&name) {
+                rc.read_optional(&path)?;
+            }
+        }
+        for keyword in KEYWORDS {
+            if let Some(value) = var(&format!("LDAP{}", keyword)) {
+                rc.set(keyword, value.trim(), "environment")?;
+            }
+        }
+        Ok(rc)
+    }
+
+    pub(crate) fn from_file(path: &Path) -> Result<Self> {
+        let mut rc = LdapRc::default();
+        rc.read_file(path)?;
+        Ok(rc)
+    }
+
+    fn read_optional(&mut self, path: &Path) -> Result<()> {
+        if path.is_file() {
+            self.read_file(path)?;
+        }
+        Ok(())
+    }
+
+    fn read_file(&mut self, path: &Path) -> Result<()> {
+        let text = fs::read_to_string(path)?;
+        self.parse(&text, &path.to_string_lossy())
+    }
+
+    // Parse the text of a configuration file. Each line holds a keyword and its value,
+    // separated by whitespace. Lines starting with '#' are comments, and a line starting
+    // with whitespace continues the previous one.
+    fn parse(&mut self, text: &str, origin: &str) -> Result<()> {
+        let mut lines: Vec<String> = vec![];
+        for line in text.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            match lines.last_mut() {
+                Some(last) if line.starts_with(char::is_whitespace) => {
+                    last.push(' ');
+                    last.push_str(trimmed);
+                }
+                _ => lines.push(String::from(trimmed)),
+            }
+        }
+        for line in lines {
+            let (keyword, value) = match line.split_once(char::is_whitespace) {
+                Some((keyword, value)) => (keyword.to_ascii_uppercase(), value.trim()),
+                None => {
+                    warn!("{}: ignoring keyword without a value: {}", origin, line);
+                    continue;
+                }
+            };
+            if KEYWORDS.contains(&keyword.as_str()) {
+                self.set(&keyword, value, origin)?;
+            } else {
+                warn!("{}: ignoring unknown keyword {}", origin, keyword);
+            }
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, keyword: &str, value: &str, origin: &str) -> Result<()> {
+        let invalid = || LdapError::InvalidConfigValue {
+            origin: String::from(origin),
+            keyword: String::from(keyword),
+            value: String::from(value),
+        };
+        let seconds = || {
+            value
+                .parse::<u64>()
+                .map(Duration::from_secs)
+                .map_err(|_| invalid())
+        };
+        match keyword {
+            "URI" => self.uri = Some(String::from(value)),
+            "BASE" => self.base = Some(String::from(value)),
+            "NETWORK_TIMEOUT" => self.network_timeout = Some(seconds()?),
+            "TIMEOUT" => self.timeout = Some(seconds()?),
+            "TLS_CACERT" => self.tls_cacert = Some(PathBuf::from(value)),
+            "TLS_CACERTDIR" => self.tls_cacertdir = Some(PathBuf::from(value)),
+            "TLS_REQCERT" => {
+                self.tls_reqcert = Some(match value.to_ascii_lowercase().as_str() {
+                    "never" => ReqCert::Never,
+                    "allow" => ReqCert::Allow,
+                    "try" => ReqCert::Try,
+                    "demand" | "hard" => ReqCert::Demand,
+                    _ => return Err(invalid()),
+                })
+            }
+            "SASL_MECH" => self.sasl_mech = Some(String::from(value)),
+            _ => unreachable!("keyword not in list"),
+        }
+        Ok(())
+    }
+
+    // Construct the connection settings and the defaults. CA certificate files are
+    // read at this point, so that a missing file is reported immediately.
+    pub(crate) fn into_settings(self) -> Result<(LdapConnSettings, DefaultsInfo)> {
+        let mut settings = LdapConnSettings::new();
+        if let Some(timeout) = self.network_timeout {
+            settings = settings.set_conn_timeout(timeout);
+        }
+        #[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
+        {
+            if let Some(ref path) = self.tls_cacert {
+                settings = settings.add_root_certificates_pem(&fs::read(path)?);
+            }
+            if let Some(ref dir) = self.tls_cacertdir {
+                for pem in read_cert_dir(dir)? {
+                    settings = settings.add_root_certificates_pem(&pem);
+                }
+            }
+            if let Some(reqcert) = self.tls_reqcert {
+                settings =
+                    settings.set_no_tls_verify(matches!(reqcert, ReqCert::Never | ReqCert::Allow));
+            }
+        }
+        #[cfg(not(any(feature = "tls-native", feature = "tls-rustls")))]
+        if self.tls_cacert.is_some() || self.tls_cacertdir.is_some() || self.tls_reqcert.is_some() {
+            warn!("TLS options in client configuration ignored without TLS support");
+        }
+        let defaults = DefaultsInfo {
+            uri: self.uri,
+            base: self.base,
+            timeout: self.timeout,
+            sasl_mech: self.sasl_mech,
+        };
+        Ok((settings, defaults))
+    }
+}
+
+const KEYWORDS: [&str; 8] = [
+    "URI",
+    "BASE",
+    "NETWORK_TIMEOUT",
+    "TIMEOUT",
+    "TLS_CACERT",
+    "TLS_CACERTDIR",
+    "TLS_REQCERT",
+    "SASL_MECH",
+];
+
+// Read the PEM files from a certificate directory. Files which don't contain a PEM
+// certificate, such as CRLs or READMEs, are skipped.
+#[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
+fn read_cert_dir(dir: &Path) -> Result<Vec<Vec<u8>>> {
+    let mut paths = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    paths.sort();
+    let mut pems = vec![];
+    for path in paths.into_iter().filter(|p| p.is_file()) {
+        let pem = fs::read(&path)?;
+        if pem
+            .windows(CERT_MARKER.len())
+            .any(|w| w == CERT_MARKER.as_bytes())
+        {
+            pems.push(pem);
+        }
+    }
+    Ok(pems)
+}
+
+#[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
+const CERT_MARKER: &str = "-----BEGIN CERTIFICATE-----";
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn parse(text: &str) -> Result<LdapRc> {
+        let mut rc = LdapRc::default();
+        rc.parse(text, "test")?;
+        Ok(rc)
+    }
+
+    #[test]
+    fn debian_default_conf() {
+        let rc = parse(
+            "#
+# LDAP Defaults
+#
+
+# See ldap.conf(5) for details
+# This file should be world readable but not world writable.
+
+#BASE\tdc=example,dc=com
+#URI\tldap://ldap.example.com ldap://ldap-provider.example.com:666
+
+#SIZELIMIT\t12
+#TIMELIMIT\t15
+#DEREF\t\tnever
+
+# TLS certificates (needed for GnuTLS)
+TLS_CACERT\t/etc/ssl/certs/ca-certificates.crt
+",
+        )
+        .expect("parse");
+        assert_eq!(
+            rc,
+            LdapRc {
+                tls_cacert: Some(PathBuf::from("/etc/ssl/certs/ca-certificates.crt")),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn full_conf() {
+        let rc = parse(
+            "BASE   dc=example,dc=org\r
+uri     ldaps://ldap1.example.org\r
+        ldaps://ldap2.example.org:636\r
+  # indented comment doesn't continue anything\r
+NETWORK_TIMEOUT 5
+TIMEOUT\t30
+TLS_REQCERT Demand
+TLS_CACERTDIR /etc/openldap/certs
+SASL_MECH GSSAPI
+SIZELIMIT 500
+DEREF never
+",
+        )
+        .expect("parse");
+        assert_eq!(
+            rc,
+            LdapRc {
+                uri: Some(String::from(
+                    "ldaps://ldap1.example.org ldaps://ldap2.example.org:636"
+                )),
+                base: Some(String::from("dc=example,dc=org")),
+                network_timeout: Some(Duration::from_secs(5)),
+                timeout: Some(Duration::from_secs(30)),
+                tls_cacert: None,
+                tls_cacertdir: Some(PathBuf::from("/etc/openldap/certs")),
+                tls_reqcert: Some(ReqCert::Demand),
+                sasl_mech: Some(String::from("GSSAPI")),
+            }
+        );
+        let defaults = DefaultsInfo {
+            uri: rc.uri,
+            ..Default::default()
+        };
+        assert_eq!(
+            defaults.uris(),
+            vec!["ldaps://ldap1.example.org", "ldaps://ldap2.example.org:636"]
+        );
+    }
+
+    #[test]
+    fn later_values_override() {
+        let rc =
+            parse("BASE dc=one\nTLS_REQCERT never\nBASE dc=two\nKEYWORDONLY\n").expect("parse");
+        assert_eq!(rc.base.as_deref(), Some("dc=two"));
+        assert_eq!(rc.tls_reqcert, Some(ReqCert::Never));
+    }
+
+    #[test]
+    fn invalid_values() {
+        for text in ["NETWORK_TIMEOUT 5s", "TIMEOUT -1", "TLS_REQCERT sometimes"] {
+            assert!(
+                matches!(parse(text), Err(LdapError::InvalidConfigValue { .. })),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn environment() {
+        let vars: HashMap<&str, &str> = [
+            ("HOME", "/nonexistent"),
+            ("LDAPURI", "ldap://env.example.org"),
+            ("LDAPBASE", " dc=example,dc=org "),
+            ("LDAPNETWORK_TIMEOUT", "10"),
+            ("LDAPTLS_REQCERT", "allow"),
+        ]
+        .into_iter()
+        .collect();
+        let rc = LdapRc::from_sources(|name| vars.get(name).map(|v| v.to_string()), &[])
+            .expect("environment");
+        assert_eq!(rc.uri.as_deref(), Some("ldap://env.example.org"));
+        assert_eq!(rc.base.as_deref(), Some("dc=example,dc=org"));
+        assert_eq!(rc.network_timeout, Some(Duration::from_secs(10)));
+        assert_eq!(rc.tls_reqcert, Some(ReqCert::Allow));
+        let rc = LdapRc::from_sources(
+            |name| match name {
+                "LDAPNOINIT" => Some(String::new()),
+                "LDAPURI" => Some(String::from("ldap://ignored")),
+                _ => None,
+            },
+            SYSTEM_CONF,
+        )
+        .expect("noinit");
+        assert_eq!(rc, LdapRc::default());
+    }
+
+    #[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
+    #[test]
+    fn cacert_files() {
+        let data = concat!(env!("CARGO_MANIFEST_DIR"), "/data/tls");
+        let rc = parse(&format!(
+            "TLS_CACERT {}/cert.pem\nTLS_CACERTDIR {}",
+            data, data
+        ))
+        .expect("parse");
+        let (settings, _) = rc.into_settings().expect("settings");
+        // cert.pem is read both as the file and from the directory; key.pem is skipped.
+        assert_eq!(settings.root_certs_pem.len(), 2);
+        let rc = parse("TLS_CACERT /nonexistent/ca.pem").expect("parse");
+        assert!(matches!(rc.into_settings(), Err(LdapError::Io { .. })));
+    }
+}
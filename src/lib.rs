@@ -40,12 +40,39 @@
 //!   For usage notes and caveats, see the documentation for
 //!   [`Ldap::sasl_gssapi_bind()`](struct.Ldap.html#method.sasl_gssapi_bind).
 //!
+//! * __runtime-tokio__ (enabled by default): run on the Tokio runtime.
+//!
+//! * __runtime-async-std__ (disabled by default): run on the async-std runtime, which
+//!   also makes the library usable with smol. To use it, disable the default features
+//!   and enable this one instead of __runtime-tokio__; if both are enabled, Tokio is
+//!   used. The public API is the same under both runtimes, including the
+//!   [`drive!`](macro.drive.html) macro, which spawns the connection on the selected
+//!   runtime. Exactly one runtime should be chosen, and at least one is required.
+//!
+//!   __Note:__ before 0.12, the crate always used Tokio. A build with
+//!   `default-features = false` must now name a runtime explicitly, e.g.
+//!   `features = ["sync", "runtime-tokio"]`, or it will fail to compile.
+//!
+//! * __scram__ (disabled by default): SASL SCRAM-SHA-1 and SCRAM-SHA-256 support, with
+//!   cryptographic primitives from the RustCrypto crates. See
+//!   [`Ldap::sasl_scram_bind()`](struct.Ldap.html#method.sasl_scram_bind).
+//!
+//! * __serde__ (disabled by default): `Serialize` and `Deserialize` implementations for
+//!   [`SearchEntry`](struct.SearchEntry.html), [`LdapResult`](result/struct.LdapResult.html),
+//!   [`SearchOptions`](struct.SearchOptions.html), [`Scope`](enum.Scope.html),
+//!   [`DerefAliases`](enum.DerefAliases.html), [`Mod`](enum.Mod.html), and controls.
+//!   Binary values are represented as Base64 strings. Also enables the
+//!   [`export`](export/index.html) module, for writing search results as NDJSON or CSV.
+//!
+//! * __test-util__ (disabled by default): the [`testkit`](testkit/index.html) module, with
+//!   a scripted LDAP server for testing code which uses the library.
+//!
 //! * __tls__ (enabled by default): TLS support, backed by the `native-tls` crate, which uses
-//!  a platform-specific TLS backend. This is an alias for __tls-native__.
+//!   a platform-specific TLS backend. This is an alias for __tls-native__.
 //!
 //! * __tls-rustls__ (disabled by default): TLS support, backed by the Rustls library.
 //!
-//! Without any features, only plain TCP connections (and Unix domain sockets on Unix-like
+//! Without any features apart from a runtime, only plain TCP connections (and Unix domain sockets on Unix-like
 //! platforms) are available. For TLS support, __tls__ and __tls-rustls__ are mutually
 //! exclusive: choosing both will produce a compile-time error.
 //!
@@ -100,6 +127,7 @@
 //! ```
 
 #![cfg_attr(docsrs, feature(doc_cfg))]
+#![forbid(unsafe_code)]
 
 #[doc(hidden)]
 #[macro_use]
@@ -110,6 +138,7 @@ pub use tokio;
 /// Type alias for the LDAP message ID.
 pub type RequestId = i32;
 
+pub mod ad;
 pub mod adapters;
 pub mod asn1 {
     //! ASN.1 structure construction and parsing.
@@ -119,15 +148,24 @@ pub mod asn1 {
     //! for, e.g., implementing a new extended operation or a control, consult the source of existing
     //! exops/controls.
     pub use lber::common::TagClass;
-    pub use lber::parse::{parse_tag, parse_uint};
+    pub use lber::debug;
+    pub use lber::parse::{parse_int, parse_tag, parse_uint};
     pub use lber::structure::{StructureTag, PL};
     pub use lber::structures::{
-        ASNTag, Boolean, Enumerated, ExplicitTag, Integer, Null, OctetString, Sequence, Set, Tag,
+        ASNTag, Boolean, Enumerated, ExplicitTag, Integer, InvalidTag, Null, OctetString, Sequence,
+        Set, Tag,
     };
     pub use lber::universal::Types;
     pub use lber::write;
     pub use lber::IResult;
 }
+pub mod attr_desc;
+mod auth;
+mod batch;
+mod cancel;
+mod capabilities;
+#[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
+mod channel_binding;
 mod conn;
 pub mod controls {
     //! Control construction and parsing.
@@ -189,18 +227,29 @@ pub mod controls {
     //! # }
     pub use crate::controls_impl::parse_syncinfo;
     pub use crate::controls_impl::{
-        Assertion, ManageDsaIt, MatchedValues, PagedResults, ProxyAuth, RelaxRules,
+        parse_extended_dn, ExtendedDn, ExtendedDnFormat, ExtendedDnRequest, ShowDeleted,
     };
+    pub use crate::controls_impl::{
+        Assertion, ManageDsaIt, MatchedValues, PagedResults, ProxyAuth, RelaxRules, TreeDelete,
+        TxnSpec,
+    };
+    pub use crate::controls_impl::{ChangeType, EntryChange, PersistentSearch};
     pub use crate::controls_impl::{
         Control, ControlParser, ControlType, CriticalControl, IntoRawControlVec, MakeCritical,
         RawControl,
     };
+    pub use crate::controls_impl::{
+        DirSync, DirSyncResp, LDAP_DIRSYNC_ANCESTORS_FIRST_ORDER, LDAP_DIRSYNC_INCREMENTAL_VALUES,
+        LDAP_DIRSYNC_OBJECT_SECURITY, LDAP_DIRSYNC_PUBLIC_DATA_ONLY,
+    };
     pub use crate::controls_impl::{
         EntryState, RefreshMode, SyncDone, SyncInfo, SyncRequest, SyncState,
     };
+    pub use crate::controls_impl::{PasswordExpired, PasswordExpiring, PasswordExpiry};
     pub use crate::controls_impl::{PostRead, PostReadResp, PreRead, PreReadResp, ReadEntryResp};
 }
 mod controls_impl;
+pub mod diff;
 mod exop_impl;
 pub mod exop {
     //! Extended operation construction and parsing.
@@ -216,30 +265,80 @@ pub mod exop {
     //! A response struct must implement the [`ExopParser`](trait.ExopParser.html)
     //! trait.
     pub use crate::exop_impl::{
-        Exop, ExopParser, PasswordModify, PasswordModifyResp, WhoAmI, WhoAmIResp,
+        Cancel, EndTxn, EndTxnResp, Exop, ExopParser, PasswordModify, PasswordModifyResp, StartTxn,
+        StartTxnResp, WhoAmI, WhoAmIResp,
     };
 }
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub mod export;
 mod filter;
+#[cfg(test)]
+mod fixtures;
+mod groups;
+mod health;
+mod intermediate;
 mod ldap;
+mod ldaprc;
+pub mod mapping;
+pub mod matching;
+pub mod migrate;
+mod observer;
+mod password;
 mod protocol;
+mod ratelimit;
 pub mod result;
+mod retry;
+#[doc(hidden)]
+pub mod rt;
+mod schema;
+mod scram;
 mod search;
+#[cfg(feature = "serde")]
+mod serde_impl;
+mod spool;
+mod stats;
+mod subtree;
 #[cfg(feature = "sync")]
 mod sync;
+#[cfg(feature = "test-util")]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-util")))]
+pub mod testkit;
+mod txn;
+mod unsolicited;
 mod util;
 
-pub use conn::{LdapConnAsync, LdapConnSettings};
+pub use auth::{AuthOutcome, Authenticator};
+pub use batch::{Batch, BatchItem, BatchItemResult};
+pub use cancel::{CancelCause, OpCancellation};
+pub use capabilities::{ServerCapabilities, ServerFlavor};
+pub use conn::{LdapConnAsync, LdapConnSettings, TlsInfo, Unmatched};
 pub use filter::parse as parse_filter;
-pub use ldap::{Ldap, Mod};
-pub use result::{LdapError, LdapResult, SearchResult};
-pub use search::parse_refs;
+pub use groups::{GroupSchema, NestedMode, MATCHING_RULE_IN_CHAIN_OID};
+pub use health::HealthStatus;
+pub use intermediate::{IntermediateParser, IntermediateResponse};
+pub use ldap::{AttrValue, Ldap, Mod, ModOwned, ModifyIfOutcome, ValueBytes, ValueSource};
+pub use ldaprc::DefaultsInfo;
+pub use observer::LdapObserver;
+pub use password::{PasswordFlavor, PasswordStatus};
+pub use result::{LdapError, LdapResult, ResultCode, SearchResult};
+pub use retry::{Backoff, RetryPolicy};
+pub use schema::{AttributeType, AttributeUsage, ObjectClass, ObjectClassKind, RootDse, Schema};
+pub use scram::ScramMech;
+pub use search::{encode_search_entry, parse_refs};
 pub use search::{
-    DerefAliases, ResultEntry, Scope, SearchEntry, SearchOptions, SearchStream, StreamState,
+    AttrCase, BinaryPolicy, ConstructOptions, DerefAliases, Duplicate, ResultEntry, ResultStream,
+    Scope, SearchEntry, SearchOptions, SearchStream, StreamState, TruncationNote, ValueLimits,
 };
+pub use spool::{SpoolReader, SpoolWriter};
+pub use stats::ConnStats;
+pub use subtree::{SubtreeDeleteOptions, SubtreeDeleteStats};
 #[cfg(feature = "sync")]
 pub use sync::{EntryStream, LdapConn};
+pub use txn::Transaction;
+pub use unsolicited::{UnsolicitedNotification, NOTICE_OF_DISCONNECTION_OID};
 #[allow(deprecated)]
 pub use util::{
-    dn_escape, get_url_params, ldap_escape, ldap_str_unescape, ldap_unescape, LdapUrlExt,
-    LdapUrlParams,
+    dn_escape, dn_escape_with, dn_unescape, get_url_params, ldap_escape, ldap_str_unescape,
+    ldap_unescape, EscapeError, EscapeStyle, LdapUrl, LdapUrlExt, LdapUrlParams,
 };
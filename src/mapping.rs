@@ -0,0 +1,434 @@
+//! Mapping of search result entries to user-defined types.
+//!
+//! A [`SearchEntry`](../struct.SearchEntry.html) holds attribute values as strings or
+//! byte vectors, which must be checked and converted before use. The [`FromEntry`](trait.FromEntry.html)
+//! trait describes a type which can be constructed from an entry, while [`FromAttr`](trait.FromAttr.html)
+//! converts the values of a single attribute into one of the common shapes:
+//!
+//! * `String`, `i64`, `bool`, `Vec<u8>`: exactly one value is required. Boolean values
+//!   must be `TRUE` or `FALSE`, as specified by the LDAP Boolean syntax. A byte vector is
+//!   taken from `bin_attrs`, or from the UTF-8 representation of a textual value.
+//!
+//! * `Option<T>`, where `T` is one of the above: at most one value is allowed.
+//!
+//! * `Vec<String>`, `Vec<Vec<u8>>`: any number of values, including none.
+//!
+//! Attribute names are matched case-insensitively. Each error carries the DN of the
+//! entry and the name of the attribute which failed to convert.
+//!
+//! There is no derive macro; implementing `FromEntry` by hand is straightforward with
+//! [`SearchEntry::get()`](../struct.SearchEntry.html#method.get):
+//!
+//! ```rust,no_run
+//! use ldap3::mapping::{EntryMapError, FromEntry};
+//! use ldap3::{LdapConnAsync, Scope, SearchEntry};
+//!
+//! struct User {
+//!     uid: String,
+//!     mail: Option<String>,
+//!     photo: Option<Vec<u8>>,
+//!     groups: Vec<String>,
+//! }
+//!
+//! impl FromEntry for User {
+//!     fn from_entry(entry: &SearchEntry) -> Result<Self, EntryMapError> {
+//!         Ok(User {
+//!             uid: entry.get("uid")?,
+//!             mail: entry.get("mail")?,
+//!             photo: entry.get("jpegPhoto")?,
+//!             groups: entry.get("memberOf")?,
+//!         })
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> ldap3::result::Result<()> {
+//! # let (conn, mut ldap) = LdapConnAsync::new("ldap://localhost:2389").await?;
+//! # ldap3::drive!(conn);
+//! let users: Vec<User> = ldap
+//!     .search("ou=People,dc=example,dc=org", Scope::OneLevel, "(uid=*)", vec!["*", "memberOf"])
+//!     .await?
+//!     .mapped()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+
+use crate::search::SearchEntry;
+
+use thiserror::Error;
+
+/// Error in mapping an entry attribute.
+#[derive(Clone, Debug, Error, PartialEq)]
+#[error("entry {dn}, attribute {attr}: {kind}")]
+pub struct EntryMapError {
+    /// DN of the entry.
+    pub dn: String,
+    /// Name of the attribute.
+    pub attr: String,
+    /// What went wrong.
+    pub kind: EntryMapErrorKind,
+}
+
+/// Kinds of entry mapping errors.
+#[derive(Clone, Debug, Error, PartialEq)]
+#[non_exhaustive]
+pub enum EntryMapErrorKind {
+    /// A required attribute is absent.
+    #[error("missing attribute")]
+    Missing,
+    /// A single-valued target received several values.
+    #[error("{0} values where at most one is expected")]
+    MultipleValues(usize),
+    /// A value can't be converted to the target type.
+    #[error("invalid value {value:?}, expected {expected}")]
+    Invalid {
+        /// The value, lossily converted to a string if binary.
+        value: String,
+        /// Description of the target type.
+        expected: &'static str,
+    },
+}
+
+impl EntryMapError {
+    /// Create an error for the given entry and attribute. Useful in `FromEntry`
+    /// implementations which perform their own checks.
+    pub fn new(entry: &SearchEntry, attr: &str, kind: EntryMapErrorKind) -> Self {
+        EntryMapError {
+            dn: entry.dn.clone(),
+            attr: attr.to_owned(),
+            kind,
+        }
+    }
+}
+
+/// Type which can be constructed from a search result entry.
+pub trait FromEntry: Sized {
+    /// Construct an instance from the entry.
+    fn from_entry(entry: &SearchEntry) -> Result<Self, EntryMapError>;
+}
+
+impl FromEntry for SearchEntry {
+    fn from_entry(entry: &SearchEntry) -> Result<Self, EntryMapError> {
+        Ok(entry.clone())
+    }
+}
+
+/// Type which can be constructed from the values of a single attribute.
+pub trait FromAttr: Sized {
+    /// Convert the values of the named attribute of the entry.
+    fn from_attr(entry: &SearchEntry, attr: &str) -> Result<Self, EntryMapError>;
+}
+
+// Values of an attribute, which may be either textual or binary.
+enum Values<'e> {
+    Text(&'e [String]),
+    Binary(&'e [Vec<u8>]),
+}
+
+impl Values<'_> {
+    fn len(&self) -> usize {
+        match *self {
+            Values::Text(vals) => vals.len(),
+            Values::Binary(vals) => vals.len(),
+        }
+    }
+
+    fn text(&self, expected: &'static str) -> Result<Vec<&str>, EntryMapErrorKind> {
+        match *self {
+            Values::Text(vals) => Ok(vals.iter().map(String::as_str).collect()),
+            Values::Binary(vals) => Err(EntryMapErrorKind::Invalid {
+                value: String::from_utf8_lossy(&vals[0]).into_owned(),
+                expected,
+            }),
+        }
+    }
+
+    fn bytes(&self) -> Vec<Vec<u8>> {
+        match *self {
+            Values::Text(vals) => vals.iter().map(|val| val.as_bytes().to_vec()).collect(),
+            Values::Binary(vals) => vals.to_vec(),
+        }
+    }
+}
+
+pub(crate) fn lookup<'e, T>(map: &'e HashMap<String, Vec<T>>, attr: &str) -> Option<&'e [T]> {
+    map.get(attr)
+        .or_else(|| {
+            map.iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(attr))
+                .map(|(_, vals)| vals)
+        })
+        .map(Vec::as_slice)
+}
+
+// Values of the attribute, matching its name case-insensitively if there's no exact match.
+fn values<'e>(entry: &'e SearchEntry, attr: &str) -> Option<Values<'e>> {
+    lookup(&entry.attrs, attr)
+        .map(Values::Text)
+        .or_else(|| lookup(&entry.bin_attrs, attr).map(Values::Binary))
+        .filter(|vals| vals.len() > 0)
+}
+
+// Convert at most one value of the attribute.
+fn single<T>(
+    entry: &SearchEntry,
+    attr: &str,
+    convert: impl FnOnce(&Values) -> Result<T, EntryMapErrorKind>,
+) -> Result<Option<T>, EntryMapError> {
+    let vals = match values(entry, attr) {
+        Some(vals) => vals,
+        None => return Ok(None),
+    };
+    let res = match vals.len() {
+        1 => convert(&vals),
+        n => Err(EntryMapErrorKind::MultipleValues(n)),
+    };
+    res.map(Some)
+        .map_err(|kind| EntryMapError::new(entry, attr, kind))
+}
+
+fn parse_string(vals: &Values) -> Result<String, EntryMapErrorKind> {
+    Ok(vals.text("UTF-8 string")?[0].to_owned())
+}
+
+fn parse_i64(vals: &Values) -> Result<i64, EntryMapErrorKind> {
+    let val = vals.text("integer")?[0];
+    val.parse().map_err(|_| EntryMapErrorKind::Invalid {
+        value: val.to_owned(),
+        expected: "integer",
+    })
+}
+
+fn parse_bool(vals: &Values) -> Result<bool, EntryMapErrorKind> {
+    match vals.text("TRUE or FALSE")?[0] {
+        "TRUE" => Ok(true),
+        "FALSE" => Ok(false),
+        val => Err(EntryMapErrorKind::Invalid {
+            value: val.to_owned(),
+            expected: "TRUE or FALSE",
+        }),
+    }
+}
+
+fn parse_bytes(vals: &Values) -> Result<Vec<u8>, EntryMapErrorKind> {
+    Ok(vals.bytes().swap_remove(0))
+}
+
+macro_rules! single_valued {
+    ($t:ty, $parse:ident) => {
+        impl FromAttr for $t {
+            fn from_attr(entry: &SearchEntry, attr: &str) -> Result<Self, EntryMapError> {
+                single(entry, attr, $parse)?
+                    .ok_or_else(|| EntryMapError::new(entry, attr, EntryMapErrorKind::Missing))
+            }
+        }
+
+        impl FromAttr for Option<$t> {
+            fn from_attr(entry: &SearchEntry, attr: &str) -> Result<Self, EntryMapError> {
+                single(entry, attr, $parse)
+            }
+        }
+    };
+}
+
+single_valued!(String, parse_string);
+single_valued!(i64, parse_i64);
+single_valued!(bool, parse_bool);
+single_valued!(Vec<u8>, parse_bytes);
+
+impl FromAttr for Vec<String> {
+    fn from_attr(entry: &SearchEntry, attr: &str) -> Result<Self, EntryMapError> {
+        match values(entry, attr) {
+            Some(vals) => vals
+                .text("UTF-8 string")
+                .map(|vals| vals.into_iter().map(str::to_owned).collect())
+                .map_err(|kind| EntryMapError::new(entry, attr, kind)),
+            None => Ok(vec![]),
+        }
+    }
+}
+
+impl FromAttr for Vec<Vec<u8>> {
+    fn from_attr(entry: &SearchEntry, attr: &str) -> Result<Self, EntryMapError> {
+        Ok(values(entry, attr)
+            .map(|vals| vals.bytes())
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::result::SearchResult;
+    use crate::search::ResultEntry;
+
+    use lber::common::TagClass;
+    use lber::structures::{ASNTag, OctetString, Sequence, Set, Tag};
+
+    const PHOTO: &[u8] = b"\xff\xd8\xff\xe0";
+
+    fn entry() -> SearchEntry {
+        let text = |vals: &[&str]| vals.iter().map(|val| val.to_string()).collect();
+        SearchEntry {
+            dn: String::from("uid=jdoe,dc=example,dc=org"),
+            attrs: HashMap::from([
+                (String::from("uid"), text(&["jdoe"])),
+                (String::from("uidNumber"), text(&["1000"])),
+                (
+                    String::from("mail"),
+                    text(&["a@example.org", "b@example.org"]),
+                ),
+                (String::from("locked"), text(&["TRUE"])),
+                (String::from("shell"), text(&["/bin/sh"])),
+            ]),
+            bin_attrs: HashMap::from([(String::from("jpegPhoto"), vec![PHOTO.to_vec()])]),
+            truncated: vec![],
+        }
+    }
+
+    #[derive(Debug)]
+    struct User {
+        uid: String,
+        uid_number: i64,
+        mail: Vec<String>,
+        locked: bool,
+        photo: Option<Vec<u8>>,
+        description: Option<String>,
+    }
+
+    impl FromEntry for User {
+        fn from_entry(entry: &SearchEntry) -> Result<Self, EntryMapError> {
+            Ok(User {
+                uid: entry.get("uid")?,
+                uid_number: entry.get("uidnumber")?,
+                mail: entry.get("mail")?,
+                locked: entry.get("locked")?,
+                photo: entry.get("jpegPhoto")?,
+                description: entry.get("description")?,
+            })
+        }
+    }
+
+    #[test]
+    fn manual_impl() {
+        let user = User::from_entry(&entry()).expect("user");
+        assert_eq!((user.uid.as_str(), user.uid_number), ("jdoe", 1000));
+        assert_eq!(user.mail.len(), 2);
+        assert!(user.locked);
+        assert_eq!(user.photo.as_deref(), Some(PHOTO));
+        assert_eq!(user.description, None);
+        let shell: Vec<u8> = entry().get("shell").expect("textual bytes");
+        assert_eq!(shell, b"/bin/sh");
+    }
+
+    #[test]
+    fn missing_required() {
+        let err = entry().get::<String>("cn").expect_err("missing");
+        assert_eq!(
+            err,
+            EntryMapError {
+                dn: String::from("uid=jdoe,dc=example,dc=org"),
+                attr: String::from("cn"),
+                kind: EntryMapErrorKind::Missing,
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "entry uid=jdoe,dc=example,dc=org, attribute cn: missing attribute"
+        );
+        assert_eq!(entry().get::<Vec<String>>("cn"), Ok(vec![]));
+    }
+
+    #[test]
+    fn type_mismatch() {
+        let entry = entry();
+        let kind = |res: Result<(), EntryMapError>| res.expect_err("mismatch").kind;
+        assert_eq!(
+            kind(entry.get::<i64>("uid").map(drop)),
+            EntryMapErrorKind::Invalid {
+                value: String::from("jdoe"),
+                expected: "integer",
+            }
+        );
+        assert!(matches!(
+            kind(entry.get::<bool>("uid").map(drop)),
+            EntryMapErrorKind::Invalid { .. }
+        ));
+        assert_eq!(
+            kind(entry.get::<Option<String>>("mail").map(drop)),
+            EntryMapErrorKind::MultipleValues(2)
+        );
+        let err = entry.get::<String>("jpegPhoto").expect_err("binary");
+        assert_eq!(err.attr, "jpegPhoto");
+        assert!(matches!(
+            err.kind,
+            EntryMapErrorKind::Invalid {
+                expected: "UTF-8 string",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn mapped_result() {
+        let entry = |uid: &str| {
+            ResultEntry::new(
+                Tag::Sequence(Sequence {
+                    id: 4,
+                    class: TagClass::Application,
+                    inner: vec![
+                        Tag::OctetString(OctetString {
+                            inner: format!("uid={},dc=example,dc=org", uid).into_bytes(),
+                            ..Default::default()
+                        }),
+                        Tag::Sequence(Sequence {
+                            inner: vec![Tag::Sequence(Sequence {
+                                inner: vec![
+                                    Tag::OctetString(OctetString {
+                                        inner: b"uid".to_vec(),
+                                        ..Default::default()
+                                    }),
+                                    Tag::Set(Set {
+                                        inner: vec![Tag::OctetString(OctetString {
+                                            inner: uid.as_bytes().to_vec(),
+                                            ..Default::default()
+                                        })],
+                                        ..Default::default()
+                                    }),
+                                ],
+                                ..Default::default()
+                            })],
+                            ..Default::default()
+                        }),
+                    ],
+                })
+                .into_structure(),
+            )
+        };
+        let res = crate::LdapResult {
+            rc: 0,
+            matched: String::new(),
+            text: String::new(),
+            refs: vec![],
+            ctrls: vec![],
+        };
+        let uids: Vec<String> = SearchResult(vec![entry("a"), entry("b")], res.clone())
+            .mapped::<SearchEntry>()
+            .expect("mapped")
+            .iter()
+            .map(|entry| entry.get("uid").expect("uid"))
+            .collect();
+        assert_eq!(uids, ["a", "b"]);
+        let err = SearchResult(vec![entry("a")], res)
+            .mapped::<User>()
+            .expect_err("incomplete");
+        assert_eq!(
+            (err.dn.as_str(), err.attr.as_str()),
+            ("uid=a,dc=example,dc=org", "uidnumber")
+        );
+    }
+}
@@ -0,0 +1,851 @@
+//! Client-side comparison of attribute values and entries.
+//!
+//! The server compares values according to the matching rules of their attribute types,
+//! but entries retrieved by a Search are just collections of strings. The functions in this
+//! module approximate the most common matching rules, which is enough for sorting entries
+//! for display, or for comparing directory snapshots.
+//!
+//! String comparisons follow the insignificant space handling of
+//! [RFC 4518](https://tools.ietf.org/html/rfc4518#section-2.6.1): leading and trailing
+//! spaces are removed, and each internal run of spaces is treated as a single space. Case
+//! folding uses the Unicode lowercase mapping of each character, which coincides with simple
+//! case folding for all but a handful of characters. The other steps of the RFC 4518 string
+//! preparation, such as normalization, are not performed.
+//!
+//! The same approximations are used by [`matches()`](fn.matches.html) to evaluate a Search
+//! filter against an entry without contacting the server, e.g., to check whether a cached
+//! or modified entry would still be returned by a query.
+//!
+//! ```rust
+//! # use std::collections::HashMap;
+//! use ldap3::matching;
+//! use ldap3::SearchEntry;
+//!
+//! let entry = SearchEntry {
+//!     dn: String::from("uid=jdoe,ou=People,dc=example,dc=org"),
+//!     attrs: HashMap::from([
+//!         (String::from("uid"), vec![String::from("jdoe")]),
+//!         (String::from("uidNumber"), vec![String::from("1005")]),
+//!     ]),
+//!     bin_attrs: HashMap::new(),
+//!     truncated: vec![],
+//! };
+//! assert_eq!(matching::matches_str("(&(uid=JDOE)(uidNumber>=999))", &entry), Ok(true));
+//! assert_eq!(matching::matches_str("(!(mail=*))", &entry), Ok(true));
+//! ```
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::attr_desc::{self, has_option, matches_name};
+use crate::filter::{
+    AND_FILT, APPROX_MATCH, EQ_MATCH, EXT_MATCH, GTE_MATCH, LTE_MATCH, NOT_FILT, OR_FILT,
+    PRES_MATCH, SUBSTR_MATCH, SUB_ANY, SUB_FINAL, SUB_INITIAL,
+};
+use crate::ldap::split_unescaped;
+use crate::mapping::lookup;
+use crate::search::SearchEntry;
+use crate::util::dn_unescape;
+
+use lber::common::TagClass;
+use lber::structures::{Boolean, ExplicitTag, OctetString, Sequence, Tag};
+use thiserror::Error;
+
+/// Matching rules for sorting entries.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MatchingRule {
+    /// `caseIgnoreMatch`/`caseIgnoreOrderingMatch`: see [`caseignore_cmp()`](fn.caseignore_cmp.html).
+    CaseIgnore,
+    /// `caseExactMatch`/`caseExactOrderingMatch`: see [`caseexact_cmp()`](fn.caseexact_cmp.html).
+    CaseExact,
+    /// `integerMatch`/`integerOrderingMatch`: see [`numeric_cmp()`](fn.numeric_cmp.html).
+    Numeric,
+}
+
+impl MatchingRule {
+    /// Compare two values according to the rule.
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        match self {
+            MatchingRule::CaseIgnore => caseignore_cmp(a, b),
+            MatchingRule::CaseExact => caseexact_cmp(a, b),
+            MatchingRule::Numeric => numeric_cmp(a, b),
+        }
+    }
+}
+
+// Characters of the value with insignificant spaces removed.
+fn fold_spaces(s: &str) -> impl Iterator<Item = char> + '_ {
+    let mut words = s.split(' ').filter(|word| !word.is_empty());
+    let first = words.next();
+    first
+        .into_iter()
+        .chain(words.flat_map(|word| [" ", word]))
+        .flat_map(str::chars)
+}
+
+/// Compare two strings ignoring case and insignificant spaces.
+///
+/// `"  Jane   Doe "` and `"jane doe"` compare as equal.
+pub fn caseignore_cmp(a: &str, b: &str) -> Ordering {
+    fold_spaces(a)
+        .flat_map(char::to_lowercase)
+        .cmp(fold_spaces(b).flat_map(char::to_lowercase))
+}
+
+/// Compare two strings ignoring insignificant spaces.
+pub fn caseexact_cmp(a: &str, b: &str) -> Ordering {
+    fold_spaces(a).cmp(fold_spaces(b))
+}
+
+// Sign and significant digits of an integer value, or None if the value isn't an integer.
+fn integer(s: &str) -> Option<(bool, &str)> {
+    let s = s.trim_matches(' ');
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s),
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let digits = digits.trim_start_matches('0');
+    Some((negative && !digits.is_empty(), digits))
+}
+
+/// Compare two strings as integers of arbitrary size.
+///
+/// The values are compared by magnitude, so that `"9"` sorts before `"10"`, and `"-0"` and `"007"`
+/// are equal to `"0"` and `"7"`, respectively. Values which aren't integers sort after all integers,
+/// and are compared with each other by [`caseexact_cmp()`](fn.caseexact_cmp.html).
+pub fn numeric_cmp(a: &str, b: &str) -> Ordering {
+    match (integer(a), integer(b)) {
+        (Some((a_neg, a)), Some((b_neg, b))) => {
+            let magnitude = a.len().cmp(&b.len()).then_with(|| a.cmp(b));
+            match (a_neg, b_neg) {
+                (false, false) => magnitude,
+                (true, true) => magnitude.reverse(),
+                (false, true) => Ordering::Greater,
+                (true, false) => Ordering::Less,
+            }
+        }
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => caseexact_cmp(a, b),
+    }
+}
+
+/// Sort entries in ascending order of the values of an attribute.
+///
+/// For a multi-valued attribute, the smallest value under the rule is used as the sort key,
+/// as with the server-side sorting control. Entries which don't have the attribute, or have it
+/// only among binary attributes, are placed after all others. The sort is stable, and the
+/// attribute name is matched case-insensitively.
+pub fn sort_entries(entries: &mut [SearchEntry], attr: &str, rule: MatchingRule) {
+    let key = |entry: &SearchEntry| -> Option<String> {
+        lookup(&entry.attrs, attr)
+            .and_then(|vals| vals.iter().min_by(|a, b| rule.compare(a, b)))
+            .cloned()
+    };
+    entries.sort_by_cached_key(|entry| SortKey(key(entry), rule));
+}
+
+// Entry sort key, ordering the entries without the attribute last.
+struct SortKey(Option<String>, MatchingRule);
+
+impl PartialEq for SortKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SortKey {}
+
+impl Ord for SortKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => self.1.compare(a, b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+}
+
+impl PartialOrd for SortKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Attribute values of the entry, as sorted byte vectors keyed by lowercased attribute names.
+fn normalized(entry: &SearchEntry, ignore_attrs: &[&str]) -> HashMap<String, Vec<Vec<u8>>> {
+    let text = entry.attrs.iter().map(|(attr, vals)| {
+        (
+            attr,
+            vals.iter().map(|val| val.as_bytes()).collect::<Vec<_>>(),
+        )
+    });
+    let binary = entry
+        .bin_attrs
+        .iter()
+        .map(|(attr, vals)| (attr, vals.iter().map(Vec::as_slice).collect()));
+    let mut map: HashMap<String, Vec<Vec<u8>>> = HashMap::new();
+    for (attr, vals) in text.chain(binary) {
+        if vals.is_empty()
+            || ignore_attrs
+                .iter()
+                .any(|ignored| ignored.eq_ignore_ascii_case(attr))
+        {
+            continue;
+        }
+        map.entry(attr.to_ascii_lowercase())
+            .or_default()
+            .extend(vals.into_iter().map(<[u8]>::to_vec));
+    }
+    for vals in map.values_mut() {
+        vals.sort_unstable();
+    }
+    map
+}
+
+/// Check whether two entries are equal, apart from the listed attributes.
+///
+/// Entry DNs are compared with [`caseignore_cmp()`](fn.caseignore_cmp.html). Attribute names
+/// are compared case-insensitively, and the order of attributes and their values is
+/// irrelevant, but the values themselves must be identical, since the matching rules of
+/// the attributes are unknown. A value is the same whether it's found among the textual or
+/// the binary attributes.
+pub fn entries_equal(a: &SearchEntry, b: &SearchEntry, ignore_attrs: &[&str]) -> bool {
+    caseignore_cmp(&a.dn, &b.dn) == Ordering::Equal
+        && normalized(a, ignore_attrs) == normalized(b, ignore_attrs)
+}
+
+/// Error evaluating a filter against an entry.
+#[derive(Clone, Debug, Error, PartialEq)]
+#[non_exhaustive]
+pub enum MatchError {
+    /// The filter string can't be parsed.
+    #[error("invalid filter")]
+    InvalidFilter,
+    /// The filter structure doesn't correspond to a Search filter.
+    #[error("malformed filter element")]
+    MalformedFilter,
+    /// An extensible match names a matching rule which can't be evaluated locally.
+    #[error("unsupported matching rule: {0}")]
+    UnsupportedMatchingRule(String),
+}
+
+// Value of a filter, as described in RFC 4511, Section 4.5.1.7.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Truth {
+    True,
+    False,
+    Undefined,
+}
+
+impl Truth {
+    fn from_bool(b: bool) -> Self {
+        if b {
+            Truth::True
+        } else {
+            Truth::False
+        }
+    }
+
+    fn not(self) -> Self {
+        match self {
+            Truth::True => Truth::False,
+            Truth::False => Truth::True,
+            Truth::Undefined => Truth::Undefined,
+        }
+    }
+}
+
+// Combine the values of set members: `dominant` decides the outcome if present, otherwise
+// Undefined does, and the result is the opposite of `dominant` if neither is present.
+fn combine(values: impl IntoIterator<Item = Truth>, dominant: Truth) -> Truth {
+    let mut result = dominant.not();
+    for value in values {
+        if value == dominant {
+            return dominant;
+        }
+        if value == Truth::Undefined {
+            result = Truth::Undefined;
+        }
+    }
+    result
+}
+
+fn any(values: impl IntoIterator<Item = Truth>) -> Truth {
+    combine(values, Truth::True)
+}
+
+fn all(values: impl IntoIterator<Item = Truth>) -> Truth {
+    combine(values, Truth::False)
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Value<'a> {
+    Text(&'a str),
+    Binary(&'a [u8]),
+}
+
+// Matching rules usable in extensible matches.
+#[derive(Clone, Copy, Debug)]
+enum Rule {
+    CaseIgnore,
+    CaseExact,
+    Integer,
+    OctetString,
+    BitAnd,
+    BitOr,
+}
+
+const RULES: &[(&[&str], Rule)] = &[
+    (&["2.5.13.2", "caseIgnoreMatch"], Rule::CaseIgnore),
+    (
+        &["1.3.6.1.4.1.1466.109.114.2", "caseIgnoreIA5Match"],
+        Rule::CaseIgnore,
+    ),
+    (&["2.5.13.5", "caseExactMatch"], Rule::CaseExact),
+    (
+        &["1.3.6.1.4.1.1466.109.114.1", "caseExactIA5Match"],
+        Rule::CaseExact,
+    ),
+    (&["2.5.13.14", "integerMatch"], Rule::Integer),
+    (&["2.5.13.17", "octetStringMatch"], Rule::OctetString),
+    (&["1.2.840.113556.1.4.803"], Rule::BitAnd),
+    (&["1.2.840.113556.1.4.804"], Rule::BitOr),
+];
+
+// Value of an integer attribute as a bit field, accepting the signed representation
+// which Active Directory uses for flags.
+fn bits(s: &str) -> Option<u64> {
+    let s = s.trim_matches(' ');
+    s.parse::<i64>()
+        .map(|n| n as u64)
+        .or_else(|_| s.parse::<u64>())
+        .ok()
+}
+
+impl Rule {
+    fn find(name: &str) -> Option<Rule> {
+        RULES
+            .iter()
+            .find(|(names, _)| names.iter().any(|n| n.eq_ignore_ascii_case(name)))
+            .map(|&(_, rule)| rule)
+    }
+
+    // Binary values are compared bytewise by every rule. A textual assertion which
+    // isn't valid for the rule makes the result Undefined.
+    fn matches(self, val: Value, assertion: &[u8]) -> Truth {
+        let val = match (self, val) {
+            (_, Value::Binary(val)) => return Truth::from_bool(val == assertion),
+            (Rule::OctetString, Value::Text(val)) => {
+                return Truth::from_bool(val.as_bytes() == assertion)
+            }
+            (_, Value::Text(val)) => val,
+        };
+        let Ok(assertion) = std::str::from_utf8(assertion) else {
+            return Truth::Undefined;
+        };
+        match self {
+            Rule::CaseIgnore => Truth::from_bool(caseignore_cmp(val, assertion) == Ordering::Equal),
+            Rule::CaseExact => Truth::from_bool(caseexact_cmp(val, assertion) == Ordering::Equal),
+            Rule::Integer => match (integer(val), integer(assertion)) {
+                (_, None) => Truth::Undefined,
+                (None, _) => Truth::False,
+                _ => Truth::from_bool(numeric_cmp(val, assertion) == Ordering::Equal),
+            },
+            Rule::BitAnd | Rule::BitOr => match (bits(val), bits(assertion)) {
+                (_, None) => Truth::Undefined,
+                (None, _) => Truth::False,
+                (Some(val), Some(mask)) => Truth::from_bool(match self {
+                    Rule::BitAnd => val & mask == mask,
+                    _ => val & mask != 0,
+                }),
+            },
+            Rule::OctetString => unreachable!(),
+        }
+    }
+}
+
+// Compare a value with the assertion of a >= or <= item, which is true unless the
+// comparison yields `excluded`. Integers are compared numerically, other values with
+// caseIgnoreOrderingMatch; binary values have no ordering.
+fn ordering(val: Value, assertion: &[u8], excluded: Ordering) -> Truth {
+    let (Value::Text(val), Ok(assertion)) = (val, std::str::from_utf8(assertion)) else {
+        return Truth::Undefined;
+    };
+    let ord = if integer(val).is_some() && integer(assertion).is_some() {
+        numeric_cmp(val, assertion)
+    } else {
+        caseignore_cmp(val, assertion)
+    };
+    Truth::from_bool(ord != excluded)
+}
+
+// Lowercase the string and collapse the runs of spaces, without trimming, so that
+// substring components can be compared with values folded by fold_spaces().
+fn fold_component(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c != ' ' || !out.ends_with(' ') {
+            out.extend(c.to_lowercase());
+        }
+    }
+    out
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn substr_match(val: &[u8], components: &[(u64, &[u8])]) -> bool {
+    let mut rest = val;
+    for &(kind, component) in components {
+        match kind {
+            SUB_INITIAL => match rest.strip_prefix(component) {
+                Some(tail) => rest = tail,
+                None => return false,
+            },
+            SUB_ANY => match find(rest, component) {
+                Some(pos) => rest = &rest[pos + component.len()..],
+                None => return false,
+            },
+            _ => return rest.ends_with(component),
+        }
+    }
+    true
+}
+
+fn substrings(val: Value, components: &[(u64, &[u8])]) -> Truth {
+    let val = match val {
+        Value::Binary(val) => return Truth::from_bool(substr_match(val, components)),
+        Value::Text(val) => fold_spaces(val)
+            .flat_map(char::to_lowercase)
+            .collect::<String>(),
+    };
+    let mut folded = Vec::with_capacity(components.len());
+    for &(kind, component) in components {
+        let Ok(component) = std::str::from_utf8(component) else {
+            return Truth::Undefined;
+        };
+        folded.push((kind, fold_component(component)));
+    }
+    let folded: Vec<_> = folded
+        .iter()
+        .map(|(kind, component)| (*kind, component.as_bytes()))
+        .collect();
+    Truth::from_bool(substr_match(val.as_bytes(), &folded))
+}
+
+// Check whether an entry attribute is selected by the attribute description of a filter
+// item: the base names must match, and the entry attribute must have all of the options.
+fn selects(filter_desc: &str, attr: &str) -> bool {
+    let (name, opts) = attr_desc::parse(filter_desc);
+    matches_name(attr, name) && opts.iter().all(|opt| has_option(attr, opt))
+}
+
+fn attr_values<'a>(entry: &'a SearchEntry, desc: Option<&str>) -> Vec<Value<'a>> {
+    let wanted = |attr: &str| desc.is_none_or(|desc| selects(desc, attr));
+    let text = entry
+        .attrs
+        .iter()
+        .filter(|(attr, _)| wanted(attr))
+        .flat_map(|(_, vals)| vals.iter().map(|val| Value::Text(val)));
+    let binary = entry
+        .bin_attrs
+        .iter()
+        .filter(|(attr, _)| wanted(attr))
+        .flat_map(|(_, vals)| vals.iter().map(|val| Value::Binary(val)));
+    text.chain(binary).collect()
+}
+
+// Attribute/value pairs of all RDNs of the DN, for extensible matches with the dnAttributes
+// flag. An unparseable DN contributes no values, nor does an invalid value.
+fn dn_values(dn: &str) -> Vec<(&str, String)> {
+    let Ok(rdns) = split_unescaped(dn, b',') else {
+        return vec![];
+    };
+    let mut pairs = vec![];
+    for rdn in rdns {
+        let Ok(avas) = split_unescaped(rdn, b'+') else {
+            return vec![];
+        };
+        for ava in avas {
+            let Some((attr, val)) = ava.split_once('=') else {
+                continue;
+            };
+            if let Ok(val) = dn_unescape(val) {
+                pairs.push((attr.trim(), String::from_utf8_lossy(&val).into_owned()));
+            }
+        }
+    }
+    pairs
+}
+
+fn text(bytes: &[u8]) -> Result<&str, MatchError> {
+    std::str::from_utf8(bytes).map_err(|_| MatchError::MalformedFilter)
+}
+
+fn octet_string(tag: &Tag) -> Result<&[u8], MatchError> {
+    match tag {
+        Tag::OctetString(OctetString { inner, .. }) => Ok(inner),
+        _ => Err(MatchError::MalformedFilter),
+    }
+}
+
+fn extensible(inner: &[Tag], entry: &SearchEntry) -> Result<Truth, MatchError> {
+    let (mut rule, mut attr, mut value, mut dn_attrs) = (None, None, None, false);
+    for tag in inner {
+        match tag {
+            Tag::OctetString(OctetString { id: 1, inner, .. }) => rule = Some(text(inner)?),
+            Tag::OctetString(OctetString { id: 2, inner, .. }) => attr = Some(text(inner)?),
+            Tag::OctetString(OctetString { id: 3, inner, .. }) => value = Some(inner.as_slice()),
+            Tag::Boolean(Boolean { id: 4, inner, .. }) => dn_attrs = *inner,
+            _ => return Err(MatchError::MalformedFilter),
+        }
+    }
+    let value = value.ok_or(MatchError::MalformedFilter)?;
+    let rule = match (rule, attr) {
+        (Some(rule), _) => {
+            Rule::find(rule).ok_or_else(|| MatchError::UnsupportedMatchingRule(rule.to_owned()))?
+        }
+        (None, Some(_)) => Rule::CaseIgnore,
+        (None, None) => return Err(MatchError::MalformedFilter),
+    };
+    let dn_pairs = if dn_attrs {
+        dn_values(&entry.dn)
+    } else {
+        vec![]
+    };
+    let mut vals = attr_values(entry, attr);
+    vals.extend(
+        dn_pairs
+            .iter()
+            .filter(|(name, _)| attr.is_none_or(|desc| selects(desc, name)))
+            .map(|(_, val)| Value::Text(val)),
+    );
+    Ok(any(vals.into_iter().map(|val| rule.matches(val, value))))
+}
+
+fn eval(filter: &Tag, entry: &SearchEntry) -> Result<Truth, MatchError> {
+    let truth = match filter {
+        Tag::Sequence(Sequence {
+            class: TagClass::Context,
+            id,
+            inner,
+        }) => match *id {
+            AND_FILT | OR_FILT => {
+                // Evaluate every member, so that an unsupported element is always reported.
+                let values = inner
+                    .iter()
+                    .map(|filter| eval(filter, entry))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if *id == AND_FILT {
+                    all(values)
+                } else {
+                    any(values)
+                }
+            }
+            EQ_MATCH | APPROX_MATCH | GTE_MATCH | LTE_MATCH => {
+                let [attr, value] = inner.as_slice() else {
+                    return Err(MatchError::MalformedFilter);
+                };
+                let (attr, value) = (text(octet_string(attr)?)?, octet_string(value)?);
+                any(attr_values(entry, Some(attr))
+                    .into_iter()
+                    .map(|val| match *id {
+                        GTE_MATCH => ordering(val, value, Ordering::Less),
+                        LTE_MATCH => ordering(val, value, Ordering::Greater),
+                        _ => Rule::CaseIgnore.matches(val, value),
+                    }))
+            }
+            SUBSTR_MATCH => {
+                let [attr, Tag::Sequence(Sequence { inner: subs, .. })] = inner.as_slice() else {
+                    return Err(MatchError::MalformedFilter);
+                };
+                let attr = text(octet_string(attr)?)?;
+                let components = subs
+                    .iter()
+                    .map(|sub| match sub {
+                        Tag::OctetString(OctetString { id, inner, .. })
+                            if [SUB_INITIAL, SUB_ANY, SUB_FINAL].contains(id) =>
+                        {
+                            Ok((*id, inner.as_slice()))
+                        }
+                        _ => Err(MatchError::MalformedFilter),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                any(attr_values(entry, Some(attr))
+                    .into_iter()
+                    .map(|val| substrings(val, &components)))
+            }
+            EXT_MATCH => extensible(inner, entry)?,
+            _ => return Err(MatchError::MalformedFilter),
+        },
+        Tag::ExplicitTag(ExplicitTag {
+            class: TagClass::Context,
+            id: NOT_FILT,
+            inner,
+        }) => eval(inner, entry)?.not(),
+        Tag::OctetString(OctetString {
+            class: TagClass::Context,
+            id: PRES_MATCH,
+            inner,
+        }) => {
+            let desc = text(inner)?;
+            let present = entry
+                .attrs
+                .keys()
+                .chain(entry.bin_attrs.keys())
+                .any(|attr| selects(desc, attr));
+            Truth::from_bool(present)
+        }
+        _ => return Err(MatchError::MalformedFilter),
+    };
+    Ok(truth)
+}
+
+/// Evaluate a parsed Search filter against an entry.
+///
+/// The filter is evaluated as described in [RFC 4511](https://tools.ietf.org/html/rfc4511#section-4.5.1.7),
+/// with the matching rules approximated as follows, since the entry carries no schema
+/// information:
+///
+/// * Equality and approximate matches use [`caseignore_cmp()`](fn.caseignore_cmp.html).
+///
+/// * Substring matches ignore case and insignificant spaces in the same way.
+///
+/// * Ordering matches (`>=`, `<=`) compare numerically if both the value and the assertion
+///   are integers, and with `caseignore_cmp()` otherwise.
+///
+/// * Extensible matches support `caseIgnoreMatch`, `caseExactMatch`, their IA5 variants,
+///   `integerMatch`, `octetStringMatch`, and the Active Directory bitwise AND and OR rules
+///   (`1.2.840.113556.1.4.803` and `.804`), by name or OID. Without a rule, `caseIgnoreMatch`
+///   is used; without an attribute, all attributes of the entry are checked, and with
+///   `:dn:`, the attribute values of the entry DN as well. Other rules, such as the
+///   transitive `LDAP_MATCHING_RULE_IN_CHAIN`, which needs the rest of the directory,
+///   cause an `UnsupportedMatchingRule` error, wherever they appear in the filter.
+///
+/// * Binary values are compared bytewise, and have no ordering.
+///
+/// An attribute description in the filter selects the entry attributes with the same base
+/// name and at least the same options, so that `cn` also covers `cn;lang-en`. An item for
+/// an attribute which the entry doesn't have is false, and thus true under negation. An
+/// item which can't be evaluated, such as an ordering match on a binary attribute, or an
+/// assertion which isn't valid UTF-8 for a textual attribute, is Undefined, which stays
+/// Undefined under negation. The entry matches only if the filter is true.
+pub fn matches(filter: &Tag, entry: &SearchEntry) -> Result<bool, MatchError> {
+    Ok(eval(filter, entry)? == Truth::True)
+}
+
+/// Parse a filter string and evaluate it against an entry with [`matches()`](fn.matches.html).
+pub fn matches_str(filter: &str, entry: &SearchEntry) -> Result<bool, MatchError> {
+    let filter = crate::filter::parse(filter).map_err(|_| MatchError::InvalidFilter)?;
+    matches(&filter, entry)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(dn: &str, attrs: &[(&str, &[&str])]) -> SearchEntry {
+        SearchEntry {
+            dn: dn.to_owned(),
+            attrs: attrs
+                .iter()
+                .map(|(attr, vals)| {
+                    (
+                        attr.to_string(),
+                        vals.iter().map(|val| val.to_string()).collect(),
+                    )
+                })
+                .collect(),
+            bin_attrs: HashMap::new(),
+            truncated: vec![],
+        }
+    }
+
+    #[test]
+    fn space_folding() {
+        assert_eq!(caseignore_cmp("  Jane   Doe ", "jane doe"), Ordering::Equal);
+        assert_eq!(caseignore_cmp("", "   "), Ordering::Equal);
+        assert_eq!(caseignore_cmp("janedoe", "jane doe"), Ordering::Greater);
+        assert_eq!(caseignore_cmp("ÉTÉ", "été"), Ordering::Equal);
+        assert_eq!(caseexact_cmp(" a  b", "a b "), Ordering::Equal);
+        assert_eq!(caseexact_cmp("A", "a"), Ordering::Less);
+    }
+
+    #[test]
+    fn numeric_values() {
+        assert_eq!(numeric_cmp("10", "9"), Ordering::Greater);
+        assert_eq!(numeric_cmp("-10", "-9"), Ordering::Less);
+        assert_eq!(numeric_cmp("-1", "0"), Ordering::Less);
+        assert_eq!(numeric_cmp("-0", "0"), Ordering::Equal);
+        assert_eq!(numeric_cmp("007", "7"), Ordering::Equal);
+        assert_eq!(
+            numeric_cmp("123456789012345678901234567890", "99"),
+            Ordering::Greater
+        );
+        assert_eq!(numeric_cmp("x", "99"), Ordering::Greater);
+        assert_eq!(numeric_cmp("-", "1"), Ordering::Greater);
+    }
+
+    #[test]
+    fn sorting() {
+        let mut entries = vec![
+            entry("cn=a", &[("uidNumber", &["10"])]),
+            entry("cn=b", &[]),
+            entry("cn=c", &[("uidnumber", &["9", "100"])]),
+            entry("cn=d", &[("uidNumber", &["1000"])]),
+        ];
+        sort_entries(&mut entries, "uidNumber", MatchingRule::Numeric);
+        let dns: Vec<_> = entries.iter().map(|e| e.dn.as_str()).collect();
+        assert_eq!(dns, ["cn=c", "cn=a", "cn=d", "cn=b"]);
+        // Lexically, the smallest value of cn=c is "100".
+        sort_entries(&mut entries, "uidNumber", MatchingRule::CaseExact);
+        let dns: Vec<_> = entries.iter().map(|e| e.dn.as_str()).collect();
+        assert_eq!(dns, ["cn=a", "cn=c", "cn=d", "cn=b"]);
+    }
+
+    #[test]
+    fn equality() {
+        let a = entry(
+            "CN=Test,dc=example",
+            &[("cn", &["Test", "t"]), ("modifyTimestamp", &["1"])],
+        );
+        let mut b = entry(
+            "cn=test,DC=example",
+            &[("CN", &["t", "Test"]), ("modifytimestamp", &["2"])],
+        );
+        assert!(entries_equal(&a, &b, &["modifyTimestamp"]));
+        assert!(!entries_equal(&a, &b, &[]));
+        b.attrs.remove("CN");
+        b.bin_attrs
+            .insert(String::from("cn"), vec![b"Test".to_vec(), b"t".to_vec()]);
+        assert!(entries_equal(&a, &b, &["modifyTimestamp"]));
+        b.attrs
+            .insert(String::from("cn"), vec![String::from("test")]);
+        assert!(!entries_equal(&a, &b, &["modifyTimestamp"]));
+    }
+
+    #[test]
+    fn filter_evaluation() {
+        let mut e = entry(
+            "cn=Jane Doe+uid=jdoe,ou=People\\2C Staff,dc=example,dc=org",
+            &[
+                ("cn", &["Jane  Doe", "J. Doe"]),
+                ("cn;lang-fr", &["Jeanne Doe"]),
+                ("sn", &["Doe"]),
+                ("uid", &["jdoe"]),
+                ("uidNumber", &["1005"]),
+                ("mail", &["jdoe@Example.org"]),
+                ("userAccountControl", &["514"]),
+                ("description", &["Über-Admin"]),
+            ],
+        );
+        e.bin_attrs
+            .insert(String::from("jpegPhoto"), vec![b"\xff\xd8\xff".to_vec()]);
+        e.bin_attrs.insert(
+            String::from("userCertificate;binary"),
+            vec![b"\x30\x82".to_vec()],
+        );
+        let unsupported = |oid: &str| Err(MatchError::UnsupportedMatchingRule(oid.to_owned()));
+        let cases: &[(&str, Result<bool, MatchError>)] = &[
+            // equality and approximate match
+            ("(cn=jane doe)", Ok(true)),
+            ("(CN=  JANE   DOE )", Ok(true)),
+            ("(cn=Jeanne Doe)", Ok(true)),
+            ("(cn;lang-fr=jane doe)", Ok(false)),
+            ("(cn;LANG-FR=jeanne doe)", Ok(true)),
+            ("(sn=Smith)", Ok(false)),
+            ("(description=über-admin)", Ok(true)),
+            ("(cn~=J. DOE)", Ok(true)),
+            // presence
+            ("(cn=*)", Ok(true)),
+            ("(jpegPhoto=*)", Ok(true)),
+            ("(userCertificate=*)", Ok(true)),
+            ("(telephoneNumber=*)", Ok(false)),
+            // substrings
+            ("(cn=jane*)", Ok(true)),
+            ("(cn=*DOE)", Ok(true)),
+            ("(cn=j*doe)", Ok(true)),
+            ("(cn=*ne d*)", Ok(true)),
+            ("(cn=jane  d*)", Ok(true)),
+            ("(cn=*e*e*)", Ok(true)),
+            ("(cn=*o*o*)", Ok(false)),
+            ("(cn=j*x*)", Ok(false)),
+            ("(cn=jane*jane)", Ok(false)),
+            ("(mail=*@example.ORG)", Ok(true)),
+            // ordering
+            ("(uidNumber>=999)", Ok(true)),
+            ("(uidNumber<=999)", Ok(false)),
+            ("(uidNumber>=1005)", Ok(true)),
+            ("(uidNumber<=01005)", Ok(true)),
+            ("(sn>=d)", Ok(true)),
+            ("(sn<=C)", Ok(false)),
+            ("(uidNumber>=abc)", Ok(false)),
+            // boolean operators
+            ("(!(telephoneNumber=123))", Ok(true)),
+            ("(!(sn=doe))", Ok(false)),
+            ("(!(cn=*))", Ok(false)),
+            ("(&(sn=doe)(uid=jdoe))", Ok(true)),
+            ("(&(sn=doe)(uid=x))", Ok(false)),
+            ("(|(sn=x)(uid=jdoe))", Ok(true)),
+            ("(|(sn=x)(uid=y))", Ok(false)),
+            ("(&)", Ok(true)),
+            ("(|)", Ok(false)),
+            // binary values
+            ("(jpegPhoto=\\ff\\d8\\ff)", Ok(true)),
+            ("(jpegPhoto=\\FF\\D8)", Ok(false)),
+            ("(jpegPhoto=\\ff*\\ff)", Ok(true)),
+            ("(userCertificate;binary=\\30\\82)", Ok(true)),
+            // Undefined stays Undefined under negation
+            ("(jpegPhoto>=\\00)", Ok(false)),
+            ("(!(jpegPhoto>=\\00))", Ok(false)),
+            ("(cn=\\ff)", Ok(false)),
+            ("(!(cn=\\ff))", Ok(false)),
+            ("(!(&(cn=\\ff)(sn=x)))", Ok(true)),
+            ("(|(cn=\\ff)(sn=doe))", Ok(true)),
+            ("(!(|(cn=\\ff)(sn=x)))", Ok(false)),
+            // extensible match
+            ("(sn:=DOE)", Ok(true)),
+            ("(sn:caseExactMatch:=Doe)", Ok(true)),
+            ("(sn:caseExactMatch:=doe)", Ok(false)),
+            ("(sn:2.5.13.5:=doe)", Ok(false)),
+            ("(uidNumber:integerMatch:=01005)", Ok(true)),
+            ("(uidNumber:2.5.13.14:=x)", Ok(false)),
+            ("(!(uidNumber:2.5.13.14:=x))", Ok(false)),
+            ("(mail:octetStringMatch:=jdoe@Example.org)", Ok(true)),
+            ("(mail:octetStringMatch:=jdoe@example.org)", Ok(false)),
+            ("(userAccountControl:1.2.840.113556.1.4.803:=2)", Ok(true)),
+            ("(userAccountControl:1.2.840.113556.1.4.803:=514)", Ok(true)),
+            ("(userAccountControl:1.2.840.113556.1.4.803:=3)", Ok(false)),
+            ("(userAccountControl:1.2.840.113556.1.4.804:=3)", Ok(true)),
+            ("(:caseExactMatch:=jdoe)", Ok(true)),
+            ("(:caseExactMatch:=Jdoe)", Ok(false)),
+            ("(ou=people, staff)", Ok(false)),
+            ("(ou:dn:=people, staff)", Ok(true)),
+            ("(uid:dn:caseExactMatch:=jdoe)", Ok(true)),
+            ("(:dn:caseIgnoreMatch:=EXAMPLE)", Ok(true)),
+            (
+                "(member:1.2.840.113556.1.4.1941:=cn=admins,dc=example,dc=org)",
+                unsupported("1.2.840.113556.1.4.1941"),
+            ),
+            (
+                "(|(sn=doe)(cn:caseIgnoreSubstringsMatch:=x))",
+                unsupported("caseIgnoreSubstringsMatch"),
+            ),
+            ("(cn=", Err(MatchError::InvalidFilter)),
+        ];
+        for (filter, expected) in cases {
+            assert_eq!(&matches_str(filter, &e), expected, "{}", filter);
+        }
+    }
+}
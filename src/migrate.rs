@@ -0,0 +1,400 @@
+//! Copying a subtree between directories.
+//!
+//! [`copy_subtree()`](fn.copy_subtree.html) reads the entries of a subtree from one
+//! connection and adds them on another, replacing the base DN of the subtree with a new
+//! one, both in the entry DNs and in the values of attributes which hold DNs. The two
+//! connections may lead to different servers, or to the same server if the subtree is
+//! copied between suffixes.
+//!
+//! ```rust,no_run
+//! # use ldap3::LdapConnAsync;
+//! # use ldap3::migrate::{copy_subtree, CopyOptions};
+//! # async fn f() -> ldap3::result::Result<()> {
+//! # let (conn, mut src) = LdapConnAsync::new("ldap://old.example.org").await?;
+//! # ldap3::drive!(conn);
+//! # let (conn, mut dst) = LdapConnAsync::new("ldap://new.example.org").await?;
+//! # ldap3::drive!(conn);
+//! let opts = CopyOptions::new().skip_attr("userPassword");
+//! let stats = copy_subtree(
+//!     &mut src,
+//!     &mut dst,
+//!     "ou=People,dc=example,dc=org",
+//!     "ou=People,dc=example,dc=com",
+//!     &opts,
+//! )
+//! .await?;
+//! for (dn, res) in &stats.failed {
+//!     eprintln!("{}: {}", dn, res);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::adapters::{Adapter, EntriesOnly, PagedResults};
+use crate::attr_desc;
+use crate::controls_impl::PAGED_RESULTS_OID;
+use crate::ldap::{split_unescaped, Ldap};
+use crate::protocol::MaybeControls;
+use crate::result::{LdapError, LdapResult, Result};
+use crate::search::{Scope, SearchEntry};
+use crate::subtree::rearm;
+
+// Operational attributes which a server may return even if not asked for, or which are
+// requested to find out whether an entry has subordinates. None of them can be added.
+const OPERATIONAL_ATTRS: &[&str] = &[
+    "createTimestamp",
+    "modifyTimestamp",
+    "creatorsName",
+    "modifiersName",
+    "entryDN",
+    "entryUUID",
+    "entryCSN",
+    "contextCSN",
+    "structuralObjectClass",
+    "subschemaSubentry",
+    "hasSubordinates",
+    "numSubordinates",
+    "nsUniqueId",
+    "objectGUID",
+    "objectSid",
+    "whenCreated",
+    "whenChanged",
+    "uSNCreated",
+    "uSNChanged",
+];
+
+/// Options for [`copy_subtree()`](fn.copy_subtree.html).
+#[derive(Clone, Debug)]
+pub struct CopyOptions {
+    dn_attrs: Vec<String>,
+    skip_attrs: Vec<String>,
+    page_size: i32,
+    fail_fast: bool,
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        CopyOptions {
+            dn_attrs: ["member", "uniqueMember", "manager", "seeAlso"]
+                .iter()
+                .map(|a| a.to_ascii_lowercase())
+                .collect(),
+            skip_attrs: OPERATIONAL_ATTRS
+                .iter()
+                .map(|a| a.to_ascii_lowercase())
+                .collect(),
+            page_size: 500,
+            fail_fast: false,
+        }
+    }
+}
+
+impl CopyOptions {
+    /// Create an instance with the default values: DNs are rewritten in `member`,
+    /// `uniqueMember`, `manager`, and `seeAlso`, the common operational attributes
+    /// are skipped, the source is read in pages of 500 entries, and failed entries
+    /// don't stop the copy.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the attributes whose values are DNs to be rewritten, replacing the default list.
+    pub fn dn_attrs(mut self, attrs: &[&str]) -> Self {
+        self.dn_attrs = attrs.iter().map(|a| a.to_ascii_lowercase()).collect();
+        self
+    }
+
+    /// Leave an attribute out of the copied entries. Can be called repeatedly.
+    pub fn skip_attr(mut self, attr: &str) -> Self {
+        self.skip_attrs.push(attr.to_ascii_lowercase());
+        self
+    }
+
+    /// Set the page size for reading the source. Paging is used only if the source
+    /// server advertises the Paged Results control in its root DSE; zero disables it.
+    pub fn page_size(mut self, page_size: i32) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    /// If `true`, stop the copy at the first entry which can't be added.
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+}
+
+/// Outcome of copying a subtree.
+#[derive(Clone, Debug, Default)]
+pub struct CopyStats {
+    /// Number of entries added to the destination.
+    pub added: usize,
+    /// DNs of the source entries whose addition failed, with the results of their Add
+    /// operations. The subordinates of a failed entry are not attempted, and not counted.
+    pub failed: Vec<(String, LdapResult)>,
+}
+
+impl CopyStats {
+    /// Check whether the whole subtree was copied.
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Copy the subtree rooted at `src_base` on the `src` connection to `dst_base` on `dst`.
+///
+/// The subtree is traversed one level at a time, with a OneLevel Search below each
+/// entry of the previous level, so that the parents are always added before their
+/// children, and only the DNs of a single level are held in memory. The Search is
+/// skipped for an entry whose `hasSubordinates` or `numSubordinates` attribute, if
+/// returned by the server, shows that it has no subordinates.
+///
+/// The DN of a copied entry is made from its RDN and the DN of its copied parent. In
+/// the attributes set with [`dn_attrs()`](struct.CopyOptions.html#method.dn_attrs), the
+/// values which name `src_base` or an entry below it are rewritten to be under
+/// `dst_base`; other values are copied unchanged. The comparison of DNs is
+/// case-insensitive, and ignores the spaces around the separators.
+///
+/// An entry which can't be added is recorded in the returned statistics, and the copy
+/// continues with the next one, unless [`fail_fast()`](struct.CopyOptions.html#method.fail_fast)
+/// is set. An error in searching the source, or in communicating with either server,
+/// is returned as such. The controls and the timeout set on each handle apply to all
+/// operations on its connection.
+pub async fn copy_subtree(
+    src: &mut Ldap,
+    dst: &mut Ldap,
+    src_base: &str,
+    dst_base: &str,
+    opts: &CopyOptions,
+) -> Result<CopyStats> {
+    let (src_controls, src_timeout) = (src.controls.take(), src.timeout.take());
+    let (dst_controls, dst_timeout) = (dst.controls.take(), dst.timeout.take());
+    let paged = opts.page_size > 0 && {
+        src.timeout = src_timeout;
+        match src.root_dse().await {
+            Ok(root_dse) => root_dse
+                .supported_control
+                .iter()
+                .any(|oid| oid == PAGED_RESULTS_OID),
+            Err(_) => false,
+        }
+    };
+    let mut copier = Copier {
+        src,
+        dst,
+        src_controls,
+        src_timeout,
+        dst_controls,
+        dst_timeout,
+        paged,
+        src_base,
+        dst_base,
+        opts,
+        stats: CopyStats::default(),
+    };
+    let mut level = vec![(String::from(src_base), String::from(dst_base))];
+    let mut scope = Scope::Base;
+    while !level.is_empty() {
+        let mut next = vec![];
+        for (src_dn, dst_dn) in level {
+            if !copier
+                .copy_level(&src_dn, &dst_dn, scope, &mut next)
+                .await?
+            {
+                return Ok(copier.stats);
+            }
+        }
+        level = next;
+        scope = Scope::OneLevel;
+    }
+    Ok(copier.stats)
+}
+
+// State of a copy in progress.
+struct Copier<'c> {
+    src: &'c mut Ldap,
+    dst: &'c mut Ldap,
+    src_controls: MaybeControls,
+    src_timeout: Option<Duration>,
+    dst_controls: MaybeControls,
+    dst_timeout: Option<Duration>,
+    paged: bool,
+    src_base: &'c str,
+    dst_base: &'c str,
+    opts: &'c CopyOptions,
+    stats: CopyStats,
+}
+
+impl Copier<'_> {
+    // Copy the entries found by a Search of `scope` at `src_dn`: with Base, the base
+    // of the subtree, which is copied to `dst_dn`; with OneLevel, the subordinates of
+    // an entry already copied to `dst_dn`. The copied entries which may have subordinates
+    // are appended to `next`. Return false if the copy must stop.
+    async fn copy_level(
+        &mut self,
+        src_dn: &str,
+        dst_dn: &str,
+        scope: Scope,
+        next: &mut Vec<(String, String)>,
+    ) -> Result<bool> {
+        let mut adapters: Vec<Box<dyn Adapter<&str, Vec<&str>>>> =
+            vec![Box::new(EntriesOnly::new())];
+        if self.paged {
+            adapters.push(Box::new(PagedResults::new(self.opts.page_size)));
+        }
+        rearm(self.src, &self.src_controls, self.src_timeout);
+        let mut stream = self
+            .src
+            .streaming_search_with(
+                adapters,
+                src_dn,
+                scope,
+                "(objectClass=*)",
+                vec!["*", "hasSubordinates", "numSubordinates"],
+            )
+            .await?;
+        while let Some(entry) = stream.next().await? {
+            let entry = SearchEntry::construct(entry);
+            let copy_dn = match scope {
+                Scope::Base => String::from(dst_dn),
+                _ => match split_unescaped(&entry.dn, b',') {
+                    Ok(rdns) => format!("{},{}", rdns[0].trim_start(), dst_dn),
+                    Err(_) => return Err(LdapError::MalformedEntry("invalid DN")),
+                },
+            };
+            let leaf = is_leaf(&entry);
+            let src_entry_dn = entry.dn.clone();
+            let attrs = self.copy_attrs(entry);
+            rearm(self.dst, &self.dst_controls, self.dst_timeout);
+            let res = self.dst.add(&copy_dn, attrs).await?;
+            if res.rc != 0 {
+                self.stats.failed.push((src_entry_dn, res));
+                if self.opts.fail_fast {
+                    let _ = stream.abandon().await;
+                    return Ok(false);
+                }
+                continue;
+            }
+            self.stats.added += 1;
+            if !leaf {
+                next.push((src_entry_dn, copy_dn));
+            }
+        }
+        let res = stream.finish().await;
+        match res.rc {
+            0 => Ok(true),
+            // A subordinate may have been deleted in the meantime.
+            32 if scope == Scope::OneLevel => Ok(true),
+            _ => Err(LdapError::from(res)),
+        }
+    }
+
+    // Attributes of the copy of the entry, without the skipped ones, and with the DNs
+    // rewritten in the DN-valued ones.
+    fn copy_attrs(&self, entry: SearchEntry) -> Vec<(Vec<u8>, HashSet<Vec<u8>>)> {
+        let has = |list: &[String], name: &str| {
+            let name = attr_desc::base_name(name).to_ascii_lowercase();
+            list.contains(&name)
+        };
+        let mut attrs = vec![];
+        for (name, vals) in entry.attrs {
+            if has(&self.opts.skip_attrs, &name) {
+                continue;
+            }
+            let rewrite = has(&self.opts.dn_attrs, &name);
+            let vals = vals
+                .into_iter()
+                .map(|val| match rewrite {
+                    true => rewrite_dn(&val, self.src_base, self.dst_base).unwrap_or(val),
+                    false => val,
+                })
+                .map(String::into_bytes)
+                .collect();
+            attrs.push((name.into_bytes(), vals));
+        }
+        for (name, vals) in entry.bin_attrs {
+            if !has(&self.opts.skip_attrs, &name) {
+                attrs.push((name.into_bytes(), vals.into_iter().collect()));
+            }
+        }
+        attrs
+    }
+}
+
+// Whether the server has shown that the entry has no subordinates.
+fn is_leaf(entry: &SearchEntry) -> bool {
+    entry.attrs.iter().any(|(name, vals)| {
+        (name.eq_ignore_ascii_case("hasSubordinates")
+            && vals.iter().any(|v| v.eq_ignore_ascii_case("FALSE")))
+            || (name.eq_ignore_ascii_case("numSubordinates") && vals.iter().any(|v| v == "0"))
+    })
+}
+
+// Normalized form of an RDN for comparison: lowercase, without the spaces around the
+// attribute types and values.
+fn normalize_rdn(rdn: &str) -> String {
+    split_unescaped(rdn, b'+')
+        .unwrap_or_else(|_| vec![rdn])
+        .into_iter()
+        .map(|ava| match ava.split_once('=') {
+            Some((attr, val)) => format!("{}={}", attr.trim(), val.trim()),
+            None => String::from(ava.trim()),
+        })
+        .collect::<Vec<_>>()
+        .join("+")
+        .to_lowercase()
+}
+
+// Replace the suffix `from` of `dn` with `to`, if `dn` is `from` or an entry below it.
+fn rewrite_dn(dn: &str, from: &str, to: &str) -> Option<String> {
+    let rdns = split_unescaped(dn, b',').ok()?;
+    let base = match from {
+        "" => vec![],
+        _ => split_unescaped(from, b',').ok()?,
+    };
+    let split = rdns.len().checked_sub(base.len())?;
+    let under = rdns[split..]
+        .iter()
+        .zip(&base)
+        .all(|(rdn, base_rdn)| normalize_rdn(rdn) == normalize_rdn(base_rdn));
+    if !under || dn.is_empty() && !from.is_empty() {
+        return None;
+    }
+    let mut parts: Vec<_> = rdns[..split].iter().map(|rdn| rdn.trim_start()).collect();
+    if !to.is_empty() {
+        parts.push(to);
+    }
+    Some(parts.join(","))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dn_rewriting() {
+        let (from, to) = ("ou=People,dc=example,dc=org", "ou=Staff,o=new");
+        assert_eq!(
+            rewrite_dn("uid=a, OU=people,dc=Example, dc=org", from, to).as_deref(),
+            Some("uid=a,ou=Staff,o=new")
+        );
+        assert_eq!(rewrite_dn(from, from, to).as_deref(), Some(to));
+        assert_eq!(
+            rewrite_dn("cn=x\\, y,uid=a,ou=People,dc=example,dc=org", from, to).as_deref(),
+            Some("cn=x\\, y,uid=a,ou=Staff,o=new")
+        );
+        assert_eq!(
+            rewrite_dn("uid=a,ou=Groups,dc=example,dc=org", from, to),
+            None
+        );
+        assert_eq!(rewrite_dn("dc=example,dc=org", from, to), None);
+        assert_eq!(rewrite_dn("not a dn", from, to), None);
+        assert_eq!(
+            rewrite_dn("cn=a+sn=b ,dc=org", "dc=org", "").as_deref(),
+            Some("cn=a+sn=b ")
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::RequestId;
+
+use lber::common::TagClass;
+use lber::structures::{Integer, Null, OctetString, Sequence, Tag};
+
+/// Observer of operation requests and responses, for collecting metrics.
+///
+/// An observer is installed on a connection with
+/// [`LdapConnSettings::set_observer()`](struct.LdapConnSettings.html#method.set_observer),
+/// and is shared by all clones of its [`Ldap`](struct.Ldap.html) handle. Its methods are
+/// called synchronously from the task performing the operation, so they should return
+/// quickly.
+///
+/// The operation name passed to `on_request()` is one of "bind", "unbind", "search",
+/// "modify", "add", "delete", "modifydn", "compare", "abandon", or "extended".
+/// `on_response()` is called when the final result of the operation is received; for
+/// a Search, this is the SearchResultDone message. Operations which fail before that,
+/// for example by timing out, don't produce a response callback. Unbind and Abandon,
+/// which have no response, are reported as completed with the result code zero as
+/// soon as the request is sent.
+pub trait LdapObserver: Send + Sync {
+    /// Called before the request with the given message ID is sent.
+    fn on_request(&self, id: RequestId, op: &'static str);
+
+    /// Called when the result of the operation is received, with the time elapsed
+    /// since the request.
+    fn on_response(&self, id: RequestId, rc: u32, elapsed: Duration);
+}
+
+#[derive(Clone)]
+pub(crate) struct Observer(pub(crate) Arc<dyn LdapObserver>);
+
+impl fmt::Debug for Observer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Observer")
+    }
+}
+
+// Derive the name of the operation from the Application tag of the request.
+pub(crate) fn op_name(req: &Tag) -> &'static str {
+    let id = match *req {
+        Tag::Sequence(Sequence {
+            id,
+            class: TagClass::Application,
+            ..
+        })
+        | Tag::OctetString(OctetString {
+            id,
+            class: TagClass::Application,
+            ..
+        })
+        | Tag::Integer(Integer {
+            id,
+            class: TagClass::Application,
+            ..
+        })
+        | Tag::Null(Null {
+            id,
+            class: TagClass::Application,
+            ..
+        }) => id,
+        _ => return "unknown",
+    };
+    match id {
+        0 => "bind",
+        2 => "unbind",
+        3 => "search",
+        6 => "modify",
+        8 => "add",
+        10 => "delete",
+        12 => "modifydn",
+        14 => "compare",
+        16 => "abandon",
+        23 => "extended",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::conn::{LdapConnAsync, LdapConnSettings};
+    use crate::fixtures::{self, entry, message, result};
+    use crate::ldap::Mod;
+    use crate::search::Scope;
+
+    #[derive(Default)]
+    struct Recorder {
+        requests: Mutex<Vec<(RequestId, &'static str)>>,
+        responses: Mutex<Vec<(RequestId, u32)>>,
+    }
+
+    impl LdapObserver for Recorder {
+        fn on_request(&self, id: RequestId, op: &'static str) {
+            self.requests.lock().unwrap().push((id, op));
+        }
+
+        fn on_response(&self, id: RequestId, rc: u32, _elapsed: Duration) {
+            self.responses.lock().unwrap().push((id, rc));
+        }
+    }
+
+    // Scripted server answering Bind, Search with a single entry, and Modify, the
+    // last one with noSuchObject.
+    async fn server() -> String {
+        fixtures::serve(|req| match req.op.id {
+            0 => message(req.msgid, result(1, 0)),
+            3 => {
+                let mut reply = message(req.msgid, entry("dc=example,dc=org", &[]));
+                reply.extend(message(req.msgid, result(5, 0)));
+                reply
+            }
+            6 => message(req.msgid, result(7, 32)),
+            _ => vec![],
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn request_response_pairs() {
+        let recorder = Arc::new(Recorder::default());
+        let url = server().await;
+        let (conn, mut ldap) = LdapConnAsync::with_settings(
+            LdapConnSettings::new().set_observer(recorder.clone()),
+            &url,
+        )
+        .await
+        .expect("connection");
+        crate::drive!(conn);
+        ldap.simple_bind("cn=admin,dc=example,dc=org", "secret")
+            .await
+            .expect("bind");
+        let (entries, res) = ldap
+            .clone()
+            .search(
+                "dc=example,dc=org",
+                Scope::Base,
+                "(objectClass=*)",
+                vec!["*"],
+            )
+            .await
+            .expect("search")
+            .success()
+            .expect("search result");
+        assert_eq!((entries.len(), res.rc), (1, 0));
+        let res = ldap
+            .modify(
+                "cn=nobody,dc=example,dc=org",
+                vec![Mod::Add("description", HashSet::from(["x"]))],
+            )
+            .await
+            .expect("modify");
+        assert_eq!(res.rc, 32);
+        let requests = recorder.requests.lock().unwrap().clone();
+        let ops: Vec<_> = requests.iter().map(|(_, op)| *op).collect();
+        assert_eq!(ops, ["bind", "search", "modify"]);
+        let responses = recorder.responses.lock().unwrap().clone();
+        assert_eq!(
+            responses,
+            requests
+                .iter()
+                .zip([0, 0, 32])
+                .map(|((id, _), rc)| (*id, rc))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn op_names() {
+        let delete = Tag::OctetString(OctetString {
+            id: 10,
+            class: TagClass::Application,
+            inner: b"dc=example,dc=org".to_vec(),
+        });
+        assert_eq!(op_name(&delete), "delete");
+        let unbind = Tag::Null(Null {
+            id: 2,
+            class: TagClass::Application,
+            inner: (),
+        });
+        assert_eq!(op_name(&unbind), "unbind");
+        assert_eq!(op_name(&Tag::Null(Null::default())), "unknown");
+    }
+}
@@ -0,0 +1,514 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::ad::filetime_to_unix;
+use crate::capabilities::{ServerCapabilities, ServerFlavor};
+use crate::ldap::{AttrValue, Mod, ModOwned};
+use crate::mapping::lookup;
+use crate::search::SearchEntry;
+
+// LDAP_CAP_ACTIVE_DIRECTORY_OID, advertised in supportedCapabilities.
+pub(crate) const AD_CAPABILITY_OID: &str = "1.2.840.113556.1.4.800";
+
+/// Method of changing a password with
+/// [`Ldap::change_password()`](struct.Ldap.html#method.change_password).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PasswordFlavor {
+    /// Password Modify extended operation ([RFC 3062](https://tools.ietf.org/html/rfc3062)).
+    Exop,
+    /// Modify of the Active Directory `unicodePwd` attribute.
+    ActiveDirectory,
+    /// Choose the method by the server capabilities: the extended operation if the server
+    /// supports it, otherwise the AD method if the server is Active Directory.
+    Auto,
+}
+
+impl PasswordFlavor {
+    // Resolve Auto according to the server capabilities. Returns None if no method is supported.
+    pub(crate) fn detect(caps: &ServerCapabilities, passmod_oid: &str) -> Option<PasswordFlavor> {
+        if caps.supports_exop(passmod_oid) {
+            Some(PasswordFlavor::Exop)
+        } else if caps.vendor() == ServerFlavor::ActiveDirectory {
+            Some(PasswordFlavor::ActiveDirectory)
+        } else {
+            None
+        }
+    }
+}
+
+// Modifications changing the AD password. A user changing their own password must
+// supply the old one, which is deleted while the new one is added; an administrator
+// resets the password by replacing the value.
+pub(crate) fn ad_password_mods(old: Option<&str>, new: &str) -> Vec<ModOwned> {
+    let attr = || AttrValue::from("unicodePwd");
+    let new = HashSet::from([AttrValue::ad_password(new)]);
+    match old {
+        Some(old) => vec![
+            Mod::Delete(attr(), HashSet::from([AttrValue::ad_password(old)])),
+            Mod::Add(attr(), new),
+        ],
+        None => vec![Mod::Replace(attr(), new)],
+    }
+}
+
+// Attributes read by Ldap::password_status(). The AD attributes must be named, since
+// AD doesn't support "+", and doesn't return constructed attributes unless asked.
+pub(crate) const STATUS_ATTRS: &[&str] = &[
+    "+",
+    "pwdChangedTime",
+    "pwdAccountLockedTime",
+    "pwdFailureTime",
+    "pwdReset",
+    "pwdPolicySubentry",
+    "pwdLastSet",
+    "lockoutTime",
+    "userAccountControl",
+    "msDS-User-Account-Control-Computed",
+    "msDS-UserPasswordExpiryTimeComputed",
+];
+
+// userAccountControl flags.
+const UF_LOCKOUT: u32 = 0x10;
+const UF_DONT_EXPIRE_PASSWD: u32 = 0x1_0000;
+
+/// Password and lockout state of an account.
+///
+/// The state is computed from the operational attributes of the OpenLDAP password policy
+/// overlay (`pwdChangedTime`, `pwdAccountLockedTime`, `pwdFailureTime`, `pwdReset`) or from
+/// the AD account attributes (`pwdLastSet`, `lockoutTime`, `userAccountControl` and its
+/// constructed counterpart, `msDS-UserPasswordExpiryTimeComputed`). Fields which can't be
+/// determined from the attributes are left at their default values.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PasswordStatus {
+    /// Whether the account is locked out, or `None` if the entry has none of the attributes
+    /// by which that could be determined.
+    pub locked: Option<bool>,
+    /// Time of the last password change.
+    pub last_changed: Option<SystemTime>,
+    /// Time when the password expires, if it does.
+    pub expires_at: Option<SystemTime>,
+    /// Whether the password must be changed before the account can be used normally: AD
+    /// `pwdLastSet` is zero, or OpenLDAP `pwdReset` is TRUE.
+    pub must_change: bool,
+    /// Times of the recent failed authentication attempts, as recorded by OpenLDAP.
+    pub failures: Vec<SystemTime>,
+    /// Attributes of the entry not used for computing the other fields.
+    pub attrs: HashMap<String, Vec<String>>,
+}
+
+impl PasswordStatus {
+    /// Compute the password status from an entry with the relevant operational attributes.
+    ///
+    /// The expiration time of an OpenLDAP password depends on the `pwdMaxAge` attribute of
+    /// the password policy, which isn't a part of the user entry;
+    /// [`Ldap::password_status()`](struct.Ldap.html#method.password_status) reads it from
+    /// the entry named by `pwdPolicySubentry`.
+    pub fn from_entry(entry: SearchEntry) -> PasswordStatus {
+        let mut attrs = entry.attrs;
+        let int = |val: Option<String>| val.and_then(|val| val.parse::<i64>().ok());
+        let mut status = PasswordStatus::default();
+        // Active Directory.
+        let pwd_last_set = int(take_first(&mut attrs, "pwdLastSet"));
+        let lockout_time = int(take_first(&mut attrs, "lockoutTime"));
+        let uac = int(take_first(&mut attrs, "userAccountControl"));
+        let uac_computed = int(take_first(&mut attrs, "msDS-User-Account-Control-Computed"));
+        let expiry = int(take_first(
+            &mut attrs,
+            "msDS-UserPasswordExpiryTimeComputed",
+        ));
+        if let Some(pwd_last_set) = pwd_last_set {
+            status.must_change = pwd_last_set == 0;
+            status.last_changed = filetime_to_unix(pwd_last_set);
+        }
+        status.locked = match (uac_computed, lockout_time) {
+            (Some(flags), _) => Some(flags as u32 & UF_LOCKOUT != 0),
+            (None, Some(time)) => Some(time > 0),
+            (None, None) => None,
+        };
+        let never_expires = uac.is_some_and(|flags| flags as u32 & UF_DONT_EXPIRE_PASSWD != 0);
+        if !never_expires {
+            status.expires_at = expiry.and_then(filetime_to_unix);
+        }
+        // OpenLDAP ppolicy.
+        if let Some(changed) = take_first(&mut attrs, "pwdChangedTime") {
+            status.last_changed = generalized_time(&changed);
+        }
+        let changed = status.last_changed.is_some();
+        if take_first(&mut attrs, "pwdAccountLockedTime").is_some() {
+            status.locked = Some(true);
+        }
+        let failures = take_values(&mut attrs, "pwdFailureTime");
+        let ppolicy =
+            changed || !failures.is_empty() || lookup(&attrs, "pwdPolicySubentry").is_some();
+        if ppolicy && status.locked.is_none() {
+            status.locked = Some(false);
+        }
+        status.failures = failures
+            .iter()
+            .filter_map(|time| generalized_time(time))
+            .collect();
+        if take_first(&mut attrs, "pwdReset")
+            .is_some_and(|reset| reset.eq_ignore_ascii_case("TRUE"))
+        {
+            status.must_change = true;
+        }
+        status.attrs = attrs;
+        status
+    }
+
+    // Set the expiration time from the OpenLDAP policy's pwdMaxAge, in seconds.
+    pub(crate) fn apply_max_age(&mut self, max_age: u64) {
+        if max_age > 0 && self.expires_at.is_none() {
+            self.expires_at = self
+                .last_changed
+                .and_then(|changed| changed.checked_add(Duration::from_secs(max_age)));
+        }
+    }
+}
+
+// Remove the values of an attribute from the map, matching the name case-insensitively.
+fn take_values(attrs: &mut HashMap<String, Vec<String>>, name: &str) -> Vec<String> {
+    let key = attrs
+        .keys()
+        .find(|key| key.eq_ignore_ascii_case(name))
+        .cloned();
+    key.and_then(|key| attrs.remove(&key)).unwrap_or_default()
+}
+
+fn take_first(attrs: &mut HashMap<String, Vec<String>>, name: &str) -> Option<String> {
+    take_values(attrs, name).into_iter().next()
+}
+
+// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+// Parse a GeneralizedTime value (RFC 4517, section 3.3.13). Minutes and seconds may be
+// omitted, and a fraction, which is ignored, may follow the last given element.
+pub(crate) fn generalized_time(val: &str) -> Option<SystemTime> {
+    let digits = val.bytes().take_while(u8::is_ascii_digit).count();
+    if !matches!(digits, 10 | 12 | 14) {
+        return None;
+    }
+    let num = |range: std::ops::Range<usize>| val.get(range)?.parse::<i64>().ok();
+    let (year, month, day, hour) = (num(0..4)?, num(4..6)?, num(6..8)?, num(8..10)?);
+    let minute = if digits >= 12 { num(10..12)? } else { 0 };
+    let second = if digits == 14 { num(12..14)? } else { 0 };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+    let mut rest = &val[digits..];
+    if let Some(frac) = rest.strip_prefix(['.', ',']) {
+        let len = frac.bytes().take_while(u8::is_ascii_digit).count();
+        if len == 0 {
+            return None;
+        }
+        rest = &frac[len..];
+    }
+    let offset = match rest.as_bytes() {
+        b"Z" => 0,
+        [sign @ (b'+' | b'-'), tz @ ..] if matches!(tz.len(), 2 | 4) => {
+            let tz = std::str::from_utf8(tz).ok()?;
+            if !tz.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            let hours: i64 = tz[..2].parse().ok()?;
+            let minutes: i64 = tz
+                .get(2..)
+                .filter(|m| !m.is_empty())
+                .map_or(Ok(0), str::parse)
+                .ok()?;
+            let offset = hours * 3600 + minutes * 60;
+            if *sign == b'+' {
+                offset
+            } else {
+                -offset
+            }
+        }
+        _ => return None,
+    };
+    let secs =
+        days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second - offset;
+    if secs >= 0 {
+        UNIX_EPOCH.checked_add(Duration::from_secs(secs as u64))
+    } else {
+        UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conn::LdapConnAsync;
+    use crate::fixtures::{self, message, result};
+    use crate::ldap::Ldap;
+    use crate::result::LdapError;
+
+    use tokio::sync::mpsc;
+
+    // Scripted server reporting the raw bytes of each request. A Search returns the root DSE
+    // with the given attribute, Modify and Extended operations succeed.
+    async fn server(
+        root_dse: (&'static str, &'static str),
+    ) -> (String, mpsc::UnboundedReceiver<Vec<u8>>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let url = fixtures::serve(move |req| {
+            let (msgid, id) = (req.msgid, req.op.id);
+            let _ = tx.send(req.raw);
+            match id {
+                3 => {
+                    let (attr, val) = root_dse;
+                    let dse = fixtures::entry("", &[(attr, &[val.as_bytes()])]);
+                    let mut reply = message(msgid, dse);
+                    reply.extend(message(msgid, result(5, 0)));
+                    reply
+                }
+                6 => message(msgid, result(7, 0)),
+                23 => message(msgid, result(24, 0)),
+                _ => vec![],
+            }
+        })
+        .await;
+        (url, rx)
+    }
+
+    async fn connect(
+        root_dse: (&'static str, &'static str),
+    ) -> (Ldap, mpsc::UnboundedReceiver<Vec<u8>>) {
+        let (url, rx) = server(root_dse).await;
+        let (conn, ldap) = LdapConnAsync::new(&url).await.expect("connection");
+        crate::drive!(conn);
+        (ldap, rx)
+    }
+
+    async fn requests(ldap: Ldap, mut rx: mpsc::UnboundedReceiver<Vec<u8>>) -> Vec<Vec<u8>> {
+        drop(ldap);
+        let mut reqs = vec![];
+        while let Some(req) = rx.recv().await {
+            reqs.push(req);
+        }
+        // Dropping the handle unbinds.
+        assert_eq!(reqs.pop().map(|req| req[5]), Some(0x42));
+        reqs
+    }
+
+    const NO_DSE: (&str, &str) = ("vendorName", "none");
+
+    fn entry(attrs: &[(&str, &[&str])]) -> SearchEntry {
+        SearchEntry {
+            dn: String::from("uid=test,dc=example,dc=org"),
+            attrs: attrs
+                .iter()
+                .map(|(attr, vals)| {
+                    (
+                        attr.to_string(),
+                        vals.iter().map(|val| val.to_string()).collect(),
+                    )
+                })
+                .collect(),
+            bin_attrs: HashMap::new(),
+            truncated: vec![],
+        }
+    }
+
+    fn at(secs: u64) -> Option<SystemTime> {
+        Some(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    // 2021-01-01T00:00:00Z.
+    const JAN_2021: u64 = 1_609_459_200;
+
+    #[test]
+    fn generalized_times() {
+        assert_eq!(generalized_time("20210101000000Z"), at(JAN_2021));
+        assert_eq!(generalized_time("202101010000Z"), at(JAN_2021));
+        assert_eq!(generalized_time("2021010100Z"), at(JAN_2021));
+        assert_eq!(generalized_time("20210101000000.123456Z"), at(JAN_2021));
+        assert_eq!(generalized_time("20210101020000+0200"), at(JAN_2021));
+        assert_eq!(generalized_time("20201231230000-01"), at(JAN_2021));
+        assert_eq!(generalized_time("20240229123456Z"), at(1_709_210_096));
+        assert!(generalized_time("000001010000Z").expect("time") < UNIX_EPOCH);
+        for bad in [
+            "",
+            "2021010100",
+            "20211301000000Z",
+            "20210101000000",
+            "20210101000000.Z",
+        ] {
+            assert_eq!(generalized_time(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn active_directory_status() {
+        let status = PasswordStatus::from_entry(entry(&[
+            ("pwdLastSet", &["132539328000000000"]),
+            ("lockoutTime", &["0"]),
+            ("userAccountControl", &["512"]),
+            (
+                "msDS-UserPasswordExpiryTimeComputed",
+                &["132617088000000000"],
+            ),
+            ("whenChanged", &["20210101000000.0Z"]),
+        ]));
+        assert_eq!(status.locked, Some(false));
+        assert_eq!(status.last_changed, at(JAN_2021));
+        assert_eq!(status.expires_at, at(JAN_2021 + 90 * 86_400));
+        assert!(!status.must_change);
+        assert_eq!(status.attrs.keys().collect::<Vec<_>>(), ["whenChanged"]);
+
+        let status = PasswordStatus::from_entry(entry(&[
+            ("pwdLastSet", &["0"]),
+            ("lockoutTime", &["132539328000000000"]),
+            ("msDS-User-Account-Control-Computed", &["0"]),
+            ("userAccountControl", &["66048"]),
+            ("msDS-UserPasswordExpiryTimeComputed", &["0"]),
+        ]));
+        assert!(status.must_change);
+        assert_eq!(status.last_changed, None);
+        assert_eq!(status.expires_at, None);
+        // The lockout has expired, as reported by the computed flags.
+        assert_eq!(status.locked, Some(false));
+    }
+
+    #[test]
+    fn openldap_status() {
+        let mut status = PasswordStatus::from_entry(entry(&[
+            ("pwdChangedTime", &["20210101000000Z"]),
+            ("pwdAccountLockedTime", &["20210102000000Z"]),
+            ("pwdFailureTime", &["20210102000000Z", "20210101235959.5Z"]),
+            (
+                "pwdPolicySubentry",
+                &["cn=default,ou=policies,dc=example,dc=org"],
+            ),
+        ]));
+        assert_eq!(status.locked, Some(true));
+        assert_eq!(status.last_changed, at(JAN_2021));
+        assert_eq!(
+            status.failures,
+            [
+                at(JAN_2021 + 86_400).unwrap(),
+                at(JAN_2021 + 86_399).unwrap()
+            ]
+        );
+        assert!(!status.must_change);
+        assert!(status.attrs.contains_key("pwdPolicySubentry"));
+        status.apply_max_age(86_400);
+        assert_eq!(status.expires_at, at(JAN_2021 + 86_400));
+
+        let status = PasswordStatus::from_entry(entry(&[
+            ("pwdChangedTime", &["20210101000000Z"]),
+            ("pwdReset", &["TRUE"]),
+        ]));
+        assert_eq!(status.locked, Some(false));
+        assert!(status.must_change);
+
+        let status = PasswordStatus::from_entry(entry(&[("entryUUID", &["x"])]));
+        assert_eq!(status.locked, None);
+        assert_eq!(status.last_changed, None);
+    }
+
+    #[tokio::test]
+    async fn active_directory_change() {
+        let (mut ldap, rx) = connect(NO_DSE).await;
+        ldap.has_tls = true;
+        let res = ldap
+            .change_password("cn=u", Some("a"), "b", PasswordFlavor::ActiveDirectory)
+            .await
+            .expect("changed");
+        assert_eq!(res.rc, 0);
+        let reqs = requests(ldap, rx).await;
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            0x30, 0x47, 0x02, 0x01, 0x01,
+            0x66, 0x42, 0x04, 0x04, b'c', b'n', b'=', b'u',
+            0x30, 0x3a,
+            0x30, 0x1b, 0x0a, 0x01, 0x01,
+            0x30, 0x16, 0x04, 0x0a, b'u', b'n', b'i', b'c', b'o', b'd', b'e', b'P', b'w', b'd',
+            0x31, 0x08, 0x04, 0x06, b'"', 0, b'a', 0, b'"', 0,
+            0x30, 0x1b, 0x0a, 0x01, 0x00,
+            0x30, 0x16, 0x04, 0x0a, b'u', b'n', b'i', b'c', b'o', b'd', b'e', b'P', b'w', b'd',
+            0x31, 0x08, 0x04, 0x06, b'"', 0, b'b', 0, b'"', 0,
+        ];
+        assert_eq!(reqs[0], expected);
+    }
+
+    #[tokio::test]
+    async fn active_directory_reset() {
+        let (mut ldap, rx) = connect(NO_DSE).await;
+        ldap.has_tls = true;
+        ldap.change_password("cn=u", None, "b", PasswordFlavor::ActiveDirectory)
+            .await
+            .expect("changed");
+        let reqs = requests(ldap, rx).await;
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            0x30, 0x2a, 0x02, 0x01, 0x01,
+            0x66, 0x25, 0x04, 0x04, b'c', b'n', b'=', b'u',
+            0x30, 0x1d,
+            0x30, 0x1b, 0x0a, 0x01, 0x02,
+            0x30, 0x16, 0x04, 0x0a, b'u', b'n', b'i', b'c', b'o', b'd', b'e', b'P', b'w', b'd',
+            0x31, 0x08, 0x04, 0x06, b'"', 0, b'b', 0, b'"', 0,
+        ];
+        assert_eq!(reqs[0], expected);
+    }
+
+    #[tokio::test]
+    async fn active_directory_requires_tls() {
+        let (mut ldap, rx) = connect(NO_DSE).await;
+        let res = ldap
+            .change_password("cn=u", None, "b", PasswordFlavor::ActiveDirectory)
+            .await;
+        assert!(matches!(res, Err(LdapError::TlsRequired)));
+        assert!(requests(ldap, rx).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn password_modify_exop() {
+        let (mut ldap, rx) = connect(NO_DSE).await;
+        let res = ldap
+            .change_password("cn=u", Some("a"), "b", PasswordFlavor::Exop)
+            .await
+            .expect("changed");
+        assert_eq!(res.rc, 0);
+        let reqs = requests(ldap, rx).await;
+        let mut expected = vec![0x30, 0x2e, 0x02, 0x01, 0x01, 0x77, 0x29, 0x80, 0x17];
+        expected.extend(b"1.3.6.1.4.1.4203.1.11.1");
+        expected.extend([0x81, 0x0e, 0x30, 0x0c, 0x80, 0x04]);
+        expected.extend(b"cn=u");
+        expected.extend([0x81, 0x01, b'a', 0x82, 0x01, b'b']);
+        assert_eq!(reqs[0], expected);
+    }
+
+    #[tokio::test]
+    async fn auto_detection() {
+        let (mut ldap, rx) = connect(("supportedExtension", "1.3.6.1.4.1.4203.1.11.1")).await;
+        ldap.change_password("cn=u", None, "b", PasswordFlavor::Auto)
+            .await
+            .expect("changed");
+        let reqs = requests(ldap, rx).await;
+        assert_eq!(reqs.len(), 2);
+        assert_eq!(reqs[1][5], 0x77);
+
+        let (mut ldap, rx) = connect(("supportedCapabilities", AD_CAPABILITY_OID)).await;
+        ldap.has_tls = true;
+        ldap.change_password("cn=u", None, "b", PasswordFlavor::Auto)
+            .await
+            .expect("changed");
+        let reqs = requests(ldap, rx).await;
+        assert_eq!(reqs.len(), 2);
+        assert_eq!(reqs[1][5], 0x66);
+
+        let (mut ldap, _rx) = connect(NO_DSE).await;
+        let res = ldap
+            .change_password("cn=u", None, "b", PasswordFlavor::Auto)
+            .await;
+        assert!(matches!(res, Err(LdapError::PasswordChangeUnsupported)));
+    }
+}
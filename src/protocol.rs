@@ -1,28 +1,36 @@
+use std::fmt;
 use std::io;
 #[cfg(feature = "gssapi")]
-use std::sync::RwLock;
-#[cfg(feature = "gssapi")]
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 
 use crate::controls::{Control, RawControl};
 use crate::controls_impl::{build_tag, parse_controls};
+use crate::exop_impl::PASSMOD_OID;
+use crate::result::LdapError;
 use crate::search::SearchItem;
+use crate::stats::ConnStats;
 use crate::RequestId;
 
 use lber::common::TagClass;
-use lber::parse::parse_uint;
+use lber::debug::dump_redacted;
+use lber::parse::{parse_tag_bytes, parse_tag_len};
 use lber::structure::{StructureTag, PL};
 use lber::structures::{ASNTag, Integer, Sequence, Tag};
 use lber::universal::Types;
 use lber::write;
 
-use bytes::{Buf, BytesMut};
+use bytes::{Bytes, BytesMut};
 #[cfg(feature = "gssapi")]
 use cross_krb5::{ClientCtx, K5Ctx};
+use tokio::io::AsyncRead;
 use tokio::sync::{mpsc, oneshot};
 use tokio_util::codec::{Decoder, Encoder};
 
 pub(crate) struct LdapCodec {
+    pub(crate) max_ber_size: usize,
+    pub(crate) wire_trace: bool,
+    pub(crate) keep_raw: bool,
+    pub(crate) stats: ConnStats,
     #[cfg(feature = "gssapi")]
     pub(crate) has_decoded_data: bool,
     #[cfg(feature = "gssapi")]
@@ -32,7 +40,7 @@ pub(crate) struct LdapCodec {
 }
 
 pub(crate) type MaybeControls = Option<Vec<RawControl>>;
-pub(crate) type ItemSender = mpsc::UnboundedSender<(SearchItem, Vec<Control>)>;
+pub(crate) type ItemSender = mpsc::Sender<(SearchItem, Vec<Control>)>;
 pub(crate) type ResultSender = oneshot::Sender<(Tag, Vec<Control>)>;
 
 #[derive(Debug)]
@@ -47,20 +55,258 @@ pub enum LdapOp {
     Search(ItemSender),
     Abandon(RequestId),
     Unbind,
+    Streamed(Box<StreamedRequest>),
+}
+
+pub(crate) type BoxedReader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// Piece of a protocol message which is written to the socket without being
+/// fully assembled in memory.
+pub(crate) enum Segment {
+    Bytes(Vec<u8>),
+    Reader(u64, BoxedReader),
+}
+
+impl fmt::Debug for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Segment::Bytes(bytes) => f.debug_tuple("Bytes").field(&bytes.len()).finish(),
+            Segment::Reader(len, _) => f.debug_tuple("Reader").field(len).finish(),
+        }
+    }
+}
+
+/// BER encoding split into segments, with the total length known in advance.
+#[derive(Debug, Default)]
+pub(crate) struct Segments {
+    pub(crate) len: u64,
+    pub(crate) parts: Vec<Segment>,
+}
+
+impl Segments {
+    pub(crate) fn push_bytes(&mut self, bytes: &[u8]) {
+        self.len += bytes.len() as u64;
+        if let Some(Segment::Bytes(last)) = self.parts.last_mut() {
+            last.extend_from_slice(bytes);
+        } else {
+            self.parts.push(Segment::Bytes(bytes.to_vec()));
+        }
+    }
+
+    pub(crate) fn push_tag(&mut self, tag: Tag) {
+        let mut buf = BytesMut::new();
+        write::encode_into(&mut buf, tag.into_structure()).expect("encoded");
+        self.push_bytes(&buf);
+    }
+
+    /// Append an OCTET STRING whose contents are read from `reader`.
+    pub(crate) fn push_reader(&mut self, len: u64, reader: BoxedReader) {
+        self.push_bytes(&ber_header(0x04, len));
+        self.len += len;
+        self.parts.push(Segment::Reader(len, reader));
+    }
+
+    pub(crate) fn append(&mut self, other: Segments) {
+        for part in other.parts {
+            match part {
+                Segment::Bytes(bytes) => self.push_bytes(&bytes),
+                Segment::Reader(len, reader) => {
+                    self.len += len;
+                    self.parts.push(Segment::Reader(len, reader));
+                }
+            }
+        }
+    }
+
+    /// Turn the segments into the contents of a constructed element identified by
+    /// the single-byte `tag`.
+    pub(crate) fn wrap(self, tag: u8) -> Segments {
+        let mut outer = Segments::default();
+        outer.push_bytes(&ber_header(tag, self.len));
+        outer.append(self);
+        outer
+    }
+}
+
+fn ber_header(tag: u8, len: u64) -> Vec<u8> {
+    let mut header = vec![tag];
+    if len < 0x80 {
+        header.push(len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let skip = bytes.iter().take_while(|&&b| b == 0).count();
+        header.push(0x80 | (bytes.len() - skip) as u8);
+        header.extend_from_slice(&bytes[skip..]);
+    }
+    header
+}
+
+/// Request whose encoding contains reader segments, which the connection task writes
+/// directly to the socket. The outcome of writing is reported through `status` before
+/// the response is awaited.
+#[derive(Debug)]
+pub struct StreamedRequest {
+    pub(crate) op: Segments,
+    pub(crate) status: oneshot::Sender<Result<(), LdapError>>,
+}
+
+impl StreamedRequest {
+    /// Build the complete LDAPMessage around the protocol op, returning it together
+    /// with the status sender.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn into_message(
+        self,
+        id: RequestId,
+        controls: MaybeControls,
+    ) -> (Segments, oneshot::Sender<Result<(), LdapError>>) {
+        let mut msg = Segments::default();
+        msg.push_tag(Tag::Integer(Integer {
+            inner: id as i64,
+            ..Default::default()
+        }));
+        msg.append(self.op);
+        if let Some(controls) = controls {
+            msg.push_tag(Tag::StructureTag(StructureTag {
+                id: 0,
+                class: TagClass::Context,
+                payload: PL::C(controls.into_iter().map(build_tag).collect()),
+            }));
+        }
+        (msg.wrap(0x30), self.status)
+    }
+}
+
+/// Log target of the wire trace.
+pub(crate) const WIRE_TARGET: &str = "ldap3::wire";
+
+// Render the message for the wire trace. The contents of the tags holding the
+// credentials of Bind and Password Modify requests are not shown.
+fn dump_message(msg: &StructureTag) -> String {
+    let mut msg = msg.clone();
+    let mut hidden: Vec<Vec<usize>> = vec![];
+    if let PL::C(ref mut parts) = msg.payload {
+        if let Some(StructureTag {
+            class: TagClass::Application,
+            id,
+            payload: PL::C(op),
+        }) = parts.get_mut(1)
+        {
+            let passmod = PL::P(Bytes::from_static(PASSMOD_OID.as_bytes()));
+            match *id {
+                // BindRequest: simple password or SASL credentials.
+                0 => match op.get(2) {
+                    Some(StructureTag {
+                        class: TagClass::Context,
+                        id: 3,
+                        payload: PL::C(sasl),
+                    }) if sasl.len() > 1 => hidden.push(vec![1, 2, 1]),
+                    Some(StructureTag {
+                        class: TagClass::Context,
+                        id: 3,
+                        ..
+                    }) => (),
+                    Some(_) => hidden.push(vec![1, 2]),
+                    None => (),
+                },
+                // ExtendedRequest: the old and new passwords of Password Modify. The
+                // request value is shown as the structure it encodes, so that the user
+                // identity remains visible.
+                23 if op.first().map(|name| name.payload == passmod) == Some(true) => {
+                    if let Some(value) = op.get_mut(1) {
+                        match passmod_fields(&value.payload) {
+                            Some(fields) => {
+                                for (n, field) in fields.iter().enumerate() {
+                                    if field.class == TagClass::Context
+                                        && (field.id == 1 || field.id == 2)
+                                    {
+                                        hidden.push(vec![1, 1, 0, n]);
+                                    }
+                                }
+                                value.payload = PL::C(vec![StructureTag {
+                                    class: TagClass::Universal,
+                                    id: Types::Sequence as u64,
+                                    payload: PL::C(fields),
+                                }]);
+                            }
+                            None => hidden.push(vec![1, 1]),
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+    let mut out = String::new();
+    let _ = dump_redacted(&msg, &|path| hidden.iter().any(|h| h == path), &mut out);
+    out.truncate(out.trim_end().len());
+    out
+}
+
+// Elements of the PasswdModifyRequestValue sequence, if the value can be parsed.
+fn passmod_fields(value: &PL) -> Option<Vec<StructureTag>> {
+    let raw = match value {
+        PL::P(raw) => raw,
+        PL::C(_) => return None,
+    };
+    match parse_tag_bytes(raw) {
+        Ok(([], tag)) => tag
+            .match_class(TagClass::Universal)
+            .and_then(|t| t.match_id(Types::Sequence as u64))
+            .and_then(|t| t.expect_constructed()),
+        _ => None,
+    }
+}
+
+// The error is carried inside the I/O error, so that the connection
+// can return it as is.
+fn frame_too_large(size: usize, limit: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        LdapError::DecodingFrameTooLarge { size, limit },
+    )
 }
 
 #[allow(clippy::type_complexity)]
-fn decode_inner(buf: &mut BytesMut) -> Result<Option<(RequestId, (Tag, Vec<Control>))>, io::Error> {
-    let decoding_error = io::Error::new(io::ErrorKind::Other, "decoding error");
-    let mut parser = lber::Parser::new();
-    let binding = parser.parse(buf);
-    let (i, tag) = match binding {
+// Encoding of the protocolOp element of a complete message, which follows the header
+// of the envelope and the message ID. The result shares the memory of the frame.
+fn protocol_op_bytes(frame: &Bytes) -> Option<Bytes> {
+    let (content, _) = parse_tag_len(frame).ok()?;
+    let (_, id_len) = parse_tag_len(content).ok()?;
+    let start = frame.len() - content.len() + id_len;
+    let (_, op_len) = parse_tag_len(frame.get(start..)?).ok()?;
+    let end = start
+        .checked_add(op_len)
+        .filter(|&end| end <= frame.len())?;
+    Some(frame.slice(start..end))
+}
+
+fn decode_inner(
+    buf: &mut BytesMut,
+    max_ber_size: usize,
+    keep_raw: bool,
+    trace: Option<log::Level>,
+) -> Result<Option<<LdapCodec as Decoder>::Item>, io::Error> {
+    let decoding_error = io::Error::other("decoding error");
+    let len = match parse_tag_len(buf) {
         Err(e) if e.is_incomplete() => return Ok(None),
         Err(_e) => return Err(decoding_error),
-        Ok((i, ref tag)) => (i, tag),
+        Ok((_, len)) => len,
+    };
+    if len > max_ber_size {
+        return Err(frame_too_large(len, max_ber_size));
+    }
+    if buf.len() < len {
+        buf.reserve(len - buf.len());
+        return Ok(None);
+    }
+    // Split off the complete message, so that the primitive values of the parsed
+    // structure can share its memory instead of being copied out.
+    let frame = buf.split_to(len).freeze();
+    let tag = match parse_tag_bytes(&frame) {
+        Ok((_, tag)) => tag,
+        Err(_e) => return Err(decoding_error),
     };
-    buf.advance(buf.len() - i.len());
-    let tag = tag.clone();
+    let dumped = trace.map(|level| (level, dump_message(&tag)));
     let mut tags = match tag
         .match_id(Types::Sequence as u64)
         .and_then(|t| t.expect_constructed())
@@ -85,8 +331,12 @@ fn decode_inner(buf: &mut BytesMut) -> Result<Option<(RequestId, (Tag, Vec<Contr
             // Unsolicited Notification should be part of the ExtendedResponse sequence
             // but AD puts it outside, where the optional controls belong. This confuses
             // our parser, which doesn't expect the extra sequence element at the end
-            // and crashes. This match arm thus ignores the element.
-            maybe_controls = tags.pop().expect("element");
+            // and crashes. This match arm thus moves the element back into the response,
+            // where it's needed to recognize the notice.
+            let name = std::mem::replace(&mut maybe_controls, tags.pop().expect("element"));
+            if let PL::C(ref mut inner) = maybe_controls.payload {
+                inner.push(name);
+            }
             false
         }
         _ => false,
@@ -100,40 +350,79 @@ fn decode_inner(buf: &mut BytesMut) -> Result<Option<(RequestId, (Tag, Vec<Contr
         Some(controls) => parse_controls(controls),
         None => vec![],
     };
-    let msgid = match parse_uint(
-        tags.pop()
-            .expect("element")
-            .match_class(TagClass::Universal)
-            .and_then(|t| t.match_id(Types::Integer as u64))
-            .and_then(|t| t.expect_primitive())
-            .expect("message id")
-            .as_slice(),
-    ) {
-        Ok((_, id)) => id as i32,
+    let msgid = match tags
+        .pop()
+        .expect("element")
+        .match_class(TagClass::Universal)
+        .and_then(|t| t.match_id(Types::Integer as u64))
+        .and_then(|t| t.expect_i64())
+        .map(RequestId::try_from)
+    {
+        Some(Ok(id)) if id >= 0 => id,
         _ => return Err(decoding_error),
     };
-    Ok(Some((msgid, (Tag::StructureTag(protoop), controls))))
+    let raw = if keep_raw {
+        match protocol_op_bytes(&frame) {
+            Some(raw) => Some(raw),
+            None => return Err(decoding_error),
+        }
+    } else {
+        None
+    };
+    if let Some((level, dumped)) = dumped {
+        log!(target: WIRE_TARGET, level, "received message, id {}:\n{}", msgid, dumped);
+    }
+    Ok(Some((msgid, (Tag::StructureTag(protoop), controls), raw)))
+}
+
+impl LdapCodec {
+    // Level at which the messages are logged, if they are. With the wire trace
+    // turned on in the connection settings it's Debug, otherwise Trace.
+    fn trace_level(&self) -> Option<log::Level> {
+        let level = if self.wire_trace {
+            log::Level::Debug
+        } else {
+            log::Level::Trace
+        };
+        log_enabled!(target: WIRE_TARGET, level).then_some(level)
+    }
 }
 
 impl Decoder for LdapCodec {
-    type Item = (RequestId, (Tag, Vec<Control>));
+    // The third element is the encoding of the protocol op, if raw frames are kept.
+    type Item = (RequestId, (Tag, Vec<Control>), Option<Bytes>);
     type Error = io::Error;
 
-    #[cfg(not(feature = "gssapi"))]
     fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        decode_inner(buf)
+        let before = buf.len();
+        let res = self.decode_message(buf);
+        self.stats.read(before.saturating_sub(buf.len()));
+        res
+    }
+}
+
+impl LdapCodec {
+    #[cfg(not(feature = "gssapi"))]
+    fn decode_message(
+        &mut self,
+        buf: &mut BytesMut,
+    ) -> Result<Option<<Self as Decoder>::Item>, io::Error> {
+        decode_inner(buf, self.max_ber_size, self.keep_raw, self.trace_level())
     }
 
     #[cfg(feature = "gssapi")]
-    fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+    fn decode_message(
+        &mut self,
+        buf: &mut BytesMut,
+    ) -> Result<Option<<Self as Decoder>::Item>, io::Error> {
         const U32_SIZE: usize = std::mem::size_of::<u32>();
 
         let sasl_wrap = { self.sasl_param.read().expect("sasl param").0 };
         if !sasl_wrap || buf.is_empty() {
-            return decode_inner(buf);
+            return decode_inner(buf, self.max_ber_size, self.keep_raw, self.trace_level());
         }
         if self.has_decoded_data {
-            let res = decode_inner(buf);
+            let res = decode_inner(buf, self.max_ber_size, self.keep_raw, self.trace_level());
             if res.is_ok() && buf.is_empty() {
                 self.has_decoded_data = false;
             }
@@ -143,6 +432,9 @@ impl Decoder for LdapCodec {
             return Err(io::Error::new(io::ErrorKind::Other, "invalid SASL buffer"));
         }
         let sasl_len = u32::from_be_bytes(buf[0..U32_SIZE].try_into().unwrap());
+        if sasl_len as usize > self.max_ber_size {
+            return Err(frame_too_large(sasl_len as usize, self.max_ber_size));
+        }
         if buf.len() - U32_SIZE < sasl_len as usize {
             return Ok(None);
         }
@@ -152,7 +444,12 @@ impl Decoder for LdapCodec {
         let mut decoded = client_ctx.unwrap_iov(sasl_len as usize, buf).map_err(|e| {
             io::Error::new(io::ErrorKind::Other, format!("gss_unwrap error: {:#}", e))
         })?;
-        let res = decode_inner(&mut decoded);
+        let res = decode_inner(
+            &mut decoded,
+            self.max_ber_size,
+            self.keep_raw,
+            self.trace_level(),
+        );
         if res.is_ok() && !decoded.is_empty() && buf.is_empty() {
             buf.extend(decoded);
             self.has_decoded_data = true;
@@ -239,7 +536,241 @@ impl Encoder<(RequestId, Tag, MaybeControls)> for LdapCodec {
             })
             .into_structure()
         };
+        if let Some(level) = self.trace_level() {
+            let dumped = dump_message(&outstruct);
+            log!(target: WIRE_TARGET, level, "sending message, id {}:\n{}", id, dumped);
+        }
+        let before = into.len();
         maybe_wrap(self, outstruct, into)?;
+        self.stats.written(into.len() - before);
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::exop::{Exop, PasswordModify};
+    use crate::exop_impl::construct_exop;
+    use lber::structures::{Enumerated, OctetString};
+
+    fn codec(max_ber_size: usize) -> LdapCodec {
+        LdapCodec {
+            max_ber_size,
+            wire_trace: false,
+            keep_raw: false,
+            stats: ConnStats::new(),
+            #[cfg(feature = "gssapi")]
+            has_decoded_data: false,
+            #[cfg(feature = "gssapi")]
+            sasl_param: Arc::new(RwLock::new((false, 0))),
+            #[cfg(feature = "gssapi")]
+            client_ctx: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    // SearchResultDone with a diagnostic message of the given length.
+    fn search_done(diag_len: usize) -> BytesMut {
+        let msg = Tag::Sequence(Sequence {
+            inner: vec![
+                Tag::Integer(Integer {
+                    inner: 2,
+                    ..Default::default()
+                }),
+                Tag::Sequence(Sequence {
+                    id: 5,
+                    class: TagClass::Application,
+                    inner: vec![
+                        Tag::Enumerated(Enumerated {
+                            inner: 0,
+                            ..Default::default()
+                        }),
+                        Tag::OctetString(OctetString::default()),
+                        Tag::OctetString(OctetString {
+                            inner: vec![b'x'; diag_len],
+                            ..Default::default()
+                        }),
+                    ],
+                }),
+            ],
+            ..Default::default()
+        });
+        let mut buf = BytesMut::new();
+        write::encode_into(&mut buf, msg.into_structure()).expect("encoded");
+        buf
+    }
+
+    fn too_large(err: io::Error) -> (usize, usize) {
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        match err.into_inner().map(|e| e.downcast::<LdapError>()) {
+            Some(Ok(e)) => match *e {
+                LdapError::DecodingFrameTooLarge { size, limit } => (size, limit),
+                e => panic!("unexpected error: {}", e),
+            },
+            _ => panic!("no inner LdapError"),
+        }
+    }
+
+    #[test]
+    fn huge_length_header() {
+        let mut buf = BytesMut::from(&[0x30, 0x84, 0x7f, 0xff, 0xff, 0xff, 0x02, 0x01][..]);
+        let err = codec(8 * 1024 * 1024).decode(&mut buf).unwrap_err();
+        assert_eq!(too_large(err), (0x7fff_ffff + 6, 8 * 1024 * 1024));
+    }
+
+    #[test]
+    fn frame_at_limit() {
+        let mut buf = search_done(64 * 1024);
+        let len = buf.len();
+        let (id, (tag, _), _) = codec(len)
+            .decode(&mut buf)
+            .expect("decoded")
+            .expect("frame");
+        assert_eq!(id, 2);
+        assert!(matches!(tag, Tag::StructureTag(StructureTag { id: 5, .. })));
+        assert!(buf.is_empty());
+
+        let mut buf = search_done(64 * 1024);
+        let err = codec(len - 1).decode(&mut buf).unwrap_err();
+        assert_eq!(too_large(err), (len, len - 1));
+    }
+
+    #[test]
+    fn raw_protocol_op() {
+        let mut buf = search_done(16);
+        let whole = buf.clone().freeze();
+        let (_, (tag, _), raw) = codec(1024)
+            .decode(&mut buf)
+            .expect("decoded")
+            .expect("frame");
+        assert!(raw.is_none());
+
+        let mut buf = BytesMut::from(&whole[..]);
+        let range = buf.as_ptr_range();
+        let mut keeping = codec(1024);
+        keeping.keep_raw = true;
+        let (_, _, raw) = keeping.decode(&mut buf).expect("decoded").expect("frame");
+        let raw = raw.expect("raw bytes");
+        // Without controls, the SearchResultDone is the tail of the message.
+        assert_eq!(raw[0], 0x65);
+        assert_eq!(&raw[..], &whole[whole.len() - raw.len()..]);
+        assert!(range.contains(&raw.as_ptr()));
+        let Tag::StructureTag(tag) = tag else {
+            panic!("structure tag");
+        };
+        assert_eq!(parse_tag_bytes(&raw).expect("parsed").1, tag);
+    }
+
+    #[test]
+    fn message_id_encoding() {
+        // Message id 2 with a redundant leading zero byte.
+        let mut buf = BytesMut::from(
+            &[
+                0x30, 0x0d, 0x02, 0x02, 0x00, 0x02, 0x65, 0x07, 0x0a, 0x01, 0x00, 0x04, 0x00, 0x04,
+                0x00,
+            ][..],
+        );
+        let (id, _, _) = codec(1024)
+            .decode(&mut buf)
+            .expect("decoded")
+            .expect("frame");
+        assert_eq!(id, 2);
+
+        // Message id -1.
+        let mut buf = BytesMut::from(
+            &[
+                0x30, 0x0c, 0x02, 0x01, 0xff, 0x65, 0x07, 0x0a, 0x01, 0x00, 0x04, 0x00, 0x04, 0x00,
+            ][..],
+        );
+        assert!(codec(1024).decode(&mut buf).is_err());
+    }
+
+    fn message(id: u64, op: Vec<Tag>) -> StructureTag {
+        Tag::Sequence(Sequence {
+            inner: vec![
+                Tag::Integer(Integer {
+                    inner: 1,
+                    ..Default::default()
+                }),
+                Tag::Sequence(Sequence {
+                    id,
+                    class: TagClass::Application,
+                    inner: op,
+                }),
+            ],
+            ..Default::default()
+        })
+        .into_structure()
+    }
+
+    fn bind_message(auth: Tag) -> StructureTag {
+        message(
+            0,
+            vec![
+                Tag::Integer(Integer {
+                    inner: 3,
+                    ..Default::default()
+                }),
+                Tag::OctetString(OctetString {
+                    inner: b"cn=admin".to_vec(),
+                    ..Default::default()
+                }),
+                auth,
+            ],
+        )
+    }
+
+    #[test]
+    fn dump_hides_credentials() {
+        let simple = bind_message(Tag::OctetString(OctetString {
+            id: 0,
+            class: TagClass::Context,
+            inner: b"secret".to_vec(),
+        }));
+        let dumped = dump_message(&simple);
+        assert!(!dumped.contains("secret"));
+        assert!(dumped.ends_with("[CONTEXT 0], <redacted, 6 bytes>"));
+        assert!(dumped.contains("|cn=admin|"));
+
+        let sasl = bind_message(Tag::Sequence(Sequence {
+            id: 3,
+            class: TagClass::Context,
+            inner: vec![
+                Tag::OctetString(OctetString {
+                    inner: b"PLAIN".to_vec(),
+                    ..Default::default()
+                }),
+                Tag::OctetString(OctetString {
+                    inner: b"\0admin\0secret".to_vec(),
+                    ..Default::default()
+                }),
+            ],
+        }));
+        let dumped = dump_message(&sasl);
+        assert!(dumped.contains("|PLAIN|"));
+        assert!(!dumped.contains("secret"));
+        assert!(dumped.ends_with("[UNIVERSAL 4] OCTET STRING, <redacted, 13 bytes>"));
+    }
+
+    #[test]
+    fn dump_hides_passwords() {
+        let passmod = PasswordModify {
+            user_id: Some("uid=jdoe"),
+            old_pass: Some("oldsecret"),
+            new_pass: Some("newsecret"),
+        };
+        let dumped = dump_message(&message(23, construct_exop(passmod.into())));
+        assert!(!dumped.contains("secret"));
+        assert!(dumped.contains("|uid=jdoe|"));
+        assert!(dumped.contains("    [CONTEXT 1], <redacted, 9 bytes>"));
+        assert!(dumped.ends_with("    [CONTEXT 2], <redacted, 9 bytes>"));
+
+        // Other extended operations are shown in full.
+        let whoami = Exop {
+            name: Some(String::from("1.3.6.1.4.1.4203.1.11.3")),
+            val: Some(b"secret".to_vec()),
+        };
+        let dumped = dump_message(&message(23, construct_exop(whoami)));
+        assert!(dumped.contains("|secret|"));
+    }
+}
@@ -0,0 +1,72 @@
+use std::time::{Duration, Instant};
+
+// Token bucket for limiting the rate of events, such as log messages. The bucket
+// starts full, and is refilled continuously, so that `capacity` tokens are added
+// over each `period`.
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    per_sec: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(capacity: u32, period: Duration) -> Self {
+        let capacity = capacity as f64;
+        TokenBucket {
+            capacity,
+            tokens: capacity,
+            per_sec: capacity / period.as_secs_f64(),
+            last: Instant::now(),
+        }
+    }
+
+    // Take a token if one is available.
+    pub(crate) fn take(&mut self) -> bool {
+        self.take_at(Instant::now())
+    }
+
+    fn take_at(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.last = self.last.max(now);
+        self.tokens = (self.tokens + elapsed * self.per_sec).min(self.capacity);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn refill() {
+        let mut bucket = TokenBucket::new(3, Duration::from_secs(60));
+        let start = bucket.last;
+        let taken = (0..5).filter(|_| bucket.take_at(start)).count();
+        assert_eq!(taken, 3);
+        // One token every 20 seconds.
+        assert!(!bucket.take_at(start + Duration::from_secs(19)));
+        assert!(bucket.take_at(start + Duration::from_secs(20)));
+        assert!(!bucket.take_at(start + Duration::from_secs(21)));
+        // A long pause doesn't accumulate more than the capacity.
+        let later = start + Duration::from_secs(3600);
+        let taken = (0..5).filter(|_| bucket.take_at(later)).count();
+        assert_eq!(taken, 3);
+        // Time going backwards is ignored.
+        assert!(!bucket.take_at(start));
+    }
+
+    #[test]
+    fn empty() {
+        let mut bucket = TokenBucket::new(0, Duration::from_secs(60));
+        let start = bucket.last;
+        assert!(!bucket.take_at(start));
+        assert!(!bucket.take_at(start + Duration::from_secs(3600)));
+    }
+}
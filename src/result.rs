@@ -8,25 +8,28 @@
 use std::error::Error;
 use std::fmt;
 use std::io;
+use std::net::SocketAddr;
 use std::result::Result as StdResult;
+use std::sync::Arc;
 
-use crate::controls::Control;
+use crate::cancel::CancelCause;
+use crate::controls::{Control, PasswordExpiry};
+use crate::controls_impl::password_expiry;
 use crate::exop::Exop;
 use crate::ldap::SaslCreds;
+use crate::mapping::{EntryMapError, FromEntry};
 use crate::protocol::MiscSender;
 use crate::protocol::{LdapOp, MaybeControls, ResultSender};
 use crate::search::parse_refs;
-use crate::search::ResultEntry;
+use crate::search::{ResultEntry, SearchEntry};
 use crate::RequestId;
 
 use lber::common::TagClass;
-use lber::parse::parse_uint;
 use lber::structures::Tag;
 use lber::universal::Types;
 
 use thiserror::Error;
 use tokio::sync::{mpsc, oneshot};
-use tokio::time;
 
 /// Type alias for the standard `Result` with the fixed `LdapError` error part.
 pub type Result<T> = std::result::Result<T, LdapError>;
@@ -42,6 +45,10 @@ pub enum LdapError {
     #[error("the port must be empty in the ldapi scheme")]
     PortInUnixPath,
 
+    /// A `ldapi://` URL contains a query string, which isn't used for connecting.
+    #[error("the query must be empty in the ldapi scheme")]
+    QueryInUnixPath,
+
     /// Encapsulated I/O error.
     #[error("I/O error: {source}")]
     Io {
@@ -49,13 +56,39 @@ pub enum LdapError {
         source: io::Error,
     },
 
-    /// Error while sending an operation to the connection handler.
-    #[error("op send error: {source}")]
-    OpSend {
-        #[from]
-        source: mpsc::error::SendError<(RequestId, LdapOp, Tag, MaybeControls, ResultSender)>,
+    /// Connection to the server couldn't be established. The `host` is the host and port
+    /// from the URL, or the socket path for an `ldapi://` URL. The `addrs` are all resolved
+    /// addresses which were tried, empty if name resolution failed or for a Unix domain
+    /// socket. The error is the one from the last failed attempt.
+    #[error("unable to connect to {}: {source}", display_target(host, addrs))]
+    ConnectFailed {
+        host: String,
+        addrs: Vec<SocketAddr>,
+        source: io::Error,
     },
 
+    /// Operation failed because of a connection-level error, e.g., the connection handler
+    /// terminating. The `op` is the name of the operation and `id` its message ID. The
+    /// underlying error is the `source`, which is also returned by `Error::source()`.
+    ///
+    /// __Note:__ before 0.12, such errors were returned as they were. Errors like
+    /// `EndOfStream`, `OpSend`, `ResultRecv` and `Io`, when they interrupt an operation,
+    /// now come wrapped in this variant, so code which matches on them must look at the
+    /// source instead, e.g. `LdapError::Operation { source, .. } if matches!(*source,
+    /// LdapError::EndOfStream)`. A Notice of Disconnection and a failed TLS upgrade are
+    /// not wrapped.
+    #[error("{op} operation (msgid {id}) failed")]
+    Operation {
+        op: &'static str,
+        id: RequestId,
+        source: Box<LdapError>,
+    },
+
+    /// Error while sending an operation to the connection handler. The unsent operation
+    /// isn't kept, since it may hold the reader of a streamed value.
+    #[error("op send error: {source}")]
+    OpSend { source: mpsc::error::SendError<()> },
+
     /// Error while receiving operation results from the connection handler.
     #[error("result recv error: {source}")]
     ResultRecv {
@@ -81,17 +114,82 @@ pub enum LdapError {
     #[error("timeout: {elapsed}")]
     Timeout {
         #[from]
-        elapsed: time::error::Elapsed,
+        elapsed: Elapsed,
     },
 
+    /// The deadline set with
+    /// [`Ldap::with_deadline()`](../struct.Ldap.html#method.with_deadline) has passed.
+    #[error("operation deadline exceeded")]
+    DeadlineExceeded,
+
+    /// No item of a streaming Search arrived within the time set with
+    /// [`SearchOptions::first_entry_timeout()`](../struct.SearchOptions.html#method.first_entry_timeout).
+    #[error("no search entry within the first entry timeout")]
+    FirstEntryTimeout,
+
+    /// A streaming Search didn't complete within the time set with
+    /// [`SearchOptions::total_timeout()`](../struct.SearchOptions.html#method.total_timeout).
+    #[error("search total timeout exceeded")]
+    SearchTotalTimeout,
+
+    /// Operation cancelled through an [`OpCancellation`](../struct.OpCancellation.html) token.
+    /// For a streaming Search, `entries` is the number of entries returned before cancellation;
+    /// for other operations, it's always zero.
+    #[error("operation cancelled ({cause}) after {entries} entries")]
+    Cancelled { cause: CancelCause, entries: usize },
+
     /// Error parsing the string representation of a search filter.
     #[error("filter parse error")]
     FilterParsing,
 
+    /// Error parsing a schema definition.
+    #[error("schema definition parse error: {0}")]
+    SchemaParsing(String),
+
+    /// Entry expected to be returned by a Search was not found.
+    #[error("entry not found: {0:?}")]
+    EntryNotFound(String),
+
+    /// A method of the synchronous API was called from asynchronous code running on
+    /// a Tokio runtime.
+    #[error("synchronous LDAP call from within an async runtime")]
+    BlockingInRuntime,
+
+    /// The connection driver terminated with an error, reported when closing the
+    /// connection with [`Ldap::close()`](../struct.Ldap.html#method.close).
+    #[error("connection terminated with error: {0}")]
+    ConnectionTerminated(String),
+
+    /// All message IDs are in use by outstanding operations.
+    #[error("no free message ID")]
+    MsgIdExhausted,
+
     /// Premature end of a search stream.
     #[error("premature end of search stream")]
     EndOfStream,
 
+    /// The server sent a protocol op which isn't valid as a response to the operation
+    /// with the message ID `msgid`. The `tag_id` is the application tag of the op.
+    #[error("unexpected protocol op (tag {tag_id}) for msgid {msgid}")]
+    UnexpectedPdu { msgid: RequestId, tag_id: u64 },
+
+    /// The server sent a response with the message ID `msgid`, which doesn't belong to
+    /// any operation sent on the connection, and the connection was terminated because
+    /// of it, as set by [`Unmatched::Disconnect`](../enum.Unmatched.html#variant.Disconnect).
+    #[error("protocol violation: response for unknown msgid {msgid}")]
+    ProtocolViolation { msgid: RequestId },
+
+    /// The server closed the connection after sending a Notice of Disconnection.
+    /// The result code and diagnostic message are taken from the notice.
+    #[error("server disconnected (rc={rc}): {text}")]
+    ServerDisconnect { rc: u32, text: String },
+
+    /// The StartTLS operation or the TLS handshake following it failed. The connection
+    /// is torn down, and every operation on its handles fails with this error, whose
+    /// source is the original failure.
+    #[error("TLS handshake failed: {source}")]
+    TlsHandshakeFailed { source: Arc<LdapError> },
+
     /// URL parsing error.
     #[error("url parse error: {source}")]
     UrlParsing {
@@ -103,6 +201,19 @@ pub enum LdapError {
     #[error("unknown LDAP URL scheme: {0}")]
     UnknownScheme(String),
 
+    /// URL scheme or SASL mechanism whose support requires a feature which wasn't
+    /// enabled at compile time.
+    #[error("{what} requires the \"{feature}\" feature, which is not enabled")]
+    FeatureNotEnabled { what: String, feature: &'static str },
+
+    /// URL scheme or other facility not supported on the current platform.
+    #[error("{what} is not supported on this platform")]
+    PlatformUnsupported { what: String },
+
+    /// Connection setting which can't be honored with a transport supplied by the caller.
+    #[error("{setting} can't be used with a caller-supplied transport")]
+    TransportSetting { setting: &'static str },
+
     #[cfg(feature = "tls-native")]
     /// Native TLS library error.
     #[error("native TLS error: {source}")]
@@ -127,6 +238,12 @@ pub enum LdapError {
         source: rustls::pki_types::InvalidDnsNameError,
     },
 
+    #[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
+    /// Client identity or additional root certificates set together with a custom TLS
+    /// connector or configuration.
+    #[error("client identity or root certificates can't be combined with a custom TLS connector or configuration")]
+    IdentityWithCustomTls,
+
     /// LDAP operation result with an error return code.
     #[error("LDAP operation result: {result}")]
     LdapResult {
@@ -138,6 +255,12 @@ pub enum LdapError {
     #[error("empty value set for Add")]
     AddNoValues,
 
+    /// The reader of a streamed value returned a different number of bytes than
+    /// declared. If the reader had excess data, `received` is `declared + 1`, since
+    /// the reader isn't consumed beyond that point.
+    #[error("streamed value length mismatch: declared {declared}, received {received}")]
+    StreamedValueLength { declared: u64, received: u64 },
+
     /// No values provided for the Add operation.
     #[error("adapter init error: {0}")]
     AdapterInit(String),
@@ -146,14 +269,116 @@ pub enum LdapError {
     #[error("utf8 decoding error")]
     DecodingUTF8,
 
-    /// Invalid scope string in LDAP URL.
-    #[error("invalid scope string in LDAP URL: {0}")]
+    /// Message received from the server exceeds the maximum size set with
+    /// [`set_max_ber_size()`](../struct.LdapConnSettings.html#method.set_max_ber_size).
+    /// The size is determined from the length header, before reading the contents.
+    #[error("received message of {size} bytes exceeds the limit of {limit} bytes")]
+    DecodingFrameTooLarge { size: usize, limit: usize },
+
+    /// Malformed BER encoding of a search result entry.
+    #[error("malformed search result entry: {0}")]
+    MalformedEntry(&'static str),
+
+    /// Attribute description repeated in a search result entry, when repeats are
+    /// treated as an error.
+    #[error("duplicate attribute in search result entry: {0}")]
+    DuplicateAttribute(String),
+
+    /// Malformed BER encoding of an Intermediate Response.
+    #[error("malformed intermediate response: {0}")]
+    MalformedIntermediate(&'static str),
+
+    /// Malformed value of a response control.
+    #[error("malformed control value: {0}")]
+    MalformedControl(&'static str),
+
+    /// Malformed value of an Extended response.
+    #[error("malformed extended response value: {0}")]
+    MalformedExop(&'static str),
+
+    /// Malformed response to a Start or End Transaction extended operation.
+    #[error("malformed transaction response: {0}")]
+    MalformedTxnResponse(&'static str),
+
+    /// Invalid or truncated entry spool.
+    #[error("invalid entry spool: {0}")]
+    InvalidSpool(&'static str),
+
+    /// Invalid scope string, in an LDAP URL or parsed into a [`Scope`](../enum.Scope.html).
+    #[error("invalid scope string: {0}")]
     InvalidScopeString(String),
 
+    /// Invalid string parsed into a [`DerefAliases`](../enum.DerefAliases.html) value.
+    #[error("invalid alias dereferencing string: {0}")]
+    InvalidDerefString(String),
+
     /// Unrecognized LDAP URL extension marked as critical.
     #[error("unrecognized critical LDAP URL extension: {0}")]
     UnrecognizedCriticalExtension(String),
 
+    /// LDAP URL names a host different from the connected one.
+    #[error("LDAP URL host {0} doesn't match the connection")]
+    ForeignHost(String),
+
+    /// Client-side limit of a streaming Search exceeded. The counts include the
+    /// entry which caused the limit to be exceeded.
+    #[error("client-side search limit exceeded after {entries} entries, {bytes} bytes")]
+    ClientSideLimit { entries: usize, bytes: usize },
+
+    /// An attribute of a search entry exceeds the strict
+    /// [`ValueLimits`](../struct.ValueLimits.html). The size is that of its largest value.
+    #[error("attribute {attr} exceeds the value limits: {values} values, largest {size} bytes")]
+    ValueLimitExceeded {
+        attr: String,
+        size: usize,
+        values: usize,
+    },
+
+    /// Operation which must be protected by TLS attempted on a connection without it.
+    #[error("operation requires a TLS connection")]
+    TlsRequired,
+
+    /// No password change method supported by the library is advertised by the server.
+    #[error("server advertises no supported password change method")]
+    PasswordChangeUnsupported,
+
+    /// A control implemented by this library was attached to an operation with which
+    /// it can't be used, and the request was rejected without being sent. The `op` is
+    /// the name of the operation, e.g., "modify".
+    #[error("control {oid} not allowed with {op} operation")]
+    ControlNotAllowed { oid: String, op: &'static str },
+
+    /// Request rejected by client-side validation before being sent. The `field`
+    /// names the invalid part of the request.
+    #[error("invalid request {field}: {reason}")]
+    InvalidRequest { field: &'static str, reason: String },
+
+    /// Invalid value of a keyword in the client configuration. The `origin` is the path
+    /// of the configuration file, or "environment" for an environment variable.
+    #[error("invalid value for {keyword} in {origin}: {value:?}")]
+    InvalidConfigValue {
+        origin: String,
+        keyword: String,
+        value: String,
+    },
+
+    /// Limit of a subtree delete exceeded while enumerating the subtree. The depth and
+    /// the count are those of the entry which caused the limit to be exceeded.
+    #[error("subtree delete limit exceeded at depth {depth}, entry {entries}")]
+    SubtreeLimit { depth: usize, entries: usize },
+
+    #[cfg(feature = "gssapi")]
+    /// Streamed request attempted on a connection with a SASL security layer.
+    #[error("streamed requests can't be used with SASL security layers")]
+    StreamingWithSaslWrap,
+
+    /// Error in mapping an entry to a user-defined type.
+    #[error("entry mapping error: {source}")]
+    EntryMapping {
+        #[from]
+        source: EntryMapError,
+    },
+
     #[cfg(feature = "gssapi")]
     /// GSSAPI operation error.
     #[error("GSSAPI operation error: {0}")]
@@ -176,13 +401,79 @@ pub enum LdapError {
     /// No CHALLENGE token received in NTLM exchange.
     #[error("no CHALLENGE token received in NTLM exchange")]
     NoNtlmChallengeToken,
+
+    #[cfg(feature = "scram")]
+    /// Malformed or unexpected message in a SCRAM exchange.
+    #[error("SCRAM protocol error: {0}")]
+    ScramProtocol(String),
+
+    #[cfg(feature = "scram")]
+    /// Server signature in a SCRAM exchange doesn't match the expected value. The
+    /// server can't prove the knowledge of the password, and may be an impostor.
+    #[error("SCRAM server signature mismatch")]
+    ScramServerSignature,
+}
+
+fn display_target(host: &str, addrs: &[SocketAddr]) -> String {
+    if addrs.is_empty() {
+        return String::from(host);
+    }
+    let addrs: Vec<_> = addrs.iter().map(SocketAddr::to_string).collect();
+    format!("{} ({})", host, addrs.join(", "))
+}
+
+impl LdapError {
+    // Attach the operation context to a connection-level error. A Notice of Disconnection
+    // or a failed TLS upgrade already describes the failure, and is left as is.
+    pub(crate) fn in_operation(self, op: &'static str, id: RequestId) -> LdapError {
+        match self {
+            e @ (LdapError::ServerDisconnect { .. } | LdapError::TlsHandshakeFailed { .. }) => e,
+            e => LdapError::Operation {
+                op,
+                id,
+                source: Box::new(e),
+            },
+        }
+    }
+}
+
+impl From<mpsc::error::SendError<(RequestId, LdapOp, Tag, MaybeControls, ResultSender)>>
+    for LdapError
+{
+    fn from(
+        _: mpsc::error::SendError<(RequestId, LdapOp, Tag, MaybeControls, ResultSender)>,
+    ) -> Self {
+        LdapError::OpSend {
+            source: mpsc::error::SendError(()),
+        }
+    }
+}
+
+/// Error returned when an operation or connection times out.
+///
+/// The type is the same whichever async runtime the crate is built with.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Elapsed(());
+
+impl Elapsed {
+    pub(crate) fn new() -> Self {
+        Elapsed(())
+    }
+}
+
+impl Error for Elapsed {}
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("deadline has elapsed")
+    }
 }
 
 impl From<LdapError> for io::Error {
     fn from(le: LdapError) -> io::Error {
         match le {
             LdapError::Io { source, .. } => source,
-            _ => io::Error::new(io::ErrorKind::Other, format!("{}", le)),
+            _ => io::Error::other(format!("{}", le)),
         }
     }
 }
@@ -198,6 +489,7 @@ impl From<LdapError> for io::Error {
 /// [`non_error()`](#method.non_error), which may be used for ergonomic error
 /// handling when simple condition checking suffices.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LdapResult {
     /// Result code.
     ///
@@ -230,65 +522,172 @@ impl Error for LdapResult {}
 
 impl fmt::Display for LdapResult {
     fn fmt(&self, f: &mut fmt::Formatter) -> StdResult<(), fmt::Error> {
-        fn description(this: &LdapResult) -> &'static str {
-            match this.rc {
-                0 => "success",
-                1 => "operationsError",
-                2 => "protocolError",
-                3 => "timeLimitExceeded",
-                4 => "sizeLimitExceeded",
-                5 => "compareFalse",
-                6 => "compareTrue",
-                7 => "authMethodNotSupported",
-                8 => "strongerAuthRequired",
-                10 => "referral",
-                11 => "adminLimitExceeded",
-                12 => "unavailableCriticalExtension",
-                13 => "confidentialityRequired",
-                14 => "saslBindInProgress",
-                16 => "noSuchAttribute",
-                17 => "undefinedAttributeType",
-                18 => "inappropriateMatching",
-                19 => "constraintViolation",
-                20 => "attributeOrValueExists",
-                21 => "invalidAttributeSyntax",
-                32 => "noSuchObject",
-                33 => "aliasProblem",
-                34 => "invalidDNSyntax",
-                36 => "aliasDereferencingProblem",
-                48 => "inappropriateAuthentication",
-                49 => "invalidCredentials",
-                50 => "insufficientAccessRights",
-                51 => "busy",
-                52 => "unavailable",
-                53 => "unwillingToPerform",
-                54 => "loopDetect",
-                64 => "namingViolation",
-                65 => "objectClassViolation",
-                66 => "notAllowedOnNonLeaf",
-                67 => "notAllowedOnRDN",
-                68 => "entryAlreadyExists",
-                69 => "objectClassModsProhibited",
-                71 => "affectsMultipleDSAs",
-                80 => "other",
-                88 => "abandoned",
-                122 => "assertionFailed",
-                _ => "unknown",
-            }
-        }
-
         write!(
             f,
             "rc={} ({}), dn: \"{}\", text: \"{}\"",
             self.rc,
-            description(self),
+            self.code().as_str(),
             self.matched,
             self.text
         )
     }
 }
 
+macro_rules! result_codes {
+    ($($rc:literal => $variant:ident $name:literal,)*) => {
+        /// Symbolic names of LDAP result codes.
+        ///
+        /// The enumeration covers the codes defined in
+        /// [Section A.1 of RFC 4511](https://tools.ietf.org/html/rfc4511#appendix-A.1), and
+        /// some codes from later standards in common use. Any other value is represented as
+        /// `Other`. Conversion from and to `u32` is lossless in both directions.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        #[non_exhaustive]
+        pub enum ResultCode {
+            $(
+                #[doc = concat!("`", $name, "` (", stringify!($rc), ").")]
+                $variant,
+            )*
+            /// Code without a symbolic name.
+            Other(u32),
+        }
+
+        impl From<u32> for ResultCode {
+            fn from(rc: u32) -> ResultCode {
+                match rc {
+                    $($rc => ResultCode::$variant,)*
+                    rc => ResultCode::Other(rc),
+                }
+            }
+        }
+
+        impl From<ResultCode> for u32 {
+            fn from(code: ResultCode) -> u32 {
+                match code {
+                    $(ResultCode::$variant => $rc,)*
+                    ResultCode::Other(rc) => rc,
+                }
+            }
+        }
+
+        impl ResultCode {
+            /// Name of the result code, as used in the standard, or `"unknown"` for a code
+            /// without a symbolic name.
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $(ResultCode::$variant => $name,)*
+                    ResultCode::Other(_) => "unknown",
+                }
+            }
+        }
+    };
+}
+
+result_codes! {
+    0 => Success "success",
+    1 => OperationsError "operationsError",
+    2 => ProtocolError "protocolError",
+    3 => TimeLimitExceeded "timeLimitExceeded",
+    4 => SizeLimitExceeded "sizeLimitExceeded",
+    5 => CompareFalse "compareFalse",
+    6 => CompareTrue "compareTrue",
+    7 => AuthMethodNotSupported "authMethodNotSupported",
+    8 => StrongerAuthRequired "strongerAuthRequired",
+    10 => Referral "referral",
+    11 => AdminLimitExceeded "adminLimitExceeded",
+    12 => UnavailableCriticalExtension "unavailableCriticalExtension",
+    13 => ConfidentialityRequired "confidentialityRequired",
+    14 => SaslBindInProgress "saslBindInProgress",
+    16 => NoSuchAttribute "noSuchAttribute",
+    17 => UndefinedAttributeType "undefinedAttributeType",
+    18 => InappropriateMatching "inappropriateMatching",
+    19 => ConstraintViolation "constraintViolation",
+    20 => AttributeOrValueExists "attributeOrValueExists",
+    21 => InvalidAttributeSyntax "invalidAttributeSyntax",
+    32 => NoSuchObject "noSuchObject",
+    33 => AliasProblem "aliasProblem",
+    34 => InvalidDnSyntax "invalidDNSyntax",
+    36 => AliasDereferencingProblem "aliasDereferencingProblem",
+    48 => InappropriateAuthentication "inappropriateAuthentication",
+    49 => InvalidCredentials "invalidCredentials",
+    50 => InsufficientAccessRights "insufficientAccessRights",
+    51 => Busy "busy",
+    52 => Unavailable "unavailable",
+    53 => UnwillingToPerform "unwillingToPerform",
+    54 => LoopDetect "loopDetect",
+    64 => NamingViolation "namingViolation",
+    65 => ObjectClassViolation "objectClassViolation",
+    66 => NotAllowedOnNonLeaf "notAllowedOnNonLeaf",
+    67 => NotAllowedOnRdn "notAllowedOnRDN",
+    68 => EntryAlreadyExists "entryAlreadyExists",
+    69 => ObjectClassModsProhibited "objectClassModsProhibited",
+    71 => AffectsMultipleDsas "affectsMultipleDSAs",
+    80 => OtherError "other",
+    88 => Abandoned "abandoned",
+    118 => Canceled "canceled",
+    119 => NoSuchOperation "noSuchOperation",
+    120 => TooLate "tooLate",
+    121 => CannotCancel "cannotCancel",
+    122 => AssertionFailed "assertionFailed",
+    123 => AuthorizationDenied "authorizationDenied",
+    4096 => SyncRefreshRequired "e-syncRefreshRequired",
+}
+
+impl fmt::Display for ResultCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> StdResult<(), fmt::Error> {
+        f.write_str(self.as_str())
+    }
+}
+
 impl LdapResult {
+    /// Return the symbolic form of the result code.
+    pub fn code(&self) -> ResultCode {
+        ResultCode::from(self.rc)
+    }
+
+    /// Return `true` if the result code is 0 (success).
+    pub fn is_success(&self) -> bool {
+        self.rc == 0
+    }
+
+    /// Return `true` if the result code is 10 (referral).
+    pub fn is_referral(&self) -> bool {
+        self.rc == 10
+    }
+
+    /// Return `true` if the result code is 48 (inappropriateAuthentication),
+    /// 49 (invalidCredentials), or 50 (insufficientAccessRights).
+    pub fn is_auth_error(&self) -> bool {
+        matches!(self.rc, 48..=50)
+    }
+
+    /// Return `true` if the result code is 51 (busy), 52 (unavailable), 53 (unwillingToPerform),
+    /// or 80 (other), which may indicate a transient server condition. An operation failing
+    /// with one of these codes is a candidate for a retry.
+    pub fn is_unavailable(&self) -> bool {
+        matches!(self.rc, 51..=53 | 80)
+    }
+
+    /// Return the password expiry notice carried by the Netscape Password Expired or
+    /// Password Expiring response control, if either is present. These controls are
+    /// typically returned with a Bind, which may have succeeded.
+    ///
+    /// ```rust,no_run
+    /// # use ldap3::LdapConn;
+    /// # use ldap3::controls::PasswordExpiry;
+    /// # fn main() -> ldap3::result::Result<()> {
+    /// # let mut ldap = LdapConn::new("ldap://localhost")?;
+    /// let res = ldap.simple_bind("uid=jdoe,ou=People,dc=example,dc=org", "secret")?;
+    /// if let Some(PasswordExpiry::Expiring(secs)) = res.password_expiry() {
+    ///     eprintln!("password expires in {} days", secs / 86400);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn password_expiry(&self) -> Option<PasswordExpiry> {
+        password_expiry(&self.ctrls)
+    }
+
     /// If the result code is zero, return the instance itself wrapped
     /// in `Ok()`, otherwise wrap the instance in an `LdapError`.
     pub fn success(self) -> Result<Self> {
@@ -337,18 +736,13 @@ impl From<Tag> for LdapResultExt {
             _ => unimplemented!(),
         };
         let mut tags = t.expect_constructed().expect("result sequence").into_iter();
-        let rc = match parse_uint(
-            tags.next()
-                .expect("element")
-                .match_class(TagClass::Universal)
-                .and_then(|t| t.match_id(Types::Enumerated as u64))
-                .and_then(|t| t.expect_primitive())
-                .expect("result code")
-                .as_slice(),
-        ) {
-            Ok((_, rc)) => rc as u32,
-            _ => panic!("failed to parse result code"),
-        };
+        let rc = tags
+            .next()
+            .expect("element")
+            .match_class(TagClass::Universal)
+            .and_then(|t| t.match_id(Types::Enumerated as u64))
+            .and_then(|t| t.expect_i64())
+            .expect("result code") as u32;
         let matched = String::from_utf8(
             tags.next()
                 .expect("element")
@@ -436,6 +830,19 @@ impl SearchResult {
             Err(LdapError::from(self.1))
         }
     }
+
+    /// Construct a [`SearchEntry`](../struct.SearchEntry.html) from each entry in the result,
+    /// and convert it into the target type. Referrals and intermediate messages are skipped.
+    ///
+    /// The result code isn't examined, so a failed Search will produce a vector of
+    /// whatever entries were received before the failure.
+    pub fn mapped<T: FromEntry>(self) -> StdResult<Vec<T>, EntryMapError> {
+        self.0
+            .into_iter()
+            .filter(|re| !re.is_ref() && !re.is_intermediate())
+            .map(|re| T::from_entry(&SearchEntry::construct(re)))
+            .collect()
+    }
 }
 
 /// Wrapper for the result of a Compare operation.
@@ -499,3 +906,204 @@ impl ExopResult {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::error::Error;
+    use std::io;
+
+    use super::{LdapError, LdapResult, ResultCode};
+
+    fn result(rc: u32) -> LdapResult {
+        LdapResult {
+            rc,
+            matched: String::from("dc=example,dc=org"),
+            text: String::from("no such entry"),
+            refs: vec![],
+            ctrls: vec![],
+        }
+    }
+
+    #[test]
+    fn error_is_send_sync() {
+        fn send_sync<T: Send + Sync + 'static>() {}
+        send_sync::<LdapError>();
+    }
+
+    fn error_chain(err: &dyn Error) -> Vec<String> {
+        let mut chain = vec![err.to_string()];
+        let mut source = err.source();
+        while let Some(err) = source {
+            chain.push(err.to_string());
+            source = err.source();
+        }
+        chain
+    }
+
+    #[test]
+    fn nested_errors() {
+        let err = result(32)
+            .success()
+            .expect_err("failure")
+            .in_operation("search", 3);
+        let chain = error_chain(&err);
+        assert_eq!(chain.len(), 3);
+        assert_eq!(chain[0], "search operation (msgid 3) failed");
+        assert!(chain[1].starts_with("LDAP operation result: "));
+        assert!(chain[2].contains("rc=32"), "{}", chain[2]);
+        let LdapError::Operation { op, id, source } = err else {
+            panic!("not an operation error");
+        };
+        assert_eq!((op, id), ("search", 3));
+        assert!(matches!(*source, LdapError::LdapResult { ref result } if result.rc == 32));
+
+        let err = LdapError::ConnectFailed {
+            host: String::from("ldap.example.org:389"),
+            addrs: vec![],
+            source: io::Error::from(io::ErrorKind::ConnectionRefused),
+        };
+        let chain = error_chain(&err);
+        assert!(chain[0].starts_with("unable to connect to ldap.example.org:389: "));
+        assert_eq!(chain[1], "connection refused");
+        let err = io::Error::from(err);
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+    }
+
+    #[test]
+    fn result_codes() {
+        let table = [
+            (0, ResultCode::Success, "success"),
+            (1, ResultCode::OperationsError, "operationsError"),
+            (2, ResultCode::ProtocolError, "protocolError"),
+            (3, ResultCode::TimeLimitExceeded, "timeLimitExceeded"),
+            (4, ResultCode::SizeLimitExceeded, "sizeLimitExceeded"),
+            (5, ResultCode::CompareFalse, "compareFalse"),
+            (6, ResultCode::CompareTrue, "compareTrue"),
+            (
+                7,
+                ResultCode::AuthMethodNotSupported,
+                "authMethodNotSupported",
+            ),
+            (8, ResultCode::StrongerAuthRequired, "strongerAuthRequired"),
+            (10, ResultCode::Referral, "referral"),
+            (11, ResultCode::AdminLimitExceeded, "adminLimitExceeded"),
+            (
+                12,
+                ResultCode::UnavailableCriticalExtension,
+                "unavailableCriticalExtension",
+            ),
+            (
+                13,
+                ResultCode::ConfidentialityRequired,
+                "confidentialityRequired",
+            ),
+            (14, ResultCode::SaslBindInProgress, "saslBindInProgress"),
+            (16, ResultCode::NoSuchAttribute, "noSuchAttribute"),
+            (
+                17,
+                ResultCode::UndefinedAttributeType,
+                "undefinedAttributeType",
+            ),
+            (
+                18,
+                ResultCode::InappropriateMatching,
+                "inappropriateMatching",
+            ),
+            (19, ResultCode::ConstraintViolation, "constraintViolation"),
+            (
+                20,
+                ResultCode::AttributeOrValueExists,
+                "attributeOrValueExists",
+            ),
+            (
+                21,
+                ResultCode::InvalidAttributeSyntax,
+                "invalidAttributeSyntax",
+            ),
+            (32, ResultCode::NoSuchObject, "noSuchObject"),
+            (33, ResultCode::AliasProblem, "aliasProblem"),
+            (34, ResultCode::InvalidDnSyntax, "invalidDNSyntax"),
+            (
+                36,
+                ResultCode::AliasDereferencingProblem,
+                "aliasDereferencingProblem",
+            ),
+            (
+                48,
+                ResultCode::InappropriateAuthentication,
+                "inappropriateAuthentication",
+            ),
+            (49, ResultCode::InvalidCredentials, "invalidCredentials"),
+            (
+                50,
+                ResultCode::InsufficientAccessRights,
+                "insufficientAccessRights",
+            ),
+            (51, ResultCode::Busy, "busy"),
+            (52, ResultCode::Unavailable, "unavailable"),
+            (53, ResultCode::UnwillingToPerform, "unwillingToPerform"),
+            (54, ResultCode::LoopDetect, "loopDetect"),
+            (64, ResultCode::NamingViolation, "namingViolation"),
+            (65, ResultCode::ObjectClassViolation, "objectClassViolation"),
+            (66, ResultCode::NotAllowedOnNonLeaf, "notAllowedOnNonLeaf"),
+            (67, ResultCode::NotAllowedOnRdn, "notAllowedOnRDN"),
+            (68, ResultCode::EntryAlreadyExists, "entryAlreadyExists"),
+            (
+                69,
+                ResultCode::ObjectClassModsProhibited,
+                "objectClassModsProhibited",
+            ),
+            (71, ResultCode::AffectsMultipleDsas, "affectsMultipleDSAs"),
+            (80, ResultCode::OtherError, "other"),
+            (88, ResultCode::Abandoned, "abandoned"),
+            (118, ResultCode::Canceled, "canceled"),
+            (119, ResultCode::NoSuchOperation, "noSuchOperation"),
+            (120, ResultCode::TooLate, "tooLate"),
+            (121, ResultCode::CannotCancel, "cannotCancel"),
+            (122, ResultCode::AssertionFailed, "assertionFailed"),
+            (123, ResultCode::AuthorizationDenied, "authorizationDenied"),
+            (
+                4096,
+                ResultCode::SyncRefreshRequired,
+                "e-syncRefreshRequired",
+            ),
+            (9, ResultCode::Other(9), "unknown"),
+            (16654, ResultCode::Other(16654), "unknown"),
+        ];
+        for (rc, code, name) in table {
+            assert_eq!(ResultCode::from(rc), code);
+            assert_eq!(u32::from(code), rc);
+            assert_eq!(code.as_str(), name);
+            assert_eq!(result(rc).code(), code);
+        }
+    }
+
+    #[test]
+    fn result_display() {
+        assert_eq!(
+            result(32).to_string(),
+            "rc=32 (noSuchObject), dn: \"dc=example,dc=org\", text: \"no such entry\""
+        );
+        assert_eq!(
+            result(9).to_string(),
+            "rc=9 (unknown), dn: \"dc=example,dc=org\", text: \"no such entry\""
+        );
+        assert_eq!(
+            ResultCode::InvalidCredentials.to_string(),
+            "invalidCredentials"
+        );
+    }
+
+    #[test]
+    fn result_predicates() {
+        let matching = |pred: fn(&LdapResult) -> bool| {
+            (0..=4096)
+                .filter(|&rc| pred(&result(rc)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(matching(LdapResult::is_success), [0]);
+        assert_eq!(matching(LdapResult::is_referral), [10]);
+        assert_eq!(matching(LdapResult::is_auth_error), [48, 49, 50]);
+        assert_eq!(matching(LdapResult::is_unavailable), [51, 52, 53, 80]);
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use crate::result::{LdapError, ResultCode};
+
+/// Delay between the attempts of a retried operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backoff {
+    /// The same delay before each retry.
+    Fixed(Duration),
+    /// The delay starts at `base` and doubles with each retry, up to `cap`.
+    Exponential { base: Duration, cap: Duration },
+}
+
+/// Policy for retrying operations which fail with a transient error.
+///
+/// The policy is set for a connection with
+/// [`LdapConnSettings::set_retry_policy()`](../struct.LdapConnSettings.html#method.set_retry_policy),
+/// and applies to the operations which can be repeated without changing their outcome:
+/// [`search()`](../struct.Ldap.html#method.search) (but not a streaming Search),
+/// [`compare()`](../struct.Ldap.html#method.compare), and the Who Am I extended operation.
+/// Other operations are retried only if explicitly requested with
+/// [`with_retries()`](../struct.Ldap.html#method.with_retries).
+///
+/// Each attempt is a new request, with a new message ID. If the operation has a deadline,
+/// no attempt is made after it would be passed by waiting for the backoff delay, and the
+/// outcome of the last attempt is returned.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    backoff: Backoff,
+    retry_on: HashSet<ResultCode>,
+    retry_transport_errors: bool,
+}
+
+impl RetryPolicy {
+    /// Create a policy which makes at most `max_attempts` attempts of an operation,
+    /// counting the first one. The default backoff is exponential, starting at
+    /// 100 milliseconds and capped at 5 seconds. By default, only the results with the
+    /// code 51 (busy) and 52 (unavailable) are retried.
+    pub fn new(max_attempts: u32) -> Self {
+        RetryPolicy {
+            max_attempts,
+            backoff: Backoff::Exponential {
+                base: Duration::from_millis(100),
+                cap: Duration::from_secs(5),
+            },
+            retry_on: HashSet::from([ResultCode::Busy, ResultCode::Unavailable]),
+            retry_transport_errors: false,
+        }
+    }
+
+    /// Set the backoff between the attempts.
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Set the result codes which cause an operation to be retried, replacing the
+    /// default set.
+    pub fn retry_on<I: IntoIterator<Item = ResultCode>>(mut self, codes: I) -> Self {
+        self.retry_on = codes.into_iter().collect();
+        self
+    }
+
+    /// If `true`, an operation will also be retried if it times out, or fails because
+    /// of an I/O or connection error. Defaults to `false`.
+    pub fn retry_transport_errors(mut self, retry: bool) -> Self {
+        self.retry_transport_errors = retry;
+        self
+    }
+
+    pub(crate) fn retry_rc(&self, rc: u32) -> bool {
+        self.retry_on.contains(&ResultCode::from(rc))
+    }
+
+    pub(crate) fn retry_err(&self, err: &LdapError) -> bool {
+        self.retry_transport_errors
+            && matches!(
+                err,
+                LdapError::Timeout { .. } | LdapError::Io { .. } | LdapError::Operation { .. }
+            )
+    }
+
+    fn delay(&self, attempt: u32) -> Duration {
+        match self.backoff {
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { base, cap } => base
+                .checked_mul(2u32.saturating_pow(attempt - 1))
+                .map_or(cap, |delay| delay.min(cap)),
+        }
+    }
+
+    // Wait before the next attempt, after `attempt` attempts have failed. Return `false`
+    // without waiting if no attempts are left, or the deadline would pass.
+    pub(crate) async fn pause(&self, attempt: u32, deadline: Option<Instant>) -> bool {
+        if attempt >= self.max_attempts {
+            return false;
+        }
+        let delay = self.delay(attempt);
+        if deadline.is_some_and(|deadline| Instant::now() + delay >= deadline) {
+            return false;
+        }
+        crate::rt::sleep(delay).await;
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{Backoff, RetryPolicy};
+
+    #[test]
+    fn backoff_delays() {
+        let policy = RetryPolicy::new(10).backoff(Backoff::Exponential {
+            base: Duration::from_millis(100),
+            cap: Duration::from_millis(500),
+        });
+        let delays: Vec<_> = (1..=5).map(|n| policy.delay(n).as_millis()).collect();
+        assert_eq!(delays, [100, 200, 400, 500, 500]);
+        assert_eq!(policy.delay(100).as_millis(), 500);
+        let policy = policy.backoff(Backoff::Fixed(Duration::from_millis(50)));
+        assert_eq!(policy.delay(3).as_millis(), 50);
+    }
+}
@@ -0,0 +1,274 @@
+//! Async runtime abstraction.
+//!
+//! The connection and operation code needs a runtime for four things: spawning tasks,
+//! timers, opening TCP and Unix domain socket connections, and, for the synchronous
+//! API, blocking on a future. This module provides them for Tokio, with the
+//! __runtime-tokio__ feature, and for async-std (and thus smol, which shares its
+//! reactor), with __runtime-async-std__. If both are enabled, Tokio is used.
+//!
+//! Everything else is runtime-agnostic and shared between the implementations: the
+//! channels and `select!`/`join!` macros from `tokio::sync` and `tokio::macros` don't
+//! depend on the Tokio runtime, nor does the codec framing from `tokio-util`. Under
+//! async-std, sockets are adapted to the Tokio I/O traits with the `compat` wrapper
+//! from `tokio-util`, so that the framing and TLS layers see the same stream types.
+//!
+//! Only [`spawn()`](fn.spawn.html) is public, for the use of the `drive!` macro.
+
+#[cfg(not(any(feature = "runtime-tokio", feature = "runtime-async-std")))]
+compile_error!("one of the features \"runtime-tokio\" or \"runtime-async-std\" must be enabled");
+
+#[cfg(feature = "runtime-tokio")]
+pub use self::tokio_rt::*;
+
+#[cfg(all(feature = "runtime-async-std", not(feature = "runtime-tokio")))]
+pub use self::async_std_rt::*;
+
+#[cfg(feature = "runtime-tokio")]
+mod tokio_rt {
+    use std::future::Future;
+    use std::io;
+    use std::net::{IpAddr, SocketAddr};
+    #[cfg(unix)]
+    use std::path::Path;
+    use std::time::Duration;
+
+    use socket2::SockRef;
+    use tokio::net::TcpSocket;
+
+    use crate::result::Elapsed;
+
+    pub(crate) use tokio::time::Instant;
+
+    pub(crate) type TcpStream = tokio::net::TcpStream;
+    #[cfg(unix)]
+    pub(crate) type UnixStream = tokio::net::UnixStream;
+
+    /// Spawn a task on the current runtime, detaching it.
+    pub fn spawn<F>(future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        tokio::spawn(future);
+    }
+
+    // Spawn a task if called from a runtime; used where there may not be one,
+    // such as in Drop implementations.
+    pub(crate) fn try_spawn<F>(future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        if let Ok(rt) = tokio::runtime::Handle::try_current() {
+            rt.spawn(future);
+        }
+    }
+
+    pub(crate) async fn sleep(duration: Duration) {
+        tokio::time::sleep(duration).await
+    }
+
+    pub(crate) async fn timeout<F: Future>(
+        duration: Duration,
+        future: F,
+    ) -> Result<F::Output, Elapsed> {
+        tokio::time::timeout(duration, future)
+            .await
+            .map_err(|_| Elapsed::new())
+    }
+
+    pub(crate) async fn timeout_at<F: Future>(
+        deadline: Instant,
+        future: F,
+    ) -> Result<F::Output, Elapsed> {
+        tokio::time::timeout_at(deadline, future)
+            .await
+            .map_err(|_| Elapsed::new())
+    }
+
+    pub(crate) fn instant_from_std(instant: std::time::Instant) -> Instant {
+        Instant::from_std(instant)
+    }
+
+    pub(crate) async fn lookup_host(host_port: &str) -> io::Result<Vec<SocketAddr>> {
+        Ok(tokio::net::lookup_host(host_port).await?.collect())
+    }
+
+    pub(crate) async fn connect_tcp(
+        addr: SocketAddr,
+        local: Option<IpAddr>,
+    ) -> io::Result<TcpStream> {
+        let socket = if addr.is_ipv6() {
+            TcpSocket::new_v6()?
+        } else {
+            TcpSocket::new_v4()?
+        };
+        if let Some(local) = local {
+            socket.bind(SocketAddr::new(local, 0))?;
+        }
+        socket.connect(addr).await
+    }
+
+    pub(crate) fn sock_ref(stream: &TcpStream) -> SockRef<'_> {
+        SockRef::from(stream)
+    }
+
+    #[cfg(unix)]
+    pub(crate) async fn connect_unix(path: &Path) -> io::Result<UnixStream> {
+        UnixStream::connect(path).await
+    }
+
+    #[cfg(unix)]
+    pub(crate) fn unix_from_std(stream: std::os::unix::net::UnixStream) -> io::Result<UnixStream> {
+        stream.set_nonblocking(true)?;
+        UnixStream::from_std(stream)
+    }
+
+    #[cfg(feature = "sync")]
+    #[derive(Debug)]
+    pub(crate) struct Runtime(tokio::runtime::Runtime);
+
+    #[cfg(feature = "sync")]
+    impl Runtime {
+        pub(crate) fn new() -> io::Result<Self> {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map(Runtime)
+        }
+
+        pub(crate) fn block_on<F: Future>(&self, future: F) -> F::Output {
+            self.0.block_on(future)
+        }
+
+        // True if the caller is running on a runtime, where blocking would panic.
+        pub(crate) fn in_async_context() -> bool {
+            tokio::runtime::Handle::try_current().is_ok()
+        }
+    }
+}
+
+#[cfg(all(feature = "runtime-async-std", not(feature = "runtime-tokio")))]
+mod async_std_rt {
+    use std::future::Future;
+    use std::io;
+    use std::net::{IpAddr, SocketAddr};
+    #[cfg(unix)]
+    use std::path::Path;
+    use std::time::Duration;
+
+    use async_std::net::ToSocketAddrs;
+    use socket2::{Domain, SockRef, Socket, Type};
+    use tokio_util::compat::{Compat, FuturesAsyncReadCompatExt};
+
+    use crate::result::Elapsed;
+
+    pub(crate) use std::time::Instant;
+
+    pub(crate) type TcpStream = Compat<async_std::net::TcpStream>;
+    #[cfg(unix)]
+    pub(crate) type UnixStream = Compat<async_std::os::unix::net::UnixStream>;
+
+    /// Spawn a task on the global executor, detaching it.
+    pub fn spawn<F>(future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        async_std::task::spawn(future);
+    }
+
+    // The global executor is always available.
+    pub(crate) fn try_spawn<F>(future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        spawn(future);
+    }
+
+    pub(crate) async fn sleep(duration: Duration) {
+        async_std::task::sleep(duration).await
+    }
+
+    pub(crate) async fn timeout<F: Future>(
+        duration: Duration,
+        future: F,
+    ) -> Result<F::Output, Elapsed> {
+        async_std::future::timeout(duration, future)
+            .await
+            .map_err(|_| Elapsed::new())
+    }
+
+    pub(crate) async fn timeout_at<F: Future>(
+        deadline: Instant,
+        future: F,
+    ) -> Result<F::Output, Elapsed> {
+        let duration = deadline.saturating_duration_since(Instant::now());
+        async_std::future::timeout(duration, future)
+            .await
+            .map_err(|_| Elapsed::new())
+    }
+
+    pub(crate) fn instant_from_std(instant: std::time::Instant) -> Instant {
+        instant
+    }
+
+    pub(crate) async fn lookup_host(host_port: &str) -> io::Result<Vec<SocketAddr>> {
+        Ok(host_port.to_socket_addrs().await?.collect())
+    }
+
+    pub(crate) async fn connect_tcp(
+        addr: SocketAddr,
+        local: Option<IpAddr>,
+    ) -> io::Result<TcpStream> {
+        let stream = match local {
+            None => async_std::net::TcpStream::connect(addr).await?,
+            Some(local) => {
+                // async-std can't bind before connecting, so the socket is set up and
+                // connected in blocking mode on the blocking thread pool.
+                let stream = async_std::task::spawn_blocking(move || {
+                    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, None)?;
+                    socket.bind(&SocketAddr::new(local, 0).into())?;
+                    socket.connect(&addr.into())?;
+                    Ok::<_, io::Error>(std::net::TcpStream::from(socket))
+                })
+                .await?;
+                async_std::net::TcpStream::from(stream)
+            }
+        };
+        Ok(stream.compat())
+    }
+
+    pub(crate) fn sock_ref(stream: &TcpStream) -> SockRef<'_> {
+        SockRef::from(stream.get_ref())
+    }
+
+    #[cfg(unix)]
+    pub(crate) async fn connect_unix(path: &Path) -> io::Result<UnixStream> {
+        Ok(async_std::os::unix::net::UnixStream::connect(path)
+            .await?
+            .compat())
+    }
+
+    #[cfg(unix)]
+    pub(crate) fn unix_from_std(stream: std::os::unix::net::UnixStream) -> io::Result<UnixStream> {
+        Ok(async_std::os::unix::net::UnixStream::from(stream).compat())
+    }
+
+    #[cfg(feature = "sync")]
+    #[derive(Debug)]
+    pub(crate) struct Runtime;
+
+    #[cfg(feature = "sync")]
+    impl Runtime {
+        pub(crate) fn new() -> io::Result<Self> {
+            Ok(Runtime)
+        }
+
+        pub(crate) fn block_on<F: Future>(&self, future: F) -> F::Output {
+            async_std::task::block_on(future)
+        }
+
+        // True if the caller is running in an async-std task.
+        pub(crate) fn in_async_context() -> bool {
+            async_std::task::try_current().is_some()
+        }
+    }
+}
@@ -0,0 +1,612 @@
+//! Root DSE and subschema parsing.
+//!
+//! Definitions of attribute types and object classes follow the grammar from
+//! [RFC 4512](https://tools.ietf.org/html/rfc4512#section-4.1), with a few relaxations
+//! for the deviations found in real servers: terms may appear in any order, values
+//! of `SYNTAX` may be quoted (Active Directory), and unknown keywords are accepted.
+
+use std::collections::HashMap;
+
+use crate::attr_desc;
+use crate::result::{LdapError, Result};
+use crate::search::SearchEntry;
+
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_while, take_while1};
+use nom::character::complete::{multispace0, multispace1};
+use nom::combinator::{map, opt};
+use nom::multi::many0;
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
+use nom::IResult;
+
+/// Syntaxes whose values can't be represented as UTF-8 strings.
+const BINARY_SYNTAXES: &[&str] = &[
+    "1.3.6.1.4.1.1466.115.121.1.4",  // Audio
+    "1.3.6.1.4.1.1466.115.121.1.5",  // Binary
+    "1.3.6.1.4.1.1466.115.121.1.8",  // Certificate
+    "1.3.6.1.4.1.1466.115.121.1.9",  // Certificate List
+    "1.3.6.1.4.1.1466.115.121.1.10", // Certificate Pair
+    "1.3.6.1.4.1.1466.115.121.1.23", // Fax
+    "1.3.6.1.4.1.1466.115.121.1.28", // JPEG
+    "1.3.6.1.4.1.1466.115.121.1.40", // Octet String
+    "1.3.6.1.4.1.1466.115.121.1.49", // Supported Algorithm
+    "1.2.840.113556.1.4.907",        // AD NT Security Descriptor
+];
+
+/// Parsed root DSE.
+///
+/// The well-known operational attributes are extracted into their own fields. All
+/// attributes returned by the server, including those, are also available in `attrs`.
+#[derive(Clone, Debug, Default)]
+pub struct RootDse {
+    /// Values of `namingContexts`.
+    pub naming_contexts: Vec<String>,
+    /// Values of `supportedControl`.
+    pub supported_control: Vec<String>,
+    /// Values of `supportedExtension`.
+    pub supported_extension: Vec<String>,
+    /// Values of `supportedFeatures`.
+    pub supported_features: Vec<String>,
+    /// Values of `supportedSASLMechanisms`.
+    pub supported_sasl_mechanisms: Vec<String>,
+    /// Values of `supportedLDAPVersion`.
+    pub supported_ldap_version: Vec<String>,
+    /// DN of the subschema entry, if advertised.
+    pub subschema_subentry: Option<String>,
+    /// All attributes of the root DSE.
+    pub attrs: HashMap<String, Vec<String>>,
+}
+
+impl RootDse {
+    /// Extract root DSE attributes from a search entry.
+    pub fn from_entry(entry: SearchEntry) -> RootDse {
+        let attrs = entry.attrs;
+        RootDse {
+            naming_contexts: values(&attrs, "namingContexts"),
+            supported_control: values(&attrs, "supportedControl"),
+            supported_extension: values(&attrs, "supportedExtension"),
+            supported_features: values(&attrs, "supportedFeatures"),
+            supported_sasl_mechanisms: values(&attrs, "supportedSASLMechanisms"),
+            supported_ldap_version: values(&attrs, "supportedLDAPVersion"),
+            subschema_subentry: values(&attrs, "subschemaSubentry").into_iter().next(),
+            attrs,
+        }
+    }
+}
+
+fn values(attrs: &HashMap<String, Vec<String>>, name: &str) -> Vec<String> {
+    attrs
+        .iter()
+        .find(|(attr, _)| attr.eq_ignore_ascii_case(name))
+        .map(|(_, vals)| vals.clone())
+        .unwrap_or_default()
+}
+
+/// Usage of an attribute type.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AttributeUsage {
+    #[default]
+    UserApplications,
+    DirectoryOperation,
+    DistributedOperation,
+    DsaOperation,
+}
+
+/// Attribute type definition.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AttributeType {
+    /// OID of the attribute type.
+    pub oid: String,
+    /// Names; the first one is the primary name.
+    pub names: Vec<String>,
+    /// Description.
+    pub desc: Option<String>,
+    /// Obsolete flag.
+    pub obsolete: bool,
+    /// Supertype.
+    pub sup: Option<String>,
+    /// Equality matching rule.
+    pub equality: Option<String>,
+    /// Ordering matching rule.
+    pub ordering: Option<String>,
+    /// Substrings matching rule.
+    pub substr: Option<String>,
+    /// Syntax OID, without the length bound.
+    pub syntax: Option<String>,
+    /// Length bound of the syntax.
+    pub syntax_len: Option<u32>,
+    /// Single-value flag.
+    pub single_value: bool,
+    /// Collective flag.
+    pub collective: bool,
+    /// No-user-modification flag.
+    pub no_user_modification: bool,
+    /// Attribute usage.
+    pub usage: AttributeUsage,
+    /// Extensions (`X-` terms) and their values.
+    pub extensions: Vec<(String, Vec<String>)>,
+}
+
+impl AttributeType {
+    /// Parse the string representation of an attribute type definition.
+    pub fn parse(def: &str) -> Result<AttributeType> {
+        let (oid, terms) = parse_definition(def)?;
+        let mut at = AttributeType {
+            oid,
+            ..Default::default()
+        };
+        for (keyword, value) in terms {
+            match keyword.as_str() {
+                "NAME" => at.names = value,
+                "DESC" => at.desc = value.into_iter().next(),
+                "OBSOLETE" => at.obsolete = true,
+                "SUP" => at.sup = value.into_iter().next(),
+                "EQUALITY" => at.equality = value.into_iter().next(),
+                "ORDERING" => at.ordering = value.into_iter().next(),
+                "SUBSTR" => at.substr = value.into_iter().next(),
+                "SYNTAX" => {
+                    let syntax = value.into_iter().next().unwrap_or_default();
+                    match syntax.split_once('{') {
+                        Some((oid, len)) => {
+                            at.syntax = Some(String::from(oid));
+                            at.syntax_len = len.trim_end_matches('}').parse().ok();
+                        }
+                        None => at.syntax = Some(syntax),
+                    }
+                }
+                "SINGLE-VALUE" => at.single_value = true,
+                "COLLECTIVE" => at.collective = true,
+                "NO-USER-MODIFICATION" => at.no_user_modification = true,
+                "USAGE" => {
+                    at.usage = match value.first().map(String::as_str) {
+                        Some("directoryOperation") => AttributeUsage::DirectoryOperation,
+                        Some("distributedOperation") => AttributeUsage::DistributedOperation,
+                        Some("dSAOperation") => AttributeUsage::DsaOperation,
+                        _ => AttributeUsage::UserApplications,
+                    }
+                }
+                _ => at.extensions.push((keyword, value)),
+            }
+        }
+        Ok(at)
+    }
+}
+
+/// Kind of an object class.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ObjectClassKind {
+    Abstract,
+    #[default]
+    Structural,
+    Auxiliary,
+}
+
+/// Object class definition.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ObjectClass {
+    /// OID of the object class.
+    pub oid: String,
+    /// Names; the first one is the primary name.
+    pub names: Vec<String>,
+    /// Description.
+    pub desc: Option<String>,
+    /// Obsolete flag.
+    pub obsolete: bool,
+    /// Superclasses.
+    pub sup: Vec<String>,
+    /// Object class kind.
+    pub kind: ObjectClassKind,
+    /// Required attributes, not including those of the superclasses.
+    pub must: Vec<String>,
+    /// Allowed attributes, not including those of the superclasses.
+    pub may: Vec<String>,
+    /// Extensions (`X-` terms) and their values.
+    pub extensions: Vec<(String, Vec<String>)>,
+}
+
+impl ObjectClass {
+    /// Parse the string representation of an object class definition.
+    pub fn parse(def: &str) -> Result<ObjectClass> {
+        let (oid, terms) = parse_definition(def)?;
+        let mut oc = ObjectClass {
+            oid,
+            ..Default::default()
+        };
+        for (keyword, value) in terms {
+            match keyword.as_str() {
+                "NAME" => oc.names = value,
+                "DESC" => oc.desc = value.into_iter().next(),
+                "OBSOLETE" => oc.obsolete = true,
+                "SUP" => oc.sup = value,
+                "ABSTRACT" => oc.kind = ObjectClassKind::Abstract,
+                "STRUCTURAL" => oc.kind = ObjectClassKind::Structural,
+                "AUXILIARY" => oc.kind = ObjectClassKind::Auxiliary,
+                "MUST" => oc.must = value,
+                "MAY" => oc.may = value,
+                _ => oc.extensions.push((keyword, value)),
+            }
+        }
+        Ok(oc)
+    }
+}
+
+/// Attribute types and object classes retrieved from the subschema entry.
+///
+/// Lookups by name or OID are case-insensitive.
+#[derive(Clone, Debug, Default)]
+pub struct Schema {
+    /// Attribute type definitions.
+    pub attribute_types: Vec<AttributeType>,
+    /// Object class definitions.
+    pub object_classes: Vec<ObjectClass>,
+    at_index: HashMap<String, usize>,
+    oc_index: HashMap<String, usize>,
+}
+
+impl Schema {
+    /// Construct the schema from the values of `attributeTypes` and `objectClasses`
+    /// in the subschema entry.
+    ///
+    /// Definitions which can't be parsed are skipped with a warning, since it's not
+    /// uncommon for a server to have a few which don't quite follow the grammar.
+    pub fn from_entry(entry: SearchEntry) -> Schema {
+        let mut schema = Schema::default();
+        for def in values(&entry.attrs, "attributeTypes") {
+            match AttributeType::parse(&def) {
+                Ok(at) => schema.add_attribute_type(at),
+                Err(e) => warn!("skipping attribute type: {}", e),
+            }
+        }
+        for def in values(&entry.attrs, "objectClasses") {
+            match ObjectClass::parse(&def) {
+                Ok(oc) => schema.add_object_class(oc),
+                Err(e) => warn!("skipping object class: {}", e),
+            }
+        }
+        schema
+    }
+
+    /// Add an attribute type to the schema.
+    pub fn add_attribute_type(&mut self, at: AttributeType) {
+        let ix = self.attribute_types.len();
+        for key in at.names.iter().chain(Some(&at.oid)) {
+            self.at_index.insert(key.to_ascii_lowercase(), ix);
+        }
+        self.attribute_types.push(at);
+    }
+
+    /// Add an object class to the schema.
+    pub fn add_object_class(&mut self, oc: ObjectClass) {
+        let ix = self.object_classes.len();
+        for key in oc.names.iter().chain(Some(&oc.oid)) {
+            self.oc_index.insert(key.to_ascii_lowercase(), ix);
+        }
+        self.object_classes.push(oc);
+    }
+
+    /// Find an attribute type by name or OID. Attribute options are ignored.
+    pub fn attribute_type(&self, name: &str) -> Option<&AttributeType> {
+        let name = attr_desc::base_name(name);
+        self.at_index
+            .get(&name.to_ascii_lowercase())
+            .map(|&ix| &self.attribute_types[ix])
+    }
+
+    /// Find an object class by name or OID.
+    pub fn object_class(&self, name: &str) -> Option<&ObjectClass> {
+        self.oc_index
+            .get(&name.to_ascii_lowercase())
+            .map(|&ix| &self.object_classes[ix])
+    }
+
+    /// Return the syntax of an attribute type, following the chain of supertypes
+    /// if the type itself doesn't specify one.
+    pub fn attribute_syntax(&self, name: &str) -> Option<&str> {
+        let mut at = self.attribute_type(name)?;
+        for _ in 0..self.attribute_types.len() {
+            if let Some(ref syntax) = at.syntax {
+                return Some(syntax);
+            }
+            at = self.attribute_type(at.sup.as_ref()?)?;
+        }
+        None
+    }
+
+    /// Check whether the values of an attribute are binary, according to its syntax.
+    /// An attribute description with the `binary` option is always binary. Unknown
+    /// attributes are reported as not binary.
+    pub fn attribute_is_binary(&self, name: &str) -> bool {
+        if attr_desc::has_option(name, "binary") {
+            return true;
+        }
+        self.attribute_syntax(name)
+            .map(|syntax| BINARY_SYNTAXES.contains(&syntax))
+            .unwrap_or(false)
+    }
+
+    /// Return the required and allowed attributes of an object class, including those
+    /// inherited from its superclasses. Attributes are listed by the names used in the
+    /// definitions, without duplicates. Returns `None` if the object class is unknown.
+    pub fn must_may(&self, oc: &str) -> Option<(Vec<String>, Vec<String>)> {
+        let mut must: Vec<String> = vec![];
+        let mut may: Vec<String> = vec![];
+        let mut pending = vec![self.object_class(oc)?];
+        let mut seen = vec![];
+        while let Some(oc) = pending.pop() {
+            if seen.contains(&oc.oid) {
+                continue;
+            }
+            seen.push(oc.oid.clone());
+            for attr in &oc.must {
+                if !must.iter().any(|a| a.eq_ignore_ascii_case(attr)) {
+                    must.push(attr.clone());
+                }
+            }
+            for attr in &oc.may {
+                if !may.iter().any(|a| a.eq_ignore_ascii_case(attr)) {
+                    may.push(attr.clone());
+                }
+            }
+            pending.extend(oc.sup.iter().filter_map(|sup| self.object_class(sup)));
+        }
+        may.retain(|attr| !must.iter().any(|a| a.eq_ignore_ascii_case(attr)));
+        Some((must, may))
+    }
+}
+
+const FLAG_KEYWORDS: &[&str] = &[
+    "OBSOLETE",
+    "SINGLE-VALUE",
+    "COLLECTIVE",
+    "NO-USER-MODIFICATION",
+    "ABSTRACT",
+    "STRUCTURAL",
+    "AUXILIARY",
+];
+
+type Term = (String, Vec<String>);
+
+fn parse_definition(def: &str) -> Result<(String, Vec<Term>)> {
+    match definition(def) {
+        Ok((rest, res)) if rest.trim().is_empty() => Ok(res),
+        _ => Err(LdapError::SchemaParsing(String::from(def))),
+    }
+}
+
+fn definition(i: &str) -> IResult<&str, (String, Vec<Term>)> {
+    delimited(
+        pair(tag("("), multispace0),
+        pair(map(token, String::from), many0(preceded(multispace1, term))),
+        pair(multispace0, tag(")")),
+    )(i)
+}
+
+fn term(i: &str) -> IResult<&str, Term> {
+    let (i, keyword) = keyword(i)?;
+    if FLAG_KEYWORDS.contains(&keyword) {
+        return Ok((i, (String::from(keyword), vec![])));
+    }
+    let (i, value) = preceded(multispace1, value)(i)?;
+    Ok((i, (String::from(keyword), value)))
+}
+
+fn keyword(i: &str) -> IResult<&str, &str> {
+    take_while1(|c: char| c.is_ascii_uppercase() || c == '-' || c == '_')(i)
+}
+
+fn value(i: &str) -> IResult<&str, Vec<String>> {
+    alt((list, map(item, |v| vec![v])))(i)
+}
+
+fn list(i: &str) -> IResult<&str, Vec<String>> {
+    delimited(
+        pair(tag("("), multispace0),
+        many0(terminated(
+            item,
+            tuple((multispace0, opt(tag("$")), multispace0)),
+        )),
+        tag(")"),
+    )(i)
+}
+
+fn item(i: &str) -> IResult<&str, String> {
+    alt((qdstring, map(token, String::from)))(i)
+}
+
+fn qdstring(i: &str) -> IResult<&str, String> {
+    map(
+        delimited(tag("'"), take_while(|c| c != '\''), tag("'")),
+        |s: &str| {
+            s.replace("\\27", "'")
+                .replace("\\5C", "\\")
+                .replace("\\5c", "\\")
+        },
+    )(i)
+}
+
+// Bare oid, descr, or noidlen.
+fn token(i: &str) -> IResult<&str, &str> {
+    take_while1(|c: char| !c.is_whitespace() && !"()'$".contains(c))(i)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AttributeType, AttributeUsage, ObjectClass, ObjectClassKind, Schema};
+
+    const OPENLDAP_AT: &[&str] = &[
+        "( 2.5.4.41 NAME 'name' DESC 'RFC4519: common supertype of name attributes' \
+         EQUALITY caseIgnoreMatch SUBSTR caseIgnoreSubstringsMatch \
+         SYNTAX 1.3.6.1.4.1.1466.115.121.1.15{32768} )",
+        "( 2.5.4.3 NAME ( 'cn' 'commonName' ) DESC 'RFC4519: common name(s) for which the entity is known by' SUP name )",
+        "( 2.5.4.4 NAME ( 'sn' 'surname' ) DESC 'RFC2256: last (family) name(s) for which the entity is known by' SUP name )",
+        "( 2.5.4.35 NAME 'userPassword' DESC 'RFC4519/2307: password of user' \
+         EQUALITY octetStringMatch SYNTAX 1.3.6.1.4.1.1466.115.121.1.40{128} )",
+        "( 2.5.4.36 NAME 'userCertificate' DESC 'RFC4523: X.509 user certificate' \
+         EQUALITY certificateExactMatch SYNTAX 1.3.6.1.4.1.1466.115.121.1.8 )",
+        "( 0.9.2342.19200300.100.1.60 NAME 'jpegPhoto' DESC 'RFC2798: a JPEG image' \
+         SYNTAX 1.3.6.1.4.1.1466.115.121.1.28 )",
+        "( 0.9.2342.19200300.100.1.3 NAME ( 'mail' 'rfc822Mailbox' ) DESC 'RFC1274: RFC822 Mailbox' \
+         EQUALITY caseIgnoreIA5Match SUBSTR caseIgnoreIA5SubstringsMatch \
+         SYNTAX 1.3.6.1.4.1.1466.115.121.1.26{256} )",
+        "( 2.5.18.1 NAME 'createTimestamp' DESC 'RFC4512: time which object was created' \
+         EQUALITY generalizedTimeMatch ORDERING generalizedTimeOrderingMatch \
+         SYNTAX 1.3.6.1.4.1.1466.115.121.1.24 SINGLE-VALUE NO-USER-MODIFICATION USAGE directoryOperation )",
+    ];
+
+    const OPENLDAP_OC: &[&str] = &[
+        "( 2.5.6.0 NAME 'top' DESC 'top of the superclass chain' ABSTRACT MUST objectClass )",
+        "( 2.5.6.6 NAME 'person' DESC 'RFC2256: a person' SUP top STRUCTURAL \
+         MUST ( sn $ cn ) MAY ( userPassword $ telephoneNumber $ seeAlso $ description ) )",
+        "( 2.5.6.7 NAME 'organizationalPerson' DESC 'RFC2256: an organizational person' SUP person STRUCTURAL \
+         MAY ( title $ x121Address $ registeredAddress $ destinationIndicator $ preferredDeliveryMethod $ \
+         telexNumber $ teletexTerminalIdentifier $ telephoneNumber $ internationaliSDNNumber $ \
+         facsimileTelephoneNumber $ street $ postOfficeBox $ postalCode $ postalAddress $ \
+         physicalDeliveryOfficeName $ ou $ st $ l ) )",
+        "( 2.16.840.1.113730.3.2.2 NAME 'inetOrgPerson' DESC 'RFC2798: Internet Organizational Person' \
+         SUP organizationalPerson STRUCTURAL MAY ( audio $ businessCategory $ carLicense $ departmentNumber $ \
+         displayName $ employeeNumber $ employeeType $ givenName $ homePhone $ homePostalAddress $ initials $ \
+         jpegPhoto $ labeledURI $ mail $ manager $ mobile $ o $ pager $ photo $ roomNumber $ secretary $ \
+         uid $ userCertificate $ x500uniqueIdentifier $ preferredLanguage $ userSMIMECertificate $ userPKCS12 ) )",
+    ];
+
+    const AD_AT: &[&str] = &[
+        "( 1.2.840.113556.1.4.2 NAME 'objectGUID' SYNTAX '1.3.6.1.4.1.1466.115.121.1.40' SINGLE-VALUE NO-USER-MODIFICATION )",
+        "( 1.2.840.113556.1.4.146 NAME 'objectSid' SYNTAX '1.3.6.1.4.1.1466.115.121.1.40' SINGLE-VALUE NO-USER-MODIFICATION )",
+        "( 1.2.840.113556.1.4.221 NAME 'sAMAccountName' SYNTAX '1.3.6.1.4.1.1466.115.121.1.15' SINGLE-VALUE )",
+        "( 1.2.840.113556.1.2.281 NAME 'nTSecurityDescriptor' SYNTAX '1.2.840.113556.1.4.907' SINGLE-VALUE )",
+        "( 1.2.840.113556.1.4.96 NAME 'pwdLastSet' SYNTAX '1.2.840.113556.1.4.906' SINGLE-VALUE )",
+        "( 1.2.840.113556.1.4.159 NAME 'accountExpires' SYNTAX '1.2.840.113556.1.4.906' SINGLE-VALUE )",
+    ];
+
+    const AD_OC: &[&str] = &[
+        "( 2.5.6.6 NAME 'person' SUP top STRUCTURAL MUST (cn ) MAY (sn $ serialNumber $ telephoneNumber $ \
+         seeAlso $ userPassword $ attributeCertificateAttribute ) )",
+        "( 1.2.840.113556.1.5.9 NAME 'user' SUP organizationalPerson STRUCTURAL MAY (o $ businessCategory $ \
+         userCertificate $ givenName $ initials $ x500uniqueIdentifier $ displayName $ networkAddress $ \
+         employeeNumber $ employeeType $ homePostalAddress $ userSMIMECertificate $ uid $ mail $ roomNumber $ \
+         photo $ manager $ homePhone $ secretary $ mobile $ pager $ audio $ jpegPhoto $ carLicense $ \
+         departmentNumber $ preferredLanguage $ userPKCS12 $ labeledURI $ msExchUserCulture ) )",
+    ];
+
+    fn schema(ats: &[&[&str]], ocs: &[&[&str]]) -> Schema {
+        let mut schema = Schema::default();
+        for def in ats.iter().flat_map(|defs| defs.iter()) {
+            schema.add_attribute_type(AttributeType::parse(def).expect(def));
+        }
+        for def in ocs.iter().flat_map(|defs| defs.iter()) {
+            schema.add_object_class(ObjectClass::parse(def).expect(def));
+        }
+        schema
+    }
+
+    #[test]
+    fn openldap_attribute_type() {
+        let at = AttributeType::parse(OPENLDAP_AT[0]).expect("name");
+        assert_eq!(at.oid, "2.5.4.41");
+        assert_eq!(at.names, vec!["name"]);
+        assert_eq!(
+            at.desc.as_deref(),
+            Some("RFC4519: common supertype of name attributes")
+        );
+        assert_eq!(at.equality.as_deref(), Some("caseIgnoreMatch"));
+        assert_eq!(at.syntax.as_deref(), Some("1.3.6.1.4.1.1466.115.121.1.15"));
+        assert_eq!(at.syntax_len, Some(32768));
+        let at = AttributeType::parse(OPENLDAP_AT[7]).expect("createTimestamp");
+        assert!(at.single_value && at.no_user_modification);
+        assert_eq!(at.usage, AttributeUsage::DirectoryOperation);
+        assert_eq!(at.ordering.as_deref(), Some("generalizedTimeOrderingMatch"));
+    }
+
+    #[test]
+    fn ad_attribute_type() {
+        let at = AttributeType::parse(AD_AT[0]).expect("objectGUID");
+        assert_eq!(at.names, vec!["objectGUID"]);
+        assert_eq!(at.syntax.as_deref(), Some("1.3.6.1.4.1.1466.115.121.1.40"));
+        assert!(at.single_value);
+    }
+
+    #[test]
+    fn object_class_lists() {
+        let oc = ObjectClass::parse(OPENLDAP_OC[1]).expect("person");
+        assert_eq!(oc.sup, vec!["top"]);
+        assert_eq!(oc.kind, ObjectClassKind::Structural);
+        assert_eq!(oc.must, vec!["sn", "cn"]);
+        let oc = ObjectClass::parse(AD_OC[0]).expect("AD person");
+        assert_eq!(oc.must, vec!["cn"]);
+        assert_eq!(oc.may.len(), 6);
+        let oc = ObjectClass::parse(OPENLDAP_OC[0]).expect("top");
+        assert_eq!(oc.kind, ObjectClassKind::Abstract);
+        assert_eq!(oc.must, vec!["objectClass"]);
+    }
+
+    #[test]
+    fn extensions_and_escapes() {
+        let at = AttributeType::parse(
+            "( 1.1.1 NAME 'x' DESC 'it\\27s' SYNTAX 1.3.6.1.4.1.1466.115.121.1.15 \
+             X-ORIGIN ( 'RFC 1' 'RFC 2' ) X-SCHEMA-FILE 'core.ldif' )",
+        )
+        .expect("definition");
+        assert_eq!(at.desc.as_deref(), Some("it's"));
+        assert_eq!(
+            at.extensions,
+            vec![
+                (
+                    String::from("X-ORIGIN"),
+                    vec![String::from("RFC 1"), String::from("RFC 2")]
+                ),
+                (
+                    String::from("X-SCHEMA-FILE"),
+                    vec![String::from("core.ldif")]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_definition() {
+        assert!(AttributeType::parse("( 1.1.1 NAME 'x' ").is_err());
+        assert!(AttributeType::parse("1.1.1 NAME 'x'").is_err());
+        assert!(ObjectClass::parse("( 1.1.1 NAME 'x ) ").is_err());
+    }
+
+    #[test]
+    fn binary_detection() {
+        let schema = schema(&[OPENLDAP_AT, AD_AT], &[]);
+        assert!(schema.attribute_is_binary("userCertificate"));
+        assert!(schema.attribute_is_binary("jpegphoto"));
+        assert!(schema.attribute_is_binary("objectGUID"));
+        assert!(schema.attribute_is_binary("nTSecurityDescriptor"));
+        assert!(schema.attribute_is_binary("mail;binary"));
+        assert!(!schema.attribute_is_binary("cn"));
+        assert!(!schema.attribute_is_binary("commonName;lang-en"));
+        assert!(!schema.attribute_is_binary("sAMAccountName"));
+        assert!(!schema.attribute_is_binary("pwdLastSet"));
+        assert!(!schema.attribute_is_binary("accountExpires"));
+        assert!(!schema.attribute_is_binary("unknownAttribute"));
+        assert_eq!(
+            schema.attribute_syntax("surname"),
+            Some("1.3.6.1.4.1.1466.115.121.1.15")
+        );
+    }
+
+    #[test]
+    fn must_may_inherited() {
+        let schema = schema(&[], &[OPENLDAP_OC]);
+        let (must, may) = schema.must_may("inetOrgPerson").expect("inetOrgPerson");
+        assert_eq!(must.len(), 3);
+        for attr in ["objectClass", "sn", "cn"] {
+            assert!(must.iter().any(|a| a == attr), "{}", attr);
+        }
+        for attr in [
+            "mail",
+            "jpegPhoto",
+            "title",
+            "userPassword",
+            "telephoneNumber",
+        ] {
+            assert!(may.iter().any(|a| a == attr), "{}", attr);
+        }
+        assert_eq!(may.iter().filter(|a| *a == "telephoneNumber").count(), 1);
+        assert!(schema.must_may("noSuchClass").is_none());
+    }
+}
@@ -0,0 +1,432 @@
+// SCRAM SASL mechanisms (RFC 5802). The client proves the knowledge of the password
+// without sending it, and verifies that the server also knows it, which protects against
+// a spoofed server. Channel binding is not supported, so the GS2 header is always "n,,".
+// The mechanism enum is always available, so that the signature of the bind method
+// doesn't depend on the __scram__ feature; the exchange itself needs the feature.
+
+#[cfg(feature = "scram")]
+use crate::result::{LdapError, Result};
+
+#[cfg(feature = "scram")]
+use base64::engine::general_purpose::STANDARD;
+#[cfg(feature = "scram")]
+use base64::Engine;
+#[cfg(feature = "scram")]
+use hmac::{Hmac, Mac};
+#[cfg(feature = "scram")]
+use sha1::Sha1;
+#[cfg(feature = "scram")]
+use sha2::{Digest, Sha256};
+
+/// SCRAM mechanism variant, determining the hash function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScramMech {
+    /// __SCRAM-SHA-1__.
+    Sha1,
+    /// __SCRAM-SHA-256__ ([RFC 7677](https://tools.ietf.org/html/rfc7677)).
+    Sha256,
+}
+
+impl ScramMech {
+    /// Return the SASL mechanism name.
+    pub fn name(self) -> &'static str {
+        match self {
+            ScramMech::Sha1 => "SCRAM-SHA-1",
+            ScramMech::Sha256 => "SCRAM-SHA-256",
+        }
+    }
+}
+
+#[cfg(feature = "scram")]
+impl ScramMech {
+    fn hmac(self, key: &[u8], data: &[u8]) -> Vec<u8> {
+        match self {
+            ScramMech::Sha1 => {
+                let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("hmac key");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+            ScramMech::Sha256 => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac key");
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+        }
+    }
+
+    fn hash(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            ScramMech::Sha1 => Sha1::digest(data).to_vec(),
+            ScramMech::Sha256 => Sha256::digest(data).to_vec(),
+        }
+    }
+
+    fn salted_password(self, password: &[u8], salt: &[u8], iterations: u32) -> Vec<u8> {
+        match self {
+            ScramMech::Sha1 => {
+                pbkdf2::pbkdf2_hmac_array::<Sha1, 20>(password, salt, iterations).to_vec()
+            }
+            ScramMech::Sha256 => {
+                pbkdf2::pbkdf2_hmac_array::<Sha256, 32>(password, salt, iterations).to_vec()
+            }
+        }
+    }
+}
+
+#[cfg(feature = "scram")]
+const GS2_HEADER: &str = "n,,";
+// Number of random bytes in the client nonce.
+#[cfg(feature = "scram")]
+const NONCE_LEN: usize = 18;
+// Upper limit for the iteration count sent by the server. Each iteration costs an HMAC
+// computation, so an unbounded count would let a rogue server tie up the client.
+#[cfg(feature = "scram")]
+const MAX_ITERATIONS: u32 = 1_000_000;
+
+#[cfg(feature = "scram")]
+fn protocol_error(msg: &str) -> LdapError {
+    LdapError::ScramProtocol(String::from(msg))
+}
+
+// Escape the characters which are special in the attribute values of SCRAM messages.
+#[cfg(feature = "scram")]
+fn escape_name(name: &str) -> String {
+    name.replace('=', "=3D").replace(',', "=2C")
+}
+
+#[cfg(feature = "scram")]
+pub(crate) fn client_nonce() -> Result<String> {
+    let mut bytes = [0u8; NONCE_LEN];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| LdapError::ScramProtocol(format!("nonce generation failed: {}", e)))?;
+    Ok(STANDARD.encode(bytes))
+}
+
+// Client side of a SCRAM exchange, advanced by the server's messages.
+#[cfg(feature = "scram")]
+pub(crate) struct ScramClient {
+    mech: ScramMech,
+    password: Vec<u8>,
+    nonce: String,
+    client_first_bare: String,
+    server_signature: Option<Vec<u8>>,
+}
+
+#[cfg(feature = "scram")]
+impl ScramClient {
+    // The password should have been prepared with SASLprep; it's used as given, which is
+    // equivalent for ASCII passwords without control characters.
+    pub(crate) fn new(mech: ScramMech, user: &str, password: &str, nonce: String) -> Self {
+        let client_first_bare = format!("n={},r={}", escape_name(user), nonce);
+        ScramClient {
+            mech,
+            password: password.as_bytes().to_vec(),
+            nonce,
+            client_first_bare,
+            server_signature: None,
+        }
+    }
+
+    pub(crate) fn client_first(&self) -> String {
+        format!("{}{}", GS2_HEADER, self.client_first_bare)
+    }
+
+    // Process the server-first message, and return the client-final message.
+    pub(crate) fn client_final(&mut self, server_first: &[u8]) -> Result<String> {
+        let server_first = std::str::from_utf8(server_first)
+            .map_err(|_| protocol_error("server-first not UTF-8"))?;
+        let mut nonce = None;
+        let mut salt = None;
+        let mut iterations = None;
+        for attr in server_first.split(',') {
+            match attr.split_once('=') {
+                Some(("m", _)) => return Err(protocol_error("unsupported mandatory extension")),
+                Some(("r", val)) => nonce = Some(val),
+                Some(("s", val)) => {
+                    salt = Some(
+                        STANDARD
+                            .decode(val)
+                            .map_err(|_| protocol_error("invalid salt"))?,
+                    )
+                }
+                Some(("i", val)) => {
+                    iterations = Some(
+                        val.parse::<u32>()
+                            .ok()
+                            .filter(|i| *i > 0 && *i <= MAX_ITERATIONS)
+                            .ok_or_else(|| protocol_error("invalid iteration count"))?,
+                    )
+                }
+                _ => (),
+            }
+        }
+        let (nonce, salt, iterations) = match (nonce, salt, iterations) {
+            (Some(n), Some(s), Some(i)) => (n, s, i),
+            _ => return Err(protocol_error("incomplete server-first message")),
+        };
+        if !nonce.starts_with(&self.nonce) || nonce.len() == self.nonce.len() {
+            return Err(protocol_error("server nonce doesn't extend client nonce"));
+        }
+        let mech = self.mech;
+        let salted = mech.salted_password(&self.password, &salt, iterations);
+        let client_key = mech.hmac(&salted, b"Client Key");
+        let stored_key = mech.hash(&client_key);
+        let without_proof = format!("c={},r={}", STANDARD.encode(GS2_HEADER), nonce);
+        let auth_message = format!(
+            "{},{},{}",
+            self.client_first_bare, server_first, without_proof
+        );
+        let client_signature = mech.hmac(&stored_key, auth_message.as_bytes());
+        let proof: Vec<u8> = client_key
+            .iter()
+            .zip(client_signature)
+            .map(|(k, s)| k ^ s)
+            .collect();
+        let server_key = mech.hmac(&salted, b"Server Key");
+        self.server_signature = Some(mech.hmac(&server_key, auth_message.as_bytes()));
+        Ok(format!("{},p={}", without_proof, STANDARD.encode(proof)))
+    }
+
+    // Verify the server signature from the server-final message.
+    pub(crate) fn verify(&self, server_final: &[u8]) -> Result<()> {
+        let server_final = std::str::from_utf8(server_final)
+            .map_err(|_| protocol_error("server-final not UTF-8"))?;
+        let expected = match self.server_signature {
+            Some(ref sig) => sig,
+            None => return Err(protocol_error("server-final before server-first")),
+        };
+        match server_final
+            .split(',')
+            .next()
+            .and_then(|a| a.split_once('='))
+        {
+            Some(("v", sig)) if STANDARD.decode(sig).ok().as_ref() == Some(expected) => Ok(()),
+            Some(("v", _)) => Err(LdapError::ScramServerSignature),
+            Some(("e", err)) => Err(LdapError::ScramProtocol(format!("server error: {}", err))),
+            _ => Err(protocol_error("invalid server-final message")),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "scram"))]
+mod test {
+    use super::{ScramClient, ScramMech};
+    use crate::result::LdapError;
+
+    const SHA1_NONCE: &str = "fyko+d2lbbFgONRv9qkxdawL";
+    const SHA1_SERVER_FIRST: &str =
+        "r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=4096";
+    const SHA1_CLIENT_FINAL: &str =
+        "c=biws,r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,p=v0X8v3Bz2T0CJGbJQyF0X+HI4Ts=";
+    const SHA1_SERVER_FINAL: &str = "v=rmF9pqV8S7suAoZWja4dJRkFsKQ=";
+
+    // RFC 7677, section 3.
+    const SHA256_NONCE: &str = "rOprNGfwEbeRWgbNEkqO";
+    const SHA256_SERVER_FIRST: &str = "r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,\
+        s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096";
+    const SHA256_CLIENT_FINAL: &str =
+        "c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,\
+        p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ=";
+    const SHA256_SERVER_FINAL: &str = "v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4=";
+
+    // RFC 5802, section 5.
+    #[test]
+    fn scram_sha1_exchange() {
+        let mut client =
+            ScramClient::new(ScramMech::Sha1, "user", "pencil", String::from(SHA1_NONCE));
+        assert_eq!(
+            client.client_first(),
+            "n,,n=user,r=fyko+d2lbbFgONRv9qkxdawL"
+        );
+        let client_final = client
+            .client_final(SHA1_SERVER_FIRST.as_bytes())
+            .expect("client-final");
+        assert_eq!(client_final, SHA1_CLIENT_FINAL);
+        assert!(client.verify(SHA1_SERVER_FINAL.as_bytes()).is_ok());
+        assert!(matches!(
+            client.verify(b"v=AAAApqV8S7suAoZWja4dJRkFsKQ="),
+            Err(LdapError::ScramServerSignature)
+        ));
+    }
+
+    #[test]
+    fn scram_errors() {
+        let mut client = ScramClient::new(
+            ScramMech::Sha256,
+            "a=b,c",
+            "pencil",
+            String::from("rOprNGfwEbeRWgbNEkqO"),
+        );
+        assert_eq!(
+            client.client_first(),
+            "n,,n=a=3Db=2Cc,r=rOprNGfwEbeRWgbNEkqO"
+        );
+        assert!(client.verify(b"v=AAAA").is_err());
+        assert!(client
+            .client_final(b"r=somethingelse,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096")
+            .is_err());
+        assert!(client
+            .client_final(b"r=rOprNGfwEbeRWgbNEkqO,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096")
+            .is_err());
+        assert!(client
+            .client_final(b"r=rOprNGfwEbeRWgbNEkqOxyz,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=0")
+            .is_err());
+        assert!(matches!(
+            client
+                .client_final(b"r=rOprNGfwEbeRWgbNEkqOxyz,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4294967295"),
+            Err(LdapError::ScramProtocol(_))
+        ));
+    }
+
+    // The server side of the exchanges below is scripted with the test vectors, and
+    // fails the bind if the client's messages don't match them.
+    #[cfg(feature = "test-util")]
+    mod bind {
+        use super::*;
+        use crate::conn::LdapConnAsync;
+        use crate::ldap::Ldap;
+        use crate::testkit::{done, sasl_bind, MockHandle, MockServer, Request, Response};
+
+        fn sasl_creds(req: &Request) -> Vec<u8> {
+            req.op
+                .clone()
+                .expect_constructed()
+                .and_then(|op| op.into_iter().nth(2))
+                .and_then(|auth| auth.expect_constructed())
+                .and_then(|auth| auth.into_iter().nth(1))
+                .and_then(|creds| creds.expect_primitive())
+                .unwrap_or_default()
+        }
+
+        fn scripted_server(
+            client_first: &'static str,
+            server_first: &'static str,
+            client_final: &'static str,
+            server_final: Vec<Response>,
+        ) -> MockServer {
+            MockServer::new().on_bind(move |req| {
+                let creds = sasl_creds(req);
+                if creds == client_first.as_bytes() {
+                    vec![sasl_bind(14, server_first.as_bytes())]
+                } else if creds == client_final.as_bytes() {
+                    server_final.clone()
+                } else if creds.is_empty() {
+                    vec![done(0)]
+                } else {
+                    vec![done(49)]
+                }
+            })
+        }
+
+        async fn connect(server: &MockHandle) -> Ldap {
+            let (conn, ldap) = LdapConnAsync::new(&server.url()).await.expect("connection");
+            crate::drive!(conn);
+            ldap
+        }
+
+        fn sha1_server(server_final: Vec<Response>) -> MockServer {
+            scripted_server(
+                "n,,n=user,r=fyko+d2lbbFgONRv9qkxdawL",
+                SHA1_SERVER_FIRST,
+                SHA1_CLIENT_FINAL,
+                server_final,
+            )
+        }
+
+        fn sha1_client() -> ScramClient {
+            ScramClient::new(ScramMech::Sha1, "user", "pencil", String::from(SHA1_NONCE))
+        }
+
+        #[tokio::test]
+        async fn scram_sha1_bind() {
+            let server = sha1_server(vec![sasl_bind(0, SHA1_SERVER_FINAL.as_bytes())])
+                .start()
+                .await
+                .expect("server");
+            let mut ldap = connect(&server).await;
+            let res = ldap
+                .sasl_scram_exchange(ScramMech::Sha1, sha1_client())
+                .await
+                .expect("bind");
+            assert_eq!(res.rc, 0);
+            let mechs: Vec<_> = server
+                .requests()
+                .iter()
+                .map(|req| {
+                    req.op.clone().expect_constructed().unwrap()[2]
+                        .clone()
+                        .expect_constructed()
+                        .unwrap()[0]
+                        .clone()
+                        .expect_primitive()
+                        .unwrap()
+                })
+                .collect();
+            assert_eq!(
+                mechs,
+                vec![b"SCRAM-SHA-1".to_vec(), b"SCRAM-SHA-1".to_vec()]
+            );
+        }
+
+        #[tokio::test]
+        async fn scram_sha256_bind_final_challenge() {
+            let server = scripted_server(
+                "n,,n=user,r=rOprNGfwEbeRWgbNEkqO",
+                SHA256_SERVER_FIRST,
+                SHA256_CLIENT_FINAL,
+                vec![sasl_bind(14, SHA256_SERVER_FINAL.as_bytes())],
+            )
+            .start()
+            .await
+            .expect("server");
+            let mut ldap = connect(&server).await;
+            let client = ScramClient::new(
+                ScramMech::Sha256,
+                "user",
+                "pencil",
+                String::from(SHA256_NONCE),
+            );
+            let res = ldap
+                .sasl_scram_exchange(ScramMech::Sha256, client)
+                .await
+                .expect("bind");
+            assert_eq!(res.rc, 0);
+            assert_eq!(server.requests().len(), 3);
+        }
+
+        #[tokio::test]
+        async fn scram_bad_server_signature() {
+            let server = sha1_server(vec![sasl_bind(0, b"v=AAAApqV8S7suAoZWja4dJRkFsKQ=")])
+                .start()
+                .await
+                .expect("server");
+            let mut ldap = connect(&server).await;
+            let res = ldap
+                .sasl_scram_exchange(ScramMech::Sha1, sha1_client())
+                .await;
+            assert!(matches!(res, Err(LdapError::ScramServerSignature)));
+        }
+
+        #[tokio::test]
+        async fn scram_missing_server_final() {
+            let server = sha1_server(vec![done(0)]).start().await.expect("server");
+            let mut ldap = connect(&server).await;
+            let res = ldap
+                .sasl_scram_exchange(ScramMech::Sha1, sha1_client())
+                .await;
+            assert!(matches!(res, Err(LdapError::ScramProtocol(_))));
+        }
+
+        #[tokio::test]
+        async fn scram_wrong_password() {
+            let server = sha1_server(vec![done(0)]).start().await.expect("server");
+            let mut ldap = connect(&server).await;
+            let client = ScramClient::new(ScramMech::Sha1, "user", "pen", String::from(SHA1_NONCE));
+            let res = ldap
+                .sasl_scram_exchange(ScramMech::Sha1, client)
+                .await
+                .expect("bind");
+            assert_eq!(res.rc, 49);
+        }
+    }
+}
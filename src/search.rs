@@ -1,35 +1,105 @@
-use std::collections::HashMap;
-use std::fmt::Debug;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Debug};
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 
 use crate::adapters::Adapter;
+use crate::attr_desc;
+use crate::cancel::{CancelCause, OpCancellation};
 use crate::controls::Control;
-use crate::ldap::Ldap;
+use crate::exop::Cancel;
+use crate::ldap::{validate_dn, Ldap};
+use crate::mapping::{EntryMapError, FromAttr};
 use crate::parse_filter;
 use crate::protocol::LdapOp;
 use crate::result::{LdapError, LdapResult, Result};
+use crate::rt;
+use crate::schema::Schema;
+use crate::RequestId;
 
+use bytes::{Bytes, BytesMut};
+use futures_util::stream::Stream;
 use tokio::sync::{mpsc, Mutex};
-use tokio::time;
 
 use lber::common::TagClass;
-use lber::structure::StructureTag;
-use lber::structures::{Boolean, Enumerated, Integer, OctetString, Sequence, Tag};
+use lber::parse::parse_tag;
+use lber::structure::{StructureTag, PL};
+use lber::structures::{ASNTag, Boolean, Enumerated, Integer, OctetString, Sequence, Set, Tag};
+use lber::write;
 
 /// Possible values for search scope.
+///
+/// A scope can be parsed from, and is displayed as, the token used in LDAP URLs:
+/// `"base"`, `"one"`, or `"sub"`, and `"children"` for the subordinate subtree scope.
+/// Parsing is case-insensitive, and also accepts `"onelevel"`, `"subtree"`, and
+/// `"subordinate"`. With the __serde__ feature, the values are represented by the
+/// same tokens.
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Scope {
     /// Base object; search only the object named in the base DN.
+    #[cfg_attr(feature = "serde", serde(rename = "base"))]
     Base = 0,
     /// Search the objects immediately below the base DN.
+    #[cfg_attr(feature = "serde", serde(rename = "one"))]
     OneLevel = 1,
     /// Search the object named in the base DN and the whole subtree below it.
+    #[cfg_attr(feature = "serde", serde(rename = "sub"))]
     Subtree = 2,
+    /// Search the whole subtree below the base DN, excluding the object named in the
+    /// base DN ([draft-sermersheim-ldap-subordinate-scope](https://tools.ietf.org/html/draft-sermersheim-ldap-subordinate-scope-02)).
+    ///
+    /// __Note__: not all servers support this scope; OpenLDAP does, but for others,
+    /// consult the server documentation.
+    #[cfg_attr(feature = "serde", serde(rename = "children"))]
+    Children = 3,
+}
+
+impl FromStr for Scope {
+    type Err = LdapError;
+
+    fn from_str(s: &str) -> Result<Scope> {
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "base" => Scope::Base,
+            "one" | "onelevel" => Scope::OneLevel,
+            "sub" | "subtree" => Scope::Subtree,
+            "children" | "subordinate" => Scope::Children,
+            _ => return Err(LdapError::InvalidScopeString(s.into())),
+        })
+    }
+}
+
+impl TryFrom<&str> for Scope {
+    type Error = LdapError;
+
+    fn try_from(s: &str) -> Result<Scope> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Scope::Base => "base",
+            Scope::OneLevel => "one",
+            Scope::Subtree => "sub",
+            Scope::Children => "children",
+        })
+    }
 }
 
 /// Possible values for alias dereferencing during search.
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum DerefAliases {
     /// Never dereference.
     #[default]
@@ -42,22 +112,162 @@ pub enum DerefAliases {
     Always = 3,
 }
 
+impl FromStr for DerefAliases {
+    type Err = LdapError;
+
+    /// Parse the lowercase name of the variant, as used by the `-a` option of OpenLDAP
+    /// tools and the __serde__ representation. Parsing is case-insensitive, and also
+    /// accepts `"search"` and `"find"`.
+    fn from_str(s: &str) -> Result<DerefAliases> {
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "never" => DerefAliases::Never,
+            "searching" | "search" => DerefAliases::Searching,
+            "finding" | "find" => DerefAliases::Finding,
+            "always" => DerefAliases::Always,
+            _ => return Err(LdapError::InvalidDerefString(s.into())),
+        })
+    }
+}
+
+impl TryFrom<&str> for DerefAliases {
+    type Error = LdapError;
+
+    fn try_from(s: &str) -> Result<DerefAliases> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for DerefAliases {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DerefAliases::Never => "never",
+            DerefAliases::Searching => "searching",
+            DerefAliases::Finding => "finding",
+            DerefAliases::Always => "always",
+        })
+    }
+}
+
+/// Case of the attribute names in returned entries.
+///
+/// Servers differ in whether they return attribute names with the casing from the schema or
+/// from the request. Setting this option with
+/// [`SearchOptions::attr_case()`](struct.SearchOptions.html#method.attr_case) makes the names
+/// in the entries of a Search predictable. Attribute options are kept in every case.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum AttrCase {
+    /// Leave the names as returned by the server.
+    #[default]
+    AsReturned,
+    /// Convert the names to lowercase.
+    Lowercase,
+    /// Replace each name with the matching one from the attribute list of the request,
+    /// compared case-insensitively. Names which aren't on the list are left as returned.
+    AsRequested,
+}
+
+// Rename the attributes of a SearchResultEntry according to `case`. A returned name
+// matches a requested one if the whole descriptions or just the base names are equal
+// ignoring case; in the latter case, the returned options are kept.
+fn normalize_attr_names<S: AsRef<str>>(tag: &mut StructureTag, case: AttrCase, requested: &[S]) {
+    if case == AttrCase::AsReturned || tag.id != 4 {
+        return;
+    }
+    let attrs = match tag.payload {
+        PL::C(ref mut parts) => match parts.get_mut(1).map(|attrs| &mut attrs.payload) {
+            Some(PL::C(attrs)) => attrs,
+            _ => return,
+        },
+        PL::P(_) => return,
+    };
+    for attr in attrs {
+        let name = match attr.payload {
+            PL::C(ref mut parts) => match parts.first_mut() {
+                Some(name) => name,
+                None => continue,
+            },
+            PL::P(_) => continue,
+        };
+        let PL::P(ref bytes) = name.payload else {
+            continue;
+        };
+        let Ok(desc) = std::str::from_utf8(bytes) else {
+            continue;
+        };
+        let renamed = match case {
+            AttrCase::AsReturned => continue,
+            AttrCase::Lowercase => desc.to_ascii_lowercase(),
+            AttrCase::AsRequested => {
+                let base = attr_desc::base_name(desc);
+                let exact = requested
+                    .iter()
+                    .find(|r| r.as_ref().eq_ignore_ascii_case(desc));
+                let by_base = || {
+                    requested
+                        .iter()
+                        .map(|r| attr_desc::base_name(r.as_ref()))
+                        .find(|r| r.eq_ignore_ascii_case(base))
+                };
+                match (exact, by_base()) {
+                    (Some(r), _) => String::from(r.as_ref()),
+                    (None, Some(r)) => format!("{}{}", r, &desc[base.len()..]),
+                    (None, None) => continue,
+                }
+            }
+        };
+        if renamed != desc {
+            name.payload = PL::P(Bytes::from(renamed));
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum SearchItem {
-    Entry(StructureTag),
-    Referral(StructureTag),
+    Entry(StructureTag, Option<Bytes>),
+    Referral(StructureTag, Option<Bytes>),
     Done(LdapResult),
+    Failed(LdapError),
 }
 
 /// Wrapper for the internal structure of a result entry.
+///
+/// The second element holds the response controls which the server attached to the
+/// message carrying the entry, such as the Sync State control of a content
+/// synchronization operation. They are also accessible with
+/// [`controls()`](#method.controls).
 #[derive(Debug, Clone)]
 #[non_exhaustive]
-pub struct ResultEntry(pub StructureTag, pub Vec<Control>);
+pub struct ResultEntry(pub StructureTag, pub Vec<Control>, pub(crate) Option<Bytes>);
 
 impl ResultEntry {
     #[doc(hidden)]
     pub fn new(st: StructureTag) -> ResultEntry {
-        ResultEntry(st, vec![])
+        ResultEntry(st, vec![], None)
+    }
+
+    /// Return the response controls of the entry.
+    pub fn controls(&self) -> &[Control] {
+        &self.1
+    }
+
+    /// Return the encoding of the entry exactly as it was received from the server.
+    ///
+    /// Like [`to_bytes()`](#method.to_bytes), the encoding contains just the
+    /// SearchResultEntry or SearchResultReference element, without the message envelope
+    /// and the response controls, so that it doesn't depend on the message ID. Unlike
+    /// it, the bytes aren't re-encoded from the parsed structure, and don't reflect any
+    /// changes made to it by the library, such as the normalization of attribute names.
+    ///
+    /// Returns `None` unless the connection was opened with
+    /// [`set_keep_raw_frames(true)`](struct.LdapConnSettings.html#method.set_keep_raw_frames),
+    /// or if the entry wasn't received from the server as such.
+    pub fn raw(&self) -> Option<&Bytes> {
+        self.2.as_ref()
     }
 
     /// Returns true if the enclosed entry is a referral.
@@ -69,16 +279,136 @@ impl ResultEntry {
     pub fn is_intermediate(&self) -> bool {
         self.0.id == 25
     }
+
+    /// Return the BER encoding of the enclosed protocol element.
+    ///
+    /// The encoding contains just the SearchResultEntry or SearchResultReference
+    /// element, without the message envelope. Response controls are not included.
+    /// The result can be turned back into a `ResultEntry` with
+    /// [`from_bytes()`](#method.from_bytes).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = BytesMut::new();
+        write::encode_into(&mut buf, self.0.clone()).expect("encoded");
+        buf.to_vec()
+    }
+
+    /// Parse a BER-encoded SearchResultEntry or SearchResultReference.
+    ///
+    /// The blob must contain exactly one protocol element, as produced by
+    /// [`to_bytes()`](#method.to_bytes). The element is checked for structural
+    /// validity, so that an entry can be safely passed to
+    /// [`SearchEntry::construct()`](struct.SearchEntry.html#method.construct).
+    pub fn from_bytes(bytes: &[u8]) -> Result<ResultEntry> {
+        let tag = match parse_tag(bytes) {
+            Ok((&[], tag)) => tag,
+            Ok(_) => return Err(LdapError::MalformedEntry("trailing data")),
+            Err(_) => return Err(LdapError::MalformedEntry("BER decoding error")),
+        };
+        if tag.class != TagClass::Application {
+            return Err(LdapError::MalformedEntry(
+                "not an application-class element",
+            ));
+        }
+        match tag.id {
+            4 => validate_entry(&tag)?,
+            19 => validate_refs(&tag)?,
+            _ => return Err(LdapError::MalformedEntry("not an entry or a reference")),
+        }
+        Ok(ResultEntry::new(tag))
+    }
+}
+
+// Length of the BER encoding of the tag.
+fn ber_len(tag: &StructureTag) -> usize {
+    let content = match tag.payload {
+        PL::P(ref bytes) => bytes.len(),
+        PL::C(ref tags) => tags.iter().map(ber_len).sum(),
+    };
+    let id_len = if tag.id < 31 {
+        1
+    } else {
+        1 + (64 - tag.id.leading_zeros() as usize).div_ceil(7)
+    };
+    let len_len = if content < 128 {
+        1
+    } else {
+        1 + (usize::BITS - content.leading_zeros()).div_ceil(8) as usize
+    };
+    id_len + len_len + content
+}
+
+fn utf8_string(tag: &StructureTag) -> Option<&str> {
+    match tag.payload {
+        PL::P(ref bytes) => std::str::from_utf8(bytes).ok(),
+        PL::C(_) => None,
+    }
+}
+
+fn constructed(tag: &StructureTag) -> Option<&[StructureTag]> {
+    match tag.payload {
+        PL::C(ref tags) => Some(tags),
+        PL::P(_) => None,
+    }
+}
+
+fn validate_entry(tag: &StructureTag) -> Result<()> {
+    let comps = constructed(tag).ok_or(LdapError::MalformedEntry("primitive entry"))?;
+    if comps.len() != 2 {
+        return Err(LdapError::MalformedEntry("wrong number of entry elements"));
+    }
+    utf8_string(&comps[0]).ok_or(LdapError::MalformedEntry("invalid DN"))?;
+    let attrs =
+        constructed(&comps[1]).ok_or(LdapError::MalformedEntry("invalid attribute list"))?;
+    for attr in attrs {
+        let part_attr =
+            constructed(attr).ok_or(LdapError::MalformedEntry("invalid partial attribute"))?;
+        if part_attr.len() != 2 {
+            return Err(LdapError::MalformedEntry("invalid partial attribute"));
+        }
+        utf8_string(&part_attr[0]).ok_or(LdapError::MalformedEntry("invalid attribute type"))?;
+        let vals = constructed(&part_attr[1])
+            .ok_or(LdapError::MalformedEntry("invalid attribute values"))?;
+        if vals.iter().any(|v| matches!(v.payload, PL::C(_))) {
+            return Err(LdapError::MalformedEntry("invalid attribute value"));
+        }
+    }
+    Ok(())
+}
+
+fn validate_refs(tag: &StructureTag) -> Result<()> {
+    let refs = constructed(tag).ok_or(LdapError::MalformedEntry("primitive reference"))?;
+    if refs.iter().any(|r| utf8_string(r).is_none()) {
+        return Err(LdapError::MalformedEntry("invalid referral URI"));
+    }
+    Ok(())
 }
 
 /// Additional parameters for the Search operation.
+///
+/// A field is `None` until the corresponding option is set, which lets the options
+/// which were set explicitly be told apart from the defaults. An unset option is sent
+/// with the value prescribed by RFC 4511 as the default: aliases are never dereferenced,
+/// there are no size and time limits, and both attribute names and values are returned.
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 #[non_exhaustive]
 pub struct SearchOptions {
-    pub deref: DerefAliases,
-    pub typesonly: bool,
-    pub timelimit: i32,
-    pub sizelimit: i32,
+    pub deref: Option<DerefAliases>,
+    pub typesonly: Option<bool>,
+    pub timelimit: Option<i32>,
+    pub sizelimit: Option<i32>,
+    pub client_max_entries: Option<usize>,
+    pub client_max_bytes: Option<usize>,
+    pub attr_case: Option<AttrCase>,
+    pub first_entry_timeout: Option<Duration>,
+    pub total_timeout: Option<Duration>,
+    pub max_value_size: Option<usize>,
+    pub max_values_per_attr: Option<usize>,
+    pub strict_value_limits: Option<bool>,
 }
 
 impl SearchOptions {
@@ -91,13 +421,13 @@ impl SearchOptions {
 
     /// Set the method for dereferencing aliases.
     pub fn deref(mut self, d: DerefAliases) -> Self {
-        self.deref = d;
+        self.deref = Some(d);
         self
     }
 
     /// Set the indicator of returning just attribute names (`true`) vs. names and values (`false`).
     pub fn typesonly(mut self, typesonly: bool) -> Self {
-        self.typesonly = typesonly;
+        self.typesonly = Some(typesonly);
         self
     }
 
@@ -106,13 +436,498 @@ impl SearchOptions {
     /// This is a server-side limit of the elapsed time for performing the operation, _not_ a
     /// network timeout for retrieving result entries or the result of the whole operation.
     pub fn timelimit(mut self, timelimit: i32) -> Self {
-        self.timelimit = timelimit;
+        self.timelimit = Some(timelimit);
         self
     }
 
     /// Set the size limit, in entries, for the whole search operation.
     pub fn sizelimit(mut self, sizelimit: i32) -> Self {
-        self.sizelimit = sizelimit;
+        self.sizelimit = Some(sizelimit);
+        self
+    }
+
+    /// Set the maximum number of entries and referrals which a streaming Search will
+    /// accept from the server.
+    ///
+    /// Unlike the size limit, this limit is enforced by the client, and protects against
+    /// servers which ignore the requested limits. When the limit is exceeded, the
+    /// operation is abandoned, and the stream returns `LdapError::ClientSideLimit`. For
+    /// a paged Search, the limit applies to the total across all pages.
+    pub fn client_max_entries(mut self, max_entries: usize) -> Self {
+        self.client_max_entries = Some(max_entries);
+        self
+    }
+
+    /// Set the maximum cumulative size, in bytes of BER encoding, of the entries and
+    /// referrals which a streaming Search will accept from the server. Exceeding the
+    /// limit has the same effect as for [`client_max_entries()`](#method.client_max_entries).
+    pub fn client_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.client_max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Set the case of the attribute names in the entries returned by the Search. The
+    /// names are changed in the raw entries, before they reach any adapter or
+    /// [`SearchEntry::construct()`](struct.SearchEntry.html#method.construct).
+    /// This is a client-side option, not sent to the server.
+    pub fn attr_case(mut self, case: AttrCase) -> Self {
+        self.attr_case = Some(case);
+        self
+    }
+
+    /// Set the time within which the first entry, referral, or intermediate message of
+    /// a streaming Search must arrive. Once an item has arrived, the timeout doesn't
+    /// apply anymore. If it expires, the stream returns `LdapError::FirstEntryTimeout`.
+    ///
+    /// This and the [total timeout](#method.total_timeout) are measured from the start
+    /// of the Search, and are independent of the per-item timeout set by
+    /// [`Ldap::with_timeout()`](struct.Ldap.html#method.with_timeout) and the deadline
+    /// set by [`Ldap::with_deadline()`](struct.Ldap.html#method.with_deadline):
+    ///
+    /// * the per-item timeout is restarted for each received item, and its expiry
+    ///   returns `LdapError::Timeout`;
+    ///
+    /// * the first entry timeout covers the wait for the first item only;
+    ///
+    /// * the total timeout covers the whole Search, up to and including the final
+    ///   result, and its expiry returns `LdapError::SearchTotalTimeout`;
+    ///
+    /// * the deadline is a fixed instant, which also covers the operations made by
+    ///   adapters, and its expiry returns `LdapError::DeadlineExceeded`.
+    ///
+    /// Any combination can be set; whichever expires first ends the Search, which is then
+    /// abandoned like an operation which has timed out. For a paged Search, the first
+    /// entry and total timeouts apply across all pages.
+    pub fn first_entry_timeout(mut self, timeout: Duration) -> Self {
+        self.first_entry_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the time within which a streaming Search must complete. If it expires, the
+    /// stream returns `LdapError::SearchTotalTimeout`. See
+    /// [`first_entry_timeout()`](#method.first_entry_timeout) for the interaction
+    /// with other timeouts.
+    pub fn total_timeout(mut self, timeout: Duration) -> Self {
+        self.total_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the maximum size, in bytes, of a single attribute value. `None` removes
+    /// the limit. See [`ValueLimits`](struct.ValueLimits.html) for the enforcement.
+    pub fn max_value_size(mut self, max_size: Option<usize>) -> Self {
+        self.max_value_size = max_size;
+        self
+    }
+
+    /// Set the maximum number of values of a single attribute. `None` removes the
+    /// limit. See [`ValueLimits`](struct.ValueLimits.html) for the enforcement.
+    pub fn max_values_per_attr(mut self, max_values: Option<usize>) -> Self {
+        self.max_values_per_attr = max_values;
+        self
+    }
+
+    /// Make the value limits strict: a streaming Search returning an entry which exceeds
+    /// them is abandoned, and the stream returns `LdapError::ValueLimitExceeded`.
+    pub fn strict_value_limits(mut self, strict: bool) -> Self {
+        self.strict_value_limits = Some(strict);
+        self
+    }
+
+    /// Return the value limits set in the options, for constructing the entries with
+    /// [`SearchEntry::try_construct_with()`](struct.SearchEntry.html#method.try_construct_with).
+    pub fn value_limits(&self) -> ValueLimits {
+        ValueLimits {
+            max_value_size: self.max_value_size,
+            max_values_per_attr: self.max_values_per_attr,
+            strict: self.strict_value_limits.unwrap_or(false),
+        }
+    }
+}
+
+/// Limits on the size and number of attribute values in a search entry.
+///
+/// The limits guard against entries with huge values, which could exhaust the memory
+/// of a client which handles them. Sizes are those of the raw values, in bytes. The
+/// limits can be enforced in two ways:
+///
+/// * Lossy, by [`SearchEntry::try_construct_with()`](struct.SearchEntry.html#method.try_construct_with)
+///   if the limits aren't strict. The values over the count limit are dropped, and each
+///   value over the size limit is replaced by an empty value. The changes are recorded
+///   in the `truncated` field of the entry.
+///
+/// * Strict, by `try_construct_with()` if the limits are strict, which returns an error
+///   for an entry exceeding them. If the strict limits are set in the
+///   [`SearchOptions`](struct.SearchOptions.html) of a Search, the entries are also
+///   checked as they arrive, before being returned by the stream, and the Search is
+///   abandoned at the first one which fails the check.
+///
+/// Checking the limits doesn't reduce the memory needed for receiving an entry; it
+/// keeps the oversized values from being copied into the parsed entry. To bound the
+/// size of the received data, use
+/// [`SearchOptions::client_max_bytes()`](struct.SearchOptions.html#method.client_max_bytes).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ValueLimits {
+    max_value_size: Option<usize>,
+    max_values_per_attr: Option<usize>,
+    strict: bool,
+}
+
+impl ValueLimits {
+    /// Create an instance without any limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum size, in bytes, of a single attribute value.
+    pub fn max_value_size(mut self, max_size: Option<usize>) -> Self {
+        self.max_value_size = max_size;
+        self
+    }
+
+    /// Set the maximum number of values of a single attribute.
+    pub fn max_values_per_attr(mut self, max_values: Option<usize>) -> Self {
+        self.max_values_per_attr = max_values;
+        self
+    }
+
+    /// Make the limits strict, so that exceeding them is an error.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    fn is_set(&self) -> bool {
+        self.max_value_size.is_some() || self.max_values_per_attr.is_some()
+    }
+
+    // Check the values of an attribute against the limits.
+    fn check<V: AsRef<[u8]>>(&self, attr: &str, values: &[V]) -> Result<()> {
+        let size = values.iter().map(|v| v.as_ref().len()).max().unwrap_or(0);
+        if self
+            .max_values_per_attr
+            .is_some_and(|max| values.len() > max)
+            || self.max_value_size.is_some_and(|max| size > max)
+        {
+            return Err(LdapError::ValueLimitExceeded {
+                attr: String::from(attr),
+                size,
+                values: values.len(),
+            });
+        }
+        Ok(())
+    }
+
+    // Drop the values over the count limit, and replace the oversized values with empty
+    // ones, returning the note about the changes, if any.
+    fn truncate(&self, attr: &str, values: &mut Vec<Bytes>) -> Option<TruncationNote> {
+        let original_size = values.iter().map(Bytes::len).sum();
+        let values_dropped = self
+            .max_values_per_attr
+            .map_or(0, |max| values.len().saturating_sub(max));
+        values.truncate(values.len() - values_dropped);
+        let mut values_truncated = 0;
+        if let Some(max) = self.max_value_size {
+            for value in values.iter_mut().filter(|v| v.len() > max) {
+                *value = Bytes::new();
+                values_truncated += 1;
+            }
+        }
+        if values_dropped == 0 && values_truncated == 0 {
+            return None;
+        }
+        Some(TruncationNote {
+            attr: String::from(attr),
+            original_size,
+            values_dropped,
+            values_truncated,
+        })
+    }
+}
+
+/// Record of an attribute changed by the lossy enforcement of
+/// [`ValueLimits`](struct.ValueLimits.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TruncationNote {
+    /// Attribute description.
+    pub attr: String,
+    /// Total size in bytes of the values of the attribute, as received.
+    pub original_size: usize,
+    /// Number of values dropped for exceeding the count limit.
+    pub values_dropped: usize,
+    /// Number of values replaced by an empty value for exceeding the size limit.
+    pub values_truncated: usize,
+}
+
+// Check the attribute values of a raw entry against strict limits.
+fn check_value_limits(tag: &StructureTag, limits: &ValueLimits) -> Result<()> {
+    let attrs = constructed(tag)
+        .and_then(|comps| comps.get(1))
+        .and_then(constructed)
+        .unwrap_or_default();
+    for attr in attrs {
+        let part_attr = constructed(attr).unwrap_or_default();
+        let (name, vals) = match part_attr {
+            [name, vals] => (name, constructed(vals).unwrap_or_default()),
+            _ => continue,
+        };
+        let vals: Vec<&[u8]> = vals
+            .iter()
+            .filter_map(|v| match v.payload {
+                PL::P(ref bytes) => Some(&bytes[..]),
+                PL::C(_) => None,
+            })
+            .collect();
+        limits.check(utf8_string(name).unwrap_or_default(), &vals)?;
+    }
+    Ok(())
+}
+
+// Limit on the wait for the next item of a Search.
+#[derive(Clone, Copy, Debug)]
+enum WaitLimit {
+    Deadline,
+    FirstEntry,
+    Total,
+}
+
+// Encode a SearchRequest. The options which aren't set are given their RFC 4511 defaults:
+// no dereferencing, no size or time limit, and both attribute names and values returned.
+fn search_request<S: AsRef<str>>(
+    base: &str,
+    scope: Scope,
+    filter: &str,
+    attrs: &[S],
+    opts: &SearchOptions,
+) -> Result<Tag> {
+    Ok(Tag::Sequence(Sequence {
+        id: 3,
+        class: TagClass::Application,
+        inner: vec![
+            Tag::OctetString(OctetString {
+                inner: Vec::from(base.as_bytes()),
+                ..Default::default()
+            }),
+            Tag::Enumerated(Enumerated {
+                inner: scope as i64,
+                ..Default::default()
+            }),
+            Tag::Enumerated(Enumerated {
+                inner: opts.deref.unwrap_or_default() as i64,
+                ..Default::default()
+            }),
+            Tag::Integer(Integer {
+                inner: opts.sizelimit.unwrap_or(0) as i64,
+                ..Default::default()
+            }),
+            Tag::Integer(Integer {
+                inner: opts.timelimit.unwrap_or(0) as i64,
+                ..Default::default()
+            }),
+            Tag::Boolean(Boolean {
+                inner: opts.typesonly.unwrap_or(false),
+                ..Default::default()
+            }),
+            match parse_filter(filter) {
+                Ok(filter) => filter,
+                _ => return Err(LdapError::FilterParsing),
+            },
+            Tag::Sequence(Sequence {
+                inner: attrs
+                    .iter()
+                    .map(|s| {
+                        Tag::OctetString(OctetString {
+                            inner: Vec::from(s.as_ref()),
+                            ..Default::default()
+                        })
+                    })
+                    .collect(),
+                ..Default::default()
+            }),
+        ],
+    }))
+}
+
+/// Rules for deciding which attributes have binary values.
+///
+/// Set with [`ConstructOptions::binary_policy()`](struct.ConstructOptions.html#method.binary_policy).
+/// An attribute is considered binary if its description has the `binary` option, if its
+/// name or OID is in the policy's set, or if the schema attached to the policy gives it
+/// a binary syntax. Name and OID matching is case-insensitive and ignores attribute
+/// options. When a schema is attached, an attribute is also matched through the other
+/// names and the OID of its type, so that listing `userCertificate` will cover `2.5.4.36`.
+///
+/// The default policy contains the commonly encountered binary attributes: certificates
+/// and CRLs, photos, Active Directory GUIDs and SIDs, and Kerberos keys.
+#[derive(Clone, Debug)]
+pub struct BinaryPolicy {
+    names: HashSet<String>,
+    schema: Option<Arc<Schema>>,
+    binary_option: bool,
+}
+
+const DEFAULT_BINARY_ATTRS: &[&str] = &[
+    "userCertificate",
+    "2.5.4.36",
+    "cACertificate",
+    "2.5.4.37",
+    "authorityRevocationList",
+    "certificateRevocationList",
+    "crossCertificatePair",
+    "userSMIMECertificate",
+    "userPKCS12",
+    "jpegPhoto",
+    "0.9.2342.19200300.100.1.60",
+    "photo",
+    "audio",
+    "thumbnailPhoto",
+    "objectGUID",
+    "1.2.840.113556.1.4.2",
+    "objectSid",
+    "1.2.840.113556.1.4.146",
+    "krbPrincipalKey",
+];
+
+impl Default for BinaryPolicy {
+    fn default() -> Self {
+        let mut policy = BinaryPolicy::empty();
+        for name in DEFAULT_BINARY_ATTRS {
+            policy.names.insert(name.to_ascii_lowercase());
+        }
+        policy
+    }
+}
+
+impl BinaryPolicy {
+    /// Create a policy without any attribute names. Only the `binary` option and the
+    /// UTF-8 validity of the values will be taken into account.
+    pub fn empty() -> Self {
+        BinaryPolicy {
+            names: HashSet::new(),
+            schema: None,
+            binary_option: true,
+        }
+    }
+
+    // Policy of construct(), which decides only by the UTF-8 validity of the values,
+    // as it did before binary policies were introduced.
+    pub(crate) fn utf8_only() -> Self {
+        BinaryPolicy {
+            binary_option: false,
+            ..BinaryPolicy::empty()
+        }
+    }
+
+    /// Add an attribute name or OID to the set of binary attributes.
+    pub fn with_attr(mut self, name: &str) -> Self {
+        self.names
+            .insert(attr_desc::base_name(name).to_ascii_lowercase());
+        self
+    }
+
+    /// Attach the schema to the policy, so that attributes with binary syntaxes are
+    /// recognized. The schema can be retrieved with
+    /// [`Ldap::schema()`](struct.Ldap.html#method.schema).
+    pub fn with_schema(mut self, schema: Arc<Schema>) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Check whether the attribute with the given description is binary.
+    pub fn is_binary(&self, attr: &str) -> bool {
+        if self.binary_option && attr_desc::has_option(attr, "binary") {
+            return true;
+        }
+        if self
+            .names
+            .contains(&attr_desc::base_name(attr).to_ascii_lowercase())
+        {
+            return true;
+        }
+        let schema = match self.schema {
+            Some(ref schema) => schema,
+            None => return false,
+        };
+        if schema.attribute_is_binary(attr) {
+            return true;
+        }
+        match schema.attribute_type(attr) {
+            Some(at) => at
+                .names
+                .iter()
+                .chain(Some(&at.oid))
+                .any(|name| self.names.contains(&name.to_ascii_lowercase())),
+            None => false,
+        }
+    }
+}
+
+/// Handling of an attribute description repeated in a single entry.
+///
+/// A server shouldn't return the same attribute twice in an entry, but some servers and
+/// proxies do. Descriptions are compared case-insensitively, so that `member` and `Member`
+/// are the same attribute. Set with
+/// [`ConstructOptions::on_duplicate()`](struct.ConstructOptions.html#method.on_duplicate).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Duplicate {
+    /// Append the values of each repeated instance to those of the first one, keeping
+    /// their order and any duplicate values. If any instance is binary, the merged
+    /// attribute is placed in `bin_attrs`.
+    #[default]
+    Merge,
+    /// Return [`LdapError::DuplicateAttribute`](result/enum.LdapError.html#variant.DuplicateAttribute).
+    Error,
+    /// Keep the first instance, ignoring the others.
+    KeepFirst,
+    /// Keep the last instance, ignoring the earlier ones.
+    KeepLast,
+}
+
+/// Options for parsing a search result entry with
+/// [`SearchEntry::try_construct_with()`](struct.SearchEntry.html#method.try_construct_with).
+///
+/// The defaults match [`SearchEntry::construct()`](struct.SearchEntry.html#method.construct):
+/// binary attributes recognized only by the UTF-8 validity of their values, no value limits,
+/// and merging of repeated attributes.
+#[derive(Clone, Debug)]
+pub struct ConstructOptions {
+    policy: BinaryPolicy,
+    limits: ValueLimits,
+    on_duplicate: Duplicate,
+}
+
+impl Default for ConstructOptions {
+    fn default() -> Self {
+        ConstructOptions {
+            policy: BinaryPolicy::utf8_only(),
+            limits: ValueLimits::new(),
+            on_duplicate: Duplicate::Merge,
+        }
+    }
+}
+
+impl ConstructOptions {
+    /// Create an instance with the default values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the policy for recognizing binary attributes.
+    pub fn binary_policy(mut self, policy: BinaryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Set the limits on the attribute values. Strict limits are checked after merging
+    /// repeated attributes.
+    pub fn value_limits(mut self, limits: ValueLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Set the handling of repeated attribute descriptions.
+    pub fn on_duplicate(mut self, on_duplicate: Duplicate) -> Self {
+        self.on_duplicate = on_duplicate;
         self
     }
 }
@@ -126,25 +941,97 @@ impl SearchOptions {
 ///
 /// For that reason, the parser tries to convert every value into a `String`. If an
 /// attribute can contain unconstrained binary strings, the conversion may fail. In that case,
-/// the attribute and all its values will be in the `bin_attrs` hashmap. Since it's
-/// possible that a particular set of values for a binary attribute _could_ be
-/// converted into UTF-8 `String`s, the presence of such an attribute in the result
-/// entry should be checked for both in `attrs` and `bin_atrrs`.
+/// the attribute and all its values will be in the `bin_attrs` hashmap. An attribute is
+/// never split between the two maps. Since it's possible that a particular set of values
+/// for a binary attribute _could_ be converted into UTF-8 `String`s, the presence of such
+/// an attribute in the result entry should be checked for both in `attrs` and `bin_atrrs`,
+/// unless the entry was constructed with a [`BinaryPolicy`](struct.BinaryPolicy.html)
+/// which covers the attribute.
+///
+/// With the __serde__ feature, the values of binary attributes are serialized as
+/// Base64 strings.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SearchEntry {
     /// Entry DN.
     pub dn: String,
     /// Attributes.
     pub attrs: HashMap<String, Vec<String>>,
     /// Binary-valued attributes.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::bin_attrs"))]
     pub bin_attrs: HashMap<String, Vec<Vec<u8>>>,
+    /// Attributes changed by enforcing [`ValueLimits`](struct.ValueLimits.html), if the
+    /// entry was constructed with them.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub truncated: Vec<TruncationNote>,
 }
 
 impl SearchEntry {
     /// Parse raw BER data and convert it into attribute map(s).
     ///
+    /// An attribute is placed in `bin_attrs` only if some of its values aren't valid
+    /// UTF-8, even if it has the `binary` option. The values of an attribute repeated in
+    /// the entry are merged, as with [`Duplicate::Merge`](enum.Duplicate.html#variant.Merge).
+    /// Use [`try_construct_with()`](#method.try_construct_with) to classify attributes by
+    /// name, option or schema, to enforce value limits, or to handle repeated attributes
+    /// differently.
+    ///
     /// __Note__: this function will panic on parsing error.
     pub fn construct(re: ResultEntry) -> SearchEntry {
+        match SearchEntry::try_construct_with(re, &ConstructOptions::new()) {
+            Ok(entry) => entry,
+            Err(e) => panic!("entry: {}", e),
+        }
+    }
+
+    /// Parse raw BER data like [`construct()`](#method.construct), first changing the
+    /// attribute names according to `case`. The list of attributes from the Search
+    /// request, `attrs`, is used with `AttrCase::AsRequested`.
+    ///
+    /// __Note__: this function will panic on parsing error.
+    pub fn construct_normalized<S: AsRef<str>>(
+        mut re: ResultEntry,
+        case: AttrCase,
+        attrs: &[S],
+    ) -> SearchEntry {
+        normalize_attr_names(&mut re.0, case, attrs);
+        SearchEntry::construct(re)
+    }
+
+    /// Parse raw BER data like [`construct()`](#method.construct), also returning the
+    /// response controls of the entry.
+    ///
+    /// __Note__: this function will panic on parsing error.
+    pub fn construct_with_controls(mut re: ResultEntry) -> (SearchEntry, Vec<Control>) {
+        let ctrls = std::mem::take(&mut re.1);
+        (SearchEntry::construct(re), ctrls)
+    }
+
+    /// Parse raw BER data with the given options, returning an error if the entry is
+    /// malformed, if it exceeds strict value limits, or if it has a repeated attribute
+    /// and the options call for an error in that case.
+    ///
+    /// The attributes recognized as binary by the [`BinaryPolicy`](struct.BinaryPolicy.html)
+    /// of the options are placed in `bin_attrs` regardless of the UTF-8 validity of their
+    /// values. Limits which aren't strict are enforced in the lossy way, and the attributes
+    /// whose values were dropped or truncated are listed in `truncated`.
+    pub fn try_construct_with(re: ResultEntry, opts: &ConstructOptions) -> Result<SearchEntry> {
+        if re.0.class != TagClass::Application || re.0.id != 4 {
+            return Err(LdapError::MalformedEntry("not a search entry"));
+        }
+        validate_entry(&re.0)?;
+        SearchEntry::construct_inner(re, &opts.policy, &opts.limits, opts.on_duplicate)
+    }
+
+    fn construct_inner(
+        re: ResultEntry,
+        policy: &BinaryPolicy,
+        limits: &ValueLimits,
+        on_duplicate: Duplicate,
+    ) -> Result<SearchEntry> {
         let mut tags =
             re.0.match_id(4)
                 .and_then(|t| t.expect_constructed())
@@ -157,8 +1044,10 @@ impl SearchEntry {
                 .expect("octet string"),
         )
         .expect("dn");
-        let mut attr_vals = HashMap::new();
-        let mut bin_attr_vals = HashMap::new();
+        // Attributes with their values as received, and whether they're binary, with
+        // repeated descriptions resolved. The index is keyed by the lowercased description.
+        let mut received: Vec<(String, Vec<Bytes>, bool)> = vec![];
+        let mut index = HashMap::new();
         let attrs = tags
             .next()
             .expect("element")
@@ -178,45 +1067,169 @@ impl SearchEntry {
                     .expect("octet string"),
             )
             .expect("attribute type");
-            let mut any_binary = false;
             let values = part_attr
                 .next()
                 .expect("element")
                 .expect_constructed()
                 .expect("values")
                 .into_iter()
-                .map(|t| t.expect_primitive().expect("octet string"))
-                .filter_map(|s| {
-                    if let Ok(s) = std::str::from_utf8(s.as_ref()) {
-                        return Some(s.to_owned());
-                    }
-                    bin_attr_vals
-                        .entry(a_type.clone())
-                        .or_insert_with(Vec::new)
-                        .push(s);
-                    any_binary = true;
-                    None
-                })
-                .collect::<Vec<String>>();
-            if any_binary {
-                bin_attr_vals.get_mut(&a_type).expect("bin vector").extend(
-                    values
-                        .into_iter()
-                        .map(String::into_bytes)
-                        .collect::<Vec<Vec<u8>>>(),
-                );
+                .map(|t| t.expect_primitive_bytes().expect("octet string"))
+                .collect::<Vec<_>>();
+            // The attribute is classified by the values as received, before enforcing
+            // the limits.
+            let binary =
+                policy.is_binary(&a_type) || values.iter().any(|v| std::str::from_utf8(v).is_err());
+            let n = match index.entry(a_type.to_ascii_lowercase()) {
+                Entry::Vacant(slot) => {
+                    slot.insert(received.len());
+                    received.push((a_type, values, binary));
+                    continue;
+                }
+                Entry::Occupied(slot) => *slot.get(),
+            };
+            match on_duplicate {
+                Duplicate::Merge => {
+                    let (_, ref mut vals, ref mut bin) = received[n];
+                    vals.extend(values);
+                    *bin |= binary;
+                }
+                Duplicate::Error => return Err(LdapError::DuplicateAttribute(a_type)),
+                Duplicate::KeepFirst => (),
+                Duplicate::KeepLast => received[n] = (a_type, values, binary),
+            }
+        }
+        let mut attr_vals = HashMap::new();
+        let mut bin_attr_vals = HashMap::new();
+        let mut truncated = vec![];
+        for (a_type, mut values, binary) in received {
+            if limits.strict {
+                limits.check(&a_type, &values)?;
+            } else if limits.is_set() {
+                truncated.extend(limits.truncate(&a_type, &mut values));
+            }
+            // Values still reference the received message; each is copied exactly once,
+            // into the representation it ends up in.
+            if binary {
+                bin_attr_vals.insert(a_type, values.into_iter().map(Vec::from).collect());
             } else {
+                let values = values
+                    .into_iter()
+                    .map(|v| String::from_utf8(Vec::from(v)).expect("utf-8 value"))
+                    .collect();
                 attr_vals.insert(a_type, values);
             }
         }
-        SearchEntry {
+        Ok(SearchEntry {
             dn,
             attrs: attr_vals,
             bin_attrs: bin_attr_vals,
-        }
+            truncated,
+        })
+    }
+
+    /// Encode the entry as a SearchResultEntry protocol element, which can be wrapped
+    /// in a [`ResultEntry`](struct.ResultEntry.html) and parsed again by
+    /// [`construct()`](#method.construct). See [`encode_search_entry()`](fn.encode_search_entry.html)
+    /// for the layout of the attributes.
+    pub fn to_structure(&self) -> StructureTag {
+        encode_search_entry(&self.dn, &self.attrs, &self.bin_attrs)
+    }
+
+    /// Convert the entry into a `ResultEntry`, such as would be received from the server.
+    /// The conversion is the inverse of [`construct()`](#method.construct).
+    pub fn into_result_entry(self) -> ResultEntry {
+        ResultEntry::new(self.to_structure())
+    }
+
+    /// Return the values of all attributes whose description has the base name `name`,
+    /// regardless of the options, e.g., both `cn` and `cn;lang-en` for `cn`. The name is
+    /// matched case-insensitively, and any options in it are ignored as well.
+    ///
+    /// This is useful when the server returns an attribute with an option which the
+    /// request specified, like `userCertificate;binary`, or with options which weren't
+    /// requested at all, like language tags. Each element of the returned vector holds
+    /// the attribute description and its values, textual values converted to bytes. The
+    /// elements are sorted by description.
+    pub fn get_ignore_options(&self, name: &str) -> Vec<(&str, Vec<&[u8]>)> {
+        let mut found: Vec<(&str, Vec<&[u8]>)> = self
+            .attrs
+            .iter()
+            .filter(|(desc, _)| attr_desc::matches_name(desc, name))
+            .map(|(desc, vals)| (desc.as_str(), vals.iter().map(String::as_bytes).collect()))
+            .chain(
+                self.bin_attrs
+                    .iter()
+                    .filter(|(desc, _)| attr_desc::matches_name(desc, name))
+                    .map(|(desc, vals)| (desc.as_str(), vals.iter().map(Vec::as_slice).collect())),
+            )
+            .collect();
+        found.sort_by(|a, b| a.0.cmp(b.0));
+        found
+    }
+
+    /// Convert the values of an attribute into the target type. See the
+    /// [`mapping`](mapping/index.html) module for the supported types.
+    pub fn get<T: FromAttr>(&self, attr: &str) -> std::result::Result<T, EntryMapError> {
+        T::from_attr(self, attr)
     }
 }
 
+/// Encode a SearchResultEntry protocol element with the given DN and attributes.
+///
+/// The result is an Application-class tag 4 sequence containing the DN and the list
+/// of partial attributes, as sent by the server. Attributes are encoded in the order of
+/// their names, textual and binary attributes together; an attribute present in both
+/// maps is encoded twice. An attribute with an empty vector of values is encoded with
+/// an empty value set.
+pub fn encode_search_entry(
+    dn: &str,
+    attrs: &HashMap<String, Vec<String>>,
+    bin_attrs: &HashMap<String, Vec<Vec<u8>>>,
+) -> StructureTag {
+    let mut attrs: Vec<(&String, Vec<&[u8]>)> = attrs
+        .iter()
+        .map(|(name, vals)| (name, vals.iter().map(|v| v.as_bytes()).collect()))
+        .chain(
+            bin_attrs
+                .iter()
+                .map(|(name, vals)| (name, vals.iter().map(|v| v.as_slice()).collect())),
+        )
+        .collect();
+    attrs.sort_by(|a, b| a.0.cmp(b.0));
+    let octet_string = |bytes: &[u8]| {
+        Tag::OctetString(OctetString {
+            inner: bytes.to_vec(),
+            ..Default::default()
+        })
+    };
+    Tag::Sequence(Sequence {
+        id: 4,
+        class: TagClass::Application,
+        inner: vec![
+            octet_string(dn.as_bytes()),
+            Tag::Sequence(Sequence {
+                inner: attrs
+                    .into_iter()
+                    .map(|(name, vals)| {
+                        Tag::Sequence(Sequence {
+                            inner: vec![
+                                octet_string(name.as_bytes()),
+                                Tag::Set(Set {
+                                    inner: vals.into_iter().map(octet_string).collect(),
+                                    ..Default::default()
+                                }),
+                            ],
+                            ..Default::default()
+                        })
+                    })
+                    .collect(),
+                ..Default::default()
+            }),
+        ],
+    })
+    .into_structure()
+}
+
 // Not really, IMO
 #[allow(rustdoc::invalid_html_tags)]
 /// Possible states of a `SearchStream`.
@@ -525,7 +1538,10 @@ impl SearchEntry {
 /// inner method, since the adapters may need to keep providing additional entries even when
 /// the original operation is over. Therefore, point (2) occurs at the end of the first call
 /// in the chain (for the adapted streams), or in the shim method (for the direct ones). As before,
-/// any error will result in the `Error` state.
+/// any error will result in the `Error` state. An adapter may keep returning entries after the inner
+/// method has returned `Ok(None)`; if the first call in the chain returns `Ok(None)` while some adapter
+/// still reports pending entries with [`has_pending()`](adapters/trait.Adapter.html#method.has_pending),
+/// the stream stays `Active`, so that the next call goes through the chain again.
 ///
 /// The `finish()` method may be called at any time. Adapters along the way can behave differently
 /// according to the state, and the final direct call will change the state to `Closed` at (3). Calling
@@ -563,7 +1579,23 @@ pub enum StreamState {
 /// to [`finish()`](#method.finish) will return the overall result of the search.
 /// Calling `finish()` earlier will terminate search result processing in the
 /// client; it is the user's responsibility to inform the server that the operation
-/// has been terminated by performing an Abandon or a Cancel operation.
+/// has been terminated by performing an Abandon or a Cancel operation. The
+/// [`abandon()`](#method.abandon) and [`cancel()`](#method.cancel) methods
+/// combine both steps.
+///
+/// Besides entries and referrals, `next()` returns any Intermediate Responses which the
+/// server sends during the search, such as the Sync Info messages of a content
+/// synchronization operation, in the order in which they were received. They can be
+/// recognized with [`ResultEntry::is_intermediate()`](struct.ResultEntry.html#method.is_intermediate),
+/// and decoded with [`IntermediateResponse::parse()`](struct.IntermediateResponse.html#method.parse).
+/// As with entries, the response controls accompanying an intermediate message are in
+/// the second element of the `ResultEntry`. Intermediate messages count toward the
+/// client-side entry and size limits.
+///
+/// A Search may not end at all: with the [`PersistentSearch`](controls/struct.PersistentSearch.html)
+/// control, set up by [`Ldap::watch()`](struct.Ldap.html#method.watch), `next()` keeps
+/// returning the changed entries until the Search is abandoned or the connection is closed.
+/// A timeout applies to the wait for each item, not to the whole stream.
 ///
 /// There are two variants of `SearchStream`, direct and adapted. The former calls
 /// stream operations directly, while the latter first passes through a chain of
@@ -571,12 +1603,23 @@ pub enum StreamState {
 #[derive(Debug)]
 pub struct SearchStream<'a, S, A> {
     pub(crate) ldap: Ldap,
-    pub(crate) rx: Option<mpsc::UnboundedReceiver<(SearchItem, Vec<Control>)>>,
+    pub(crate) rx: Option<mpsc::Receiver<(SearchItem, Vec<Control>)>>,
     state: StreamState,
     #[allow(clippy::type_complexity)]
     adapters: Vec<Arc<Mutex<Box<dyn Adapter<'a, S, A> + 'a>>>>,
     ax: usize,
     timeout: Option<Duration>,
+    deadline: Option<Instant>,
+    first_entry_deadline: Option<rt::Instant>,
+    total_deadline: Option<rt::Instant>,
+    cancel: Option<OpCancellation>,
+    entries: usize,
+    bytes: usize,
+    max_entries: Option<usize>,
+    max_bytes: Option<usize>,
+    value_limits: Option<ValueLimits>,
+    attr_case: AttrCase,
+    requested: Vec<String>,
     pub res: Option<LdapResult>,
 }
 
@@ -593,6 +1636,17 @@ where
             adapters: adapters.into_iter().map(Mutex::new).map(Arc::new).collect(),
             ax: 0,
             timeout: None,
+            deadline: None,
+            first_entry_deadline: None,
+            total_deadline: None,
+            cancel: None,
+            entries: 0,
+            bytes: 0,
+            max_entries: None,
+            max_bytes: None,
+            value_limits: None,
+            attr_case: AttrCase::AsReturned,
+            requested: vec![],
             res: None,
         }
     }
@@ -604,59 +1658,66 @@ where
         filter: &str,
         attrs: A,
     ) -> Result<()> {
-        let opts = match self.ldap.search_opts.take() {
-            Some(opts) => opts,
+        let opts = self.arm()?;
+        self.attr_case = opts.attr_case.unwrap_or_default();
+        if self.attr_case == AttrCase::AsRequested {
+            self.requested = attrs
+                .as_ref()
+                .iter()
+                .map(|a| a.as_ref().to_owned())
+                .collect();
+        }
+        if self.ldap.strict_validation {
+            validate_dn("base", base, false)?;
+            if filter.contains('\0') {
+                return Err(LdapError::InvalidRequest {
+                    field: "filter",
+                    reason: String::from("NUL character"),
+                });
+            }
+        }
+        let req = search_request(base, scope, filter, attrs.as_ref(), &opts)?;
+        self.send(req).await
+    }
+
+    // Take over the timeout, deadline, cancellation, and search options of the handle,
+    // returning the options.
+    fn arm(&mut self) -> Result<SearchOptions> {
+        let opts = match self.ldap.search_opts.take() {
+            Some(opts) => opts,
             None => SearchOptions::new(),
         };
         self.timeout = self.ldap.timeout;
-        let req = Tag::Sequence(Sequence {
-            id: 3,
-            class: TagClass::Application,
-            inner: vec![
-                Tag::OctetString(OctetString {
-                    inner: Vec::from(base.as_bytes()),
-                    ..Default::default()
-                }),
-                Tag::Enumerated(Enumerated {
-                    inner: scope as i64,
-                    ..Default::default()
-                }),
-                Tag::Enumerated(Enumerated {
-                    inner: opts.deref as i64,
-                    ..Default::default()
-                }),
-                Tag::Integer(Integer {
-                    inner: opts.sizelimit as i64,
-                    ..Default::default()
-                }),
-                Tag::Integer(Integer {
-                    inner: opts.timelimit as i64,
-                    ..Default::default()
-                }),
-                Tag::Boolean(Boolean {
-                    inner: opts.typesonly,
-                    ..Default::default()
-                }),
-                match parse_filter(filter) {
-                    Ok(filter) => filter,
-                    _ => return Err(LdapError::FilterParsing),
-                },
-                Tag::Sequence(Sequence {
-                    inner: attrs
-                        .as_ref()
-                        .iter()
-                        .map(|s| {
-                            Tag::OctetString(OctetString {
-                                inner: Vec::from(s.as_ref()),
-                                ..Default::default()
-                            })
-                        })
-                        .collect(),
-                    ..Default::default()
-                }),
-            ],
-        });
-        let (tx, rx) = mpsc::unbounded_channel();
+        self.deadline = self.ldap.deadline;
+        self.max_entries = opts.client_max_entries;
+        self.max_bytes = opts.client_max_bytes;
+        let limits = opts.value_limits();
+        self.value_limits = Some(limits).filter(|l| l.strict && l.is_set());
+        let now = rt::Instant::now();
+        self.first_entry_deadline = opts.first_entry_timeout.map(|t| now + t);
+        self.total_deadline = opts.total_timeout.map(|t| now + t);
+        if let Some(cancel) = self.ldap.cancel.take() {
+            self.cancel = Some(cancel);
+        }
+        if let Some(cause) = self.cancel.as_ref().and_then(OpCancellation::cause) {
+            return Err(LdapError::Cancelled { cause, entries: 0 });
+        }
+        Ok(opts)
+    }
+
+    // Start the operation with a request built by the caller. Only the client-side
+    // limits of the search options apply.
+    pub(crate) async fn start_raw(&mut self, req: Tag) -> Result<()> {
+        self.arm()?;
+        let res = self.send(req).await;
+        if res.is_err() {
+            self.state = StreamState::Error;
+        }
+        res
+    }
+
+    async fn send(&mut self, req: Tag) -> Result<()> {
+        let (tx, rx) = mpsc::channel(self.ldap.search_queue_size);
         self.rx = Some(rx);
         if let Some(timeout) = self.timeout {
             self.ldap.with_timeout(timeout);
@@ -666,33 +1727,143 @@ where
         })
     }
 
+    // The earliest of the limits on the wait for the next item. The first entry
+    // timeout only applies until an item has been received.
+    fn wait_limit(&self) -> Option<(rt::Instant, WaitLimit)> {
+        let first_entry = self.first_entry_deadline.filter(|_| self.entries == 0);
+        [
+            (self.deadline.map(rt::instant_from_std), WaitLimit::Deadline),
+            (first_entry, WaitLimit::FirstEntry),
+            (self.total_deadline, WaitLimit::Total),
+        ]
+        .into_iter()
+        .filter_map(|(at, limit)| at.map(|at| (at, limit)))
+        .min_by_key(|&(at, _)| at)
+    }
+
+    // Stop the search whose wait limit has passed.
+    fn expire(&mut self, limit: WaitLimit) -> LdapError {
+        self.rx = None;
+        let last_id = self.ldap.last_id;
+        match self.ldap.expire_msgid(last_id, true) {
+            Ok(()) => match limit {
+                WaitLimit::Deadline => LdapError::DeadlineExceeded,
+                WaitLimit::FirstEntry => LdapError::FirstEntryTimeout,
+                WaitLimit::Total => LdapError::SearchTotalTimeout,
+            },
+            Err(e) => e,
+        }
+    }
+
     pub(crate) async fn next_inner(&mut self) -> Result<Option<ResultEntry>> {
-        let item = if let Some(timeout) = self.timeout {
-            let res = time::timeout(timeout, self.rx.as_mut().unwrap().recv()).await;
-            if res.is_err() {
-                let last_id = self.ldap.last_id;
-                self.ldap.id_scrub_tx.send(last_id)?;
-            }
-            res?
-        } else {
-            self.rx.as_mut().unwrap().recv().await
+        // The operation is over, but adapters may keep asking for more.
+        if self.rx.is_none() {
+            return Ok(None);
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(self.expire(WaitLimit::Deadline));
+        }
+        let limit = self.wait_limit();
+        let wait = async {
+            let item = match (self.cancel.clone(), self.timeout) {
+                (Some(cancel), timeout) => {
+                    let sleep = async {
+                        match timeout {
+                            Some(timeout) => rt::sleep(timeout).await,
+                            None => futures_util::future::pending().await,
+                        }
+                    };
+                    let cause = tokio::select! {
+                        biased;
+                        item = self.rx.as_mut().unwrap().recv() => Ok(item),
+                        cause = cancel.cancelled() => Err(cause),
+                        _ = sleep => {
+                            cancel.trigger(CancelCause::Timeout);
+                            Err(cancel.cause().unwrap_or(CancelCause::Timeout))
+                        },
+                    };
+                    match cause {
+                        Ok(item) => item,
+                        Err(cause) => {
+                            self.rx = None;
+                            let last_id = self.ldap.last_id;
+                            self.ldap.cancel_msgid(last_id, true).await?;
+                            return Err(LdapError::Cancelled {
+                                cause,
+                                entries: self.entries,
+                            });
+                        }
+                    }
+                }
+                (None, Some(timeout)) => {
+                    let res = rt::timeout(timeout, self.rx.as_mut().unwrap().recv()).await;
+                    if res.is_err() {
+                        let last_id = self.ldap.last_id;
+                        self.ldap.expire_msgid(last_id, true)?;
+                    }
+                    res?
+                }
+                (None, None) => self.rx.as_mut().unwrap().recv().await,
+            };
+            Ok(item)
+        };
+        let item = match limit {
+            Some((at, limit)) => match rt::timeout_at(at, wait).await {
+                Ok(item) => item?,
+                Err(_) => return Err(self.expire(limit)),
+            },
+            None => wait.await?,
         };
         let (item, controls) = match item {
             Some((item, controls)) => (item, controls),
             None => {
                 self.rx = None;
-                return Err(LdapError::EndOfStream);
+                return Err(self
+                    .ldap
+                    .notifier
+                    .map_err(LdapError::EndOfStream)
+                    .in_operation("search", self.ldap.last_id));
             }
         };
         match item {
-            SearchItem::Entry(tag) | SearchItem::Referral(tag) => {
-                return Ok(Some(ResultEntry(tag, controls)))
+            SearchItem::Entry(mut tag, raw) | SearchItem::Referral(mut tag, raw) => {
+                normalize_attr_names(&mut tag, self.attr_case, &self.requested);
+                self.entries += 1;
+                self.bytes += ber_len(&tag);
+                if self.max_entries.is_some_and(|max| self.entries > max)
+                    || self.max_bytes.is_some_and(|max| self.bytes > max)
+                {
+                    self.rx = None;
+                    let last_id = self.ldap.last_id;
+                    self.ldap.cancel_msgid(last_id, true).await?;
+                    return Err(LdapError::ClientSideLimit {
+                        entries: self.entries,
+                        bytes: self.bytes,
+                    });
+                }
+                if let Some(ref limits) = self.value_limits.filter(|_| tag.id == 4) {
+                    if let Err(e) = check_value_limits(&tag, limits) {
+                        self.rx = None;
+                        let last_id = self.ldap.last_id;
+                        self.ldap.cancel_msgid(last_id, true).await?;
+                        return Err(e);
+                    }
+                }
+                return Ok(Some(ResultEntry(tag, controls, raw)));
             }
             SearchItem::Done(mut res) => {
                 res.ctrls = controls;
+                self.ldap.observe_response(res.rc);
                 self.res = Some(res);
                 self.rx = None;
             }
+            SearchItem::Failed(e) => {
+                self.rx = None;
+                return Err(e);
+            }
         }
         Ok(None)
     }
@@ -763,29 +1934,46 @@ where
             return res;
         }
         let adapter = self.adapters[self.ax].clone();
-        let mut adapter = adapter.lock().await;
-        self.ax += 1;
-        let res = adapter.next(self).await;
-        self.ax -= 1;
+        let res = {
+            let mut adapter = adapter.lock().await;
+            self.ax += 1;
+            let res = adapter.next(self).await;
+            self.ax -= 1;
+            res
+        };
         match res {
-            Ok(None) if self.ax == 0 => self.state = StreamState::Done,
+            Ok(None) if self.ax == 0 && !self.has_pending().await => self.state = StreamState::Done,
             Err(_) => self.state = StreamState::Error,
             _ => (),
         }
         res
     }
 
+    // Check whether any adapter in the chain has entries to return.
+    async fn has_pending(&self) -> bool {
+        for adapter in &self.adapters {
+            if adapter.lock().await.has_pending() {
+                return true;
+            }
+        }
+        false
+    }
+
     /// Return the overall result of the Search.
     ///
     /// This method can be called at any time. If the stream has been read to the
     /// end, the return value will be the actual result returned by the server.
     /// Otherwise, a synthetic cancellation result is returned, and it's the user's
-    /// responsibility to abandon or cancel the operation on the server.
+    /// responsibility to abandon or cancel the operation on the server, unless the
+    /// stream was terminated with [`abandon()`](#method.abandon) or [`cancel()`](#method.cancel).
     ///
     /// If the Search is adapted, this method will first execute the `finish()` methods of
     /// all adapters in the chain.
     pub async fn finish(&mut self) -> LdapResult {
         if self.state == StreamState::Closed {
+            if let Some(res) = self.res.take() {
+                return res;
+            }
             return LdapResult {
                 rc: 80,
                 matched: String::from(""),
@@ -805,6 +1993,77 @@ where
         res
     }
 
+    /// Terminate the Search by abandoning it on the server.
+    ///
+    /// The stream is finalized first, as if [`finish()`](#method.finish) had been called,
+    /// running the `finish()` methods of all adapters in the chain if the Search is adapted.
+    /// Then, an Abandon for the stream's operation is sent through the stream's own `Ldap`
+    /// handle. After this, `next()` will return `Ok(None)`, and `finish()` will return the
+    /// synthetic cancellation result collected while finalizing the stream.
+    ///
+    /// Calling this method on a stream which isn't in the `Active` state does nothing.
+    pub async fn abandon(&mut self) -> Result<()> {
+        if let Some(msgid) = self.close_active().await {
+            self.ldap.abandon(msgid).await?;
+        }
+        Ok(())
+    }
+
+    /// Terminate the Search by cancelling it on the server with the Cancel extended
+    /// operation ([RFC 3909](https://tools.ietf.org/html/rfc3909)).
+    ///
+    /// The stream is finalized in the same way as with [`abandon()`](#method.abandon),
+    /// but the method then waits for the response to the Cancel operation. A non-zero
+    /// result code of that response, such as 120 (tooLate) if the Search has already
+    /// completed on the server, is returned as an error.
+    ///
+    /// Calling this method on a stream which isn't in the `Active` state does nothing.
+    pub async fn cancel(&mut self) -> Result<()> {
+        if let Some(msgid) = self.close_active().await {
+            self.ldap.extended(Cancel { msgid }).await?.success()?;
+        }
+        Ok(())
+    }
+
+    /// Stop the Search on the server, ending the stream as if it was completed successfully.
+    ///
+    /// This method is meant for adapters which decide that no more entries are needed,
+    /// such as [`Limit`](adapters/struct.Limit.html). Unlike [`abandon()`](#method.abandon),
+    /// it doesn't finalize the stream: an Abandon for the operation is sent, and from then on,
+    /// the innermost `next()` returns `Ok(None)`, while the adapters above the caller in the
+    /// chain keep working as usual. The result returned by `finish()` has the result code 0
+    /// (success), with a diagnostic message noting that the Search was abandoned.
+    ///
+    /// Calling this method on a stream which isn't in the `Active` state, or whose operation
+    /// has already been completed by the server, does nothing.
+    pub async fn abandon_inner(&mut self) -> Result<()> {
+        if self.state != StreamState::Active || self.rx.is_none() {
+            return Ok(());
+        }
+        self.rx = None;
+        self.res = Some(LdapResult {
+            rc: 0,
+            matched: String::from(""),
+            text: String::from("search abandoned by the client"),
+            refs: vec![],
+            ctrls: vec![],
+        });
+        let msgid = self.ldap.last_id;
+        self.ldap.abandon(msgid).await
+    }
+
+    // Finalize an active stream before telling the server to stop the operation, keeping
+    // the result for finish(). Return the ID of the operation, if there is one to stop.
+    async fn close_active(&mut self) -> Option<RequestId> {
+        if self.state != StreamState::Active {
+            return None;
+        }
+        let msgid = self.ldap.last_id;
+        let res = self.finish().await;
+        self.res = Some(res);
+        Some(msgid)
+    }
+
     /// Return a vector of the remaining adapters in the chain at the point
     /// of the method call. Adapter instances are cloned and collected into the
     /// resulting vector. The purpose of this method is to enable uniformly
@@ -824,6 +2083,14 @@ where
         self.state
     }
 
+    /// Convert the stream into a [`ResultStream`](struct.ResultStream.html), which implements
+    /// the `Stream` trait from the `futures` crate and can be used with its combinators.
+    pub fn into_stream(self) -> ResultStream<'a, S, A> {
+        ResultStream {
+            inner: ResultStreamState::Idle(Box::new(self)),
+        }
+    }
+
     /// Return the `Ldap` handle of the stream.
     ///
     /// Mutating the public elements of `Ldap` through the obtained handle can't affect
@@ -834,6 +2101,107 @@ where
     }
 }
 
+type PendingNext<'a, S, A> = Pin<
+    Box<
+        dyn Future<Output = (Box<SearchStream<'a, S, A>>, Result<Option<ResultEntry>>)> + Send + 'a,
+    >,
+>;
+
+enum ResultStreamState<'a, S, A> {
+    Idle(Box<SearchStream<'a, S, A>>),
+    Pending(PendingNext<'a, S, A>),
+    Empty,
+}
+
+/// Search result stream usable with `futures` combinators.
+///
+/// A `ResultStream` is obtained by calling [`into_stream()`](struct.SearchStream.html#method.into_stream)
+/// on a `SearchStream`. It implements `Stream<Item = Result<ResultEntry>>`, with the items being
+/// the same as those returned by [`SearchStream::next()`](struct.SearchStream.html#method.next).
+/// The stream ends after the last entry or the first error. The overall result of the Search is
+/// retrieved with [`finish()`](#method.finish), which can also be called before reaching the end
+/// of the stream, with the same consequences as calling `finish()` on a `SearchStream`.
+///
+/// Since the stream is `Unpin`, it can be borrowed for a combinator with `by_ref()`, and
+/// finished afterwards:
+///
+/// ```rust,no_run
+/// # use futures::{StreamExt, TryStreamExt};
+/// # use ldap3::{LdapConnAsync, Scope, SearchEntry};
+/// # #[tokio::main]
+/// # async fn main() -> ldap3::result::Result<()> {
+/// # let (conn, mut ldap) = LdapConnAsync::new("ldap://localhost:2389").await?;
+/// # ldap3::drive!(conn);
+/// let mut stream = ldap
+///     .streaming_search("dc=example,dc=org", Scope::Subtree, "(objectClass=*)", vec!["cn"])
+///     .await?
+///     .into_stream();
+/// let first: Vec<_> = stream.by_ref().take(10).try_collect().await?;
+/// for entry in first {
+///     println!("{:?}", SearchEntry::construct(entry));
+/// }
+/// let res = stream.finish().await;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ResultStream<'a, S, A> {
+    inner: ResultStreamState<'a, S, A>,
+}
+
+impl<'a, S, A> ResultStream<'a, S, A>
+where
+    S: AsRef<str> + Send + Sync + 'a,
+    A: AsRef<[S]> + Send + Sync + 'a,
+{
+    /// Return the overall result of the Search.
+    ///
+    /// If an item is being fetched when this method is called, the fetch is completed and
+    /// the item discarded before finishing the underlying stream.
+    pub async fn finish(&mut self) -> LdapResult {
+        let mut stream = match std::mem::replace(&mut self.inner, ResultStreamState::Empty) {
+            ResultStreamState::Idle(stream) => stream,
+            ResultStreamState::Pending(next) => next.await.0,
+            ResultStreamState::Empty => unreachable!(),
+        };
+        let res = stream.finish().await;
+        self.inner = ResultStreamState::Idle(stream);
+        res
+    }
+}
+
+impl<'a, S, A> Stream for ResultStream<'a, S, A>
+where
+    S: AsRef<str> + Send + Sync + 'a,
+    A: AsRef<[S]> + Send + Sync + 'a,
+{
+    type Item = Result<ResultEntry>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut next = match std::mem::replace(&mut self.inner, ResultStreamState::Empty) {
+            ResultStreamState::Idle(stream) if stream.state() != StreamState::Active => {
+                self.inner = ResultStreamState::Idle(stream);
+                return Poll::Ready(None);
+            }
+            ResultStreamState::Idle(mut stream) => Box::pin(async move {
+                let item = stream.next().await;
+                (stream, item)
+            }) as PendingNext<'a, S, A>,
+            ResultStreamState::Pending(next) => next,
+            ResultStreamState::Empty => unreachable!(),
+        };
+        match next.as_mut().poll(cx) {
+            Poll::Pending => {
+                self.inner = ResultStreamState::Pending(next);
+                Poll::Pending
+            }
+            Poll::Ready((stream, item)) => {
+                self.inner = ResultStreamState::Idle(stream);
+                Poll::Ready(item.transpose())
+            }
+        }
+    }
+}
+
 /// Parse the referrals from the supplied BER-encoded sequence.
 pub fn parse_refs(t: StructureTag) -> Vec<String> {
     t.expect_constructed()
@@ -844,3 +2212,1342 @@ pub fn parse_refs(t: StructureTag) -> Vec<String> {
         .map(|s| s.expect("uri"))
         .collect()
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use std::collections::HashMap;
+
+    use super::{
+        ber_len, encode_search_entry, search_request, AttrCase, BinaryPolicy, ConstructOptions,
+        DerefAliases, Duplicate, ResultEntry, Scope, SearchEntry, SearchOptions, SearchStream,
+        StreamState, TruncationNote, ValueLimits,
+    };
+    use crate::adapters::{
+        Adapter, EntriesOnly, MapEntries, PagedResults, ProjectAttrs, SoloMarker,
+    };
+    use crate::conn::{LdapConnAsync, LdapConnSettings};
+    use crate::controls;
+    use crate::fixtures::{self, message, message_with_controls, octet_string, result, Requests};
+    use crate::ldap::{Ldap, Mod};
+    use crate::result::{LdapError, LdapResult};
+    use crate::schema::{AttributeType, Schema};
+
+    use async_trait::async_trait;
+    use bytes::BytesMut;
+    use futures_util::{StreamExt, TryStreamExt};
+    use lber::common::TagClass;
+    use lber::parse::{parse_tag, parse_uint};
+    use lber::structures::{ASNTag, Sequence, Tag};
+    use lber::write;
+    use tokio::io::AsyncWriteExt;
+    use tokio::sync::mpsc;
+    use tokio::time;
+
+    fn entry(attrs: &[(&str, &[&[u8]])]) -> ResultEntry {
+        ResultEntry::new(entry_tag(attrs).into_structure())
+    }
+
+    fn entry_tag(attrs: &[(&str, &[&[u8]])]) -> Tag {
+        fixtures::entry("cn=test,dc=example,dc=org", attrs)
+    }
+
+    // Encode a SearchRequest with the given options, decode it, and return the scope,
+    // derefAliases, sizeLimit, timeLimit and typesOnly elements.
+    fn search_request_options(scope: Scope, opts: &SearchOptions) -> (u64, u64, u64, u64, bool) {
+        let req =
+            search_request("dc=example,dc=org", scope, "(cn=*)", &["cn"], opts).expect("request");
+        let mut buf = BytesMut::new();
+        write::encode_into(&mut buf, req.into_structure()).expect("encoded");
+        let (rest, tag) = parse_tag(&buf).expect("parsed");
+        assert!(rest.is_empty());
+        assert_eq!((tag.class, tag.id), (TagClass::Application, 3));
+        let parts = tag.expect_constructed().expect("request sequence");
+        assert_eq!(parts.len(), 8);
+        let value = |n: usize, id: u64| {
+            assert_eq!((parts[n].class, parts[n].id), (TagClass::Universal, id));
+            parts[n].clone().expect_primitive().expect("primitive")
+        };
+        let number = |n: usize, id: u64| parse_uint(&value(n, id)).expect("number").1;
+        (
+            number(1, 10),
+            number(2, 10),
+            number(3, 2),
+            number(4, 2),
+            value(5, 1) != [0],
+        )
+    }
+
+    #[test]
+    fn attributes_ignoring_options() {
+        let entry = SearchEntry {
+            dn: String::from("cn=test,dc=example,dc=org"),
+            attrs: HashMap::from([
+                (String::from("cn"), vec![String::from("test")]),
+                (String::from("CN;lang-en;x-foo"), vec![String::from("Test")]),
+                (String::from("cnx"), vec![String::from("other")]),
+            ]),
+            bin_attrs: HashMap::from([(
+                String::from("userCertificate;binary"),
+                vec![vec![0x30, 0x82]],
+            )]),
+            truncated: vec![],
+        };
+        assert_eq!(
+            entry.get_ignore_options("Cn;lang-de"),
+            [
+                ("CN;lang-en;x-foo", vec![&b"Test"[..]]),
+                ("cn", vec![&b"test"[..]])
+            ]
+        );
+        assert_eq!(
+            entry.get_ignore_options("usercertificate"),
+            [("userCertificate;binary", vec![&[0x30u8, 0x82][..]])]
+        );
+        assert!(entry.get_ignore_options("sn").is_empty());
+        let policy = BinaryPolicy::empty();
+        assert!(policy.is_binary("cn;BINARY;lang-en"));
+        assert!(!policy.is_binary("cn;lang-en;x-foo"));
+        assert!(BinaryPolicy::empty()
+            .with_attr("userCertificate;binary")
+            .is_binary("USERCERTIFICATE"));
+    }
+
+    #[test]
+    fn attribute_name_case() {
+        let tag = entry_tag(&[
+            ("samaccountname", &[b"jdoe"]),
+            ("usercertificate;binary", &[b"\x30\x82"]),
+            ("jpegphoto", &[b"\xff\xd8"]),
+            ("objectClass", &[b"user"]),
+        ])
+        .into_structure();
+        let requested = ["sAMAccountName", "userCertificate", "jpegPhoto;binary"];
+        let entry = SearchEntry::construct_normalized(
+            ResultEntry::new(tag.clone()),
+            AttrCase::AsRequested,
+            &requested,
+        );
+        let mut names: Vec<_> = entry.attrs.keys().collect();
+        names.sort();
+        assert_eq!(names, ["objectClass", "sAMAccountName"]);
+        let mut bin_names: Vec<_> = entry.bin_attrs.keys().collect();
+        bin_names.sort();
+        assert_eq!(bin_names, ["jpegPhoto", "userCertificate;binary"]);
+
+        let entry = SearchEntry::construct_normalized(
+            ResultEntry::new(tag.clone()),
+            AttrCase::Lowercase,
+            &requested,
+        );
+        assert!(entry.attrs.contains_key("objectclass"));
+        assert!(entry.bin_attrs.contains_key("usercertificate;binary"));
+
+        let entry = SearchEntry::construct_normalized(
+            ResultEntry::new(tag),
+            AttrCase::AsReturned,
+            &requested,
+        );
+        assert!(entry.attrs.contains_key("samaccountname"));
+    }
+
+    #[test]
+    fn search_request_defaults() {
+        let opts = SearchOptions::new();
+        assert_eq!(
+            (opts.deref, opts.sizelimit, opts.timelimit, opts.typesonly),
+            (None, None, None, None)
+        );
+        assert_eq!(
+            search_request_options(Scope::OneLevel, &opts),
+            (1, 0, 0, 0, false)
+        );
+    }
+
+    #[test]
+    fn search_request_options_set() {
+        let opts = SearchOptions::new()
+            .deref(DerefAliases::Finding)
+            .sizelimit(25)
+            .timelimit(7)
+            .typesonly(true);
+        assert_eq!(
+            search_request_options(Scope::OneLevel, &opts),
+            (1, 2, 25, 7, true)
+        );
+        let opts = SearchOptions::new().deref(DerefAliases::Always);
+        assert_eq!(
+            search_request_options(Scope::OneLevel, &opts),
+            (1, 3, 0, 0, false)
+        );
+
+        // Explicitly set values equal to the defaults are recorded as set.
+        let opts = SearchOptions::new()
+            .deref(DerefAliases::Never)
+            .sizelimit(0)
+            .typesonly(false);
+        assert_eq!(
+            (opts.deref, opts.sizelimit, opts.timelimit, opts.typesonly),
+            (Some(DerefAliases::Never), Some(0), None, Some(false))
+        );
+        assert_eq!(
+            search_request_options(Scope::OneLevel, &opts),
+            (1, 0, 0, 0, false)
+        );
+    }
+
+    #[test]
+    fn scope_and_deref_strings() {
+        for scope in [
+            Scope::Base,
+            Scope::OneLevel,
+            Scope::Subtree,
+            Scope::Children,
+        ] {
+            assert_eq!(scope.to_string().parse::<Scope>().expect("scope"), scope);
+        }
+        for (s, scope) in [
+            ("SUB", Scope::Subtree),
+            ("subtree", Scope::Subtree),
+            ("oneLevel", Scope::OneLevel),
+            ("subordinate", Scope::Children),
+        ] {
+            assert_eq!(Scope::try_from(s).expect("scope"), scope);
+        }
+        assert!(matches!(
+            "tree".parse::<Scope>(),
+            Err(LdapError::InvalidScopeString(s)) if s == "tree"
+        ));
+        for deref in [
+            DerefAliases::Never,
+            DerefAliases::Searching,
+            DerefAliases::Finding,
+            DerefAliases::Always,
+        ] {
+            assert_eq!(
+                deref.to_string().parse::<DerefAliases>().expect("deref"),
+                deref
+            );
+        }
+        assert_eq!(
+            DerefAliases::try_from("Find").expect("deref"),
+            DerefAliases::Finding
+        );
+        assert!(matches!(
+            "sometimes".parse::<DerefAliases>(),
+            Err(LdapError::InvalidDerefString(_))
+        ));
+    }
+
+    #[test]
+    fn children_scope_encoding() {
+        let opts = SearchOptions::new();
+        assert_eq!(
+            search_request_options(Scope::Children, &opts),
+            (3, 0, 0, 0, false)
+        );
+    }
+
+    #[test]
+    fn utf8_guid_is_binary() {
+        let re = entry(&[("cn", &[b"test"]), ("objectGUID", &[b"0123456789abcdef"])]);
+        let opts = ConstructOptions::new().binary_policy(BinaryPolicy::default());
+        let se = SearchEntry::try_construct_with(re.clone(), &opts).expect("entry");
+        assert_eq!(se.attrs["cn"], vec!["test"]);
+        assert!(!se.attrs.contains_key("objectGUID"));
+        assert_eq!(
+            se.bin_attrs["objectGUID"],
+            vec![b"0123456789abcdef".to_vec()]
+        );
+        let se = SearchEntry::construct(re);
+        assert!(se.attrs.contains_key("objectGUID"));
+    }
+
+    #[test]
+    fn mixed_values_not_split() {
+        let re = entry(&[("description", &[b"one", b"\xff\xfe", b"three"])]);
+        let se = SearchEntry::construct(re);
+        assert!(se.attrs.is_empty());
+        assert_eq!(
+            se.bin_attrs["description"],
+            vec![b"one".to_vec(), b"\xff\xfe".to_vec(), b"three".to_vec()]
+        );
+    }
+
+    #[test]
+    fn duplicate_attributes() {
+        let re = || {
+            entry(&[
+                ("member", &[b"uid=a", b"uid=b"]),
+                ("cn", &[b"group"]),
+                ("Member", &[b"uid=c", b"uid=a"]),
+            ])
+        };
+        let construct = |on_duplicate| {
+            SearchEntry::try_construct_with(
+                re(),
+                &ConstructOptions::new().on_duplicate(on_duplicate),
+            )
+        };
+        let se = SearchEntry::construct(re());
+        assert_eq!(se.attrs.len(), 2);
+        assert_eq!(se.attrs["member"], ["uid=a", "uid=b", "uid=c", "uid=a"]);
+        let se = construct(Duplicate::Merge).expect("entry");
+        assert_eq!(se.attrs["member"], ["uid=a", "uid=b", "uid=c", "uid=a"]);
+        let se = construct(Duplicate::KeepFirst).expect("entry");
+        assert_eq!(se.attrs["member"], ["uid=a", "uid=b"]);
+        let se = construct(Duplicate::KeepLast).expect("entry");
+        assert!(!se.attrs.contains_key("member"));
+        assert_eq!(se.attrs["Member"], ["uid=c", "uid=a"]);
+        assert!(matches!(
+            construct(Duplicate::Error),
+            Err(LdapError::DuplicateAttribute(ref attr)) if attr == "Member"
+        ));
+
+        // Descriptions differing in options are distinct attributes.
+        let options = entry(&[("cn", &[b"a"]), ("cn;lang-en", &[b"b"])]);
+        let opts = ConstructOptions::new().on_duplicate(Duplicate::Error);
+        let se = SearchEntry::try_construct_with(options, &opts).expect("entry");
+        assert_eq!(se.attrs.len(), 2);
+
+        // Limits apply to the merged values.
+        let limits = ValueLimits::new().max_values_per_attr(Some(3));
+        let opts = ConstructOptions::new().value_limits(limits);
+        let se = SearchEntry::try_construct_with(re(), &opts).expect("entry");
+        assert_eq!(se.attrs["member"], ["uid=a", "uid=b", "uid=c"]);
+        assert_eq!(se.truncated.len(), 1);
+        let opts = ConstructOptions::new().value_limits(limits.strict(true));
+        assert!(matches!(
+            SearchEntry::try_construct_with(re(), &opts),
+            Err(LdapError::ValueLimitExceeded { values: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn duplicates_across_maps() {
+        // Textual first, binary second, and the reverse.
+        let re = entry(&[("description", &[b"one"]), ("DESCRIPTION", &[b"\xff\xfe"])]);
+        let se = SearchEntry::construct(re);
+        assert!(se.attrs.is_empty());
+        assert_eq!(
+            se.bin_attrs["description"],
+            vec![b"one".to_vec(), b"\xff\xfe".to_vec()]
+        );
+        let re = entry(&[("description", &[b"\xff\xfe"]), ("Description", &[b"two"])]);
+        let se = SearchEntry::construct(re);
+        assert!(se.attrs.is_empty());
+        assert_eq!(
+            se.bin_attrs["description"],
+            vec![b"\xff\xfe".to_vec(), b"two".to_vec()]
+        );
+
+        // The policy makes every instance binary.
+        let re = entry(&[("jpegPhoto", &[b"a"]), ("jpegphoto", &[b"b"])]);
+        let opts =
+            ConstructOptions::new().binary_policy(BinaryPolicy::empty().with_attr("jpegPhoto"));
+        let se = SearchEntry::try_construct_with(re, &opts).expect("entry");
+        assert_eq!(
+            se.bin_attrs["jpegPhoto"],
+            vec![b"a".to_vec(), b"b".to_vec()]
+        );
+
+        // With KeepLast, the classification of the kept instance applies.
+        let re = entry(&[("description", &[b"\xff\xfe"]), ("description", &[b"two"])]);
+        let opts = ConstructOptions::new().on_duplicate(Duplicate::KeepLast);
+        let se = SearchEntry::try_construct_with(re, &opts).expect("entry");
+        assert!(se.bin_attrs.is_empty());
+        assert_eq!(se.attrs["description"], ["two"]);
+    }
+
+    #[test]
+    fn binary_option() {
+        let re = entry(&[("userCertificate;binary", &[b"cert"])]);
+        let opts = ConstructOptions::new().binary_policy(BinaryPolicy::empty());
+        let se = SearchEntry::try_construct_with(re, &opts).expect("entry");
+        assert!(se.bin_attrs.contains_key("userCertificate;binary"));
+        assert!(!BinaryPolicy::empty().is_binary("userCertificate"));
+        assert!(BinaryPolicy::empty()
+            .with_attr("userCertificate")
+            .is_binary("USERCERTIFICATE;lang-en"));
+    }
+
+    #[test]
+    fn schema_backed_policy() {
+        let mut schema = Schema::default();
+        schema.add_attribute_type(
+            AttributeType::parse(
+                "( 1.3.6.1.4.1.99999.1 NAME 'fooBlob' SYNTAX 1.3.6.1.4.1.1466.115.121.1.40 )",
+            )
+            .expect("fooBlob"),
+        );
+        schema.add_attribute_type(
+            AttributeType::parse(
+                "( 1.2.840.113556.1.4.7000 NAME 'thumbnailPhoto' \
+                 SYNTAX 1.3.6.1.4.1.1466.115.121.1.15 SINGLE-VALUE )",
+            )
+            .expect("thumbnailPhoto"),
+        );
+        let policy = BinaryPolicy::default().with_schema(Arc::new(schema));
+        let re = entry(&[
+            ("fooBlob", &[b"text"]),
+            ("1.2.840.113556.1.4.7000", &[b"text"]),
+            ("description", &[b"text"]),
+        ]);
+        let opts = ConstructOptions::new().binary_policy(policy);
+        let se = SearchEntry::try_construct_with(re, &opts).expect("entry");
+        assert!(se.bin_attrs.contains_key("fooBlob"));
+        assert!(se.bin_attrs.contains_key("1.2.840.113556.1.4.7000"));
+        assert!(se.attrs.contains_key("description"));
+    }
+
+    #[test]
+    fn encoded_length() {
+        let long = vec![b'x'; 300];
+        for re in [
+            entry(&[("cn", &[b"test"])]),
+            entry(&[("description", &[&long[..200]])]),
+            entry(&[("description", &[&long[..], &long[..]])]),
+        ] {
+            assert_eq!(ber_len(&re.0), re.to_bytes().len());
+        }
+    }
+
+    // Scripted server which answers a Search with `count` entries and then stalls.
+    // The ids of abandoned or cancelled operations are sent to the returned channel;
+    // a Cancel is always successful.
+    async fn flooding_server(count: usize) -> (String, mpsc::UnboundedReceiver<u64>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let url = fixtures::serve(move |req| match req.op.id {
+            3 => {
+                let mut reply = vec![];
+                for _ in 0..count {
+                    reply.extend(message(req.msgid, entry_tag(&[("cn", &[b"test"])])));
+                }
+                reply
+            }
+            16 => {
+                tx.send(req.abandoned()).expect("sent");
+                vec![]
+            }
+            23 => {
+                let val = req
+                    .op
+                    .expect_constructed()
+                    .expect("exop")
+                    .into_iter()
+                    .find(|t| t.id == 1)
+                    .and_then(|t| t.expect_primitive())
+                    .expect("cancel value");
+                let id = parse_tag(&val)
+                    .expect("cancel seq")
+                    .1
+                    .expect_constructed()
+                    .and_then(|mut seq| seq.pop())
+                    .and_then(|t| t.expect_primitive())
+                    .expect("cancel id");
+                tx.send(parse_uint(&id).expect("id").1).expect("sent");
+                message(req.msgid, result(24, 0))
+            }
+            _ => vec![],
+        })
+        .await;
+        (url, rx)
+    }
+
+    async fn limited_search(opts: SearchOptions) -> (usize, LdapError, u64, u64) {
+        let (url, mut abandoned) = flooding_server(10).await;
+        let (conn, mut ldap) = LdapConnAsync::new(&url).await.expect("connection");
+        crate::drive!(conn);
+        let mut stream = ldap
+            .with_search_options(opts)
+            .streaming_search("", Scope::Base, "(objectClass=*)", vec!["*"])
+            .await
+            .expect("stream");
+        let mut received = 0;
+        let err = loop {
+            match stream.next().await {
+                Ok(Some(_)) => received += 1,
+                Ok(None) => panic!("unexpected end of stream"),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(stream.state(), StreamState::Error);
+        let search_id = stream.ldap_handle().last_id() as u64;
+        let abandoned = abandoned.recv().await.expect("abandon");
+        (received, err, search_id, abandoned)
+    }
+
+    #[tokio::test]
+    async fn client_max_entries() {
+        let (received, err, search_id, abandoned) =
+            limited_search(SearchOptions::new().client_max_entries(3)).await;
+        assert_eq!(received, 3);
+        let entry_len = entry(&[("cn", &[b"test"])]).to_bytes().len();
+        assert!(matches!(
+            err,
+            LdapError::ClientSideLimit { entries: 4, bytes } if bytes == 4 * entry_len
+        ));
+        assert_eq!(abandoned, search_id);
+    }
+
+    #[tokio::test]
+    async fn client_max_bytes() {
+        let entry_len = entry(&[("cn", &[b"test"])]).to_bytes().len();
+        let (received, err, search_id, abandoned) =
+            limited_search(SearchOptions::new().client_max_bytes(5 * entry_len + 1)).await;
+        assert_eq!(received, 5);
+        assert!(matches!(
+            err,
+            LdapError::ClientSideLimit { entries: 6, bytes } if bytes == 6 * entry_len
+        ));
+        assert_eq!(abandoned, search_id);
+    }
+
+    #[tokio::test]
+    async fn strict_value_limits() {
+        let opts = SearchOptions::new()
+            .max_value_size(Some(3))
+            .strict_value_limits(true);
+        let (received, err, search_id, abandoned) = limited_search(opts).await;
+        assert_eq!(received, 0);
+        assert!(matches!(
+            err,
+            LdapError::ValueLimitExceeded { ref attr, size: 4, values: 1 } if attr == "cn"
+        ));
+        assert_eq!(abandoned, search_id);
+    }
+
+    #[test]
+    fn value_limits() {
+        // Five characters, ten bytes.
+        let wide = "ÄÖÜßé".as_bytes();
+        let re = || {
+            entry(&[
+                ("cn", &[b"short", wide]),
+                ("mail", &[b"a@x", b"b@x", b"c@x"]),
+                ("jpegPhoto", &[b"\xff\xd8\xff\xe0\x00\x10"]),
+            ])
+        };
+        let construct = |re, limits| {
+            SearchEntry::try_construct_with(re, &ConstructOptions::new().value_limits(limits))
+        };
+        let limits = ValueLimits::new()
+            .max_value_size(Some(5))
+            .max_values_per_attr(Some(2));
+        let se = construct(re(), limits).expect("entry");
+        assert_eq!(se.attrs["cn"], ["short", ""]);
+        assert_eq!(se.attrs["mail"], ["a@x", "b@x"]);
+        assert_eq!(se.bin_attrs["jpegPhoto"], [b""]);
+        let mut notes = se.truncated.clone();
+        notes.sort_by(|a, b| a.attr.cmp(&b.attr));
+        let note = |attr: &str, original_size, values_dropped, values_truncated| TruncationNote {
+            attr: String::from(attr),
+            original_size,
+            values_dropped,
+            values_truncated,
+        };
+        assert_eq!(
+            notes,
+            [
+                note("cn", 15, 0, 1),
+                note("jpegPhoto", 6, 0, 1),
+                note("mail", 9, 1, 0)
+            ]
+        );
+
+        // A value as long as the limit is kept.
+        let limits = ValueLimits::new().max_value_size(Some(10));
+        let se = construct(re(), limits).expect("entry");
+        assert_eq!(se.attrs["cn"][1], "ÄÖÜßé");
+        assert!(se.truncated.is_empty());
+
+        let err = construct(re(), limits.max_value_size(Some(9)).strict(true)).expect_err("strict");
+        assert!(matches!(
+            err,
+            LdapError::ValueLimitExceeded { ref attr, size: 10, values: 2 } if attr == "cn"
+        ));
+        let err = construct(
+            re(),
+            ValueLimits::new().max_values_per_attr(Some(2)).strict(true),
+        )
+        .expect_err("strict");
+        assert!(matches!(
+            err,
+            LdapError::ValueLimitExceeded { ref attr, size: 3, values: 3 } if attr == "mail"
+        ));
+        let err = construct(
+            ResultEntry::new(octet_string(b"junk").into_structure()),
+            ValueLimits::new(),
+        )
+        .expect_err("malformed");
+        assert!(matches!(err, LdapError::MalformedEntry(_)));
+    }
+
+    #[tokio::test]
+    async fn stream_abandon() {
+        let (url, mut abandoned) = flooding_server(1000).await;
+        let (conn, mut ldap) = LdapConnAsync::new(&url).await.expect("connection");
+        crate::drive!(conn);
+        let mut stream = ldap
+            .streaming_search("", Scope::Base, "(objectClass=*)", vec!["*"])
+            .await
+            .expect("stream");
+        assert!(stream.next().await.expect("entry").is_some());
+        let search_id = stream.ldap_handle().last_id() as u64;
+        stream.abandon().await.expect("abandon");
+        assert_eq!(abandoned.recv().await, Some(search_id));
+        assert_eq!(stream.state(), StreamState::Closed);
+        assert!(stream.next().await.expect("next").is_none());
+        assert_eq!(stream.finish().await.rc, 88);
+    }
+
+    #[tokio::test]
+    async fn adapted_stream_cancel() {
+        let (url, mut cancelled) = flooding_server(1000).await;
+        let (conn, mut ldap) = LdapConnAsync::new(&url).await.expect("connection");
+        crate::drive!(conn);
+        let mut stream = ldap
+            .streaming_search_with(
+                EntriesOnly::new(),
+                "",
+                Scope::Base,
+                "(objectClass=*)",
+                vec!["*"],
+            )
+            .await
+            .expect("stream");
+        assert!(stream.next().await.expect("entry").is_some());
+        let search_id = stream.ldap_handle().last_id() as u64;
+        stream.cancel().await.expect("cancel");
+        assert_eq!(cancelled.recv().await, Some(search_id));
+        assert!(stream.next().await.expect("next").is_none());
+        let res = stream.finish().await;
+        assert_eq!((res.rc, res.text.as_str()), (88, "user cancelled"));
+        // The result is only reported once.
+        assert_eq!(stream.finish().await.rc, 80);
+    }
+
+    #[tokio::test]
+    async fn result_stream_combinators() {
+        let (url, _) = flooding_server(1000).await;
+        let (conn, mut ldap) = LdapConnAsync::new(&url).await.expect("connection");
+        crate::drive!(conn);
+        let mut stream = ldap
+            .streaming_search("", Scope::Base, "(objectClass=*)", vec!["*"])
+            .await
+            .expect("stream")
+            .into_stream();
+        let entries: Vec<_> = stream
+            .by_ref()
+            .take(10)
+            .try_collect()
+            .await
+            .expect("entries");
+        assert_eq!(entries.len(), 10);
+        assert_eq!(stream.finish().await.rc, 88);
+        assert!(stream.next().await.is_none());
+    }
+
+    async fn timed_out_modify(settings: LdapConnSettings) -> (Ldap, mpsc::UnboundedReceiver<u64>) {
+        let (url, abandoned) = flooding_server(0).await;
+        let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &url)
+            .await
+            .expect("connection");
+        crate::drive!(conn);
+        let res = ldap
+            .with_timeout(Duration::from_millis(100))
+            .modify("cn=test,dc=example,dc=org", Vec::<Mod<&str>>::new())
+            .await;
+        assert!(matches!(res, Err(LdapError::Timeout { .. })));
+        (ldap, abandoned)
+    }
+
+    #[tokio::test]
+    async fn modify_timeout_abandons() {
+        let (mut ldap, mut abandoned) = timed_out_modify(LdapConnSettings::new()).await;
+        assert_eq!(abandoned.recv().await, Some(ldap.last_id() as u64));
+    }
+
+    #[tokio::test]
+    async fn modify_timeout_without_abandon() {
+        let settings = LdapConnSettings::new().set_abandon_on_timeout(false);
+        let (_ldap, mut abandoned) = timed_out_modify(settings).await;
+        assert!(time::timeout(Duration::from_millis(200), abandoned.recv())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn search_timeout_abandons() {
+        let (url, mut abandoned) = flooding_server(0).await;
+        let (conn, mut ldap) = LdapConnAsync::new(&url).await.expect("connection");
+        crate::drive!(conn);
+        let mut stream = ldap
+            .with_timeout(Duration::from_millis(100))
+            .streaming_search("", Scope::Base, "(objectClass=*)", vec!["*"])
+            .await
+            .expect("stream");
+        assert!(matches!(
+            stream.next().await,
+            Err(LdapError::Timeout { .. })
+        ));
+        let search_id = stream.ldap_handle().last_id() as u64;
+        assert_eq!(abandoned.recv().await, Some(search_id));
+    }
+
+    // Scripted server which answers a Search for "cn=flood" with `FLOOD_ENTRIES` large
+    // entries, and any other Search with a single entry. Entries are written one by
+    // one, and the number of written entries is kept in the returned counter.
+    const FLOOD_ENTRIES: usize = 4096;
+
+    async fn backpressure_server() -> (String, Arc<AtomicUsize>) {
+        let (listener, url) = fixtures::listener().await;
+        let written = Arc::new(AtomicUsize::new(0));
+        let counter = written.clone();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.expect("accept");
+            let (rd, mut wr) = stream.into_split();
+            let big = vec![b'x'; 8192];
+            let mut requests = Requests::new(rd);
+            while let Some(req) = requests.next().await {
+                if req.op.id != 3 {
+                    continue;
+                }
+                let count = if req.dn() == "cn=flood" {
+                    FLOOD_ENTRIES
+                } else {
+                    1
+                };
+                for _ in 0..count {
+                    let entry = message(req.msgid, entry_tag(&[("description", &[&big])]));
+                    if wr.write_all(&entry).await.is_err() {
+                        return;
+                    }
+                    counter.fetch_add(1, Ordering::SeqCst);
+                }
+                wr.write_all(&message(req.msgid, result(5, 0)))
+                    .await
+                    .expect("write");
+            }
+        });
+        (url, written)
+    }
+
+    // Scripted server which answers each Search with a single entry, followed after
+    // the given delay by a result announcing another page.
+    async fn slow_paging_server(delay: Duration) -> (String, mpsc::UnboundedReceiver<u64>) {
+        let (listener, url) = fixtures::listener().await;
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.expect("accept");
+            let (rd, mut wr) = stream.into_split();
+            let mut requests = Requests::new(rd);
+            while let Some(req) = requests.next().await {
+                match req.op.id {
+                    3 => (),
+                    16 => {
+                        tx.send(req.abandoned()).expect("sent");
+                        continue;
+                    }
+                    _ => continue,
+                }
+                let entry = message(req.msgid, entry_tag(&[("cn", &[b"test"])]));
+                wr.write_all(&entry).await.expect("write");
+                time::sleep(delay).await;
+                let pr = controls::PagedResults {
+                    size: 1,
+                    cookie: b"next".to_vec(),
+                };
+                let done = message_with_controls(req.msgid, result(5, 0), vec![pr.into()]);
+                if wr.write_all(&done).await.is_err() {
+                    return;
+                }
+            }
+        });
+        (url, rx)
+    }
+
+    #[tokio::test]
+    async fn paged_search_deadline() {
+        let (url, mut abandoned) = slow_paging_server(Duration::from_millis(100)).await;
+        let (conn, mut ldap) = LdapConnAsync::new(&url).await.expect("connection");
+        crate::drive!(conn);
+        let started = Instant::now();
+        let mut stream = ldap
+            .with_timeout(Duration::from_secs(1))
+            .with_deadline(started + Duration::from_millis(350))
+            .streaming_search_with(
+                PagedResults::new(1),
+                "",
+                Scope::Subtree,
+                "(objectClass=*)",
+                vec!["*"],
+            )
+            .await
+            .expect("stream");
+        let mut received = 0;
+        let err = loop {
+            match stream.next().await {
+                Ok(Some(_)) => received += 1,
+                Ok(None) => panic!("unexpected end of stream"),
+                Err(e) => break e,
+            }
+        };
+        assert!(matches!(err, LdapError::DeadlineExceeded), "{:?}", err);
+        assert!((3..=5).contains(&received), "{} entries", received);
+        assert!(started.elapsed() < Duration::from_secs(1));
+        let page_id = stream.ldap_handle().last_id() as u64;
+        assert_eq!(abandoned.recv().await, Some(page_id));
+    }
+
+    #[tokio::test]
+    async fn slow_consumer_backpressure() {
+        let (url, written) = backpressure_server().await;
+        let settings = LdapConnSettings::new().set_search_queue_size(4);
+        let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &url)
+            .await
+            .expect("connection");
+        crate::drive!(conn);
+        let mut stream = ldap
+            .streaming_search("cn=flood", Scope::Base, "(objectClass=*)", vec!["*"])
+            .await
+            .expect("stream");
+        for _ in 0..10 {
+            assert!(stream.next().await.expect("entry").is_some());
+            time::sleep(Duration::from_millis(10)).await;
+        }
+        // The server must have been stopped by TCP flow control, well before the end.
+        let stalled = written.load(Ordering::SeqCst);
+        time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(written.load(Ordering::SeqCst), stalled);
+        assert!(stalled < FLOOD_ENTRIES / 2, "{} entries written", stalled);
+        let mut count = 10;
+        while stream.next().await.expect("entry").is_some() {
+            count += 1;
+        }
+        assert_eq!(count, FLOOD_ENTRIES);
+        assert_eq!(stream.finish().await.rc, 0);
+    }
+
+    #[tokio::test]
+    async fn finish_releases_blocked_connection() {
+        let (url, _written) = backpressure_server().await;
+        let settings = LdapConnSettings::new().set_search_queue_size(1);
+        let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &url)
+            .await
+            .expect("connection");
+        crate::drive!(conn);
+        let mut stream = ldap
+            .streaming_search("cn=flood", Scope::Base, "(objectClass=*)", vec!["*"])
+            .await
+            .expect("stream");
+        assert!(stream.next().await.expect("entry").is_some());
+        stream.finish().await;
+        let (entries, _res) = time::timeout(
+            Duration::from_secs(10),
+            ldap.search("cn=small", Scope::Base, "(objectClass=*)", vec!["*"]),
+        )
+        .await
+        .expect("no deadlock")
+        .expect("search")
+        .success()
+        .expect("success");
+        assert_eq!(entries.len(), 1);
+    }
+
+    // Scripted server which returns the entries of a Search in pages of two, with a
+    // referral on the first page. Each entry has "cn", "sn" and "jpegPhoto;binary". A Search for "cn=limited" without paging ends with
+    // sizeLimitExceeded after three entries, and a Search for "cn=missing" with
+    // noSuchObject. The root DSE lists the Paged Results control if `paging` is set.
+    // The base DN, attributes and controls of each Search request are reported through
+    // the channel.
+    const PAGED_ENTRIES: usize = 5;
+
+    type SearchRequest = (String, Vec<String>, Vec<controls::RawControl>);
+
+    fn search_done(msgid: i64, rc: i64, ctrl: Option<controls::RawControl>) -> Vec<u8> {
+        message_with_controls(msgid, result(5, rc), ctrl.into_iter().collect())
+    }
+
+    async fn paging_server(paging: bool) -> (String, mpsc::UnboundedReceiver<SearchRequest>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let url = fixtures::serve(move |req| {
+            if req.op.id != 3 {
+                return vec![];
+            }
+            let mut search = req.op.expect_constructed().expect("search").into_iter();
+            let base = search
+                .next()
+                .expect("base")
+                .expect_primitive()
+                .expect("base");
+            let base = String::from_utf8(base).expect("base");
+            let attrs = search
+                .last()
+                .expect("attrs")
+                .expect_constructed()
+                .expect("attrs")
+                .into_iter()
+                .map(|a| String::from_utf8(a.expect_primitive().expect("attr")).expect("attr"))
+                .collect();
+            let ctrls = req.controls;
+            let offset = ctrls
+                .iter()
+                .find(|c| c.ctype == "1.2.840.113556.1.4.319")
+                .map(|c| c.parse::<controls::PagedResults>().cookie)
+                .map(|cookie| {
+                    if cookie.is_empty() {
+                        0
+                    } else {
+                        String::from_utf8(cookie)
+                            .expect("cookie")
+                            .parse()
+                            .expect("offset")
+                    }
+                });
+            tx.send((base.clone(), attrs, ctrls)).expect("sent");
+            let mut reply = vec![];
+            if base.is_empty() {
+                let supported: &[&[u8]] = if paging {
+                    &[b"1.2.840.113556.1.4.319"]
+                } else {
+                    &[]
+                };
+                let dse = entry_tag(&[("supportedControl", supported)]);
+                reply.extend(message(req.msgid, dse));
+                reply.extend(search_done(req.msgid, 0, None));
+            } else if base == "cn=missing" {
+                reply.extend(search_done(req.msgid, 32, None));
+            } else {
+                let (page, done) = match offset {
+                    Some(offset) => {
+                        let end = PAGED_ENTRIES.min(offset + 2);
+                        let cookie = if end < PAGED_ENTRIES {
+                            end.to_string().into_bytes()
+                        } else {
+                            vec![]
+                        };
+                        let pr = controls::PagedResults {
+                            size: PAGED_ENTRIES as i32,
+                            cookie,
+                        };
+                        (offset..end, search_done(req.msgid, 0, Some(pr.into())))
+                    }
+                    None if base == "cn=limited" => (0..3, search_done(req.msgid, 4, None)),
+                    None => (0..PAGED_ENTRIES, search_done(req.msgid, 0, None)),
+                };
+                if page.start == 0 {
+                    let referral = Tag::Sequence(Sequence {
+                        id: 19,
+                        class: TagClass::Application,
+                        inner: vec![octet_string(b"ldap://other.example.org/dc=example,dc=org")],
+                    });
+                    reply.extend(message(req.msgid, referral));
+                }
+                for n in page {
+                    let value = n.to_string();
+                    let entry = entry_tag(&[
+                        ("cn", &[value.as_bytes()]),
+                        ("sn", &[b"Doe"]),
+                        ("jpegPhoto;binary", &[b"\xff\xd8"]),
+                    ]);
+                    reply.extend(message(req.msgid, entry));
+                }
+                reply.extend(done);
+            }
+            reply
+        })
+        .await;
+        (url, rx)
+    }
+
+    async fn paging_connection(paging: bool) -> (Ldap, mpsc::UnboundedReceiver<SearchRequest>) {
+        let (url, requests) = paging_server(paging).await;
+        let (conn, ldap) = LdapConnAsync::new(&url).await.expect("connection");
+        crate::drive!(conn);
+        (ldap, requests)
+    }
+
+    #[tokio::test]
+    async fn search_paged_collects_pages() {
+        let (mut ldap, mut requests) = paging_connection(true).await;
+        let res = ldap
+            .with_controls(controls::ManageDsaIt)
+            .search_paged(
+                "dc=example,dc=org",
+                Scope::Subtree,
+                "(objectClass=*)",
+                vec!["cn"],
+                2,
+            )
+            .await
+            .expect("search");
+        let values: Vec<_> = res
+            .0
+            .iter()
+            .map(|re| {
+                assert!(!re.is_ref());
+                SearchEntry::construct(re.clone()).attrs["cn"][0].clone()
+            })
+            .collect();
+        assert_eq!(values, vec!["0", "1", "2", "3", "4"]);
+        assert_eq!(res.1.rc, 0);
+        assert_eq!(
+            res.1.refs,
+            vec!["ldap://other.example.org/dc=example,dc=org"]
+        );
+        let estimate = res
+            .1
+            .ctrls
+            .iter()
+            .find_map(|ctrl| match ctrl {
+                controls::Control(Some(controls::ControlType::PagedResults), raw) => {
+                    Some(raw.parse::<controls::PagedResults>())
+                }
+                _ => None,
+            })
+            .expect("paged results control");
+        assert_eq!(estimate.size, PAGED_ENTRIES as i32);
+        assert!(estimate.cookie.is_empty());
+        // The capability probe doesn't take the pending controls.
+        let (base, attrs, ctrls) = requests.recv().await.expect("request");
+        assert_eq!(base, "");
+        assert_eq!(attrs, vec!["*", "+"]);
+        assert!(ctrls.is_empty());
+        let mut cookies = vec![];
+        for _ in 0..3 {
+            let (_, _, ctrls) = requests.recv().await.expect("request");
+            assert_eq!(ctrls.len(), 2);
+            assert_eq!(ctrls[0].ctype, "2.16.840.1.113730.3.4.2");
+            let pr: controls::PagedResults = ctrls[1].parse();
+            assert_eq!(pr.size, 2);
+            cookies.push(pr.cookie);
+        }
+        assert_eq!(cookies, vec![b"".to_vec(), b"2".to_vec(), b"4".to_vec()]);
+        // Pending operation state must have been consumed by the paged search.
+        ldap.search("cn=small", Scope::Base, "(objectClass=*)", vec!["cn"])
+            .await
+            .expect("search");
+        assert!(requests.recv().await.expect("request").2.is_empty());
+    }
+
+    #[tokio::test]
+    async fn search_paged_rejects_paged_control() {
+        let (mut ldap, _requests) = paging_connection(true).await;
+        let err = ldap
+            .with_controls(controls::PagedResults {
+                size: 10,
+                cookie: vec![],
+            })
+            .search_paged("", Scope::Base, "(objectClass=*)", vec!["cn"], 2)
+            .await
+            .expect_err("duplicate control");
+        assert!(matches!(err, LdapError::AdapterInit(_)), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn dns_and_existence() {
+        let (mut ldap, mut requests) = paging_connection(true).await;
+        let dns = ldap
+            .search_dns("dc=example,dc=org", Scope::Subtree, "(objectClass=*)")
+            .await
+            .expect("dns");
+        assert_eq!(dns, vec!["cn=test,dc=example,dc=org"; PAGED_ENTRIES]);
+        let (_, attrs, _) = requests.recv().await.expect("request");
+        assert_eq!(attrs, vec!["1.1"]);
+        assert!(ldap
+            .exists("cn=test,dc=example,dc=org")
+            .await
+            .expect("exists"));
+        assert!(!ldap.exists("cn=missing").await.expect("exists"));
+        let err = ldap
+            .search_dns("cn=missing", Scope::Subtree, "(objectClass=*)")
+            .await
+            .expect_err("missing base");
+        assert!(matches!(err, LdapError::LdapResult { ref result } if result.rc == 32));
+    }
+
+    #[tokio::test]
+    async fn count_entries() {
+        let (mut ldap, mut requests) = paging_connection(true).await;
+        let count = ldap
+            .count("dc=example,dc=org", Scope::Subtree, "(objectClass=*)")
+            .await
+            .expect("count");
+        assert_eq!(count, PAGED_ENTRIES as u64);
+        let (_, attrs, ctrls) = requests.recv().await.expect("request");
+        assert_eq!(attrs, vec!["1.1"]);
+        assert!(ctrls.is_empty());
+    }
+
+    #[tokio::test]
+    async fn count_retries_with_paging() {
+        let (mut ldap, mut requests) = paging_connection(true).await;
+        let count = ldap
+            .with_controls(controls::ManageDsaIt)
+            .count("cn=limited", Scope::Subtree, "(objectClass=*)")
+            .await
+            .expect("count");
+        assert_eq!(count, PAGED_ENTRIES as u64);
+        let (base, _, ctrls) = requests.recv().await.expect("request");
+        assert_eq!((base.as_str(), ctrls.len()), ("cn=limited", 1));
+        let (base, _, ctrls) = requests.recv().await.expect("request");
+        assert_eq!((base.as_str(), ctrls.len()), ("", 0));
+        // Pages of three, the number of entries received before the limit was hit.
+        for _ in 0..2 {
+            let (base, _, ctrls) = requests.recv().await.expect("request");
+            assert_eq!(base, "cn=limited");
+            assert_eq!(ctrls[0].ctype, "2.16.840.1.113730.3.4.2");
+            assert_eq!(ctrls[1].parse::<controls::PagedResults>().size, 3);
+        }
+    }
+
+    #[tokio::test]
+    async fn count_without_paging_support() {
+        let (mut ldap, _requests) = paging_connection(false).await;
+        let err = ldap
+            .count("cn=limited", Scope::Subtree, "(objectClass=*)")
+            .await
+            .expect_err("size limit");
+        assert!(matches!(err, LdapError::LdapResult { ref result } if result.rc == 4));
+    }
+
+    #[test]
+    fn entry_structure_roundtrip() {
+        let entry = SearchEntry::construct(entry(&[
+            ("sn", &[b"Doe"]),
+            ("cn", &[b"John", b"Johnny"]),
+            ("photo", &[b"\xff\xd8"]),
+        ]));
+        let tag = entry.to_structure();
+        let again = SearchEntry::construct(ResultEntry::new(tag.clone()));
+        assert_eq!(again.dn, entry.dn);
+        assert_eq!(again.attrs, entry.attrs);
+        assert_eq!(again.bin_attrs, entry.bin_attrs);
+        assert_eq!(
+            tag,
+            entry_tag(&[
+                ("cn", &[b"John", b"Johnny"]),
+                ("photo", &[b"\xff\xd8"]),
+                ("sn", &[b"Doe"]),
+            ])
+            .into_structure()
+        );
+    }
+
+    #[test]
+    fn encoded_entry_roundtrips() {
+        let mut attrs = HashMap::new();
+        attrs.insert(String::from("description"), vec![]);
+        for n in 0..200 {
+            attrs.insert(format!("attr{}", n), vec![n.to_string(), String::from("é")]);
+        }
+        let mut bin_attrs = HashMap::new();
+        bin_attrs.insert(
+            String::from("jpegPhoto"),
+            vec![vec![0xff, 0xd8], vec![0x80]],
+        );
+        // Textual values, kept binary by the option when a binary policy is used.
+        bin_attrs.insert(String::from("objectGUID;binary"), vec![b"plain".to_vec()]);
+        let policy = BinaryPolicy::empty();
+        let entry = SearchEntry {
+            dn: String::from("cn=roundtrip,dc=example,dc=org"),
+            attrs: attrs.clone(),
+            bin_attrs: bin_attrs.clone(),
+            truncated: vec![],
+        };
+        let tag = encode_search_entry(&entry.dn, &attrs, &bin_attrs);
+        assert_eq!((tag.class, tag.id), (TagClass::Application, 4));
+        let opts = ConstructOptions::new().binary_policy(policy);
+        let again =
+            SearchEntry::try_construct_with(ResultEntry::new(tag.clone()), &opts).expect("entry");
+        assert_eq!(again.dn, entry.dn);
+        assert_eq!(again.attrs, attrs);
+        assert_eq!(again.bin_attrs, bin_attrs);
+        let plain = SearchEntry::construct(ResultEntry::new(tag));
+        assert_eq!(plain.attrs["objectGUID;binary"], ["plain"]);
+        assert!(!plain.bin_attrs.contains_key("objectGUID;binary"));
+        let re = entry.clone().into_result_entry();
+        assert!(!re.is_ref() && !re.is_intermediate());
+        let again = SearchEntry::try_construct_with(
+            ResultEntry::from_bytes(&re.to_bytes()).expect("valid"),
+            &opts,
+        )
+        .expect("entry");
+        assert_eq!(again.attrs, entry.attrs);
+        assert_eq!(again.bin_attrs, entry.bin_attrs);
+    }
+
+    #[tokio::test]
+    async fn projection_with_paging() {
+        let (mut ldap, _requests) = paging_connection(true).await;
+        let adapters: Vec<Box<dyn Adapter<_, _>>> = vec![
+            Box::new(EntriesOnly::new()),
+            Box::new(ProjectAttrs::new(vec!["CN".into(), "jpegphoto".into()])),
+            Box::new(PagedResults::new(2)),
+        ];
+        let mut stream = ldap
+            .streaming_search_with(
+                adapters,
+                "dc=example,dc=org",
+                Scope::Subtree,
+                "(objectClass=*)",
+                vec!["*"],
+            )
+            .await
+            .expect("stream");
+        let mut count = 0;
+        while let Some(re) = stream.next().await.expect("entry") {
+            let entry = SearchEntry::construct(re);
+            assert_eq!(entry.attrs.keys().collect::<Vec<_>>(), vec!["cn"]);
+            assert_eq!(entry.attrs["cn"], vec![count.to_string()]);
+            assert_eq!(
+                entry.bin_attrs["jpegPhoto;binary"],
+                vec![b"\xff\xd8".to_vec()]
+            );
+            count += 1;
+        }
+        assert_eq!(count, PAGED_ENTRIES);
+        let res = stream.finish().await;
+        assert_eq!(res.rc, 0);
+        assert_eq!(res.refs.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn mapped_entries() {
+        let (mut ldap, _requests) = paging_connection(true).await;
+        let odd_only = MapEntries::new(|re| {
+            if re.is_ref() {
+                return Some(re);
+            }
+            let cn: u32 = SearchEntry::construct(re.clone()).attrs["cn"][0]
+                .parse()
+                .expect("number");
+            if cn % 2 == 1 {
+                Some(re)
+            } else {
+                None
+            }
+        });
+        let adapters: Vec<Box<dyn Adapter<_, _>>> = vec![
+            Box::new(EntriesOnly::new()),
+            Box::new(odd_only),
+            Box::new(PagedResults::new(2)),
+        ];
+        let mut stream = ldap
+            .streaming_search_with(
+                adapters,
+                "dc=example,dc=org",
+                Scope::Subtree,
+                "(objectClass=*)",
+                vec!["cn"],
+            )
+            .await
+            .expect("stream");
+        let mut values = vec![];
+        while let Some(re) = stream.next().await.expect("entry") {
+            values.push(SearchEntry::construct(re).attrs["cn"][0].clone());
+        }
+        assert_eq!(values, vec!["1", "3"]);
+        assert_eq!(stream.finish().await.refs.len(), 1);
+    }
+
+    // Adapter counting the entries which pass through it in a counter owned by the caller.
+    #[derive(Clone, Debug)]
+    struct CountEntries<'c> {
+        seen: &'c AtomicUsize,
+    }
+
+    impl SoloMarker for CountEntries<'_> {}
+
+    #[async_trait]
+    impl<'a, 'c: 'a, S, A> Adapter<'a, S, A> for CountEntries<'c>
+    where
+        S: AsRef<str> + Send + Sync + 'a,
+        A: AsRef<[S]> + Send + Sync + 'a,
+    {
+        async fn start(
+            &mut self,
+            stream: &mut SearchStream<'a, S, A>,
+            base: &str,
+            scope: Scope,
+            filter: &str,
+            attrs: A,
+        ) -> crate::result::Result<()> {
+            stream.start(base, scope, filter, attrs).await
+        }
+
+        async fn next(
+            &mut self,
+            stream: &mut SearchStream<'a, S, A>,
+        ) -> crate::result::Result<Option<ResultEntry>> {
+            let item = stream.next().await?;
+            if item.is_some() {
+                self.seen.fetch_add(1, Ordering::Relaxed);
+            }
+            Ok(item)
+        }
+
+        async fn finish(&mut self, stream: &mut SearchStream<'a, S, A>) -> LdapResult {
+            stream.finish().await
+        }
+    }
+
+    #[tokio::test]
+    async fn borrowed_adapter_state() {
+        let (mut ldap, _requests) = paging_connection(true).await;
+        let seen = AtomicUsize::new(0);
+        let mapped = AtomicUsize::new(0);
+        let adapters: Vec<Box<dyn Adapter<_, _>>> = vec![
+            Box::new(EntriesOnly::new()),
+            Box::new(CountEntries { seen: &seen }),
+            Box::new(MapEntries::new(|re| {
+                mapped.fetch_add(1, Ordering::Relaxed);
+                Some(re)
+            })),
+            Box::new(PagedResults::new(2)),
+        ];
+        let mut stream = ldap
+            .streaming_search_with(
+                adapters,
+                "dc=example,dc=org",
+                Scope::Subtree,
+                "(objectClass=*)",
+                vec!["cn"],
+            )
+            .await
+            .expect("stream");
+        let mut count = 0;
+        while stream.next().await.expect("entry").is_some() {
+            count += 1;
+        }
+        assert_eq!(stream.finish().await.rc, 0);
+        assert_eq!(count, PAGED_ENTRIES);
+        // The referral is counted too, since EntriesOnly removes it later.
+        assert_eq!(seen.load(Ordering::Relaxed), PAGED_ENTRIES + 1);
+        assert_eq!(mapped.load(Ordering::Relaxed), PAGED_ENTRIES + 1);
+        // Borrowing adapters can be cloned for a chain on another connection.
+        let tail = stream.adapter_chain_tail().await;
+        assert_eq!(tail.len(), 4);
+        drop(stream);
+
+        // A bare borrowing adapter.
+        let mut stream = ldap
+            .streaming_search_with(
+                CountEntries { seen: &seen },
+                "dc=example,dc=org",
+                Scope::Subtree,
+                "(objectClass=*)",
+                vec!["cn"],
+            )
+            .await
+            .expect("stream");
+        while stream.next().await.expect("entry").is_some() {}
+        stream.finish().await;
+        drop(stream);
+        assert!(seen.load(Ordering::Relaxed) > PAGED_ENTRIES + 1);
+    }
+}
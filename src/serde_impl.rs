@@ -0,0 +1,210 @@
+// Serde helpers for binary values, which are represented as Base64 strings.
+
+use std::collections::HashMap;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::ldap::AttrValue;
+
+// Attribute map with binary values, as in SearchEntry::bin_attrs.
+pub(crate) mod bin_attrs {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        attrs: &HashMap<String, Vec<Vec<u8>>>,
+        ser: S,
+    ) -> Result<S::Ok, S::Error> {
+        attrs
+            .iter()
+            .map(|(attr, vals)| {
+                let vals: Vec<_> = vals.iter().map(|val| STANDARD.encode(val)).collect();
+                (attr, vals)
+            })
+            .collect::<HashMap<_, _>>()
+            .serialize(ser)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        de: D,
+    ) -> Result<HashMap<String, Vec<Vec<u8>>>, D::Error> {
+        HashMap::<String, Vec<String>>::deserialize(de)?
+            .into_iter()
+            .map(|(attr, vals)| {
+                let vals = vals
+                    .iter()
+                    .map(|val| STANDARD.decode(val).map_err(D::Error::custom))
+                    .collect::<Result<_, _>>()?;
+                Ok((attr, vals))
+            })
+            .collect()
+    }
+}
+
+// Optional binary value, as in RawControl::val.
+pub(crate) mod opt_bytes {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        val: &Option<Vec<u8>>,
+        ser: S,
+    ) -> Result<S::Ok, S::Error> {
+        val.as_ref().map(|val| STANDARD.encode(val)).serialize(ser)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        de: D,
+    ) -> Result<Option<Vec<u8>>, D::Error> {
+        Option::<String>::deserialize(de)?
+            .map(|val| STANDARD.decode(val).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+// Representation of AttrValue: a string, or a map with a Base64-encoded binary value.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum AttrValueRepr {
+    Text(String),
+    Bin {
+        #[serde(with = "bytes")]
+        bin: Vec<u8>,
+    },
+}
+
+impl From<AttrValue> for AttrValueRepr {
+    fn from(val: AttrValue) -> Self {
+        match val {
+            AttrValue::Text(s) => AttrValueRepr::Text(s),
+            AttrValue::Bin(bin) => AttrValueRepr::Bin { bin },
+        }
+    }
+}
+
+impl From<AttrValueRepr> for AttrValue {
+    fn from(repr: AttrValueRepr) -> Self {
+        match repr {
+            AttrValueRepr::Text(s) => AttrValue::Text(s),
+            AttrValueRepr::Bin { bin } => AttrValue::Bin(bin),
+        }
+    }
+}
+
+// Binary value.
+mod bytes {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(val: &[u8], ser: S) -> Result<S::Ok, S::Error> {
+        STANDARD.encode(val).serialize(ser)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<Vec<u8>, D::Error> {
+        STANDARD
+            .decode(String::deserialize(de)?)
+            .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{HashMap, HashSet};
+
+    use crate::controls::{Control, ControlType, MatchedValues};
+    use crate::{
+        AttrValue, DerefAliases, LdapResult, Mod, ModOwned, Scope, SearchEntry, SearchOptions,
+    };
+
+    use serde_json::json;
+
+    // Not a real JPEG, but the initial bytes aren't valid UTF-8 either.
+    const PHOTO: &[u8] = b"\xff\xd8\xff\xe0\x00\x10JFIF";
+
+    #[test]
+    fn entry_round_trip() {
+        let entry = SearchEntry {
+            dn: String::from("cn=test,dc=example,dc=org"),
+            attrs: HashMap::from([(String::from("cn"), vec![String::from("test")])]),
+            bin_attrs: HashMap::from([(String::from("jpegPhoto"), vec![PHOTO.to_vec()])]),
+            truncated: vec![],
+        };
+        let value = serde_json::to_value(&entry).expect("serialized");
+        assert_eq!(
+            value,
+            json!({
+                "dn": "cn=test,dc=example,dc=org",
+                "attrs": { "cn": ["test"] },
+                "bin_attrs": { "jpegPhoto": ["/9j/4AAQSkZJRg=="] },
+            })
+        );
+        let text = serde_json::to_string(&entry).expect("serialized");
+        for parsed in [
+            serde_json::from_value::<SearchEntry>(value).expect("from value"),
+            serde_json::from_str::<SearchEntry>(&text).expect("from string"),
+        ] {
+            assert_eq!(parsed.dn, entry.dn);
+            assert_eq!(parsed.attrs, entry.attrs);
+            assert_eq!(parsed.bin_attrs, entry.bin_attrs);
+        }
+        let bad = json!({ "dn": "", "attrs": {}, "bin_attrs": { "jpegPhoto": ["not base64!"] } });
+        assert!(serde_json::from_value::<SearchEntry>(bad).is_err());
+    }
+
+    #[test]
+    fn result_with_control() {
+        let res = LdapResult {
+            rc: 0,
+            matched: String::new(),
+            text: String::new(),
+            refs: vec![],
+            ctrls: vec![Control(None, MatchedValues::new("((cn=test))"))],
+        };
+        let value = serde_json::to_value(&res).expect("serialized");
+        let ctrl = &value["ctrls"][0];
+        assert_eq!(ctrl["ctype"], "1.2.826.0.1.3344810.2.3");
+        assert!(ctrl["val"].is_string());
+        let parsed: LdapResult = serde_json::from_value(value).expect("deserialized");
+        assert!(matches!(
+            parsed.ctrls[0].0,
+            Some(ControlType::MatchedValues)
+        ));
+        assert_eq!(parsed.ctrls[0].1.val, res.ctrls[0].1.val);
+    }
+
+    #[test]
+    fn options_and_mods() {
+        assert_eq!(
+            serde_json::to_value([Scope::Base, Scope::OneLevel, Scope::Subtree]).expect("scopes"),
+            json!(["base", "one", "sub"])
+        );
+        let opts: SearchOptions =
+            serde_json::from_value(json!({ "deref": "always", "sizelimit": 10 })).expect("opts");
+        assert_eq!(opts.deref, Some(DerefAliases::Always));
+        assert_eq!((opts.sizelimit, opts.timelimit), (Some(10), None));
+        let m = Mod::Replace(
+            String::from("description"),
+            HashSet::from([String::from("x")]),
+        );
+        let value = serde_json::to_value(&m).expect("mod");
+        assert_eq!(value, json!({ "replace": ["description", ["x"]] }));
+        assert_eq!(
+            serde_json::from_value::<Mod<String>>(value).expect("mod"),
+            m
+        );
+    }
+
+    #[test]
+    fn attr_values() {
+        let m: ModOwned = Mod::Add(
+            AttrValue::from("jpegPhoto"),
+            HashSet::from([AttrValue::from(PHOTO)]),
+        );
+        let value = serde_json::to_value(&m).expect("mod");
+        assert_eq!(
+            value,
+            json!({ "add": ["jpegPhoto", [{ "bin": "/9j/4AAQSkZJRg==" }]] })
+        );
+        assert_eq!(serde_json::from_value::<ModOwned>(value).expect("mod"), m);
+    }
+}
@@ -0,0 +1,219 @@
+use std::io::{self, Read, Write};
+
+use crate::result::{LdapError, Result};
+use crate::search::ResultEntry;
+
+const SPOOL_MAGIC: &[u8; 4] = b"LDSP";
+const SPOOL_VERSION: u8 = 1;
+
+/// Writer for a spool of search result entries.
+///
+/// A spool is a simple framed format for storing a series of [`ResultEntry`](struct.ResultEntry.html)
+/// instances. It starts with a header consisting of four magic bytes and a version byte,
+/// followed by any number of frames. Each frame contains a four-byte big-endian length and
+/// the BER encoding of an entry, as produced by
+/// [`ResultEntry::to_bytes()`](struct.ResultEntry.html#method.to_bytes). Response controls
+/// are not stored.
+///
+/// The spool can be read back with [`SpoolReader`](struct.SpoolReader.html).
+#[derive(Debug)]
+pub struct SpoolWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> SpoolWriter<W> {
+    /// Create a spool writer, immediately writing the header to `inner`.
+    pub fn new(mut inner: W) -> Result<Self> {
+        inner.write_all(SPOOL_MAGIC)?;
+        inner.write_all(&[SPOOL_VERSION])?;
+        Ok(SpoolWriter { inner })
+    }
+
+    /// Append an entry to the spool.
+    pub fn write(&mut self, entry: &ResultEntry) -> Result<()> {
+        let bytes = entry.to_bytes();
+        let len =
+            u32::try_from(bytes.len()).map_err(|_| LdapError::InvalidSpool("entry too large"))?;
+        self.inner.write_all(&len.to_be_bytes())?;
+        self.inner.write_all(&bytes)?;
+        Ok(())
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(self.inner.flush()?)
+    }
+
+    /// Flush the underlying writer and return it.
+    pub fn into_inner(mut self) -> Result<W> {
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+/// Reader for a spool of search result entries.
+///
+/// See [`SpoolWriter`](struct.SpoolWriter.html) for the description of the format. The
+/// reader validates the header on creation, and each entry as it's read. A spool which
+/// ends in the middle of a frame is reported as an error, as opposed to the clean end
+/// of the spool at a frame boundary.
+#[derive(Debug)]
+pub struct SpoolReader<R: Read> {
+    inner: R,
+    buf: Vec<u8>,
+}
+
+impl<R: Read> SpoolReader<R> {
+    /// Create a spool reader, reading and checking the header from `inner`.
+    pub fn new(mut inner: R) -> Result<Self> {
+        let mut header = [0u8; 5];
+        read_exact(&mut inner, &mut header, "truncated header")?;
+        if &header[..4] != SPOOL_MAGIC {
+            return Err(LdapError::InvalidSpool("bad magic"));
+        }
+        if header[4] != SPOOL_VERSION {
+            return Err(LdapError::InvalidSpool("unsupported version"));
+        }
+        Ok(SpoolReader { inner, buf: vec![] })
+    }
+
+    /// Read the next entry from the spool.
+    ///
+    /// Returns `Ok(None)` at the end of the spool.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<ResultEntry>> {
+        let mut len = [0u8; 4];
+        let first = loop {
+            match self.inner.read(&mut len[..1]) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        };
+        if first == 0 {
+            return Ok(None);
+        }
+        read_exact(&mut self.inner, &mut len[1..], "truncated frame length")?;
+        // The length isn't trusted: the buffer grows with the data actually read, so
+        // that a corrupt prefix can't cause a huge allocation.
+        let len = u64::from(u32::from_be_bytes(len));
+        self.buf.clear();
+        (&mut self.inner).take(len).read_to_end(&mut self.buf)?;
+        if (self.buf.len() as u64) < len {
+            return Err(LdapError::InvalidSpool("truncated frame"));
+        }
+        ResultEntry::from_bytes(&self.buf).map(Some)
+    }
+}
+
+impl<R: Read> Iterator for SpoolReader<R> {
+    type Item = Result<ResultEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        SpoolReader::next(self).transpose()
+    }
+}
+
+fn read_exact<R: Read>(r: &mut R, buf: &mut [u8], what: &'static str) -> Result<()> {
+    match r.read_exact(buf) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Err(LdapError::InvalidSpool(what)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SpoolReader, SpoolWriter};
+    use crate::fixtures;
+    use crate::result::LdapError;
+    use crate::search::{ResultEntry, SearchEntry};
+
+    use lber::structures::ASNTag;
+
+    fn entry(dn: &str, attrs: &[(&str, &[&[u8]])]) -> ResultEntry {
+        ResultEntry::new(fixtures::entry(dn, attrs).into_structure())
+    }
+
+    fn sample() -> ResultEntry {
+        entry(
+            "uid=test,dc=example,dc=org",
+            &[
+                ("cn", &[b"Test User"]),
+                ("jpegPhoto", &[b"\xff\xd8\xff\xe0"]),
+            ],
+        )
+    }
+
+    #[test]
+    fn entry_round_trip() {
+        let re = sample();
+        let decoded = ResultEntry::from_bytes(&re.to_bytes()).expect("entry");
+        let direct = SearchEntry::construct(re);
+        let parsed = SearchEntry::construct(decoded);
+        assert_eq!(parsed.dn, direct.dn);
+        assert_eq!(parsed.attrs, direct.attrs);
+        assert_eq!(parsed.bin_attrs, direct.bin_attrs);
+    }
+
+    #[test]
+    fn reject_non_entry() {
+        let bytes = fixtures::octet_string(b"dc=example,dc=org").into_structure();
+        let re = ResultEntry::new(bytes);
+        assert!(matches!(
+            ResultEntry::from_bytes(&re.to_bytes()),
+            Err(LdapError::MalformedEntry(_))
+        ));
+    }
+
+    #[test]
+    fn spool_round_trip() {
+        let mut writer = SpoolWriter::new(Vec::new()).expect("writer");
+        for _ in 0..3 {
+            writer.write(&sample()).expect("write");
+        }
+        let spool = writer.into_inner().expect("spool");
+        let mut reader = SpoolReader::new(&spool[..]).expect("reader");
+        let mut count = 0;
+        while let Some(re) = reader.next().expect("entry") {
+            assert_eq!(re.0, sample().0);
+            count += 1;
+        }
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn spool_truncated() {
+        let mut writer = SpoolWriter::new(Vec::new()).expect("writer");
+        writer.write(&sample()).expect("write");
+        let spool = writer.into_inner().expect("spool");
+        for cut in [3, 7, spool.len() - 1] {
+            let res = SpoolReader::new(&spool[..cut]).and_then(|mut r| r.next());
+            assert!(
+                matches!(res, Err(LdapError::InvalidSpool(_))),
+                "cut at {}",
+                cut
+            );
+        }
+    }
+
+    #[test]
+    fn spool_huge_frame_length() {
+        let mut spool = b"LDSP\x01\xff\xff\xff\xff".to_vec();
+        spool.extend_from_slice(&sample().to_bytes());
+        let mut reader = SpoolReader::new(&spool[..]).expect("reader");
+        assert!(matches!(
+            reader.next(),
+            Err(LdapError::InvalidSpool("truncated frame"))
+        ));
+        assert!(reader.buf.capacity() < 1 << 20);
+    }
+
+    #[test]
+    fn spool_bad_magic() {
+        assert!(matches!(
+            SpoolReader::new(&b"LDIF\x01"[..]),
+            Err(LdapError::InvalidSpool("bad magic"))
+        ));
+    }
+}
@@ -0,0 +1,146 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Statistics of a connection.
+///
+/// The statistics are maintained by the connection driver, and are shared by all handles
+/// of the connection; an instance is obtained by calling
+/// [`Ldap::conn_stats()`](struct.Ldap.html#method.conn_stats), and its methods return
+/// the current values. Counters only grow, and are updated as messages are processed.
+/// The sizes of the internal queues and maps are sampled each time the driver wakes up,
+/// so they may briefly lag behind the operations which change them.
+///
+/// Byte counts are those of the messages as exchanged with the server, including SASL
+/// wrapping if it's used, but not TLS records. The values are updated with relaxed atomic operations, so reading
+/// them is cheap, but values read together aren't guaranteed to be mutually consistent.
+#[derive(Clone, Debug)]
+pub struct ConnStats(Arc<Counters>);
+
+#[derive(Debug)]
+struct Counters {
+    created: Instant,
+    ops_sent: AtomicU64,
+    results_received: AtomicU64,
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+    // Microseconds since `created`.
+    last_activity: AtomicU64,
+    pending_results: AtomicUsize,
+    active_searches: AtomicUsize,
+    queued_requests: AtomicUsize,
+    unmatched_responses: AtomicU64,
+}
+
+impl ConnStats {
+    pub(crate) fn new() -> Self {
+        ConnStats(Arc::new(Counters {
+            created: Instant::now(),
+            ops_sent: AtomicU64::new(0),
+            results_received: AtomicU64::new(0),
+            bytes_read: AtomicU64::new(0),
+            bytes_written: AtomicU64::new(0),
+            last_activity: AtomicU64::new(0),
+            pending_results: AtomicUsize::new(0),
+            active_searches: AtomicUsize::new(0),
+            queued_requests: AtomicUsize::new(0),
+            unmatched_responses: AtomicU64::new(0),
+        }))
+    }
+
+    /// Return the number of requests written to the connection, including Abandon
+    /// and Unbind.
+    pub fn ops_sent(&self) -> u64 {
+        self.0.ops_sent.load(Ordering::Relaxed)
+    }
+
+    /// Return the number of final results received, for a Search the SearchResultDone
+    /// message. Entries, referrals, intermediate responses and unsolicited notifications
+    /// aren't counted.
+    pub fn results_received(&self) -> u64 {
+        self.0.results_received.load(Ordering::Relaxed)
+    }
+
+    /// Return the number of bytes of LDAP messages received.
+    pub fn bytes_read(&self) -> u64 {
+        self.0.bytes_read.load(Ordering::Relaxed)
+    }
+
+    /// Return the number of bytes of LDAP messages sent.
+    pub fn bytes_written(&self) -> u64 {
+        self.0.bytes_written.load(Ordering::Relaxed)
+    }
+
+    /// Return the time when a message was last sent or received, or when the connection
+    /// was established if there haven't been any messages.
+    pub fn last_activity(&self) -> Instant {
+        let micros = self.0.last_activity.load(Ordering::Relaxed);
+        self.0.created + Duration::from_micros(micros)
+    }
+
+    /// Return the number of sent operations other than Search which are waiting for
+    /// their result.
+    pub fn pending_results(&self) -> usize {
+        self.0.pending_results.load(Ordering::Relaxed)
+    }
+
+    /// Return the number of Searches whose results are still being received.
+    pub fn active_searches(&self) -> usize {
+        self.0.active_searches.load(Ordering::Relaxed)
+    }
+
+    /// Return the number of operations submitted by the handles, but not yet picked
+    /// up by the connection driver.
+    pub fn queued_requests(&self) -> usize {
+        self.0.queued_requests.load(Ordering::Relaxed)
+    }
+
+    /// Return the number of responses received for message IDs which were never used
+    /// by the client, as described for [`Unmatched`](enum.Unmatched.html).
+    pub fn unmatched_responses(&self) -> u64 {
+        self.0.unmatched_responses.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn op_sent(&self) {
+        self.0.ops_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn result_received(&self) {
+        self.0.results_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn unmatched_received(&self) {
+        self.0.unmatched_responses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn read(&self, bytes: usize) {
+        if bytes > 0 {
+            self.0.bytes_read.fetch_add(bytes as u64, Ordering::Relaxed);
+            self.touch();
+        }
+    }
+
+    pub(crate) fn written(&self, bytes: usize) {
+        if bytes > 0 {
+            self.0
+                .bytes_written
+                .fetch_add(bytes as u64, Ordering::Relaxed);
+            self.touch();
+        }
+    }
+
+    pub(crate) fn sample(&self, pending_results: usize, active_searches: usize, queued: usize) {
+        self.0
+            .pending_results
+            .store(pending_results, Ordering::Relaxed);
+        self.0
+            .active_searches
+            .store(active_searches, Ordering::Relaxed);
+        self.0.queued_requests.store(queued, Ordering::Relaxed);
+    }
+
+    fn touch(&self) {
+        let micros = self.0.created.elapsed().as_micros() as u64;
+        self.0.last_activity.fetch_max(micros, Ordering::Relaxed);
+    }
+}
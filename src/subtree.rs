@@ -0,0 +1,293 @@
+use std::time::Duration;
+
+use crate::adapters::EntriesOnly;
+use crate::ldap::Ldap;
+use crate::protocol::MaybeControls;
+use crate::result::{LdapError, LdapResult, Result};
+use crate::search::{Scope, SearchEntry};
+
+/// Limits and choices for [`Ldap::delete_subtree_with()`](struct.Ldap.html#method.delete_subtree_with).
+#[derive(Clone, Debug)]
+pub struct SubtreeDeleteOptions {
+    pub(crate) tree_delete: bool,
+    pub(crate) max_depth: usize,
+    pub(crate) max_entries: usize,
+}
+
+impl Default for SubtreeDeleteOptions {
+    fn default() -> Self {
+        SubtreeDeleteOptions {
+            tree_delete: true,
+            max_depth: 32,
+            max_entries: 10_000,
+        }
+    }
+}
+
+impl SubtreeDeleteOptions {
+    /// Create an instance with the default values: the Tree Delete control is tried first,
+    /// the subtree may be at most 32 levels deep below the base, and may contain at most
+    /// 10,000 entries, including the base.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Try to delete the subtree with a single operation using the
+    /// [`TreeDelete`](controls/struct.TreeDelete.html) control before falling back to
+    /// deleting the entries one by one.
+    pub fn tree_delete(mut self, tree_delete: bool) -> Self {
+        self.tree_delete = tree_delete;
+        self
+    }
+
+    /// Set the maximum depth of the subtree, counting the children of the base as
+    /// depth 1.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Set the maximum number of entries in the subtree, including the base.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+}
+
+/// Outcome of deleting a subtree.
+#[derive(Clone, Debug, Default)]
+pub struct SubtreeDeleteStats {
+    /// Whether the subtree was deleted by a single operation with the Tree Delete control.
+    pub tree_delete: bool,
+    /// Number of successful Delete operations. If the Tree Delete control was used,
+    /// this is 1, regardless of the size of the subtree.
+    pub deleted: usize,
+    /// Number of entries which weren't attempted because some of their subordinates
+    /// couldn't be deleted.
+    pub skipped: usize,
+    /// DNs of the entries whose deletion failed, with the results of their Delete operations.
+    pub failed: Vec<(String, LdapResult)>,
+}
+
+impl SubtreeDeleteStats {
+    /// Check whether the whole subtree was deleted.
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+// Restore the controls and the timeout taken from the handle, so that they apply to
+// the next operation.
+pub(crate) fn rearm(ldap: &mut Ldap, controls: &MaybeControls, timeout: Option<Duration>) {
+    ldap.controls = controls.clone();
+    ldap.timeout = timeout;
+}
+
+// DNs of the entries in the subtree rooted at `dn`, in pre-order, each with the index
+// of its superior. No entry is deleted before the whole subtree has been enumerated, so
+// that exceeding a limit leaves the subtree intact.
+pub(crate) async fn enumerate(
+    ldap: &mut Ldap,
+    dn: &str,
+    opts: &SubtreeDeleteOptions,
+    controls: &MaybeControls,
+    timeout: Option<Duration>,
+) -> Result<Vec<(String, Option<usize>)>> {
+    let mut entries = vec![];
+    let mut pending = vec![(dn.to_owned(), None, 0)];
+    while let Some((dn, sup, depth)) = pending.pop() {
+        if depth > opts.max_depth || entries.len() == opts.max_entries {
+            return Err(LdapError::SubtreeLimit {
+                depth,
+                entries: entries.len() + 1,
+            });
+        }
+        let index = entries.len();
+        entries.push((dn, sup));
+        rearm(ldap, controls, timeout);
+        let mut stream = ldap
+            .streaming_search_with(
+                EntriesOnly::new(),
+                &entries[index].0,
+                Scope::OneLevel,
+                "(objectClass=*)",
+                vec!["1.1"],
+            )
+            .await?;
+        while let Some(entry) = stream.next().await? {
+            let entry = SearchEntry::construct(entry);
+            pending.push((entry.dn, Some(index), depth + 1));
+        }
+        let res = stream.finish().await;
+        match res.rc {
+            // The entry may have been deleted in the meantime.
+            0 | 32 => (),
+            _ => return Err(LdapError::from(res)),
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::conn::LdapConnAsync;
+    use crate::controls::{RawControl, TreeDelete};
+    use crate::fixtures::{self, entry, message, result};
+
+    const BASE: &str = "ou=people,dc=example,dc=org";
+
+    fn parent(dn: &str) -> &str {
+        dn.split_once(',').map(|(_, sup)| sup).unwrap_or("")
+    }
+
+    // Scripted server holding a set of DNs. A OneLevel Search returns the immediate
+    // subordinates of the base, a Delete fails with notAllowedOnNonLeaf (66) if the entry
+    // has subordinates, or with unwillingToPerform (53) if the entry is `refused`. The Tree
+    // Delete control is rejected with `tree_delete_rc` if it's not zero.
+    async fn server(
+        tree: Arc<Mutex<BTreeSet<String>>>,
+        tree_delete_rc: i64,
+        refused: &'static str,
+    ) -> String {
+        let tree_delete_oid = RawControl::from(TreeDelete).ctype;
+        fixtures::serve(move |req| {
+            let mut tree = tree.lock().expect("tree");
+            let dn = req.dn();
+            match req.op.id {
+                3 if !tree.contains(&dn) => message(req.msgid, result(5, 32)),
+                3 => {
+                    let mut reply = vec![];
+                    for sub in tree.iter().filter(|sub| parent(sub) == dn) {
+                        reply.extend(message(req.msgid, entry(sub, &[])));
+                    }
+                    reply.extend(message(req.msgid, result(5, 0)));
+                    reply
+                }
+                10 => {
+                    let tree_delete = req.has_control(&tree_delete_oid);
+                    let suffix = format!(",{}", dn);
+                    let rc = if !tree.contains(&dn) {
+                        32
+                    } else if tree_delete && tree_delete_rc != 0 {
+                        tree_delete_rc
+                    } else if tree_delete {
+                        tree.retain(|entry| *entry != dn && !entry.ends_with(&suffix));
+                        0
+                    } else if tree.iter().any(|entry| entry.ends_with(&suffix)) {
+                        66
+                    } else if dn == refused {
+                        53
+                    } else {
+                        tree.remove(&dn);
+                        0
+                    };
+                    message(req.msgid, result(11, rc))
+                }
+                _ => vec![],
+            }
+        })
+        .await
+    }
+
+    fn tree() -> Arc<Mutex<BTreeSet<String>>> {
+        let mut tree = BTreeSet::new();
+        tree.insert(String::from("dc=example,dc=org"));
+        for rdn in [
+            "",
+            "uid=a,",
+            "uid=b,",
+            "cn=x,uid=b,",
+            "cn=y,uid=b,",
+            "uid=c,",
+        ] {
+            tree.insert(format!("{}{}", rdn, BASE));
+        }
+        Arc::new(Mutex::new(tree))
+    }
+
+    async fn delete(
+        tree: &Arc<Mutex<BTreeSet<String>>>,
+        tree_delete_rc: i64,
+        refused: &'static str,
+        opts: SubtreeDeleteOptions,
+    ) -> Result<SubtreeDeleteStats> {
+        let url = server(tree.clone(), tree_delete_rc, refused).await;
+        let (conn, mut ldap) = LdapConnAsync::new(&url).await.expect("connection");
+        crate::drive!(conn);
+        ldap.delete_subtree_with(BASE, &opts).await
+    }
+
+    fn remaining(tree: &Arc<Mutex<BTreeSet<String>>>) -> Vec<String> {
+        tree.lock().expect("tree").iter().cloned().collect()
+    }
+
+    #[tokio::test]
+    async fn with_control() {
+        let tree = tree();
+        let stats = delete(&tree, 0, "", SubtreeDeleteOptions::new())
+            .await
+            .expect("deleted");
+        assert!(stats.tree_delete);
+        assert_eq!(stats.deleted, 1);
+        assert!(stats.is_complete());
+        assert_eq!(remaining(&tree), ["dc=example,dc=org"]);
+    }
+
+    #[tokio::test]
+    async fn recursive_fallback() {
+        let tree = tree();
+        let stats = delete(&tree, 12, "", SubtreeDeleteOptions::new())
+            .await
+            .expect("deleted");
+        assert!(!stats.tree_delete);
+        assert_eq!(stats.deleted, 6);
+        assert!(stats.is_complete());
+        assert_eq!(remaining(&tree), ["dc=example,dc=org"]);
+    }
+
+    #[tokio::test]
+    async fn refused_child() {
+        let tree = tree();
+        let refused = "cn=x,uid=b,ou=people,dc=example,dc=org";
+        let stats = delete(&tree, 53, refused, SubtreeDeleteOptions::new())
+            .await
+            .expect("deleted");
+        assert_eq!(stats.deleted, 3);
+        assert_eq!(stats.skipped, 2);
+        assert_eq!(stats.failed.len(), 1);
+        assert_eq!(stats.failed[0].0, refused);
+        assert_eq!(stats.failed[0].1.rc, 53);
+        assert_eq!(
+            remaining(&tree),
+            [
+                "cn=x,uid=b,ou=people,dc=example,dc=org",
+                "dc=example,dc=org",
+                "ou=people,dc=example,dc=org",
+                "uid=b,ou=people,dc=example,dc=org",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn limits() {
+        let tree = tree();
+        let opts = SubtreeDeleteOptions::new().tree_delete(false).max_depth(1);
+        let res = delete(&tree, 0, "", opts).await;
+        assert!(matches!(res, Err(LdapError::SubtreeLimit { depth: 2, .. })));
+        assert_eq!(remaining(&tree).len(), 7);
+        let tree = self::tree();
+        let opts = SubtreeDeleteOptions::new()
+            .tree_delete(false)
+            .max_entries(5);
+        let res = delete(&tree, 0, "", opts).await;
+        assert!(matches!(
+            res,
+            Err(LdapError::SubtreeLimit { entries: 6, .. })
+        ));
+        assert_eq!(remaining(&tree).len(), 7);
+    }
+}
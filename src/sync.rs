@@ -1,17 +1,35 @@
 use std::collections::HashSet;
+use std::fmt;
+use std::future::Future;
 use std::hash::Hash;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use crate::adapters::IntoAdapterVec;
-use crate::conn::{LdapConnAsync, LdapConnSettings};
+use crate::batch::{Batch, BatchItemResult};
+use crate::cancel::OpCancellation;
+use crate::capabilities::ServerCapabilities;
+use crate::conn::{LdapConnAsync, LdapConnSettings, TlsInfo};
+use crate::controls::{Control, PersistentSearch};
 use crate::controls_impl::IntoRawControlVec;
+use crate::diff::{EnsureOptions, EnsureOutcome};
 use crate::exop::Exop;
-use crate::ldap::{Ldap, Mod};
-use crate::result::{CompareResult, ExopResult, LdapResult, Result, SearchResult};
-use crate::search::{ResultEntry, Scope, SearchOptions, SearchStream};
+use crate::groups::{GroupSchema, NestedMode};
+use crate::health::HealthStatus;
+use crate::ldap::{Ldap, Mod, ModifyIfOutcome, ValueBytes, ValueSource};
+use crate::ldaprc::DefaultsInfo;
+use crate::password::{PasswordFlavor, PasswordStatus};
+use crate::result::{CompareResult, ExopResult, LdapError, LdapResult, Result, SearchResult};
+use crate::rt::Runtime;
+use crate::schema::{RootDse, Schema};
+use crate::scram::ScramMech;
+use crate::search::{ResultEntry, Scope, SearchOptions, SearchStream, StreamState};
+use crate::stats::ConnStats;
+use crate::subtree::{SubtreeDeleteOptions, SubtreeDeleteStats};
+use crate::util::LdapUrl;
 use crate::RequestId;
 
-use tokio::runtime::{self, Runtime};
+use lber::structures::Tag;
 use url::Url;
 
 /// Synchronous connection to an LDAP server.
@@ -24,10 +42,14 @@ use url::Url;
 /// The API is virtually identical to the asynchronous one. The chief difference is
 /// that `LdapConn` is not cloneable: if you need another handle, you must open a
 /// new connection.
+///
+/// The methods can't be called from asynchronous code running on a Tokio runtime,
+/// since they would block the runtime's thread. Doing so returns
+/// [`LdapError::BlockingInRuntime`](result/enum.LdapError.html#variant.BlockingInRuntime).
 #[cfg_attr(docsrs, doc(cfg(feature = "sync")))]
 #[derive(Debug)]
 pub struct LdapConn {
-    rt: Runtime,
+    rt: Arc<Runtime>,
     ldap: Ldap,
 }
 
@@ -55,10 +77,8 @@ impl LdapConn {
     /// Open a connection to an LDAP server specified by an already parsed `Url`, using
     /// `settings` to specify additional parameters.
     pub fn from_url_with_settings(settings: LdapConnSettings, url: &Url) -> Result<Self> {
-        let rt = runtime::Builder::new_current_thread()
-            .enable_all()
-            .build()?;
-        let ldap = rt.block_on(async move {
+        let rt = Runtime::new()?;
+        let ldap = block_on(&rt, async move {
             let (conn, ldap) = match LdapConnAsync::from_url_with_settings(settings, url).await {
                 Ok((conn, ldap)) => (conn, ldap),
                 Err(e) => return Err(e),
@@ -66,7 +86,28 @@ impl LdapConn {
             super::drive!(conn);
             Ok(ldap)
         })?;
-        Ok(LdapConn { ldap, rt })
+        Ok(LdapConn {
+            ldap,
+            rt: Arc::new(rt),
+        })
+    }
+
+    /// Open a connection using the client configuration. See
+    /// [`LdapConnAsync::from_env()`](struct.LdapConnAsync.html#method.from_env).
+    pub fn from_env() -> Result<(Self, DefaultsInfo)> {
+        let rt = Runtime::new()?;
+        let (ldap, defaults) = block_on(&rt, async move {
+            let (conn, ldap, defaults) = LdapConnAsync::from_env().await?;
+            super::drive!(conn);
+            Ok((ldap, defaults))
+        })?;
+        Ok((
+            LdapConn {
+                ldap,
+                rt: Arc::new(rt),
+            },
+            defaults,
+        ))
     }
 
     /// See [`Ldap::with_search_options()`](struct.Ldap.html#method.with_search_options).
@@ -87,36 +128,102 @@ impl LdapConn {
         self
     }
 
+    /// See [`Ldap::with_deadline()`](struct.Ldap.html#method.with_deadline).
+    pub fn with_deadline(&mut self, deadline: Instant) -> &mut Self {
+        self.ldap.deadline = Some(deadline);
+        self
+    }
+
+    /// See [`Ldap::with_retries()`](struct.Ldap.html#method.with_retries).
+    pub fn with_retries(&mut self) -> &mut Self {
+        self.ldap.retries = true;
+        self
+    }
+
+    /// See [`Ldap::without_probe()`](struct.Ldap.html#method.without_probe).
+    pub fn without_probe(&mut self) -> &mut Self {
+        self.ldap.no_probe = true;
+        self
+    }
+
+    /// See [`Ldap::with_cancellation()`](struct.Ldap.html#method.with_cancellation).
+    ///
+    /// Since the operation blocks the calling thread, the token must be triggered from
+    /// another thread.
+    pub fn with_cancellation(&mut self, cancel: &OpCancellation) -> &mut Self {
+        self.ldap.cancel = Some(cancel.clone());
+        self
+    }
+
     /// See [`Ldap::simple_bind()`](struct.Ldap.html#method.simple_bind).
     pub fn simple_bind(&mut self, bind_dn: &str, bind_pw: &str) -> Result<LdapResult> {
-        let rt = &mut self.rt;
         let ldap = &mut self.ldap;
-        rt.block_on(async move { ldap.simple_bind(bind_dn, bind_pw).await })
+        block_on(
+            &self.rt,
+            async move { ldap.simple_bind(bind_dn, bind_pw).await },
+        )
     }
 
     /// See [`Ldap::sasl_external_bind()`](struct.Ldap.html#method.sasl_external_bind).
     pub fn sasl_external_bind(&mut self) -> Result<LdapResult> {
-        let rt = &mut self.rt;
         let ldap = &mut self.ldap;
-        rt.block_on(async move { ldap.sasl_external_bind().await })
+        block_on(&self.rt, async move { ldap.sasl_external_bind().await })
     }
 
-    #[cfg_attr(docsrs, doc(cfg(feature = "gssapi")))]
-    #[cfg(feature = "gssapi")]
     /// See [`Ldap::sasl_gssapi_bind()`](struct.Ldap.html#method.sasl_gssapi_bind).
     pub fn sasl_gssapi_bind(&mut self, server_fqdn: &str) -> Result<LdapResult> {
-        let rt = &mut self.rt;
         let ldap = &mut self.ldap;
-        rt.block_on(async move { ldap.sasl_gssapi_bind(server_fqdn).await })
+        block_on(
+            &self.rt,
+            async move { ldap.sasl_gssapi_bind(server_fqdn).await },
+        )
+    }
+
+    /// See [`Ldap::sasl_gssapi_bind_with_cb()`](struct.Ldap.html#method.sasl_gssapi_bind_with_cb).
+    pub fn sasl_gssapi_bind_with_cb(
+        &mut self,
+        server_fqdn: &str,
+        cb_data: Option<&[u8]>,
+    ) -> Result<LdapResult> {
+        let ldap = &mut self.ldap;
+        block_on(&self.rt, async move {
+            ldap.sasl_gssapi_bind_with_cb(server_fqdn, cb_data).await
+        })
     }
 
-    #[cfg_attr(docsrs, doc(cfg(feature = "ntlm")))]
-    #[cfg(feature = "ntlm")]
     /// See [`Ldap::sasl_ntlm_bind()`](struct.Ldap.html#method.sasl_ntlm_bind).
     pub fn sasl_ntlm_bind(&mut self, username: &str, password: &str) -> Result<LdapResult> {
-        let rt = &mut self.rt;
         let ldap = &mut self.ldap;
-        rt.block_on(async move { ldap.sasl_ntlm_bind(username, password).await })
+        block_on(&self.rt, async move {
+            ldap.sasl_ntlm_bind(username, password).await
+        })
+    }
+
+    /// See [`Ldap::sasl_ntlm_bind_with_cb()`](struct.Ldap.html#method.sasl_ntlm_bind_with_cb).
+    pub fn sasl_ntlm_bind_with_cb(
+        &mut self,
+        username: &str,
+        password: &str,
+        cb_data: Option<&[u8]>,
+    ) -> Result<LdapResult> {
+        let ldap = &mut self.ldap;
+        block_on(&self.rt, async move {
+            ldap.sasl_ntlm_bind_with_cb(username, password, cb_data)
+                .await
+        })
+    }
+
+    /// See [`Ldap::sasl_scram_bind()`](struct.Ldap.html#method.sasl_scram_bind).
+    pub fn sasl_scram_bind(
+        &mut self,
+        mech: ScramMech,
+        user: &str,
+        password: &str,
+    ) -> Result<LdapResult> {
+        let ldap = &mut self.ldap;
+        block_on(&self.rt, async move {
+            ldap.sasl_scram_bind(mech, user, password).await
+        })
     }
 
     /// See [`Ldap::search()`](struct.Ldap.html#method.search).
@@ -127,9 +234,82 @@ impl LdapConn {
         filter: &str,
         attrs: A,
     ) -> Result<SearchResult> {
-        let rt = &mut self.rt;
         let ldap = &mut self.ldap;
-        rt.block_on(async move { ldap.search(base, scope, filter, attrs).await })
+        block_on(&self.rt, async move {
+            ldap.search(base, scope, filter, attrs).await
+        })
+    }
+
+    /// See [`Ldap::search_paged()`](struct.Ldap.html#method.search_paged).
+    pub fn search_paged<
+        'a,
+        S: AsRef<str> + Clone + fmt::Debug + Send + Sync + 'a,
+        A: AsRef<[S]> + Clone + fmt::Debug + Send + Sync + 'a,
+    >(
+        &mut self,
+        base: &str,
+        scope: Scope,
+        filter: &str,
+        attrs: A,
+        page_size: i32,
+    ) -> Result<SearchResult> {
+        let ldap = &mut self.ldap;
+        block_on(&self.rt, async move {
+            ldap.search_paged(base, scope, filter, attrs, page_size)
+                .await
+        })
+    }
+
+    /// See [`Ldap::search_dns()`](struct.Ldap.html#method.search_dns).
+    pub fn search_dns(&mut self, base: &str, scope: Scope, filter: &str) -> Result<Vec<String>> {
+        let ldap = &mut self.ldap;
+        block_on(&self.rt, async move {
+            ldap.search_dns(base, scope, filter).await
+        })
+    }
+
+    /// See [`Ldap::exists()`](struct.Ldap.html#method.exists).
+    pub fn exists(&mut self, dn: &str) -> Result<bool> {
+        let ldap = &mut self.ldap;
+        block_on(&self.rt, async move { ldap.exists(dn).await })
+    }
+
+    /// See [`Ldap::count()`](struct.Ldap.html#method.count).
+    pub fn count(&mut self, base: &str, scope: Scope, filter: &str) -> Result<u64> {
+        let ldap = &mut self.ldap;
+        block_on(
+            &self.rt,
+            async move { ldap.count(base, scope, filter).await },
+        )
+    }
+
+    /// See [`Ldap::root_dse()`](struct.Ldap.html#method.root_dse).
+    pub fn root_dse(&mut self) -> Result<RootDse> {
+        let ldap = &mut self.ldap;
+        block_on(&self.rt, async move { ldap.root_dse().await })
+    }
+
+    /// See [`Ldap::capabilities()`](struct.Ldap.html#method.capabilities).
+    pub fn capabilities(&mut self) -> Result<ServerCapabilities> {
+        let ldap = &mut self.ldap;
+        block_on(&self.rt, async move { ldap.capabilities().await })
+    }
+
+    /// See [`Ldap::invalidate_capabilities()`](struct.Ldap.html#method.invalidate_capabilities).
+    pub fn invalidate_capabilities(&mut self) {
+        self.ldap.invalidate_capabilities();
+    }
+
+    /// See [`Ldap::schema()`](struct.Ldap.html#method.schema).
+    pub fn schema(&mut self) -> Result<Schema> {
+        let ldap = &mut self.ldap;
+        block_on(&self.rt, async move { ldap.schema().await })
+    }
+
+    /// See [`Ldap::search_url()`](struct.Ldap.html#method.search_url).
+    pub fn search_url(&mut self, url: &LdapUrl) -> Result<SearchResult> {
+        let ldap = &mut self.ldap;
+        block_on(&self.rt, async move { ldap.search_url(url).await })
     }
 
     /// Perform a Search, but unlike `search()`, which returns all results at once, return a handle which
@@ -137,87 +317,213 @@ impl LdapConn {
     /// for the explanation of the protocol which must be adhered to in this case.
     pub fn streaming_search<
         'a,
-        'b,
         S: AsRef<str> + Send + Sync + 'a,
         A: AsRef<[S]> + Send + Sync + 'a,
     >(
-        &'b mut self,
+        &mut self,
         base: &str,
         scope: Scope,
         filter: &str,
         attrs: A,
-    ) -> Result<EntryStream<'a, 'b, S, A>> {
-        let rt = &mut self.rt;
+    ) -> Result<EntryStream<'a, S, A>> {
         let ldap = &mut self.ldap;
-        let stream =
-            rt.block_on(async move { ldap.streaming_search(base, scope, filter, attrs).await })?;
-        Ok(EntryStream { stream, conn: self })
+        let stream = block_on(&self.rt, async move {
+            ldap.streaming_search(base, scope, filter, attrs).await
+        })?;
+        Ok(EntryStream {
+            stream,
+            rt: self.rt.clone(),
+        })
     }
 
     /// Perform a streaming Search internally modified by a chain of [adapters](adapters/index.html).
     /// See [`Ldap::streaming_search_with()`](struct.Ldap.html#method.streaming_search_with).
     pub fn streaming_search_with<
         'a,
-        'b,
         V: IntoAdapterVec<'a, S, A>,
         S: AsRef<str> + Send + Sync + 'a,
         A: AsRef<[S]> + Send + Sync + 'a,
     >(
-        &'b mut self,
+        &mut self,
         adapters: V,
         base: &str,
         scope: Scope,
         filter: &str,
         attrs: A,
-    ) -> Result<EntryStream<'a, 'b, S, A>> {
-        let rt = &mut self.rt;
+    ) -> Result<EntryStream<'a, S, A>> {
         let ldap = &mut self.ldap;
-        let stream = rt.block_on(async move {
+        let stream = block_on(&self.rt, async move {
             ldap.streaming_search_with(adapters.into(), base, scope, filter, attrs)
                 .await
         })?;
-        Ok(EntryStream { stream, conn: self })
+        Ok(EntryStream {
+            stream,
+            rt: self.rt.clone(),
+        })
+    }
+
+    /// See [`Ldap::watch()`](struct.Ldap.html#method.watch).
+    pub fn watch<'a, S: AsRef<str> + Send + Sync + 'a, A: AsRef<[S]> + Send + Sync + 'a>(
+        &mut self,
+        base: &str,
+        scope: Scope,
+        filter: &str,
+        attrs: A,
+        psearch: PersistentSearch,
+    ) -> Result<EntryStream<'a, S, A>> {
+        let ldap = &mut self.ldap;
+        let stream = block_on(&self.rt, async move {
+            ldap.watch(base, scope, filter, attrs, psearch).await
+        })?;
+        Ok(EntryStream {
+            stream,
+            rt: self.rt.clone(),
+        })
     }
 
     /// See [`Ldap::add()`](struct.Ldap.html#method.add).
-    pub fn add<S: AsRef<[u8]> + Eq + Hash>(
+    pub fn add<S: ValueBytes + Eq + Hash>(
         &mut self,
         dn: &str,
         attrs: Vec<(S, HashSet<S>)>,
     ) -> Result<LdapResult> {
-        let rt = &mut self.rt;
         let ldap = &mut self.ldap;
-        rt.block_on(async move { ldap.add(dn, attrs).await })
+        block_on(&self.rt, async move { ldap.add(dn, attrs).await })
+    }
+
+    /// See [`Ldap::add_streaming()`](struct.Ldap.html#method.add_streaming).
+    pub fn add_streaming<S: ValueBytes>(
+        &mut self,
+        dn: &str,
+        attrs: Vec<(S, ValueSource)>,
+    ) -> Result<LdapResult> {
+        let ldap = &mut self.ldap;
+        block_on(&self.rt, async move { ldap.add_streaming(dn, attrs).await })
     }
 
     /// See [`Ldap::compare()`](struct.Ldap.html#method.compare).
-    pub fn compare<B: AsRef<[u8]>>(
+    pub fn compare<B: ValueBytes>(
         &mut self,
         dn: &str,
         attr: &str,
         val: B,
     ) -> Result<CompareResult> {
-        let rt = &mut self.rt;
         let ldap = &mut self.ldap;
-        rt.block_on(async move { ldap.compare(dn, attr, val).await })
+        block_on(&self.rt, async move { ldap.compare(dn, attr, val).await })
     }
 
     /// See [`Ldap::delete()`](struct.Ldap.html#method.delete).
     pub fn delete(&mut self, dn: &str) -> Result<LdapResult> {
-        let rt = &mut self.rt;
         let ldap = &mut self.ldap;
-        rt.block_on(async move { ldap.delete(dn).await })
+        block_on(&self.rt, async move { ldap.delete(dn).await })
+    }
+
+    /// See [`Ldap::delete_if()`](struct.Ldap.html#method.delete_if).
+    pub fn delete_if(&mut self, dn: &str, assertion_filter: &str) -> Result<ModifyIfOutcome> {
+        let ldap = &mut self.ldap;
+        block_on(&self.rt, async move {
+            ldap.delete_if(dn, assertion_filter).await
+        })
+    }
+
+    /// See [`Ldap::delete_subtree()`](struct.Ldap.html#method.delete_subtree).
+    pub fn delete_subtree(&mut self, dn: &str) -> Result<SubtreeDeleteStats> {
+        let ldap = &mut self.ldap;
+        block_on(&self.rt, async move { ldap.delete_subtree(dn).await })
+    }
+
+    /// See [`Ldap::delete_subtree_with()`](struct.Ldap.html#method.delete_subtree_with).
+    pub fn delete_subtree_with(
+        &mut self,
+        dn: &str,
+        opts: &SubtreeDeleteOptions,
+    ) -> Result<SubtreeDeleteStats> {
+        let ldap = &mut self.ldap;
+        block_on(
+            &self.rt,
+            async move { ldap.delete_subtree_with(dn, opts).await },
+        )
+    }
+
+    /// See [`Ldap::is_member()`](struct.Ldap.html#method.is_member).
+    pub fn is_member(&mut self, group_dn: &str, user_dn: &str, nested: NestedMode) -> Result<bool> {
+        let ldap = &mut self.ldap;
+        block_on(&self.rt, async move {
+            ldap.is_member(group_dn, user_dn, nested).await
+        })
+    }
+
+    /// See [`Ldap::is_member_with()`](struct.Ldap.html#method.is_member_with).
+    pub fn is_member_with(
+        &mut self,
+        group_dn: &str,
+        user_dn: &str,
+        nested: NestedMode,
+        schema: &GroupSchema,
+    ) -> Result<bool> {
+        let ldap = &mut self.ldap;
+        block_on(&self.rt, async move {
+            ldap.is_member_with(group_dn, user_dn, nested, schema).await
+        })
+    }
+
+    /// See [`Ldap::member_of()`](struct.Ldap.html#method.member_of).
+    pub fn member_of(&mut self, user_dn: &str, nested: NestedMode) -> Result<Vec<String>> {
+        let ldap = &mut self.ldap;
+        block_on(
+            &self.rt,
+            async move { ldap.member_of(user_dn, nested).await },
+        )
+    }
+
+    /// See [`Ldap::member_of_with()`](struct.Ldap.html#method.member_of_with).
+    pub fn member_of_with(
+        &mut self,
+        user_dn: &str,
+        nested: NestedMode,
+        schema: &GroupSchema,
+    ) -> Result<Vec<String>> {
+        let ldap = &mut self.ldap;
+        block_on(&self.rt, async move {
+            ldap.member_of_with(user_dn, nested, schema).await
+        })
     }
 
     /// See [`Ldap::modify()`](struct.Ldap.html#method.modify).
-    pub fn modify<S: AsRef<[u8]> + Eq + Hash>(
+    pub fn modify<S: ValueBytes + Eq + Hash>(
         &mut self,
         dn: &str,
         mods: Vec<Mod<S>>,
     ) -> Result<LdapResult> {
-        let rt = &mut self.rt;
         let ldap = &mut self.ldap;
-        rt.block_on(async move { ldap.modify(dn, mods).await })
+        block_on(&self.rt, async move { ldap.modify(dn, mods).await })
+    }
+
+    /// See [`Ldap::modify_if()`](struct.Ldap.html#method.modify_if).
+    pub fn modify_if<S: ValueBytes + Eq + Hash>(
+        &mut self,
+        dn: &str,
+        assertion_filter: &str,
+        mods: Vec<Mod<S>>,
+    ) -> Result<ModifyIfOutcome> {
+        let ldap = &mut self.ldap;
+        block_on(&self.rt, async move {
+            ldap.modify_if(dn, assertion_filter, mods).await
+        })
+    }
+
+    /// See [`Ldap::ensure_entry()`](struct.Ldap.html#method.ensure_entry).
+    pub fn ensure_entry(
+        &mut self,
+        dn: &str,
+        attrs: Vec<(String, HashSet<String>)>,
+        opts: &EnsureOptions,
+    ) -> Result<EnsureOutcome> {
+        let ldap = &mut self.ldap;
+        block_on(
+            &self.rt,
+            async move { ldap.ensure_entry(dn, attrs, opts).await },
+        )
     }
 
     /// See [`Ldap::modifydn()`](struct.Ldap.html#method.modifydn).
@@ -228,16 +534,42 @@ impl LdapConn {
         delete_old: bool,
         new_sup: Option<&str>,
     ) -> Result<LdapResult> {
-        let rt = &mut self.rt;
         let ldap = &mut self.ldap;
-        rt.block_on(async move { ldap.modifydn(dn, rdn, delete_old, new_sup).await })
+        block_on(&self.rt, async move {
+            ldap.modifydn(dn, rdn, delete_old, new_sup).await
+        })
     }
 
     /// See [`Ldap::unbind()`](struct.Ldap.html#method.unbind).
     pub fn unbind(&mut self) -> Result<()> {
-        let rt = &mut self.rt;
         let ldap = &mut self.ldap;
-        rt.block_on(async move { ldap.unbind().await })
+        block_on(&self.rt, async move { ldap.unbind().await })
+    }
+
+    /// See [`Ldap::close()`](struct.Ldap.html#method.close).
+    pub fn close(self) -> Result<()> {
+        let LdapConn { rt, ldap } = self;
+        block_on(&rt, ldap.close())
+    }
+
+    /// See [`Ldap::change_password()`](struct.Ldap.html#method.change_password).
+    pub fn change_password(
+        &mut self,
+        user_dn: &str,
+        old: Option<&str>,
+        new: &str,
+        flavor: PasswordFlavor,
+    ) -> Result<LdapResult> {
+        let ldap = &mut self.ldap;
+        block_on(&self.rt, async move {
+            ldap.change_password(user_dn, old, new, flavor).await
+        })
+    }
+
+    /// See [`Ldap::password_status()`](struct.Ldap.html#method.password_status).
+    pub fn password_status(&mut self, user_dn: &str) -> Result<PasswordStatus> {
+        let ldap = &mut self.ldap;
+        block_on(&self.rt, async move { ldap.password_status(user_dn).await })
     }
 
     /// See [`Ldap::extended()`](struct.Ldap.html#method.extended).
@@ -245,9 +577,43 @@ impl LdapConn {
     where
         E: Into<Exop>,
     {
-        let rt = &mut self.rt;
         let ldap = &mut self.ldap;
-        rt.block_on(async move { ldap.extended(exop).await })
+        block_on(&self.rt, async move { ldap.extended(exop).await })
+    }
+
+    /// See [`Ldap::raw_op()`](struct.Ldap.html#method.raw_op).
+    pub fn raw_op(&mut self, req: Tag) -> Result<(LdapResult, Exop, Vec<Control>)> {
+        let ldap = &mut self.ldap;
+        block_on(&self.rt, async move { ldap.raw_op(req).await })
+    }
+
+    /// See [`Ldap::raw_search_op()`](struct.Ldap.html#method.raw_search_op).
+    pub fn raw_search_op(&mut self, req: Tag) -> Result<EntryStream<'static, String, Vec<String>>> {
+        let ldap = &mut self.ldap;
+        let stream = block_on(&self.rt, async move { ldap.raw_search_op(req).await })?;
+        Ok(EntryStream {
+            stream,
+            rt: self.rt.clone(),
+        })
+    }
+
+    /// See [`Ldap::batch()`](struct.Ldap.html#method.batch).
+    pub fn batch(&mut self) -> Batch {
+        self.ldap.batch()
+    }
+
+    /// Perform the operations queued in `batch`. See
+    /// [`Batch::execute()`](struct.Batch.html#method.execute). The outer `Result` can
+    /// only fail if the method is called from within an async runtime.
+    pub fn execute_batch(
+        &mut self,
+        batch: Batch,
+        concurrency: usize,
+    ) -> Result<Vec<Result<BatchItemResult>>> {
+        block_on(
+            &self.rt,
+            async move { Ok(batch.execute(concurrency).await) },
+        )
     }
 
     /// See [`Ldap::last_id()`](struct.Ldap.html#method.last_id).
@@ -257,9 +623,8 @@ impl LdapConn {
 
     /// See [`Ldap::abandon()`](struct.Ldap.html#method.abandon).
     pub fn abandon(&mut self, msgid: RequestId) -> Result<()> {
-        let rt = &mut self.rt;
         let ldap = &mut self.ldap;
-        rt.block_on(async move { ldap.abandon(msgid).await })
+        block_on(&self.rt, async move { ldap.abandon(msgid).await })
     }
 
     /// See [`Ldap::is_closed()`](struct.Ldap.html#method.is_closed).
@@ -267,11 +632,39 @@ impl LdapConn {
         self.ldap.tx.is_closed()
     }
 
+    /// See [`Ldap::check_health()`](struct.Ldap.html#method.check_health).
+    pub fn check_health(&mut self, timeout: Duration) -> HealthStatus {
+        let ldap = &mut self.ldap;
+        let status = block_on(
+            &self.rt,
+            async move { Ok(ldap.check_health(timeout).await) },
+        );
+        status.unwrap_or_else(|error| HealthStatus::Unusable { error })
+    }
+
     /// See [`Ldap::get_peer_certificate()`](struct.Ldap.html#method.get_peer_certificate).
     pub fn get_peer_certificate(&mut self) -> Result<Option<Vec<u8>>> {
-        let rt = &mut self.rt;
         let ldap = &mut self.ldap;
-        rt.block_on(async move { ldap.get_peer_certificate().await })
+        block_on(&self.rt, async move { ldap.get_peer_certificate().await })
+    }
+
+    /// See [`Ldap::conn_stats()`](struct.Ldap.html#method.conn_stats).
+    pub fn conn_stats(&self) -> ConnStats {
+        self.ldap.conn_stats()
+    }
+
+    /// Return the TLS parameters of the connection.
+    ///
+    /// See [`Ldap::tls_info()`](struct.Ldap.html#method.tls_info).
+    pub fn tls_info(&self) -> Option<TlsInfo> {
+        self.ldap.tls_info()
+    }
+
+    /// Return the __tls-server-end-point__ channel binding data of the connection.
+    ///
+    /// See [`Ldap::channel_binding_token()`](struct.Ldap.html#method.channel_binding_token).
+    pub fn channel_binding_token(&self) -> Option<Vec<u8>> {
+        self.ldap.channel_binding_token()
     }
 }
 
@@ -284,17 +677,30 @@ impl LdapConn {
 ///
 /// For compatibility, this struct's name is different from the async version
 /// which is [`SearchStream`](struct.SearchStream.html). The protocol and behavior
-/// are the same, with one important difference: an `EntryStream` shares the
-/// Tokio runtime with `LdapConn` from which it's obtained, but the two can't be
-/// used in parallel, which is enforced by capturing the reference to `LdapConn`
-/// during the lifetime of `EntryStream`.
+/// are the same. An `EntryStream` shares the Tokio runtime with the `LdapConn` from
+/// which it's obtained, but doesn't borrow the connection, which remains usable while
+/// the stream is active. For example, the Search can be abandoned before reading all
+/// entries:
+///
+/// ```rust,no_run
+/// # use ldap3::{LdapConn, Scope};
+/// # fn main() -> ldap3::result::Result<()> {
+/// let mut ldap = LdapConn::new("ldap://localhost:2389")?;
+/// let mut stream = ldap.streaming_search("", Scope::Subtree, "(objectClass=*)", vec!["*"])?;
+/// if let Some(_entry) = stream.next()? {
+///     ldap.abandon(stream.last_id())?;
+/// }
+/// let _res = stream.finish();
+/// # Ok(())
+/// # }
+/// ```
 #[cfg_attr(docsrs, doc(cfg(feature = "sync")))]
-pub struct EntryStream<'a, 'b, S, A> {
+pub struct EntryStream<'a, S, A> {
     stream: SearchStream<'a, S, A>,
-    conn: &'b mut LdapConn,
+    rt: Arc<Runtime>,
 }
 
-impl<'a, 'b, S, A> EntryStream<'a, 'b, S, A>
+impl<'a, S, A> EntryStream<'a, S, A>
 where
     S: AsRef<str> + Send + Sync + 'a,
     A: AsRef<[S]> + Send + Sync + 'a,
@@ -302,18 +708,51 @@ where
     /// See [`SearchStream::next()`](struct.SearchStream.html#method.next).
     #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Result<Option<ResultEntry>> {
-        let rt = &mut self.conn.rt;
         let stream = &mut self.stream;
-        rt.block_on(async move { stream.next().await })
+        block_on(&self.rt, async move { stream.next().await })
     }
 
     /// See [`SearchStream::finish()`](struct.SearchStream.html#method.finish).
     ///
+    /// If called from asynchronous code, the stream is left as it is, and the returned
+    /// result has the code 80 (other) and the description of the error.
+    pub fn finish(&mut self) -> LdapResult {
+        let stream = &mut self.stream;
+        match block_on(&self.rt, async move { Ok(stream.finish().await) }) {
+            Ok(res) => res,
+            Err(e) => LdapResult {
+                rc: 80,
+                matched: String::from(""),
+                text: e.to_string(),
+                refs: vec![],
+                ctrls: vec![],
+            },
+        }
+    }
+
+    /// Finish the stream and return the overall result, consuming the stream.
+    /// Equivalent to [`finish()`](#method.finish).
+    ///
     /// The name `result()` was kept for backwards compatibility.
     pub fn result(mut self) -> LdapResult {
-        let rt = &mut self.conn.rt;
+        self.finish()
+    }
+
+    /// See [`SearchStream::abandon()`](struct.SearchStream.html#method.abandon).
+    pub fn abandon(&mut self) -> Result<()> {
+        let stream = &mut self.stream;
+        block_on(&self.rt, async move { stream.abandon().await })
+    }
+
+    /// See [`SearchStream::cancel()`](struct.SearchStream.html#method.cancel).
+    pub fn cancel(&mut self) -> Result<()> {
         let stream = &mut self.stream;
-        rt.block_on(async move { stream.finish().await })
+        block_on(&self.rt, async move { stream.cancel().await })
+    }
+
+    /// See [`SearchStream::state()`](struct.SearchStream.html#method.state).
+    pub fn state(&self) -> StreamState {
+        self.stream.state()
     }
 
     /// Returns the Message ID of the initial Search.
@@ -324,3 +763,234 @@ where
         self.stream.ldap_handle().last_id()
     }
 }
+
+// Run the future on the connection's runtime, unless the caller is itself running
+// on a runtime, where blocking the thread would panic.
+pub(crate) fn block_on<T, F: Future<Output = Result<T>>>(rt: &Runtime, f: F) -> Result<T> {
+    if Runtime::in_async_context() {
+        return Err(LdapError::BlockingInRuntime);
+    }
+    rt.block_on(f)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::LdapConn;
+    use crate::adapters::{Adapter, EntriesOnly, SoloMarker};
+    use crate::fixtures::{entry, message, octet_string, result, Requests};
+    use crate::result::{LdapError, Result};
+    use crate::search::{ResultEntry, Scope, SearchStream, StreamState};
+
+    use async_trait::async_trait;
+    use lber::common::TagClass;
+    use lber::structures::{Sequence, Tag};
+
+    // Scripted server, running in its own thread, which answers each Search with
+    // a referral, two entries and a successful result.
+    fn server() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("listener");
+        let port = listener.local_addr().expect("local addr").port();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("accept");
+            let mut requests = Requests::new(stream.try_clone().expect("stream"));
+            while let Some(req) = requests.next_blocking() {
+                if req.op.id != 3 {
+                    continue;
+                }
+                let mut reply = message(
+                    req.msgid,
+                    Tag::Sequence(Sequence {
+                        id: 19,
+                        class: TagClass::Application,
+                        inner: vec![octet_string(b"ldap://elsewhere/")],
+                    }),
+                );
+                for cn in ["one", "two"] {
+                    let dn = format!("cn={},dc=example,dc=org", cn);
+                    reply.extend(message(req.msgid, entry(&dn, &[])));
+                }
+                reply.extend(message(req.msgid, result(5, 0)));
+                stream.write_all(&reply).expect("write");
+            }
+        });
+        port
+    }
+
+    // Adapter which counts the entries passing through it.
+    #[derive(Clone, Debug, Default)]
+    struct Counter(usize);
+
+    impl SoloMarker for Counter {}
+
+    #[async_trait]
+    impl<'a, S, A> Adapter<'a, S, A> for Counter
+    where
+        S: AsRef<str> + Send + Sync + 'a,
+        A: AsRef<[S]> + Send + Sync + 'a,
+    {
+        async fn start(
+            &mut self,
+            stream: &mut SearchStream<'a, S, A>,
+            base: &str,
+            scope: Scope,
+            filter: &str,
+            attrs: A,
+        ) -> Result<()> {
+            stream.start(base, scope, filter, attrs).await
+        }
+
+        async fn next(
+            &mut self,
+            stream: &mut SearchStream<'a, S, A>,
+        ) -> Result<Option<ResultEntry>> {
+            let entry = stream.next().await?;
+            if entry.is_some() {
+                self.0 += 1;
+            }
+            Ok(entry)
+        }
+
+        async fn finish(&mut self, stream: &mut SearchStream<'a, S, A>) -> crate::LdapResult {
+            let mut res = stream.finish().await;
+            res.text = format!("{} entries", self.0);
+            res
+        }
+    }
+
+    #[test]
+    fn adapted_stream() {
+        let port = server();
+        let mut ldap = LdapConn::new(&format!("ldap://127.0.0.1:{}", port)).expect("connection");
+        let mut stream = ldap
+            .streaming_search_with(
+                vec![
+                    Box::new(EntriesOnly::new()) as Box<dyn Adapter<_, _>>,
+                    Box::new(Counter::default()),
+                ],
+                "dc=example,dc=org",
+                Scope::Subtree,
+                "(objectClass=*)",
+                vec!["*"],
+            )
+            .expect("stream");
+        let search_id = stream.last_id();
+        let mut dns = vec![];
+        while let Some(entry) = stream.next().expect("entry") {
+            dns.push(crate::SearchEntry::construct(entry).dn);
+        }
+        assert_eq!(
+            dns,
+            ["cn=one,dc=example,dc=org", "cn=two,dc=example,dc=org"]
+        );
+        // The counter is closer to the stream than EntriesOnly, and also sees the referral,
+        // which EntriesOnly moves to the result.
+        let res = stream.finish();
+        assert_eq!((res.rc, res.text.as_str()), (0, "3 entries"));
+        assert_eq!(res.refs, ["ldap://elsewhere/"]);
+        assert_eq!(stream.state(), StreamState::Closed);
+        drop(stream);
+        // The connection is available again after the stream is dropped.
+        let mut stream = ldap
+            .streaming_search(
+                "dc=example,dc=org",
+                Scope::Subtree,
+                "(objectClass=*)",
+                vec!["*"],
+            )
+            .expect("stream");
+        assert!(stream.last_id() > search_id);
+        let mut count = 0;
+        while stream.next().expect("entry").is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 3);
+        assert_eq!(stream.result().rc, 0);
+    }
+
+    #[test]
+    fn abandon_active_stream() {
+        let port = server();
+        let mut ldap = LdapConn::new(&format!("ldap://127.0.0.1:{}", port)).expect("connection");
+        let mut stream = ldap
+            .streaming_search(
+                "dc=example,dc=org",
+                Scope::Subtree,
+                "(objectClass=*)",
+                vec!["*"],
+            )
+            .expect("stream");
+        assert!(stream.next().expect("entry").is_some());
+        ldap.abandon(stream.last_id()).expect("abandon");
+        // The server has already sent everything, so whatever was received before
+        // the Abandon may still be delivered; the stream must nevertheless end.
+        while let Ok(Some(_)) = stream.next() {}
+        stream.finish();
+        assert_eq!(stream.state(), StreamState::Closed);
+        // Another operation is possible while a stream is active.
+        let mut stream = ldap
+            .streaming_search(
+                "dc=example,dc=org",
+                Scope::Subtree,
+                "(objectClass=*)",
+                vec!["*"],
+            )
+            .expect("stream");
+        let (entries, res) = ldap
+            .search(
+                "dc=example,dc=org",
+                Scope::Subtree,
+                "(objectClass=*)",
+                vec!["*"],
+            )
+            .expect("search")
+            .success()
+            .expect("search result");
+        assert_eq!((entries.len(), res.rc), (2, 0));
+        let mut count = 0;
+        while stream.next().expect("entry").is_some() {
+            count += 1;
+        }
+        assert_eq!((count, stream.finish().rc), (3, 0));
+    }
+
+    #[cfg(feature = "runtime-tokio")]
+    #[test]
+    fn call_from_runtime() {
+        let port = server();
+        let mut ldap = LdapConn::new(&format!("ldap://127.0.0.1:{}", port)).expect("connection");
+        let rt = tokio::runtime::Runtime::new().expect("runtime");
+        let err = rt
+            .block_on(async { ldap.search("", Scope::Base, "(objectClass=*)", vec!["*"]) })
+            .expect_err("blocking call");
+        assert!(matches!(err, LdapError::BlockingInRuntime));
+        drop(rt);
+        let (entries, _) = ldap
+            .search("", Scope::Base, "(objectClass=*)", vec!["*"])
+            .expect("search")
+            .success()
+            .expect("search result");
+        assert_eq!(entries.len(), 2);
+    }
+
+    #[cfg(all(feature = "runtime-async-std", not(feature = "runtime-tokio")))]
+    #[test]
+    fn call_from_async_std_task() {
+        let port = server();
+        let mut ldap = LdapConn::new(&format!("ldap://127.0.0.1:{}", port)).expect("connection");
+        let err = async_std::task::block_on(async {
+            ldap.search("", Scope::Base, "(objectClass=*)", vec!["*"])
+        })
+        .expect_err("blocking call");
+        assert!(matches!(err, LdapError::BlockingInRuntime));
+        let (entries, _) = ldap
+            .search("", Scope::Base, "(objectClass=*)", vec!["*"])
+            .expect("search")
+            .success()
+            .expect("search result");
+        assert_eq!(entries.len(), 2);
+    }
+}
@@ -0,0 +1,759 @@
+//! Scripted LDAP server for testing.
+//!
+//! The [`MockServer`](struct.MockServer.html) listens on a random port of the loopback
+//! interface and answers each request with a sequence of responses produced by a closure
+//! registered for the type of the operation. A real connection can be made to the server
+//! by using the URL returned by [`MockHandle::url()`](struct.MockHandle.html#method.url),
+//! which makes it possible to test code using the library without a directory server.
+//! Alternatively, [`MockHandle::duplex()`](struct.MockHandle.html#method.duplex) opens
+//! an in-process connection, for use with
+//! [`LdapConnAsync::from_transport()`](../struct.LdapConnAsync.html#method.from_transport).
+//!
+//! ```rust,no_run
+//! # use ldap3::testkit::{bind_ok, done, entry, MockServer};
+//! # use ldap3::{LdapConnAsync, Scope};
+//! # async fn f() -> ldap3::result::Result<()> {
+//! let server = MockServer::new()
+//!     .on_bind(|_| bind_ok())
+//!     .on_search(|_| vec![entry("cn=a,dc=example,dc=org", &[("cn", &["a"])]), done(0)])
+//!     .start()
+//!     .await?;
+//! let (conn, mut ldap) = LdapConnAsync::new(&server.url()).await?;
+//! ldap3::drive!(conn);
+//! ldap.simple_bind("cn=admin,dc=example,dc=org", "secret").await?.success()?;
+//! let (entries, _res) = ldap
+//!     .search("dc=example,dc=org", Scope::Subtree, "(cn=a)", vec!["cn"])
+//!     .await?
+//!     .success()?;
+//! assert_eq!(entries.len(), 1);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Requests are decoded with the same codec the client uses, and every received request
+//! is recorded, so that a test can examine what was sent after the fact. An operation
+//! without a registered closure is answered with a successful result, except for Abandon
+//! and Unbind, which are not answered; Unbind also closes the connection. Each
+//! connection, and the sequence of responses to each request, is handled by a separate
+//! task, so that a delayed response doesn't hold up the processing of later requests.
+//! All tasks are stopped when the `MockHandle` is dropped.
+//!
+//! This module is available with the __test-util__ feature.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::conn::DEFAULT_MAX_BER_SIZE;
+use crate::controls::RawControl;
+use crate::controls_impl::IntoRawControlVec;
+use crate::protocol::{LdapCodec, MaybeControls};
+use crate::search::{encode_search_entry, SearchEntry};
+use crate::stats::ConnStats;
+use crate::unsolicited::NOTICE_OF_DISCONNECTION_OID;
+use crate::RequestId;
+
+use futures_util::sink::SinkExt;
+use lber::common::TagClass;
+use lber::structure::StructureTag;
+use lber::structures::{ASNTag, Enumerated, OctetString, Sequence, Tag};
+#[cfg(feature = "gssapi")]
+use std::sync::RwLock;
+use tokio::io::{AsyncRead, AsyncWrite, DuplexStream};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio::task::{JoinHandle, JoinSet};
+use tokio::time;
+use tokio_stream::StreamExt;
+use tokio_util::codec::Framed;
+
+/// Type of a protocol operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Operation {
+    /// Bind.
+    Bind,
+    /// Unbind.
+    Unbind,
+    /// Search.
+    Search,
+    /// Modify.
+    Modify,
+    /// Add.
+    Add,
+    /// Delete.
+    Delete,
+    /// ModifyDN.
+    ModifyDN,
+    /// Compare.
+    Compare,
+    /// Abandon.
+    Abandon,
+    /// Extended operation.
+    Extended,
+}
+
+impl Operation {
+    fn from_id(id: u64) -> Option<Operation> {
+        Some(match id {
+            0 => Operation::Bind,
+            2 => Operation::Unbind,
+            3 => Operation::Search,
+            6 => Operation::Modify,
+            8 => Operation::Add,
+            10 => Operation::Delete,
+            12 => Operation::ModifyDN,
+            14 => Operation::Compare,
+            16 => Operation::Abandon,
+            23 => Operation::Extended,
+            _ => return None,
+        })
+    }
+
+    // Protocol op ID of the result of the operation.
+    fn result_id(self) -> u64 {
+        match self {
+            Operation::Bind => 1,
+            Operation::Search => 5,
+            Operation::Modify => 7,
+            Operation::Add => 9,
+            Operation::Delete => 11,
+            Operation::ModifyDN => 13,
+            Operation::Compare => 15,
+            Operation::Unbind | Operation::Abandon | Operation::Extended => 24,
+        }
+    }
+}
+
+/// Request received by the mock server.
+#[derive(Clone, Debug)]
+pub struct Request {
+    /// Message ID.
+    pub id: RequestId,
+    /// Type of the operation.
+    pub operation: Operation,
+    /// Undecoded protocol operation.
+    pub op: StructureTag,
+    /// Request controls.
+    pub controls: Vec<RawControl>,
+}
+
+impl Request {
+    /// Return the DN which is the target of the operation: the name of a Bind, the base
+    /// of a Search, or the entry DN of an update or a Compare. For other operations,
+    /// return `None`.
+    pub fn dn(&self) -> Option<String> {
+        let bytes = match self.operation {
+            Operation::Delete => self.op.clone().expect_primitive(),
+            Operation::Bind => self.element(1),
+            Operation::Search
+            | Operation::Modify
+            | Operation::Add
+            | Operation::ModifyDN
+            | Operation::Compare => self.element(0),
+            _ => None,
+        };
+        bytes.and_then(|b| String::from_utf8(b).ok())
+    }
+
+    /// Return the request control with the given OID, if present.
+    pub fn control(&self, oid: &str) -> Option<&RawControl> {
+        self.controls.iter().find(|c| c.ctype == oid)
+    }
+
+    fn element(&self, index: usize) -> Option<Vec<u8>> {
+        self.op
+            .clone()
+            .expect_constructed()?
+            .into_iter()
+            .nth(index)?
+            .expect_primitive()
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Item {
+    Result {
+        rc: u32,
+        matched: String,
+        text: String,
+        refs: Vec<String>,
+        exop: Option<(Option<String>, Option<Vec<u8>>)>,
+        sasl_creds: Option<Vec<u8>>,
+    },
+    Op(StructureTag),
+    Delay(Duration),
+    Disconnect,
+}
+
+/// Response of the mock server.
+///
+/// Responses are constructed by the functions of this module, and can be further
+/// modified by the methods of this struct. Besides protocol messages, a sequence of
+/// responses can contain a delay or an instruction to close the connection.
+#[derive(Clone, Debug)]
+pub struct Response {
+    item: Item,
+    controls: Vec<RawControl>,
+    msgid: Option<RequestId>,
+}
+
+impl Response {
+    fn new(item: Item) -> Self {
+        Response {
+            item,
+            controls: vec![],
+            msgid: None,
+        }
+    }
+
+    /// Attach the controls to the response.
+    pub fn with_controls<V: IntoRawControlVec>(mut self, ctrls: V) -> Self {
+        self.controls.extend(ctrls.into());
+        self
+    }
+
+    /// Set the diagnostic message of a result.
+    pub fn with_text(mut self, text: &str) -> Self {
+        if let Item::Result {
+            text: ref mut t, ..
+        } = self.item
+        {
+            *t = String::from(text);
+        }
+        self
+    }
+
+    /// Set the matched DN of a result.
+    pub fn with_matched(mut self, dn: &str) -> Self {
+        if let Item::Result {
+            ref mut matched, ..
+        } = self.item
+        {
+            *matched = String::from(dn);
+        }
+        self
+    }
+
+    /// Set the referral URLs of a result.
+    pub fn with_referrals(mut self, urls: &[&str]) -> Self {
+        if let Item::Result { ref mut refs, .. } = self.item {
+            *refs = urls.iter().map(|u| String::from(*u)).collect();
+        }
+        self
+    }
+
+    /// Send the response as an unsolicited message, with the message ID of zero.
+    pub fn unsolicited(mut self) -> Self {
+        self.msgid = Some(0);
+        self
+    }
+
+    /// Send the response with the given message ID, instead of the ID of the request.
+    pub fn with_msgid(mut self, msgid: RequestId) -> Self {
+        self.msgid = Some(msgid);
+        self
+    }
+
+    fn encode(self, operation: Operation) -> Tag {
+        let tag = match self.item {
+            Item::Result {
+                rc,
+                matched,
+                text,
+                refs,
+                exop,
+                sasl_creds,
+            } => {
+                let mut inner = vec![
+                    Tag::Enumerated(Enumerated {
+                        inner: rc as i64,
+                        ..Default::default()
+                    }),
+                    octet_string(matched.into_bytes()),
+                    octet_string(text.into_bytes()),
+                ];
+                if !refs.is_empty() {
+                    inner.push(Tag::Sequence(Sequence {
+                        id: 3,
+                        class: TagClass::Context,
+                        inner: refs
+                            .into_iter()
+                            .map(|u| octet_string(u.into_bytes()))
+                            .collect(),
+                    }));
+                }
+                if let Some(creds) = sasl_creds {
+                    inner.push(Tag::OctetString(OctetString {
+                        id: 7,
+                        class: TagClass::Context,
+                        inner: creds,
+                    }));
+                }
+                let id = match exop {
+                    Some((name, value)) => {
+                        inner.extend(exop_parts(name, value, 10));
+                        24
+                    }
+                    None => operation.result_id(),
+                };
+                Tag::Sequence(Sequence {
+                    id,
+                    class: TagClass::Application,
+                    inner,
+                })
+                .into_structure()
+            }
+            Item::Op(tag) => tag,
+            Item::Delay(_) | Item::Disconnect => unreachable!(),
+        };
+        Tag::StructureTag(tag)
+    }
+}
+
+impl From<SearchEntry> for Response {
+    fn from(entry: SearchEntry) -> Response {
+        Response::new(Item::Op(entry.to_structure()))
+    }
+}
+
+fn octet_string(inner: Vec<u8>) -> Tag {
+    Tag::OctetString(OctetString {
+        inner,
+        ..Default::default()
+    })
+}
+
+// Optional name and value of an Extended response or an Intermediate message, with
+// context tags starting at `first_id`.
+fn exop_parts(name: Option<String>, value: Option<Vec<u8>>, first_id: u64) -> Vec<Tag> {
+    let mut parts = vec![];
+    if let Some(name) = name {
+        parts.push(Tag::OctetString(OctetString {
+            id: first_id,
+            class: TagClass::Context,
+            inner: name.into_bytes(),
+        }));
+    }
+    if let Some(value) = value {
+        parts.push(Tag::OctetString(OctetString {
+            id: first_id + 1,
+            class: TagClass::Context,
+            inner: value,
+        }));
+    }
+    parts
+}
+
+/// Result of the operation with the given result code. The type of the result
+/// matches the type of the request.
+pub fn done(rc: u32) -> Response {
+    Response::new(Item::Result {
+        rc,
+        matched: String::new(),
+        text: String::new(),
+        refs: vec![],
+        exop: None,
+        sasl_creds: None,
+    })
+}
+
+/// Successful result of a Bind.
+pub fn bind_ok() -> Response {
+    done(0)
+}
+
+/// Bind result with the given result code and SASL credentials from the server.
+/// For a multi-round SASL exchange, the result code is 14 (saslBindInProgress).
+pub fn sasl_bind(rc: u32, creds: &[u8]) -> Response {
+    Response::new(Item::Result {
+        rc,
+        matched: String::new(),
+        text: String::new(),
+        refs: vec![],
+        exop: None,
+        sasl_creds: Some(creds.to_vec()),
+    })
+}
+
+/// Search result entry with textual attribute values.
+pub fn entry(dn: &str, attrs: &[(&str, &[&str])]) -> Response {
+    let attrs = attrs
+        .iter()
+        .map(|(name, vals)| {
+            (
+                String::from(*name),
+                vals.iter().map(|v| String::from(*v)).collect(),
+            )
+        })
+        .collect();
+    Response::new(Item::Op(encode_search_entry(dn, &attrs, &HashMap::new())))
+}
+
+/// Search result reference with the given URLs.
+pub fn referral(urls: &[&str]) -> Response {
+    Response::new(Item::Op(
+        Tag::Sequence(Sequence {
+            id: 19,
+            class: TagClass::Application,
+            inner: urls
+                .iter()
+                .map(|u| octet_string(u.as_bytes().to_vec()))
+                .collect(),
+        })
+        .into_structure(),
+    ))
+}
+
+/// Extended response with the given result code, and optional name and value.
+pub fn extended(rc: u32, name: Option<&str>, value: Option<Vec<u8>>) -> Response {
+    Response::new(Item::Result {
+        rc,
+        matched: String::new(),
+        text: String::new(),
+        refs: vec![],
+        exop: Some((name.map(String::from), value)),
+        sasl_creds: None,
+    })
+}
+
+/// Intermediate response with optional name and value.
+pub fn intermediate(name: Option<&str>, value: Option<Vec<u8>>) -> Response {
+    Response::new(Item::Op(
+        Tag::Sequence(Sequence {
+            id: 25,
+            class: TagClass::Application,
+            inner: exop_parts(name.map(String::from), value, 0),
+        })
+        .into_structure(),
+    ))
+}
+
+/// Unsolicited Notice of Disconnection with the given result code. The server
+/// doesn't close the connection by itself; add [`disconnect()`](fn.disconnect.html)
+/// after the notice to do so.
+pub fn notice_of_disconnection(rc: u32) -> Response {
+    extended(rc, Some(NOTICE_OF_DISCONNECTION_OID), None).unsolicited()
+}
+
+/// Arbitrary protocol operation, sent as-is.
+pub fn raw(op: StructureTag) -> Response {
+    Response::new(Item::Op(op))
+}
+
+/// Pause before sending the rest of the responses.
+pub fn delay(duration: Duration) -> Response {
+    Response::new(Item::Delay(duration))
+}
+
+/// Close the connection without sending the rest of the responses.
+pub fn disconnect() -> Response {
+    Response::new(Item::Disconnect)
+}
+
+/// Conversion into a sequence of responses, for the return values of handlers.
+pub trait IntoResponses {
+    fn into_responses(self) -> Vec<Response>;
+}
+
+impl IntoResponses for Response {
+    fn into_responses(self) -> Vec<Response> {
+        vec![self]
+    }
+}
+
+impl IntoResponses for Vec<Response> {
+    fn into_responses(self) -> Vec<Response> {
+        self
+    }
+}
+
+type Handler = Arc<dyn Fn(&Request) -> Vec<Response> + Send + Sync>;
+
+/// Scripted LDAP server.
+///
+/// Handlers for operations are registered with the `on_*()` methods. Registering another
+/// handler for the same operation replaces the earlier one. The server is started with
+/// [`start()`](#method.start).
+#[derive(Clone, Default)]
+pub struct MockServer {
+    handlers: HashMap<Operation, Handler>,
+}
+
+impl fmt::Debug for MockServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockServer")
+            .field("handlers", &self.handlers.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl MockServer {
+    /// Create a server without any handlers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the handler for an operation.
+    pub fn on<F, R>(mut self, operation: Operation, f: F) -> Self
+    where
+        F: Fn(&Request) -> R + Send + Sync + 'static,
+        R: IntoResponses,
+    {
+        self.handlers
+            .insert(operation, Arc::new(move |req| f(req).into_responses()));
+        self
+    }
+
+    /// Register the handler for Bind.
+    pub fn on_bind<F, R>(self, f: F) -> Self
+    where
+        F: Fn(&Request) -> R + Send + Sync + 'static,
+        R: IntoResponses,
+    {
+        self.on(Operation::Bind, f)
+    }
+
+    /// Register the handler for Search.
+    pub fn on_search<F, R>(self, f: F) -> Self
+    where
+        F: Fn(&Request) -> R + Send + Sync + 'static,
+        R: IntoResponses,
+    {
+        self.on(Operation::Search, f)
+    }
+
+    /// Register the handler for Modify.
+    pub fn on_modify<F, R>(self, f: F) -> Self
+    where
+        F: Fn(&Request) -> R + Send + Sync + 'static,
+        R: IntoResponses,
+    {
+        self.on(Operation::Modify, f)
+    }
+
+    /// Register the handler for Add.
+    pub fn on_add<F, R>(self, f: F) -> Self
+    where
+        F: Fn(&Request) -> R + Send + Sync + 'static,
+        R: IntoResponses,
+    {
+        self.on(Operation::Add, f)
+    }
+
+    /// Register the handler for Delete.
+    pub fn on_delete<F, R>(self, f: F) -> Self
+    where
+        F: Fn(&Request) -> R + Send + Sync + 'static,
+        R: IntoResponses,
+    {
+        self.on(Operation::Delete, f)
+    }
+
+    /// Register the handler for ModifyDN.
+    pub fn on_modifydn<F, R>(self, f: F) -> Self
+    where
+        F: Fn(&Request) -> R + Send + Sync + 'static,
+        R: IntoResponses,
+    {
+        self.on(Operation::ModifyDN, f)
+    }
+
+    /// Register the handler for Compare.
+    pub fn on_compare<F, R>(self, f: F) -> Self
+    where
+        F: Fn(&Request) -> R + Send + Sync + 'static,
+        R: IntoResponses,
+    {
+        self.on(Operation::Compare, f)
+    }
+
+    /// Register the handler for Extended operations.
+    pub fn on_extended<F, R>(self, f: F) -> Self
+    where
+        F: Fn(&Request) -> R + Send + Sync + 'static,
+        R: IntoResponses,
+    {
+        self.on(Operation::Extended, f)
+    }
+
+    /// Bind a listener to a random port on the loopback interface, and start serving
+    /// connections in the background.
+    pub async fn start(self) -> io::Result<MockHandle> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        let handlers = Arc::new(self.handlers);
+        let requests = Arc::new(Mutex::new(vec![]));
+        let (conn_handlers, recorded) = (handlers.clone(), requests.clone());
+        let task = tokio::spawn(async move {
+            let mut conns = JoinSet::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                conns.spawn(serve(stream, conn_handlers.clone(), recorded.clone()));
+            }
+        });
+        Ok(MockHandle {
+            port,
+            handlers,
+            requests,
+            task,
+            duplex_conns: Mutex::new(JoinSet::new()),
+        })
+    }
+}
+
+/// Handle of a running mock server.
+///
+/// Dropping the handle stops the server and closes all connections.
+pub struct MockHandle {
+    port: u16,
+    handlers: Arc<HashMap<Operation, Handler>>,
+    requests: Arc<Mutex<Vec<Request>>>,
+    task: JoinHandle<()>,
+    duplex_conns: Mutex<JoinSet<()>>,
+}
+
+impl fmt::Debug for MockHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockHandle")
+            .field("port", &self.port)
+            .field("requests", &self.requests)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MockHandle {
+    /// Port on which the server is listening.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// LDAP URL of the server.
+    pub fn url(&self) -> String {
+        format!("ldap://127.0.0.1:{}", self.port)
+    }
+
+    /// Requests received so far, in the order of arrival.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().expect("requests").clone()
+    }
+
+    /// Open an in-process connection to the server, bypassing the network. The returned
+    /// stream is the client end, which can be passed to
+    /// [`LdapConnAsync::from_transport()`](../struct.LdapConnAsync.html#method.from_transport).
+    /// Requests on the connection are recorded together with those on TCP connections.
+    pub fn duplex(&self) -> DuplexStream {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        self.duplex_conns.lock().expect("connections").spawn(serve(
+            server,
+            self.handlers.clone(),
+            self.requests.clone(),
+        ));
+        client
+    }
+}
+
+impl Drop for MockHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+// Message from a request task to the connection task.
+enum Outgoing {
+    Message(RequestId, Tag, MaybeControls),
+    Close,
+}
+
+// Each request is answered by a separate task, so that delayed responses don't hold up
+// reading further requests, such as an Abandon of the delayed operation.
+async fn serve<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: S,
+    handlers: Arc<HashMap<Operation, Handler>>,
+    requests: Arc<Mutex<Vec<Request>>>,
+) {
+    let codec = LdapCodec {
+        max_ber_size: DEFAULT_MAX_BER_SIZE,
+        wire_trace: false,
+        keep_raw: false,
+        stats: ConnStats::new(),
+        #[cfg(feature = "gssapi")]
+        has_decoded_data: false,
+        #[cfg(feature = "gssapi")]
+        sasl_param: Arc::new(RwLock::new((false, 0))),
+        #[cfg(feature = "gssapi")]
+        client_ctx: Arc::new(Mutex::new(None)),
+    };
+    let mut framed = Framed::new(stream, codec);
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut tasks = JoinSet::new();
+    loop {
+        tokio::select! {
+            msg = framed.next() => {
+                let (id, (tag, controls), _) = match msg {
+                    Some(Ok(msg)) => msg,
+                    _ => return,
+                };
+                let op = match tag {
+                    Tag::StructureTag(op) if op.class == TagClass::Application => op,
+                    _ => return,
+                };
+                let operation = match Operation::from_id(op.id) {
+                    Some(operation) => operation,
+                    None => return,
+                };
+                let req = Request {
+                    id,
+                    operation,
+                    op,
+                    controls: controls.into_iter().map(|c| c.1).collect(),
+                };
+                requests.lock().expect("requests").push(req.clone());
+                let responses = match handlers.get(&operation) {
+                    Some(handler) => handler(&req),
+                    None if operation == Operation::Unbind || operation == Operation::Abandon => {
+                        vec![]
+                    }
+                    None => vec![done(0)],
+                };
+                tasks.spawn(respond(req, responses, tx.clone()));
+            }
+            Some(out) = rx.recv() => match out {
+                Outgoing::Message(msgid, tag, controls) => {
+                    if framed.send((msgid, tag, controls)).await.is_err() {
+                        return;
+                    }
+                }
+                Outgoing::Close => return,
+            },
+        }
+    }
+}
+
+async fn respond(req: Request, responses: Vec<Response>, tx: mpsc::UnboundedSender<Outgoing>) {
+    for mut resp in responses {
+        let out = match resp.item {
+            Item::Delay(duration) => {
+                time::sleep(duration).await;
+                continue;
+            }
+            Item::Disconnect => Outgoing::Close,
+            _ => {
+                let msgid = resp.msgid.unwrap_or(req.id);
+                let controls = std::mem::take(&mut resp.controls);
+                let controls = if controls.is_empty() {
+                    None
+                } else {
+                    Some(controls)
+                };
+                Outgoing::Message(msgid, resp.encode(req.operation), controls)
+            }
+        };
+        if tx.send(out).is_err() {
+            return;
+        }
+    }
+    if req.operation == Operation::Unbind {
+        let _ = tx.send(Outgoing::Close);
+    }
+}
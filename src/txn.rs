@@ -0,0 +1,186 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::time::Duration;
+
+use crate::controls_impl::{IntoRawControlVec, RawControl, TxnSpec};
+use crate::exop_impl::{EndTxn, EndTxnResp, ExopParser, StartTxn, StartTxnResp};
+use crate::ldap::{Ldap, Mod, ValueBytes};
+use crate::result::{LdapError, LdapResult, Result};
+use crate::RequestId;
+
+/// Transaction in progress ([RFC 5805](https://tools.ietf.org/html/rfc5805)).
+///
+/// A transaction is started by [`Ldap::begin_transaction()`](struct.Ldap.html#method.begin_transaction).
+/// The update methods of this struct work like their namesakes on [`Ldap`](struct.Ldap.html),
+/// but attach the Transaction Specification control to each request, so that the server
+/// queues the update instead of applying it. The server answers each update immediately;
+/// a non-success result means that the update can't be a part of the transaction, which
+/// will then fail if committed. The queued updates are applied together by
+/// [`commit()`](#method.commit), or discarded by [`abort()`](#method.abort).
+///
+/// The transaction has its own clone of the handle it was started from. If it's dropped
+/// without being ended, an abort is sent in a background task if a Tokio runtime is
+/// available; the outcome isn't reported, and the server will eventually discard the
+/// transaction in any case.
+///
+/// ```rust,no_run
+/// # use std::collections::HashSet;
+/// # use ldap3::{LdapConnAsync, Mod};
+/// # async fn f() -> ldap3::result::Result<()> {
+/// # let (conn, mut ldap) = LdapConnAsync::new("ldap://localhost:2389").await?;
+/// # ldap3::drive!(conn);
+/// let mut txn = ldap.begin_transaction().await?;
+/// txn.modify("uid=a,ou=People,dc=example,dc=org", vec![Mod::Delete("memberOf", HashSet::new())])
+///     .await?
+///     .success()?;
+/// txn.delete("uid=b,ou=People,dc=example,dc=org").await?.success()?;
+/// let (res, _resp) = txn.commit().await?;
+/// res.success()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Transaction {
+    ldap: Ldap,
+    txn_id: Vec<u8>,
+    controls: Vec<RawControl>,
+    timeout: Option<Duration>,
+    ended: bool,
+}
+
+impl Transaction {
+    pub(crate) async fn begin(mut ldap: Ldap) -> Result<Transaction> {
+        let (exop, _res) = ldap.extended(StartTxn).await?.success()?;
+        let txn_id = match exop.val {
+            Some(ref val) => StartTxnResp::try_parse(val)?.txn_id,
+            None => return Err(LdapError::MalformedTxnResponse("no transaction identifier")),
+        };
+        Ok(Transaction {
+            ldap,
+            txn_id,
+            controls: vec![],
+            timeout: None,
+            ended: false,
+        })
+    }
+
+    /// Return the transaction identifier assigned by the server.
+    pub fn id(&self) -> &[u8] {
+        &self.txn_id
+    }
+
+    /// Return the message ID of the last update in the transaction, which identifies
+    /// it in the [`EndTxnResp`](exop/struct.EndTxnResp.html) returned by `commit()`.
+    pub fn last_id(&mut self) -> RequestId {
+        self.ldap.last_id()
+    }
+
+    /// Pass the provided request control(s) to the next operation, in addition to the
+    /// Transaction Specification control.
+    pub fn with_controls<V: IntoRawControlVec>(&mut self, ctrls: V) -> &mut Self {
+        self.controls = ctrls.into();
+        self
+    }
+
+    /// Perform the next operation with the timeout specified in `duration`.
+    pub fn with_timeout(&mut self, duration: Duration) -> &mut Self {
+        self.timeout = Some(duration);
+        self
+    }
+
+    // Arm the handle for the next update in the transaction.
+    fn arm(&mut self) -> &mut Ldap {
+        let mut controls = std::mem::take(&mut self.controls);
+        controls.push(RawControl::from(TxnSpec {
+            txn_id: self.txn_id.clone(),
+        }));
+        self.ldap.controls = Some(controls);
+        self.ldap.timeout = self.timeout.take();
+        &mut self.ldap
+    }
+
+    /// Queue the addition of an entry, as in [`Ldap::add()`](struct.Ldap.html#method.add).
+    pub async fn add<S: ValueBytes + Eq + Hash>(
+        &mut self,
+        dn: &str,
+        attrs: Vec<(S, HashSet<S>)>,
+    ) -> Result<LdapResult> {
+        self.arm().add(dn, attrs).await
+    }
+
+    /// Queue the modification of an entry, as in [`Ldap::modify()`](struct.Ldap.html#method.modify).
+    pub async fn modify<S: ValueBytes + Eq + Hash>(
+        &mut self,
+        dn: &str,
+        mods: Vec<Mod<S>>,
+    ) -> Result<LdapResult> {
+        self.arm().modify(dn, mods).await
+    }
+
+    /// Queue the deletion of an entry, as in [`Ldap::delete()`](struct.Ldap.html#method.delete).
+    pub async fn delete(&mut self, dn: &str) -> Result<LdapResult> {
+        self.arm().delete(dn).await
+    }
+
+    /// Queue the renaming of an entry, as in [`Ldap::modifydn()`](struct.Ldap.html#method.modifydn).
+    pub async fn modifydn(
+        &mut self,
+        dn: &str,
+        rdn: &str,
+        delete_old: bool,
+        new_sup: Option<&str>,
+    ) -> Result<LdapResult> {
+        self.arm().modifydn(dn, rdn, delete_old, new_sup).await
+    }
+
+    /// Commit the transaction, applying all queued updates, or none of them.
+    ///
+    /// The result of the End Transaction operation tells whether the updates were applied.
+    /// If they weren't, the response may identify the update which caused the failure;
+    /// if they were, it carries the response controls of the updates, if any, such as
+    /// those of the Read Entry controls. A response without a value is returned as an
+    /// empty `EndTxnResp`.
+    pub async fn commit(mut self) -> Result<(LdapResult, EndTxnResp)> {
+        self.end(true).await
+    }
+
+    /// Abort the transaction, discarding all queued updates.
+    pub async fn abort(mut self) -> Result<LdapResult> {
+        self.end(false).await.map(|(res, _)| res)
+    }
+
+    async fn end(&mut self, commit: bool) -> Result<(LdapResult, EndTxnResp)> {
+        self.ended = true;
+        self.ldap.controls = match std::mem::take(&mut self.controls) {
+            ctrls if ctrls.is_empty() => None,
+            ctrls => Some(ctrls),
+        };
+        self.ldap.timeout = self.timeout.take();
+        let end = EndTxn {
+            commit,
+            txn_id: self.txn_id.clone(),
+        };
+        let res = self.ldap.extended(end).await?;
+        let resp = match res.0.val {
+            Some(ref val) => EndTxnResp::try_parse(val)?,
+            None => EndTxnResp::default(),
+        };
+        Ok((res.1, resp))
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if self.ended {
+            return;
+        }
+        let mut ldap = self.ldap.clone();
+        let end = EndTxn {
+            commit: false,
+            txn_id: std::mem::take(&mut self.txn_id),
+        };
+        crate::rt::try_spawn(async move {
+            let _ = ldap.extended(end).await;
+        });
+    }
+}
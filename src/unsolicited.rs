@@ -0,0 +1,181 @@
+use std::result::Result as StdResult;
+use std::sync::{Arc, Mutex, Weak};
+
+use crate::exop::Exop;
+use crate::result::{LdapError, LdapResult};
+
+use tokio::sync::{broadcast, watch};
+
+/// OID of the Notice of Disconnection.
+pub const NOTICE_OF_DISCONNECTION_OID: &str = "1.3.6.1.4.1.1466.20036";
+
+// Number of notifications retained for a receiver which doesn't keep up.
+pub(crate) const NOTIFICATION_QUEUE_SIZE: usize = 16;
+
+/// Unsolicited Notification.
+///
+/// The server can send a notification which isn't a response to any client
+/// request by using an Extended Response with the message ID of zero. The
+/// only notification defined by the core LDAP specification is the
+/// Notice of Disconnection, which informs the client that the server is about
+/// to close the connection. Notifications are received through the channel
+/// returned by [`Ldap::unsolicited_notifications()`](struct.Ldap.html#method.unsolicited_notifications).
+///
+/// After a Notice of Disconnection, operations which are still in progress
+/// fail with [`LdapError::ServerDisconnect`](enum.LdapError.html#variant.ServerDisconnect).
+#[derive(Clone, Debug)]
+pub struct UnsolicitedNotification {
+    /// Result components of the notification, including any response controls.
+    pub result: LdapResult,
+    /// Notification OID and value.
+    pub exop: Exop,
+}
+
+impl UnsolicitedNotification {
+    /// Return `true` if this is a Notice of Disconnection.
+    pub fn is_disconnect(&self) -> bool {
+        self.exop.name.as_deref() == Some(NOTICE_OF_DISCONNECTION_OID)
+    }
+}
+
+// Outcome of driving the connection, once it has terminated.
+type Closed = Option<StdResult<(), String>>;
+
+// Connection state shared between the connection and its handles: the sending side
+// of the notification channel, the reason for a server-initiated disconnect, the
+// failure which poisoned the connection during a TLS upgrade, and the outcome of
+// driving the connection.
+//
+// The connection owns the sender, while the handles keep only a weak reference to it,
+// so that the receivers see the channel as closed when the connection terminates.
+#[derive(Debug)]
+pub(crate) struct Notifier {
+    tx: Arc<broadcast::Sender<UnsolicitedNotification>>,
+    pub(crate) disconnect: Arc<Mutex<Option<(u32, String)>>>,
+    poison: Arc<Mutex<Option<Arc<LdapError>>>>,
+    closed_tx: Option<watch::Sender<Closed>>,
+    closed_rx: watch::Receiver<Closed>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct NotifierHandle {
+    tx: Weak<broadcast::Sender<UnsolicitedNotification>>,
+    disconnect: Arc<Mutex<Option<(u32, String)>>>,
+    poison: Arc<Mutex<Option<Arc<LdapError>>>>,
+    closed: watch::Receiver<Closed>,
+}
+
+impl Notifier {
+    pub(crate) fn new() -> Self {
+        let (tx, _) = broadcast::channel(NOTIFICATION_QUEUE_SIZE);
+        let (closed_tx, closed_rx) = watch::channel(None);
+        Notifier {
+            tx: Arc::new(tx),
+            disconnect: Arc::new(Mutex::new(None)),
+            poison: Arc::new(Mutex::new(None)),
+            closed_tx: Some(closed_tx),
+            closed_rx,
+        }
+    }
+
+    pub(crate) fn handle(&self) -> NotifierHandle {
+        NotifierHandle {
+            tx: Arc::downgrade(&self.tx),
+            disconnect: self.disconnect.clone(),
+            poison: self.poison.clone(),
+            closed: self.closed_rx.clone(),
+        }
+    }
+
+    // Take the sender through which the driver announces its termination. If it's
+    // dropped without sending, the connection is considered closed without error.
+    pub(crate) fn take_closer(&mut self) -> Option<watch::Sender<Closed>> {
+        self.closed_tx.take()
+    }
+
+    // Record the reason for disconnection before publishing the notice, so that any
+    // operation failing afterwards can report it.
+    pub(crate) fn notify(&self, notification: UnsolicitedNotification) {
+        if notification.is_disconnect() {
+            let result = &notification.result;
+            *self.disconnect.lock().expect("disconnect mutex") =
+                Some((result.rc, result.text.clone()));
+        }
+        // An error only means that nobody is listening.
+        let _ = self.tx.send(notification);
+    }
+
+    pub(crate) fn disconnected(&self) -> bool {
+        self.disconnect.lock().expect("disconnect mutex").is_some()
+    }
+
+    pub(crate) fn poisoned(&self) -> Option<LdapError> {
+        self.poison
+            .lock()
+            .expect("poison mutex")
+            .clone()
+            .map(|source| LdapError::TlsHandshakeFailed { source })
+    }
+}
+
+impl NotifierHandle {
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<UnsolicitedNotification> {
+        match self.tx.upgrade() {
+            Some(tx) => tx.subscribe(),
+            None => broadcast::channel(1).1,
+        }
+    }
+
+    // Mark the connection as unusable after a failed TLS upgrade, returning the error
+    // which all operations will report from now on. The first failure is kept.
+    pub(crate) fn poison(&self, e: LdapError) -> LdapError {
+        let source = self
+            .poison
+            .lock()
+            .expect("poison mutex")
+            .get_or_insert_with(|| match e {
+                LdapError::TlsHandshakeFailed { source } => source,
+                e => Arc::new(e),
+            })
+            .clone();
+        LdapError::TlsHandshakeFailed { source }
+    }
+
+    pub(crate) fn poisoned(&self) -> Option<LdapError> {
+        self.poison
+            .lock()
+            .expect("poison mutex")
+            .clone()
+            .map(|source| LdapError::TlsHandshakeFailed { source })
+    }
+
+    // Wait until the connection has terminated, returning the description of the error
+    // which ended it, if any.
+    pub(crate) async fn closed(&self) -> StdResult<(), String> {
+        let mut closed = self.closed.clone();
+        loop {
+            if let Some(ref res) = *closed.borrow() {
+                return res.clone();
+            }
+            if closed.changed().await.is_err() {
+                return closed.borrow().clone().unwrap_or(Ok(()));
+            }
+        }
+    }
+
+    // If the connection was poisoned, or the server has announced the disconnection,
+    // replace the error caused by the connection shutting down with the one describing
+    // the actual cause.
+    pub(crate) fn map_err(&self, e: LdapError) -> LdapError {
+        if let Some(e) = self.poisoned() {
+            return e;
+        }
+        match *self.disconnect.lock().expect("disconnect mutex") {
+            Some((rc, ref text)) => LdapError::ServerDisconnect {
+                rc,
+                text: text.clone(),
+            },
+            None => e,
+        }
+    }
+}
@@ -2,11 +2,11 @@ use std::borrow::Cow;
 use std::collections::HashSet;
 use std::hash::{Hash, Hasher};
 
-use crate::filter::Unescaper;
 use crate::result::{LdapError, Result};
 use crate::search::Scope;
 
 use percent_encoding::percent_decode_str;
+use thiserror::Error;
 use url::Url;
 
 /// Escape a filter literal.
@@ -52,6 +52,19 @@ pub fn ldap_escape<'a, S: Into<Cow<'a, str>>>(lit: S) -> Cow<'a, str> {
     }
 }
 
+/// Style of escaping used by [`dn_escape_with()`](fn.dn_escape_with.html).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum EscapeStyle {
+    /// Escape characters as pairs of hex digits, e.g., `\2c` for a comma. This is
+    /// the style used by [`dn_escape()`](fn.dn_escape.html).
+    #[default]
+    Hex,
+    /// Escape characters by prefixing them with a backslash, e.g., `\,` for a comma,
+    /// as often seen in LDIF produced by directory tools. NUL, which has no such form,
+    /// is still escaped as `\00`.
+    Readable,
+}
+
 /// Escape an attribute value in a relative distinguished name (RDN).
 ///
 /// When a literal string is used to represent an attribute value in an RDN,
@@ -64,6 +77,21 @@ pub fn ldap_escape<'a, S: Into<Cow<'a, str>>>(lit: S) -> Cow<'a, str> {
 /// The argument, `val`, can be owned or borrowed. The function doesn't
 /// allocate the return value unless there's need to escape the input.
 pub fn dn_escape<'a, S: Into<Cow<'a, str>>>(val: S) -> Cow<'a, str> {
+    dn_escape_with(val, EscapeStyle::Hex)
+}
+
+/// Escape an attribute value in an RDN, using the given style of escapes.
+///
+/// The same characters are escaped as by [`dn_escape()`](fn.dn_escape.html), which
+/// is equivalent to calling this function with `EscapeStyle::Hex`.
+///
+/// ```rust
+/// use ldap3::{dn_escape_with, EscapeStyle};
+///
+/// assert_eq!(dn_escape_with("Doe, John", EscapeStyle::Hex), "Doe\\2c John");
+/// assert_eq!(dn_escape_with("Doe, John", EscapeStyle::Readable), "Doe\\, John");
+/// ```
+pub fn dn_escape_with<'a, S: Into<Cow<'a, str>>>(val: S, style: EscapeStyle) -> Cow<'a, str> {
     #[inline]
     fn always_escape(c: u8) -> bool {
         c == b'"'
@@ -105,8 +133,12 @@ pub fn dn_escape<'a, S: Into<Cow<'a, str>>>(val: S) -> Cow<'a, str> {
             }
             let output = output.as_mut().unwrap();
             output.push(b'\\');
-            output.push(xdigit(c >> 4));
-            output.push(xdigit(c & 0xF));
+            if style == EscapeStyle::Readable && c != 0 {
+                output.push(c);
+            } else {
+                output.push(xdigit(c >> 4));
+                output.push(xdigit(c & 0xF));
+            }
         } else if let Some(ref mut output) = output {
             output.push(c);
         }
@@ -118,6 +150,125 @@ pub fn dn_escape<'a, S: Into<Cow<'a, str>>>(val: S) -> Cow<'a, str> {
     }
 }
 
+/// Error in unescaping a filter or DN value.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum EscapeError {
+    /// The value ends with a backslash, or a backslash and a single hex digit.
+    #[error("incomplete escape sequence at offset {0}")]
+    Incomplete(usize),
+    /// A backslash is followed by a character which can't be escaped, or by a hex
+    /// digit and a character which isn't one.
+    #[error("invalid escape sequence at offset {0}")]
+    InvalidEscape(usize),
+    /// A character which must be escaped appears as such.
+    #[error("unescaped character at offset {0}")]
+    Unescaped(usize),
+    /// A DN value in the `#hexstring` form doesn't contain a non-empty sequence of
+    /// hex digit pairs.
+    #[error("invalid hex string")]
+    InvalidHexString,
+}
+
+fn hex_value(c: u8) -> Option<u8> {
+    (c as char).to_digit(16).map(|d| d as u8)
+}
+
+// Decode the escapes in a value, copying it only if there are any. Besides pairs of hex
+// digits, a backslash may be followed by a character for which `special()` is true, and
+// the characters which may appear unescaped are those for which `literal()` is true.
+fn unescape<'a>(
+    val: Cow<'a, str>,
+    special: fn(u8) -> bool,
+    literal: fn(u8) -> bool,
+) -> std::result::Result<Cow<'a, [u8]>, EscapeError> {
+    let bytes = val.as_bytes();
+    let mut output: Option<Vec<u8>> = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c != b'\\' {
+            if !literal(c) {
+                return Err(EscapeError::Unescaped(i));
+            }
+            if let Some(ref mut output) = output {
+                output.push(c);
+            }
+            i += 1;
+            continue;
+        }
+        let output = output.get_or_insert_with(|| {
+            let mut output = Vec::with_capacity(bytes.len());
+            output.extend(&bytes[..i]);
+            output
+        });
+        match (bytes.get(i + 1).copied(), bytes.get(i + 2).copied()) {
+            (Some(h), l) if hex_value(h).is_some() => match l.map(hex_value) {
+                Some(Some(l)) => {
+                    output.push(hex_value(h).expect("hex digit") << 4 | l);
+                    i += 3;
+                }
+                Some(None) => return Err(EscapeError::InvalidEscape(i)),
+                None => return Err(EscapeError::Incomplete(i)),
+            },
+            (Some(c), _) if special(c) => {
+                output.push(c);
+                i += 2;
+            }
+            (Some(_), _) => return Err(EscapeError::InvalidEscape(i)),
+            (None, _) => return Err(EscapeError::Incomplete(i)),
+        }
+    }
+    Ok(match (output, val) {
+        (Some(output), _) => Cow::Owned(output),
+        (None, Cow::Borrowed(val)) => Cow::Borrowed(val.as_bytes()),
+        (None, Cow::Owned(val)) => Cow::Owned(val.into_bytes()),
+    })
+}
+
+/// Unescape an attribute value in an RDN.
+///
+/// This is the inverse of [`dn_escape()`](fn.dn_escape.html) and
+/// [`dn_escape_with()`](fn.dn_escape_with.html), accepting every form of escaping
+/// permitted by [RFC 4514](https://tools.ietf.org/html/rfc4514#section-3): pairs of hex
+/// digits, a backslash followed by one of the characters `` "+,;<=>\#`` or a space, and
+/// a value consisting of `#` and the hex encoding of the value's BER representation,
+/// in which case the BER bytes are returned without further decoding. The characters
+/// `"+,;<>\` and NUL must be escaped; if any appears unescaped, an error is returned.
+///
+/// The result is a byte sequence, since escapes can represent bytes which aren't valid
+/// UTF-8. It borrows from the argument if the value contains no escapes.
+///
+/// ```rust
+/// use ldap3::dn_unescape;
+///
+/// assert_eq!(dn_unescape("Doe\\, John").unwrap(), &b"Doe, John"[..]);
+/// assert_eq!(dn_unescape("Doe\\2c John").unwrap(), &b"Doe, John"[..]);
+/// assert_eq!(dn_unescape("#04024869").unwrap(), &b"\x04\x02Hi"[..]);
+/// assert!(dn_unescape("Doe, John").is_err());
+/// ```
+pub fn dn_unescape<'a, S: Into<Cow<'a, str>>>(
+    val: S,
+) -> std::result::Result<Cow<'a, [u8]>, EscapeError> {
+    let val = val.into();
+    if let Some(hex) = val.strip_prefix('#') {
+        let hex = hex.as_bytes();
+        if hex.is_empty() || hex.len() % 2 != 0 {
+            return Err(EscapeError::InvalidHexString);
+        }
+        return hex
+            .chunks(2)
+            .map(|pair| Some(hex_value(pair[0])? << 4 | hex_value(pair[1])?))
+            .collect::<Option<Vec<_>>>()
+            .map(Cow::Owned)
+            .ok_or(EscapeError::InvalidHexString);
+    }
+    unescape(
+        val,
+        |c| b" \"#+,;<=>\\".contains(&c),
+        |c| !b"\"+,;<>\\\0".contains(&c),
+    )
+}
+
 /// LDAP URL extensions.
 ///
 /// Historically, very few extensions have been described in the LDAP standards,
@@ -232,7 +383,14 @@ fn ascii_lc_equal(s: &str, t: &str) -> bool {
 }
 
 /// Extract parameters from an LDAP URL.
+///
+/// If the scope is missing from the URL, `Scope::Subtree` is returned. This differs from
+/// the RFC 4516 default of `Scope::Base`, which is used by [`LdapUrl`](struct.LdapUrl.html).
 pub fn get_url_params(url: &Url) -> Result<LdapUrlParams<'_>> {
+    url_params(url, Scope::Subtree)
+}
+
+fn url_params(url: &Url, default_scope: Scope) -> Result<LdapUrlParams<'_>> {
     let mut base = url.path();
     if base.chars().next().unwrap_or('\0') == '/' {
         base = &base[1..];
@@ -246,13 +404,8 @@ pub fn get_url_params(url: &Url) -> Result<LdapUrlParams<'_>> {
         Some(alist) => alist.split(',').collect(),
     };
     let scope = match query.next() {
-        Some("") | None => Scope::Subtree,
-        Some(scope_str) => match scope_str {
-            "base" => Scope::Base,
-            "one" => Scope::OneLevel,
-            "sub" => Scope::Subtree,
-            any => return Err(LdapError::InvalidScopeString(any.into())),
-        },
+        Some("") | None => default_scope,
+        Some(scope_str) => scope_str.parse()?,
     };
     let filter = match query.next() {
         Some("") | None => "(objectClass=*)",
@@ -318,53 +471,149 @@ pub fn get_url_params(url: &Url) -> Result<LdapUrlParams<'_>> {
     })
 }
 
-#[deprecated(since = "0.10.4", note = "use ldap_unescape instead")]
-pub fn ldap_str_unescape<'a, S: Into<Cow<'a, str>>>(val: S) -> Result<Cow<'a, str>> {
-    ldap_unescape(val)
+impl<'a> LdapUrlExt<'a> {
+    fn into_owned(self) -> LdapUrlExt<'static> {
+        match self {
+            LdapUrlExt::Bindname(v) => LdapUrlExt::Bindname(Cow::Owned(v.into_owned())),
+            LdapUrlExt::XBindpw(v) => LdapUrlExt::XBindpw(Cow::Owned(v.into_owned())),
+            LdapUrlExt::Credentials(v) => LdapUrlExt::Credentials(Cow::Owned(v.into_owned())),
+            LdapUrlExt::SaslMech(v) => LdapUrlExt::SaslMech(Cow::Owned(v.into_owned())),
+            LdapUrlExt::StartTLS => LdapUrlExt::StartTLS,
+            LdapUrlExt::Unknown(v) => LdapUrlExt::Unknown(Cow::Owned(v.into_owned())),
+        }
+    }
 }
 
-/// Unescape a string using LDAP filter escapes.
+/// Parsed LDAP URL.
 ///
-/// If a string contains `\nn` hexadecimal escapes, return a string where those
-/// escapes are turned back into characters they represent. The result must be
-/// a valid UTF-8 string, otherwise an error is returned.
-pub fn ldap_unescape<'a, S: Into<Cow<'a, str>>>(val: S) -> Result<Cow<'a, str>> {
-    let val = val.into();
-    let mut output = None;
-    let mut esc = Unescaper::Value(0);
-    for (i, &c) in val.as_bytes().iter().enumerate() {
-        esc = esc.feed(c);
-        match esc {
-            Unescaper::WantFirst => {
-                if output.is_none() {
-                    output = Some(Vec::with_capacity(val.len() + 12)); // guess: up to 4 escaped chars
-                    output.as_mut().unwrap().extend(val[..i].as_bytes());
-                }
-            }
-            Unescaper::Value(c) => {
-                if let Some(output) = &mut output {
-                    output.push(c);
-                }
-            }
-            _ => (),
+/// Unlike [`LdapUrlParams`](struct.LdapUrlParams.html), which borrows from an already
+/// parsed `Url`, this structure owns all of its components, and additionally contains
+/// the connection parameters. Missing components are filled in with the defaults
+/// specified by [RFC 4516](https://tools.ietf.org/html/rfc4516): the empty base DN,
+/// all user attributes, base scope, and the `(objectClass=*)` filter.
+///
+/// The Search described by the URL can be performed with
+/// [`Ldap::search_url()`](struct.Ldap.html#method.search_url).
+#[derive(Clone, Debug)]
+pub struct LdapUrl {
+    /// URL scheme: __ldap__, __ldaps__, or __ldapi__.
+    pub scheme: String,
+
+    /// Host name, or the percent-encoded socket path for __ldapi__. Empty if the
+    /// URL doesn't specify a host.
+    pub host: String,
+
+    /// Port, if given in the URL.
+    pub port: Option<u16>,
+
+    /// Search base, percent-decoded.
+    pub base: String,
+
+    /// Attribute list, returned as `*` (all attributes) if missing.
+    pub attrs: Vec<String>,
+
+    /// Search scope, returned as `Scope::Base` if missing.
+    pub scope: Scope,
+
+    /// Filter string, percent-decoded.
+    pub filter: String,
+
+    /// Extensions, whose values are percent-decoded.
+    pub extensions: HashSet<LdapUrlExt<'static>>,
+
+    /// If `true`, a Search described by the URL may be performed on a connection to
+    /// a host different from the one named in the URL. Defaults to `false`.
+    pub allow_foreign_host: bool,
+}
+
+impl LdapUrl {
+    /// Parse the string representation of an LDAP URL.
+    pub fn parse(url: &str) -> Result<LdapUrl> {
+        let url = Url::parse(url)?;
+        match url.scheme() {
+            "ldap" | "ldaps" | "ldapi" => (),
+            s => return Err(LdapError::UnknownScheme(String::from(s))),
         }
+        let params = url_params(&url, Scope::Base)?;
+        Ok(LdapUrl {
+            scheme: String::from(url.scheme()),
+            host: String::from(url.host_str().unwrap_or("")),
+            port: url.port(),
+            base: params.base.into_owned(),
+            attrs: params.attrs.into_iter().map(String::from).collect(),
+            scope: params.scope,
+            filter: params.filter.into_owned(),
+            extensions: params
+                .extensions
+                .into_iter()
+                .map(LdapUrlExt::into_owned)
+                .collect(),
+            allow_foreign_host: false,
+        })
     }
-    if output.is_some() {
-        if let Unescaper::Value(_) = esc {
-            Ok(Cow::Owned(
-                String::from_utf8(output.unwrap()).map_err(|_| LdapError::DecodingUTF8)?,
-            ))
-        } else {
-            Err(LdapError::DecodingUTF8)
+
+    /// Set the flag which permits the Search described by the URL to be
+    /// performed on a connection to a different host.
+    pub fn allow_foreign_host(mut self, allow: bool) -> Self {
+        self.allow_foreign_host = allow;
+        self
+    }
+
+    /// Return the port given in the URL, or the default port for the scheme.
+    pub fn port_or_default(&self) -> u16 {
+        match self.port {
+            Some(port) => port,
+            None if self.scheme == "ldaps" => 636,
+            None => 389,
         }
-    } else {
-        Ok(val)
     }
 }
 
+#[deprecated(since = "0.10.4", note = "use ldap_unescape instead")]
+pub fn ldap_str_unescape<'a, S: Into<Cow<'a, str>>>(val: S) -> Result<Cow<'a, str>> {
+    match ldap_unescape(val).map_err(|_| LdapError::DecodingUTF8)? {
+        Cow::Borrowed(val) => Ok(Cow::Borrowed(
+            std::str::from_utf8(val).expect("unescaped str"),
+        )),
+        Cow::Owned(val) => String::from_utf8(val)
+            .map(Cow::Owned)
+            .map_err(|_| LdapError::DecodingUTF8),
+    }
+}
+
+/// Unescape a string using LDAP filter escapes.
+///
+/// This is the inverse of [`ldap_escape()`](fn.ldap_escape.html). Every `\nn`
+/// hexadecimal escape is turned back into the byte it represents. A backslash which
+/// isn't followed by two hex digits is an error, as is a NUL, parenthesis, or
+/// asterisk appearing unescaped, as described in
+/// [RFC 4515](https://tools.ietf.org/html/rfc4515#section-3).
+///
+/// The result is a byte sequence, since escapes can represent bytes which aren't valid
+/// UTF-8. It borrows from the argument if the value contains no escapes.
+///
+/// ```rust
+/// use ldap3::ldap_unescape;
+///
+/// assert_eq!(ldap_unescape("a\\2ab").unwrap(), &b"a*b"[..]);
+/// assert_eq!(ldap_unescape("\\ff").unwrap(), &b"\xff"[..]);
+/// assert!(ldap_unescape("a\\2").is_err());
+/// ```
+pub fn ldap_unescape<'a, S: Into<Cow<'a, str>>>(
+    val: S,
+) -> std::result::Result<Cow<'a, [u8]>, EscapeError> {
+    unescape(val.into(), |_| false, |c| !b"()*\\\0".contains(&c))
+}
+
 #[cfg(test)]
 mod test {
-    use super::dn_escape;
+    use std::borrow::Cow;
+
+    use super::{
+        dn_escape, dn_escape_with, dn_unescape, ldap_escape, ldap_unescape, EscapeError,
+        EscapeStyle, LdapUrl, LdapUrlExt,
+    };
+    use crate::search::Scope;
 
     #[test]
     fn dn_esc_leading_space() {
@@ -400,4 +649,163 @@ mod test {
     fn dn_esc_leading_hash() {
         assert_eq!(dn_escape("#rust"), "\\23rust");
     }
+
+    #[test]
+    fn dn_esc_readable() {
+        assert_eq!(
+            dn_escape_with(" #a,b=c+\"d\"; ", EscapeStyle::Readable),
+            r#"\ #a\,b\=c\+\"d\"\;\ "#
+        );
+        assert_eq!(dn_escape_with("#a\0", EscapeStyle::Readable), "\\#a\\00");
+        assert!(matches!(
+            dn_escape_with("plain", EscapeStyle::Readable),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn unescape_forms() {
+        assert_eq!(ldap_unescape("\\28x\\29\\2A").unwrap(), &b"(x)*"[..]);
+        assert_eq!(dn_unescape("\\ a\\2C\\,b\\ ").unwrap(), &b" a,,b "[..]);
+        assert_eq!(dn_unescape("a=b\\c3\\a9").unwrap(), "a=bé".as_bytes());
+        assert_eq!(dn_unescape("#0403414243").unwrap(), &b"\x04\x03ABC"[..]);
+        assert!(matches!(
+            ldap_unescape("plain"),
+            Ok(Cow::Borrowed(b"plain"))
+        ));
+        assert!(matches!(
+            dn_unescape(String::from("plain")),
+            Ok(Cow::Owned(ref v)) if v == b"plain"
+        ));
+    }
+
+    #[test]
+    fn unescape_errors() {
+        assert_eq!(ldap_unescape("ab\\"), Err(EscapeError::Incomplete(2)));
+        assert_eq!(ldap_unescape("\\4"), Err(EscapeError::Incomplete(0)));
+        assert_eq!(ldap_unescape("x\\4g"), Err(EscapeError::InvalidEscape(1)));
+        assert_eq!(ldap_unescape("\\,"), Err(EscapeError::InvalidEscape(0)));
+        assert_eq!(ldap_unescape("a*b"), Err(EscapeError::Unescaped(1)));
+        assert_eq!(ldap_unescape("(a"), Err(EscapeError::Unescaped(0)));
+        assert_eq!(dn_unescape("a\\"), Err(EscapeError::Incomplete(1)));
+        assert_eq!(dn_unescape("\\x"), Err(EscapeError::InvalidEscape(0)));
+        assert_eq!(dn_unescape("a,b"), Err(EscapeError::Unescaped(1)));
+        assert_eq!(dn_unescape("a\0"), Err(EscapeError::Unescaped(1)));
+        for hex in ["#", "#0", "#0g", "#04 02"] {
+            assert_eq!(
+                dn_unescape(hex),
+                Err(EscapeError::InvalidHexString),
+                "{}",
+                hex
+            );
+        }
+    }
+
+    // Deterministic xorshift generator, so that failures can be reproduced.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 as usize
+        }
+
+        fn string(&mut self, alphabet: &[char]) -> String {
+            let len = self.next() % 12;
+            (0..len)
+                .map(|_| alphabet[self.next() % alphabet.len()])
+                .collect()
+        }
+    }
+
+    #[test]
+    fn escape_round_trip() {
+        let alphabet: Vec<char> = " #\\\"+,;<=>()*\0aZ09é€😀\t".chars().collect();
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..5000 {
+            let val = rng.string(&alphabet);
+            let unescaped = ldap_unescape(ldap_escape(val.as_str()));
+            assert_eq!(unescaped.as_deref(), Ok(val.as_bytes()), "{:?}", val);
+            for style in [EscapeStyle::Hex, EscapeStyle::Readable] {
+                let escaped = dn_escape_with(val.as_str(), style);
+                let unescaped = dn_unescape(escaped.as_ref());
+                assert_eq!(unescaped.as_deref(), Ok(val.as_bytes()), "{:?}", escaped);
+            }
+        }
+    }
+
+    #[test]
+    fn unescape_random_input() {
+        let alphabet: Vec<char> = "\\\\\\0fFg #,=(*é".chars().collect();
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..5000 {
+            let val = rng.string(&alphabet);
+            // Whatever is successfully unescaped is a fixed point of escaping and
+            // unescaping, in both the filter and the DN form.
+            if let Ok(bytes) = ldap_unescape(val.as_str()) {
+                if let Ok(text) = std::str::from_utf8(&bytes) {
+                    assert_eq!(
+                        ldap_unescape(ldap_escape(text)).as_deref(),
+                        Ok(text.as_bytes())
+                    );
+                }
+            }
+            if let Ok(bytes) = dn_unescape(val.as_str()) {
+                if let Ok(text) = std::str::from_utf8(&bytes) {
+                    assert_eq!(dn_unescape(dn_escape(text)).as_deref(), Ok(text.as_bytes()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn url_full() {
+        let url = LdapUrl::parse(
+            "ldap://host.example.org:1389/ou=x,dc=y?cn,mail?sub?(objectClass=person)?!1.3.6.1.4.1.1466.20037",
+        )
+        .expect("url");
+        assert_eq!(url.scheme, "ldap");
+        assert_eq!(url.host, "host.example.org");
+        assert_eq!(url.port, Some(1389));
+        assert_eq!(url.base, "ou=x,dc=y");
+        assert_eq!(url.attrs, vec!["cn", "mail"]);
+        assert_eq!(url.scope, Scope::Subtree);
+        assert_eq!(url.filter, "(objectClass=person)");
+        assert!(url.extensions.contains(&LdapUrlExt::StartTLS));
+    }
+
+    #[test]
+    fn url_empty_components() {
+        let url = LdapUrl::parse("ldap:///??sub").expect("url");
+        assert_eq!(url.host, "");
+        assert_eq!(url.port_or_default(), 389);
+        assert_eq!(url.base, "");
+        assert_eq!(url.attrs, vec!["*"]);
+        assert_eq!(url.scope, Scope::Subtree);
+        assert_eq!(url.filter, "(objectClass=*)");
+    }
+
+    #[test]
+    fn url_default_scope_is_base() {
+        let url = LdapUrl::parse("ldaps://localhost/dc=example,dc=org").expect("url");
+        assert_eq!(url.scope, Scope::Base);
+        assert_eq!(url.port_or_default(), 636);
+    }
+
+    #[test]
+    fn url_percent_decoding() {
+        let url =
+            LdapUrl::parse("ldap:///o=University%20of%20Michigan,c=US??one?(cn=Babs%20Jensen)")
+                .expect("url");
+        assert_eq!(url.base, "o=University of Michigan,c=US");
+        assert_eq!(url.scope, Scope::OneLevel);
+        assert_eq!(url.filter, "(cn=Babs Jensen)");
+    }
+
+    #[test]
+    fn url_unknown_scheme() {
+        assert!(LdapUrl::parse("http://localhost/").is_err());
+    }
 }
@@ -0,0 +1,252 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+
+use ldap3::asn1::{ASNTag, Enumerated, Integer, OctetString, Sequence, Tag, TagClass};
+use ldap3::{Ldap, LdapConnAsync, LdapError, ValueSource};
+
+use bytes::BytesMut;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+
+// Allocator which tracks the peak of allocated memory.
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let now = ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(now, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const VALUE_LEN: u64 = 64 * 1024 * 1024;
+
+fn pattern(i: u64) -> u8 {
+    (i % 251) as u8
+}
+
+// Reader producing the test pattern in small chunks, yielding between them.
+struct ThrottledReader {
+    pos: u64,
+    len: u64,
+    ready: bool,
+}
+
+impl ThrottledReader {
+    fn new(len: u64) -> Self {
+        ThrottledReader {
+            pos: 0,
+            len,
+            ready: false,
+        }
+    }
+}
+
+impl AsyncRead for ThrottledReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        if !self.ready {
+            self.ready = true;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        self.ready = false;
+        let n = (self.len - self.pos).min(8192).min(buf.remaining() as u64);
+        for i in 0..n {
+            buf.put_slice(&[pattern(self.pos + i)]);
+        }
+        self.pos += n;
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn add_response(msgid: i64) -> Vec<u8> {
+    let msg = Tag::Sequence(Sequence {
+        inner: vec![
+            Tag::Integer(Integer {
+                inner: msgid,
+                ..Default::default()
+            }),
+            Tag::Sequence(Sequence {
+                id: 9,
+                class: TagClass::Application,
+                inner: vec![
+                    Tag::Enumerated(Enumerated {
+                        inner: 0,
+                        ..Default::default()
+                    }),
+                    Tag::OctetString(OctetString::default()),
+                    Tag::OctetString(OctetString::default()),
+                ],
+            }),
+        ],
+        ..Default::default()
+    });
+    let mut buf = BytesMut::new();
+    ldap3::asn1::write::encode_into(&mut buf, msg.into_structure()).expect("encoded");
+    buf.to_vec()
+}
+
+// Read one request without buffering it, checking that it ends with the streamed
+// value, and answer with a successful AddResponse.
+async fn serve_add(mut stream: TcpStream) {
+    let mut head = vec![];
+    let mut chunk = vec![0u8; 64 * 1024];
+    let mut total = None;
+    let mut seen = 0u64;
+    loop {
+        let n = stream.read(&mut chunk).await.expect("read");
+        if n == 0 {
+            return;
+        }
+        for &b in &chunk[..n] {
+            if head.len() < 16 {
+                head.push(b);
+            }
+            if total.is_none() && head.len() == 16 {
+                assert_eq!(head[0], 0x30);
+                let (hlen, len) = if head[1] < 0x80 {
+                    (2, head[1] as u64)
+                } else {
+                    let nlen = (head[1] & 0x7f) as usize;
+                    let len = head[2..2 + nlen]
+                        .iter()
+                        .fold(0u64, |acc, &b| (acc << 8) | b as u64);
+                    (2 + nlen as u64, len)
+                };
+                total = Some(hlen + len);
+            }
+            if let Some(total) = total {
+                if seen >= total - VALUE_LEN {
+                    assert_eq!(b, pattern(seen + VALUE_LEN - total), "offset {}", seen);
+                }
+            }
+            seen += 1;
+        }
+        if Some(seen) == total {
+            break;
+        }
+    }
+    // Message id immediately follows the outer header.
+    let hlen = if head[1] < 0x80 {
+        2
+    } else {
+        2 + (head[1] & 0x7f) as usize
+    };
+    assert_eq!(head[hlen], 0x02);
+    let msgid = head[hlen + 2..hlen + 2 + head[hlen + 1] as usize]
+        .iter()
+        .fold(0i64, |acc, &b| (acc << 8) | b as i64);
+    stream.write_all(&add_response(msgid)).await.expect("write");
+}
+
+async fn setup() -> (Ldap, TcpListener) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("listener");
+    let port = listener.local_addr().expect("local addr").port();
+    let (conn, ldap) = LdapConnAsync::new(&format!("ldap://127.0.0.1:{}", port))
+        .await
+        .expect("connection");
+    ldap3::drive!(conn);
+    (ldap, listener)
+}
+
+#[tokio::test]
+async fn streamed_value_memory() {
+    let (mut ldap, listener) = setup().await;
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.expect("accept");
+        serve_add(stream).await;
+    });
+    let baseline = ALLOCATED.load(Ordering::SeqCst);
+    PEAK.store(baseline, Ordering::SeqCst);
+    let res = ldap
+        .add_streaming(
+            "cn=firmware,dc=example,dc=org",
+            vec![
+                ("objectClass", ValueSource::InMemory(b"top".to_vec())),
+                ("objectClass", ValueSource::InMemory(b"device".to_vec())),
+                ("cn", ValueSource::InMemory(b"firmware".to_vec())),
+                (
+                    "firmwareBlob",
+                    ValueSource::reader(VALUE_LEN, ThrottledReader::new(VALUE_LEN)),
+                ),
+            ],
+        )
+        .await
+        .expect("add")
+        .success()
+        .expect("success");
+    let peak = PEAK.load(Ordering::SeqCst) - baseline;
+    server.await.expect("server");
+    assert_eq!(res.rc, 0);
+    assert!(peak < 4 * 1024 * 1024, "peak allocation {} bytes", peak);
+}
+
+#[tokio::test]
+async fn short_reader_closes_connection() {
+    let (mut ldap, listener) = setup().await;
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.expect("accept");
+        let mut buf = vec![0u8; 1024];
+        while stream.read(&mut buf).await.map(|n| n > 0).unwrap_or(false) {}
+    });
+    let res = ldap
+        .add_streaming(
+            "cn=short",
+            vec![("blob", ValueSource::reader(100, ThrottledReader::new(50)))],
+        )
+        .await;
+    assert!(matches!(
+        res,
+        Err(LdapError::StreamedValueLength {
+            declared: 100,
+            received: 50
+        })
+    ));
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    assert!(ldap.is_closed());
+}
+
+#[tokio::test]
+async fn long_reader_closes_connection() {
+    let (mut ldap, listener) = setup().await;
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.expect("accept");
+        let mut buf = vec![0u8; 1024];
+        while stream.read(&mut buf).await.map(|n| n > 0).unwrap_or(false) {}
+    });
+    let res = ldap
+        .add_streaming(
+            "cn=long",
+            vec![("blob", ValueSource::reader(100, ThrottledReader::new(101)))],
+        )
+        .await;
+    assert!(matches!(
+        res,
+        Err(LdapError::StreamedValueLength {
+            declared: 100,
+            received: 101
+        })
+    ));
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    assert!(ldap.is_closed());
+}
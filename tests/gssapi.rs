@@ -0,0 +1,31 @@
+// GSSAPI bind against a live directory, which needs a KDC and a Kerberos ticket
+// for the current user. The test runs only if LDAP3_GSSAPI_URL is set to the URL
+// of the server, whose host name must match its SPN.
+
+use ldap3::LdapConnAsync;
+use url::Url;
+
+#[tokio::test]
+async fn gssapi_bind() {
+    let url = match std::env::var("LDAP3_GSSAPI_URL") {
+        Ok(url) => url,
+        Err(_) => return,
+    };
+    let fqdn = Url::parse(&url)
+        .expect("url")
+        .host_str()
+        .expect("host")
+        .to_owned();
+    let (conn, mut ldap) = LdapConnAsync::new(&url).await.expect("connection");
+    ldap3::drive!(conn);
+    let res = ldap.sasl_gssapi_bind(&fqdn).await.expect("bind");
+    assert_eq!(res.rc, 0, "{:?}", res);
+    let (exop, _) = ldap
+        .extended(ldap3::exop::WhoAmI)
+        .await
+        .expect("whoami")
+        .success()
+        .expect("whoami result");
+    assert!(exop.val.is_some_and(|authzid| !authzid.is_empty()));
+    ldap.unbind().await.expect("unbind");
+}
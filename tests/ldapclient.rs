@@ -0,0 +1,425 @@
+// Smoke test for the ldapclient example, running each of its commands
+// against a scripted server.
+
+#[allow(dead_code)]
+#[path = "../examples/ldapclient.rs"]
+mod ldapclient;
+
+use std::sync::{Arc, Mutex};
+
+use ldap3::asn1::{
+    parse_tag, ASNTag, Enumerated, Integer, OctetString, Sequence, Set, StructureTag, Tag,
+    TagClass, PL,
+};
+
+use bytes::BytesMut;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+type Log = Arc<Mutex<Vec<String>>>;
+
+fn octet_string(s: &[u8]) -> Tag {
+    Tag::OctetString(OctetString {
+        inner: s.to_vec(),
+        ..Default::default()
+    })
+}
+
+fn encode(tag: Tag) -> Vec<u8> {
+    let mut buf = BytesMut::new();
+    ldap3::asn1::write::encode_into(&mut buf, tag.into_structure()).expect("encoded");
+    buf.to_vec()
+}
+
+fn message(msgid: i64, op: Tag, ctrls: Option<Tag>) -> Vec<u8> {
+    let mut inner = vec![
+        Tag::Integer(Integer {
+            inner: msgid,
+            ..Default::default()
+        }),
+        op,
+    ];
+    inner.extend(ctrls);
+    encode(Tag::Sequence(Sequence {
+        inner,
+        ..Default::default()
+    }))
+}
+
+fn result_op(id: u64, extra: Vec<Tag>) -> Tag {
+    let mut inner = vec![
+        Tag::Enumerated(Enumerated {
+            inner: 0,
+            ..Default::default()
+        }),
+        octet_string(b""),
+        octet_string(b""),
+    ];
+    inner.extend(extra);
+    Tag::Sequence(Sequence {
+        id,
+        class: TagClass::Application,
+        inner,
+    })
+}
+
+fn entry_op(dn: &str, attrs: &[(&str, &[&[u8]])]) -> Tag {
+    Tag::Sequence(Sequence {
+        id: 4,
+        class: TagClass::Application,
+        inner: vec![
+            octet_string(dn.as_bytes()),
+            Tag::Sequence(Sequence {
+                inner: attrs
+                    .iter()
+                    .map(|(name, vals)| {
+                        Tag::Sequence(Sequence {
+                            inner: vec![
+                                octet_string(name.as_bytes()),
+                                Tag::Set(Set {
+                                    inner: vals.iter().map(|v| octet_string(v)).collect(),
+                                    ..Default::default()
+                                }),
+                            ],
+                            ..Default::default()
+                        })
+                    })
+                    .collect(),
+                ..Default::default()
+            }),
+        ],
+    })
+}
+
+fn paged_control(cookie: &[u8]) -> Tag {
+    let value = encode(Tag::Sequence(Sequence {
+        inner: vec![
+            Tag::Integer(Integer {
+                inner: 0,
+                ..Default::default()
+            }),
+            octet_string(cookie),
+        ],
+        ..Default::default()
+    }));
+    Tag::Sequence(Sequence {
+        id: 0,
+        class: TagClass::Context,
+        inner: vec![Tag::Sequence(Sequence {
+            inner: vec![
+                octet_string(b"1.2.840.113556.1.4.319"),
+                octet_string(&value),
+            ],
+            ..Default::default()
+        })],
+    })
+}
+
+fn children(tag: &StructureTag) -> &[StructureTag] {
+    match tag.payload {
+        PL::C(ref tags) => tags,
+        PL::P(_) => panic!("primitive tag"),
+    }
+}
+
+fn bytes(tag: &StructureTag) -> &[u8] {
+    match tag.payload {
+        PL::P(ref bytes) => bytes,
+        PL::C(_) => panic!("constructed tag"),
+    }
+}
+
+fn string(tag: &StructureTag) -> String {
+    String::from_utf8(bytes(tag).to_vec()).expect("string")
+}
+
+fn response(msgid: i64, op: &StructureTag, searches: &mut usize, log: &Log) -> Vec<u8> {
+    let parts = children(op);
+    let mut log = log.lock().unwrap();
+    match op.id {
+        0 => {
+            log.push(format!("bind {}", string(&parts[1])));
+            message(msgid, result_op(1, vec![]), None)
+        }
+        3 => {
+            let base = string(&parts[0]);
+            if base.is_empty() {
+                let dse = entry_op(
+                    "",
+                    &[
+                        ("namingContexts", &[b"dc=example,dc=org"]),
+                        ("supportedLDAPVersion", &[b"3"]),
+                    ],
+                );
+                let mut resp = message(msgid, dse, None);
+                resp.extend(message(msgid, result_op(5, vec![]), None));
+                return resp;
+            }
+            log.push(format!("search {}", base));
+            *searches += 1;
+            let (entry, cookie): (Tag, &[u8]) = if *searches == 1 {
+                let entry = entry_op(
+                    "cn=one,dc=example,dc=org",
+                    &[("cn", &[b"one"]), ("jpegPhoto", &[b"abc"])],
+                );
+                (entry, b"next")
+            } else {
+                let entry = entry_op(
+                    "cn=two,dc=example,dc=org",
+                    &[("cn", &[b"two"]), ("description", &[b" leading space"])],
+                );
+                (entry, b"")
+            };
+            let mut resp = message(msgid, entry, None);
+            resp.extend(message(
+                msgid,
+                result_op(5, vec![]),
+                Some(paged_control(cookie)),
+            ));
+            resp
+        }
+        6 => {
+            let changes = children(&parts[1])
+                .iter()
+                .map(|change| {
+                    let change = children(change);
+                    let op = bytes(&change[0])[0];
+                    let attr = children(&change[1]);
+                    format!("{}:{}", op, string(&attr[0]))
+                })
+                .collect::<Vec<_>>();
+            log.push(format!(
+                "modify {} {}",
+                string(&parts[0]),
+                changes.join(",")
+            ));
+            message(msgid, result_op(7, vec![]), None)
+        }
+        8 => {
+            let mut attrs = children(&parts[1])
+                .iter()
+                .map(|attr| {
+                    let attr = children(attr);
+                    format!("{}={}", string(&attr[0]), children(&attr[1]).len())
+                })
+                .collect::<Vec<_>>();
+            attrs.sort();
+            log.push(format!("add {} {}", string(&parts[0]), attrs.join(",")));
+            message(msgid, result_op(9, vec![]), None)
+        }
+        12 => {
+            let sup = parts.get(3).map(string).unwrap_or_default();
+            log.push(format!(
+                "moddn {} {} {}",
+                string(&parts[0]),
+                string(&parts[1]),
+                sup
+            ));
+            message(msgid, result_op(13, vec![]), None)
+        }
+        23 => {
+            let oid = string(&parts[0]);
+            log.push(format!("exop {}", oid));
+            let value = if oid == "1.3.6.1.4.1.4203.1.11.3" {
+                Some(b"dn:cn=admin,dc=example,dc=org".to_vec())
+            } else {
+                let (_, req) = parse_tag(bytes(&parts[1])).expect("passmod value");
+                if children(&req).iter().any(|t| t.id == 2) {
+                    None
+                } else {
+                    Some(encode(Tag::Sequence(Sequence {
+                        inner: vec![Tag::OctetString(OctetString {
+                            id: 0,
+                            class: TagClass::Context,
+                            inner: b"s3cr3t".to_vec(),
+                        })],
+                        ..Default::default()
+                    })))
+                }
+            };
+            let extra = value
+                .map(|inner| {
+                    Tag::OctetString(OctetString {
+                        id: 11,
+                        class: TagClass::Context,
+                        inner,
+                    })
+                })
+                .into_iter()
+                .collect();
+            message(msgid, result_op(24, extra), None)
+        }
+        id => panic!("unexpected operation: {}", id),
+    }
+}
+
+async fn serve(mut stream: TcpStream, log: Log) {
+    let mut buf = vec![];
+    let mut chunk = [0u8; 4096];
+    let mut searches = 0;
+    loop {
+        let (consumed, msg) = match parse_tag(&buf) {
+            Ok((rest, msg)) => (buf.len() - rest.len(), msg),
+            Err(_) => {
+                let n = stream.read(&mut chunk).await.expect("read");
+                if n == 0 {
+                    return;
+                }
+                buf.extend_from_slice(&chunk[..n]);
+                continue;
+            }
+        };
+        buf.drain(..consumed);
+        let parts = children(&msg);
+        let msgid = bytes(&parts[0])
+            .iter()
+            .fold(0i64, |acc, &b| (acc << 8) | b as i64);
+        if parts[1].id == 2 {
+            return;
+        }
+        let resp = response(msgid, &parts[1], &mut searches, &log);
+        stream.write_all(&resp).await.expect("write");
+    }
+}
+
+async fn run(args: &[&str]) -> (Result<String, String>, Vec<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("listener");
+    let url = format!("ldap://127.0.0.1:{}", listener.local_addr().unwrap().port());
+    let log = Log::default();
+    let server_log = log.clone();
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.expect("accept");
+        serve(stream, server_log).await;
+    });
+    let mut full_args = vec![String::from("-H"), url];
+    full_args.extend(args.iter().map(|s| s.to_string()));
+    let mut out = vec![];
+    let res = ldapclient::run(&full_args, &mut out)
+        .await
+        .map(|_| String::from_utf8(out).expect("output"))
+        .map_err(|e| e.to_string());
+    server.await.expect("server");
+    let log = log.lock().unwrap().clone();
+    (res, log)
+}
+
+fn ldif_file(name: &str, contents: &str) -> String {
+    let path =
+        std::env::temp_dir().join(format!("ldapclient-{}-{}.ldif", name, std::process::id()));
+    std::fs::write(&path, contents).expect("ldif file");
+    path.to_str().expect("path").to_owned()
+}
+
+#[tokio::test]
+async fn search() {
+    let (res, log) = run(&["search", "-z", "1", "dc=example,dc=org", "(cn=*)"]).await;
+    assert_eq!(
+        res.unwrap(),
+        "dn: cn=one,dc=example,dc=org\n\
+         cn: one\n\
+         jpegPhoto:: YWJj\n\
+         \n\
+         dn: cn=two,dc=example,dc=org\n\
+         cn: two\n\
+         description:: IGxlYWRpbmcgc3BhY2U=\n"
+    );
+    assert_eq!(
+        log,
+        ["search dc=example,dc=org", "search dc=example,dc=org"]
+    );
+}
+
+#[tokio::test]
+async fn add() {
+    let file = ldif_file(
+        "add",
+        "version: 1\n\
+         # new entry\n\
+         dn: cn=new,dc=exa\n mple,dc=org\n\
+         objectClass: top\n\
+         objectClass: person\n\
+         cn: new\n\
+         sn:: bmV3\n",
+    );
+    let (res, log) = run(&["add", &file]).await;
+    std::fs::remove_file(&file).expect("remove");
+    assert_eq!(res.unwrap(), "added: cn=new,dc=example,dc=org\n");
+    assert_eq!(
+        log,
+        ["add cn=new,dc=example,dc=org cn=1,objectClass=2,sn=1"]
+    );
+}
+
+#[tokio::test]
+async fn modify() {
+    let file = ldif_file(
+        "modify",
+        "dn: cn=one,dc=example,dc=org\n\
+         changetype: modify\n\
+         replace: mail\n\
+         mail: one@example.org\n\
+         -\n\
+         delete: description\n\
+         -\n",
+    );
+    let (res, log) = run(&["modify", &file]).await;
+    std::fs::remove_file(&file).expect("remove");
+    assert_eq!(res.unwrap(), "modified: cn=one,dc=example,dc=org\n");
+    assert_eq!(
+        log,
+        ["modify cn=one,dc=example,dc=org 2:mail,1:description"]
+    );
+}
+
+#[tokio::test]
+async fn passwd() {
+    let (res, _) = run(&["passwd", "cn=one,dc=example,dc=org"]).await;
+    assert_eq!(res.unwrap(), "new password: s3cr3t\n");
+    let (res, log) = run(&["passwd", "cn=one,dc=example,dc=org", "pw"]).await;
+    assert_eq!(res.unwrap(), "password changed: cn=one,dc=example,dc=org\n");
+    assert_eq!(log, ["exop 1.3.6.1.4.1.4203.1.11.1"]);
+}
+
+#[tokio::test]
+async fn whoami() {
+    let (res, log) = run(&["-D", "cn=admin,dc=example,dc=org", "-w", "secret", "whoami"]).await;
+    assert_eq!(res.unwrap(), "dn:cn=admin,dc=example,dc=org\n");
+    assert_eq!(
+        log,
+        [
+            "bind cn=admin,dc=example,dc=org",
+            "exop 1.3.6.1.4.1.4203.1.11.3"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn move_entry() {
+    let (res, log) = run(&[
+        "move",
+        "cn=one,dc=example,dc=org",
+        "cn=uno",
+        "ou=people,dc=example,dc=org",
+    ])
+    .await;
+    assert_eq!(res.unwrap(), "moved: cn=one,dc=example,dc=org\n");
+    assert_eq!(
+        log,
+        ["moddn cn=one,dc=example,dc=org cn=uno ou=people,dc=example,dc=org"]
+    );
+}
+
+#[tokio::test]
+async fn health() {
+    let (res, _) = run(&["health"]).await;
+    assert_eq!(
+        res.unwrap(),
+        "LDAP versions: 3\nnaming context: dc=example,dc=org\n"
+    );
+}
+
+#[tokio::test]
+async fn unknown_command() {
+    let (res, _) = run(&["frobnicate"]).await;
+    assert_eq!(res.unwrap_err(), "unknown command: frobnicate");
+}
@@ -0,0 +1,205 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use ldap3::adapters::{Adapter, Concat, Limit};
+use ldap3::testkit::{delay, done, MockServer, Operation};
+use ldap3::{Scope, SearchEntry, StreamState};
+
+use crate::{connect, locality, requests_after_unbind};
+
+fn places_server() -> MockServer {
+    MockServer::new().on_search(|_| {
+        let mut responses: Vec<_> = (0..3).map(locality).collect();
+        responses.push(delay(Duration::from_millis(100)));
+        responses.extend((3..6).map(locality));
+        responses.push(done(0));
+        responses
+    })
+}
+
+fn places(entries: Vec<ldap3::ResultEntry>) -> Vec<String> {
+    entries
+        .into_iter()
+        .map(|re| SearchEntry::construct(re).attrs["l"][0].clone())
+        .collect()
+}
+
+#[tokio::test]
+async fn search_limit() {
+    let server = places_server().start().await.expect("server");
+    let mut ldap = connect(&server).await;
+    let mut stream = ldap
+        .streaming_search_with(
+            Limit::new(2),
+            "ou=Places,dc=example,dc=org",
+            Scope::Subtree,
+            "(objectClass=locality)",
+            vec!["l"],
+        )
+        .await
+        .expect("stream");
+    let mut entries = vec![];
+    while let Some(re) = stream.next().await.expect("entry") {
+        assert_eq!(stream.state(), StreamState::Active);
+        entries.push(re);
+    }
+    assert_eq!(places(entries), ["Place 0", "Place 1"]);
+    assert_eq!(stream.state(), StreamState::Done);
+    assert!(stream.next().await.expect("after end").is_none());
+    stream.finish().await.success().expect("success");
+    ldap.close().await.expect("close");
+    let ops: Vec<_> = requests_after_unbind(&server)
+        .await
+        .iter()
+        .map(|r| r.operation)
+        .collect();
+    assert_eq!(
+        ops,
+        [Operation::Search, Operation::Abandon, Operation::Unbind]
+    );
+
+    // A search which ends before the limit isn't abandoned.
+    let server = places_server().start().await.expect("server");
+    let mut ldap = connect(&server).await;
+    let mut stream = ldap
+        .streaming_search_with(
+            Limit::new(10),
+            "ou=Places,dc=example,dc=org",
+            Scope::Subtree,
+            "(objectClass=locality)",
+            vec!["l"],
+        )
+        .await
+        .expect("stream");
+    let mut count = 0;
+    while stream.next().await.expect("entry").is_some() {
+        count += 1;
+    }
+    assert_eq!(count, 6);
+    stream.finish().await.success().expect("success");
+    ldap.close().await.expect("close");
+    let ops: Vec<_> = requests_after_unbind(&server)
+        .await
+        .iter()
+        .map(|r| r.operation)
+        .collect();
+    assert_eq!(ops, [Operation::Search, Operation::Unbind]);
+}
+
+fn summary(n: usize) -> ldap3::ResultEntry {
+    let l = format!("Summary {}", n);
+    SearchEntry {
+        dn: format!("l={},ou=Places,dc=example,dc=org", l),
+        attrs: [(String::from("l"), vec![l])].into_iter().collect(),
+        bin_attrs: Default::default(),
+        truncated: vec![],
+    }
+    .into_result_entry()
+}
+
+#[tokio::test]
+async fn search_concat() {
+    let server = places_server().start().await.expect("server");
+    let mut ldap = connect(&server).await;
+    let calls = AtomicUsize::new(0);
+    let mut stream = ldap
+        .streaming_search_with(
+            Concat::new(|| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                vec![summary(0), summary(1)]
+            }),
+            "ou=Places,dc=example,dc=org",
+            Scope::Subtree,
+            "(objectClass=locality)",
+            vec!["l"],
+        )
+        .await
+        .expect("stream");
+    let mut entries = vec![];
+    while let Some(re) = stream.next().await.expect("entry") {
+        assert_eq!(stream.state(), StreamState::Active);
+        entries.push(re);
+    }
+    assert_eq!(stream.state(), StreamState::Done);
+    assert!(stream.next().await.expect("after end").is_none());
+    let res = stream.finish().await.success().expect("success");
+    assert_eq!(res.rc, 0);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    let names = places(entries);
+    assert_eq!(names.len(), 8);
+    assert_eq!(names[5..], ["Place 5", "Summary 0", "Summary 1"]);
+}
+
+// The stream isn't done while an adapter later in the chain holds entries, even
+// though the first adapter has ended the iteration.
+#[tokio::test]
+async fn search_limit_concat() {
+    let server = places_server().start().await.expect("server");
+    let mut ldap = connect(&server).await;
+    let adapters: Vec<Box<dyn Adapter<_, _>>> = vec![
+        Box::new(Limit::new(7)),
+        Box::new(Concat::new(|| vec![summary(0), summary(1)])),
+    ];
+    let mut stream = ldap
+        .streaming_search_with(
+            adapters,
+            "ou=Places,dc=example,dc=org",
+            Scope::Subtree,
+            "(objectClass=locality)",
+            vec!["l"],
+        )
+        .await
+        .expect("stream");
+    let mut entries = vec![];
+    while let Some(re) = stream.next().await.expect("entry") {
+        entries.push(re);
+    }
+    assert_eq!(places(entries).last().unwrap(), "Summary 0");
+    assert_eq!(stream.state(), StreamState::Active);
+    assert!(stream.next().await.expect("after limit").is_none());
+    assert_eq!(stream.state(), StreamState::Active);
+    stream.finish().await.success().expect("success");
+    assert_eq!(stream.state(), StreamState::Closed);
+
+    // With the adapters reversed, the appended entries are limited too, and the
+    // Search is abandoned before the server completes it.
+    let adapters: Vec<Box<dyn Adapter<_, _>>> = vec![
+        Box::new(Concat::new(|| vec![summary(0), summary(1)])),
+        Box::new(Limit::new(2)),
+    ];
+    let mut stream = ldap
+        .streaming_search_with(
+            adapters,
+            "ou=Places,dc=example,dc=org",
+            Scope::Subtree,
+            "(objectClass=locality)",
+            vec!["l"],
+        )
+        .await
+        .expect("stream");
+    let mut entries = vec![];
+    while let Some(re) = stream.next().await.expect("entry") {
+        entries.push(re);
+    }
+    assert_eq!(
+        places(entries),
+        ["Place 0", "Place 1", "Summary 0", "Summary 1"]
+    );
+    assert_eq!(stream.state(), StreamState::Done);
+    stream.finish().await.success().expect("success");
+    ldap.close().await.expect("close");
+    let ops: Vec<_> = requests_after_unbind(&server)
+        .await
+        .iter()
+        .map(|r| r.operation)
+        .collect();
+    assert_eq!(
+        ops,
+        [
+            Operation::Search,
+            Operation::Search,
+            Operation::Abandon,
+            Operation::Unbind
+        ]
+    );
+}
@@ -0,0 +1,42 @@
+use ldap3::testkit::{done, entry, MockServer};
+use ldap3::{AttrCase, Scope, SearchEntry, SearchOptions};
+
+use crate::connect;
+
+#[tokio::test]
+async fn attribute_names_as_requested() {
+    let server = MockServer::new()
+        .on_search(|_| {
+            vec![
+                entry(
+                    "cn=jdoe,ou=People,dc=example,dc=org",
+                    &[
+                        ("samaccountname", &["jdoe"]),
+                        ("memberof", &["cn=Staff,dc=example,dc=org"]),
+                        ("cn;lang-en", &["John Doe"]),
+                    ],
+                ),
+                done(0),
+            ]
+        })
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect(&server).await;
+    let (entries, _res) = ldap
+        .with_search_options(SearchOptions::new().attr_case(AttrCase::AsRequested))
+        .search(
+            "ou=People,dc=example,dc=org",
+            Scope::Subtree,
+            "(cn=*)",
+            vec!["sAMAccountName", "memberOf", "CN"],
+        )
+        .await
+        .expect("search")
+        .success()
+        .expect("entries");
+    let entry = SearchEntry::construct(entries[0].clone());
+    let mut names: Vec<_> = entry.attrs.keys().map(String::as_str).collect();
+    names.sort_unstable();
+    assert_eq!(names, ["CN;lang-en", "memberOf", "sAMAccountName"]);
+}
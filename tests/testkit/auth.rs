@@ -0,0 +1,107 @@
+use ldap3::testkit::{bind_ok, done, entry, MockHandle, MockServer, Operation, Request};
+use ldap3::{AuthOutcome, Authenticator, LdapError};
+
+use tokio::runtime::Runtime;
+
+// Value of the equality filter of a Search, or the password of a simple Bind.
+fn request_value(req: &Request, index: usize) -> String {
+    let mut tag = req.op.clone().expect_constructed().expect("op")[index].clone();
+    if req.operation == Operation::Search {
+        tag = tag.expect_constructed().expect("filter")[1].clone();
+    }
+    String::from_utf8(tag.expect_primitive().expect("value")).expect("utf8")
+}
+
+async fn directory() -> MockHandle {
+    MockServer::new()
+        .on_bind(
+            |req: &Request| match (req.dn().as_deref(), request_value(req, 2).as_str()) {
+                (Some("cn=reader,dc=example,dc=org"), "readpw")
+                | (Some("uid=jdoe,ou=People,dc=example,dc=org"), "secret") => bind_ok(),
+                _ => done(49),
+            },
+        )
+        .on_search(|req: &Request| match request_value(req, 6).as_str() {
+            "jdoe" => vec![entry("uid=jdoe,ou=People,dc=example,dc=org", &[]), done(0)],
+            "smith" => vec![
+                entry("uid=smith,ou=People,dc=example,dc=org", &[]),
+                entry("uid=smith,ou=Contractors,dc=example,dc=org", &[]),
+                done(0),
+            ],
+            _ => vec![done(0)],
+        })
+        .start()
+        .await
+        .expect("server")
+}
+
+#[tokio::test]
+async fn authenticate_user() {
+    let server = directory().await;
+    let auth = Authenticator::new(&server.url(), "dc=example,dc=org", "(uid={})")
+        .with_service_bind("cn=reader,dc=example,dc=org", "readpw");
+    let outcome = auth.authenticate("jdoe", "secret").await.expect("outcome");
+    assert!(
+        matches!(outcome, AuthOutcome::Success { ref dn } if dn == "uid=jdoe,ou=People,dc=example,dc=org")
+    );
+    let outcome = auth.authenticate("jdoe", "wrong").await.expect("outcome");
+    assert!(matches!(outcome, AuthOutcome::BadCredentials));
+    let outcome = auth.authenticate("smith", "secret").await.expect("outcome");
+    assert!(matches!(outcome, AuthOutcome::MultipleMatches(2)));
+    let outcome = auth
+        .authenticate("nobody", "secret")
+        .await
+        .expect("outcome");
+    assert!(matches!(outcome, AuthOutcome::NoSuchUser));
+    let err = auth
+        .authenticate("jdoe", "")
+        .await
+        .expect_err("empty password");
+    assert!(matches!(
+        err,
+        LdapError::InvalidRequest {
+            field: "password",
+            ..
+        }
+    ));
+    // A failed service bind stops the verification.
+    let bad_service = Authenticator::new(&server.url(), "dc=example,dc=org", "(uid={})")
+        .with_service_bind("cn=reader,dc=example,dc=org", "wrong");
+    let err = bad_service
+        .authenticate("jdoe", "secret")
+        .await
+        .expect_err("service bind");
+    assert!(matches!(err, LdapError::LdapResult { ref result } if result.rc == 49));
+    let searches: Vec<_> = server
+        .requests()
+        .into_iter()
+        .filter(|req| req.operation == Operation::Search)
+        .collect();
+    assert_eq!(searches.len(), 4);
+}
+
+#[test]
+fn authenticate_user_sync() {
+    let rt = Runtime::new().expect("runtime");
+    let server = rt.block_on(directory());
+    let auth = Authenticator::new(&server.url(), "dc=example,dc=org", "(uid={})");
+    let outcome = auth.authenticate_sync("jd*", "secret").expect("outcome");
+    assert!(matches!(outcome, AuthOutcome::NoSuchUser));
+    let outcome = auth.authenticate_sync("jdoe", "secret").expect("outcome");
+    assert!(outcome.is_success());
+    let searches: Vec<_> = server
+        .requests()
+        .into_iter()
+        .filter(|req| req.operation == Operation::Search)
+        .map(|req| request_value(&req, 6))
+        .collect();
+    // The escaped asterisk makes an equality match with a literal value, not a substring match.
+    assert_eq!(searches, vec!["jd*", "jdoe"]);
+    let filter = server.requests()[0]
+        .op
+        .clone()
+        .expect_constructed()
+        .expect("op")[6]
+        .clone();
+    assert_eq!(filter.id, 3);
+}
@@ -0,0 +1,58 @@
+use std::collections::HashSet;
+
+use ldap3::testkit::{MockServer, Operation, Request};
+use ldap3::{LdapConnAsync, LdapConnSettings, Mod};
+
+// Values of the first attribute of an Add, or of the first modification of a Modify.
+fn request_attr_values(req: &Request) -> Vec<String> {
+    let mut tag = req.op.clone().expect_constructed().expect("op")[1]
+        .clone()
+        .expect_constructed()
+        .expect("attrs")[0]
+        .clone();
+    if req.operation == Operation::Modify {
+        tag = tag.expect_constructed().expect("change")[1].clone();
+    }
+    tag.expect_constructed().expect("attr")[1]
+        .clone()
+        .expect_constructed()
+        .expect("values")
+        .into_iter()
+        .map(|v| String::from_utf8(v.expect_primitive().expect("value")).expect("utf8"))
+        .collect()
+}
+
+#[tokio::test]
+async fn canonical_sets() {
+    let server = MockServer::new().start().await.expect("server");
+    let settings = LdapConnSettings::new().set_canonical_sets(true);
+    let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &server.url())
+        .await
+        .expect("connection");
+    ldap3::drive!(conn);
+    let vals = HashSet::from(["ccc", "b", "a", "bb", "aaaa", "ab"]);
+    ldap.add(
+        "cn=set,dc=example,dc=org",
+        vec![("description", vals.clone())],
+    )
+    .await
+    .expect("add")
+    .success()
+    .expect("success");
+    ldap.modify(
+        "cn=set,dc=example,dc=org",
+        vec![Mod::Replace("description", vals)],
+    )
+    .await
+    .expect("modify")
+    .success()
+    .expect("success");
+    let requests = server.requests();
+    for req in &requests {
+        assert_eq!(
+            request_attr_values(req),
+            ["a", "b", "ab", "bb", "ccc", "aaaa"]
+        );
+    }
+    assert_eq!(requests.len(), 2);
+}
@@ -0,0 +1,206 @@
+use ldap3::controls;
+use ldap3::testkit::{done, entry, extended, MockHandle, MockServer, Operation, Request};
+use ldap3::{LdapError, PasswordFlavor, Scope, ServerFlavor};
+
+use crate::{connect, PAGED_RESULTS_OID};
+
+const TREE_DELETE_OID: &str = "1.2.840.113556.1.4.805";
+
+type RootDseAttrs = &'static [(&'static str, &'static [&'static str])];
+
+const AD_ROOT_DSE: RootDseAttrs = &[
+    ("namingContexts", &["DC=example,DC=org"]),
+    ("rootDomainNamingContext", &["DC=example,DC=org"]),
+    (
+        "supportedCapabilities",
+        &["1.2.840.113556.1.4.800", "1.2.840.113556.1.4.1670"],
+    ),
+    (
+        "supportedControl",
+        &[PAGED_RESULTS_OID, TREE_DELETE_OID, "1.2.840.113556.1.4.417"],
+    ),
+    (
+        "supportedSASLMechanisms",
+        &["GSSAPI", "GSS-SPNEGO", "EXTERNAL"],
+    ),
+];
+
+const OPENLDAP_ROOT_DSE: RootDseAttrs = &[
+    ("objectClass", &["top", "OpenLDAProotDSE"]),
+    ("namingContexts", &["dc=example,dc=org"]),
+    (
+        "supportedControl",
+        &["2.16.840.1.113730.3.4.2", "1.3.6.1.1.12", "1.3.6.1.1.13.1"],
+    ),
+    (
+        "supportedExtension",
+        &["1.3.6.1.4.1.4203.1.11.1", "1.3.6.1.4.1.4203.1.11.3"],
+    ),
+    ("supportedSASLMechanisms", &["EXTERNAL", "SCRAM-SHA-256"]),
+];
+
+fn root_dse_server(root_dse: RootDseAttrs) -> MockServer {
+    MockServer::new()
+        .on_search(move |req| match req.dn().as_deref() {
+            Some("") => vec![entry("", root_dse), done(0)],
+            _ => vec![done(0)],
+        })
+        .on_delete(|_| done(0))
+        .on_extended(|_| extended(0, None, None))
+}
+
+fn root_dse_probes(server: &MockHandle) -> usize {
+    server
+        .requests()
+        .iter()
+        .filter(|r| r.operation == Operation::Search && r.dn().as_deref() == Some(""))
+        .count()
+}
+
+fn last_request(server: &MockHandle, operation: Operation) -> Request {
+    server
+        .requests()
+        .into_iter()
+        .filter(|r| r.operation == operation)
+        .last()
+        .expect("request")
+}
+
+#[tokio::test]
+async fn capabilities_cached() {
+    let server = root_dse_server(AD_ROOT_DSE).start().await.expect("server");
+    let mut ldap = connect(&server).await;
+    let caps = ldap
+        .with_controls(controls::ManageDsaIt)
+        .capabilities()
+        .await
+        .expect("capabilities");
+    assert_eq!(caps.vendor(), ServerFlavor::ActiveDirectory);
+    assert!(caps.supports_control(TREE_DELETE_OID));
+    assert!(caps.supports_sasl_mech("gss-spnego"));
+    assert!(!caps.supports_exop("1.3.6.1.4.1.4203.1.11.1"));
+    assert_eq!(caps.naming_contexts(), ["DC=example,DC=org"]);
+    // The probe leaves the pending controls to the next operation.
+    assert!(last_request(&server, Operation::Search)
+        .control("2.16.840.1.113730.3.4.2")
+        .is_none());
+    ldap.search(
+        "dc=example,dc=org",
+        Scope::Base,
+        "(objectClass=*)",
+        vec!["1.1"],
+    )
+    .await
+    .expect("search");
+    assert!(last_request(&server, Operation::Search)
+        .control("2.16.840.1.113730.3.4.2")
+        .is_some());
+
+    let mut clone = ldap.clone();
+    let caps = clone.capabilities().await.expect("cached");
+    assert_eq!(caps.vendor(), ServerFlavor::ActiveDirectory);
+    assert_eq!(root_dse_probes(&server), 1);
+    clone.invalidate_capabilities();
+    ldap.capabilities().await.expect("probed again");
+    assert_eq!(root_dse_probes(&server), 2);
+}
+
+#[tokio::test]
+async fn capabilities_gate_ad() {
+    let server = root_dse_server(AD_ROOT_DSE).start().await.expect("server");
+    let mut ldap = connect(&server).await;
+    let res = ldap
+        .change_password("cn=u,dc=example,dc=org", None, "new", PasswordFlavor::Auto)
+        .await;
+    assert!(matches!(res, Err(LdapError::TlsRequired)), "{:?}", res);
+
+    let stats = ldap
+        .delete_subtree("ou=Gone,dc=example,dc=org")
+        .await
+        .expect("subtree deleted");
+    assert!(stats.tree_delete);
+    assert!(last_request(&server, Operation::Delete)
+        .control(TREE_DELETE_OID)
+        .is_some());
+
+    ldap.search_paged(
+        "dc=example,dc=org",
+        Scope::Subtree,
+        "(objectClass=*)",
+        vec!["cn"],
+        10,
+    )
+    .await
+    .expect("search");
+    assert!(last_request(&server, Operation::Search)
+        .control(PAGED_RESULTS_OID)
+        .is_some());
+    assert_eq!(root_dse_probes(&server), 1);
+}
+
+#[tokio::test]
+async fn capabilities_gate_openldap() {
+    let server = root_dse_server(OPENLDAP_ROOT_DSE)
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect(&server).await;
+    let caps = ldap.capabilities().await.expect("capabilities");
+    assert_eq!(caps.vendor(), ServerFlavor::OpenLdap);
+
+    ldap.change_password("cn=u,dc=example,dc=org", None, "new", PasswordFlavor::Auto)
+        .await
+        .expect("password changed");
+    assert!(server
+        .requests()
+        .iter()
+        .any(|r| r.operation == Operation::Extended));
+
+    let stats = ldap
+        .delete_subtree("ou=Gone,dc=example,dc=org")
+        .await
+        .expect("subtree deleted");
+    assert!(!stats.tree_delete);
+    assert_eq!(stats.deleted, 1);
+    assert!(server
+        .requests()
+        .iter()
+        .filter(|r| r.operation == Operation::Delete)
+        .all(|r| r.control(TREE_DELETE_OID).is_none()));
+
+    ldap.search_paged(
+        "dc=example,dc=org",
+        Scope::Subtree,
+        "(objectClass=*)",
+        vec!["cn"],
+        10,
+    )
+    .await
+    .expect("search");
+    assert!(last_request(&server, Operation::Search)
+        .control(PAGED_RESULTS_OID)
+        .is_none());
+
+    // Skipping the probe restores the default strategies.
+    ldap.without_probe()
+        .search_paged(
+            "dc=example,dc=org",
+            Scope::Subtree,
+            "(objectClass=*)",
+            vec!["cn"],
+            10,
+        )
+        .await
+        .expect("search");
+    assert!(last_request(&server, Operation::Search)
+        .control(PAGED_RESULTS_OID)
+        .is_some());
+    ldap.without_probe()
+        .delete_subtree("ou=Gone,dc=example,dc=org")
+        .await
+        .expect("subtree deleted");
+    assert!(last_request(&server, Operation::Delete)
+        .control(TREE_DELETE_OID)
+        .is_some());
+    assert_eq!(root_dse_probes(&server), 1);
+}
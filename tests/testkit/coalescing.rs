@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use ldap3::testkit::{delay, done, MockServer, Operation};
+use ldap3::{LdapConnAsync, LdapConnSettings};
+
+#[tokio::test]
+async fn coalesced_writes() {
+    // Responses are delayed by varying amounts, so that they arrive out of order.
+    let server = MockServer::new()
+        .on_compare(|req| {
+            let n: u64 = req
+                .dn()
+                .and_then(|dn| dn.strip_prefix("cn=").and_then(|n| n.parse().ok()))
+                .expect("n");
+            let rc = if n.is_multiple_of(2) { 6 } else { 5 };
+            vec![delay(Duration::from_millis(n % 7)), done(rc)]
+        })
+        .start()
+        .await
+        .expect("server");
+    for (i, coalescing) in [None, Some(1), Some(16)].into_iter().enumerate() {
+        let settings = LdapConnSettings::new().set_write_coalescing(coalescing);
+        let (conn, ldap) = LdapConnAsync::with_settings(settings, &server.url())
+            .await
+            .expect("connection");
+        ldap3::drive!(conn);
+        let tasks: Vec<_> = (0..200u64)
+            .map(|n| {
+                let mut ldap = ldap.clone();
+                tokio::spawn(async move {
+                    let res = ldap
+                        .compare(&format!("cn={}", n), "cn", "x")
+                        .await
+                        .expect("compare");
+                    (n, res.equal().expect("equal"))
+                })
+            })
+            .collect();
+        for task in tasks {
+            let (n, equal) = task.await.expect("task");
+            assert_eq!(equal, n.is_multiple_of(2), "{:?}: cn={}", coalescing, n);
+        }
+        ldap.close().await.expect("close");
+        // The connection is closed once the Unbind is written, which may be before
+        // the server has read it.
+        for _ in 0..100 {
+            if server.requests().len() == (i + 1) * 201 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3 * 201);
+    for conn in requests.chunks(201) {
+        assert_eq!(conn[200].operation, Operation::Unbind);
+    }
+}
@@ -0,0 +1,58 @@
+use std::collections::HashSet;
+
+use ldap3::controls;
+use ldap3::testkit::{done, MockServer, Operation, Request};
+use ldap3::{LdapError, Mod, ModifyIfOutcome};
+
+use crate::connect;
+
+#[tokio::test]
+async fn conditional_updates() {
+    const ASSERTION_OID: &str = "1.3.6.1.1.12";
+
+    // The entry is at version 5. The server compares the control value with the
+    // encoding of the only filter which holds, since that's all it needs to do here.
+    let holds = controls::Assertion::try_new("(version=5)")
+        .expect("control")
+        .val;
+    let check = move |req: &Request| {
+        let ctrl = req.control(ASSERTION_OID).expect("assertion");
+        assert!(ctrl.crit);
+        if ctrl.val == holds {
+            done(0)
+        } else {
+            done(122)
+        }
+    };
+    let server = MockServer::new()
+        .on_modify(check.clone())
+        .on_delete(check)
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect(&server).await;
+    let dn = "uid=test,dc=example,dc=org";
+    let mods = || vec![Mod::Replace("version", HashSet::from(["6"]))];
+    let outcome = ldap
+        .modify_if(dn, "(version=5)", mods())
+        .await
+        .expect("modify");
+    assert!(matches!(outcome, ModifyIfOutcome::Applied(ref res) if res.rc == 0));
+    let outcome = ldap
+        .modify_if(dn, "(version=4)", mods())
+        .await
+        .expect("modify");
+    assert!(matches!(outcome, ModifyIfOutcome::PreconditionFailed(ref res) if res.rc == 122));
+    let outcome = ldap.delete_if(dn, "(version=4)").await.expect("delete");
+    assert!(matches!(outcome, ModifyIfOutcome::PreconditionFailed(_)));
+    let err = ldap
+        .modify_if(dn, "(version=5", mods())
+        .await
+        .expect_err("invalid filter");
+    assert!(matches!(err, LdapError::FilterParsing));
+    let ops: Vec<_> = server.requests().iter().map(|r| r.operation).collect();
+    assert_eq!(
+        ops,
+        [Operation::Modify, Operation::Modify, Operation::Delete]
+    );
+}
@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use ldap3::testkit::{delay, done, MockServer, Request};
+use ldap3::{ConnStats, Scope};
+
+use crate::{connect, locality};
+
+// Wait until the sampled statistics satisfy the condition.
+async fn stats_until<F: Fn(&ConnStats) -> bool>(stats: &ConnStats, cond: F) {
+    let wait = async {
+        while !cond(stats) {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(5), wait)
+        .await
+        .expect("statistics");
+}
+
+#[tokio::test]
+async fn connection_statistics() {
+    let server = MockServer::new()
+        .on_compare(|req: &Request| match req.dn().as_deref() {
+            Some("cn=slow,dc=example,dc=org") => vec![delay(Duration::from_millis(200)), done(6)],
+            _ => vec![done(6)],
+        })
+        .on_search(|_| vec![locality(1), delay(Duration::from_millis(200)), done(0)])
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect(&server).await;
+    let stats = ldap.conn_stats();
+    let started = stats.last_activity();
+    assert_eq!((stats.ops_sent(), stats.results_received()), (0, 0));
+    assert_eq!((stats.bytes_read(), stats.bytes_written()), (0, 0));
+
+    for _ in 0..3 {
+        ldap.compare("cn=a,dc=example,dc=org", "cn", "a")
+            .await
+            .expect("compare");
+    }
+    assert_eq!((stats.ops_sent(), stats.results_received()), (3, 3));
+    let (read, written) = (stats.bytes_read(), stats.bytes_written());
+    assert!(
+        read > 0 && written > read,
+        "read {}, written {}",
+        read,
+        written
+    );
+    assert!(stats.last_activity() > started);
+
+    // An operation waiting for its result.
+    let mut slow = ldap.clone();
+    let compare = tokio::spawn(async move {
+        slow.compare("cn=slow,dc=example,dc=org", "cn", "slow")
+            .await
+            .expect("compare")
+    });
+    // The operation is registered as pending before it's counted as sent.
+    stats_until(&stats, |s| s.pending_results() == 1 && s.ops_sent() == 4).await;
+    assert_eq!(stats.results_received(), 3);
+    compare.await.expect("task");
+    assert_eq!(stats.results_received(), 4);
+    stats_until(&stats, |s| s.pending_results() == 0).await;
+
+    // A Search counts as active until it's done, and each entry adds to the bytes read.
+    let mut stream = ldap
+        .streaming_search("dc=example,dc=org", Scope::Subtree, "(l=*)", vec!["l"])
+        .await
+        .expect("search");
+    stream.next().await.expect("entry").expect("some entry");
+    stats_until(&stats, |s| s.active_searches() == 1).await;
+    assert_eq!((stats.ops_sent(), stats.results_received()), (5, 4));
+    assert!(stats.bytes_read() > read);
+    assert!(stream.next().await.expect("done").is_none());
+    stream.finish().await.success().expect("success");
+    assert_eq!(stats.results_received(), 5);
+    stats_until(&stats, |s| s.active_searches() == 0).await;
+    assert_eq!(stats.queued_requests(), 0);
+
+    // The Unbind is counted too.
+    ldap.unbind().await.expect("unbind");
+    assert_eq!(stats.ops_sent(), 6);
+}
@@ -0,0 +1,66 @@
+use std::collections::HashSet;
+
+use ldap3::controls::{self, RawControl};
+use ldap3::testkit::{MockServer, Operation};
+use ldap3::{LdapError, Mod};
+
+use crate::{connect, requests_after_unbind, PAGED_RESULTS_OID};
+
+#[tokio::test]
+async fn abandon_and_unbind_controls() {
+    let server = MockServer::new().start().await.expect("server");
+    let mut ldap = connect(&server).await;
+    let txn = RawControl {
+        ctype: String::from("1.3.6.1.1.21.2"),
+        crit: true,
+        val: Some(b"txn-1".to_vec()),
+    };
+    ldap.with_controls(txn.clone())
+        .abandon(42)
+        .await
+        .expect("abandon");
+    ldap.with_controls(txn);
+    ldap.close().await.expect("close");
+    let requests = requests_after_unbind(&server).await;
+    let ops: Vec<_> = requests.iter().map(|r| r.operation).collect();
+    assert_eq!(ops, [Operation::Abandon, Operation::Unbind]);
+    for req in &requests {
+        let ctrl = req.control("1.3.6.1.1.21.2").expect("control");
+        assert!(ctrl.crit);
+        assert_eq!(ctrl.val.as_deref(), Some(&b"txn-1"[..]));
+    }
+}
+
+#[tokio::test]
+async fn control_not_allowed() {
+    let server = MockServer::new().start().await.expect("server");
+    let mut ldap = connect(&server).await;
+    let res = ldap
+        .with_controls(controls::PagedResults {
+            size: 100,
+            cookie: vec![],
+        })
+        .modify(
+            "cn=a,dc=example,dc=org",
+            vec![Mod::Replace("cn", HashSet::from(["a"]))],
+        )
+        .await;
+    match res {
+        Err(LdapError::ControlNotAllowed { oid, op }) => {
+            assert_eq!((oid.as_str(), op), (PAGED_RESULTS_OID, "modify"));
+        }
+        res => panic!("unexpected result: {:?}", res),
+    }
+    // The controls are consumed by the failed operation.
+    ldap.modify(
+        "cn=a,dc=example,dc=org",
+        vec![Mod::Replace("cn", HashSet::from(["a"]))],
+    )
+    .await
+    .expect("modify")
+    .success()
+    .expect("success");
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].controls.is_empty());
+}
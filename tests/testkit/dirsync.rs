@@ -0,0 +1,118 @@
+use ldap3::adapters::{Adapter, DirSync, EntriesOnly};
+use ldap3::asn1::{parse_tag, write, ASNTag, Integer, OctetString, Sequence, Tag};
+use ldap3::controls::{
+    Control, ControlType, RawControl, LDAP_DIRSYNC_INCREMENTAL_VALUES, LDAP_DIRSYNC_OBJECT_SECURITY,
+};
+use ldap3::testkit::{done, MockServer, Request};
+use ldap3::{Ldap, Scope};
+
+use crate::{connect, locality};
+
+const DIRSYNC_OID: &str = "1.2.840.113556.1.4.841";
+
+fn dirsync_resp(more: bool, cookie: &str) -> RawControl {
+    let val = Tag::Sequence(Sequence {
+        inner: vec![
+            Tag::Integer(Integer {
+                inner: more as i64,
+                ..Default::default()
+            }),
+            Tag::Integer(Integer::default()),
+            Tag::OctetString(OctetString {
+                inner: Vec::from(cookie),
+                ..Default::default()
+            }),
+        ],
+        ..Default::default()
+    });
+    let mut buf = bytes::BytesMut::new();
+    write::encode_into(&mut buf, val.into_structure()).expect("encoded");
+    RawControl {
+        ctype: String::from(DIRSYNC_OID),
+        crit: false,
+        val: Some(buf.to_vec()),
+    }
+}
+
+// Flags and cookie of the DirSync control in a request.
+fn dirsync_request(req: &Request) -> (i64, String) {
+    let ctrl = req.control(DIRSYNC_OID).expect("dirsync");
+    assert!(ctrl.crit);
+    let (_, val) = parse_tag(ctrl.val.as_deref().expect("value")).expect("parsed");
+    let mut comps = val.expect_constructed().expect("components").into_iter();
+    let flags = comps.next().and_then(|c| c.expect_i64()).expect("flags");
+    let _max_bytes = comps.next();
+    let cookie = comps
+        .next()
+        .and_then(|c| c.expect_primitive())
+        .expect("cookie");
+    (flags, String::from_utf8(cookie).expect("cookie"))
+}
+
+async fn dirsync_round(ldap: &mut Ldap, cookie: Vec<u8>) -> (usize, Vec<u8>) {
+    let dirsync = DirSync::new(
+        LDAP_DIRSYNC_OBJECT_SECURITY | LDAP_DIRSYNC_INCREMENTAL_VALUES,
+        cookie,
+    );
+    let adapters: Vec<Box<dyn Adapter<_, _>>> =
+        vec![Box::new(EntriesOnly::new()), Box::new(dirsync.clone())];
+    let mut stream = ldap
+        .streaming_search_with(
+            adapters,
+            "dc=example,dc=org",
+            Scope::Subtree,
+            "(objectClass=*)",
+            vec!["l"],
+        )
+        .await
+        .expect("stream");
+    let mut count = 0;
+    while stream.next().await.expect("entry").is_some() {
+        count += 1;
+    }
+    let res = stream.finish().await.success().expect("success");
+    assert!(res
+        .ctrls
+        .iter()
+        .any(|c| matches!(c, Control(Some(ControlType::DirSync), _))));
+    (count, dirsync.cookie())
+}
+
+#[tokio::test]
+async fn dirsync_rounds() {
+    let server = MockServer::new()
+        .on_search(|req: &Request| {
+            let (flags, cookie) = dirsync_request(req);
+            assert_eq!(flags, 0x8000_0001u32 as i32 as i64);
+            match cookie.as_str() {
+                "" => vec![
+                    locality(0),
+                    locality(1),
+                    done(0).with_controls(dirsync_resp(true, "c1")),
+                ],
+                "c1" => vec![
+                    locality(2),
+                    done(0).with_controls(dirsync_resp(false, "c2")),
+                ],
+                "c2" => vec![
+                    locality(3),
+                    done(0).with_controls(dirsync_resp(false, "c3")),
+                ],
+                _ => panic!("unexpected cookie: {}", cookie),
+            }
+        })
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect(&server).await;
+    let (count, cookie) = dirsync_round(&mut ldap, vec![]).await;
+    assert_eq!((count, cookie.as_slice()), (3, &b"c2"[..]));
+    let (count, cookie) = dirsync_round(&mut ldap, cookie).await;
+    assert_eq!((count, cookie.as_slice()), (1, &b"c3"[..]));
+    let cookies: Vec<_> = server
+        .requests()
+        .iter()
+        .map(|req| dirsync_request(req).1)
+        .collect();
+    assert_eq!(cookies, ["", "c1", "c2"]);
+}
@@ -0,0 +1,78 @@
+use ldap3::adapters::{Adapter, EntriesOnly, PagedResults};
+use ldap3::asn1::{write, ASNTag, Enumerated, OctetString, Sequence, Tag};
+use ldap3::controls::{self, Control, ControlType, EntryState, RawControl, SyncState};
+use ldap3::testkit::{done, MockServer, Request};
+use ldap3::{Scope, SearchEntry};
+
+use crate::{connect, locality, PAGED_RESULTS_OID};
+
+// Sync State control marking an added entry, with a one-byte entryUUID.
+fn sync_state(n: u8) -> RawControl {
+    let val = Tag::Sequence(Sequence {
+        inner: vec![
+            Tag::Enumerated(Enumerated {
+                inner: 1,
+                ..Default::default()
+            }),
+            Tag::OctetString(OctetString {
+                inner: vec![n],
+                ..Default::default()
+            }),
+        ],
+        ..Default::default()
+    });
+    let mut buf = bytes::BytesMut::new();
+    write::encode_into(&mut buf, val.into_structure()).expect("encoded");
+    RawControl {
+        ctype: String::from("1.3.6.1.4.1.4203.1.9.1.2"),
+        crit: false,
+        val: Some(buf.to_vec()),
+    }
+}
+
+#[tokio::test]
+async fn entry_controls() {
+    let server = MockServer::new()
+        .on_search(|req: &Request| {
+            let pr: controls::PagedResults =
+                req.control(PAGED_RESULTS_OID).expect("paging").parse();
+            let start = if pr.cookie.is_empty() { 0 } else { 2 };
+            let mut responses: Vec<_> = (start..start + 2)
+                .map(|n| locality(n).with_controls(sync_state(n as u8)))
+                .collect();
+            let cookie = if start == 0 { b"2".to_vec() } else { vec![] };
+            responses.push(done(0).with_controls(controls::PagedResults { size: 4, cookie }));
+            responses
+        })
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect(&server).await;
+    let adapters: Vec<Box<dyn Adapter<_, _>>> =
+        vec![Box::new(EntriesOnly::new()), Box::new(PagedResults::new(2))];
+    let mut search = ldap
+        .streaming_search_with(
+            adapters,
+            "ou=Places,dc=example,dc=org",
+            Scope::Subtree,
+            "(objectClass=*)",
+            vec!["l"],
+        )
+        .await
+        .expect("stream");
+    let mut count = 0u8;
+    while let Some(entry) = search.next().await.expect("entry") {
+        let state = match entry.controls() {
+            [Control(Some(ControlType::SyncState), raw)] => raw.parse::<SyncState>(),
+            ctrls => panic!("unexpected controls: {:?}", ctrls),
+        };
+        assert!(matches!(state.state, EntryState::Add));
+        assert_eq!(state.entry_uuid, [count]);
+        let (entry, ctrls) = SearchEntry::construct_with_controls(entry);
+        assert_eq!(entry.attrs["l"], vec![format!("Place {}", count)]);
+        assert_eq!(ctrls.len(), 1);
+        count += 1;
+    }
+    assert_eq!(count, 4);
+    search.finish().await.success().expect("success");
+}
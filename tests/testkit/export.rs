@@ -0,0 +1,198 @@
+use std::io::{self, Write};
+use std::time::Duration;
+
+use ldap3::testkit::{delay, done, entry, referral, MockServer, Operation};
+use ldap3::{Ldap, LdapError, Scope};
+
+use crate::{connect, locality};
+
+#[tokio::test]
+async fn export_entries() {
+    use ldap3::export::{self, ExportOptions};
+    use ldap3::testkit::raw;
+    use std::collections::HashMap;
+
+    let person = raw(ldap3::encode_search_entry(
+        "cn=Doe\\, John,ou=People,dc=example,dc=org",
+        &HashMap::from([
+            (String::from("cn"), vec![String::from("Doe, John")]),
+            (
+                String::from("mail"),
+                vec![
+                    String::from("jdoe@example.org"),
+                    String::from("john@example.org"),
+                ],
+            ),
+        ]),
+        &HashMap::from([(String::from("jpegPhoto"), vec![b"\xff\xd8\xff".to_vec()])]),
+    ));
+    let script = vec![
+        person,
+        referral(&["ldap://elsewhere/ou=People,dc=example,dc=org"]),
+        entry("cn=x,ou=People,dc=example,dc=org", &[("cn", &["x"])]),
+        done(0),
+    ];
+    let server = MockServer::new()
+        .on_search(move |_| script.clone())
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect(&server).await;
+    let search = |ldap: &mut Ldap| {
+        let mut ldap = ldap.clone();
+        async move {
+            ldap.streaming_search(
+                "ou=People,dc=example,dc=org",
+                Scope::Subtree,
+                "(cn=*)",
+                vec!["*"],
+            )
+            .await
+            .expect("stream")
+        }
+    };
+
+    let mut stream = search(&mut ldap).await;
+    let mut out = vec![];
+    let opts = ExportOptions::new().flatten(true);
+    let stats = export::ndjson(&mut stream, &mut out, &opts)
+        .await
+        .expect("ndjson");
+    assert_eq!((stats.entries, stats.referrals, stats.result.rc), (2, 1, 0));
+    let lines: Vec<serde_json::Value> = String::from_utf8(out)
+        .expect("utf-8")
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("json"))
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            serde_json::json!({
+                "dn": "cn=Doe\\, John,ou=People,dc=example,dc=org",
+                "attrs": {
+                    "cn": "Doe, John",
+                    "mail": ["jdoe@example.org", "john@example.org"],
+                },
+                "bin_attrs": { "jpegPhoto": "/9j/" },
+            }),
+            serde_json::json!({
+                "dn": "cn=x,ou=People,dc=example,dc=org",
+                "attrs": { "cn": "x" },
+                "bin_attrs": {},
+            }),
+        ]
+    );
+
+    let mut stream = search(&mut ldap).await;
+    let mut out = vec![];
+    let stats = export::csv(
+        &mut stream,
+        &mut out,
+        &["dn", "cn", "mail", "jpegPhoto"],
+        &ExportOptions::new(),
+    )
+    .await
+    .expect("csv");
+    assert_eq!((stats.entries, stats.referrals, stats.result.rc), (2, 1, 0));
+    assert_eq!(
+        String::from_utf8(out).expect("utf-8"),
+        "dn,cn,mail,jpegPhoto\r\n\
+         \"cn=Doe\\, John,ou=People,dc=example,dc=org\",\"Doe, John\",\
+         jdoe@example.org;john@example.org,/9j/\r\n\
+         \"cn=x,ou=People,dc=example,dc=org\",x,,\r\n"
+    );
+}
+
+#[tokio::test]
+async fn export_interrupted() {
+    use ldap3::export::{self, ExportOptions};
+
+    let server = MockServer::new()
+        .on_search(|_| {
+            vec![
+                locality(0),
+                locality(1),
+                delay(Duration::from_secs(5)),
+                done(0),
+            ]
+        })
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect(&server).await;
+    let mut stream = ldap
+        .with_timeout(Duration::from_millis(100))
+        .streaming_search(
+            "ou=Places,dc=example,dc=org",
+            Scope::Subtree,
+            "(objectClass=locality)",
+            vec!["l"],
+        )
+        .await
+        .expect("stream");
+    let mut out = vec![];
+    let res = export::ndjson(&mut stream, &mut out, &ExportOptions::new()).await;
+    assert!(matches!(res, Err(LdapError::Timeout { .. })), "{:?}", res);
+    assert_eq!(String::from_utf8(out).expect("utf-8").lines().count(), 2);
+}
+
+// Output which fails every write.
+struct BrokenPipe;
+
+impl Write for BrokenPipe {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::from(io::ErrorKind::BrokenPipe))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn export_write_failed() {
+    use ldap3::export::{self, ExportOptions};
+
+    let server = MockServer::new()
+        .on_search(|req| match req.dn().as_deref() {
+            Some("ou=Places,dc=example,dc=org") => vec![
+                locality(0),
+                locality(1),
+                delay(Duration::from_secs(5)),
+                done(0),
+            ],
+            _ => vec![done(0)],
+        })
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect(&server).await;
+    let mut stream = ldap
+        .streaming_search(
+            "ou=Places,dc=example,dc=org",
+            Scope::Subtree,
+            "(objectClass=locality)",
+            vec!["l"],
+        )
+        .await
+        .expect("stream");
+    let res = export::ndjson(&mut stream, BrokenPipe, &ExportOptions::new()).await;
+    assert!(matches!(res, Err(LdapError::Io { .. })), "{:?}", res);
+    let (entries, _res) = ldap
+        .search(
+            "ou=People,dc=example,dc=org",
+            Scope::Subtree,
+            "(objectClass=*)",
+            vec!["cn"],
+        )
+        .await
+        .expect("search")
+        .success()
+        .expect("success");
+    assert!(entries.is_empty());
+    let ops: Vec<_> = server.requests().iter().map(|r| r.operation).collect();
+    assert_eq!(
+        ops,
+        [Operation::Search, Operation::Abandon, Operation::Search]
+    );
+}
@@ -0,0 +1,143 @@
+use ldap3::testkit::{done, entry, MockHandle, MockServer, Request, Response};
+use ldap3::{GroupSchema, NestedMode};
+
+use crate::connect;
+
+// Groups under ou=Groups, with cyclic membership between cn=a and cn=b.
+const GROUPS: &[(&str, &[&str])] = &[
+    (
+        "cn=a,ou=Groups,dc=example,dc=org",
+        &["cn=b,ou=Groups,dc=example,dc=org"],
+    ),
+    (
+        "cn=b,ou=Groups,dc=example,dc=org",
+        &[
+            "cn=a,ou=Groups,dc=example,dc=org",
+            "uid=alice,ou=People,dc=example,dc=org",
+        ],
+    ),
+    (
+        "cn=c,ou=Groups,dc=example,dc=org",
+        &["cn=a,ou=Groups,dc=example,dc=org"],
+    ),
+];
+
+fn search_filter(req: &Request) -> ldap3::asn1::StructureTag {
+    req.op.clone().expect_constructed().expect("op")[6].clone()
+}
+
+async fn group_directory() -> MockHandle {
+    MockServer::new()
+        .on_search(|req: &Request| {
+            let base = req.dn().expect("base");
+            if base == "ou=Groups,dc=example,dc=org" {
+                // (|(member=X)(uniqueMember=X)): the value of the first equality match.
+                let terms = search_filter(req).expect_constructed().expect("or");
+                let value = terms[0].clone().expect_constructed().expect("eq")[1]
+                    .clone()
+                    .expect_primitive()
+                    .expect("value");
+                let value = String::from_utf8(value).expect("utf8");
+                let mut resp: Vec<Response> = GROUPS
+                    .iter()
+                    .filter(|(_, members)| members.contains(&value.as_str()))
+                    .map(|(dn, _)| entry(dn, &[]))
+                    .collect();
+                resp.push(done(0));
+                return resp;
+            }
+            match GROUPS.iter().find(|(dn, _)| *dn == base) {
+                Some((dn, members)) => vec![entry(dn, &[("member", members)]), done(0)],
+                None if base.starts_with("uid=") => vec![entry(&base, &[]), done(0)],
+                None => vec![done(32)],
+            }
+        })
+        .start()
+        .await
+        .expect("server")
+}
+
+#[tokio::test]
+async fn nested_membership() {
+    const ALICE: &str = "uid=alice,ou=People,dc=example,dc=org";
+    const BOB: &str = "uid=bob,ou=People,dc=example,dc=org";
+    let server = group_directory().await;
+    let mut ldap = connect(&server).await;
+    let c = "cn=c,ou=Groups,dc=example,dc=org";
+    assert!(!ldap
+        .is_member(c, ALICE, NestedMode::Direct)
+        .await
+        .expect("direct"));
+    // c has a as a member, which has b, which has alice.
+    assert!(!ldap
+        .is_member(c, ALICE, NestedMode::Recursive { max_depth: 1 })
+        .await
+        .expect("depth 1"));
+    assert!(ldap
+        .is_member(c, ALICE, NestedMode::Recursive { max_depth: 2 })
+        .await
+        .expect("depth 2"));
+    // The a/b cycle is expanded only once: c, a, b, and the member alice.
+    let before = server.requests().len();
+    assert!(!ldap
+        .is_member(c, BOB, NestedMode::Recursive { max_depth: 100 })
+        .await
+        .expect("cycle"));
+    assert_eq!(server.requests().len() - before, 4);
+    let schema = GroupSchema::new().group_base("ou=Groups,dc=example,dc=org");
+    let groups = ldap
+        .member_of_with(ALICE, NestedMode::Recursive { max_depth: 100 }, &schema)
+        .await
+        .expect("member of");
+    assert_eq!(
+        groups,
+        vec![
+            "cn=b,ou=Groups,dc=example,dc=org",
+            "cn=a,ou=Groups,dc=example,dc=org",
+            "cn=c,ou=Groups,dc=example,dc=org",
+        ]
+    );
+    let groups = ldap
+        .member_of_with(ALICE, NestedMode::Direct, &schema)
+        .await
+        .expect("direct member of");
+    assert_eq!(groups, vec!["cn=b,ou=Groups,dc=example,dc=org"]);
+}
+
+#[tokio::test]
+async fn membership_matching_rule_in_chain() {
+    let server = MockServer::new()
+        .on_search(|req: &Request| {
+            // Extensible match: [9] { matchingRule [1], type [2], matchValue [3] }.
+            let filter = search_filter(req);
+            assert_eq!(filter.id, 9);
+            let parts: Vec<_> = filter
+                .expect_constructed()
+                .expect("extensible")
+                .into_iter()
+                .map(|t| String::from_utf8(t.expect_primitive().expect("part")).expect("utf8"))
+                .collect();
+            assert_eq!(
+                parts,
+                vec![
+                    "1.2.840.113556.1.4.1941",
+                    "memberOf",
+                    "CN=Admins,CN=Users,DC=example,DC=org",
+                ]
+            );
+            vec![entry(&req.dn().expect("base"), &[]), done(0)]
+        })
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect(&server).await;
+    assert!(ldap
+        .is_member(
+            "CN=Admins,CN=Users,DC=example,DC=org",
+            "CN=Alice,CN=Users,DC=example,DC=org",
+            NestedMode::MatchingRuleInChain,
+        )
+        .await
+        .expect("in chain"));
+    assert_eq!(server.requests().len(), 1);
+}
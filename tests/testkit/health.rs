@@ -0,0 +1,92 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use ldap3::controls::RawControl;
+use ldap3::testkit::{delay, disconnect, done, entry, MockServer, Operation, Request};
+use ldap3::{HealthStatus, LdapConn, LdapError};
+
+use tokio::runtime::Runtime;
+
+use crate::connect;
+
+#[tokio::test]
+async fn check_health() {
+    let probes = Arc::new(AtomicUsize::new(0));
+    let count = probes.clone();
+    let server = MockServer::new()
+        .on_search(move |_| match count.fetch_add(1, Ordering::SeqCst) {
+            0 => vec![entry("", &[]), done(0)],
+            1 => vec![done(53).with_text("read-only maintenance")],
+            _ => vec![delay(Duration::from_millis(500)), done(0)],
+        })
+        .on_compare(|_| done(6))
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect(&server).await;
+    let ctrl = RawControl {
+        ctype: String::from("1.2.3.4"),
+        crit: false,
+        val: None,
+    };
+    ldap.with_controls(ctrl)
+        .with_timeout(Duration::from_secs(5));
+    match ldap.check_health(Duration::from_secs(1)).await {
+        HealthStatus::Healthy { rtt } => assert!(rtt < Duration::from_secs(1)),
+        status => panic!("unexpected status: {:?}", status),
+    }
+    assert!(matches!(
+        ldap.check_health(Duration::from_secs(1)).await,
+        HealthStatus::Degraded { reason } if reason.rc == 53
+    ));
+    let status = ldap.check_health(Duration::from_millis(100)).await;
+    assert!(status.is_unusable());
+    assert!(matches!(
+        status,
+        HealthStatus::Unusable {
+            error: LdapError::Timeout { .. }
+        }
+    ));
+
+    // The modifiers set before the probes apply to the next operation.
+    ldap.compare("cn=x,dc=example,dc=org", "cn", "x")
+        .await
+        .expect("compare");
+    let requests = server.requests();
+    let probes: Vec<_> = requests
+        .iter()
+        .filter(|req| req.operation == Operation::Search)
+        .collect();
+    assert_eq!(probes.len(), 3);
+    assert!(probes.iter().all(|req| req.controls.is_empty()));
+    assert_eq!(probes[0].dn().as_deref(), Some(""));
+    let compare = requests
+        .iter()
+        .find(|req| req.operation == Operation::Compare)
+        .expect("compare");
+    assert!(compare.control("1.2.3.4").is_some());
+}
+
+#[test]
+fn check_health_sync() {
+    let rt = Runtime::new().expect("runtime");
+    let server = rt
+        .block_on(
+            MockServer::new()
+                .on_search(|req: &Request| match req.id {
+                    1 => vec![done(0)],
+                    _ => vec![disconnect()],
+                })
+                .start(),
+        )
+        .expect("server");
+    let mut ldap = LdapConn::new(&server.url()).expect("connection");
+    assert!(ldap.check_health(Duration::from_secs(1)).is_healthy());
+    assert!(matches!(
+        ldap.check_health(Duration::from_secs(1)),
+        HealthStatus::Unusable {
+            error: LdapError::LdapResult { ref result }
+        } if result.rc == 52
+    ));
+}
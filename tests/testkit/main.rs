@@ -0,0 +1,350 @@
+// Versions of several examples, running against the scripted server from
+// the testkit module instead of a live directory.
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use ldap3::adapters::{Adapter, EntriesOnly, PagedResults};
+use ldap3::controls::{self, Control, ControlType};
+use ldap3::testkit::{
+    bind_ok, delay, disconnect, done, entry, notice_of_disconnection, referral, MockHandle,
+    MockServer, Operation, Request, Response,
+};
+use ldap3::{Ldap, LdapConn, LdapConnAsync, LdapError, Scope, SearchEntry};
+
+use futures::{StreamExt, TryStreamExt};
+use tokio::runtime::Runtime;
+
+mod adapters;
+mod attr_case;
+mod auth;
+mod canonical_sets;
+mod capabilities;
+mod coalescing;
+mod conditional;
+mod conn_stats;
+mod control_checks;
+mod dirsync;
+mod entry_controls;
+#[cfg(feature = "serde")]
+mod export;
+mod groups;
+mod health;
+mod migrate;
+mod paged;
+mod password_expiry;
+mod persistent_search;
+mod raw_entry;
+mod raw_op;
+mod retry;
+#[cfg(feature = "runtime-tokio")]
+mod search_timeouts;
+mod search_url;
+#[cfg(any(feature = "tls-native", feature = "tls-rustls"))]
+mod starttls;
+mod transactions;
+mod transport;
+mod unmatched;
+mod wire_trace;
+
+const PAGED_RESULTS_OID: &str = "1.2.840.113556.1.4.319";
+
+async fn connect(server: &MockHandle) -> Ldap {
+    let (conn, ldap) = LdapConnAsync::new(&server.url()).await.expect("connection");
+    ldap3::drive!(conn);
+    ldap
+}
+
+fn locality(n: usize) -> Response {
+    let l = format!("Place {}", n);
+    entry(
+        &format!("l={},ou=Places,dc=example,dc=org", l),
+        &[("l", &[l.as_str()])],
+    )
+}
+
+// examples/search_stream.rs
+#[tokio::test]
+async fn search_stream() {
+    let server = MockServer::new()
+        .on_search(|_| {
+            let mut responses: Vec<_> = (0..10).map(locality).collect();
+            responses.push(delay(Duration::from_millis(100)));
+            responses.extend((10..20).map(locality));
+            responses.push(done(0));
+            responses
+        })
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect(&server).await;
+    let mut stream = ldap
+        .streaming_search(
+            "ou=Places,dc=example,dc=org",
+            Scope::Subtree,
+            "(objectClass=locality)",
+            vec!["l"],
+        )
+        .await
+        .expect("stream")
+        .into_stream();
+    let entries: Vec<_> = stream
+        .by_ref()
+        .take(10)
+        .try_collect()
+        .await
+        .expect("entries");
+    assert_eq!(entries.len(), 10);
+    assert_eq!(
+        SearchEntry::construct(entries[3].clone()).attrs["l"],
+        vec!["Place 3"]
+    );
+    // The server hasn't sent the rest of the entries yet, so the result is synthetic.
+    let res = stream.finish().await;
+    assert_eq!(res.rc, 88);
+    ldap.unbind().await.expect("unbind");
+    let ops: Vec<_> = server.requests().iter().map(|r| r.operation).collect();
+    assert_eq!(ops[0], Operation::Search);
+}
+
+// examples/compare_sync.rs
+#[test]
+fn compare_sync() {
+    let rt = Runtime::new().expect("runtime");
+    let server = rt
+        .block_on(
+            MockServer::new()
+                .on_bind(|req: &Request| {
+                    if req.dn().as_deref() == Some("cn=Manager,dc=example,dc=org") {
+                        bind_ok()
+                    } else {
+                        done(49)
+                    }
+                })
+                .on_compare(|_| done(6))
+                .start(),
+        )
+        .expect("server");
+    let mut ldap = LdapConn::new(&server.url()).expect("connection");
+    ldap.simple_bind("cn=Manager,dc=example,dc=org", "secret")
+        .expect("bind")
+        .success()
+        .expect("success");
+    let eq = ldap
+        .compare(
+            "uid=inejge,ou=People,dc=example,dc=org",
+            "userPassword",
+            "doublesecret",
+        )
+        .expect("compare")
+        .equal()
+        .expect("equal");
+    assert!(eq);
+    let err = ldap
+        .simple_bind("cn=Intruder,dc=example,dc=org", "secret")
+        .expect("bind")
+        .success()
+        .expect_err("invalid credentials");
+    assert!(matches!(err, LdapError::LdapResult { ref result } if result.rc == 49));
+    let requests = server.requests();
+    assert_eq!(
+        requests[1].dn().as_deref(),
+        Some("uid=inejge,ou=People,dc=example,dc=org")
+    );
+}
+
+// examples/add_sync.rs
+#[tokio::test]
+async fn add() {
+    let server = MockServer::new()
+        .on_add(|req: &Request| {
+            if req.dn().as_deref() == Some("uid=extra,ou=People,dc=example,dc=org") {
+                done(68).with_text("entry already exists")
+            } else {
+                done(0)
+            }
+        })
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect(&server).await;
+    let attrs = vec![
+        ("objectClass", HashSet::from(["inetOrgPerson"])),
+        ("uid", HashSet::from(["new"])),
+        ("cn", HashSet::from(["New User"])),
+        ("sn", HashSet::from(["User"])),
+    ];
+    ldap.add("uid=new,ou=People,dc=example,dc=org", attrs.clone())
+        .await
+        .expect("add")
+        .success()
+        .expect("success");
+    let res = ldap
+        .add("uid=extra,ou=People,dc=example,dc=org", attrs)
+        .await
+        .expect("add");
+    assert_eq!((res.rc, res.text.as_str()), (68, "entry already exists"));
+}
+
+// Requests recorded by the server, once the last one is an Unbind. The client doesn't
+// wait for the server to read the Unbind, so it may not be recorded when close() returns.
+async fn requests_after_unbind(server: &MockHandle) -> Vec<Request> {
+    for _ in 0..100 {
+        let requests = server.requests();
+        if requests.last().map(|r| r.operation) == Some(Operation::Unbind) {
+            return requests;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    server.requests()
+}
+
+// examples/search_adapted_paged_plus.rs
+#[tokio::test]
+async fn search_adapted_paged_plus() {
+    let server = MockServer::new()
+        .on_search(|req: &Request| {
+            let pr: controls::PagedResults =
+                req.control(PAGED_RESULTS_OID).expect("paging").parse();
+            let start = if pr.cookie.is_empty() {
+                0
+            } else {
+                String::from_utf8(pr.cookie)
+                    .expect("cookie")
+                    .parse()
+                    .expect("offset")
+            };
+            let end = 7.min(start + pr.size as usize);
+            let mut responses = vec![];
+            if start == 0 {
+                responses.push(referral(&[
+                    "ldap://elsewhere.example.org/ou=Places,dc=example,dc=org",
+                ]));
+            }
+            responses.extend((start..end).map(locality));
+            let cookie = if end < 7 {
+                end.to_string().into_bytes()
+            } else {
+                vec![]
+            };
+            responses.push(done(0).with_controls(controls::PagedResults { size: 7, cookie }));
+            responses
+        })
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect(&server).await;
+    let adapters: Vec<Box<dyn Adapter<_, _>>> =
+        vec![Box::new(EntriesOnly::new()), Box::new(PagedResults::new(3))];
+    let mut search = ldap
+        .streaming_search_with(
+            adapters,
+            "ou=Places,dc=example,dc=org",
+            Scope::Subtree,
+            "(objectClass=*)",
+            vec!["dn"],
+        )
+        .await
+        .expect("stream");
+    let mut count = 0;
+    while let Some(entry) = search.next().await.expect("entry") {
+        let entry = SearchEntry::construct(entry);
+        assert_eq!(entry.attrs["l"], vec![format!("Place {}", count)]);
+        count += 1;
+    }
+    assert_eq!(count, 7);
+    let res = search.finish().await.success().expect("success");
+    assert_eq!(res.refs.len(), 1);
+    assert!(!res
+        .ctrls
+        .iter()
+        .any(|c| matches!(c, Control(Some(ControlType::PagedResults), _))));
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn notice_of_disconnection_fails_pending_operation() {
+    let server = MockServer::new()
+        .on_search(|_| {
+            vec![
+                locality(0),
+                notice_of_disconnection(52).with_text("shutting down"),
+                disconnect(),
+            ]
+        })
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect(&server).await;
+    let err = ldap
+        .search(
+            "ou=Places,dc=example,dc=org",
+            Scope::Subtree,
+            "(l=*)",
+            vec!["l"],
+        )
+        .await
+        .expect_err("disconnected");
+    assert!(
+        matches!(err, LdapError::ServerDisconnect { rc: 52, ref text } if text == "shutting down"),
+        "{:?}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn delayed_response_times_out() {
+    let server = MockServer::new()
+        .on_compare(|_| vec![delay(Duration::from_secs(2)), done(6)])
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect(&server).await;
+    let err = ldap
+        .with_timeout(Duration::from_millis(50))
+        .compare("uid=slow,dc=example,dc=org", "cn", "slow")
+        .await
+        .expect_err("timeout");
+    assert!(matches!(err, LdapError::Timeout { .. }), "{:?}", err);
+}
+
+// Collector of the messages logged with the wire trace target, and of the warnings
+// from the library, installed once for the whole test binary.
+struct TestLog(std::sync::Mutex<Vec<(String, String)>>);
+
+impl TestLog {
+    fn install() {
+        let _ = ldap3::log::set_logger(&TEST_LOG);
+        ldap3::log::set_max_level(ldap3::log::LevelFilter::Debug);
+    }
+
+    fn lines(target: &str) -> Vec<String> {
+        let lines = TEST_LOG.0.lock().expect("lock");
+        lines
+            .iter()
+            .filter(|(t, _)| t == target)
+            .map(|(_, line)| line.clone())
+            .collect()
+    }
+}
+
+impl ldap3::log::Log for TestLog {
+    fn enabled(&self, metadata: &ldap3::log::Metadata) -> bool {
+        let level = match metadata.target() {
+            "ldap3::wire" => ldap3::log::Level::Debug,
+            _ => ldap3::log::Level::Warn,
+        };
+        metadata.target().starts_with("ldap3::") && metadata.level() <= level
+    }
+
+    fn log(&self, record: &ldap3::log::Record) {
+        if self.enabled(record.metadata()) {
+            let mut lines = self.0.lock().expect("lock");
+            lines.push((record.target().to_string(), record.args().to_string()));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static TEST_LOG: TestLog = TestLog(std::sync::Mutex::new(Vec::new()));
@@ -0,0 +1,242 @@
+use ldap3::controls;
+use ldap3::testkit::{done, entry, MockHandle, MockServer, Operation, Request};
+
+use crate::{connect, PAGED_RESULTS_OID};
+
+// Source tree for copy_subtree(), as (DN, parent DN, attributes).
+type TreeEntry = (
+    &'static str,
+    &'static str,
+    &'static [(&'static str, &'static [&'static str])],
+);
+
+const OLD_TREE: &[TreeEntry] = &[
+    (
+        "ou=Old,dc=example,dc=org",
+        "",
+        &[("ou", &["Old"]), ("hasSubordinates", &["TRUE"])],
+    ),
+    (
+        "ou=People,ou=Old,dc=example,dc=org",
+        "ou=Old,dc=example,dc=org",
+        &[("ou", &["People"]), ("entryUUID", &["1"])],
+    ),
+    (
+        "ou=Groups,ou=Old,dc=example,dc=org",
+        "ou=Old,dc=example,dc=org",
+        &[("ou", &["Groups"]), ("hasSubordinates", &["TRUE"])],
+    ),
+    (
+        "ou=Broken,ou=Old,dc=example,dc=org",
+        "ou=Old,dc=example,dc=org",
+        &[("ou", &["Broken"])],
+    ),
+    (
+        "uid=a,ou=People,ou=Old,dc=example,dc=org",
+        "ou=People,ou=Old,dc=example,dc=org",
+        &[
+            ("uid", &["a"]),
+            ("manager", &["uid=b,ou=People,ou=Old,dc=example,dc=org"]),
+            ("hasSubordinates", &["FALSE"]),
+        ],
+    ),
+    (
+        "uid=b,ou=People,ou=Old,dc=example,dc=org",
+        "ou=People,ou=Old,dc=example,dc=org",
+        &[("uid", &["b"]), ("numSubordinates", &["0"])],
+    ),
+    (
+        "cn=staff,ou=Groups,ou=Old,dc=example,dc=org",
+        "ou=Groups,ou=Old,dc=example,dc=org",
+        &[
+            ("cn", &["staff"]),
+            (
+                "member",
+                &[
+                    "uid=a,ou=People,ou=Old,dc=example,dc=org",
+                    "uid=b, OU=people,ou=old,DC=example,dc=org",
+                    "uid=x,ou=Elsewhere,dc=example,dc=org",
+                ],
+            ),
+            ("hasSubordinates", &["FALSE"]),
+        ],
+    ),
+    (
+        "cn=hidden,ou=Broken,ou=Old,dc=example,dc=org",
+        "ou=Broken,ou=Old,dc=example,dc=org",
+        &[("cn", &["hidden"])],
+    ),
+];
+
+async fn old_directory() -> MockHandle {
+    MockServer::new()
+        .on_search(|req: &Request| {
+            let base = req.dn().expect("base");
+            if base.is_empty() {
+                return vec![
+                    entry("", &[("supportedControl", &[PAGED_RESULTS_OID])]),
+                    done(0),
+                ];
+            }
+            assert!(req.control(PAGED_RESULTS_OID).is_some());
+            let scope = req.op.clone().expect_constructed().expect("op")[1]
+                .clone()
+                .expect_primitive()
+                .expect("scope")[0];
+            let mut responses: Vec<_> = OLD_TREE
+                .iter()
+                .filter(|(dn, parent, _)| match scope {
+                    0 => *dn == base,
+                    _ => *parent == base,
+                })
+                .map(|(dn, _, attrs)| entry(dn, attrs))
+                .collect();
+            let paged = controls::PagedResults {
+                size: 0,
+                cookie: vec![],
+            };
+            responses.push(done(0).with_controls(paged));
+            responses
+        })
+        .start()
+        .await
+        .expect("server")
+}
+
+async fn new_directory() -> MockHandle {
+    MockServer::new()
+        .on_add(|req: &Request| {
+            if req.dn().as_deref() == Some("ou=Broken,ou=New,dc=example,dc=com") {
+                done(68)
+            } else {
+                done(0)
+            }
+        })
+        .start()
+        .await
+        .expect("server")
+}
+
+// All attributes of an Add, with their values.
+fn request_attrs(req: &Request) -> Vec<(String, Vec<String>)> {
+    let text = |tag: ldap3::asn1::StructureTag| {
+        String::from_utf8(tag.expect_primitive().expect("value")).expect("utf8")
+    };
+    req.op.clone().expect_constructed().expect("op")[1]
+        .clone()
+        .expect_constructed()
+        .expect("attrs")
+        .into_iter()
+        .map(|attr| {
+            let mut attr = attr.expect_constructed().expect("attr").into_iter();
+            let name = text(attr.next().expect("name"));
+            let vals = attr
+                .next()
+                .expect("values")
+                .expect_constructed()
+                .expect("values");
+            (name, vals.into_iter().map(text).collect())
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn copy_subtree() {
+    use ldap3::migrate::{self, CopyOptions};
+
+    let (src_server, dst_server) = (old_directory().await, new_directory().await);
+    let mut src = connect(&src_server).await;
+    let mut dst = connect(&dst_server).await;
+    let stats = migrate::copy_subtree(
+        &mut src,
+        &mut dst,
+        "ou=Old,dc=example,dc=org",
+        "ou=New,dc=example,dc=com",
+        &CopyOptions::new().page_size(2),
+    )
+    .await
+    .expect("copy");
+    assert_eq!(stats.added, 6);
+    assert!(!stats.is_complete());
+    assert_eq!(stats.failed.len(), 1);
+    assert_eq!(
+        (stats.failed[0].0.as_str(), stats.failed[0].1.rc),
+        ("ou=Broken,ou=Old,dc=example,dc=org", 68)
+    );
+
+    let adds: Vec<_> = dst_server
+        .requests()
+        .into_iter()
+        .filter(|req| req.operation == Operation::Add)
+        .collect();
+    let dns: Vec<_> = adds.iter().map(|req| req.dn().expect("dn")).collect();
+    assert_eq!(
+        dns,
+        [
+            "ou=New,dc=example,dc=com",
+            "ou=People,ou=New,dc=example,dc=com",
+            "ou=Groups,ou=New,dc=example,dc=com",
+            "ou=Broken,ou=New,dc=example,dc=com",
+            "uid=a,ou=People,ou=New,dc=example,dc=com",
+            "uid=b,ou=People,ou=New,dc=example,dc=com",
+            "cn=staff,ou=Groups,ou=New,dc=example,dc=com",
+        ]
+    );
+    for req in &adds {
+        for (name, _) in request_attrs(req) {
+            assert!(!["hasSubordinates", "numSubordinates", "entryUUID"].contains(&name.as_str()));
+        }
+    }
+    let manager = request_attrs(&adds[4])
+        .into_iter()
+        .find(|(name, _)| name == "manager")
+        .expect("manager");
+    assert_eq!(manager.1, ["uid=b,ou=People,ou=New,dc=example,dc=com"]);
+    let mut members = request_attrs(&adds[6])
+        .into_iter()
+        .find(|(name, _)| name == "member")
+        .expect("member")
+        .1;
+    members.sort();
+    assert_eq!(
+        members,
+        [
+            "uid=a,ou=People,ou=New,dc=example,dc=com",
+            "uid=b,OU=people,ou=New,dc=example,dc=com",
+            "uid=x,ou=Elsewhere,dc=example,dc=org",
+        ]
+    );
+
+    // Searches for subordinates are made only below the entries which may have them.
+    let bases: Vec<_> = src_server
+        .requests()
+        .iter()
+        .filter(|req| req.operation == Operation::Search)
+        .map(|req| req.dn().expect("base"))
+        .collect();
+    assert_eq!(
+        bases,
+        [
+            "",
+            "ou=Old,dc=example,dc=org",
+            "ou=Old,dc=example,dc=org",
+            "ou=People,ou=Old,dc=example,dc=org",
+            "ou=Groups,ou=Old,dc=example,dc=org",
+        ]
+    );
+
+    // With fail_fast, the copy stops at the first failure.
+    let dst_server = new_directory().await;
+    let mut dst = connect(&dst_server).await;
+    let stats = migrate::copy_subtree(
+        &mut src,
+        &mut dst,
+        "ou=Old,dc=example,dc=org",
+        "ou=New,dc=example,dc=com",
+        &CopyOptions::new().fail_fast(true),
+    )
+    .await
+    .expect("copy");
+    assert_eq!((stats.added, stats.failed.len()), (3, 1));
+    assert_eq!(dst_server.requests().len(), 4);
+}
@@ -0,0 +1,137 @@
+use std::sync::{Arc, Mutex};
+
+use ldap3::adapters::{Adapter, EntriesOnly, PageDecision, PageInfo, PagedResults};
+use ldap3::controls;
+use ldap3::testkit::{done, MockHandle, MockServer, Operation, Request};
+use ldap3::Scope;
+
+use futures::TryStreamExt;
+
+use crate::{connect, locality, PAGED_RESULTS_OID};
+
+// Seven localities, paged by the offset in the cookie, with the size estimate in
+// every response. A request with the page size of zero ends the paged search.
+fn paged_places_server() -> MockServer {
+    MockServer::new().on_search(|req: &Request| {
+        let pr: controls::PagedResults = req.control(PAGED_RESULTS_OID).expect("paging").parse();
+        let start = if pr.cookie.is_empty() {
+            0
+        } else {
+            String::from_utf8(pr.cookie)
+                .expect("cookie")
+                .parse()
+                .expect("offset")
+        };
+        let end = 7.min(start + pr.size as usize);
+        let mut responses: Vec<_> = (start..end).map(locality).collect();
+        let cookie = if pr.size > 0 && end < 7 {
+            end.to_string().into_bytes()
+        } else {
+            vec![]
+        };
+        responses.push(done(0).with_controls(controls::PagedResults { size: 7, cookie }));
+        responses
+    })
+}
+
+fn page_requests(server: &MockHandle) -> Vec<(i32, Vec<u8>)> {
+    server
+        .requests()
+        .iter()
+        .filter(|r| r.operation == Operation::Search)
+        .map(|r| {
+            let pr: controls::PagedResults = r.control(PAGED_RESULTS_OID).expect("paging").parse();
+            (pr.size, pr.cookie)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn paged_page_size_change() {
+    let server = paged_places_server().start().await.expect("server");
+    let mut ldap = connect(&server).await;
+    let pages = Arc::new(Mutex::new(vec![]));
+    let seen = pages.clone();
+    let paged = PagedResults::new(3).on_page(move |info: PageInfo| {
+        seen.lock().unwrap().push(info.clone());
+        PageDecision::ContinueWith { page_size: 2 }
+    });
+    let mut stream = ldap
+        .streaming_search_with(
+            paged.clone(),
+            "ou=Places,dc=example,dc=org",
+            Scope::Subtree,
+            "(objectClass=locality)",
+            vec!["l"],
+        )
+        .await
+        .expect("stream");
+    let mut count = 0;
+    while stream.next().await.expect("entry").is_some() {
+        count += 1;
+    }
+    assert_eq!(count, 7);
+    let res = stream.finish().await.success().expect("success");
+    assert!(res.ctrls.is_empty());
+    assert_eq!(paged.size_estimate(), Some(7));
+    let pages = pages.lock().unwrap();
+    let summary: Vec<_> = pages
+        .iter()
+        .map(|p| (p.index, p.entries, p.size_estimate))
+        .collect();
+    assert_eq!(summary, [(0, 3, Some(7)), (1, 2, Some(7)), (2, 2, Some(7))]);
+    assert_eq!(
+        page_requests(&server),
+        [(3, vec![]), (2, b"3".to_vec()), (2, b"5".to_vec())]
+    );
+}
+
+#[tokio::test]
+async fn paged_stop() {
+    let server = paged_places_server().start().await.expect("server");
+    let mut ldap = connect(&server).await;
+    let paged = PagedResults::new(3).on_page(|_| PageDecision::Stop);
+    let adapters: Vec<Box<dyn Adapter<_, _>>> = vec![Box::new(EntriesOnly::new()), Box::new(paged)];
+    let mut stream = ldap
+        .streaming_search_with(
+            adapters,
+            "ou=Places,dc=example,dc=org",
+            Scope::Subtree,
+            "(objectClass=locality)",
+            vec!["l"],
+        )
+        .await
+        .expect("stream");
+    let mut count = 0;
+    while stream.next().await.expect("entry").is_some() {
+        count += 1;
+    }
+    assert_eq!(count, 3);
+    let res = stream.finish().await.success().expect("success");
+    assert!(res.ctrls.is_empty());
+    // The result set is released with a zero-sized page.
+    assert_eq!(page_requests(&server), [(3, vec![]), (0, b"3".to_vec())]);
+
+    // The page limit has the same effect.
+    let server = paged_places_server().start().await.expect("server");
+    let mut ldap = connect(&server).await;
+    let entries: Vec<_> = ldap
+        .streaming_search_with(
+            PagedResults::new(3).max_pages(2),
+            "ou=Places,dc=example,dc=org",
+            Scope::Subtree,
+            "(objectClass=locality)",
+            vec!["l"],
+        )
+        .await
+        .expect("stream")
+        .into_stream()
+        .try_collect()
+        .await
+        .expect("entries");
+    assert_eq!(entries.len(), 6);
+    assert_eq!(
+        page_requests(&server),
+        [(3, vec![]), (3, b"3".to_vec()), (0, b"6".to_vec())]
+    );
+}
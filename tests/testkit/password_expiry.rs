@@ -0,0 +1,69 @@
+use ldap3::controls::{Control, ControlType, PasswordExpiring, PasswordExpiry, RawControl};
+use ldap3::testkit::{bind_ok, MockServer, Request};
+use ldap3::Ldap;
+
+use crate::connect;
+
+fn expiry_control(oid: &str, val: &[u8]) -> RawControl {
+    RawControl {
+        ctype: String::from(oid),
+        crit: false,
+        val: Some(val.to_vec()),
+    }
+}
+
+async fn bind_as(ldap: &mut Ldap, dn: &str) -> ldap3::LdapResult {
+    ldap.simple_bind(dn, "secret")
+        .await
+        .expect("bind")
+        .success()
+        .expect("success")
+}
+
+#[tokio::test]
+async fn password_expiry_controls() {
+    const EXPIRED: &str = "2.16.840.1.113730.3.4.4";
+    const EXPIRING: &str = "2.16.840.1.113730.3.4.5";
+    let server = MockServer::new()
+        .on_bind(|req: &Request| match req.dn().as_deref() {
+            Some("uid=soon,dc=example,dc=org") => {
+                bind_ok().with_controls(expiry_control(EXPIRING, b"3600"))
+            }
+            Some("uid=vague,dc=example,dc=org") => {
+                bind_ok().with_controls(expiry_control(EXPIRING, b"in an hour"))
+            }
+            Some("uid=old,dc=example,dc=org") => bind_ok().with_controls(vec![
+                expiry_control(EXPIRING, b"0"),
+                expiry_control(EXPIRED, b"0"),
+            ]),
+            _ => bind_ok(),
+        })
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect(&server).await;
+    let res = bind_as(&mut ldap, "uid=soon,dc=example,dc=org").await;
+    assert_eq!(res.password_expiry(), Some(PasswordExpiry::Expiring(3600)));
+    assert!(matches!(
+        res.ctrls[0],
+        Control(Some(ControlType::PasswordExpiring), _)
+    ));
+    assert_eq!(
+        res.ctrls[0].1.parse::<PasswordExpiring>(),
+        PasswordExpiring::Seconds(3600)
+    );
+    let res = bind_as(&mut ldap, "uid=vague,dc=example,dc=org").await;
+    assert_eq!(
+        res.password_expiry(),
+        Some(PasswordExpiry::Unparsed(b"in an hour".to_vec()))
+    );
+    let res = bind_as(&mut ldap, "uid=old,dc=example,dc=org").await;
+    assert_eq!(res.password_expiry(), Some(PasswordExpiry::Expired));
+    assert!(matches!(
+        res.ctrls[1],
+        Control(Some(ControlType::PasswordExpired), _)
+    ));
+    let res = bind_as(&mut ldap, "uid=fresh,dc=example,dc=org").await;
+    assert_eq!(res.password_expiry(), None);
+    ldap.unbind().await.expect("unbind");
+}
@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+use ldap3::asn1::{parse_tag, write, ASNTag, Enumerated, Integer, OctetString, Sequence, Tag};
+use ldap3::controls::{
+    ChangeType, Control, ControlType, EntryChange, PersistentSearch, RawControl,
+};
+use ldap3::testkit::{MockServer, Request};
+use ldap3::Scope;
+
+use crate::{connect, locality};
+
+fn entry_change(
+    change_type: i64,
+    previous_dn: Option<&str>,
+    change_number: Option<i64>,
+) -> RawControl {
+    let mut inner = vec![Tag::Enumerated(Enumerated {
+        inner: change_type,
+        ..Default::default()
+    })];
+    if let Some(dn) = previous_dn {
+        inner.push(Tag::OctetString(OctetString {
+            inner: Vec::from(dn),
+            ..Default::default()
+        }));
+    }
+    if let Some(number) = change_number {
+        inner.push(Tag::Integer(Integer {
+            inner: number,
+            ..Default::default()
+        }));
+    }
+    let val = Tag::Sequence(Sequence {
+        inner,
+        ..Default::default()
+    });
+    let mut buf = bytes::BytesMut::new();
+    write::encode_into(&mut buf, val.into_structure()).expect("encoded");
+    RawControl {
+        ctype: String::from("2.16.840.1.113730.3.4.7"),
+        crit: false,
+        val: Some(buf.to_vec()),
+    }
+}
+
+#[tokio::test]
+async fn watch_changes() {
+    let server = MockServer::new()
+        .on_search(|_: &Request| {
+            vec![
+                locality(0).with_controls(entry_change(1, None, Some(10))),
+                locality(1).with_controls(entry_change(4, None, Some(11))),
+                locality(2).with_controls(entry_change(2, None, None)),
+                locality(3).with_controls(entry_change(
+                    8,
+                    Some("l=Old Place,ou=Places,dc=example,dc=org"),
+                    Some(13),
+                )),
+            ]
+        })
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect(&server).await;
+    let mut stream = ldap
+        .watch(
+            "ou=Places,dc=example,dc=org",
+            Scope::Subtree,
+            "(objectClass=*)",
+            vec!["l"],
+            PersistentSearch::default(),
+        )
+        .await
+        .expect("watch");
+    let mut changes = vec![];
+    for _ in 0..4 {
+        let entry = stream.next().await.expect("entry").expect("change");
+        let change = match entry.controls() {
+            [Control(Some(ControlType::EntryChange), raw)] => raw.parse::<EntryChange>(),
+            ctrls => panic!("unexpected controls: {:?}", ctrls),
+        };
+        changes.push((change.change_type, change.previous_dn, change.change_number));
+    }
+    assert_eq!(
+        changes,
+        [
+            (ChangeType::Add, None, Some(10)),
+            (ChangeType::Modify, None, Some(11)),
+            (ChangeType::Delete, None, None),
+            (
+                ChangeType::ModDn,
+                Some(String::from("l=Old Place,ou=Places,dc=example,dc=org")),
+                Some(13)
+            ),
+        ]
+    );
+    let more = tokio::time::timeout(Duration::from_millis(100), stream.next()).await;
+    assert!(more.is_err(), "persistent search ended");
+    stream.abandon().await.expect("abandon");
+
+    let requests = server.requests();
+    let ctrl = requests[0]
+        .control("2.16.840.1.113730.3.4.3")
+        .expect("persistent search control");
+    assert!(ctrl.crit);
+    let (_, val) = parse_tag(ctrl.val.as_deref().expect("value")).expect("parsed");
+    let mut comps = val.expect_constructed().expect("components").into_iter();
+    assert_eq!(comps.next().and_then(|c| c.expect_i64()), Some(15));
+    assert_eq!(comps.next().and_then(|c| c.expect_bool()), Some(true));
+    assert_eq!(comps.next().and_then(|c| c.expect_bool()), Some(true));
+}
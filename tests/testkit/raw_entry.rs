@@ -0,0 +1,69 @@
+use ldap3::asn1::parse_tag;
+use ldap3::testkit::{done, entry, MockServer};
+use ldap3::{AttrCase, Ldap, LdapConnAsync, LdapConnSettings, Scope, SearchEntry, SearchOptions};
+
+use crate::connect;
+
+#[tokio::test]
+async fn raw_entry_bytes() {
+    let server = MockServer::new()
+        .on_search(|_| {
+            vec![
+                entry(
+                    "cn=jdoe,ou=People,dc=example,dc=org",
+                    &[("samaccountname", &["jdoe"]), ("cn", &["John Doe"])],
+                ),
+                done(0),
+            ]
+        })
+        .start()
+        .await
+        .expect("server");
+    let search = |mut ldap: Ldap, opts: SearchOptions| async move {
+        let (entries, _res) = ldap
+            .with_search_options(opts)
+            .search(
+                "ou=People,dc=example,dc=org",
+                Scope::Subtree,
+                "(cn=*)",
+                vec!["sAMAccountName", "cn"],
+            )
+            .await
+            .expect("search")
+            .success()
+            .expect("entries");
+        entries
+    };
+
+    let entries = search(connect(&server).await, SearchOptions::new()).await;
+    assert_eq!(entries.len(), 1);
+    assert!(entries[0].raw().is_none());
+
+    let settings = LdapConnSettings::new().set_keep_raw_frames(true);
+    let (conn, ldap) = LdapConnAsync::with_settings(settings, &server.url())
+        .await
+        .expect("connection");
+    ldap3::drive!(conn);
+    let entries = search(ldap.clone(), SearchOptions::new()).await;
+    let raw = entries[0].raw().expect("raw bytes");
+    let (rest, tag) = parse_tag(raw).expect("parsed");
+    assert!(rest.is_empty());
+    assert_eq!(tag, entries[0].0);
+    assert_eq!(raw.to_vec(), entries[0].to_bytes());
+
+    // The raw bytes keep the attribute names as sent by the server.
+    let opts = SearchOptions::new().attr_case(AttrCase::AsRequested);
+    let entries = search(ldap, opts).await;
+    let (_, tag) = parse_tag(entries[0].raw().expect("raw bytes")).expect("parsed");
+    assert_ne!(tag, entries[0].0);
+    let names = |tag| {
+        let mut names: Vec<_> = SearchEntry::construct(ldap3::ResultEntry::new(tag))
+            .attrs
+            .into_keys()
+            .collect();
+        names.sort_unstable();
+        names
+    };
+    assert_eq!(names(tag), ["cn", "samaccountname"]);
+    assert_eq!(names(entries[0].0.clone()), ["cn", "sAMAccountName"]);
+}
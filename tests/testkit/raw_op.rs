@@ -0,0 +1,145 @@
+use ldap3::asn1::{Boolean, Enumerated, Integer, OctetString, Sequence, Tag, TagClass};
+use ldap3::controls::RawControl;
+use ldap3::exop::{WhoAmI, WhoAmIResp};
+use ldap3::result::ExopResult;
+use ldap3::testkit::{done, extended, MockServer};
+use ldap3::{LdapError, SearchEntry};
+
+use crate::{connect, locality};
+
+fn whoami_request() -> Tag {
+    Tag::Sequence(Sequence {
+        id: 23,
+        class: TagClass::Application,
+        inner: vec![Tag::OctetString(OctetString {
+            id: 0,
+            class: TagClass::Context,
+            inner: b"1.3.6.1.4.1.4203.1.11.3".to_vec(),
+        })],
+    })
+}
+
+#[tokio::test]
+async fn raw_whoami() {
+    let server = MockServer::new()
+        .on_extended(|_| {
+            vec![
+                extended(0, None, Some(b"dn:cn=me,dc=example,dc=org".to_vec())).with_controls(
+                    RawControl {
+                        ctype: String::from("1.2.3.4"),
+                        crit: false,
+                        val: Some(b"resp".to_vec()),
+                    },
+                ),
+            ]
+        })
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect(&server).await;
+    let req_ctrl = RawControl {
+        ctype: String::from("1.2.3.5"),
+        crit: true,
+        val: None,
+    };
+    let ExopResult(builtin_exop, builtin_res) = ldap
+        .with_controls(req_ctrl.clone())
+        .extended(WhoAmI)
+        .await
+        .expect("whoami");
+    let (raw_res, raw_exop, raw_ctrls) = ldap
+        .with_controls(req_ctrl)
+        .raw_op(whoami_request())
+        .await
+        .expect("raw whoami");
+    assert_eq!(raw_res.rc, builtin_res.rc);
+    assert!(raw_res.ctrls.is_empty());
+    assert_eq!(raw_ctrls.len(), 1);
+    assert_eq!(raw_ctrls[0].1.ctype, builtin_res.ctrls[0].1.ctype);
+    assert_eq!(raw_ctrls[0].1.val, builtin_res.ctrls[0].1.val);
+    assert_eq!(raw_exop.name, builtin_exop.name);
+    assert_eq!(raw_exop.val, builtin_exop.val);
+    assert_eq!(
+        raw_exop.parse::<WhoAmIResp>().authzid,
+        "dn:cn=me,dc=example,dc=org"
+    );
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].op, requests[1].op);
+    assert!(requests.iter().all(|r| r.control("1.2.3.5").is_some()));
+
+    let search = Tag::Sequence(Sequence {
+        id: 3,
+        class: TagClass::Application,
+        inner: vec![],
+    });
+    assert!(matches!(
+        ldap.raw_op(search).await,
+        Err(LdapError::InvalidRequest { .. })
+    ));
+    let universal = Tag::Sequence(Sequence::default());
+    assert!(matches!(
+        ldap.raw_op(universal).await,
+        Err(LdapError::InvalidRequest { .. })
+    ));
+}
+
+#[tokio::test]
+async fn raw_search() {
+    let server = MockServer::new()
+        .on_search(|_| vec![locality(1), locality(2), done(0)])
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect(&server).await;
+    let enumerated = |inner| {
+        Tag::Enumerated(Enumerated {
+            inner,
+            ..Default::default()
+        })
+    };
+    let integer = |inner| {
+        Tag::Integer(Integer {
+            inner,
+            ..Default::default()
+        })
+    };
+    let req = Tag::Sequence(Sequence {
+        id: 3,
+        class: TagClass::Application,
+        inner: vec![
+            Tag::OctetString(OctetString {
+                inner: b"ou=Places,dc=example,dc=org".to_vec(),
+                ..Default::default()
+            }),
+            enumerated(2),
+            enumerated(0),
+            integer(0),
+            integer(0),
+            Tag::Boolean(Boolean::default()),
+            Tag::OctetString(OctetString {
+                id: 7,
+                class: TagClass::Context,
+                inner: b"objectClass".to_vec(),
+            }),
+            Tag::Sequence(Sequence::default()),
+        ],
+    });
+    let mut stream = ldap.raw_search_op(req).await.expect("stream");
+    let mut names = vec![];
+    while let Some(entry) = stream.next().await.expect("entry") {
+        names.push(SearchEntry::construct(entry).dn);
+    }
+    assert_eq!(stream.finish().await.rc, 0);
+    assert_eq!(
+        names,
+        vec![
+            "l=Place 1,ou=Places,dc=example,dc=org",
+            "l=Place 2,ou=Places,dc=example,dc=org"
+        ]
+    );
+    assert_eq!(
+        server.requests()[0].dn().as_deref(),
+        Some("ou=Places,dc=example,dc=org")
+    );
+}
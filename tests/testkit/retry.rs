@@ -0,0 +1,120 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "runtime-tokio")]
+use std::time::Duration;
+
+use ldap3::testkit::{done, MockHandle, MockServer, Operation};
+#[cfg(feature = "runtime-tokio")]
+use ldap3::{Backoff, Scope};
+use ldap3::{Ldap, LdapConnAsync, LdapConnSettings, Mod, RetryPolicy};
+
+#[cfg(feature = "runtime-tokio")]
+use tokio::time::Instant;
+
+use crate::locality;
+
+// Server answering each request of the given operation with 51 (busy) until it has
+// seen `busy` of them, then with `rc`.
+fn busy_server(operation: Operation, busy: usize, rc: u32) -> MockServer {
+    let seen = AtomicUsize::new(0);
+    MockServer::new().on(operation, move |_| {
+        if seen.fetch_add(1, Ordering::SeqCst) < busy {
+            vec![done(51)]
+        } else if operation == Operation::Search {
+            vec![locality(0), done(rc)]
+        } else {
+            vec![done(rc)]
+        }
+    })
+}
+
+async fn connect_with_retries(server: &MockHandle, policy: RetryPolicy) -> Ldap {
+    let settings = LdapConnSettings::new().set_retry_policy(policy);
+    let (conn, ldap) = LdapConnAsync::with_settings(settings, &server.url())
+        .await
+        .expect("connection");
+    ldap3::drive!(conn);
+    ldap
+}
+
+#[cfg(feature = "runtime-tokio")]
+#[tokio::test(start_paused = true)]
+async fn busy_compare_retried() {
+    let server = busy_server(Operation::Compare, 2, 6)
+        .start()
+        .await
+        .expect("server");
+    let policy = RetryPolicy::new(3).backoff(Backoff::Exponential {
+        base: Duration::from_millis(100),
+        cap: Duration::from_secs(1),
+    });
+    let mut ldap = connect_with_retries(&server, policy).await;
+    let start = Instant::now();
+    let res = ldap
+        .compare("uid=test,dc=example,dc=org", "cn", "test")
+        .await
+        .expect("compare");
+    assert!(res.equal().expect("equal"));
+    assert_eq!(start.elapsed(), Duration::from_millis(300));
+    let ids: Vec<_> = server.requests().iter().map(|r| r.id).collect();
+    assert_eq!(ids, [1, 2, 3]);
+}
+
+#[cfg(feature = "runtime-tokio")]
+#[tokio::test(start_paused = true)]
+async fn busy_search_retried() {
+    let server = busy_server(Operation::Search, 1, 0)
+        .start()
+        .await
+        .expect("server");
+    let policy = RetryPolicy::new(2).backoff(Backoff::Fixed(Duration::from_millis(250)));
+    let mut ldap = connect_with_retries(&server, policy).await;
+    let start = Instant::now();
+    let (entries, _) = ldap
+        .search("dc=example,dc=org", Scope::Subtree, "(l=*)", vec!["l"])
+        .await
+        .expect("search")
+        .success()
+        .expect("success");
+    assert_eq!(entries.len(), 1);
+    assert_eq!(start.elapsed(), Duration::from_millis(250));
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test(start_paused = true)]
+async fn retries_exhausted() {
+    let server = busy_server(Operation::Compare, 5, 6)
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect_with_retries(&server, RetryPolicy::new(3)).await;
+    let res = ldap
+        .compare("uid=test,dc=example,dc=org", "cn", "test")
+        .await
+        .expect("compare");
+    assert_eq!(res.0.rc, 51);
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[tokio::test(start_paused = true)]
+async fn modify_retried_only_on_request() {
+    let server = busy_server(Operation::Modify, 3, 0)
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect_with_retries(&server, RetryPolicy::new(3)).await;
+    let mods = vec![Mod::Replace("cn", HashSet::from(["test"]))];
+    let res = ldap
+        .modify("uid=test,dc=example,dc=org", mods.clone())
+        .await
+        .expect("modify");
+    assert_eq!(res.rc, 51);
+    assert_eq!(server.requests().len(), 1);
+    let res = ldap
+        .with_retries()
+        .modify("uid=test,dc=example,dc=org", mods)
+        .await
+        .expect("modify");
+    assert_eq!(res.rc, 0);
+    assert_eq!(server.requests().len(), 4);
+}
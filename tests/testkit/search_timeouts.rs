@@ -0,0 +1,149 @@
+use std::time::Duration;
+
+use ldap3::testkit::{delay, done, MockServer, Operation, Response};
+use ldap3::{LdapError, Scope, SearchOptions};
+
+use tokio::time::Instant;
+
+use crate::{connect, locality};
+
+// Read a Search from a server answering with `script`, with the given options and
+// per-item timeout. Return the number of entries received, the outcome, the elapsed
+// time, and whether the Search was abandoned.
+async fn timed_search(
+    script: Vec<Response>,
+    opts: SearchOptions,
+    timeout: Option<Duration>,
+) -> (usize, Result<(), LdapError>, Duration, bool) {
+    let server = MockServer::new()
+        .on_search(move |_| script.clone())
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect(&server).await;
+    let start = Instant::now();
+    ldap.with_search_options(opts);
+    if let Some(timeout) = timeout {
+        ldap.with_timeout(timeout);
+    }
+    let mut stream = ldap
+        .streaming_search(
+            "ou=Places,dc=example,dc=org",
+            Scope::Subtree,
+            "(objectClass=locality)",
+            vec!["l"],
+        )
+        .await
+        .expect("stream");
+    let mut count = 0;
+    let res = loop {
+        match stream.next().await {
+            Ok(Some(_)) => count += 1,
+            Ok(None) => break Ok(()),
+            Err(e) => break Err(e),
+        }
+    };
+    let elapsed = start.elapsed();
+    // The Abandon, if any, is sent before the Compare, so the server will have seen
+    // it when the Compare completes.
+    ldap.compare("ou=Places,dc=example,dc=org", "ou", "Places")
+        .await
+        .expect("compare");
+    let abandoned = server
+        .requests()
+        .iter()
+        .any(|r| r.operation == Operation::Abandon);
+    (count, res, elapsed, abandoned)
+}
+
+#[tokio::test(start_paused = true)]
+async fn search_slow_first_entry() {
+    let script = vec![
+        delay(Duration::from_secs(2)),
+        locality(0),
+        locality(1),
+        done(0),
+    ];
+    let opts = SearchOptions::new().first_entry_timeout(Duration::from_secs(1));
+    let (count, res, elapsed, abandoned) = timed_search(script.clone(), opts, None).await;
+    assert_eq!(count, 0);
+    assert!(
+        matches!(res, Err(LdapError::FirstEntryTimeout)),
+        "{:?}",
+        res
+    );
+    assert_eq!(elapsed, Duration::from_secs(1));
+    assert!(abandoned);
+
+    // The total timeout is longer than the wait.
+    let opts = SearchOptions::new().total_timeout(Duration::from_secs(3));
+    let (count, res, _, _) = timed_search(script, opts, None).await;
+    assert_eq!(count, 2);
+    assert!(res.is_ok(), "{:?}", res);
+}
+
+#[tokio::test(start_paused = true)]
+async fn search_slow_tail() {
+    let script = vec![
+        locality(0),
+        delay(Duration::from_secs(5)),
+        locality(1),
+        done(0),
+    ];
+    // The first entry timeout doesn't limit the wait for later entries.
+    let opts = SearchOptions::new().first_entry_timeout(Duration::from_secs(1));
+    let (count, res, elapsed, _) = timed_search(script.clone(), opts, None).await;
+    assert_eq!(count, 2);
+    assert!(res.is_ok(), "{:?}", res);
+    assert_eq!(elapsed, Duration::from_secs(5));
+
+    let opts = SearchOptions::new()
+        .first_entry_timeout(Duration::from_secs(1))
+        .total_timeout(Duration::from_secs(3));
+    let (count, res, elapsed, abandoned) = timed_search(script.clone(), opts, None).await;
+    assert_eq!(count, 1);
+    assert!(
+        matches!(res, Err(LdapError::SearchTotalTimeout)),
+        "{:?}",
+        res
+    );
+    assert_eq!(elapsed, Duration::from_secs(3));
+    assert!(abandoned);
+
+    // The per-item timeout expires first.
+    let script = vec![delay(Duration::from_secs(5)), locality(0), done(0)];
+    let opts = SearchOptions::new().total_timeout(Duration::from_secs(3));
+    let (count, res, elapsed, _) = timed_search(script, opts, Some(Duration::from_secs(2))).await;
+    assert_eq!(count, 0);
+    assert!(matches!(res, Err(LdapError::Timeout { .. })), "{:?}", res);
+    assert_eq!(elapsed, Duration::from_secs(2));
+}
+
+#[tokio::test(start_paused = true)]
+async fn search_slow_overall() {
+    let mut script = vec![];
+    for n in 0..10 {
+        script.push(delay(Duration::from_millis(400)));
+        script.push(locality(n));
+    }
+    script.push(done(0));
+    // No single wait exceeds the per-item or the first entry timeout.
+    let opts = SearchOptions::new().first_entry_timeout(Duration::from_secs(1));
+    let (count, res, elapsed, _) =
+        timed_search(script.clone(), opts, Some(Duration::from_secs(1))).await;
+    assert_eq!(count, 10);
+    assert!(res.is_ok(), "{:?}", res);
+    assert!(elapsed >= Duration::from_secs(4));
+
+    let opts = SearchOptions::new()
+        .first_entry_timeout(Duration::from_secs(1))
+        .total_timeout(Duration::from_millis(2100));
+    let (count, res, elapsed, _) = timed_search(script, opts, Some(Duration::from_secs(1))).await;
+    assert_eq!(count, 5);
+    assert!(
+        matches!(res, Err(LdapError::SearchTotalTimeout)),
+        "{:?}",
+        res
+    );
+    assert_eq!(elapsed, Duration::from_millis(2100));
+}
@@ -0,0 +1,58 @@
+use ldap3::testkit::{done, MockServer};
+use ldap3::{LdapError, LdapUrl};
+
+use crate::{connect, locality};
+
+#[tokio::test]
+async fn search_url_foreign_host() {
+    let server = MockServer::new()
+        .on_search(|_| vec![locality(0), done(0)])
+        .start()
+        .await
+        .expect("server");
+    let mut ldap = connect(&server).await;
+    let foreign = format!(
+        "ldap://other.example.org:{}/ou=Places,dc=example,dc=org?l?sub",
+        server.port()
+    );
+    let res = ldap
+        .search_url(&LdapUrl::parse(&foreign).expect("url"))
+        .await;
+    let expected = format!("other.example.org:{}", server.port());
+    assert!(
+        matches!(res, Err(LdapError::ForeignHost(ref host)) if *host == expected),
+        "{:?}",
+        res
+    );
+    let res = ldap
+        .search_url(&LdapUrl::parse("ldap://127.0.0.1:1/ou=Places,dc=example,dc=org").expect("url"))
+        .await;
+    assert!(matches!(res, Err(LdapError::ForeignHost(_))), "{:?}", res);
+    assert!(server.requests().is_empty());
+
+    let urls = [
+        LdapUrl::parse(&foreign)
+            .expect("url")
+            .allow_foreign_host(true),
+        LdapUrl::parse(&format!(
+            "{}/ou=Places,dc=example,dc=org?l?sub",
+            server.url()
+        ))
+        .expect("url"),
+        LdapUrl::parse("ldap:///ou=Places,dc=example,dc=org?l?sub").expect("url"),
+    ];
+    for url in &urls {
+        let (entries, _res) = ldap
+            .search_url(url)
+            .await
+            .expect("search")
+            .success()
+            .expect("success");
+        assert_eq!(entries.len(), 1);
+    }
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    assert!(requests
+        .iter()
+        .all(|r| r.dn().as_deref() == Some("ou=Places,dc=example,dc=org")));
+}
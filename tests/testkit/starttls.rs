@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+use ldap3::testkit::{extended, MockHandle, MockServer, Operation};
+use ldap3::{LdapConnAsync, LdapConnSettings, LdapError};
+
+async fn starttls(server: &MockHandle) -> LdapError {
+    let settings = LdapConnSettings::new().set_starttls(true);
+    match LdapConnAsync::with_settings(settings, &server.url()).await {
+        Ok(_) => panic!("StartTLS succeeded"),
+        Err(e) => e,
+    }
+}
+
+#[tokio::test]
+async fn starttls_rejected() {
+    let server = MockServer::new()
+        .on_extended(|_| vec![extended(2, None, None).with_text("no TLS here")])
+        .start()
+        .await
+        .expect("server");
+    match starttls(&server).await {
+        LdapError::TlsHandshakeFailed { source } => match *source {
+            LdapError::LdapResult { ref result } => assert_eq!(result.rc, 2),
+            ref e => panic!("unexpected source: {:?}", e),
+        },
+        e => panic!("unexpected error: {:?}", e),
+    }
+    let ops: Vec<_> = server.requests().iter().map(|r| r.operation).collect();
+    assert_eq!(ops, [Operation::Extended]);
+}
+
+#[tokio::test]
+async fn starttls_handshake_garbage() {
+    // The second response arrives where the client expects the ServerHello.
+    let server = MockServer::new()
+        .on_extended(|_| vec![extended(0, None, None), extended(0, None, None)])
+        .start()
+        .await
+        .expect("server");
+    let e = tokio::time::timeout(Duration::from_secs(5), starttls(&server))
+        .await
+        .expect("teardown");
+    assert!(matches!(e, LdapError::TlsHandshakeFailed { .. }));
+    let ops: Vec<_> = server.requests().iter().map(|r| r.operation).collect();
+    assert_eq!(ops, [Operation::Extended]);
+}
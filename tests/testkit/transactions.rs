@@ -0,0 +1,254 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ldap3::asn1::{parse_tag, write, ASNTag, Integer, OctetString, Sequence, Tag};
+use ldap3::exop::{EndTxnResp, ExopParser};
+use ldap3::testkit::{done, extended, MockHandle, MockServer, Operation, Request};
+use ldap3::Mod;
+
+use crate::connect;
+
+const START_TXN_OID: &str = "1.3.6.1.1.21.1";
+
+const TXN_SPEC_OID: &str = "1.3.6.1.1.21.2";
+
+fn encode(tag: Tag) -> Vec<u8> {
+    let mut buf = bytes::BytesMut::new();
+    write::encode_into(&mut buf, tag.into_structure()).expect("encoded");
+    buf.to_vec()
+}
+
+// State of the single transaction supported by txn_server().
+#[derive(Default)]
+struct TxnState {
+    queued: Vec<ldap3::RequestId>,
+    failed: Option<ldap3::RequestId>,
+    ended: Option<bool>,
+}
+
+// Server which queues updates carrying the transaction control, and fails those
+// of entries whose RDN starts with "cn=bad".
+async fn txn_server(state: Arc<Mutex<TxnState>>) -> MockHandle {
+    let update_state = state.clone();
+    let update = move |req: &Request| {
+        let mut state = update_state.lock().unwrap();
+        match req.control(TXN_SPEC_OID) {
+            Some(ctrl) if ctrl.crit && ctrl.val.as_deref() == Some(&b"txn-1"[..]) => (),
+            _ => return done(2).with_text("no transaction"),
+        }
+        if req.dn().expect("dn").starts_with("cn=bad") {
+            state.failed.get_or_insert(req.id);
+            done(53).with_text("unwilling")
+        } else {
+            state.queued.push(req.id);
+            done(0)
+        }
+    };
+    MockServer::new()
+        .on_add(update.clone())
+        .on_modify(update.clone())
+        .on_delete(update)
+        .on_extended(move |req: &Request| {
+            let mut elems = req
+                .op
+                .clone()
+                .expect_constructed()
+                .expect("exop")
+                .into_iter();
+            let name = elems
+                .next()
+                .and_then(|n| n.expect_primitive())
+                .expect("name");
+            if name == START_TXN_OID.as_bytes() {
+                return extended(0, None, Some(b"txn-1".to_vec()));
+            }
+            let val = elems
+                .next()
+                .and_then(|v| v.expect_primitive())
+                .expect("value");
+            let end = parse_tag(&val)
+                .expect("end")
+                .1
+                .expect_constructed()
+                .expect("seq");
+            let commit = end.len() == 1 || end[0].clone().expect_bool() == Some(true);
+            let mut state = state.lock().unwrap();
+            state.ended = Some(commit);
+            if !commit {
+                return extended(0, None, None);
+            }
+            if let Some(id) = state.failed {
+                let resp = Tag::Sequence(Sequence {
+                    inner: vec![Tag::Integer(Integer {
+                        inner: id as i64,
+                        ..Default::default()
+                    })],
+                    ..Default::default()
+                });
+                return extended(53, None, Some(encode(resp)));
+            }
+            let updates = state
+                .queued
+                .iter()
+                .map(|&id| {
+                    Tag::Sequence(Sequence {
+                        inner: vec![
+                            Tag::Integer(Integer {
+                                inner: id as i64,
+                                ..Default::default()
+                            }),
+                            Tag::Sequence(Sequence {
+                                inner: vec![Tag::Sequence(Sequence {
+                                    inner: vec![
+                                        Tag::OctetString(OctetString {
+                                            inner: b"1.2.3.4".to_vec(),
+                                            ..Default::default()
+                                        }),
+                                        Tag::OctetString(OctetString {
+                                            inner: format!("update {}", id).into_bytes(),
+                                            ..Default::default()
+                                        }),
+                                    ],
+                                    ..Default::default()
+                                })],
+                                ..Default::default()
+                            }),
+                        ],
+                        ..Default::default()
+                    })
+                })
+                .collect();
+            let resp = Tag::Sequence(Sequence {
+                inner: vec![Tag::Sequence(Sequence {
+                    inner: updates,
+                    ..Default::default()
+                })],
+                ..Default::default()
+            });
+            extended(0, None, Some(encode(resp)))
+        })
+        .start()
+        .await
+        .expect("server")
+}
+
+#[tokio::test]
+async fn transaction_commit() {
+    let state = Arc::new(Mutex::new(TxnState::default()));
+    let server = txn_server(state.clone()).await;
+    let mut ldap = connect(&server).await;
+    let mut txn = ldap.begin_transaction().await.expect("begin");
+    assert_eq!(txn.id(), b"txn-1");
+    txn.add(
+        "cn=new,dc=example,dc=org",
+        vec![("cn", HashSet::from(["new"]))],
+    )
+    .await
+    .expect("add")
+    .success()
+    .expect("success");
+    let add_id = txn.last_id();
+    txn.modify(
+        "cn=old,dc=example,dc=org",
+        vec![Mod::Replace("description", HashSet::from(["moved"]))],
+    )
+    .await
+    .expect("modify")
+    .success()
+    .expect("success");
+    let modify_id = txn.last_id();
+    let (res, resp) = txn.commit().await.expect("commit");
+    res.success().expect("success");
+    assert_eq!(state.lock().unwrap().ended, Some(true));
+    assert_eq!(resp.failed_msgid, None);
+    let updates: Vec<_> = resp
+        .update_controls
+        .iter()
+        .map(|(id, ctrls)| (*id, ctrls[0].1.val.clone().expect("value")))
+        .collect();
+    assert_eq!(
+        updates,
+        [
+            (add_id, format!("update {}", add_id).into_bytes()),
+            (modify_id, format!("update {}", modify_id).into_bytes()),
+        ]
+    );
+    // Operations on the original handle are outside of the transaction.
+    let res = ldap
+        .delete("cn=new,dc=example,dc=org")
+        .await
+        .expect("delete");
+    assert_eq!(res.rc, 2);
+}
+
+#[tokio::test]
+async fn transaction_abort() {
+    let state = Arc::new(Mutex::new(TxnState::default()));
+    let server = txn_server(state.clone()).await;
+    let mut ldap = connect(&server).await;
+    let mut txn = ldap.begin_transaction().await.expect("begin");
+    txn.delete("cn=old,dc=example,dc=org")
+        .await
+        .expect("delete")
+        .success()
+        .expect("success");
+    txn.abort()
+        .await
+        .expect("abort")
+        .success()
+        .expect("success");
+    assert_eq!(state.lock().unwrap().ended, Some(false));
+
+    // Dropping an unfinished transaction aborts it in the background.
+    state.lock().unwrap().ended = None;
+    let txn = ldap.begin_transaction().await.expect("begin");
+    drop(txn);
+    let aborted = async {
+        while state.lock().unwrap().ended.is_none() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(5), aborted)
+        .await
+        .expect("abort sent");
+    assert_eq!(state.lock().unwrap().ended, Some(false));
+    let ends = server
+        .requests()
+        .iter()
+        .filter(|r| r.operation == Operation::Extended)
+        .count();
+    assert_eq!(ends, 4);
+}
+
+#[tokio::test]
+async fn transaction_failed_update() {
+    let state = Arc::new(Mutex::new(TxnState::default()));
+    let server = txn_server(state.clone()).await;
+    let mut ldap = connect(&server).await;
+    let mut txn = ldap.begin_transaction().await.expect("begin");
+    txn.delete("cn=good,dc=example,dc=org")
+        .await
+        .expect("delete")
+        .success()
+        .expect("success");
+    let res = txn
+        .delete("cn=bad,dc=example,dc=org")
+        .await
+        .expect("delete");
+    assert_eq!(res.rc, 53);
+    let bad_id = txn.last_id();
+    let (res, resp) = txn.commit().await.expect("commit");
+    assert_eq!(res.rc, 53);
+    assert_eq!(resp.failed_msgid, Some(bad_id));
+    assert!(resp.update_controls.is_empty());
+    // The response value can also be parsed through the generic exop interface.
+    let val = encode(Tag::Sequence(Sequence {
+        inner: vec![Tag::Integer(Integer {
+            inner: 7,
+            ..Default::default()
+        })],
+        ..Default::default()
+    }));
+    assert_eq!(EndTxnResp::parse(&val).failed_msgid, Some(7));
+}
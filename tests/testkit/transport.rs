@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use ldap3::testkit::{bind_ok, done, MockServer, Operation};
+use ldap3::{LdapConnAsync, LdapConnSettings, LdapError, Scope};
+
+use crate::{locality, requests_after_unbind};
+
+#[tokio::test]
+async fn custom_transport() {
+    let server = MockServer::new()
+        .on_bind(|_| bind_ok())
+        .on_search(|_| vec![locality(1), locality(2), done(0)])
+        .start()
+        .await
+        .expect("server");
+    let (conn, mut ldap) = LdapConnAsync::from_transport(server.duplex());
+    ldap3::drive!(conn);
+    ldap.simple_bind("cn=admin,dc=example,dc=org", "secret")
+        .await
+        .expect("bind")
+        .success()
+        .expect("bind result");
+    let (entries, _res) = ldap
+        .search(
+            "ou=Places,dc=example,dc=org",
+            Scope::OneLevel,
+            "(l=*)",
+            vec!["l"],
+        )
+        .await
+        .expect("search")
+        .success()
+        .expect("search result");
+    assert_eq!(entries.len(), 2);
+    assert!(ldap.tls_info().is_none());
+    ldap.close().await.expect("close");
+    let ops: Vec<_> = requests_after_unbind(&server)
+        .await
+        .iter()
+        .map(|r| r.operation)
+        .collect();
+    assert_eq!(
+        &ops[..2],
+        &[Operation::Bind, Operation::Search],
+        "{:?}",
+        ops
+    );
+
+    let settings = LdapConnSettings::new().set_conn_timeout(Duration::from_secs(5));
+    let res = LdapConnAsync::from_transport_with_settings(settings, server.duplex());
+    assert!(matches!(
+        res,
+        Err(LdapError::TransportSetting {
+            setting: "connection timeout"
+        })
+    ));
+    let settings = LdapConnSettings::new().set_search_queue_size(1);
+    let (conn, mut ldap) =
+        LdapConnAsync::from_transport_with_settings(settings, server.duplex()).expect("connection");
+    ldap3::drive!(conn);
+    let (entries, _res) = ldap
+        .search(
+            "ou=Places,dc=example,dc=org",
+            Scope::OneLevel,
+            "(l=*)",
+            vec!["l"],
+        )
+        .await
+        .expect("search")
+        .success()
+        .expect("search result");
+    assert_eq!(entries.len(), 2);
+}
@@ -0,0 +1,130 @@
+use ldap3::testkit::{done, MockServer};
+use ldap3::{Ldap, LdapConnAsync, LdapConnSettings, LdapError, Scope, Unmatched};
+
+use crate::{locality, TestLog};
+
+// A Search whose entry is preceded by five responses with message IDs from `first_id`,
+// which the client never used.
+fn unmatched_burst_server(first_id: i32) -> MockServer {
+    MockServer::new().on_search(move |_| {
+        let mut responses: Vec<_> = (0..5)
+            .map(|n| locality(n).with_msgid(first_id + n as i32))
+            .collect();
+        responses.push(locality(5));
+        responses.push(done(0));
+        responses
+    })
+}
+
+async fn unmatched_search(policy: Unmatched, first_id: i32) -> (Ldap, Result<(), LdapError>) {
+    let server = unmatched_burst_server(first_id)
+        .start()
+        .await
+        .expect("server");
+    let settings = LdapConnSettings::new().set_unmatched_response_policy(policy);
+    let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &server.url())
+        .await
+        .expect("connection");
+    let driver = tokio::spawn(conn.drive());
+    let res = ldap
+        .search(
+            "ou=Places,dc=example,dc=org",
+            Scope::OneLevel,
+            "(l=*)",
+            vec!["l"],
+        )
+        .await
+        .expect("search");
+    match policy {
+        Unmatched::Disconnect => {
+            assert_eq!(res.1.rc, 52);
+            assert!(res.0.is_empty());
+        }
+        _ => {
+            let (entries, _res) = res.success().expect("success");
+            assert_eq!(entries.len(), 1);
+            ldap.unbind().await.expect("unbind");
+        }
+    }
+    let outcome = driver.await.expect("driver");
+    (ldap, outcome)
+}
+
+fn unmatched_warnings(first_id: i32) -> Vec<String> {
+    TestLog::lines("ldap3::conn")
+        .into_iter()
+        .filter(|line| (0..5).any(|n| line.starts_with(&format!("unmatched id: {}", first_id + n))))
+        .collect()
+}
+
+#[tokio::test]
+async fn unmatched_ignore() {
+    TestLog::install();
+    let (ldap, outcome) = unmatched_search(Unmatched::Ignore, 500_000).await;
+    outcome.expect("clean termination");
+    assert_eq!(ldap.conn_stats().unmatched_responses(), 5);
+    assert!(unmatched_warnings(500_000).is_empty());
+}
+
+#[tokio::test]
+async fn unmatched_warn_sampled() {
+    TestLog::install();
+    let policy = Unmatched::WarnSampled { per_minute: 2 };
+    let (ldap, outcome) = unmatched_search(policy, 600_000).await;
+    outcome.expect("clean termination");
+    assert_eq!(ldap.conn_stats().unmatched_responses(), 5);
+    assert_eq!(
+        unmatched_warnings(600_000),
+        ["unmatched id: 600000", "unmatched id: 600001"]
+    );
+}
+
+#[tokio::test]
+async fn unmatched_disconnect() {
+    TestLog::install();
+    let (mut ldap, outcome) = unmatched_search(Unmatched::Disconnect, 700_000).await;
+    assert!(
+        matches!(
+            outcome,
+            Err(LdapError::ProtocolViolation { msgid: 700_000 })
+        ),
+        "{:?}",
+        outcome
+    );
+    assert_eq!(ldap.conn_stats().unmatched_responses(), 1);
+    assert!(ldap.is_closed());
+}
+
+// A response with the ID of an operation which has already completed is unmatched, even
+// though the ID was used on the connection.
+#[tokio::test]
+async fn unmatched_finished_id() {
+    let server = MockServer::new()
+        .on_search(|req| match req.dn().as_deref() {
+            Some("ou=Places,dc=example,dc=org") => {
+                vec![locality(0).with_msgid(req.id - 1), locality(1), done(0)]
+            }
+            _ => vec![done(0)],
+        })
+        .start()
+        .await
+        .expect("server");
+    let settings = LdapConnSettings::new().set_unmatched_response_policy(Unmatched::Ignore);
+    let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &server.url())
+        .await
+        .expect("connection");
+    ldap3::drive!(conn);
+    for (base, count) in [
+        ("ou=People,dc=example,dc=org", 0),
+        ("ou=Places,dc=example,dc=org", 1),
+    ] {
+        let (entries, _res) = ldap
+            .search(base, Scope::OneLevel, "(objectClass=*)", vec!["l"])
+            .await
+            .expect("search")
+            .success()
+            .expect("success");
+        assert_eq!(entries.len(), count);
+    }
+    assert_eq!(ldap.conn_stats().unmatched_responses(), 1);
+}
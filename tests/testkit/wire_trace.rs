@@ -0,0 +1,44 @@
+use ldap3::testkit::{bind_ok, done, entry, MockServer};
+use ldap3::{LdapConnAsync, LdapConnSettings, Scope};
+
+use crate::TestLog;
+
+#[tokio::test]
+async fn wire_trace_redaction() {
+    TestLog::install();
+    let server = MockServer::new()
+        .on_bind(|_| bind_ok())
+        .on_search(|_| vec![entry("cn=t,ou=Wire", &[("cn", &["traced"])]), done(0)])
+        .start()
+        .await
+        .expect("server");
+    let settings = LdapConnSettings::new().set_wire_trace(true);
+    let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &server.url())
+        .await
+        .expect("connection");
+    ldap3::drive!(conn);
+    ldap.simple_bind("cn=tracer", "w1r3-s3cr3t")
+        .await
+        .expect("bind")
+        .success()
+        .expect("success");
+    ldap.search("ou=Wire", Scope::Subtree, "(cn=traced)", vec!["cn"])
+        .await
+        .expect("search")
+        .success()
+        .expect("success");
+    let lines = TestLog::lines("ldap3::wire");
+    let bind = lines
+        .iter()
+        .find(|l| l.contains("|cn=tracer|"))
+        .expect("bind trace");
+    assert!(bind.starts_with("sending message"));
+    assert!(bind.ends_with("[CONTEXT 0], <redacted, 11 bytes>"));
+    assert!(lines.iter().all(|l| !l.contains("w1r3")));
+    assert!(lines.iter().any(|l| l.starts_with("sending message")
+        && l.contains("|ou=Wire|")
+        && l.contains("|traced|")));
+    assert!(lines
+        .iter()
+        .any(|l| l.starts_with("received message") && l.contains("|cn=t,ou=Wire|")));
+}